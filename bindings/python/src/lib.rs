@@ -0,0 +1,169 @@
+//! Python bindings of task-maker-rust.
+//!
+//! The `task_maker` module parses the tasks, builds their DAG and evaluates them inside the Python
+//! process, calling back Python with the messages of the UI.
+
+// the code generated by pyo3 for the methods returning PyResult triggers this lint
+#![allow(clippy::useless_conversion)]
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use clap::Parser;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use serde::Serialize;
+
+use task_maker_format::ui::UIMessage;
+use task_maker_format::{find_task, EvaluationConfig, EvaluationData, TaskFormat};
+use task_maker_rust::{evaluate_task, render_dag, ExecutionOpt, StorageOpt};
+
+/// The command line options of task-maker-rust about the execution and the storage, that can be
+/// passed to `Task.evaluate`.
+#[derive(Parser, Debug)]
+#[clap(name = "task-maker")]
+struct EvaluateOpt {
+    #[clap(flatten)]
+    execution: ExecutionOpt,
+
+    #[clap(flatten)]
+    storage: StorageOpt,
+}
+
+/// Convert an error of task-maker to a Python exception, keeping its causes in the message.
+fn to_py_err(e: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{:#}", e))
+}
+
+/// Convert a serializable value to the Python object of its JSON.
+fn to_python<T: Serialize>(py: Python, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let value = py.import_bound("json")?.call_method1("loads", (json,))?;
+    Ok(value.unbind())
+}
+
+/// A task parsed from its directory, either IOI or Terry.
+#[pyclass(module = "task_maker")]
+struct Task {
+    /// The parsed task.
+    task: TaskFormat,
+    /// The configuration used for parsing and evaluating the task.
+    config: EvaluationConfig,
+}
+
+#[pymethods]
+impl Task {
+    /// Parse the task in a directory. Only the `solutions` are evaluated, if some are given, or
+    /// the solutions of the task whose names start with one of the `filter`.
+    #[new]
+    #[pyo3(signature = (path, solutions = vec![], filter = vec![], no_statement = false, dry_run = false))]
+    fn new(
+        path: PathBuf,
+        solutions: Vec<PathBuf>,
+        filter: Vec<String>,
+        no_statement: bool,
+        dry_run: bool,
+    ) -> PyResult<Self> {
+        let config = EvaluationConfig {
+            solution_filter: filter,
+            no_statement,
+            solution_paths: solutions,
+            dry_run,
+            ..Default::default()
+        };
+        let task = find_task(Some(path), 1, &config).map_err(to_py_err)?;
+        Ok(Task { task, config })
+    }
+
+    /// The root directory of the task.
+    #[getter]
+    fn path(&self) -> PathBuf {
+        self.task.path().to_path_buf()
+    }
+
+    /// The format of the task, either `ioi` or `terry`.
+    #[getter]
+    fn format(&self) -> &'static str {
+        match &self.task {
+            TaskFormat::IOI(_) => "ioi",
+            TaskFormat::Terry(_) => "terry",
+        }
+    }
+
+    /// The information about the task, the same of `task-maker-tools task-info --json`.
+    fn info(&self, py: Python) -> PyResult<PyObject> {
+        let info = self.task.task_info().map_err(to_py_err)?;
+        to_python(py, &info)
+    }
+
+    /// Build the DAG of the evaluation, without running it, returning it in DOT format.
+    fn dag(&self) -> PyResult<String> {
+        let mut task = self.task.clone();
+        let (mut eval, _receiver) = EvaluationData::new(task.path());
+        eval.dag.config_mut().dry_run(true);
+        task.build_dag(&mut eval, &self.config).map_err(to_py_err)?;
+        Ok(render_dag(&eval.dag))
+    }
+
+    /// Evaluate the task, returning its results, the same of `task-maker-rust --results`.
+    ///
+    /// `on_event` is called with each message of the UI, the same of `--ui json`, while `options`
+    /// are the command line options of task-maker-rust about the execution and the storage, like
+    /// `["--evaluate-on", "server", "--no-cache"]`.
+    #[pyo3(signature = (on_event = None, options = vec![]))]
+    fn evaluate(
+        &self,
+        py: Python,
+        on_event: Option<PyObject>,
+        options: Vec<String>,
+    ) -> PyResult<PyObject> {
+        let args = std::iter::once("task-maker".to_string()).chain(options);
+        let mut opt =
+            EvaluateOpt::try_parse_from(args).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        opt.execution.dry_run |= self.config.dry_run;
+
+        // the first exception raised by the callback stops the calls, and it's raised at the end
+        let error = Arc::new(Mutex::new(None::<PyErr>));
+        let on_message = {
+            let error = error.clone();
+            move |message: UIMessage| {
+                let Some(on_event) = &on_event else {
+                    return;
+                };
+                Python::with_gil(|py| {
+                    let mut error = error.lock().unwrap();
+                    if error.is_some() {
+                        return;
+                    }
+                    let result =
+                        to_python(py, &message).and_then(|message| on_event.call1(py, (message,)));
+                    if let Err(e) = result {
+                        *error = Some(e);
+                    }
+                });
+            }
+        };
+        // the callback runs in another thread, which needs the GIL
+        let results = py.allow_threads(|| {
+            evaluate_task(
+                self.task.clone(),
+                &self.config,
+                &opt.execution,
+                &opt.storage,
+                on_message,
+            )
+        });
+        if let Some(e) = error.lock().unwrap().take() {
+            return Err(e);
+        }
+        let results = results.map_err(to_py_err)?;
+        to_python(py, &results)
+    }
+}
+
+#[pymodule]
+fn task_maker(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_class::<Task>()?;
+    Ok(())
+}
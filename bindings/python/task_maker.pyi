@@ -0,0 +1,27 @@
+from os import PathLike
+from typing import Any, Callable, Dict, List, Optional, Union
+
+__version__: str
+
+class Task:
+    """A task parsed from its directory, either IOI or Terry."""
+
+    def __init__(
+        self,
+        path: Union[str, PathLike],
+        solutions: List[Union[str, PathLike]] = [],
+        filter: List[str] = [],
+        no_statement: bool = False,
+        dry_run: bool = False,
+    ) -> None: ...
+    @property
+    def path(self) -> str: ...
+    @property
+    def format(self) -> str: ...
+    def info(self) -> Dict[str, Any]: ...
+    def dag(self) -> str: ...
+    def evaluate(
+        self,
+        on_event: Optional[Callable[[Any], None]] = None,
+        options: List[str] = [],
+    ) -> Dict[str, Any]: ...
@@ -0,0 +1,41 @@
+"""Evaluate one of the test tasks of task-maker-rust through the Python bindings."""
+
+import shutil
+from pathlib import Path
+
+import pytest
+
+import task_maker
+
+TASKS = Path(__file__).resolve().parents[3] / "tests" / "tasks"
+
+
+@pytest.fixture
+def task_dir(tmp_path):
+    # the evaluation writes the testcases in the task directory
+    return shutil.copytree(TASKS / "with_stdio", tmp_path / "with_stdio")
+
+
+def test_info(task_dir):
+    task = task_maker.Task(task_dir)
+    assert task.format == "ioi"
+    assert task.info()["IOI"]["name"] == "with_stdio"
+
+
+def test_evaluate(task_dir, tmp_path):
+    task = task_maker.Task(task_dir, filter=["soluzione.cpp", "wa.cpp"], no_statement=True)
+    events = []
+    results = task.evaluate(
+        on_event=events.append,
+        options=["--store-dir", str(tmp_path / "store"), "--no-cache"],
+    )
+    assert events
+    scores = {solution["name"]: solution["score"] for solution in results["solutions"]}
+    assert scores == {"soluzione.cpp": 100.0, "wa.cpp": 50.0}
+    assert results["max_score"] == 100.0
+
+
+def test_invalid_options(task_dir):
+    task = task_maker.Task(task_dir)
+    with pytest.raises(ValueError):
+        task.evaluate(options=["--not-an-option"])
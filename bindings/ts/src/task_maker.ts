@@ -24,6 +24,7 @@ export type UIMessage =
       IOIGeneration: {
         subtask: SubtaskId;
         testcase: TestcaseId;
+        name: string | null;
         status: UIExecutionStatus;
       };
     }
@@ -59,10 +60,19 @@ export type UIMessage =
         status: UIExecutionStatus;
       };
     }
+  | {
+      IOICheckerError: {
+        subtask: SubtaskId;
+        testcase: TestcaseId;
+        solution: string;
+        message: string;
+      };
+    }
   | {
       IOITestcaseScore: {
         subtask: SubtaskId;
         testcase: TestcaseId;
+        name: string | null;
         solution: string;
         score: number;
         message: string;
@@ -104,7 +114,10 @@ export type UIMessage =
         outcome: { Ok: SolutionOutcome } | { Err: string };
       };
     }
-  | { Warning: { message: string } };
+  | { Warning: { message: string } }
+  | { FuzzBatch: { index: number; size: number } }
+  | { FuzzMinimization: { parameter: number; low: number; high: number } }
+  | { FuzzFailure: { generator_args: string[]; message: string } };
 // The status of an execution.
 export type UIExecutionStatus =
   | "Pending"
@@ -118,12 +131,15 @@ export type ExecutorStatus<T> = {
   connected_workers: ExecutorWorkerStatus<T>[];
   ready_execs: number;
   waiting_execs: number;
+  cache_hits: number;
+  cache_misses: number;
 };
 // Status of a worker of an `Executor`.
 export type ExecutorWorkerStatus<T> = {
   uuid: WorkerUuid;
   name: string;
   current_job: WorkerCurrentJobStatus<T> | null;
+  telemetry: WorkerTelemetry;
 };
 // Information about the job the worker is currently doing.
 export type WorkerCurrentJobStatus<T> = {
@@ -131,6 +147,12 @@ export type WorkerCurrentJobStatus<T> = {
   client: ClientInfo;
   duration: T;
 };
+// Information about the machine a worker is running on, reported by the worker itself every time
+// it asks for a new job.
+export type WorkerTelemetry = {
+  load_average: number | null;
+  free_memory: number | null;
+};
 // Information about a client of the scheduler.
 export type ClientInfo = { uuid: ClientUuid; name: string };
 // Information about a generic IOI task.
@@ -171,6 +193,7 @@ export type ExecutionResult = {
   resources: ExecutionResourcesUsage;
   stdout: number[] | null;
   stderr: number[] | null;
+  time_scale: number;
 };
 // The type of the task. This changes the behavior of the solutions.
 export type TaskType =
@@ -188,6 +211,7 @@ export type SubtaskInfo = {
 // The files are written there only if it's not a dry-run and if the files are not static.
 export type TestcaseInfo = {
   id: TestcaseId;
+  name: string | null;
   input_generator: InputGenerator;
   input_validator: InputValidator;
   output_generator: OutputGenerator;
@@ -299,7 +323,16 @@ export type CommunicationTypeData = {
 };
 // Which tool to use to compute the score on a testcase given the input file, the _correct_ output
 // file and the output file to evaluate.
-export type Checker = "WhiteDiff" | { Custom: SourceFile };
+export type Checker =
+  | { WhiteDiff: OutputNormalization }
+  | { Custom: SourceFile };
+// How the outputs are normalized by the white diff checker before comparing them.
+export type OutputNormalization = {
+  space_change: boolean;
+  trailing_whitespace: boolean;
+  blank_lines: boolean;
+  unicode_nfc: boolean;
+};
 // A source file that will be able to be executed (with an optional compilation step).
 // After creating a `SourceFile` using `new` you can add start using it via the `execute` method.
 // Note that it may add to the DAG an extra execution for compiling the source file.
@@ -0,0 +1,506 @@
+//! The configuration files of task-maker.
+//!
+//! The defaults of some command line options are read from the user configuration file
+//! (`~/.config/task-maker/config.toml`) and from the `.task-maker.toml` file in the task directory
+//! or in one of its parents. The values of the task configuration take precedence over the user
+//! one, and they are both overridden by the environment variables and by the command line flags.
+//!
+//! The task configuration is committed together with the tasks, so it's as trusted as the tasks
+//! themselves: it cannot set where the evaluations run, where the files are stored, where the
+//! results are sent nor which compilers are run. Those values are only read from the user
+//! configuration.
+//!
+//! The values are exported as the environment variables the options and the languages already
+//! read, like `TM_STORE_DIR` and `TM_CXXFLAGS`, before parsing the command line.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// Name of the per-task configuration file.
+pub const TASK_CONFIG_FILE: &str = ".task-maker.toml";
+
+/// The content of a configuration file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// The default UI, like `print` or `curses`.
+    pub ui: Option<String>,
+    /// The number of CPU cores to use.
+    pub num_cores: Option<usize>,
+    /// Where to store the storage files, including the cache.
+    pub store_dir: Option<PathBuf>,
+    /// The address of the server to run the evaluations on.
+    pub evaluate_on: Option<String>,
+    /// The name of the client in the remote executions.
+    pub name: Option<String>,
+    /// The URL notified with the results at the end of the evaluations.
+    pub webhook: Option<String>,
+    /// The template of the body of the request sent to the webhook.
+    pub webhook_template: Option<PathBuf>,
+    /// The URL of the central collector the results are uploaded to.
+    pub upload_results: Option<String>,
+    /// The token authenticating the uploads to the collector.
+    pub upload_token: Option<String>,
+    /// The chats notified with the summary of the evaluations.
+    pub notifications: NotificationsConfig,
+    /// The compilers and their flags.
+    pub languages: LanguagesConfig,
+}
+
+/// The chats notified with the summary of the evaluations.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// When to notify, like `finish` or `official-loses-points`.
+    pub on: Option<String>,
+    /// The token of the Slack bot.
+    pub slack_token: Option<String>,
+    /// The Slack channel to post to.
+    pub slack_channel: Option<String>,
+    /// The token of the Telegram bot.
+    pub telegram_token: Option<String>,
+    /// The Telegram chat to post to.
+    pub telegram_chat: Option<String>,
+}
+
+/// The compilers and the flags used for compiling the source files.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LanguagesConfig {
+    /// The C compiler.
+    pub cc: Option<String>,
+    /// The C standard version, like `c11`.
+    pub cc_std_version: Option<String>,
+    /// Extra flags for the C compiler.
+    pub cflags: Option<String>,
+    /// The C++ compiler.
+    pub cxx: Option<String>,
+    /// The C++ standard version, like `c++17`.
+    pub cxx_std_version: Option<String>,
+    /// Extra flags for the C++ compiler.
+    pub cxxflags: Option<String>,
+}
+
+/// The content of a task configuration file: only the values that don't change what is run nor
+/// where the data is sent.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TaskConfig {
+    /// The default UI, like `print` or `curses`.
+    pub ui: Option<String>,
+    /// The number of CPU cores to use.
+    pub num_cores: Option<usize>,
+    /// The name of the client in the remote executions.
+    pub name: Option<String>,
+    /// The standard versions and the flags of the compilers.
+    pub languages: TaskLanguagesConfig,
+}
+
+/// The standard versions and the flags used for compiling the source files of a task.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TaskLanguagesConfig {
+    /// The C standard version, like `c11`.
+    pub cc_std_version: Option<String>,
+    /// Extra flags for the C compiler.
+    pub cflags: Option<String>,
+    /// The C++ standard version, like `c++17`.
+    pub cxx_std_version: Option<String>,
+    /// Extra flags for the C++ compiler.
+    pub cxxflags: Option<String>,
+}
+
+impl From<TaskConfig> for Config {
+    fn from(config: TaskConfig) -> Config {
+        Config {
+            ui: config.ui,
+            num_cores: config.num_cores,
+            name: config.name,
+            languages: LanguagesConfig {
+                cc_std_version: config.languages.cc_std_version,
+                cflags: config.languages.cflags,
+                cxx_std_version: config.languages.cxx_std_version,
+                cxxflags: config.languages.cxxflags,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// Parse a TOML configuration file.
+fn read_toml<T: DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Invalid configuration file {}", path.display()))
+}
+
+impl Config {
+    /// Parse a configuration file. The relative paths are resolved from the directory of the file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, Error> {
+        let path = path.as_ref();
+        let mut config: Config = read_toml(path)?;
+        if let Some(base) = path.parent() {
+            let relative = [&mut config.store_dir, &mut config.webhook_template];
+            for path in relative.into_iter().flatten() {
+                *path = base.join(&path);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Parse a task configuration file, which can contain only the values of [`TaskConfig`].
+    pub fn load_task<P: AsRef<Path>>(path: P) -> Result<Config, Error> {
+        let path = path.as_ref();
+        let config: TaskConfig = read_toml(path).with_context(|| {
+            format!(
+                "{} can set only ui, num-cores, name and the standard versions and the flags of \
+                 the languages, the other values go in the user configuration",
+                TASK_CONFIG_FILE
+            )
+        })?;
+        Ok(config.into())
+    }
+
+    /// Path of the user configuration file, if the configuration directory is known.
+    pub fn user_config_path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("", "", "task-maker")
+            .map(|project| project.config_dir().join("config.toml"))
+    }
+
+    /// Search the task configuration file in `dir` and in its parents.
+    pub fn task_config_path<P: AsRef<Path>>(dir: P) -> Option<PathBuf> {
+        let dir = std::env::current_dir().ok()?.join(dir);
+        dir.ancestors()
+            .map(|dir| dir.join(TASK_CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Combine two configurations, the values of `other` take precedence.
+    pub fn merge(self, other: Config) -> Config {
+        Config {
+            ui: other.ui.or(self.ui),
+            num_cores: other.num_cores.or(self.num_cores),
+            store_dir: other.store_dir.or(self.store_dir),
+            evaluate_on: other.evaluate_on.or(self.evaluate_on),
+            name: other.name.or(self.name),
+            webhook: other.webhook.or(self.webhook),
+            webhook_template: other.webhook_template.or(self.webhook_template),
+            upload_results: other.upload_results.or(self.upload_results),
+            upload_token: other.upload_token.or(self.upload_token),
+            notifications: NotificationsConfig {
+                on: other.notifications.on.or(self.notifications.on),
+                slack_token: other
+                    .notifications
+                    .slack_token
+                    .or(self.notifications.slack_token),
+                slack_channel: other
+                    .notifications
+                    .slack_channel
+                    .or(self.notifications.slack_channel),
+                telegram_token: other
+                    .notifications
+                    .telegram_token
+                    .or(self.notifications.telegram_token),
+                telegram_chat: other
+                    .notifications
+                    .telegram_chat
+                    .or(self.notifications.telegram_chat),
+            },
+            languages: LanguagesConfig {
+                cc: other.languages.cc.or(self.languages.cc),
+                cc_std_version: other
+                    .languages
+                    .cc_std_version
+                    .or(self.languages.cc_std_version),
+                cflags: other.languages.cflags.or(self.languages.cflags),
+                cxx: other.languages.cxx.or(self.languages.cxx),
+                cxx_std_version: other
+                    .languages
+                    .cxx_std_version
+                    .or(self.languages.cxx_std_version),
+                cxxflags: other.languages.cxxflags.or(self.languages.cxxflags),
+            },
+        }
+    }
+
+    /// The environment variables corresponding to the values of this configuration.
+    pub fn env_vars(&self) -> Vec<(&'static str, String)> {
+        let notifications = &self.notifications;
+        let languages = &self.languages;
+        [
+            ("TM_UI", self.ui.clone()),
+            ("TM_NUM_CORES", self.num_cores.map(|n| n.to_string())),
+            (
+                "TM_STORE_DIR",
+                self.store_dir.as_ref().map(|d| d.display().to_string()),
+            ),
+            ("TM_EVALUATE_ON", self.evaluate_on.clone()),
+            ("TM_CLIENT_NAME", self.name.clone()),
+            ("TM_WEBHOOK", self.webhook.clone()),
+            (
+                "TM_WEBHOOK_TEMPLATE",
+                self.webhook_template
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+            ("TM_UPLOAD_RESULTS", self.upload_results.clone()),
+            ("TM_UPLOAD_TOKEN", self.upload_token.clone()),
+            ("TM_NOTIFY_ON", notifications.on.clone()),
+            ("TM_SLACK_TOKEN", notifications.slack_token.clone()),
+            ("TM_SLACK_CHANNEL", notifications.slack_channel.clone()),
+            ("TM_TELEGRAM_TOKEN", notifications.telegram_token.clone()),
+            ("TM_TELEGRAM_CHAT", notifications.telegram_chat.clone()),
+            ("TM_CC", languages.cc.clone()),
+            ("TM_CC_STD_VERSION", languages.cc_std_version.clone()),
+            ("TM_CFLAGS", languages.cflags.clone()),
+            ("TM_CXX", languages.cxx.clone()),
+            ("TM_CXX_STD_VERSION", languages.cxx_std_version.clone()),
+            ("TM_CXXFLAGS", languages.cxxflags.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+        .collect()
+    }
+}
+
+/// Find the task directory in the command line arguments, without parsing them.
+fn task_dir_from_args<I: IntoIterator<Item = OsString>>(args: I) -> Option<PathBuf> {
+    let mut args = args.into_iter().skip(1);
+    while let Some(arg) = args.next() {
+        let arg = arg.to_string_lossy();
+        if arg == "--task-dir" || arg == "-t" {
+            return args.next().map(PathBuf::from);
+        } else if let Some(dir) = arg.strip_prefix("--task-dir=") {
+            return Some(PathBuf::from(dir));
+        }
+    }
+    None
+}
+
+/// Load the user and the task configuration files, exporting their values as environment
+/// variables. The variables already set are not overwritten.
+///
+/// This must be called before parsing the command line and before spawning any thread.
+pub fn apply_config_files() -> Result<(), Error> {
+    let task_dir = task_dir_from_args(std::env::args_os()).unwrap_or_else(|| PathBuf::from("."));
+    let config = load_config_files(Config::user_config_path(), &task_dir)?;
+    for (name, value) in env_vars_to_set(&config, |name| std::env::var_os(name).is_some()) {
+        std::env::set_var(name, value);
+    }
+    Ok(())
+}
+
+/// Load the user configuration, if it exists, and the task configuration of `task_dir`, merged.
+fn load_config_files(user_config: Option<PathBuf>, task_dir: &Path) -> Result<Config, Error> {
+    let mut config = Config::default();
+    if let Some(path) = user_config.filter(|path| path.is_file()) {
+        config = config.merge(Config::load(path)?);
+    }
+    if let Some(path) = Config::task_config_path(task_dir) {
+        config = config.merge(Config::load_task(path)?);
+    }
+    Ok(config)
+}
+
+/// The environment variables of a configuration that are not already set.
+fn env_vars_to_set<F: Fn(&str) -> bool>(config: &Config, is_set: F) -> Vec<(&'static str, String)> {
+    config
+        .env_vars()
+        .into_iter()
+        .filter(|(name, _)| !is_set(name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a file inside `dir`, creating its parents.
+    fn write(dir: &Path, path: &str, content: &str) -> PathBuf {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_merge() {
+        let user = Config {
+            ui: Some("print".into()),
+            num_cores: Some(2),
+            languages: LanguagesConfig {
+                cxx: Some("g++".into()),
+                cxxflags: Some("-O2".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let task = Config {
+            ui: Some("curses".into()),
+            languages: LanguagesConfig {
+                cxxflags: Some("-fsanitize=address".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let config = user.merge(task);
+        assert_eq!(config.ui.as_deref(), Some("curses"));
+        assert_eq!(config.num_cores, Some(2));
+        assert_eq!(config.languages.cxx.as_deref(), Some("g++"));
+        assert_eq!(
+            config.languages.cxxflags.as_deref(),
+            Some("-fsanitize=address")
+        );
+        assert_eq!(config.store_dir, None);
+    }
+
+    #[test]
+    fn test_load_relative_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = write(
+            dir.path(),
+            "config.toml",
+            "store-dir = \"store\"\nwebhook-template = \"/etc/hook.json\"\n",
+        );
+        let config = Config::load(path).unwrap();
+        assert_eq!(config.store_dir, Some(dir.path().join("store")));
+        assert_eq!(config.webhook_template, Some("/etc/hook.json".into()));
+    }
+
+    #[test]
+    fn test_load_unknown_value() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = write(dir.path(), "config.toml", "num-core = 4\n");
+        assert!(Config::load(path).is_err());
+    }
+
+    #[test]
+    fn test_load_task() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = write(
+            dir.path(),
+            TASK_CONFIG_FILE,
+            "ui = \"print\"\nnum-cores = 4\n[languages]\ncxx-std-version = \"c++20\"\n",
+        );
+        let config = Config::load_task(path).unwrap();
+        assert_eq!(config.ui.as_deref(), Some("print"));
+        assert_eq!(config.num_cores, Some(4));
+        assert_eq!(config.languages.cxx_std_version.as_deref(), Some("c++20"));
+    }
+
+    #[test]
+    fn test_load_task_not_trusted() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for content in [
+            "evaluate-on = \"tcp://evil.example.com:27182\"\n",
+            "store-dir = \"/home\"\n",
+            "webhook = \"https://evil.example.com\"\n",
+            "[notifications]\ntelegram-chat = \"42\"\n",
+            "[languages]\ncxx = \"./evil.sh\"\n",
+        ] {
+            let path = write(dir.path(), TASK_CONFIG_FILE, content);
+            assert!(Config::load_task(&path).is_err(), "{} is accepted", content);
+            // the same values are fine in the user configuration
+            assert!(Config::load(&path).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_env_vars() {
+        let config = Config {
+            num_cores: Some(4),
+            store_dir: Some("/var/cache/task-maker".into()),
+            notifications: NotificationsConfig {
+                on: Some("finish".into()),
+                ..Default::default()
+            },
+            languages: LanguagesConfig {
+                cxxflags: Some("-Wall".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            config.env_vars(),
+            vec![
+                ("TM_NUM_CORES", "4".to_string()),
+                ("TM_STORE_DIR", "/var/cache/task-maker".to_string()),
+                ("TM_NOTIFY_ON", "finish".to_string()),
+                ("TM_CXXFLAGS", "-Wall".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_env_vars_not_overwritten() {
+        let config = Config {
+            ui: Some("print".into()),
+            num_cores: Some(4),
+            ..Default::default()
+        };
+        let vars = env_vars_to_set(&config, |name| name == "TM_UI");
+        assert_eq!(vars, vec![("TM_NUM_CORES", "4".to_string())]);
+    }
+
+    #[test]
+    fn test_task_dir_from_args() {
+        let args = |args: &[&str]| {
+            let args = std::iter::once("task-maker-rust").chain(args.iter().copied());
+            task_dir_from_args(args.map(OsString::from))
+        };
+        assert_eq!(args(&[]), None);
+        assert_eq!(args(&["--ui", "print"]), None);
+        assert_eq!(args(&["-t", "poldo"]), Some("poldo".into()));
+        assert_eq!(
+            args(&["--ui", "print", "--task-dir", "poldo"]),
+            Some("poldo".into())
+        );
+        assert_eq!(
+            args(&["--task-dir=poldo", "-t", "other"]),
+            Some("poldo".into())
+        );
+        assert_eq!(args(&["--task-dir"]), None);
+    }
+
+    #[test]
+    fn test_task_config_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let task_dir = dir.path().join("contest/poldo");
+        std::fs::create_dir_all(&task_dir).unwrap();
+        assert_eq!(Config::task_config_path(&task_dir), None);
+        let contest = write(dir.path(), "contest/.task-maker.toml", "");
+        assert_eq!(Config::task_config_path(&task_dir), Some(contest));
+        let task = write(&task_dir, ".task-maker.toml", "");
+        assert_eq!(Config::task_config_path(&task_dir), Some(task));
+    }
+
+    #[test]
+    fn test_load_config_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let user = write(
+            dir.path(),
+            "user/config.toml",
+            "ui = \"print\"\nnum-cores = 2\nevaluate-on = \"tcp://tm.example.com:27182\"\n",
+        );
+        let task_dir = dir.path().join("poldo");
+        write(&task_dir, TASK_CONFIG_FILE, "ui = \"curses\"\n");
+        let config = load_config_files(Some(user), &task_dir).unwrap();
+        assert_eq!(config.ui.as_deref(), Some("curses"));
+        assert_eq!(config.num_cores, Some(2));
+        assert_eq!(
+            config.evaluate_on.as_deref(),
+            Some("tcp://tm.example.com:27182")
+        );
+
+        let missing = dir.path().join("missing.toml");
+        let config = load_config_files(Some(missing), &task_dir).unwrap();
+        assert_eq!(config.ui.as_deref(), Some("curses"));
+        assert_eq!(config.evaluate_on, None);
+    }
+}
@@ -7,14 +7,16 @@ use std::thread::JoinHandle;
 
 use anyhow::{anyhow, bail, Context, Error};
 
-use task_maker_cache::Cache;
-use task_maker_dag::CacheMode;
+use task_maker_dag::{CacheMode, EvaluationUuid};
+use task_maker_diagnostics::Diagnostic;
 use task_maker_exec::ductile::{new_local_channel, ChannelReceiver, ChannelSender};
 use task_maker_exec::executors::{LocalExecutor, RemoteEntityMessage, RemoteEntityMessageResponse};
 use task_maker_exec::proto::{ExecutorClientMessage, ExecutorServerMessage};
-use task_maker_exec::ExecutorClient;
+use task_maker_exec::quota::QuotaExceeded;
+use task_maker_exec::{available_cores, ExecutorClient};
+use task_maker_format::results::ResultsCollector;
 use task_maker_format::ui::{UIChannelReceiver, UIMessage, UIType, UI};
-use task_maker_format::{EvaluationData, TaskFormat, UISender, VALID_TAGS};
+use task_maker_format::{EvaluationData, Tag, TaskFormat, UISender, VALID_TAGS};
 use task_maker_store::FileStore;
 
 use crate::remote::connect_to_remote_server;
@@ -23,6 +25,14 @@ use crate::{render_dag, ExecutionOpt, StorageOpt, ToolsSandboxRunner};
 /// Version of task-maker.
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// A sender to the executor, shared with the control-C handler.
+type SharedClientSender = Arc<Mutex<Option<ChannelSender<ExecutorClientMessage>>>>;
+
+lazy_static! {
+    /// The senders of the running clients, stopped when control-C is pressed.
+    static ref CTRLC_CLIENT_SENDERS: Mutex<Vec<SharedClientSender>> = Mutex::new(Vec::new());
+}
+
 /// First step of the execution: take a task and build the Execution DAG. This needs setting the
 /// first configurations of the environment.
 pub struct RuntimeContext {
@@ -44,6 +54,17 @@ pub struct ConnectedExecutor {
     pub file_store: Arc<FileStore>,
     pub tx: ChannelSender<ExecutorClientMessage>,
     pub rx: ChannelReceiver<ExecutorServerMessage>,
+    pub compression: bool,
+    pub local_executor: Option<JoinHandle<Result<(), Error>>>,
+}
+
+/// A connection to an executor (either local or remote), not yet used by an evaluation.
+pub struct ExecutorConnection {
+    pub file_store: Arc<FileStore>,
+    pub tx: ChannelSender<ExecutorClientMessage>,
+    pub rx: ChannelReceiver<ExecutorServerMessage>,
+    /// Whether the files sent to the executor are compressed, as negotiated with it.
+    pub compression: bool,
     pub local_executor: Option<JoinHandle<Result<(), Error>>>,
 }
 
@@ -55,11 +76,12 @@ pub struct ConnectedExecutorWithUI {
     pub file_store: Arc<FileStore>,
     pub tx: ChannelSender<ExecutorClientMessage>,
     pub rx: ChannelReceiver<ExecutorServerMessage>,
+    pub compression: bool,
     pub local_executor: Option<JoinHandle<Result<(), Error>>>,
 
     // new fields
     pub ui_thread: JoinHandle<()>,
-    pub client_sender: Arc<Mutex<Option<ChannelSender<ExecutorClientMessage>>>>,
+    pub client_sender: SharedClientSender,
 }
 
 impl RuntimeContext {
@@ -75,6 +97,9 @@ impl RuntimeContext {
         BuildDag: FnOnce(&mut TaskFormat, &mut EvaluationData) -> Result<(), Error>,
     {
         let (mut eval, ui_receiver) = EvaluationData::new(task.path());
+        eval.strict = opt.strict;
+        eval.detect_flaky = opt.detect_flaky;
+        eval.external_diff = opt.external_diff;
 
         // extract the configuration from the command line arguments
         let config = eval.dag.config_mut();
@@ -84,9 +109,17 @@ impl RuntimeContext {
             .cache_mode(
                 CacheMode::try_from(&opt.no_cache, &VALID_TAGS).context("Invalid cache mode")?,
             )
+            .failure_cache_mode(
+                CacheMode::try_from(&opt.no_cache_failures, &VALID_TAGS)
+                    .context("Invalid cache mode for the failures")?,
+            )
             .copy_exe(opt.copy_exe)
             .copy_logs(opt.copy_logs)
             .priority(opt.priority);
+        // remote evaluations keep running on the server if the connection drops
+        if opt.evaluate_on.is_some() {
+            config.evaluation_id(Some(opt.resume.unwrap_or_else(EvaluationUuid::new_v4)));
+        }
         if let Some(extra_time) = opt.extra_time {
             if extra_time < 0.0 {
                 bail!("The extra time ({}) cannot be negative!", extra_time);
@@ -96,6 +129,14 @@ impl RuntimeContext {
         if let Some(extra_memory) = opt.extra_memory {
             config.extra_memory(extra_memory);
         }
+        if opt.runs == 0 {
+            bail!("The number of runs must be at least 1");
+        }
+        config.runs(opt.runs, opt.runs_policy);
+        // the timings in the cache come from a single run
+        if opt.runs > 1 {
+            config.cache_mode.disable([Tag::Evaluation.into()]);
+        }
 
         // build the execution dag
         build_dag(&mut task, &mut eval)?;
@@ -127,80 +168,140 @@ impl RuntimeContext {
         opt: &ExecutionOpt,
         storage_opt: &StorageOpt,
     ) -> Result<ConnectedExecutor, Error> {
+        let mut connections =
+            ExecutorConnection::connect(1, opt, storage_opt, self.sandbox_runner.clone())?;
+        let connection = connections.pop().expect("One connection is made");
+        Ok(self.with_connection(connection))
+    }
+
+    /// Use a connection to an executor made with [`ExecutorConnection::connect`].
+    pub fn with_connection(self, connection: ExecutorConnection) -> ConnectedExecutor {
+        ConnectedExecutor {
+            task: self.task,
+            eval: self.eval,
+            ui_receiver: self.ui_receiver,
+
+            file_store: connection.file_store,
+            tx: connection.tx,
+            rx: connection.rx,
+            compression: connection.compression,
+            local_executor: connection.local_executor,
+        }
+    }
+}
+
+impl ExecutorConnection {
+    /// Start the local executor or connect to a remote one, making `count` connections for running
+    /// as many evaluations at the same time. They share the same file store and the same local
+    /// executor, whose workers are used by all the evaluations, while each one has its own
+    /// connection to the remote server.
+    ///
+    /// The local executor exits only after all the connections are closed, so the evaluations
+    /// should be executed concurrently.
+    pub fn connect(
+        count: usize,
+        opt: &ExecutionOpt,
+        storage_opt: &StorageOpt,
+        sandbox_runner: ToolsSandboxRunner,
+    ) -> Result<Vec<ExecutorConnection>, Error> {
         // setup the file store
-        let store_path = storage_opt.store_dir();
-        let file_store = Arc::new(
-            FileStore::new(
-                store_path.join("store"),
-                storage_opt.max_cache * 1024 * 1024,
-                storage_opt.min_cache * 1024 * 1024,
-            )
-            .context(
-                "Cannot create the file store (You can try wiping it with task-maker-tools reset)",
-            )?,
-        );
+        let file_store = Arc::new(storage_opt.file_store().context(
+            "Cannot create the file store (You can try wiping it with task-maker-tools reset)",
+        )?);
 
         // connect either to the remote executor or spawn a local one
-        let (tx, rx, local_executor) = if let Some(evaluate_on) = &opt.evaluate_on {
-            let (tx, rx) = connect_to_remote_server(evaluate_on, 27182)
-                .context("Cannot connect to the remote server")?;
-            let name = opt.name.clone().unwrap_or_else(|| {
-                format!(
-                    "{}@{}",
-                    whoami::username(),
-                    whoami::fallible::hostname().unwrap()
-                )
-            });
-            tx.send(RemoteEntityMessage::Welcome {
-                name,
-                version: VERSION.into(),
-            })
-            .context("Cannot send welcome to the server")?;
-            if let RemoteEntityMessageResponse::Rejected(err) =
-                rx.recv().context("Failed to receive welcome response")?
-            {
-                bail!("The server rejected the client connection: {}", err);
+        let mut channels = Vec::new();
+        let mut local_executor = None;
+        if let Some(evaluate_on) = &opt.evaluate_on {
+            for _ in 0..count {
+                channels.push(connect_to_remote_client(evaluate_on, opt)?);
             }
-            (tx.change_type(), rx.change_type(), None)
         } else {
-            // start the server and the client
-            let (tx, rx_remote) = new_local_channel();
-            let (tx_remote, rx) = new_local_channel();
+            // start the server and the clients
+            let mut clients = Vec::new();
+            for _ in 0..count {
+                let (tx, rx_remote) = new_local_channel();
+                let (tx_remote, rx) = new_local_channel();
+                channels.push((tx, rx, false));
+                clients.push((tx_remote, rx_remote));
+            }
 
             // setup the local cache
-            let cache_path = store_path.join("cache");
-            let cache = Cache::new(cache_path).context("Cannot create the cache")?;
+            let cache = storage_opt.cache().context("Cannot create the cache")?;
 
             // setup the local executor
-            let num_cores = opt.num_cores.unwrap_or_else(num_cpus::get_physical);
-            let sandbox_path = storage_opt.store_dir().join("sandboxes");
-            let executor = LocalExecutor::new(
+            let num_cores = opt.num_cores.unwrap_or_else(available_cores);
+            let sandbox_path = storage_opt.sandbox_dir();
+            let executor = LocalExecutor::new_with_sandbox_pool(
                 file_store.clone(),
                 cache,
                 num_cores,
                 sandbox_path,
-                self.sandbox_runner,
+                opt.sandbox_pool,
+                sandbox_runner,
             )?;
-            let local_executor = std::thread::Builder::new()
-                .name("Executor thread".into())
-                .spawn(move || executor.evaluate(tx_remote, rx_remote))
-                .context("Failed to spawn the executor thread")?;
-            (tx, rx, Some(local_executor))
-        };
-
-        Ok(ConnectedExecutor {
-            task: self.task,
-            eval: self.eval,
-            ui_receiver: self.ui_receiver,
+            local_executor = Some(
+                std::thread::Builder::new()
+                    .name("Executor thread".into())
+                    .spawn(move || executor.evaluate_clients(clients))
+                    .context("Failed to spawn the executor thread")?,
+            );
+        }
 
-            file_store,
-            tx,
-            rx,
-            local_executor,
-        })
+        let mut connections: Vec<_> = channels
+            .into_iter()
+            .map(|(tx, rx, compression)| ExecutorConnection {
+                file_store: file_store.clone(),
+                tx,
+                rx,
+                compression,
+                local_executor: None,
+            })
+            .collect();
+        // the local executor is waited by the last evaluation
+        if let Some(last) = connections.last_mut() {
+            last.local_executor = local_executor;
+        }
+        Ok(connections)
     }
 }
 
+/// Connect to the remote server as a client, sending the welcome message. Returns the channels
+/// and whether the files sent to the server should be compressed.
+fn connect_to_remote_client(
+    evaluate_on: &str,
+    opt: &ExecutionOpt,
+) -> Result<
+    (
+        ChannelSender<ExecutorClientMessage>,
+        ChannelReceiver<ExecutorServerMessage>,
+        bool,
+    ),
+    Error,
+> {
+    let (tx, rx) = connect_to_remote_server(evaluate_on, 27182)
+        .context("Cannot connect to the remote server")?;
+    let name = opt.name.clone().unwrap_or_else(|| {
+        format!(
+            "{}@{}",
+            whoami::username(),
+            whoami::fallible::hostname().unwrap()
+        )
+    });
+    tx.send(RemoteEntityMessage::Welcome {
+        name,
+        version: VERSION.into(),
+    })
+    .context("Cannot send welcome to the server")?;
+    let compression = match rx.recv().context("Failed to receive welcome response")? {
+        RemoteEntityMessageResponse::Accepted { compression } => compression,
+        RemoteEntityMessageResponse::Rejected(err) => {
+            bail!("The server rejected the client connection: {}", err);
+        }
+    };
+    Ok((tx.change_type(), rx.change_type(), compression))
+}
+
 impl ConnectedExecutor {
     /// Now that we are connected to an executor, we can start the UI thread in background. This
     /// thread will run until the execution is completed or until it is stopped.
@@ -237,21 +338,29 @@ impl ConnectedExecutor {
         // a shared sender for the ctrl-c handler, it has to be wrapped in Arc-Mutex-Option to be freed
         // at the end of the computation to allow the client to exit.
         let client_sender = Arc::new(Mutex::new(Some(self.tx.clone())));
-        // `ctrlc` crate doesn't allow multiple calls of set_handler, and the tests may call this
-        // function multiple times, so in the tests ^C handler is disabled.
+        CTRLC_CLIENT_SENDERS
+            .lock()
+            .unwrap()
+            .push(client_sender.clone());
+        // `ctrlc` crate doesn't allow multiple calls of set_handler, so a single handler stops all
+        // the clients. In the tests ^C handler is disabled.
         #[cfg(not(test))]
         {
-            let client_sender = client_sender.clone();
-            if let Err(e) = ctrlc::set_handler(move || {
-                let sender = client_sender.lock().unwrap();
-                if let Some(sender) = sender.as_ref() {
-                    if sender.send(ExecutorClientMessage::Stop).is_err() {
-                        error!("Cannot tell the server to stop");
+            static CTRLC_HANDLER: std::sync::Once = std::sync::Once::new();
+            CTRLC_HANDLER.call_once(|| {
+                if let Err(e) = ctrlc::set_handler(|| {
+                    for client_sender in CTRLC_CLIENT_SENDERS.lock().unwrap().iter() {
+                        let sender = client_sender.lock().unwrap();
+                        if let Some(sender) = sender.as_ref() {
+                            if sender.send(ExecutorClientMessage::Stop).is_err() {
+                                error!("Cannot tell the server to stop");
+                            }
+                        }
                     }
+                }) {
+                    warn!("Cannot bind control-C handler: {:?}", e);
                 }
-            }) {
-                warn!("Cannot bind control-C handler: {:?}", e);
-            }
+            });
         }
 
         Ok(ConnectedExecutorWithUI {
@@ -260,12 +369,42 @@ impl ConnectedExecutor {
             file_store: self.file_store,
             tx: self.tx,
             rx: self.rx,
+            compression: self.compression,
             local_executor: self.local_executor,
 
             ui_thread,
             client_sender,
         })
     }
+
+    /// Start the UI thread and run the execution, like [`start_ui`](Self::start_ui) followed by
+    /// [`execute`](ConnectedExecutorWithUI::execute), collecting the results of the evaluation.
+    ///
+    /// The callback receives the messages after they are applied to the collector.
+    pub fn collect_results<OnMessage>(
+        self,
+        ui_type: &UIType,
+        mut on_message: OnMessage,
+    ) -> Result<ResultsCollector, Error>
+    where
+        OnMessage: FnMut(&mut dyn UI, UIMessage) + Send + 'static,
+    {
+        let collector = Arc::new(Mutex::new(ResultsCollector::new(&self.task)));
+        let executor = self.start_ui(ui_type, {
+            let collector = collector.clone();
+            move |ui, message| {
+                collector.lock().unwrap().apply(message.clone());
+                on_message(ui, message);
+            }
+        })?;
+        executor.execute()?;
+        // the UI thread, owning the other reference, has been joined
+        let collector = Arc::try_unwrap(collector)
+            .map_err(|_| anyhow!("The results collector is still in use"))?
+            .into_inner()
+            .unwrap();
+        Ok(collector)
+    }
 }
 
 impl ConnectedExecutorWithUI {
@@ -299,10 +438,26 @@ impl ConnectedExecutorWithUI {
 
         // run the actual computation and block until it ends
         let client_sender = self.client_sender;
-        ExecutorClient::evaluate(dag, self.tx, &self.rx, self.file_store, move |status| {
-            ui_sender.send(UIMessage::ServerStatus { status })
-        })
-        .with_context(|| {
+        let result = ExecutorClient::evaluate(
+            dag,
+            self.tx,
+            &self.rx,
+            self.file_store,
+            self.compression,
+            move |update| ui_sender.send(update.into()),
+        );
+        // show the rejections of the server in the UI, not only in the logs
+        let quota = result
+            .as_ref()
+            .err()
+            .and_then(|e| e.downcast_ref::<QuotaExceeded>());
+        if let Some(quota) = quota {
+            let diagnostic =
+                Diagnostic::error(format!("The server rejected the evaluation: {}", quota))
+                    .with_help(quota.help());
+            let _ = self.eval.add_diagnostic(diagnostic);
+        }
+        result.with_context(|| {
             if let Some(tx) = client_sender.lock().unwrap().as_ref() {
                 let _ = tx.send(ExecutorClientMessage::Stop);
             }
@@ -3,18 +3,74 @@ use std::fmt::Display;
 
 /// Adds methods for failing without panic. Like `expect` but without panic.
 pub trait NiceError<T, E> {
-    /// Fail exiting with `1` if the value is not present. Otherwise return the content.
+    /// Fail exiting with the status of the error (see [`ExitStatus::of`]) if the value is not
+    /// present. Otherwise return the content.
     fn nice_unwrap(self) -> T;
 
-    /// Fail exiting with `1` if the value is not present, printing to stderr the message. Otherwise
-    /// return the content.
+    /// Fail exiting with the status of the error if the value is not present, printing to stderr
+    /// the message. Otherwise return the content.
     fn nice_expect<S: Display + Send + Sync + 'static>(self, mex: S) -> T;
 
-    /// Fail exiting with `1` if the value is not present, printing the message returned by the
-    /// provided function. Otherwise return the content.
+    /// Fail exiting with the status of the error if the value is not present, printing the
+    /// message returned by the provided function. Otherwise return the content.
     fn nice_expect_with<S: Display + Send + Sync + 'static, F: FnOnce() -> S>(self, f: F) -> T;
 }
 
+/// The exit statuses of task-maker, each one for a different kind of failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Everything went fine.
+    Success,
+    /// Some solutions didn't score as expected by their `@check` comments or by `--require`.
+    UnexpectedScores,
+    /// task-maker itself failed, for example it couldn't run the sandbox or reach the server.
+    InternalError,
+    /// The task is malformed, it cannot be parsed or its evaluation emitted errors (e.g. a crash
+    /// of the checker).
+    MalformedTask,
+}
+
+impl ExitStatus {
+    /// The exit code of the process.
+    pub fn code(self) -> i32 {
+        match self {
+            ExitStatus::Success => 0,
+            ExitStatus::UnexpectedScores => 1,
+            ExitStatus::InternalError => 2,
+            ExitStatus::MalformedTask => 3,
+        }
+    }
+
+    /// The status of a failure: the one added as context to the error, an internal error if
+    /// there is none.
+    pub fn of(error: &Error) -> ExitStatus {
+        error
+            .downcast_ref::<ExitStatus>()
+            .copied()
+            .unwrap_or(ExitStatus::InternalError)
+    }
+}
+
+impl Display for ExitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExitStatus::Success => write!(f, "Success"),
+            ExitStatus::UnexpectedScores => write!(f, "Some solutions scored unexpectedly"),
+            ExitStatus::InternalError => write!(f, "Internal error"),
+            ExitStatus::MalformedTask => write!(f, "The task is malformed"),
+        }
+    }
+}
+
+impl std::error::Error for ExitStatus {}
+
+/// Print the error to stderr and exit with its status, see [`ExitStatus::of`].
+pub fn exit_with_status(error: Error) -> ! {
+    let status = ExitStatus::of(&error);
+    print_error(error);
+    std::process::exit(status.code());
+}
+
 fn print_error(error: Error) {
     debug!("{:?}", error);
     let mut fail: &dyn std::error::Error = error.as_ref();
@@ -30,8 +86,7 @@ impl<T> NiceError<T, Error> for Result<T, Error> {
         match self {
             Ok(x) => x,
             Err(e) => {
-                print_error(e);
-                std::process::exit(1);
+                exit_with_status(e);
             }
         }
     }
@@ -40,8 +95,7 @@ impl<T> NiceError<T, Error> for Result<T, Error> {
         match self {
             Ok(x) => x,
             Err(e) => {
-                print_error(e.context(mex));
-                std::process::exit(1);
+                exit_with_status(e.context(mex));
             }
         }
     }
@@ -50,8 +104,7 @@ impl<T> NiceError<T, Error> for Result<T, Error> {
         match self {
             Ok(x) => x,
             Err(e) => {
-                print_error(e.context(f()));
-                std::process::exit(1);
+                exit_with_status(e.context(f()));
             }
         }
     }
@@ -62,8 +115,7 @@ impl<T> NiceError<T, ()> for Option<T> {
         match self {
             Some(x) => x,
             None => {
-                print_error(anyhow!("Option is None"));
-                std::process::exit(1);
+                exit_with_status(anyhow!("Option is None"));
             }
         }
     }
@@ -72,8 +124,7 @@ impl<T> NiceError<T, ()> for Option<T> {
         match self {
             Some(x) => x,
             None => {
-                print_error(anyhow!("{}", mex));
-                std::process::exit(1);
+                exit_with_status(anyhow!("{}", mex));
             }
         }
     }
@@ -82,8 +133,7 @@ impl<T> NiceError<T, ()> for Option<T> {
         match self {
             Some(x) => x,
             None => {
-                print_error(anyhow!("{}", f()));
-                std::process::exit(1);
+                exit_with_status(anyhow!("{}", f()));
             }
         }
     }
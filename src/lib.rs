@@ -16,11 +16,15 @@ pub use local::*;
 pub use opt::*;
 pub use sandbox::*;
 
+pub mod config;
 pub mod context;
 pub mod copy_dag;
 pub mod error;
 pub mod local;
+pub mod notify;
 pub mod opt;
 pub mod remote;
 pub mod sandbox;
 pub mod tools;
+pub mod upload;
+pub mod webhook;
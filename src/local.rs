@@ -1,10 +1,18 @@
-use anyhow::{bail, Context, Error};
+use std::time::SystemTime;
 
-use task_maker_format::ui::{UIMessage, UI};
+use anyhow::{anyhow, bail, Context, Error};
+
+use task_maker_exec::plan::{plan_dag, ExecutionPlan};
+use task_maker_format::results::EvaluationResults;
+use task_maker_format::ui::{StdoutPrinter, UIMessage, UIType, BOLD, GREEN, UI, YELLOW};
+use task_maker_format::{cwrite, cwriteln, EvaluationConfig, EvaluationData, TaskFormat};
 
 use crate::context::RuntimeContext;
-use crate::error::NiceError;
-use crate::opt::Opt;
+use crate::error::{exit_with_status, ExitStatus};
+use crate::notify::Notifier;
+use crate::opt::{ExecutionOpt, FailOn, Opt, StorageOpt};
+use crate::upload::upload_results;
+use crate::webhook::send_webhook;
 
 /// The result of an evaluation.
 pub enum Evaluation {
@@ -12,6 +20,8 @@ pub enum Evaluation {
     Done,
     /// The task directory has been cleaned.
     Clean,
+    /// The planned executions have been printed, without running them.
+    Planned,
 }
 
 /// Run the local evaluation of some actions (either building a task or cleaning its directory).
@@ -40,7 +50,13 @@ where
 
     // setup the task
     let eval_config = opt.to_config();
-    let task = opt.find_task.find_task(&eval_config)?;
+    let task = opt
+        .find_task
+        .find_task(&eval_config)
+        .context(ExitStatus::MalformedTask)?;
+    if opt.terry_feedback.is_some() && !matches!(task, TaskFormat::Terry(_)) {
+        bail!("--terry-feedback is available only for Terry tasks");
+    }
 
     // clean the task
     if opt.clean {
@@ -50,21 +66,227 @@ where
     }
 
     // setup the configuration and the evaluation metadata
+    let only_statement = opt.booklet.only_statement;
+    // the plan and the DOT file of the DAG include all the executions
+    let incremental = !opt.plan && !opt.execution.copy_dag && !opt.execution.no_incremental_dag;
     let context = RuntimeContext::new(task, &opt.execution, |task, eval| {
+        eval.incremental = incremental;
+        if only_statement {
+            return build_statements(task, eval)
+                .context("Cannot build the statements")
+                .context(ExitStatus::MalformedTask);
+        }
         // build the DAG for the task
         task.build_dag(eval, &eval_config)
             .context("Cannot build the task DAG")
+            .context(ExitStatus::MalformedTask)
     })?;
 
+    if opt.plan {
+        if opt.execution.evaluate_on.is_some() {
+            bail!("--plan uses the local cache, it cannot be used with --evaluate-on");
+        }
+        let file_store = opt
+            .storage
+            .file_store()
+            .context("Cannot create the file store")?;
+        let mut cache = opt.storage.cache().context("Cannot create the cache")?;
+        let plan = plan_dag(&context.eval.dag.data, &mut cache, &file_store)
+            .context("Cannot plan the evaluation")?;
+        print_plan(&plan);
+        return Ok(Evaluation::Planned);
+    }
+
+    // keep track of the results, if they are needed at the end of the evaluation
+    let sinks = opt.notify.sinks();
+    let collect = opt.results.is_some()
+        || opt.terry_feedback.is_some()
+        || opt.webhook.is_some()
+        || opt.upload_results.is_some()
+        || !opt.require.is_empty()
+        || !opt.fail_on.is_empty()
+        || !sinks.is_empty();
+    let notifier =
+        (!sinks.is_empty()).then(|| Notifier::new(&context.task, opt.notify.notify_on, sinks));
+
     // start the execution
+    let task_dir = context.task.path().to_path_buf();
+    let started_at = SystemTime::now();
     let executor = context.connect_executor(&opt.execution, &opt.storage)?;
-    let executor = executor.start_ui(&opt.ui.ui, on_message)?;
-    executor.execute()?;
+    let collector = if collect {
+        Some(executor.collect_results(&opt.ui.ui, on_message)?)
+    } else {
+        executor.start_ui(&opt.ui.ui, on_message)?.execute()?;
+        None
+    };
+
+    if let Some(collector) = collector {
+        let results = collector.results();
+        // a chat that cannot be reached doesn't make the evaluation fail
+        if let Some(notifier) = &notifier {
+            if let Err(e) = notifier.finish(&results) {
+                warn!("{:#}", e);
+            }
+        }
+        if let Some(path) = &opt.results {
+            let content = serde_json::to_string_pretty(&results)
+                .context("Failed to serialize the results")?;
+            std::fs::write(path, content)
+                .with_context(|| format!("Failed to write the results to {}", path.display()))?;
+        }
+        if let Some(dir) = &opt.terry_feedback {
+            collector
+                .write_terry_feedback(dir)
+                .context("Failed to write the feedback pages")?;
+        }
+        // as for the notifications, an unreachable webhook doesn't change the outcome
+        if let Some(url) = &opt.webhook {
+            if let Err(e) = send_webhook(url, opt.webhook_template.as_deref(), &results) {
+                warn!("{:#}", e);
+            }
+        }
+        if let Some(url) = &opt.upload_results {
+            if let Err(e) = upload_results(
+                url,
+                opt.upload_token.as_deref(),
+                &task_dir,
+                opt.execution.name.as_deref(),
+                started_at,
+                &results,
+            ) {
+                warn!("{:#}", e);
+            }
+        }
+        // the problems of the task are reported before the unexpected scores they may cause
+        for (condition, kind, messages) in [
+            (FailOn::Errors, "error", &results.errors),
+            (FailOn::Warnings, "warning", &results.warnings),
+        ] {
+            if opt.fail_on.contains(&condition) && !messages.is_empty() {
+                return Err(anyhow!(
+                    "{} {}(s) emitted:\n    {}",
+                    messages.len(),
+                    kind,
+                    messages.join("\n    ")
+                )
+                .context(ExitStatus::MalformedTask));
+            }
+        }
+        let mut failures: Vec<_> = opt
+            .require
+            .iter()
+            .filter_map(|requirement| requirement.check(&results).err())
+            .collect();
+        if opt.fail_on.contains(&FailOn::Checks) {
+            failures.extend(
+                results
+                    .checks
+                    .iter()
+                    .filter(|check| !check.success)
+                    .map(|check| {
+                        format!(
+                            "{} is not {} on subtask {}, as its @check expects",
+                            check.solution.display(),
+                            check.expected,
+                            check.subtask
+                        )
+                    }),
+            );
+        }
+        if !failures.is_empty() {
+            return Err(anyhow!(
+                "{} requirement(s) not satisfied:\n    {}",
+                failures.len(),
+                failures.join("\n    ")
+            )
+            .context(ExitStatus::UnexpectedScores));
+        }
+    }
 
     Ok(Evaluation::Done)
 }
 
+/// Evaluate a task already parsed, without a UI, collecting its results.
+///
+/// The callback is called with every UI message produced during the evaluation, this is the
+/// building block for driving task-maker from another program.
+pub fn evaluate_task<F>(
+    task: TaskFormat,
+    eval_config: &EvaluationConfig,
+    execution: &ExecutionOpt,
+    storage: &StorageOpt,
+    mut on_message: F,
+) -> Result<EvaluationResults, Error>
+where
+    F: FnMut(UIMessage) + Send + 'static,
+{
+    let context = RuntimeContext::new(task, execution, |task, eval| {
+        task.build_dag(eval, eval_config)
+            .context("Cannot build the task DAG")
+            .context(ExitStatus::MalformedTask)
+    })?;
+    let executor = context.connect_executor(execution, storage)?;
+    let collector = executor.collect_results(&UIType::Silent, move |ui, message| {
+        ui.on_message(message.clone());
+        on_message(message);
+    })?;
+    Ok(collector.results())
+}
+
+/// Build the DAG compiling only the statements and the booklets of the task, skipping the
+/// generation and the evaluation.
+fn build_statements(task: &mut TaskFormat, eval: &mut EvaluationData) -> Result<(), Error> {
+    match task {
+        TaskFormat::IOI(task) => {
+            if task.booklets.is_empty() {
+                bail!("The task has no statement to compile");
+            }
+            // the testcases are not evaluated, hide them from the UI
+            task.subtasks.clear();
+            task.testcases.clear();
+            for booklet in task.booklets.iter() {
+                booklet.build(eval)?;
+            }
+        }
+        TaskFormat::Terry(task) => {
+            let Some(statement) = &task.statement else {
+                bail!("The task has no statement to render");
+            };
+            statement.build(eval)?;
+        }
+    }
+    Ok(())
+}
+
+/// Print the executions that would run and the ones that would come from the cache.
+fn print_plan(plan: &ExecutionPlan) {
+    let mut printer = StdoutPrinter::default();
+    let sections = [
+        ("Would be executed", &*YELLOW, &plan.to_run),
+        ("Would come from the cache", &*GREEN, &plan.cached),
+    ];
+    for (title, color, groups) in sections {
+        cwriteln!(printer, color, "{} ({})", title, groups.len());
+        for group in groups.iter() {
+            match group.tag() {
+                Some(tag) => cwrite!(printer, BOLD, "    [{}] ", tag.name),
+                None => print!("    "),
+            }
+            println!("{}", group.description);
+        }
+        println!();
+    }
+    cwrite!(printer, BOLD, "Total: ");
+    println!(
+        "{} to execute, {} from the cache",
+        plan.to_run.len(),
+        plan.cached.len()
+    );
+}
+
 /// Entry point of the local execution.
 pub fn main_local(opt: Opt) {
-    run_evaluation(opt, |ui, mex| ui.on_message(mex)).nice_unwrap();
+    if let Err(e) = run_evaluation(opt, |ui, mex| ui.on_message(mex)) {
+        exit_with_status(e);
+    }
 }
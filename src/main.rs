@@ -144,7 +144,9 @@
 //! ```
 //!
 //! This will start `task-maker` in server mode, listening for connections from clients and workers
-//! respectively on port 27182 and 27183.
+//! respectively on port 27182 and 27183. The addresses to listen on can be changed, including IPv6
+//! addresses (like `[::]:27182`) and Unix domain sockets (like `unix:///run/task-maker/client.sock`),
+//! which are handy when the server is on the same host as a reverse proxy.
 //!
 //! Then on the worker machines start a worker with
 //! ```bash
@@ -188,10 +190,13 @@
 
 use clap::Parser;
 
+use task_maker_rust::config::apply_config_files;
+use task_maker_rust::error::NiceError;
 use task_maker_rust::main_local;
 use task_maker_rust::opt;
 
 fn main() {
+    apply_config_files().nice_unwrap();
     let mut opt = opt::Opt::parse();
     opt.enable_log();
     main_local(opt);
@@ -0,0 +1,381 @@
+//! Notification of the outcome of the evaluations to the chats, like Slack and Telegram.
+//!
+//! At the end of the evaluation a [`Notifier`] sends a compact summary of its results, the same
+//! ones written by `--results`, to all its sinks. Each chat is a [`NotificationSink`], new
+//! chats are supported implementing that trait.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Error};
+use serde_json::{json, Value};
+
+use task_maker_format::ioi::OutputGenerator;
+use task_maker_format::results::EvaluationResults;
+use task_maker_format::TaskFormat;
+
+/// Timeout of the requests to the chats.
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// When the chats are notified, see `--notify-on`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyOn {
+    /// At the end of every evaluation.
+    Finish,
+    /// Only when the official solution doesn't get the maximum score.
+    OfficialLosesPoints,
+}
+
+impl FromStr for NotifyOn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<NotifyOn, Self::Err> {
+        match s.trim() {
+            "finish" => Ok(NotifyOn::Finish),
+            "official-loses-points" => Ok(NotifyOn::OfficialLosesPoints),
+            _ => Err(format!(
+                "Unknown condition: {} (expected finish or official-loses-points)",
+                s
+            )),
+        }
+    }
+}
+
+/// A chat where the summaries of the evaluations are posted.
+pub trait NotificationSink: Send {
+    /// The name of the chat, for the error messages.
+    fn name(&self) -> &'static str;
+
+    /// Post a message in the chat.
+    fn send(&self, text: &str) -> Result<(), Error>;
+}
+
+/// A Slack channel, where a bot posts the messages.
+pub struct SlackSink {
+    /// The OAuth token of the bot, like `xoxb-...`.
+    pub token: String,
+    /// The name or the id of the channel.
+    pub channel: String,
+}
+
+impl NotificationSink for SlackSink {
+    fn name(&self) -> &'static str {
+        "Slack"
+    }
+
+    fn send(&self, text: &str) -> Result<(), Error> {
+        let response = post_json(
+            "https://slack.com/api/chat.postMessage",
+            Some(&self.token),
+            json!({ "channel": self.channel, "text": text }),
+        )?;
+        // Slack reports the errors in the body of a successful response
+        if response["ok"] != Value::Bool(true) {
+            bail!("{}", response["error"].as_str().unwrap_or("unknown error"));
+        }
+        Ok(())
+    }
+}
+
+/// A Telegram chat, where a bot posts the messages.
+pub struct TelegramSink {
+    /// The token of the bot, like `123456:ABC-...`.
+    pub token: String,
+    /// The id of the chat, or the `@username` of the channel.
+    pub chat: String,
+}
+
+impl NotificationSink for TelegramSink {
+    fn name(&self) -> &'static str {
+        "Telegram"
+    }
+
+    fn send(&self, text: &str) -> Result<(), Error> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.token);
+        post_json(&url, None, json!({ "chat_id": self.chat, "text": text }))?;
+        Ok(())
+    }
+}
+
+/// Send a JSON body with a POST request, returning the JSON of the response.
+fn post_json(url: &str, bearer: Option<&str>, body: Value) -> Result<Value, Error> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(NOTIFICATION_TIMEOUT)
+        .build();
+    let mut request = agent.post(url);
+    if let Some(token) = bearer {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+    let response = request.send_json(body).map_err(|e| match e {
+        // don't leak the tokens in the URL
+        ureq::Error::Status(code, _) => anyhow!("The request failed with status {}", code),
+        ureq::Error::Transport(e) => anyhow!("The request failed: {}", e.kind()),
+    })?;
+    response.into_json().context("Invalid response")
+}
+
+/// Notifies its sinks with the results of an evaluation.
+pub struct Notifier {
+    /// The path of the official solution, if the task has one.
+    official_solution: Option<PathBuf>,
+    /// When the sinks are notified.
+    notify_on: NotifyOn,
+    /// The chats to notify.
+    sinks: Vec<Box<dyn NotificationSink>>,
+}
+
+impl Notifier {
+    /// Make a new `Notifier` for the evaluation of a task.
+    pub fn new(
+        task: &TaskFormat,
+        notify_on: NotifyOn,
+        sinks: Vec<Box<dyn NotificationSink>>,
+    ) -> Self {
+        let official_solution = match task {
+            TaskFormat::IOI(task) => {
+                task.testcases
+                    .values()
+                    .find_map(|testcase| match &testcase.output_generator {
+                        OutputGenerator::Custom(solution, _) => Some(solution.path.clone()),
+                        _ => None,
+                    })
+            }
+            TaskFormat::Terry(_) => None,
+        };
+        Notifier {
+            official_solution,
+            notify_on,
+            sinks,
+        }
+    }
+
+    /// Notify the sinks with the summary of the results of the evaluation, if needed. All the
+    /// sinks are notified even if some of them fail.
+    pub fn finish(&self, results: &EvaluationResults) -> Result<(), Error> {
+        let lost_points = self.official_lost_points(results);
+        if lost_points.is_none() && self.notify_on == NotifyOn::OfficialLosesPoints {
+            return Ok(());
+        }
+        let text = summary(results, lost_points.as_deref());
+        let failures: Vec<_> = self
+            .sinks
+            .iter()
+            .filter_map(|sink| {
+                sink.send(&text)
+                    .err()
+                    .map(|e| format!("{}: {:#}", sink.name(), e))
+            })
+            .collect();
+        if !failures.is_empty() {
+            bail!("Failed to send the notifications: {}", failures.join(", "));
+        }
+        Ok(())
+    }
+
+    /// The description of how the official solution lost points, if it did.
+    fn official_lost_points(&self, results: &EvaluationResults) -> Option<String> {
+        let official = self.official_solution.as_ref()?;
+        let solution = results
+            .solutions
+            .iter()
+            .find(|solution| official.ends_with(&solution.path))?;
+        match solution.score {
+            _ if !solution.compiled => Some(format!(
+                "The official solution {} does not compile",
+                solution.path.display()
+            )),
+            Some(score) if score + 1e-6 < results.max_score => Some(format!(
+                "The official solution {} scored {}/{}",
+                solution.path.display(),
+                score,
+                results.max_score
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// A compact summary of the results, with a line for each solution.
+fn summary(results: &EvaluationResults, lost_points: Option<&str>) -> String {
+    let mut lines = vec![format!(
+        "{}: evaluation finished (max score {})",
+        results.task, results.max_score
+    )];
+    if let Some(lost_points) = lost_points {
+        lines.push(format!("WARNING: {}", lost_points));
+    }
+    for solution in &results.solutions {
+        let score = match solution.score {
+            _ if !solution.compiled => "compilation failed".to_string(),
+            Some(score) => score.to_string(),
+            None => "not evaluated".to_string(),
+        };
+        lines.push(format!("{}: {}", solution.path.display(), score));
+    }
+    if !results.errors.is_empty() || !results.warnings.is_empty() {
+        lines.push(format!(
+            "{} errors, {} warnings",
+            results.errors.len(),
+            results.warnings.len()
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use task_maker_format::ioi::IOITask;
+    use task_maker_format::results::SolutionResults;
+
+    use super::*;
+
+    /// A sink that records the messages, or fails to send them.
+    struct RecordingSink {
+        sent: Arc<Mutex<Vec<String>>>,
+        fail: bool,
+    }
+
+    impl NotificationSink for RecordingSink {
+        fn name(&self) -> &'static str {
+            "Recording"
+        }
+
+        fn send(&self, text: &str) -> Result<(), Error> {
+            if self.fail {
+                bail!("unreachable");
+            }
+            self.sent.lock().unwrap().push(text.to_string());
+            Ok(())
+        }
+    }
+
+    fn solution(path: &str, compiled: bool, score: Option<f64>) -> SolutionResults {
+        SolutionResults {
+            name: path.rsplit('/').next().unwrap().into(),
+            path: path.into(),
+            compiled,
+            score,
+            summary: Default::default(),
+            subtasks: vec![],
+        }
+    }
+
+    fn results(official_score: Option<f64>) -> EvaluationResults {
+        EvaluationResults {
+            task: "poldo".into(),
+            max_score: 100.0,
+            solutions: vec![
+                solution("sol/solution.cpp", true, official_score),
+                solution("sol/wrong.cpp", true, Some(30.0)),
+                solution("sol/broken.cpp", false, None),
+            ],
+            checks: vec![],
+            errors: vec![],
+            warnings: vec!["Missing statement".into()],
+        }
+    }
+
+    /// A notifier of the task `poldo`, with the sinks that record the messages in `sent`.
+    fn notifier(notify_on: NotifyOn, sent: &Arc<Mutex<Vec<String>>>) -> Notifier {
+        Notifier {
+            official_solution: Some("/tasks/poldo/sol/solution.cpp".into()),
+            notify_on,
+            sinks: vec![Box::new(RecordingSink {
+                sent: sent.clone(),
+                fail: false,
+            })],
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(
+            summary(&results(Some(100.0)), None),
+            "poldo: evaluation finished (max score 100)\n\
+             sol/solution.cpp: 100\n\
+             sol/wrong.cpp: 30\n\
+             sol/broken.cpp: compilation failed\n\
+             0 errors, 1 warnings"
+        );
+        let mut results = results(None);
+        results.warnings.clear();
+        assert_eq!(
+            summary(&results, Some("The official solution does not compile")),
+            "poldo: evaluation finished (max score 100)\n\
+             WARNING: The official solution does not compile\n\
+             sol/solution.cpp: not evaluated\n\
+             sol/wrong.cpp: 30\n\
+             sol/broken.cpp: compilation failed"
+        );
+    }
+
+    #[test]
+    fn test_official_lost_points() {
+        let sent = Arc::new(Mutex::new(vec![]));
+        let notifier = notifier(NotifyOn::Finish, &sent);
+        assert_eq!(notifier.official_lost_points(&results(Some(100.0))), None);
+        assert_eq!(
+            notifier.official_lost_points(&results(Some(60.0))),
+            Some("The official solution sol/solution.cpp scored 60/100".into())
+        );
+        let mut results = results(None);
+        results.solutions[0].compiled = false;
+        assert_eq!(
+            notifier.official_lost_points(&results),
+            Some("The official solution sol/solution.cpp does not compile".into())
+        );
+    }
+
+    #[test]
+    fn test_finish() {
+        let sent = Arc::new(Mutex::new(vec![]));
+        notifier(NotifyOn::Finish, &sent)
+            .finish(&results(Some(100.0)))
+            .unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+        assert!(sent.lock().unwrap()[0].starts_with("poldo: evaluation finished"));
+    }
+
+    #[test]
+    fn test_finish_official_loses_points() {
+        let sent = Arc::new(Mutex::new(vec![]));
+        let notifier = notifier(NotifyOn::OfficialLosesPoints, &sent);
+        notifier.finish(&results(Some(100.0))).unwrap();
+        assert!(sent.lock().unwrap().is_empty());
+        notifier.finish(&results(Some(60.0))).unwrap();
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].contains("WARNING: The official solution sol/solution.cpp scored 60/100"));
+    }
+
+    #[test]
+    fn test_finish_failing_sink() {
+        let sent = Arc::new(Mutex::new(vec![]));
+        let mut notifier = notifier(NotifyOn::Finish, &sent);
+        notifier.sinks.insert(
+            0,
+            Box::new(RecordingSink {
+                sent: sent.clone(),
+                fail: true,
+            }),
+        );
+        let error = notifier.finish(&results(Some(100.0))).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Failed to send the notifications: Recording: unreachable"
+        );
+        // the other sinks are notified anyway
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_no_official_solution() {
+        let task = TaskFormat::IOI(IOITask::fake());
+        let notifier = Notifier::new(&task, NotifyOn::OfficialLosesPoints, vec![]);
+        assert_eq!(notifier.official_solution, None);
+        assert_eq!(notifier.official_lost_points(&results(Some(0.0))), None);
+    }
+}
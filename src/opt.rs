@@ -1,14 +1,25 @@
 use std::collections::HashMap;
+use std::fs::Permissions;
+use std::ops::RangeInclusive;
+use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
+use std::str::FromStr;
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use clap::{ArgAction, Parser};
 use itertools::Itertools;
 
-use task_maker_dag::DagPriority;
+use task_maker_cache::{Cache, RemoteCache};
+use task_maker_dag::{DagPriority, EvaluationUuid, MemoryMetric, RunsPolicy};
+use task_maker_format::ioi::{SubtaskId, TestcaseId};
+use task_maker_format::results::Requirement;
 use task_maker_format::terry::Seed;
+use task_maker_format::ui::{set_color_choice, ColorChoice};
 use task_maker_format::{find_task, get_sanity_check_list, TaskFormat};
 use task_maker_format::{EvaluationConfig, VALID_TAGS};
+use task_maker_store::FileStore;
+
+use crate::notify::{NotificationSink, NotifyOn, SlackSink, TelegramSink};
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -38,6 +49,84 @@ pub struct Opt {
     #[clap(long = "clean")]
     pub clean: bool,
 
+    /// Print what would be executed and what would come from the cache, without running anything
+    ///
+    /// The DAG is built and looked up in the local cache (and in the remote one, if any), so this
+    /// cannot be used together with --evaluate-on. The executions depending on one that would run
+    /// are reported as executed as well, since their inputs are not known yet.
+    #[clap(long = "plan")]
+    pub plan: bool,
+
+    /// Write the results of the evaluation to this file, in JSON
+    #[clap(long = "results", value_name = "FILE")]
+    pub results: Option<PathBuf>,
+
+    /// Write to this directory the HTML feedback page of each solution of a Terry task
+    ///
+    /// The pages show the validation and the feedback of each test case, as the contestants see
+    /// them after submitting the same output.
+    #[clap(long = "terry-feedback", value_name = "DIR")]
+    pub terry_feedback: Option<PathBuf>,
+
+    /// Fail if the score of a solution doesn't satisfy this requirement
+    ///
+    /// The requirements have the form "sol/sol.cpp == 100", where the solution is either its path
+    /// relative to the task directory or its file name, and the operator is one of `==`, `!=`,
+    /// `>=`, `<=`, `>` and `<`. When a requirement is not satisfied task-maker exits with a non-zero
+    /// status. Can be specified multiple times.
+    #[clap(long = "require", value_name = "REQUIREMENT")]
+    pub require: Vec<Requirement>,
+
+    /// Fail when one of these comma separated conditions happens
+    ///
+    /// The conditions are `checks` (a solution doesn't satisfy its @check comments, exit status
+    /// 1), `errors` (the evaluation emitted an error, like a crash of the checker, exit status 3)
+    /// and `warnings` (the evaluation emitted a warning, exit status 3). Regardless of this
+    /// option, task-maker exits with status 1 when a --require is not satisfied, 2 when it fails
+    /// internally and 3 when the task cannot be parsed.
+    #[clap(long = "fail-on", value_name = "CONDITIONS", value_delimiter = ',')]
+    pub fail_on: Vec<FailOn>,
+
+    /// Send the results to this URL, with a POST request, when the evaluation finishes
+    ///
+    /// The body of the request is the JSON of the results, the same written by --results, unless
+    /// --webhook-template is specified.
+    #[clap(long = "webhook", value_name = "URL", env = "TM_WEBHOOK")]
+    pub webhook: Option<String>,
+
+    /// The template of the body of the request sent to the webhook
+    ///
+    /// The placeholders `{task}`, `{scores}` (the score of each solution), `{errors}`,
+    /// `{warnings}` and `{results}` (all the results) are replaced by their JSON value.
+    #[clap(
+        long = "webhook-template",
+        value_name = "FILE",
+        env = "TM_WEBHOOK_TEMPLATE",
+        requires = "webhook"
+    )]
+    pub webhook_template: Option<PathBuf>,
+
+    /// Upload the results to this central collector, with a POST request, when the evaluation
+    /// finishes
+    ///
+    /// Alongside the results, the body of the request contains the name of the task, the git
+    /// commit and the path of the task in its repository, the name of the client and the duration
+    /// of the evaluation.
+    #[clap(long = "upload-results", value_name = "URL", env = "TM_UPLOAD_RESULTS")]
+    pub upload_results: Option<String>,
+
+    /// The token authenticating the uploads to the collector, sent as a bearer token
+    #[clap(
+        long = "upload-token",
+        env = "TM_UPLOAD_TOKEN",
+        hide_env_values = true,
+        requires = "upload_results"
+    )]
+    pub upload_token: Option<String>,
+
+    #[clap(flatten, next_help_heading = Some("NOTIFICATIONS"))]
+    pub notify: NotifyOpt,
+
     #[clap(flatten, next_help_heading = Some("BOOKLET"))]
     pub booklet: BookletOpt,
 
@@ -45,6 +134,22 @@ pub struct Opt {
     #[clap(short = 'W', long = "skip-checks", long_help = skip_sanity_checks_long_help())]
     pub skip_sanity_checks: Vec<String>,
 
+    /// Run each generator twice with the same arguments, reporting an error if the inputs differ
+    ///
+    /// This catches the generators that read uninitialized memory or use an unseeded random number
+    /// generator. The second generations never come from the cache, so the generation takes about
+    /// twice the time.
+    #[clap(long = "verify-generators")]
+    pub verify_generators: bool,
+
+    /// Check the official outputs with the checker, reporting an error if it doesn't give them full
+    /// score
+    ///
+    /// Each official output is checked twice, and an error is reported also if the two scores
+    /// differ. The second checkings never come from the cache.
+    #[clap(long = "check-checker")]
+    pub check_checker: bool,
+
     #[clap(flatten, next_help_heading = Some("STORAGE"))]
     pub storage: StorageOpt,
 
@@ -60,6 +165,16 @@ pub struct LoggerOpt {
     /// Verbose mode (-v, -vv, -vvv, etc.). Note that it does not play well with curses ui.
     #[clap(short, long, action = ArgAction::Count)]
     pub verbose: u8,
+
+    /// When to print colors: auto, always or never. With auto the colors are printed only to a
+    /// terminal, and never if the NO_COLOR environment variable is set.
+    #[clap(
+        long = "color",
+        value_name = "WHEN",
+        default_value = "auto",
+        env = "TM_COLOR"
+    )]
+    pub color: clap::ColorChoice,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -78,7 +193,7 @@ pub struct UIOpt {
     /// Which UI to use, available UIs are: print, raw, curses, json.
     ///
     /// Note that the JSON api is not stable yet.
-    #[clap(long = "ui", default_value = "curses")]
+    #[clap(long = "ui", default_value = "curses", env = "TM_UI")]
     pub ui: task_maker_format::ui::UIType,
 }
 
@@ -97,6 +212,15 @@ pub struct ExecutionOpt {
     #[allow(clippy::option_option)]
     pub no_cache: Option<Option<String>>,
 
+    /// Do not cache the failed executions with this comma separated list of tags
+    #[clap(
+        long = "no-cache-failures",
+        long_help = no_cache_failures_long_help(),
+        require_equals = true
+    )]
+    #[allow(clippy::option_option)]
+    pub no_cache_failures: Option<Option<String>>,
+
     /// Give to the solution some extra time before being killed
     #[clap(long = "extra-time")]
     pub extra_time: Option<f64>,
@@ -105,6 +229,46 @@ pub struct ExecutionOpt {
     #[clap(long = "extra-memory")]
     pub extra_memory: Option<u64>,
 
+    /// Which memory of the solutions is checked against the memory limit: address-space or rss.
+    /// Overrides the one of the task.
+    ///
+    /// With address-space the allocations above the limit fail, but the programs reserving large
+    /// ranges of memory (like the JVM or the builds with the sanitizers) fail even when they use
+    /// little memory. With rss the peak resident memory is checked after the execution.
+    #[clap(long = "memory-metric")]
+    pub memory_metric: Option<MemoryMetric>,
+
+    /// Run each evaluation this many times, reporting the time of all the runs
+    #[clap(long = "runs", default_value_t = 1)]
+    pub runs: usize,
+
+    /// Which run decides the result of a repeated evaluation, ordering them by time: min, median
+    /// or max
+    #[clap(long = "runs-policy", default_value = "median", requires = "runs")]
+    pub runs_policy: RunsPolicy,
+
+    /// Abort the evaluation when a checker crashes or prints an invalid score
+    ///
+    /// By default the testcase scores zero with a checker error and the evaluation continues.
+    #[clap(long = "strict")]
+    pub strict: bool,
+
+    /// Evaluate each solution twice on each testcase, warning when the verdicts or the outputs
+    /// differ
+    ///
+    /// This catches the race conditions of the multithreaded solutions and the unstable floating
+    /// point outputs. The second evaluations never come from the cache. Only the batch tasks are
+    /// supported.
+    #[clap(long = "detect-flaky")]
+    pub detect_flaky: bool,
+
+    /// Compare the outputs with the white diff checker by spawning `diff`
+    ///
+    /// By default the workers compare the outputs by themselves, without spawning a process for
+    /// each testcase.
+    #[clap(long = "external-diff")]
+    pub external_diff: bool,
+
     /// Copy the executables to the bin/ folder
     #[clap(long = "copy-exe")]
     pub copy_exe: bool,
@@ -117,37 +281,99 @@ pub struct ExecutionOpt {
     #[clap(long = "copy-dag")]
     pub copy_dag: bool,
 
+    /// Build the whole DAG before starting the evaluation
+    ///
+    /// By default the evaluations of the compiled solutions are added to the DAG only after their
+    /// compilation, so the evaluation of the tasks with many testcases and solutions starts sooner.
+    #[clap(long = "no-incremental-dag")]
+    pub no_incremental_dag: bool,
+
     /// The number of CPU cores to use.
-    #[clap(long = "num-cores")]
+    ///
+    /// By default all the physical cores are used, unless the process is bound to fewer CPUs or
+    /// the CPU time is limited by a cgroup quota (e.g. inside a container).
+    #[clap(long = "num-cores", env = "TM_NUM_CORES")]
     pub num_cores: Option<usize>,
 
+    /// Keep this many sandbox directories ready to be reused by the local workers, reducing the
+    /// overhead of tasks with many tiny executions. Zero disables the pool.
+    ///
+    /// The directories of the programs without a compilation (e.g. the Python generators) are kept
+    /// prepared with their files as well, up to the same number, for running them again.
+    #[clap(long = "sandbox-pool", default_value_t = 0)]
+    pub sandbox_pool: usize,
+
     /// Run the evaluation on a remote server instead of locally
-    #[clap(long = "evaluate-on")]
+    #[clap(long = "evaluate-on", env = "TM_EVALUATE_ON")]
     pub evaluate_on: Option<String>,
 
     /// The name to use for the client in remote executions
-    #[clap(long)]
+    #[clap(long, env = "TM_CLIENT_NAME")]
     pub name: Option<String>,
 
     /// Priority of the evaluations spawned by this invocation of task-maker; no effect if running
     /// locally.
-    #[clap(long, default_value = "0")]
+    ///
+    /// The server always runs the executions of the evaluations with the highest priority first,
+    /// sharing the workers fairly among the evaluations with the same priority. Negative values are
+    /// allowed for background evaluations.
+    #[clap(long, default_value = "0", allow_hyphen_values = true)]
     pub priority: DagPriority,
+
+    /// Reattach to an evaluation that is still running on the remote server after the client
+    /// disconnected, replaying the results obtained in the meantime.
+    ///
+    /// The identifier of the evaluation is printed when the connection to the server is lost.
+    #[clap(long, value_name = "EVALUATION-ID", requires = "evaluate_on")]
+    pub resume: Option<EvaluationUuid>,
 }
 
 #[derive(Parser, Debug, Clone)]
 pub struct StorageOpt {
     /// Where to store the storage files, including the cache
-    #[clap(long = "store-dir")]
+    #[clap(long = "store-dir", env = "TM_STORE_DIR")]
     pub store_dir: Option<PathBuf>,
 
     /// Maximum size of the storage directory, in MiB
+    ///
+    /// When it's exceeded, the least recently used files are removed, together with the cache
+    /// entries that refer to them.
     #[clap(long = "max-cache", default_value = "3072")]
     pub max_cache: u64,
 
     /// When the storage is flushed, this is the new maximum size, in MiB.
     #[clap(long = "min-cache", default_value = "2048")]
     pub min_cache: u64,
+
+    /// Do not compress the text files added to the storage
+    ///
+    /// By default they are compressed with zstd, and decompressed when they are used.
+    #[clap(long = "no-store-compression")]
+    pub no_store_compression: bool,
+
+    /// Share the storage directory with the other users of its group
+    ///
+    /// The files and the directories created in the storage are made writable by the group, so
+    /// that everyone reuses the cached compilations and generations of the others. The storage
+    /// directory should be created beforehand, owned by the group and with the setgid bit (i.e.
+    /// `chmod 2775`). The instances of task-maker using the storage wait for each other: to run
+    /// them in parallel start a `task-maker-tools server` with this storage, and connect to it with
+    /// `--evaluate-on`.
+    #[clap(long = "shared-store")]
+    pub shared_store: bool,
+
+    /// URL of a remote cache to share the executions between machines
+    ///
+    /// The executions not found in the local cache are searched in the remote one, and the new
+    /// executions are uploaded to it. Any HTTP server supporting GET, HEAD and PUT works, for
+    /// example bazel-remote or nginx with WebDAV. If the TM_REMOTE_CACHE_TOKEN environment variable
+    /// is set, it's sent as bearer token.
+    #[clap(long = "remote-cache")]
+    pub remote_cache: Option<String>,
+
+    /// Only download from the remote cache, without uploading the new executions
+    #[clap(long = "remote-cache-read-only", requires = "remote_cache")]
+    pub remote_cache_read_only: bool,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -163,6 +389,83 @@ pub struct FilterOpt {
     /// The solution can reside anywhere in the filesystem.
     #[clap(long, short)]
     pub solution: Vec<PathBuf>,
+
+    /// Evaluate also the solution with the specified path, together with the ones of the task
+    ///
+    /// The solution can reside anywhere in the filesystem (e.g. a submission of a contestant), and
+    /// it's evaluated even if it doesn't match the filter.
+    #[clap(long = "add-solution", value_name = "PATH")]
+    pub add_solution: Vec<PathBuf>,
+
+    /// Evaluate only the subtasks with these ids (e.g. 3 or 1,3-5)
+    ///
+    /// Only the generations and the evaluations of the testcases of these subtasks are executed.
+    /// Since the subtasks of the other testcases are not evaluated, the checks of the solutions are
+    /// skipped.
+    #[clap(long, value_delimiter = ',', value_parser = parse_id_range)]
+    pub subtask: Vec<RangeInclusive<SubtaskId>>,
+
+    /// Evaluate only the testcases with these ids (e.g. 12 or 12-17)
+    ///
+    /// Can be combined with --subtask, and the checks of the solutions are skipped as well.
+    #[clap(long, value_delimiter = ',', value_parser = parse_id_range)]
+    pub testcase: Vec<RangeInclusive<TestcaseId>>,
+}
+
+impl FilterOpt {
+    /// The ids of the subtasks to evaluate, empty for all of them.
+    pub fn subtask_ids(&self) -> Vec<SubtaskId> {
+        self.subtask.iter().cloned().flatten().collect()
+    }
+
+    /// The ids of the testcases to evaluate, empty for all of them.
+    pub fn testcase_ids(&self) -> Vec<TestcaseId> {
+        self.testcase.iter().cloned().flatten().collect()
+    }
+}
+
+/// Parse an id (e.g. `3`) or an inclusive range of ids (e.g. `12-17`).
+fn parse_id_range(s: &str) -> Result<RangeInclusive<u32>, Error> {
+    let (start, end) = s.split_once('-').unwrap_or((s, s));
+    let start: u32 = start
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid id: {}", start))?;
+    let end: u32 = end
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid id: {}", end))?;
+    if start > end {
+        bail!("Empty range of ids: {}", s);
+    }
+    Ok(start..=end)
+}
+
+/// A condition that makes the evaluation fail, see `--fail-on`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailOn {
+    /// A solution doesn't satisfy its `@check` comments.
+    Checks,
+    /// The evaluation emitted an error.
+    Errors,
+    /// The evaluation emitted a warning.
+    Warnings,
+}
+
+impl FromStr for FailOn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FailOn, Self::Err> {
+        match s.trim() {
+            "checks" => Ok(FailOn::Checks),
+            "errors" => Ok(FailOn::Errors),
+            "warnings" => Ok(FailOn::Warnings),
+            _ => Err(format!(
+                "Unknown condition: {} (expected checks, errors or warnings)",
+                s
+            )),
+        }
+    }
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -170,6 +473,64 @@ pub struct TerryOpt {
     /// Force this seed instead of a random one.
     #[clap(long)]
     pub seed: Option<Seed>,
+
+    /// Ask the generator for this number of test cases instead of the one it chooses. The number is
+    /// passed to the generator as its third argument.
+    #[clap(long, value_name = "N")]
+    pub cases: Option<usize>,
+
+    /// Evaluate each solution on N input files, generated with different seeds, reporting the
+    /// lowest, the mean and the highest score. With `--seed` the seeds are consecutive, starting
+    /// from it.
+    #[clap(long, value_name = "N")]
+    pub seeds: Option<usize>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct NotifyOpt {
+    /// When to notify the chats: at the end of every evaluation (`finish`), or only when the
+    /// official solution doesn't get the maximum score (`official-loses-points`)
+    #[clap(
+        long = "notify-on",
+        value_name = "WHEN",
+        env = "TM_NOTIFY_ON",
+        default_value = "finish"
+    )]
+    pub notify_on: NotifyOn,
+
+    /// The Slack channel where the summary of the evaluation is posted
+    #[clap(
+        long = "slack-channel",
+        env = "TM_SLACK_CHANNEL",
+        requires = "slack_token"
+    )]
+    pub slack_channel: Option<String>,
+
+    /// The token of the Slack bot posting the summary, like `xoxb-...`
+    #[clap(
+        long = "slack-token",
+        env = "TM_SLACK_TOKEN",
+        hide_env_values = true,
+        requires = "slack_channel"
+    )]
+    pub slack_token: Option<String>,
+
+    /// The Telegram chat where the summary of the evaluation is posted
+    #[clap(
+        long = "telegram-chat",
+        env = "TM_TELEGRAM_CHAT",
+        requires = "telegram_token"
+    )]
+    pub telegram_chat: Option<String>,
+
+    /// The token of the Telegram bot posting the summary
+    #[clap(
+        long = "telegram-token",
+        env = "TM_TELEGRAM_TOKEN",
+        hide_env_values = true,
+        requires = "telegram_chat"
+    )]
+    pub telegram_token: Option<String>,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -181,6 +542,10 @@ pub struct BookletOpt {
     /// Do not build the statement files and the booklets
     #[clap(long = "no-statement")]
     pub no_statement: bool,
+
+    /// Build only the statement files and the booklets, without evaluating the task
+    #[clap(long = "only-statement", conflicts_with = "no_statement")]
+    pub only_statement: bool,
 }
 
 /// Returns the long-help for the "skip sanity checks" option.
@@ -218,6 +583,37 @@ fn no_cache_long_help() -> &'static str {
     &DOC
 }
 
+/// Returns the long-help for the --no-cache-failures option.
+fn no_cache_failures_long_help() -> &'static str {
+    lazy_static! {
+        pub static ref DOC: String = format!(
+            "Do not cache the failed executions with this comma separated list of tags\n\nThe failed executions with those tags are executed again every time, while the successful ones are still cached. Providing an empty list will disable the cache for all the failures. The supported tags are: {}.",
+            VALID_TAGS.iter().join(", ")
+        );
+    }
+    &DOC
+}
+
+impl NotifyOpt {
+    /// The chats to notify, the ones with both a token and a channel.
+    pub fn sinks(&self) -> Vec<Box<dyn NotificationSink>> {
+        let mut sinks: Vec<Box<dyn NotificationSink>> = vec![];
+        if let (Some(token), Some(channel)) = (&self.slack_token, &self.slack_channel) {
+            sinks.push(Box::new(SlackSink {
+                token: token.clone(),
+                channel: channel.clone(),
+            }));
+        }
+        if let (Some(token), Some(chat)) = (&self.telegram_token, &self.telegram_chat) {
+            sinks.push(Box::new(TelegramSink {
+                token: token.clone(),
+                chat: chat.clone(),
+            }));
+        }
+        sinks
+    }
+}
+
 impl Opt {
     /// Make an `EvaluationConfig` from this command line options.
     pub fn to_config(&self) -> EvaluationConfig {
@@ -226,9 +622,18 @@ impl Opt {
             booklet_solutions: self.booklet.booklet_solutions,
             no_statement: self.booklet.no_statement,
             solution_paths: self.filter.solution.clone(),
+            extra_solution_paths: self.filter.add_solution.clone(),
             disabled_sanity_checks: self.skip_sanity_checks.clone(),
             seed: self.terry.seed,
+            terry_cases: self.terry.cases,
+            terry_seeds: self.terry.seeds,
             dry_run: self.execution.dry_run,
+            subtask_filter: self.filter.subtask_ids(),
+            testcase_filter: self.filter.testcase_ids(),
+            sample_testcases: None,
+            memory_metric: self.execution.memory_metric,
+            verify_generators: self.verify_generators,
+            check_checker: self.check_checker,
         }
     }
 
@@ -271,6 +676,45 @@ impl StorageOpt {
             }
         }
     }
+
+    /// Open the file store inside the store directory, waiting for the other instances using it.
+    pub fn file_store(&self) -> Result<FileStore, Error> {
+        let mut file_store = FileStore::new(
+            self.store_dir().join("store"),
+            self.max_cache * 1024 * 1024,
+            self.min_cache * 1024 * 1024,
+        )?;
+        file_store.set_compression(!self.no_store_compression);
+        file_store.set_shared(self.shared_store);
+        Ok(file_store)
+    }
+
+    /// Open the cache inside the store directory.
+    pub fn cache(&self) -> Result<Cache, Error> {
+        let mut cache = Cache::new(self.store_dir().join("cache"))?;
+        cache.set_shared(self.shared_store);
+        if let Some(url) = &self.remote_cache {
+            let token = std::env::var("TM_REMOTE_CACHE_TOKEN").ok();
+            cache.set_remote(RemoteCache::new(url, token, self.remote_cache_read_only));
+        }
+        Ok(cache)
+    }
+
+    /// The directory where the sandboxes are created. When the storage is shared each user has its
+    /// own directory, since the sandboxes are not shared.
+    pub fn sandbox_dir(&self) -> PathBuf {
+        let sandboxes = self.store_dir().join("sandboxes");
+        if self.shared_store {
+            if let Err(e) = std::fs::create_dir_all(&sandboxes)
+                .and_then(|_| std::fs::set_permissions(&sandboxes, Permissions::from_mode(0o2775)))
+            {
+                debug!("Cannot share {}: {}", sandboxes.display(), e);
+            }
+            sandboxes.join(whoami::username())
+        } else {
+            sandboxes
+        }
+    }
 }
 
 impl LoggerOpt {
@@ -286,8 +730,16 @@ impl LoggerOpt {
             _ => std::env::set_var("RUST_LOG", "trace,tabox=trace"),
         }
 
+        let (color_choice, write_style) = match self.color {
+            clap::ColorChoice::Auto => (ColorChoice::Auto, env_logger::WriteStyle::Auto),
+            clap::ColorChoice::Always => (ColorChoice::Always, env_logger::WriteStyle::Always),
+            clap::ColorChoice::Never => (ColorChoice::Never, env_logger::WriteStyle::Never),
+        };
+        set_color_choice(color_choice);
+
         env_logger::Builder::from_default_env()
             .format_timestamp_nanos()
+            .write_style(write_style)
             .init();
         better_panic::install();
     }
@@ -1,26 +1,32 @@
-use std::net::SocketAddr;
+use std::net::{Ipv6Addr, SocketAddr};
 use std::path::PathBuf;
 
 use anyhow::{bail, Context, Error};
 use task_maker_exec::ductile::{
     connect_channel, connect_channel_with_enc, connect_unix_channel, ChannelReceiver, ChannelSender,
 };
-use url::{ParseError, Url};
+use url::Url;
 
 use task_maker_exec::derive_key_from_password;
 
 /// Parse the server url address and try to connect to that host.
+///
+/// The address is either `unix://path` for a Unix domain socket, or a host with an optional port,
+/// optionally prefixed by `tcp://`. IPv6 addresses with the port should be enclosed in square
+/// brackets (e.g. `[::1]:27182`).
 pub fn connect_to_remote_server<S, R, Str: AsRef<str>>(
     server_url: Str,
     default_port: u16,
 ) -> Result<(ChannelSender<S>, ChannelReceiver<R>), Error> {
-    let url = match Url::parse(server_url.as_ref()) {
-        Ok(u) => u,
-        Err(ParseError::RelativeUrlWithoutBase) => {
-            Url::parse(&format!("tcp://{}", server_url.as_ref())).context("Invalid server url")?
-        }
-        Err(e) => return Err(e.into()),
+    let server_url = server_url.as_ref();
+    let url = if server_url.contains("://") {
+        server_url.to_string()
+    } else if let Ok(ip) = server_url.parse::<Ipv6Addr>() {
+        format!("tcp://[{}]", ip)
+    } else {
+        format!("tcp://{}", server_url)
     };
+    let url = Url::parse(&url).context("Invalid server url")?;
 
     enum Schema {
         Tcp(Vec<SocketAddr>),
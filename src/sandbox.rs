@@ -10,6 +10,7 @@ use tabox::result::SandboxExecutionResult;
 use tabox::{Sandbox, SandboxImplementation};
 
 use task_maker_exec::find_tools::find_tools_path;
+use task_maker_exec::process_priority::lower_process_priority;
 use task_maker_exec::{RawSandboxResult, SandboxRunner};
 
 /// Actually parse the input and return the result.
@@ -55,8 +56,13 @@ impl Default for ToolsSandboxRunner {
 }
 
 impl SandboxRunner for ToolsSandboxRunner {
-    fn run(&self, config: SandboxConfiguration, pid: Arc<AtomicU32>) -> RawSandboxResult {
-        match tools_sandbox_internal(&self.tools_path, config, pid) {
+    fn run(
+        &self,
+        config: SandboxConfiguration,
+        pid: Arc<AtomicU32>,
+        background: bool,
+    ) -> RawSandboxResult {
+        match tools_sandbox_internal(&self.tools_path, config, pid, background) {
             Ok(res) => res,
             Err(e) => RawSandboxResult::Error(e.to_string()),
         }
@@ -68,6 +74,7 @@ fn tools_sandbox_internal(
     tools_path: &Path,
     config: SandboxConfiguration,
     pid: Arc<AtomicU32>,
+    background: bool,
 ) -> Result<RawSandboxResult, Error> {
     let mut cmd = Command::new(tools_path)
         .arg("internal-sandbox")
@@ -77,6 +84,13 @@ fn tools_sandbox_internal(
         .spawn()
         .context("Cannot spawn the sandbox")?;
     pid.store(cmd.id(), Ordering::SeqCst);
+    // the sandbox waits for the configuration before spawning the process, which inherits the
+    // priority of the sandbox
+    if background {
+        if let Err(e) = lower_process_priority(cmd.id()) {
+            warn!("Cannot lower the priority of the sandbox: {:?}", e);
+        }
+    }
     {
         let stdin = cmd.stdin.as_mut().context("Failed to open stdin")?;
         serde_json::to_writer(stdin, &config.build()).context("Failed to write config to stdin")?;
@@ -55,14 +55,26 @@ pub fn main_add_solution_checks(
     logger_opt: LoggerOpt,
 ) -> Result<(), Error> {
     opt.ui.disable_if_needed(&logger_opt);
+    if !opt.filter.subtask.is_empty() || !opt.filter.testcase.is_empty() {
+        bail!("The checks cannot be inferred evaluating only some subtasks or testcases");
+    }
     let eval_config = EvaluationConfig {
         solution_filter: opt.filter.filter,
         booklet_solutions: false,
         no_statement: true,
         solution_paths: opt.filter.solution,
+        extra_solution_paths: opt.filter.add_solution,
         disabled_sanity_checks: Default::default(),
         seed: Default::default(),
+        terry_cases: None,
+        terry_seeds: None,
         dry_run: true,
+        subtask_filter: vec![],
+        testcase_filter: vec![],
+        sample_testcases: None,
+        memory_metric: opt.execution.memory_metric,
+        verify_generators: false,
+        check_checker: false,
     };
     let task = opt
         .find_task
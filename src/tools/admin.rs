@@ -0,0 +1,164 @@
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+
+use task_maker_dag::EvaluationUuid;
+use task_maker_exec::admin::{AdminRequest, AdminResponse, AdminStatus};
+use task_maker_exec::executors::{RemoteEntityMessage, RemoteEntityMessageResponse};
+use task_maker_exec::ExecutorClient;
+
+use crate::remote::connect_to_remote_server;
+
+#[derive(Parser, Debug, Clone)]
+pub struct AdminOpt {
+    /// Address of the server to administer, the same used by the clients
+    #[clap(long)]
+    pub server: String,
+
+    /// The admin password of the server, set with its `--admin-password`
+    #[clap(long, env = "TM_ADMIN_PASSWORD", hide_env_values = true)]
+    pub password: Option<String>,
+
+    /// Produce JSON output.
+    #[clap(long, short)]
+    pub json: bool,
+
+    /// What to do on the server
+    #[clap(subcommand)]
+    pub command: AdminCommand,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub enum AdminCommand {
+    /// List the workers connected to the server
+    Workers,
+    /// List the clients of the server and their evaluations
+    Clients,
+    /// Show the queue of the evaluations, sorted by priority
+    Queue,
+    /// Stop an evaluation, killing its running executions
+    Cancel {
+        /// The uuid of the client, or the evaluation id
+        evaluation: EvaluationUuid,
+    },
+    /// Stop sending jobs to a worker, and make it exit when its current job is done
+    Drain {
+        /// The uuid or the name of the worker
+        worker: String,
+    },
+}
+
+/// Version of task-maker
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Entry point of `task-maker-tools admin`.
+pub fn main_admin(opt: AdminOpt) -> Result<(), Error> {
+    let (tx, rx) = connect_to_remote_server(&opt.server, 27182)
+        .context("Cannot connect to the remote server")?;
+    let name = format!(
+        "admin {}@{}",
+        whoami::username(),
+        whoami::fallible::hostname().unwrap()
+    );
+    tx.send(RemoteEntityMessage::Welcome {
+        name,
+        version: VERSION.into(),
+    })
+    .context("Cannot send welcome to the server")?;
+    if let RemoteEntityMessageResponse::Rejected(err) =
+        rx.recv().context("Failed to receive welcome response")?
+    {
+        bail!("The server rejected the connection: {}", err);
+    }
+    let (tx, rx) = (tx.change_type(), rx.change_type());
+
+    let request = match &opt.command {
+        AdminCommand::Workers | AdminCommand::Clients | AdminCommand::Queue => AdminRequest::Status,
+        AdminCommand::Cancel { evaluation } => AdminRequest::CancelEvaluation(*evaluation),
+        AdminCommand::Drain { worker } => AdminRequest::DrainWorker(worker.clone()),
+    };
+    match ExecutorClient::admin(request, opt.password.clone(), &tx, &rx)? {
+        AdminResponse::Status(status) => print_status(&opt, status),
+        AdminResponse::Ok(message) => {
+            println!("{}", message);
+            Ok(())
+        }
+        AdminResponse::Error(error) => bail!("The server refused the request: {}", error),
+    }
+}
+
+/// Print the part of the status of the server the user asked for.
+fn print_status(opt: &AdminOpt, mut status: AdminStatus) -> Result<(), Error> {
+    if opt.json {
+        let json = match opt.command {
+            AdminCommand::Workers => serde_json::to_string(&status.workers),
+            _ => serde_json::to_string(&status.evaluations),
+        }
+        .context("Non-serializable status")?;
+        println!("{}", json);
+        return Ok(());
+    }
+    match opt.command {
+        AdminCommand::Workers => {
+            status.workers.sort_by(|a, b| a.name.cmp(&b.name));
+            for worker in &status.workers {
+                let state = if status.draining_workers.contains(&worker.uuid) {
+                    "draining"
+                } else if worker.current_job.is_some() {
+                    "busy"
+                } else {
+                    "idle"
+                };
+                println!("{}  {:<30} {}", worker.uuid, worker.name, state);
+                if let Some(job) = &worker.current_job {
+                    println!(
+                        "    {} for {} ({:.1}s)",
+                        job.job,
+                        job.client.name,
+                        job.duration.as_secs_f64()
+                    );
+                }
+            }
+        }
+        AdminCommand::Clients => {
+            status
+                .evaluations
+                .sort_by(|a, b| a.client.name.cmp(&b.client.name));
+            for evaluation in &status.evaluations {
+                let state = match (evaluation.detached, evaluation.recovered) {
+                    (false, _) => "connected",
+                    (true, false) => "detached",
+                    (true, true) => "recovered",
+                };
+                let id = evaluation
+                    .evaluation_id
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| "-".into());
+                println!(
+                    "{}  {:<30} {:<10} evaluation {}",
+                    evaluation.client.uuid, evaluation.client.name, state, id
+                );
+            }
+        }
+        _ => {
+            status
+                .evaluations
+                .sort_by_key(|e| std::cmp::Reverse(e.priority));
+            println!(
+                "{:<36}  {:<30} {:>8} {:>7} {:>7} {:>7}",
+                "CLIENT", "NAME", "PRIORITY", "READY", "RUNNING", "WAITING"
+            );
+            for evaluation in &status.evaluations {
+                println!(
+                    "{:<36}  {:<30} {:>8} {:>7} {:>7} {:>7}",
+                    evaluation.client.uuid,
+                    evaluation.client.name,
+                    evaluation.priority,
+                    evaluation.ready_execs,
+                    evaluation.running_execs,
+                    evaluation.waiting_execs
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,298 @@
+//! The master template of a task, `sol/templates.yaml`: the signatures of the functions the
+//! contestants implement, from which the template of each language with a grader is generated.
+//!
+//! ```yaml
+//! functions:
+//!   - long solve(int N, int[] A)
+//!   - void init(string S)
+//! ```
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Error};
+use serde::Deserialize;
+
+/// The path of the master template, relative to the task directory.
+pub const MASTER_TEMPLATE: &str = "sol/templates.yaml";
+
+/// The type of a parameter or of the return value of a function of the master template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateType {
+    /// No value, valid only as the return type.
+    Void,
+    /// A 32-bit integer.
+    Int,
+    /// A 64-bit integer.
+    Long,
+    /// A floating point number.
+    Double,
+    /// A boolean.
+    Bool,
+    /// A single character.
+    Char,
+    /// A string of characters.
+    String,
+    /// An array of values of the inner type.
+    Array(Box<TemplateType>),
+}
+
+/// A function implemented by the contestants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateFunction {
+    /// The name of the function.
+    pub name: String,
+    /// The type of the returned value.
+    pub returns: TemplateType,
+    /// The types and the names of the parameters.
+    pub params: Vec<(TemplateType, String)>,
+}
+
+/// The master template of a task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MasterTemplate {
+    /// The functions implemented by the contestants, in the order of the templates.
+    pub functions: Vec<TemplateFunction>,
+}
+
+/// The content of `sol/templates.yaml`.
+#[derive(Deserialize)]
+struct MasterTemplateFile {
+    functions: Vec<String>,
+}
+
+impl TemplateType {
+    /// Parse a type like `int` or `long[][]`.
+    fn parse(s: &str) -> Result<TemplateType, Error> {
+        if let Some(inner) = s.strip_suffix("[]") {
+            let inner = TemplateType::parse(inner.trim_end())?;
+            if inner == TemplateType::Void {
+                bail!("Invalid type {}: there are no arrays of void", s);
+            }
+            return Ok(TemplateType::Array(Box::new(inner)));
+        }
+        match s {
+            "void" => Ok(TemplateType::Void),
+            "int" => Ok(TemplateType::Int),
+            "long" => Ok(TemplateType::Long),
+            "double" => Ok(TemplateType::Double),
+            "bool" => Ok(TemplateType::Bool),
+            "char" => Ok(TemplateType::Char),
+            "string" => Ok(TemplateType::String),
+            _ => bail!(
+                "Unknown type {} (valid are: void, int, long, double, bool, char, string and \
+                 their arrays, like int[])",
+                s
+            ),
+        }
+    }
+
+    /// The type in C++.
+    fn cpp(&self) -> String {
+        match self {
+            TemplateType::Void => "void".into(),
+            TemplateType::Int => "int".into(),
+            TemplateType::Long => "long long".into(),
+            TemplateType::Double => "double".into(),
+            TemplateType::Bool => "bool".into(),
+            TemplateType::Char => "char".into(),
+            TemplateType::String => "string".into(),
+            TemplateType::Array(inner) => format!("vector<{}>", inner.cpp()),
+        }
+    }
+
+    /// The type in C, where the arrays are pointers to their first element.
+    fn c(&self) -> String {
+        match self {
+            TemplateType::String => "char*".into(),
+            TemplateType::Array(inner) => format!("{}*", inner.c()),
+            _ => self.cpp(),
+        }
+    }
+
+    /// The value returned by the templates in C++, `None` for `void`.
+    fn cpp_default(&self) -> Option<&'static str> {
+        match self {
+            TemplateType::Void => None,
+            TemplateType::Int | TemplateType::Long => Some("0"),
+            TemplateType::Double => Some("0.0"),
+            TemplateType::Bool => Some("false"),
+            TemplateType::Char => Some("' '"),
+            TemplateType::String => Some("\"\""),
+            TemplateType::Array(_) => Some("{}"),
+        }
+    }
+
+    /// The value returned by the templates in C, `None` for `void`.
+    fn c_default(&self) -> Option<&'static str> {
+        match self {
+            TemplateType::String | TemplateType::Array(_) => Some("NULL"),
+            _ => self.cpp_default(),
+        }
+    }
+
+    /// The value returned by the templates in Python, `None` for `void`.
+    fn py_default(&self) -> Option<&'static str> {
+        match self {
+            TemplateType::Void => None,
+            TemplateType::Int | TemplateType::Long => Some("0"),
+            TemplateType::Double => Some("0.0"),
+            TemplateType::Bool => Some("False"),
+            TemplateType::Char => Some("\" \""),
+            TemplateType::String => Some("\"\""),
+            TemplateType::Array(_) => Some("[]"),
+        }
+    }
+}
+
+impl TemplateFunction {
+    /// Parse a signature like `long solve(int N, int[] A)`.
+    fn parse(signature: &str) -> Result<TemplateFunction, Error> {
+        let (head, params) = signature
+            .trim()
+            .strip_suffix(')')
+            .and_then(|s| s.split_once('('))
+            .ok_or_else(|| anyhow!("Expecting a signature like: long solve(int N, int[] A)"))?;
+        let (returns, name) = parse_declaration(head)?;
+        let mut parsed_params = vec![];
+        if !params.trim().is_empty() {
+            for param in params.split(',') {
+                let (ty, name) = parse_declaration(param)?;
+                if ty == TemplateType::Void {
+                    bail!("The parameter {} cannot be void", name);
+                }
+                if parsed_params.iter().any(|(_, other)| other == &name) {
+                    bail!("Duplicate parameter {}", name);
+                }
+                parsed_params.push((ty, name));
+            }
+        }
+        Ok(TemplateFunction {
+            name,
+            returns,
+            params: parsed_params,
+        })
+    }
+}
+
+/// Parse a type followed by a name, like `int[] A`.
+fn parse_declaration(s: &str) -> Result<(TemplateType, String), Error> {
+    let (ty, name) = s
+        .trim()
+        .rsplit_once(char::is_whitespace)
+        .ok_or_else(|| anyhow!("Expecting a type and a name, found: {}", s.trim()))?;
+    let valid_name = name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_name {
+        bail!("Invalid name: {}", name);
+    }
+    Ok((TemplateType::parse(ty.trim())?, name.to_string()))
+}
+
+impl MasterTemplate {
+    /// Parse the content of a master template.
+    pub fn parse(content: &str) -> Result<MasterTemplate, Error> {
+        let file: MasterTemplateFile = serde_yaml::from_str(content)?;
+        if file.functions.is_empty() {
+            bail!("The master template has no functions");
+        }
+        let mut functions: Vec<TemplateFunction> = vec![];
+        for signature in &file.functions {
+            let function = TemplateFunction::parse(signature)
+                .with_context(|| format!("Invalid signature: {}", signature))?;
+            if functions.iter().any(|other| other.name == function.name) {
+                bail!("Duplicate function {}", function.name);
+            }
+            functions.push(function);
+        }
+        Ok(MasterTemplate { functions })
+    }
+
+    /// Load the master template of the task, if it has one.
+    pub fn load(task_dir: &Path) -> Result<Option<MasterTemplate>, Error> {
+        let path = task_dir.join(MASTER_TEMPLATE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let template = MasterTemplate::parse(&content)
+            .with_context(|| format!("Invalid {}", MASTER_TEMPLATE))?;
+        Ok(Some(template))
+    }
+
+    /// The template of the language with the specified extension, `None` if the templates of that
+    /// language cannot be generated.
+    pub fn render(&self, ext: &str) -> Option<String> {
+        let (header, functions): (_, Vec<_>) = match ext {
+            "cpp" | "cc" | "cxx" => (
+                "#include <bits/stdc++.h>\nusing namespace std;\n",
+                self.functions.iter().map(render_cpp).collect(),
+            ),
+            "c" => (
+                "#include <stdbool.h>\n#include <stddef.h>\n",
+                self.functions.iter().map(render_c).collect(),
+            ),
+            "py" => ("", self.functions.iter().map(render_py).collect()),
+            _ => return None,
+        };
+        let mut template = header.to_string();
+        for function in functions {
+            if !template.is_empty() {
+                template.push('\n');
+            }
+            template += &function;
+        }
+        Some(template)
+    }
+}
+
+/// A function of the C++ template.
+fn render_cpp(function: &TemplateFunction) -> String {
+    render_c_like(function, TemplateType::cpp, function.returns.cpp_default())
+}
+
+/// A function of the C template.
+fn render_c(function: &TemplateFunction) -> String {
+    render_c_like(function, TemplateType::c, function.returns.c_default())
+}
+
+/// A function with the syntax of C, with the types written by `ty`.
+fn render_c_like(
+    function: &TemplateFunction,
+    ty: fn(&TemplateType) -> String,
+    default: Option<&str>,
+) -> String {
+    let params: Vec<_> = function
+        .params
+        .iter()
+        .map(|(param, name)| format!("{} {}", ty(param), name))
+        .collect();
+    let mut res = format!(
+        "{} {}({}) {{\n    // insert your code here\n",
+        ty(&function.returns),
+        function.name,
+        params.join(", ")
+    );
+    if let Some(default) = default {
+        res += &format!("    return {};\n", default);
+    }
+    res + "}\n"
+}
+
+/// A function of the Python template.
+fn render_py(function: &TemplateFunction) -> String {
+    let params: Vec<_> = function
+        .params
+        .iter()
+        .map(|(_, name)| name.as_str())
+        .collect();
+    format!(
+        "def {}({}):\n    # insert your code here\n    {}\n",
+        function.name,
+        params.join(", "),
+        match function.returns.py_default() {
+            Some(default) => format!("return {}", default),
+            None => "pass".into(),
+        }
+    )
+}
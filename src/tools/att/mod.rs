@@ -0,0 +1,417 @@
+//! Build the att/ directory of the IOI tasks: the templates generated from the master template, the
+//! graders and the sample cases, then verify it with the sanity checks of the attachments.
+
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+
+use task_maker_format::ioi::{IOITask, InputGenerator, TestcaseId};
+use task_maker_format::ui::{StdoutPrinter, GREEN, RED, YELLOW};
+use task_maker_format::{
+    cwrite, cwriteln, get_sanity_check_list, EvaluationConfig, SanityCheckCategory, TaskFormat,
+};
+
+use crate::context::RuntimeContext;
+use crate::{ExecutionOpt, FindTaskOpt, LoggerOpt, StorageOpt, UIOpt};
+
+pub use master::{MasterTemplate, TemplateFunction, TemplateType, MASTER_TEMPLATE};
+
+mod master;
+
+/// The sanity checks of the solutions verifying the templates, run with the ones of the
+/// attachments.
+const TEMPLATE_SANITY_CHECKS: &[&str] = &["SolTemplateSymlink", "SolTemplatesShouldRun"];
+
+#[derive(Parser, Debug, Clone)]
+pub struct AttOpt {
+    /// Only report what is not up to date in att/, failing if something is, without changing
+    /// anything.
+    #[clap(long)]
+    pub check: bool,
+
+    /// Don't run the sanity checks of the attachments after building att/.
+    #[clap(long)]
+    pub no_verify: bool,
+
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+
+    #[clap(flatten, next_help_heading = Some("UI"))]
+    pub ui: UIOpt,
+
+    #[clap(flatten, next_help_heading = Some("EXECUTION"))]
+    pub execution: ExecutionOpt,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+}
+
+/// A change to the files of the task for bringing att/ up to date. The paths are relative to the
+/// task directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttChange {
+    /// Write a template generated from the master template.
+    Template(PathBuf, String),
+    /// Make the first path a symlink to the second one, which is relative to the directory of the
+    /// link.
+    Symlink(PathBuf, PathBuf),
+    /// Copy the first file to the second path.
+    Copy(PathBuf, PathBuf),
+    /// Remove a symlink that is not used anymore.
+    Remove(PathBuf),
+}
+
+/// What has to be done for bringing att/ up to date.
+#[derive(Debug, Clone, Default)]
+pub struct AttPlan {
+    /// The changes to the files of the task, empty if att/ is up to date.
+    pub changes: Vec<AttChange>,
+    /// The problems that have to be fixed by hand.
+    pub warnings: Vec<String>,
+}
+
+impl Display for AttChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttChange::Template(path, _) => {
+                write!(f, "Write {} from {}", path.display(), MASTER_TEMPLATE)
+            }
+            AttChange::Symlink(path, target) => {
+                write!(f, "Link {} to {}", path.display(), target.display())
+            }
+            AttChange::Copy(from, to) => write!(f, "Copy {} to {}", from.display(), to.display()),
+            AttChange::Remove(path) => write!(f, "Remove the stale {}", path.display()),
+        }
+    }
+}
+
+impl AttChange {
+    /// Make the change in the task directory.
+    pub fn apply(&self, task_dir: &Path) -> Result<(), Error> {
+        match self {
+            AttChange::Template(path, content) => {
+                let path = task_dir.join(path);
+                std::fs::write(&path, content)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+            AttChange::Symlink(path, target) => {
+                let path = task_dir.join(path);
+                // a wrong symlink is replaced
+                if path.is_symlink() {
+                    std::fs::remove_file(&path)
+                        .with_context(|| format!("Failed to remove {}", path.display()))?;
+                }
+                std::os::unix::fs::symlink(target, &path)
+                    .with_context(|| format!("Failed to create the link {}", path.display()))?;
+            }
+            AttChange::Copy(from, to) => {
+                std::fs::copy(task_dir.join(from), task_dir.join(to))
+                    .with_context(|| format!("Failed to copy {}", from.display()))?;
+            }
+            AttChange::Remove(path) => {
+                let path = task_dir.join(path);
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AttPlan {
+    /// Find what has to be done for bringing att/ up to date.
+    pub fn new(task: &IOITask) -> Result<AttPlan, Error> {
+        let mut plan = AttPlan::default();
+        plan.add_templates(task)?;
+        plan.add_graders(task);
+        plan.add_samples(task)?;
+        Ok(plan)
+    }
+
+    /// Generate the templates of the languages with a grader from the master template, and link
+    /// them in sol/ for checking that they compile.
+    fn add_templates(&mut self, task: &IOITask) -> Result<(), Error> {
+        let Some(master) = MasterTemplate::load(&task.path)? else {
+            return Ok(());
+        };
+        let mut extensions: Vec<_> = task
+            .grader_map
+            .all_paths()
+            .filter_map(|grader| grader.extension())
+            .map(|ext| ext.to_string_lossy().to_string())
+            .collect();
+        extensions.sort();
+        extensions.dedup();
+        if extensions.is_empty() {
+            self.warnings.push(format!(
+                "The task has no graders, {} is not used",
+                MASTER_TEMPLATE
+            ));
+        }
+        for ext in extensions {
+            let att_name = PathBuf::from(format!("att/{}.{}", task.name, ext));
+            let Some(content) = master.render(&ext) else {
+                self.warnings.push(format!(
+                    "The templates in .{} cannot be generated, write {} by hand",
+                    ext,
+                    att_name.display()
+                ));
+                continue;
+            };
+            let current = std::fs::read_to_string(task.path.join(&att_name)).ok();
+            if current.as_ref() != Some(&content) {
+                self.changes
+                    .push(AttChange::Template(att_name.clone(), content));
+            }
+            let sol_name = PathBuf::from(format!("sol/template.{}", ext));
+            let sol_path = task.path.join(&sol_name);
+            if sol_path.is_symlink() {
+                continue;
+            }
+            if sol_path.exists() {
+                self.warnings.push(format!(
+                    "{} is not a symlink to {}, remove it to let it be linked",
+                    sol_name.display(),
+                    att_name.display()
+                ));
+            } else {
+                self.changes.push(AttChange::Symlink(
+                    sol_name,
+                    Path::new("..").join(&att_name),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Link the graders in att/, unless they already have a version for the contestants.
+    fn add_graders(&mut self, task: &IOITask) {
+        let mut graders: Vec<_> = task.grader_map.all_paths().collect();
+        graders.sort();
+        for grader in graders {
+            let Some(file_name) = grader.file_name() else {
+                continue;
+            };
+            let att_name = Path::new("att").join(file_name);
+            if !task.path.join(&att_name).exists() && !task.path.join(&att_name).is_symlink() {
+                self.changes.push(AttChange::Symlink(
+                    att_name,
+                    Path::new("..").join(task.path_of(grader)),
+                ));
+            }
+        }
+    }
+
+    /// Link the sample cases in att/, copying the outputs generated by task-maker next to the
+    /// inputs when they are missing.
+    fn add_samples(&mut self, task: &IOITask) -> Result<(), Error> {
+        let samples = sample_inputs(task);
+        if samples.is_empty() {
+            return Ok(());
+        }
+        let mut linked = vec![];
+        for (index, (id, input)) in samples.into_iter().enumerate() {
+            let input = task.path_of(&input).to_path_buf();
+            let att_input = PathBuf::from(format!("att/input{}.txt", index));
+            let att_output = PathBuf::from(format!("att/output{}.txt", index));
+            self.add_link(task, &att_input, &input);
+            linked.push(att_input);
+            // a missing output is a warning, the link to its old version is not removed
+            linked.push(att_output.clone());
+
+            let file_name = input.file_name().unwrap_or_default().to_string_lossy();
+            if !file_name.contains("input") {
+                self.warnings.push(format!(
+                    "The name of the sample {} doesn't contain \"input\", link its output to {} \
+                     by hand",
+                    input.display(),
+                    att_output.display()
+                ));
+                continue;
+            }
+            let output = input.with_file_name(file_name.replacen("input", "output", 1));
+            if !task.path.join(&output).exists() {
+                let generated = PathBuf::from(format!("output/output{}.txt", id));
+                if !task.path.join(&generated).exists() {
+                    self.warnings.push(format!(
+                        "The output of the sample {} is missing, run task-maker for generating it",
+                        input.display()
+                    ));
+                    continue;
+                }
+                self.changes
+                    .push(AttChange::Copy(generated, output.clone()));
+            }
+            self.add_link(task, &att_output, &output);
+        }
+
+        let att = task.path.join("att");
+        if !att.is_dir() {
+            return Ok(());
+        }
+        let mut stale = vec![];
+        for entry in
+            std::fs::read_dir(&att).with_context(|| format!("Failed to read {}", att.display()))?
+        {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let is_sample =
+                name.ends_with(".txt") && (name.contains("input") || name.contains("output"));
+            let path = task.path_of(&path).to_path_buf();
+            if is_sample && task.path.join(&path).is_symlink() && !linked.contains(&path) {
+                stale.push(path);
+            }
+        }
+        stale.sort();
+        self.changes
+            .extend(stale.into_iter().map(AttChange::Remove));
+        Ok(())
+    }
+
+    /// Make `link` a symlink to `target`, both relative to the task directory, if it isn't already.
+    fn add_link(&mut self, task: &IOITask, link: &Path, target: &Path) {
+        let target = Path::new("..").join(target);
+        let path = task.path.join(link);
+        if path.is_symlink() {
+            if path.read_link().ok().as_ref() != Some(&target) {
+                self.changes
+                    .push(AttChange::Symlink(link.to_path_buf(), target));
+            }
+        } else if path.exists() {
+            self.warnings.push(format!(
+                "{} is not a symlink, remove it to let it be linked to {}",
+                link.display(),
+                target.display()
+            ));
+        } else {
+            self.changes
+                .push(AttChange::Symlink(link.to_path_buf(), target));
+        }
+    }
+
+    /// Make all the changes in the task directory.
+    pub fn apply(&self, task_dir: &Path) -> Result<(), Error> {
+        if !self.changes.is_empty() {
+            let att = task_dir.join("att");
+            std::fs::create_dir_all(&att)
+                .with_context(|| format!("Failed to create {}", att.display()))?;
+        }
+        for change in &self.changes {
+            change.apply(task_dir)?;
+        }
+        Ok(())
+    }
+}
+
+/// The sample cases of the task, the inputs copied in the first subtask with their testcase ids,
+/// like the AttSampleFiles sanity check expects them. There are no samples if the first subtask
+/// has a generated testcase.
+fn sample_inputs(task: &IOITask) -> Vec<(TestcaseId, PathBuf)> {
+    let Some(subtask) = task.subtasks.get(&0) else {
+        return vec![];
+    };
+    let mut samples = vec![];
+    for id in &subtask.testcases {
+        let Some(testcase) = task.testcases.get(id) else {
+            continue;
+        };
+        match &testcase.input_generator {
+            InputGenerator::StaticFile(path) => samples.push((*id, path.clone())),
+            InputGenerator::Custom(_, _) => return vec![],
+        }
+    }
+    samples.sort();
+    samples
+}
+
+pub fn main_att(mut opt: AttOpt, logger_opt: LoggerOpt) -> Result<(), Error> {
+    opt.ui.disable_if_needed(&logger_opt);
+    let task = find_ioi_task(&opt, &EvaluationConfig::default())?;
+    let plan = AttPlan::new(&task)?;
+
+    let mut printer = StdoutPrinter::default();
+    for warning in &plan.warnings {
+        cwrite!(printer, YELLOW, "Warning");
+        println!(": {}", warning);
+    }
+    if opt.check {
+        if plan.changes.is_empty() {
+            cwriteln!(printer, GREEN, "att/ is up to date");
+            return Ok(());
+        }
+        for change in &plan.changes {
+            cwrite!(printer, RED, "Not up to date");
+            println!(": {}", change);
+        }
+        bail!("att/ is not up to date, run task-maker-tools att for updating it");
+    }
+    for change in &plan.changes {
+        println!("{}", change);
+    }
+    plan.apply(&task.path)?;
+
+    if !opt.no_verify {
+        verify_att(&opt)?;
+    }
+    if plan.changes.is_empty() {
+        cwriteln!(printer, GREEN, "att/ is up to date");
+    } else {
+        cwriteln!(
+            printer,
+            GREEN,
+            "att/ updated with {} changes",
+            plan.changes.len()
+        );
+    }
+    Ok(())
+}
+
+/// Find the IOI task to build the att/ of.
+fn find_ioi_task(opt: &AttOpt, eval_config: &EvaluationConfig) -> Result<IOITask, Error> {
+    let task = opt
+        .find_task
+        .find_task(eval_config)
+        .context("Failed to locate the task")?;
+    match task {
+        TaskFormat::IOI(task) => Ok(task),
+        TaskFormat::Terry(_) => bail!("Only the IOI tasks have the att/ directory"),
+    }
+}
+
+/// Compile the templates with the graders and check the sample cases, running only the sanity
+/// checks of the attachments, without evaluating the solutions.
+fn verify_att(opt: &AttOpt) -> Result<(), Error> {
+    let eval_config = EvaluationConfig {
+        solution_filter: vec!["do not evaluate the solutions!!".into()],
+        no_statement: true,
+        disabled_sanity_checks: get_sanity_check_list()
+            .into_iter()
+            .filter(|(name, category)| {
+                *category != SanityCheckCategory::Attachments
+                    && !TEMPLATE_SANITY_CHECKS.contains(name)
+            })
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        dry_run: opt.execution.dry_run,
+        ..Default::default()
+    };
+    let task = TaskFormat::IOI(find_ioi_task(opt, &eval_config)?);
+
+    let context = RuntimeContext::new(task, &opt.execution, |task, eval| {
+        task.build_dag(eval, &eval_config)
+            .context("Cannot build the task DAG")
+    })?;
+    let executor = context.connect_executor(&opt.execution, &opt.storage)?;
+    let results = executor
+        .collect_results(&opt.ui.ui, |ui, message| ui.on_message(message))?
+        .results();
+    if !results.errors.is_empty() {
+        bail!(
+            "The verification of att/ emitted errors:\n{}",
+            results.errors.join("\n")
+        );
+    }
+    Ok(())
+}
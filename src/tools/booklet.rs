@@ -48,9 +48,18 @@ pub fn main_booklet(mut opt: BookletOpt, logger_opt: LoggerOpt) -> Result<(), Er
         booklet_solutions: opt.booklet_solutions,
         no_statement: false,
         solution_paths: vec![],
+        extra_solution_paths: vec![],
         disabled_sanity_checks: vec![],
         seed: None,
+        terry_cases: None,
+        terry_seeds: None,
         dry_run: opt.execution.dry_run,
+        subtask_filter: vec![],
+        testcase_filter: vec![],
+        sample_testcases: None,
+        memory_metric: None,
+        verify_generators: false,
+        check_checker: false,
     };
 
     if opt.contest_dir.is_some() && !opt.task_dir.is_empty() {
@@ -0,0 +1,230 @@
+//! Collect the files produced by an evaluation in a single directory or zip file, ready to be
+//! uploaded as the artifact of a CI job.
+//!
+//! The bundle contains, in order of importance, the results written by `--results`, the booklets,
+//! the logs copied by `--copy-logs` and, optionally, the testcases. The files that exceed the size
+//! limits are skipped, and a `manifest.json` lists both the bundled and the skipped files.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use task_maker_format::{EvaluationConfig, TaskFormat};
+
+use crate::tools::export::{testcase_files, write_directory, write_zip, ExportedFile};
+use crate::FindTaskOpt;
+
+/// Name of the manifest inside the bundle.
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Parser, Debug, Clone)]
+pub struct BundleArtifactsOpt {
+    /// Where to write the bundle: a directory, or a zip file with --zip. It must not exist.
+    pub output: PathBuf,
+
+    /// Write a zip file instead of a directory.
+    #[clap(long)]
+    pub zip: bool,
+
+    /// The results of the evaluation, the file written by task-maker with --results.
+    #[clap(long, value_name = "FILE")]
+    pub results: Option<PathBuf>,
+
+    /// Include the input and output files of the testcases.
+    #[clap(long)]
+    pub testcases: bool,
+
+    /// Skip the files larger than this size, in MiB.
+    #[clap(long, value_name = "MIB", default_value = "50")]
+    pub max_file_size: u64,
+
+    /// Maximum total size of the bundled files, in MiB: the less important files that don't fit
+    /// are skipped.
+    #[clap(long, value_name = "MIB", default_value = "500")]
+    pub max_size: u64,
+
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+}
+
+/// The kind of a file of the bundle.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ArtifactKind {
+    Results,
+    Booklet,
+    Log,
+    Testcase,
+}
+
+/// A file considered for the bundle.
+#[derive(Debug, Clone, Serialize)]
+struct Artifact {
+    /// The path of the file inside the bundle.
+    path: PathBuf,
+    /// The path of the original file.
+    source: PathBuf,
+    /// What the file is.
+    kind: ArtifactKind,
+    /// The size of the file, in bytes.
+    size: u64,
+    /// Why the file is not in the bundle, if it's skipped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped: Option<String>,
+}
+
+/// The content of `manifest.json`.
+#[derive(Debug, Serialize)]
+struct Manifest<'a> {
+    /// The name of the task.
+    task: &'a str,
+    /// The version of task-maker that made the bundle.
+    version: &'static str,
+    /// All the files considered, including the skipped ones.
+    files: &'a [Artifact],
+}
+
+pub fn main_bundle_artifacts(opt: BundleArtifactsOpt) -> Result<(), Error> {
+    if opt.output.exists() {
+        bail!("{} already exists", opt.output.display());
+    }
+    // the task is only parsed, nothing is written in its directory
+    let eval_config = EvaluationConfig {
+        dry_run: true,
+        ..Default::default()
+    };
+    let task = opt
+        .find_task
+        .find_task(&eval_config)
+        .context("Failed to locate the task")?;
+
+    // the files are listed from the most to the least important
+    let mut sources = vec![];
+    if let Some(results) = &opt.results {
+        if !results.is_file() {
+            bail!("The results file {} does not exist", results.display());
+        }
+        sources.push((
+            PathBuf::from("results.json"),
+            results.clone(),
+            ArtifactKind::Results,
+        ));
+    }
+    if let TaskFormat::IOI(task) = &task {
+        for booklet in &task.booklets {
+            let name = booklet.dest.file_name().context("Invalid booklet path")?;
+            sources.push((
+                Path::new("booklets").join(name),
+                booklet.dest.clone(),
+                ArtifactKind::Booklet,
+            ));
+        }
+    }
+    let logs_dir = task.path().join("bin/logs");
+    for log in WalkDir::new(&logs_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+    {
+        let path = log.path().strip_prefix(&logs_dir)?;
+        sources.push((
+            Path::new("logs").join(path),
+            log.path().into(),
+            ArtifactKind::Log,
+        ));
+    }
+    if opt.testcases {
+        let TaskFormat::IOI(task) = &task else {
+            bail!("Only the testcases of the IOI tasks can be bundled");
+        };
+        for (id, testcase) in &task.testcases {
+            let (input, output) = testcase_files(task, *id, testcase);
+            sources.push((
+                PathBuf::from(format!("testcases/input{}.txt", id)),
+                input,
+                ArtifactKind::Testcase,
+            ));
+            if let Some(output) = output {
+                sources.push((
+                    PathBuf::from(format!("testcases/output{}.txt", id)),
+                    output,
+                    ArtifactKind::Testcase,
+                ));
+            }
+        }
+    }
+
+    let max_file_size = opt.max_file_size * 1024 * 1024;
+    let mut remaining = opt.max_size * 1024 * 1024;
+    let mut artifacts = vec![];
+    for (path, source, kind) in sources {
+        // the booklets and the testcases are missing if they were not built
+        let Ok(metadata) = source.metadata() else {
+            continue;
+        };
+        let size = metadata.len();
+        let skipped = if size > max_file_size {
+            Some(format!("larger than {} MiB", opt.max_file_size))
+        } else if size > remaining {
+            Some(format!("the bundle would exceed {} MiB", opt.max_size))
+        } else {
+            remaining -= size;
+            None
+        };
+        artifacts.push(Artifact {
+            path,
+            source,
+            kind,
+            size,
+            skipped,
+        });
+    }
+
+    let mut files: BTreeMap<PathBuf, ExportedFile> = artifacts
+        .iter()
+        .filter(|artifact| artifact.skipped.is_none())
+        .map(|artifact| {
+            (
+                artifact.path.clone(),
+                ExportedFile::Copy(artifact.source.clone()),
+            )
+        })
+        .collect();
+    let task_name = match &task {
+        TaskFormat::IOI(task) => &task.name,
+        TaskFormat::Terry(task) => &task.name,
+    };
+    let manifest = Manifest {
+        task: task_name,
+        version: env!("CARGO_PKG_VERSION"),
+        files: &artifacts,
+    };
+    let manifest =
+        serde_json::to_string_pretty(&manifest).context("Failed to serialize the manifest")?;
+    files.insert(MANIFEST.into(), ExportedFile::Content(manifest));
+    if opt.zip {
+        write_zip(&files, &opt.output)?;
+    } else {
+        write_directory(&files, &opt.output)?;
+    }
+
+    let skipped = artifacts.iter().filter(|a| a.skipped.is_some()).count();
+    println!(
+        "Bundled {} files of {} in {}",
+        artifacts.len() - skipped,
+        task_name,
+        opt.output.display()
+    );
+    if skipped > 0 {
+        println!(
+            "{} files exceeded the size limits and were skipped, see {}",
+            skipped, MANIFEST
+        );
+    }
+    Ok(())
+}
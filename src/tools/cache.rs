@@ -0,0 +1,132 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+use anyhow::{Context, Error};
+use clap::Parser;
+
+use crate::StorageOpt;
+
+#[derive(Parser, Debug, Clone)]
+pub struct CacheOpt {
+    /// What to do with the cache
+    #[clap(subcommand)]
+    pub command: CacheCommand,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub enum CacheCommand {
+    /// Remove the least recently used files from the storage, and the cache entries referring to
+    /// them
+    Gc(CacheGcOpt),
+    /// Export the cache entries used by the last run, and their files, to a portable bundle
+    Export(CacheExportOpt),
+    /// Import a bundle made by `cache export` into the cache
+    Import(CacheImportOpt),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CacheGcOpt {
+    /// The size of the storage after the collection, in MiB. Defaults to `--min-cache`
+    #[clap(long)]
+    pub size: Option<u64>,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CacheExportOpt {
+    /// Where to write the bundle
+    pub file: PathBuf,
+
+    /// Export all the cache entries, not only the ones used by the last run
+    #[clap(long)]
+    pub all: bool,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CacheImportOpt {
+    /// The bundle to import
+    pub file: PathBuf,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+}
+
+/// Entry point for the cache tool.
+pub fn main_cache(opt: CacheOpt) -> Result<(), Error> {
+    match opt.command {
+        CacheCommand::Gc(opt) => main_cache_gc(opt),
+        CacheCommand::Export(opt) => main_cache_export(opt),
+        CacheCommand::Import(opt) => main_cache_import(opt),
+    }
+}
+
+/// Write the bundle with the cache entries of the last run.
+fn main_cache_export(opt: CacheExportOpt) -> Result<(), Error> {
+    let file_store = opt.storage.file_store().context(
+        "Cannot create the file store (You can try wiping it with task-maker-tools reset)",
+    )?;
+    let cache = opt.storage.cache().context("Cannot create the cache")?;
+    let file =
+        File::create(&opt.file).with_context(|| format!("Cannot create {}", opt.file.display()))?;
+    let stats = cache
+        .export_bundle(&file_store, BufWriter::new(file), opt.all)
+        .context("Failed to export the cache")?;
+    println!(
+        "Exported {} cache entries and {} files to {}",
+        stats.entries,
+        stats.files,
+        opt.file.display()
+    );
+    Ok(())
+}
+
+/// Add the content of a bundle to the cache.
+fn main_cache_import(opt: CacheImportOpt) -> Result<(), Error> {
+    let file_store = opt.storage.file_store().context(
+        "Cannot create the file store (You can try wiping it with task-maker-tools reset)",
+    )?;
+    let mut cache = opt.storage.cache().context("Cannot create the cache")?;
+    let file =
+        File::open(&opt.file).with_context(|| format!("Cannot open {}", opt.file.display()))?;
+    let stats = cache
+        .import_bundle(&file_store, BufReader::new(file))
+        .context("Failed to import the cache")?;
+    println!(
+        "Imported {} cache entries and {} files from {}",
+        stats.entries,
+        stats.files,
+        opt.file.display()
+    );
+    Ok(())
+}
+
+/// Shrink the store down to the requested size, and then drop the cache entries that are not valid
+/// anymore. The store is opened first, so this waits for the other instances of task-maker using
+/// the same storage to exit.
+fn main_cache_gc(opt: CacheGcOpt) -> Result<(), Error> {
+    let file_store = opt.storage.file_store().context(
+        "Cannot create the file store (You can try wiping it with task-maker-tools reset)",
+    )?;
+    let mut cache = opt.storage.cache().context("Cannot create the cache")?;
+
+    let before = file_store.total_size();
+    let target = opt.size.unwrap_or(opt.storage.min_cache) * 1024 * 1024;
+    let removed_bytes = file_store
+        .collect_garbage(target, &Default::default())
+        .context("Failed to collect the garbage of the store")?;
+    let removed_entries = cache.collect_garbage(&file_store);
+    println!(
+        "Removed {:.1} MiB from the storage ({:.1} MiB -> {:.1} MiB) and {} cache entries",
+        removed_bytes as f64 / 1024.0 / 1024.0,
+        before as f64 / 1024.0 / 1024.0,
+        file_store.total_size() as f64 / 1024.0 / 1024.0,
+        removed_entries
+    );
+    Ok(())
+}
@@ -0,0 +1,203 @@
+//! A check of the whole task that only reports the problems, fast enough with `--quick` to be run
+//! by a git hook before every commit.
+
+use std::collections::HashSet;
+use std::time::Instant;
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+
+use task_maker_diagnostics::DiagnosticLevel;
+use task_maker_format::ioi::{OutputGenerator, SubtaskId};
+use task_maker_format::lint::lint_task;
+use task_maker_format::results::EvaluationResults;
+use task_maker_format::ui::{StdoutPrinter, UIType, GREEN, RED, YELLOW};
+use task_maker_format::{cwrite, cwriteln, EvaluationConfig, TaskFormat};
+
+use crate::context::RuntimeContext;
+use crate::{ExecutionOpt, FindTaskOpt, StorageOpt};
+
+#[derive(Parser, Debug, Clone)]
+pub struct CheckOpt {
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+
+    /// Evaluate the official solution only on a sample of the testcases of each subtask
+    #[clap(long)]
+    pub quick: bool,
+
+    /// Number of testcases of each subtask the official solution is evaluated on with --quick
+    #[clap(long, default_value = "2", requires = "quick")]
+    pub sample: usize,
+
+    #[clap(flatten, next_help_heading = Some("EXECUTION"))]
+    pub execution: ExecutionOpt,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+}
+
+pub fn main_check(opt: CheckOpt) -> Result<(), Error> {
+    let start = Instant::now();
+    let mut eval_config = EvaluationConfig {
+        no_statement: true,
+        dry_run: opt.execution.dry_run,
+        memory_metric: opt.execution.memory_metric,
+        ..Default::default()
+    };
+    let task = opt
+        .find_task
+        .find_task(&eval_config)
+        .context("Failed to locate the task")?;
+    let TaskFormat::IOI(ioi_task) = &task else {
+        bail!("Only IOI tasks can be checked");
+    };
+
+    let mut failures = vec![];
+    let diagnostics = lint_task(task.path());
+    for diagnostic in &diagnostics {
+        print!("{}", diagnostic);
+    }
+    let lint_errors = diagnostics
+        .iter()
+        .filter(|d| d.level() == DiagnosticLevel::Error)
+        .count();
+    if lint_errors > 0 {
+        failures.push(format!("The lint found {} errors", lint_errors));
+    }
+
+    // only the official solution is evaluated, the other solutions are just compiled
+    let official =
+        ioi_task
+            .testcases
+            .values()
+            .find_map(|testcase| match &testcase.output_generator {
+                OutputGenerator::Custom(solution, _) => Some(solution.path.clone()),
+                _ => None,
+            });
+    match &official {
+        Some(official) => {
+            eval_config.solution_paths = vec![official.clone()];
+            if opt.quick {
+                eval_config.sample_testcases = Some(opt.sample);
+            }
+        }
+        None => eval_config.sample_testcases = Some(0),
+    }
+    let evaluated: HashSet<SubtaskId> = ioi_task
+        .subtasks
+        .values()
+        .filter(|subtask| match eval_config.sample_testcases {
+            Some(sample) => !subtask.sampled_testcases(sample).is_empty(),
+            None => !subtask.testcases_owned.is_empty(),
+        })
+        .map(|subtask| subtask.id)
+        .collect();
+
+    let results = evaluate(task, &eval_config, &opt.execution, &opt.storage)?;
+    let mut printer = StdoutPrinter::default();
+    for error in &results.errors {
+        cwrite!(printer, RED, "Error");
+        println!(": {}", error);
+    }
+    if !results.errors.is_empty() {
+        failures.push(format!(
+            "The evaluation emitted {} errors",
+            results.errors.len()
+        ));
+    }
+    if let Some(official) = results.solutions.iter().find(|solution| {
+        official
+            .as_ref()
+            .is_some_and(|o| o.ends_with(&solution.path))
+    }) {
+        let mut subtasks: Vec<_> = evaluated.iter().copied().collect();
+        subtasks.sort();
+        for id in subtasks {
+            let verdict = official
+                .subtasks
+                .iter()
+                .find(|subtask| subtask.id == id)
+                .and_then(|subtask| subtask.summary.verdict.as_deref())
+                .unwrap_or("not evaluated");
+            if verdict != "accepted" {
+                failures.push(format!(
+                    "{} is {} on subtask {}",
+                    official.path.display(),
+                    verdict,
+                    id
+                ));
+            }
+        }
+    }
+    if official.is_none() {
+        cwriteln!(
+            printer,
+            YELLOW,
+            "The task has no official solution, no solution has been evaluated"
+        );
+    }
+    if !results.warnings.is_empty() {
+        cwriteln!(
+            printer,
+            YELLOW,
+            "{} warnings, run task-maker on the task to see them",
+            results.warnings.len()
+        );
+    }
+
+    if !failures.is_empty() {
+        bail!("The check failed:\n    {}", failures.join("\n    "));
+    }
+    cwrite!(printer, GREEN, "Check passed");
+    println!(
+        " in {:.1}s, the official solution was evaluated on {} testcases",
+        start.elapsed().as_secs_f64(),
+        if official.is_some() {
+            evaluated.len()
+        } else {
+            0
+        }
+    );
+    Ok(())
+}
+
+/// Evaluate the task without a UI, compiling also the solutions that are not evaluated.
+fn evaluate(
+    task: TaskFormat,
+    eval_config: &EvaluationConfig,
+    execution: &ExecutionOpt,
+    storage: &StorageOpt,
+) -> Result<EvaluationResults, Error> {
+    let context = RuntimeContext::new(task, execution, |task, eval| {
+        task.build_dag(eval, eval_config)
+            .context("Cannot build the task DAG")?;
+        if let TaskFormat::IOI(task) = task {
+            let mut solutions = eval.solutions.clone();
+            let all = EvaluationConfig::default().find_solutions(
+                &task.path,
+                vec!["sol/*"],
+                Some(task.grader_map.clone()),
+                eval,
+            );
+            for solution in all {
+                let path = &solution.source_file.path;
+                if !solutions.iter().any(|s| &s.source_file.path == path) {
+                    solutions.push(solution);
+                }
+            }
+            // the solutions already evaluated are not compiled again
+            for solution in solutions {
+                solution
+                    .source_file
+                    .prepare(eval)
+                    .context("Failed to compile the solution")?;
+            }
+        }
+        Ok(())
+    })?;
+    let executor = context.connect_executor(execution, storage)?;
+    let collector =
+        executor.collect_results(&UIType::Silent, |ui, message| ui.on_message(message))?;
+    Ok(collector.results())
+}
@@ -6,10 +6,25 @@ use clap::Parser;
 pub struct ClearOpt {
     #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
     pub find_task: FindTaskOpt,
+
+    /// Only list the files that would be removed, without removing them
+    #[clap(long = "dry-run")]
+    pub dry_run: bool,
 }
 
 pub fn main_clear(opt: ClearOpt) -> Result<(), Error> {
     let task = opt.find_task.find_task(&Default::default())?;
+    if opt.dry_run {
+        for artifact in task.artifacts()? {
+            let path = artifact.strip_prefix(task.path()).unwrap_or(&artifact);
+            if artifact.is_dir() {
+                println!("{}/", path.display());
+            } else {
+                println!("{}", path.display());
+            }
+        }
+        return Ok(());
+    }
     task.clean().context("Cannot clear the task directory")?;
     Ok(())
 }
@@ -0,0 +1,348 @@
+//! Best-effort conversion of the tasks between the IOI and the Terry formats.
+//!
+//! The parts of the task that can be reused are copied, the others are scaffolded from a template.
+//! Everything that must be finished by hand is listed in a report at the end of the conversion.
+
+use std::fmt::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+
+use task_maker_format::ioi::{
+    Checker, IOITask, InputGenerator, InputValidator, OutputGenerator, TaskType,
+};
+use task_maker_format::terry::TerryTask;
+use task_maker_format::ui::{StdoutPrinter, YELLOW};
+use task_maker_format::{cwriteln, EvaluationConfig, TaskFormat};
+
+use crate::tools::{yaml_quote, TaskYamlOrig};
+use crate::FindTaskOpt;
+
+/// The generator of the Terry tasks converted from IOI.
+const TERRY_GENERATOR: &str = include_str!("templates/terry_generator.py");
+/// The checker of the Terry tasks converted from IOI.
+const TERRY_CHECKER: &str = include_str!("templates/terry_checker.py");
+/// The number of input files of the IOI tasks converted from Terry, each with a different seed.
+const IOI_INPUTS: u64 = 10;
+
+#[derive(Parser, Debug, Clone)]
+pub struct ConvertOpt {
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+
+    /// Directory where to create the converted task, it must not exist.
+    ///
+    /// IOI tasks are converted to Terry, and Terry tasks to IOI.
+    #[clap(short, long)]
+    pub output: PathBuf,
+}
+
+/// A task being converted, with the list of what must be finished by hand.
+struct Conversion {
+    /// The directory of the converted task.
+    task_dir: PathBuf,
+    /// What must be finished by hand.
+    todo: Vec<String>,
+}
+
+impl Conversion {
+    /// Copy a file of the original task in the converted one.
+    fn copy(&self, from: &Path, to: &str) -> Result<(), Error> {
+        let to = self.create_parent(to)?;
+        std::fs::copy(from, &to)
+            .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+        Ok(())
+    }
+
+    /// Write a file of the converted task. The scripts are made executable.
+    fn write(&self, to: &str, content: &str) -> Result<(), Error> {
+        let to = self.create_parent(to)?;
+        std::fs::write(&to, content)
+            .with_context(|| format!("Failed to write {}", to.display()))?;
+        if content.starts_with("#!") {
+            std::fs::set_permissions(&to, std::fs::Permissions::from_mode(0o755))
+                .with_context(|| format!("Failed to make {} executable", to.display()))?;
+        }
+        Ok(())
+    }
+
+    /// The path of a file of the converted task, creating the directory containing it.
+    fn create_parent(&self, path: &str) -> Result<PathBuf, Error> {
+        let path = self.task_dir.join(path);
+        let parent = path.parent().context("Invalid destination path")?;
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+        Ok(path)
+    }
+}
+
+pub fn main_convert(opt: ConvertOpt) -> Result<(), Error> {
+    if opt.output.exists() {
+        bail!("{} already exists", opt.output.display());
+    }
+    // the task is only parsed, nothing is written in its directory
+    let eval_config = EvaluationConfig {
+        dry_run: true,
+        ..Default::default()
+    };
+    let task = opt
+        .find_task
+        .find_task(&eval_config)
+        .context("Failed to locate the task")?;
+    let mut conversion = Conversion {
+        task_dir: opt.output.clone(),
+        todo: vec![],
+    };
+    let (name, format) = match &task {
+        TaskFormat::IOI(task) => {
+            ioi_to_terry(task, &mut conversion)?;
+            (&task.name, "Terry")
+        }
+        TaskFormat::Terry(task) => {
+            terry_to_ioi(task, &mut conversion)?;
+            (&task.name, "IOI")
+        }
+    };
+
+    println!(
+        "Converted {} to the {} format in {}",
+        name,
+        format,
+        opt.output.display()
+    );
+    if !conversion.todo.is_empty() {
+        let mut printer = StdoutPrinter::default();
+        cwriteln!(printer, YELLOW, "To be finished by hand:");
+        for todo in &conversion.todo {
+            println!("- {}", todo);
+        }
+    }
+    Ok(())
+}
+
+/// The extension of a source file, with the leading dot.
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default()
+}
+
+/// The files directly inside a directory of a task, sorted by name.
+fn files_in(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut files = vec![];
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Convert a batch IOI task to a Terry one. Since the input files of Terry contain many testcases,
+/// the generator, the solutions and the checker must be adapted.
+fn ioi_to_terry(task: &IOITask, conversion: &mut Conversion) -> Result<(), Error> {
+    let TaskType::Batch(data) = &task.task_type else {
+        bail!("Only batch tasks can be converted to Terry");
+    };
+    let max_score: f64 = task
+        .subtasks
+        .values()
+        .map(|subtask| subtask.max_score)
+        .sum();
+    let mut task_yaml = String::new();
+    writeln!(task_yaml, "name: {}", yaml_quote(&task.name))?;
+    writeln!(task_yaml, "description: {}", yaml_quote(&task.title))?;
+    writeln!(task_yaml, "max_score: {}", max_score)?;
+    conversion.write("task.yaml", &task_yaml)?;
+
+    conversion.write("managers/generator.py", TERRY_GENERATOR)?;
+    let generator = task
+        .testcases
+        .values()
+        .find_map(|testcase| match &testcase.input_generator {
+            InputGenerator::Custom(source, _) => Some(source.path.display().to_string()),
+            InputGenerator::StaticFile(_) => None,
+        })
+        .unwrap_or_else(|| "the input files".into());
+    conversion.todo.push(format!(
+        "Write managers/generator.py, printing many testcases like {}",
+        generator
+    ));
+    let validator = task
+        .subtasks
+        .values()
+        .find_map(|subtask| match &subtask.input_validator {
+            InputValidator::Custom(source, _) => Some(source.path.clone()),
+            InputValidator::AssumeValid => None,
+        });
+    if let Some(validator) = validator {
+        conversion.todo.push(format!(
+            "Port {} to managers/validator.*, reading the input from stdin",
+            validator.display()
+        ));
+    }
+
+    conversion.write("managers/checker.py", TERRY_CHECKER)?;
+    if let Checker::Custom(checker) = &data.checker {
+        conversion.todo.push(format!(
+            "Port {} to managers/checker.py, which compares the outputs ignoring whitespaces",
+            checker.path.display()
+        ));
+    }
+    let official = match &data.output_generator {
+        Some(OutputGenerator::Custom(solution, _)) => {
+            let dest = format!("managers/solution{}", extension(&solution.path));
+            conversion.copy(&solution.path, &dest)?;
+            conversion.todo.push(format!(
+                "Make {} solve all the testcases of an input file",
+                dest
+            ));
+            Some(solution.path.clone())
+        }
+        _ => {
+            conversion.todo.push(
+                "Write the official solution in managers/solution.*, the checker runs it".into(),
+            );
+            None
+        }
+    };
+    if task.grader_map.all_paths().next().is_some() {
+        conversion.todo.push(
+            "Terry does not support graders, the solutions must read the input and write the \
+             output"
+                .into(),
+        );
+    }
+
+    // the other solutions are copied, but they solve a single testcase too
+    let mut solutions = 0;
+    for solution in files_in(&task.path.join("sol"))? {
+        let name = solution
+            .file_name()
+            .context("Invalid path")?
+            .to_string_lossy();
+        let is_helper = ["grader.", "stub.", "solution.", "soluzione."]
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+            || name.ends_with(".h")
+            || name.ends_with(".hpp");
+        if is_helper || Some(&solution) == official.as_ref() {
+            continue;
+        }
+        conversion.copy(&solution, &format!("solutions/{}", name))?;
+        solutions += 1;
+    }
+    if solutions > 0 {
+        conversion.todo.push(format!(
+            "Make the {} solutions in solutions/ solve all the testcases of an input file",
+            solutions
+        ));
+    }
+
+    conversion.write(
+        "statement/statement.md",
+        &format!("# {}\n\nTODO: write the statement.\n", task.title),
+    )?;
+    let statement_dir = ["statement", "testo"]
+        .iter()
+        .map(|dir| task.path.join(dir))
+        .find(|dir| dir.is_dir())
+        .unwrap_or_else(|| task.path.join("statement"));
+    conversion.todo.push(format!(
+        "Write statement/statement.md, translating the statement in {}",
+        statement_dir.display()
+    ));
+    Ok(())
+}
+
+/// Convert a Terry task to an IOI one. Each input file is generated by the generator of Terry
+/// with a different seed, and solved by the official solution.
+fn terry_to_ioi(task: &TerryTask, conversion: &mut Conversion) -> Result<(), Error> {
+    let task_yaml = TaskYamlOrig {
+        name: &task.name,
+        title: &task.description,
+        time_limit: 1.0,
+        memory_limit: 256,
+        infile: "",
+        outfile: "",
+        score_type: None,
+    };
+    conversion.write("task.yaml.orig", &task_yaml.to_yaml()?)?;
+    conversion
+        .todo
+        .push("Choose the time and memory limits in task.yaml.orig".into());
+
+    let mut has_solution = false;
+    for (name, manager) in task.managers() {
+        let ext = extension(&manager.path);
+        match name {
+            "generator" => conversion.copy(&manager.path, &format!("gen/generator{}", ext))?,
+            "validator" => {
+                let dest = format!("gen/validator{}", ext);
+                conversion.copy(&manager.path, &dest)?;
+                conversion.todo.push(format!(
+                    "Make {} read the input file passed as first argument, instead of stdin",
+                    dest
+                ));
+            }
+            "checker" => conversion.todo.push(format!(
+                "The outputs are compared ignoring whitespaces: if {} accepts other outputs, \
+                 port it to check/checker.*",
+                manager.path.display()
+            )),
+            "solution" => {
+                conversion.copy(&manager.path, &format!("sol/solution{}", ext))?;
+                has_solution = true;
+            }
+            _ => {}
+        }
+    }
+    if !has_solution {
+        conversion.todo.push(
+            "Write the official solution in sol/solution.*, it generates the correct outputs"
+                .into(),
+        );
+    }
+
+    let mut gen = String::from(
+        "# Generated by task-maker-tools convert: each input file is generated by the generator\n\
+         # of the Terry task, with a different seed.\n",
+    );
+    writeln!(gen, "#ST: {}", task.max_score)?;
+    for seed in 1..=IOI_INPUTS {
+        writeln!(gen, "{} 0", seed)?;
+    }
+    conversion.write("gen/GEN", &gen)?;
+
+    for solution in files_in(&task.path.join("solutions"))? {
+        let name = solution
+            .file_name()
+            .context("Invalid path")?
+            .to_string_lossy();
+        // solution.* would be detected as the official solution
+        let dest = if name.starts_with("solution.") {
+            format!("sol/terry_{}", name)
+        } else {
+            format!("sol/{}", name)
+        };
+        conversion.copy(&solution, &dest)?;
+    }
+
+    for file in files_in(&task.path.join("statement"))? {
+        let name = file.file_name().context("Invalid path")?.to_string_lossy();
+        conversion.copy(&file, &format!("statement/{}", name))?;
+    }
+    conversion
+        .todo
+        .push("Write the LaTeX statement in statement/statement.tex, from statement.md".into());
+    Ok(())
+}
@@ -0,0 +1,41 @@
+#!/usr/bin/env python3
+# Generated by task-maker-tools convert: the checker of the Terry task, called with the input file
+# and the output of the contestant. The correct output is computed running the official solution,
+# which is copied in the current directory.
+#
+# TODO: this checker expects the output of each testcase on its own line, and compares the lines
+# ignoring the whitespaces like the white-diff of the IOI tasks.
+import glob
+import json
+import subprocess
+import sys
+
+input_path, output_path = sys.argv[1], sys.argv[2]
+solution = glob.glob("./solution.*")[0]
+with open(input_path) as input_file:
+    correct = subprocess.run(
+        [solution], stdin=input_file, capture_output=True, text=True, check=True
+    ).stdout.splitlines()
+with open(output_path) as output_file:
+    output = output_file.read().splitlines()
+
+validation = []
+feedback = []
+for index, expected in enumerate(correct):
+    if index < len(output):
+        validation.append({"status": "parsed", "message": None})
+        feedback.append({"correct": output[index].split() == expected.split(), "message": None})
+    else:
+        validation.append({"status": "missing", "message": None})
+        feedback.append({"correct": False, "message": None})
+
+score = sum(case["correct"] for case in feedback) / max(len(correct), 1)
+print(
+    json.dumps(
+        {
+            "score": score,
+            "validation": {"cases": validation, "alerts": []},
+            "feedback": {"cases": feedback, "alerts": []},
+        }
+    )
+)
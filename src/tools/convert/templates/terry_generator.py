@@ -0,0 +1,16 @@
+#!/usr/bin/env python3
+# Generated by task-maker-tools convert: the generator of the Terry task, printing an input file
+# with many testcases. It's called with the seed and the number of the input (always 0).
+#
+# TODO: generate the testcases like the generator of the IOI task did, one per input file.
+import random
+import sys
+
+seed = int(sys.argv[1])
+random.seed(seed)
+
+T = 10
+print(T)
+for _ in range(T):
+    # TODO: print a testcase
+    print(random.randint(1, 100))
@@ -38,9 +38,18 @@ pub fn copy_competition_files_main(
         booklet_solutions: false,
         no_statement: false,
         solution_paths: vec![],
+        extra_solution_paths: vec![],
         disabled_sanity_checks: vec![],
         seed: None,
+        terry_cases: None,
+        terry_seeds: None,
         dry_run: opt.execution.dry_run,
+        subtask_filter: vec![],
+        testcase_filter: vec![],
+        sample_testcases: None,
+        memory_metric: None,
+        verify_generators: false,
+        check_checker: false,
     };
 
     // create folder for competition files
@@ -0,0 +1,654 @@
+//! A long-running process evaluating the tasks on request, controlled with JSON-RPC over a Unix
+//! domain socket.
+//!
+//! Each line sent to the socket is a JSON-RPC 2.0 request, and each line received is either a
+//! response or a notification. The methods are:
+//!
+//! - `evaluate` with `{"task_dir": ..., "solutions": [...], "filter": [...], "no_statement": ...}`,
+//!   queues the evaluation of a task and returns `{"evaluation": id}`. Only `task_dir` is required,
+//!   `solutions` are the paths of the solutions to evaluate, anywhere in the filesystem, while
+//!   `filter` keeps only the solutions of the task whose names start with one of the filters.
+//! - `status` with `{"evaluation": id}`, returns `{"state": ..., "results": ..., "error": ...}`.
+//!   The state is one of `queued`, `running`, `done` and `failed`; the results, the same of
+//!   `task-maker-rust --results`, are present when it's `done` and the error when it's `failed`.
+//! - `events` with `{"evaluation": id}`, sends all the UI messages of the evaluation, the past ones
+//!   and the new ones, as `event` notifications with `{"evaluation": id, "message": ...}`. When
+//!   the evaluation ends, it returns like `status`. Only the last `--keep-events` messages of
+//!   each evaluation are kept, a client asking for the events late misses the older ones.
+//!
+//! The requests without an `id` are notifications: they are executed, but not answered.
+//!
+//! The evaluations are run one at a time, in the order they are submitted.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+
+use anyhow::{anyhow, bail, Context, Error};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use task_maker_format::results::EvaluationResults;
+use task_maker_format::ui::UIMessage;
+use task_maker_format::{find_task, EvaluationConfig};
+
+use crate::{evaluate_task, ExecutionOpt, StorageOpt};
+
+/// The JSON-RPC error code of the lines that are not valid JSON.
+const PARSE_ERROR: i64 = -32700;
+/// The JSON-RPC error code of the lines that are not valid requests.
+const INVALID_REQUEST: i64 = -32600;
+/// The JSON-RPC error code of the requests with an unknown method.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// The JSON-RPC error code of the requests with invalid parameters.
+const INVALID_PARAMS: i64 = -32602;
+/// The JSON-RPC error code of the requests about an unknown evaluation.
+const UNKNOWN_EVALUATION: i64 = -32000;
+
+#[derive(Parser, Debug, Clone)]
+pub struct DaemonOpt {
+    /// Path of the Unix domain socket to listen on for the requests.
+    pub socket: PathBuf,
+
+    /// Number of ended evaluations to remember, the oldest ones are forgotten
+    #[clap(long, default_value = "100")]
+    pub keep_evaluations: usize,
+
+    /// Number of UI messages to remember for each evaluation, the oldest ones are forgotten
+    #[clap(long, default_value = "100000")]
+    pub keep_events: usize,
+
+    #[clap(flatten, next_help_heading = Some("EXECUTION"))]
+    pub execution: ExecutionOpt,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+}
+
+/// The parameters of the `evaluate` method.
+#[derive(Debug, Clone, Deserialize)]
+struct EvaluateParams {
+    /// The directory of the task.
+    task_dir: PathBuf,
+    /// Evaluate only these solutions, that can reside anywhere in the filesystem.
+    #[serde(default)]
+    solutions: Vec<PathBuf>,
+    /// Evaluate only the solutions whose names start with one of these filters.
+    #[serde(default)]
+    filter: Vec<String>,
+    /// Do not build the statement files and the booklets.
+    #[serde(default)]
+    no_statement: bool,
+}
+
+/// The parameters of the methods about an evaluation.
+#[derive(Debug, Clone, Deserialize)]
+struct EvaluationParams {
+    /// The id of the evaluation.
+    evaluation: u64,
+}
+
+/// The state of an evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum EvaluationState {
+    /// The evaluation waits for the previous ones to end.
+    Queued,
+    /// The evaluation is running.
+    Running,
+    /// The evaluation has ended, and its results are available.
+    Done,
+    /// The evaluation could not be completed.
+    Failed,
+}
+
+/// An evaluation submitted to the daemon.
+struct Evaluation {
+    /// The state of the evaluation.
+    state: EvaluationState,
+    /// The last UI messages emitted.
+    events: VecDeque<UIMessage>,
+    /// The number of UI messages emitted before the ones in `events`, that are forgotten.
+    forgotten_events: usize,
+    /// The results of the evaluation, when it's done.
+    results: Option<EvaluationResults>,
+    /// Why the evaluation failed.
+    error: Option<String>,
+}
+
+impl Evaluation {
+    /// The response of `status` and `events`.
+    fn status(&self) -> Value {
+        json!({
+            "state": self.state,
+            "results": self.results,
+            "error": self.error,
+        })
+    }
+
+    /// The number of UI messages emitted so far, including the forgotten ones.
+    fn num_events(&self) -> usize {
+        self.forgotten_events + self.events.len()
+    }
+
+    /// Record a UI message, forgetting the oldest ones to keep at most `keep` of them.
+    fn push_event(&mut self, message: UIMessage, keep: usize) {
+        self.events.push_back(message);
+        while self.events.len() > keep {
+            self.events.pop_front();
+            self.forgotten_events += 1;
+        }
+    }
+
+    /// Whether the evaluation has ended.
+    fn ended(&self) -> bool {
+        matches!(self.state, EvaluationState::Done | EvaluationState::Failed)
+    }
+}
+
+/// The state of the daemon, shared by the connections and the evaluation thread.
+struct Daemon {
+    /// The known evaluations, by id.
+    evaluations: Mutex<BTreeMap<u64, Evaluation>>,
+    /// Notified every time an evaluation changes.
+    changed: Condvar,
+    /// The queue of the evaluations to run.
+    queue: Mutex<Sender<(u64, EvaluateParams)>>,
+    /// Number of ended evaluations to remember.
+    keep_evaluations: usize,
+    /// Number of UI messages to remember for each evaluation.
+    keep_events: usize,
+}
+
+impl Daemon {
+    /// Queue a new evaluation, returning its id.
+    fn submit(&self, params: EvaluateParams) -> Result<u64, Error> {
+        let mut evaluations = self.evaluations.lock().unwrap();
+        let id = evaluations.keys().next_back().map_or(1, |id| id + 1);
+        evaluations.insert(
+            id,
+            Evaluation {
+                state: EvaluationState::Queued,
+                events: VecDeque::new(),
+                forgotten_events: 0,
+                results: None,
+                error: None,
+            },
+        );
+        self.queue
+            .lock()
+            .unwrap()
+            .send((id, params))
+            .context("The evaluation thread has exited")?;
+        Ok(id)
+    }
+
+    /// Change an evaluation, waking up who is waiting for its events.
+    fn update<F: FnOnce(&mut Evaluation)>(&self, id: u64, update: F) {
+        let mut evaluations = self.evaluations.lock().unwrap();
+        if let Some(evaluation) = evaluations.get_mut(&id) {
+            update(evaluation);
+        }
+        if evaluations.get(&id).is_some_and(Evaluation::ended) {
+            // forget the oldest ended evaluations, but never the last one to keep the ids unique
+            let last = evaluations.keys().next_back().copied();
+            let ended: Vec<_> = evaluations
+                .iter()
+                .filter(|(id, evaluation)| evaluation.ended() && Some(**id) != last)
+                .map(|(id, _)| *id)
+                .collect();
+            let forget = ended.len().saturating_sub(self.keep_evaluations);
+            for id in &ended[..forget] {
+                evaluations.remove(id);
+            }
+        }
+        self.changed.notify_all();
+    }
+}
+
+pub fn main_daemon(opt: DaemonOpt) -> Result<(), Error> {
+    if opt.socket.exists() {
+        if UnixStream::connect(&opt.socket).is_ok() {
+            bail!("Another daemon is listening on {}", opt.socket.display());
+        }
+        std::fs::remove_file(&opt.socket)
+            .with_context(|| format!("Failed to remove {}", opt.socket.display()))?;
+    }
+    let listener = UnixListener::bind(&opt.socket)
+        .with_context(|| format!("Failed to listen on {}", opt.socket.display()))?;
+
+    let (queue, receiver) = channel();
+    let daemon = Arc::new(Daemon {
+        evaluations: Mutex::new(BTreeMap::new()),
+        changed: Condvar::new(),
+        queue: Mutex::new(queue),
+        keep_evaluations: opt.keep_evaluations,
+        keep_events: opt.keep_events,
+    });
+    std::thread::Builder::new()
+        .name("Evaluations".into())
+        .spawn({
+            let daemon = daemon.clone();
+            move || run_evaluations(daemon, receiver, opt.execution, opt.storage)
+        })
+        .context("Failed to spawn the evaluation thread")?;
+
+    info!("Listening on {}", opt.socket.display());
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Failed to accept a connection: {:?}", e);
+                continue;
+            }
+        };
+        let daemon = daemon.clone();
+        std::thread::Builder::new()
+            .name("Daemon connection".into())
+            .spawn(move || {
+                if let Err(e) = serve_connection(&daemon, stream) {
+                    warn!("Connection closed: {:?}", e);
+                }
+            })
+            .context("Failed to spawn the connection thread")?;
+    }
+    Ok(())
+}
+
+/// Run the queued evaluations, one at a time.
+fn run_evaluations(
+    daemon: Arc<Daemon>,
+    receiver: Receiver<(u64, EvaluateParams)>,
+    execution: ExecutionOpt,
+    storage: StorageOpt,
+) {
+    while let Ok((id, params)) = receiver.recv() {
+        daemon.update(id, |evaluation| evaluation.state = EvaluationState::Running);
+        // a panicking evaluation must not stop the daemon
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            evaluate(&daemon, id, params, &execution, &storage)
+        }))
+        .unwrap_or_else(|e| Err(anyhow!("Evaluation panicked: {:?}", e)));
+        daemon.update(id, |evaluation| match result {
+            Ok(results) => {
+                evaluation.state = EvaluationState::Done;
+                evaluation.results = Some(results);
+            }
+            Err(e) => {
+                evaluation.state = EvaluationState::Failed;
+                evaluation.error = Some(format!("{:#}", e));
+            }
+        });
+    }
+}
+
+/// Evaluate a task, recording the UI messages in the evaluation and collecting its results.
+fn evaluate(
+    daemon: &Arc<Daemon>,
+    id: u64,
+    params: EvaluateParams,
+    execution: &ExecutionOpt,
+    storage: &StorageOpt,
+) -> Result<EvaluationResults, Error> {
+    let eval_config = EvaluationConfig {
+        solution_filter: params.filter,
+        booklet_solutions: false,
+        no_statement: params.no_statement,
+        solution_paths: params.solutions,
+        extra_solution_paths: vec![],
+        disabled_sanity_checks: vec![],
+        seed: None,
+        terry_cases: None,
+        terry_seeds: None,
+        dry_run: execution.dry_run,
+        subtask_filter: vec![],
+        testcase_filter: vec![],
+        sample_testcases: None,
+        memory_metric: execution.memory_metric,
+        verify_generators: false,
+        check_checker: false,
+    };
+    let task =
+        find_task(Some(params.task_dir), 1, &eval_config).context("Invalid task directory")?;
+    let daemon = daemon.clone();
+    let keep_events = daemon.keep_events;
+    evaluate_task(task, &eval_config, execution, storage, move |message| {
+        daemon.update(id, |evaluation| evaluation.push_event(message, keep_events))
+    })
+}
+
+/// Answer the requests sent on a connection, until it's closed.
+fn serve_connection(daemon: &Daemon, stream: UnixStream) -> Result<(), Error> {
+    let reader = BufReader::new(stream.try_clone().context("Failed to clone the socket")?);
+    let mut writer = stream;
+    for line in reader.lines() {
+        let line = line.context("Failed to read the request")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let error = json!({"code": PARSE_ERROR, "message": e.to_string()});
+                send(
+                    &mut writer,
+                    json!({"jsonrpc": "2.0", "id": null, "error": error}),
+                )?;
+                continue;
+            }
+        };
+        // the notifications have no id, and they are not answered
+        let id = request.get("id").cloned();
+        let result = match (
+            request.get("jsonrpc").and_then(Value::as_str),
+            request.get("method").and_then(Value::as_str),
+        ) {
+            (Some("2.0"), Some(method)) => {
+                let params = request.get("params").cloned().unwrap_or(Value::Null);
+                handle(daemon, method, params, &mut writer)?
+            }
+            (Some("2.0"), None) => Err((INVALID_REQUEST, "Missing method".to_string())),
+            _ => Err((
+                INVALID_REQUEST,
+                "Only JSON-RPC 2.0 is supported".to_string(),
+            )),
+        };
+        let response = match result {
+            // the invalid requests are answered even without an id, as required by JSON-RPC
+            Err((INVALID_REQUEST, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id.unwrap_or(Value::Null),
+                "error": {"code": INVALID_REQUEST, "message": message},
+            }),
+            _ if id.is_none() => continue,
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err((code, message)) => {
+                json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+            }
+        };
+        send(&mut writer, response)?;
+    }
+    Ok(())
+}
+
+/// Execute a method, returning either its result or the code and the message of the error. The
+/// outer error is for the failures of the connection.
+fn handle(
+    daemon: &Daemon,
+    method: &str,
+    params: Value,
+    writer: &mut UnixStream,
+) -> Result<Result<Value, (i64, String)>, Error> {
+    let parse_error = |e: serde_json::Error| (INVALID_PARAMS, e.to_string());
+    let unknown = |id: u64| (UNKNOWN_EVALUATION, format!("Unknown evaluation {}", id));
+    match method {
+        "evaluate" => {
+            let params: EvaluateParams = match serde_json::from_value(params) {
+                Ok(params) => params,
+                Err(e) => return Ok(Err(parse_error(e))),
+            };
+            let id = daemon.submit(params)?;
+            Ok(Ok(json!({ "evaluation": id })))
+        }
+        "status" => {
+            let params: EvaluationParams = match serde_json::from_value(params) {
+                Ok(params) => params,
+                Err(e) => return Ok(Err(parse_error(e))),
+            };
+            let evaluations = daemon.evaluations.lock().unwrap();
+            Ok(evaluations
+                .get(&params.evaluation)
+                .map(Evaluation::status)
+                .ok_or_else(|| unknown(params.evaluation)))
+        }
+        "events" => {
+            let params: EvaluationParams = match serde_json::from_value(params) {
+                Ok(params) => params,
+                Err(e) => return Ok(Err(parse_error(e))),
+            };
+            let mut sent = 0;
+            loop {
+                // the events are sent without holding the lock, not to block the evaluation
+                let (events, status) = {
+                    let mut evaluations = daemon.evaluations.lock().unwrap();
+                    let evaluation = loop {
+                        let Some(evaluation) = evaluations.get(&params.evaluation) else {
+                            return Ok(Err(unknown(params.evaluation)));
+                        };
+                        if evaluation.num_events() > sent || evaluation.ended() {
+                            break evaluation;
+                        }
+                        evaluations = daemon.changed.wait(evaluations).unwrap();
+                    };
+                    // the events forgotten in the meantime are skipped
+                    sent = sent.max(evaluation.forgotten_events);
+                    let skip = sent - evaluation.forgotten_events;
+                    let events: Vec<_> = evaluation.events.iter().skip(skip).cloned().collect();
+                    let status = evaluation.ended().then(|| evaluation.status());
+                    (events, status)
+                };
+                sent += events.len();
+                for message in events {
+                    let params = json!({"evaluation": params.evaluation, "message": message});
+                    send(
+                        writer,
+                        json!({"jsonrpc": "2.0", "method": "event", "params": params}),
+                    )?;
+                }
+                if let Some(status) = status {
+                    return Ok(Ok(status));
+                }
+            }
+        }
+        _ => Ok(Err((
+            METHOD_NOT_FOUND,
+            format!("Unknown method: {}", method),
+        ))),
+    }
+}
+
+/// Send a line of JSON on the connection.
+fn send(writer: &mut UnixStream, message: Value) -> Result<(), Error> {
+    let mut line = serde_json::to_vec(&message).context("Failed to serialize the message")?;
+    line.push(b'\n');
+    writer
+        .write_all(&line)
+        .context("Failed to send the message")
+}
+
+#[cfg(test)]
+mod tests {
+    use task_maker_format::ui::UIExecutionStatus;
+
+    use super::*;
+
+    /// A connection to a daemon whose evaluations are never run.
+    struct Client {
+        daemon: Arc<Daemon>,
+        reader: BufReader<UnixStream>,
+        writer: UnixStream,
+        _queue: Receiver<(u64, EvaluateParams)>,
+    }
+
+    impl Client {
+        fn new(keep_events: usize) -> Client {
+            let (queue, receiver) = channel();
+            let daemon = Arc::new(Daemon {
+                evaluations: Mutex::new(BTreeMap::new()),
+                changed: Condvar::new(),
+                queue: Mutex::new(queue),
+                keep_evaluations: 10,
+                keep_events,
+            });
+            let (client, server) = UnixStream::pair().unwrap();
+            let server_daemon = daemon.clone();
+            std::thread::spawn(move || serve_connection(&server_daemon, server).unwrap());
+            Client {
+                daemon,
+                reader: BufReader::new(client.try_clone().unwrap()),
+                writer: client,
+                _queue: receiver,
+            }
+        }
+
+        /// Send a line to the daemon.
+        fn send(&mut self, line: &str) {
+            writeln!(self.writer, "{}", line).unwrap();
+        }
+
+        /// Receive a line from the daemon.
+        fn recv(&mut self) -> Value {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            serde_json::from_str(&line).unwrap()
+        }
+
+        /// Send a request and receive its response.
+        fn request(&mut self, id: u64, method: &str, params: Value) -> Value {
+            let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+            self.send(&request.to_string());
+            let response = self.recv();
+            assert_eq!(response["jsonrpc"], "2.0");
+            assert_eq!(response["id"], id);
+            response
+        }
+
+        /// Record a UI message of an evaluation, as the evaluation thread would.
+        fn event(&self, id: u64, file: &str) {
+            let keep_events = self.daemon.keep_events;
+            let message = UIMessage::Compilation {
+                file: file.into(),
+                status: UIExecutionStatus::Pending,
+            };
+            self.daemon
+                .update(id, |evaluation| evaluation.push_event(message, keep_events));
+        }
+    }
+
+    #[test]
+    fn test_evaluate_and_status() {
+        let mut client = Client::new(10);
+        let response = client.request(1, "evaluate", json!({"task_dir": "/tasks/poldo"}));
+        assert_eq!(response["result"], json!({"evaluation": 1}));
+        let response = client.request(2, "evaluate", json!({"task_dir": "/tasks/poldo"}));
+        assert_eq!(response["result"], json!({"evaluation": 2}));
+        let response = client.request(3, "status", json!({"evaluation": 1}));
+        assert_eq!(
+            response["result"],
+            json!({"state": "queued", "results": null, "error": null})
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut client = Client::new(10);
+        let response = client.request(1, "status", json!({"evaluation": 42}));
+        assert_eq!(response["error"]["code"], UNKNOWN_EVALUATION);
+        let response = client.request(2, "evaluate", json!({"solutions": []}));
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+        let response = client.request(3, "cancel", json!({"evaluation": 1}));
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        client.send("{not json");
+        let response = client.recv();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+    }
+
+    #[test]
+    fn test_invalid_request() {
+        let mut client = Client::new(10);
+        for request in [
+            json!({"jsonrpc": "1.0", "id": 1, "method": "status"}),
+            json!({"id": 1, "method": "status"}),
+            json!({"jsonrpc": "2.0", "id": 1}),
+        ] {
+            client.send(&request.to_string());
+            let response = client.recv();
+            assert_eq!(response["error"]["code"], INVALID_REQUEST);
+            assert_eq!(response["id"], 1);
+        }
+        // the id of an invalid request may be missing, it's answered anyway
+        client.send("[1, 2, 3]");
+        let response = client.recv();
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+        assert_eq!(response["id"], Value::Null);
+    }
+
+    #[test]
+    fn test_notifications_not_answered() {
+        let mut client = Client::new(10);
+        let notification =
+            json!({"jsonrpc": "2.0", "method": "evaluate", "params": {"task_dir": "/tasks/poldo"}});
+        client.send(&notification.to_string());
+        client.send(&json!({"jsonrpc": "2.0", "method": "status"}).to_string());
+        // the first line received is the response to the next request
+        let response = client.request(1, "status", json!({"evaluation": 1}));
+        assert_eq!(response["result"]["state"], "queued");
+    }
+
+    #[test]
+    fn test_events() {
+        let mut client = Client::new(10);
+        client.request(1, "evaluate", json!({"task_dir": "/tasks/poldo"}));
+        client.event(1, "first.cpp");
+        client.event(1, "second.cpp");
+        client.daemon.update(1, |evaluation| {
+            evaluation.state = EvaluationState::Failed;
+            evaluation.error = Some("Broken".into());
+        });
+        client.send(
+            &json!({"jsonrpc": "2.0", "id": 2, "method": "events", "params": {"evaluation": 1}})
+                .to_string(),
+        );
+        for file in ["first.cpp", "second.cpp"] {
+            let notification = client.recv();
+            assert_eq!(notification["method"], "event");
+            assert_eq!(notification["params"]["evaluation"], 1);
+            assert_eq!(
+                notification["params"]["message"]["Compilation"]["file"],
+                file
+            );
+        }
+        let response = client.recv();
+        assert_eq!(response["id"], 2);
+        assert_eq!(response["result"]["state"], "failed");
+        assert_eq!(response["result"]["error"], "Broken");
+    }
+
+    #[test]
+    fn test_events_forgotten() {
+        let mut client = Client::new(2);
+        client.request(1, "evaluate", json!({"task_dir": "/tasks/poldo"}));
+        for i in 0..5 {
+            client.event(1, &format!("sol{}.cpp", i));
+        }
+        {
+            let evaluations = client.daemon.evaluations.lock().unwrap();
+            let evaluation = &evaluations[&1];
+            assert_eq!(evaluation.events.len(), 2);
+            assert_eq!(evaluation.num_events(), 5);
+        }
+        client
+            .daemon
+            .update(1, |evaluation| evaluation.state = EvaluationState::Done);
+        client.send(
+            &json!({"jsonrpc": "2.0", "id": 2, "method": "events", "params": {"evaluation": 1}})
+                .to_string(),
+        );
+        // only the newest events are sent
+        for file in ["sol3.cpp", "sol4.cpp"] {
+            let notification = client.recv();
+            assert_eq!(
+                notification["params"]["message"]["Compilation"]["file"],
+                file
+            );
+        }
+        assert_eq!(client.recv()["result"]["state"], "done");
+    }
+}
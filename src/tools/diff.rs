@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Error};
+use clap::Parser;
+
+use task_maker_format::results::{diff_results, DiffThresholds, DifferenceKind, EvaluationResults};
+use task_maker_format::ui::{StdoutPrinter, BOLD, GREEN, RED, YELLOW};
+use task_maker_format::{cwrite, cwriteln};
+
+use crate::error::ExitStatus;
+
+#[derive(Parser, Debug, Clone)]
+pub struct DiffOpt {
+    /// The results of the baseline run, written by task-maker with --results.
+    pub baseline: PathBuf,
+
+    /// The results of the new run, written by task-maker with --results.
+    pub results: PathBuf,
+
+    /// Minimum change of the CPU time to report, in percent of the baseline time.
+    #[clap(long, default_value = "20")]
+    pub time_threshold: f64,
+
+    /// Minimum change of the CPU time to report, in seconds.
+    #[clap(long, default_value = "0.05")]
+    pub min_time_change: f64,
+
+    /// Minimum change of the memory to report, in percent of the baseline memory.
+    #[clap(long, default_value = "20")]
+    pub memory_threshold: f64,
+
+    /// Exit with a non-zero status if a solution got worse.
+    #[clap(long)]
+    pub fail_on_regression: bool,
+}
+
+/// Read a results file.
+fn load_results(path: &Path) -> Result<EvaluationResults, Error> {
+    let content =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_slice(&content)
+        .with_context(|| format!("{} is not a valid results file", path.display()))
+}
+
+pub fn main_diff(opt: DiffOpt) -> Result<(), Error> {
+    let baseline = load_results(&opt.baseline)?;
+    let results = load_results(&opt.results)?;
+    if baseline.task != results.task {
+        warn!(
+            "Comparing the results of different tasks: {} and {}",
+            baseline.task, results.task
+        );
+    }
+    let thresholds = DiffThresholds {
+        time: opt.time_threshold / 100.0,
+        min_time: opt.min_time_change,
+        memory: opt.memory_threshold / 100.0,
+    };
+    let differences = diff_results(&baseline, &results, &thresholds);
+    if differences.is_empty() {
+        println!("No differences");
+        return Ok(());
+    }
+
+    let mut printer = StdoutPrinter::default();
+    let mut last_solution = None;
+    for difference in &differences {
+        if last_solution != Some(&difference.solution) {
+            cwriteln!(printer, BOLD, "{}", difference.solution.display());
+            last_solution = Some(&difference.solution);
+        }
+        print!("    ");
+        if let Some(subtask) = difference.subtask {
+            print!("subtask {}: ", subtask);
+        }
+        let color = match difference.kind {
+            _ if difference.regression => &*RED,
+            DifferenceKind::Added | DifferenceKind::Removed => &*YELLOW,
+            _ => &*GREEN,
+        };
+        cwriteln!(printer, color, "{}", difference.kind);
+    }
+    let regressions = differences.iter().filter(|d| d.regression).count();
+    println!();
+    cwrite!(printer, BOLD, "Total: ");
+    println!(
+        "{} differences, {} regressions",
+        differences.len(),
+        regressions
+    );
+    if opt.fail_on_regression && regressions > 0 {
+        return Err(
+            anyhow!("{} regressions found", regressions).context(ExitStatus::UnexpectedScores)
+        );
+    }
+    Ok(())
+}
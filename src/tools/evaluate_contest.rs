@@ -0,0 +1,237 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{anyhow, bail, Context, Error};
+use clap::Parser;
+
+use task_maker_format::ioi::{BookletConfig, IOITask};
+use task_maker_format::results::EvaluationResults;
+use task_maker_format::terry::TerryTask;
+use task_maker_format::ui::{StdoutPrinter, UIType, BOLD, GREEN, RED, YELLOW};
+use task_maker_format::{cwrite, cwriteln, find_task, EvaluationConfig, TaskFormat};
+
+use crate::context::{ExecutorConnection, RuntimeContext};
+use crate::{ExecutionOpt, StorageOpt, ToolsSandboxRunner};
+
+#[derive(Parser, Debug, Clone)]
+pub struct EvaluateContestOpt {
+    /// Directory of the contest.
+    ///
+    /// The tasks are the ones listed in its contest.yaml or, if it's missing, all the
+    /// subdirectories containing a task.
+    #[clap(short = 'c', long = "contest-dir", default_value = ".")]
+    pub contest_dir: PathBuf,
+
+    /// Execute only the solutions whose names start with the filter, in all the tasks
+    pub filter: Vec<String>,
+
+    /// Do not build the statement files and the booklets
+    #[clap(long = "no-statement")]
+    pub no_statement: bool,
+
+    #[clap(flatten, next_help_heading = Some("EXECUTION"))]
+    pub execution: ExecutionOpt,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+}
+
+/// The outcome of the evaluation of a task of the contest.
+struct TaskOutcome {
+    /// The name of the directory of the task.
+    name: String,
+    /// The results of the evaluation, or the reason why the task was not evaluated.
+    results: Result<EvaluationResults, Error>,
+}
+
+pub fn main_evaluate_contest(opt: EvaluateContestOpt) -> Result<(), Error> {
+    if opt.execution.resume.is_some() {
+        bail!("--resume is not supported when evaluating a contest");
+    }
+    let eval_config = EvaluationConfig {
+        solution_filter: opt.filter.clone(),
+        booklet_solutions: false,
+        no_statement: opt.no_statement,
+        solution_paths: vec![],
+        extra_solution_paths: vec![],
+        disabled_sanity_checks: vec![],
+        seed: None,
+        terry_cases: None,
+        terry_seeds: None,
+        dry_run: opt.execution.dry_run,
+        subtask_filter: vec![],
+        testcase_filter: vec![],
+        sample_testcases: None,
+        memory_metric: opt.execution.memory_metric,
+        verify_generators: false,
+        check_checker: false,
+    };
+
+    // parse all the tasks, keeping the ones that cannot be evaluated for the summary
+    let mut outcomes = vec![];
+    let mut tasks = vec![];
+    for task_dir in contest_task_dirs(&opt.contest_dir)? {
+        let name = task_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| task_dir.display().to_string());
+        match find_task(Some(task_dir), 1, &eval_config).context("Invalid task directory") {
+            Ok(task) => tasks.push((name, task)),
+            Err(e) => outcomes.push(TaskOutcome {
+                name,
+                results: Err(e),
+            }),
+        }
+    }
+    if tasks.is_empty() && outcomes.is_empty() {
+        bail!("No task found in {}", opt.contest_dir.display());
+    }
+
+    // run all the evaluations at the same time, sharing the executor
+    let start = Instant::now();
+    let mut connections = ExecutorConnection::connect(
+        tasks.len(),
+        &opt.execution,
+        &opt.storage,
+        ToolsSandboxRunner::default(),
+    )?;
+    // the executor is waited after all the evaluations, even the ones that fail to start
+    let local_executor = connections
+        .last_mut()
+        .and_then(|connection| connection.local_executor.take());
+    let mut evaluations = vec![];
+    for ((name, task), connection) in tasks.into_iter().zip(connections) {
+        let execution = opt.execution.clone();
+        let eval_config = eval_config.clone();
+        let thread = std::thread::Builder::new()
+            .name(format!("Evaluation of {}", name))
+            .spawn({
+                let name = name.clone();
+                move || {
+                    let results = evaluate_task(task, connection, &execution, &eval_config);
+                    match &results {
+                        Ok(_) => println!(
+                            "{} evaluated in {:.1}s",
+                            name,
+                            start.elapsed().as_secs_f64()
+                        ),
+                        Err(_) => println!("{} failed", name),
+                    }
+                    results
+                }
+            })
+            .context("Failed to spawn the evaluation thread")?;
+        evaluations.push((name, thread));
+    }
+    for (name, thread) in evaluations {
+        let results = thread
+            .join()
+            .map_err(|e| anyhow!("Evaluation panicked: {:?}", e))
+            .and_then(|results| results);
+        outcomes.push(TaskOutcome { name, results });
+    }
+    if let Some(local_executor) = local_executor {
+        local_executor
+            .join()
+            .map_err(|e| anyhow!("Executor panicked: {:?}", e))?
+            .context("Local executor failed")?;
+    }
+    outcomes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    print_summary(&outcomes);
+    let failed = outcomes.iter().filter(|o| o.results.is_err()).count();
+    if failed > 0 {
+        bail!("{} tasks have not been evaluated", failed);
+    }
+    Ok(())
+}
+
+/// Evaluate a task using a connection to the shared executor, collecting its results.
+fn evaluate_task(
+    task: TaskFormat,
+    connection: ExecutorConnection,
+    execution: &ExecutionOpt,
+    eval_config: &EvaluationConfig,
+) -> Result<EvaluationResults, Error> {
+    let context = RuntimeContext::new(task, execution, |task, eval| {
+        task.build_dag(eval, eval_config)
+            .context("Cannot build the task DAG")
+    })?;
+    let executor = context.with_connection(connection);
+    let collector =
+        executor.collect_results(&UIType::Silent, |ui, message| ui.on_message(message))?;
+    Ok(collector.results())
+}
+
+/// The directories of the tasks of the contest: the ones listed in contest.yaml, if present,
+/// otherwise the subdirectories containing a task.
+fn contest_task_dirs(contest_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    if let Some(contest_yaml) = BookletConfig::contest_yaml(contest_dir) {
+        let contest_yaml = contest_yaml?;
+        return Ok(contest_yaml
+            .tasks
+            .iter()
+            .map(|task| contest_dir.join(task))
+            .collect());
+    }
+    let mut task_dirs = vec![];
+    let entries = std::fs::read_dir(contest_dir)
+        .with_context(|| format!("Failed to list {}", contest_dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() && (IOITask::is_valid(&path) || TerryTask::is_valid(&path)) {
+            task_dirs.push(path);
+        }
+    }
+    task_dirs.sort();
+    Ok(task_dirs)
+}
+
+/// Print the scores of the solutions and the errors of each task.
+fn print_summary(outcomes: &[TaskOutcome]) {
+    let mut printer = StdoutPrinter::default();
+    println!();
+    for outcome in outcomes {
+        cwrite!(printer, BOLD, "{}", outcome.name);
+        let results = match &outcome.results {
+            Ok(results) => results,
+            Err(e) => {
+                print!(": ");
+                cwriteln!(printer, RED, "not evaluated");
+                println!("    {:?}", e);
+                continue;
+            }
+        };
+        println!(" (max score {})", results.max_score);
+        let name_width = results
+            .solutions
+            .iter()
+            .map(|s| s.path.to_string_lossy().len())
+            .max()
+            .unwrap_or(0);
+        for solution in &results.solutions {
+            print!(
+                "    {:<width$}  ",
+                solution.path.display(),
+                width = name_width
+            );
+            match solution.score {
+                _ if !solution.compiled => cwriteln!(printer, RED, "compilation failed"),
+                Some(score) if (score - results.max_score).abs() < 1e-6 => {
+                    cwriteln!(printer, GREEN, "{}", score)
+                }
+                Some(score) if score == 0.0 => cwriteln!(printer, RED, "{}", score),
+                Some(score) => cwriteln!(printer, YELLOW, "{}", score),
+                None => println!("not evaluated"),
+            }
+        }
+        for error in &results.errors {
+            cwrite!(printer, RED, "    Error");
+            println!(": {}", error);
+        }
+        if !results.warnings.is_empty() {
+            cwrite!(printer, YELLOW, "    {} warnings", results.warnings.len());
+            println!(", run task-maker on the task to see them");
+        }
+    }
+}
@@ -0,0 +1,526 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Error};
+use clap::Parser;
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use task_maker_format::ioi::italian_yaml::cms_task_yaml;
+use task_maker_format::ioi::{
+    Checker, IOITask, InputGenerator, OutputGenerator, TaskType, TestcaseId, TestcaseInfo,
+};
+use task_maker_format::terry::TerryTask;
+use task_maker_format::{EvaluationConfig, TaskFormat};
+
+use crate::context::RuntimeContext;
+use crate::tools::yaml_quote;
+use crate::{ExecutionOpt, FindTaskOpt, LoggerOpt, StorageOpt, UIOpt};
+
+#[derive(Parser, Debug, Clone)]
+pub struct ExportOpt {
+    /// The format of the exported task: `cms` or `icpc` (the problem package of Kattis and
+    /// DOMjudge) for the IOI tasks, `terry` for the Terry tasks.
+    #[clap(long, default_value = "cms")]
+    pub format: ExportFormat,
+
+    /// Where to write the exported task: a directory, or a zip file with --zip. It must not exist.
+    #[clap(short, long)]
+    pub output: PathBuf,
+
+    /// Write a zip file instead of a directory.
+    #[clap(long)]
+    pub zip: bool,
+
+    /// Don't run the evaluation for building the files, use the ones already in the task directory.
+    #[clap(long)]
+    pub no_build: bool,
+
+    /// Do not include the statements in the exported task.
+    #[clap(long = "no-statement")]
+    pub no_statement: bool,
+
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+
+    #[clap(flatten, next_help_heading = Some("UI"))]
+    pub ui: UIOpt,
+
+    #[clap(flatten, next_help_heading = Some("EXECUTION"))]
+    pub execution: ExecutionOpt,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+}
+
+/// A format the tasks can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// The layout imported by `cmsImportTask`, with the task.yaml of the italian loader.
+    Cms,
+    /// The problem package format of the ICPC judges, like Kattis and DOMjudge.
+    Icpc,
+    /// The task directory read by the Terry server, with the compiled managers.
+    Terry,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ExportFormat, Self::Err> {
+        match s {
+            "cms" => Ok(ExportFormat::Cms),
+            "icpc" => Ok(ExportFormat::Icpc),
+            "terry" => Ok(ExportFormat::Terry),
+            _ => Err(format!(
+                "Unknown export format: {} (expected cms, icpc or terry)",
+                s
+            )),
+        }
+    }
+}
+
+/// The content of a file of the exported task.
+pub(crate) enum ExportedFile {
+    /// A copy of a file of the task.
+    Copy(PathBuf),
+    /// A file generated during the export.
+    Content(String),
+    /// An executable script generated during the export.
+    Script(String),
+}
+
+/// The platform of the machines running the Terry server, the suffix of the managers.
+const TERRY_PLATFORM: &str = "linux.x86_64";
+
+/// The output validator of the ICPC format, running the checker of the task. A testcase is accepted
+/// only if the checker gives it the full score.
+const ICPC_OUTPUT_VALIDATOR: &str = r#"#!/bin/sh
+# Generated by task-maker-tools export: runs the checker of task-maker as an output validator.
+# Usage: run input correct_output feedback_dir < team_output
+dir=$(dirname "$0")
+cat > "$3/team_output"
+score=$("$dir/checker" "$1" "$2" "$3/team_output" 2> "$3/judgemessage.txt") || exit 1
+if awk -v score="$score" 'BEGIN { exit !(score >= 1) }'; then
+    exit 42
+fi
+exit 43
+"#;
+
+pub fn main_export(mut opt: ExportOpt, logger_opt: LoggerOpt) -> Result<(), Error> {
+    opt.ui.disable_if_needed(&logger_opt);
+    if opt.execution.dry_run {
+        bail!("The task cannot be exported in a dry run");
+    }
+    if opt.output.exists() {
+        bail!("{} already exists", opt.output.display());
+    }
+    let eval_config = EvaluationConfig {
+        solution_filter: vec!["do not evaluate the solutions!!".into()],
+        no_statement: opt.no_statement,
+        ..Default::default()
+    };
+    let task = opt
+        .find_task
+        .find_task(&eval_config)
+        .context("Failed to locate the task")?;
+    let name = match (&task, opt.format) {
+        (TaskFormat::IOI(task), ExportFormat::Cms | ExportFormat::Icpc) => task.name.clone(),
+        (TaskFormat::Terry(task), ExportFormat::Terry) => task.name.clone(),
+        (TaskFormat::IOI(_), ExportFormat::Terry) => {
+            bail!("Only Terry tasks can be exported in the terry format")
+        }
+        (TaskFormat::Terry(_), _) => bail!("Terry tasks can only be exported with --format terry"),
+    };
+
+    if !opt.no_build {
+        build_task(task.clone(), &opt, &eval_config)?;
+    }
+
+    let files = match (&task, opt.format) {
+        (TaskFormat::IOI(task), ExportFormat::Cms) => cms_files(task, opt.no_statement)?,
+        (TaskFormat::IOI(task), ExportFormat::Icpc) => icpc_files(task, opt.no_statement)?,
+        (TaskFormat::Terry(task), _) => terry_files(task, opt.no_statement)?,
+        _ => unreachable!("The format is checked above"),
+    };
+    for (dest, file) in &files {
+        if let ExportedFile::Copy(path) = file {
+            if !path.exists() {
+                bail!(
+                    "{} is missing, cannot export {} (run task-maker first)",
+                    path.display(),
+                    dest.display()
+                );
+            }
+        }
+    }
+    if opt.zip {
+        write_zip(&files, &opt.output)?;
+    } else {
+        write_directory(&files, &opt.output)?;
+    }
+    println!(
+        "Exported {} files of {} to {}",
+        files.len(),
+        name,
+        opt.output.display()
+    );
+    Ok(())
+}
+
+/// Run task-maker on the task, without evaluating the solutions, to build the files to export.
+fn build_task(
+    task: TaskFormat,
+    opt: &ExportOpt,
+    eval_config: &EvaluationConfig,
+) -> Result<(), Error> {
+    let context = RuntimeContext::new(task, &opt.execution, |task, eval| {
+        task.build_dag(eval, eval_config)
+            .context("Cannot build the task DAG")?;
+        // the managers of Terry are compiled only when used, but all of them are exported
+        if let TaskFormat::Terry(task) = task {
+            for (_, manager) in task.managers() {
+                manager.prepare(eval)?;
+            }
+        }
+        Ok(())
+    })?;
+    let executor = context.connect_executor(&opt.execution, &opt.storage)?;
+    let results = executor
+        .collect_results(&opt.ui.ui, |ui, message| ui.on_message(message))?
+        .results();
+    if !results.errors.is_empty() {
+        bail!(
+            "The task cannot be exported, the evaluation emitted errors:\n{}",
+            results.errors.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// The files of the cms package of the task, indexed by their path inside the package.
+fn cms_files(task: &IOITask, no_statement: bool) -> Result<BTreeMap<PathBuf, ExportedFile>, Error> {
+    let mut files = BTreeMap::new();
+    files.insert(
+        PathBuf::from("task.yaml"),
+        ExportedFile::Content(cms_task_yaml(task)?),
+    );
+    // the GEN is used by cms to detect the subtasks
+    let gen = task.path.join("gen/GEN");
+    if gen.exists() {
+        files.insert(PathBuf::from("gen/GEN"), ExportedFile::Copy(gen));
+    }
+
+    for (id, testcase) in &task.testcases {
+        let (input, output) = testcase_files(task, *id, testcase);
+        files.insert(
+            PathBuf::from(format!("input/input{}.txt", id)),
+            ExportedFile::Copy(input),
+        );
+        if let Some(output) = output {
+            files.insert(
+                PathBuf::from(format!("output/output{}.txt", id)),
+                ExportedFile::Copy(output),
+            );
+        }
+    }
+
+    // the compiled checker or manager, the graders and the headers for the solutions
+    let program = match &task.task_type {
+        TaskType::Batch(data) => match &data.checker {
+            Checker::Custom(checker) => checker.write_bin_to(),
+            Checker::WhiteDiff(_) => None,
+        },
+        TaskType::Communication(data) => data.manager.write_bin_to(),
+        TaskType::None => bail!("The task has no task type, it cannot be exported"),
+    };
+    let graders = task.grader_map.all_paths().map(Path::to_path_buf);
+    let headers = task_files(&task.path, "sol", 1, |path| {
+        let name = path.to_string_lossy();
+        name.ends_with(".h") || name.ends_with(".hpp") || name.ends_with("lib.pas")
+    });
+    for path in program.into_iter().chain(graders).chain(headers) {
+        files.insert(task.path_of(&path).to_path_buf(), ExportedFile::Copy(path));
+    }
+
+    if !no_statement {
+        for dir in ["statement", "testo"] {
+            for path in task_files(&task.path, dir, 1, |path| {
+                path.extension().is_some_and(|ext| ext == "pdf")
+            }) {
+                files.insert(task.path_of(&path).to_path_buf(), ExportedFile::Copy(path));
+            }
+        }
+    }
+    for path in task_files(&task.path, "att", usize::MAX, |_| true) {
+        files.insert(task.path_of(&path).to_path_buf(), ExportedFile::Copy(path));
+    }
+    Ok(files)
+}
+
+/// The files of the ICPC problem package of the task, indexed by their path inside the package.
+///
+/// The testcases of the subtasks worth no points are the samples, and the checker is wrapped in an
+/// output validator. The scores of the subtasks are lost, since a solution is either accepted or
+/// not.
+fn icpc_files(
+    task: &IOITask,
+    no_statement: bool,
+) -> Result<BTreeMap<PathBuf, ExportedFile>, Error> {
+    let TaskType::Batch(data) = &task.task_type else {
+        bail!("Only batch tasks can be exported in the ICPC format");
+    };
+    if task.infile.is_some() || task.outfile.is_some() {
+        bail!("The solutions must use stdin and stdout in the ICPC format");
+    }
+    if task.grader_map.all_paths().next().is_some() {
+        bail!("Tasks with graders cannot be exported in the ICPC format");
+    }
+    let mut files = BTreeMap::new();
+
+    let mut problem_yaml = format!("name: {}\n", yaml_quote(&task.title));
+    if let Checker::Custom(_) = &data.checker {
+        problem_yaml += "validation: custom\n";
+    }
+    if let Some(memory_limit) = task.memory_limit {
+        problem_yaml += &format!("limits:\n  memory: {}\n", memory_limit);
+    }
+    files.insert(
+        PathBuf::from("problem.yaml"),
+        ExportedFile::Content(problem_yaml),
+    );
+    // the time limit is not part of problem.yaml, DOMjudge reads it from this file
+    if let Some(time_limit) = task.time_limit {
+        files.insert(
+            PathBuf::from(".timelimit"),
+            ExportedFile::Content(format!("{}\n", time_limit)),
+        );
+    }
+
+    let samples: HashSet<TestcaseId> = task
+        .subtasks
+        .values()
+        .filter(|subtask| subtask.max_score == 0.0)
+        .flat_map(|subtask| subtask.testcases_owned.iter().copied())
+        .collect();
+    for (id, testcase) in &task.testcases {
+        let (input, output) = testcase_files(task, *id, testcase);
+        let output = output.with_context(|| format!("Testcase {} has no correct output", id))?;
+        let dir = if samples.contains(id) {
+            "sample"
+        } else {
+            "secret"
+        };
+        files.insert(
+            PathBuf::from(format!("data/{}/{:03}.in", dir, id)),
+            ExportedFile::Copy(input),
+        );
+        files.insert(
+            PathBuf::from(format!("data/{}/{:03}.ans", dir, id)),
+            ExportedFile::Copy(output),
+        );
+    }
+
+    if let Checker::Custom(checker) = &data.checker {
+        let checker = checker
+            .write_bin_to()
+            .context("The checker is not compiled in the task directory")?;
+        files.insert(
+            PathBuf::from("output_validators/checker/checker"),
+            ExportedFile::Copy(checker),
+        );
+        files.insert(
+            PathBuf::from("output_validators/checker/run"),
+            ExportedFile::Script(ICPC_OUTPUT_VALIDATOR.into()),
+        );
+    }
+    if let Some(OutputGenerator::Custom(solution, _)) = &data.output_generator {
+        let name = solution
+            .path
+            .file_name()
+            .context("Invalid path of the official solution")?;
+        files.insert(
+            Path::new("submissions/accepted").join(name),
+            ExportedFile::Copy(solution.path.clone()),
+        );
+    }
+
+    if !no_statement {
+        for dir in ["statement", "testo"] {
+            for path in task_files(&task.path, dir, 1, |path| {
+                path.extension().is_some_and(|ext| ext == "pdf")
+            }) {
+                let name = path.file_name().context("Invalid statement path")?;
+                files.insert(
+                    Path::new("problem_statement").join(name),
+                    ExportedFile::Copy(path.clone()),
+                );
+            }
+        }
+    }
+    for path in task_files(&task.path, "att", usize::MAX, |_| true) {
+        let relative = path.strip_prefix(task.path.join("att"))?;
+        files.insert(
+            Path::new("attachments").join(relative),
+            ExportedFile::Copy(path.clone()),
+        );
+    }
+    Ok(files)
+}
+
+/// The files of the Terry task, indexed by their path inside the directory read by the server.
+///
+/// The managers must be compiled for the platform of the server, and be newer than their sources.
+fn terry_files(
+    task: &TerryTask,
+    no_statement: bool,
+) -> Result<BTreeMap<PathBuf, ExportedFile>, Error> {
+    let platform = format!("{}.{}", std::env::consts::OS, std::env::consts::ARCH);
+    if platform != TERRY_PLATFORM {
+        bail!(
+            "The managers are compiled for {}, but the Terry server needs them for {}",
+            platform,
+            TERRY_PLATFORM
+        );
+    }
+    let mut files = BTreeMap::new();
+    files.insert(
+        PathBuf::from("task.yaml"),
+        ExportedFile::Copy(task.path.join("task.yaml")),
+    );
+    for (name, manager) in task.managers() {
+        let binary = manager
+            .write_bin_to()
+            .with_context(|| format!("The {} is not compiled in the task directory", name))?;
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|meta| meta.modified());
+        if let (Ok(binary_time), Ok(source_time)) = (modified(&binary), modified(&manager.path)) {
+            if binary_time < source_time {
+                bail!(
+                    "{} is older than {} (run task-maker again)",
+                    binary.display(),
+                    manager.path.display()
+                );
+            }
+        }
+        files.insert(
+            PathBuf::from(format!("managers/{}.{}", name, TERRY_PLATFORM)),
+            ExportedFile::Copy(binary),
+        );
+    }
+    if !no_statement {
+        let statement = task.path.join("statement/statement.md");
+        if !statement.exists() {
+            bail!("statement/statement.md is missing, the Terry server needs it");
+        }
+        // the statement may include some images
+        for path in task_files(&task.path, "statement", usize::MAX, |_| true) {
+            let dest = path.strip_prefix(&task.path)?.to_path_buf();
+            files.insert(dest, ExportedFile::Copy(path));
+        }
+    }
+    Ok(files)
+}
+
+/// The input file of a testcase and, if available, its correct output file.
+pub(crate) fn testcase_files(
+    task: &IOITask,
+    id: TestcaseId,
+    testcase: &TestcaseInfo,
+) -> (PathBuf, Option<PathBuf>) {
+    let input = match &testcase.input_generator {
+        InputGenerator::StaticFile(path) => path.clone(),
+        InputGenerator::Custom(_, _) => task.path.join(format!("input/input{}.txt", id)),
+    };
+    let output = match &testcase.output_generator {
+        OutputGenerator::StaticFile(path) => Some(path.clone()),
+        OutputGenerator::Custom(_, _) => Some(task.path.join(format!("output/output{}.txt", id))),
+        // there is no correct output, like in some communication tasks
+        OutputGenerator::NotAvailable => None,
+    };
+    (input, output)
+}
+
+/// The files inside the directory `dir` of the task, up to the given depth, that match the filter.
+fn task_files<F: Fn(&Path) -> bool>(
+    task_dir: &Path,
+    dir: &str,
+    max_depth: usize,
+    filter: F,
+) -> Vec<PathBuf> {
+    WalkDir::new(task_dir.join(dir))
+        .max_depth(max_depth)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && filter(entry.path()))
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+/// Write the exported files in a new directory.
+pub(crate) fn write_directory(
+    files: &BTreeMap<PathBuf, ExportedFile>,
+    dest: &Path,
+) -> Result<(), Error> {
+    for (path, file) in files {
+        let path = dest.join(path);
+        let parent = path.parent().expect("Invalid destination path");
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+        match file {
+            ExportedFile::Copy(source) => {
+                std::fs::copy(source, &path).with_context(|| {
+                    format!("Failed to copy {} to {}", source.display(), path.display())
+                })?;
+            }
+            ExportedFile::Content(content) => {
+                std::fs::write(&path, content)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+            ExportedFile::Script(content) => {
+                std::fs::write(&path, content)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+                    .with_context(|| format!("Failed to make {} executable", path.display()))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the exported files in a new zip file, keeping the permissions of the executables.
+pub(crate) fn write_zip(files: &BTreeMap<PathBuf, ExportedFile>, dest: &Path) -> Result<(), Error> {
+    let zip_file =
+        File::create(dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+    let mut zip = ZipWriter::new(zip_file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (path, file) in files {
+        let name = path.to_string_lossy();
+        match file {
+            ExportedFile::Copy(source) => {
+                let mut source_file = File::open(source)
+                    .with_context(|| format!("Failed to open {}", source.display()))?;
+                let mode = source_file.metadata()?.permissions().mode();
+                zip.start_file(name, options.unix_permissions(mode))?;
+                std::io::copy(&mut source_file, &mut zip)
+                    .with_context(|| format!("Failed to compress {}", source.display()))?;
+            }
+            ExportedFile::Content(content) => {
+                zip.start_file(name, options.unix_permissions(0o644))?;
+                zip.write_all(content.as_bytes())?;
+            }
+            ExportedFile::Script(content) => {
+                zip.start_file(name, options.unix_permissions(0o755))?;
+                zip.write_all(content.as_bytes())?;
+            }
+        }
+    }
+    zip.finish()
+        .map_err(|e| anyhow!("Failed to write {}: {}", dest.display(), e))?;
+    Ok(())
+}
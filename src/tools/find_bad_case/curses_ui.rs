@@ -19,7 +19,7 @@ impl CursesDrawer<UIState> for CursesUI {
 
 impl CursesUI {
     fn draw_frame(state: &UIState, f: &mut Frame, loading: char, frame_index: usize) {
-        let header_len = 10; // Number of lines of the header.
+        let header_len = 12; // Number of lines of the header.
         let workers_len = state
             .executor_status
             .as_ref()
@@ -46,6 +46,7 @@ impl CursesUI {
             f,
             chunks[2],
             state.executor_status.as_ref(),
+            state.file_transfer.as_ref(),
             loading,
             frame_index,
         );
@@ -104,6 +105,22 @@ impl CursesUI {
                 Span::styled("    Errors:      ", *BOLD),
                 Span::raw(errors.to_string()),
             ]),
+            Line::from(vec![
+                Span::styled("Minimizing:      ", *BOLD),
+                Span::raw(match state.minimization {
+                    Some((parameter, low, high)) => {
+                        format!("parameter {} in [{}, {}]", parameter, low, high)
+                    }
+                    None => "-".into(),
+                }),
+            ]),
+            Line::from(vec![
+                Span::styled("Smallest fail:   ", *BOLD),
+                Span::raw(match &state.smallest_failure {
+                    Some(generator_args) => generator_args.join(" "),
+                    None => "-".into(),
+                }),
+            ]),
         ];
 
         let paragraph = Paragraph::new(text);
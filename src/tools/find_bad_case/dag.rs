@@ -1,12 +1,14 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::{anyhow, bail, Context, Error};
 
 use task_maker_format::ioi::{
-    InputGenerator, SubtaskInfo, TestcaseId, TestcaseInfo, GENERATION_PRIORITY,
+    InputGenerator, OutputGenerator, SubtaskInfo, TestcaseId, TestcaseInfo, GENERATION_PRIORITY,
 };
-use task_maker_format::{EvaluationData, TaskFormat};
+use task_maker_format::{EvaluationData, SourceFile, TaskFormat};
+use task_maker_lang::GraderMap;
 
 /// The information about a testcase to generate.
 #[derive(Debug, Clone, Default)]
@@ -23,6 +25,49 @@ pub struct TestcaseData {
     pub correct_output_path: PathBuf,
 }
 
+/// An argument of the generator, as specified by the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeneratorArg {
+    /// An argument passed as-is.
+    Literal(String),
+    /// The random seed of the testcase, written as `{}`.
+    Seed,
+    /// A random integer in the inclusive range, written as `{low..high}`.
+    Range(i64, i64),
+}
+
+impl GeneratorArg {
+    /// Parse the arguments of the generator.
+    pub fn parse_all(args: &[String]) -> Result<Vec<GeneratorArg>, Error> {
+        args.iter().map(|arg| GeneratorArg::parse(arg)).collect()
+    }
+
+    fn parse(arg: &str) -> Result<GeneratorArg, Error> {
+        if arg == "{}" {
+            return Ok(GeneratorArg::Seed);
+        }
+        let range = arg
+            .strip_prefix('{')
+            .and_then(|arg| arg.strip_suffix('}'))
+            .and_then(|arg| arg.split_once(".."));
+        let Some((low, high)) = range else {
+            return Ok(GeneratorArg::Literal(arg.into()));
+        };
+        let low: i64 = low
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid lower bound of {}", arg))?;
+        let high: i64 = high
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid upper bound of {}", arg))?;
+        if low > high {
+            bail!("Empty range: {}", arg);
+        }
+        Ok(GeneratorArg::Range(low, high))
+    }
+}
+
 /// A set of testcases that will be put in a single DAG.
 #[derive(Debug, Clone, Default)]
 pub struct Batch {
@@ -31,9 +76,14 @@ pub struct Batch {
 
 /// Modify the task changing the subtasks and testcases in order to produce a DAG that runs the test
 /// testcases instead of the normal ones.
+///
+/// When `generator` or `model` are specified they replace, respectively, the generator and the
+/// official solution of the task.
 pub fn patch_task_for_batch(
     task: &mut TaskFormat,
-    generator_args: &[String],
+    generator: Option<&Path>,
+    model: Option<&Path>,
+    generator_args: &[GeneratorArg],
     batch_size: usize,
     batch_index: usize,
     working_directory: &Path,
@@ -42,14 +92,35 @@ pub fn patch_task_for_batch(
 
     match task {
         TaskFormat::IOI(task) => {
-            // A template testcase for selecting the generator and validator.
+            // A template testcase for selecting the generator and the official solution.
             let testcase_template = task
                 .testcases
                 .values()
                 .find(|tc| matches!(tc.input_generator, InputGenerator::Custom(_, _)))
-                .cloned()
-                // FIXME: in theory we can find the generator and the solution even without a testcase
-                .ok_or_else(|| anyhow!("Failed to find a base testcase"))?;
+                .cloned();
+            let generator = match generator {
+                Some(path) => custom_source_file(&task.path, path, "Generator", None)?,
+                None => match testcase_template.as_ref().map(|tc| &tc.input_generator) {
+                    Some(InputGenerator::Custom(generator, _)) => generator.clone(),
+                    // FIXME: in theory we can find the generator even without a testcase
+                    _ => bail!("Failed to find a base testcase, specify the generator"),
+                },
+            };
+            let output_generator = match model {
+                Some(path) => OutputGenerator::Custom(
+                    custom_source_file(
+                        &task.path,
+                        path,
+                        "Model solution",
+                        Some(task.grader_map.clone()),
+                    )?,
+                    vec![],
+                ),
+                None => match testcase_template.as_ref().map(|tc| &tc.output_generator) {
+                    Some(gen @ OutputGenerator::Custom(_, _)) => gen.clone(),
+                    _ => bail!("The task has no official solution, specify the model solution"),
+                },
+            };
             // Remove all the original testcases.
             task.subtasks.clear();
             // Create a single subtask with all the testcases of this batch.
@@ -61,21 +132,11 @@ pub fn patch_task_for_batch(
                 let seed = fastrand::i32(0..i32::MAX);
 
                 let generator_args = generator_args_for_testcase(generator_args, seed);
-                let mut input_generator = testcase_template.input_generator.clone();
-                match &mut input_generator {
-                    InputGenerator::StaticFile(_) => {
-                        unreachable!("The generator cannot be StaticFile")
-                    }
-                    InputGenerator::Custom(_, args) => {
-                        args.clone_from(&generator_args);
-                    }
-                }
+                let input_generator =
+                    InputGenerator::Custom(generator.clone(), generator_args.clone());
 
-                let testcase = TestcaseInfo::new(
-                    testcase_id,
-                    input_generator,
-                    testcase_template.output_generator.clone(),
-                );
+                let testcase =
+                    TestcaseInfo::new(testcase_id, input_generator, output_generator.clone());
 
                 let data = TestcaseData {
                     generator_args,
@@ -109,12 +170,39 @@ pub fn patch_task_for_batch(
     Ok(batch)
 }
 
-/// Produce the set of arguments of the generator replacing '{}' with the seed.
-fn generator_args_for_testcase(args: &[String], seed: i32) -> Vec<String> {
+/// Build the source file of a program specified by the user, compiling it inside the `bin` folder
+/// of the task.
+fn custom_source_file(
+    task_dir: &Path,
+    path: &Path,
+    kind: &str,
+    grader_map: Option<Arc<GraderMap>>,
+) -> Result<Arc<SourceFile>, Error> {
+    if !path.exists() {
+        bail!("{} not found at {}", kind, path.display());
+    }
+    let file_name = path.file_name().context("Invalid file name")?;
+    let write_bin_to = task_dir.join("bin").join(file_name);
+    SourceFile::new(
+        path,
+        task_dir,
+        format!("{} at {}", kind, path.display()),
+        grader_map,
+        Some(write_bin_to),
+    )
+    .map(Arc::new)
+    .ok_or_else(|| anyhow!("Cannot use {} '{}': unknown language", kind, path.display()))
+}
+
+/// Produce the set of arguments of the generator replacing '{}' with the seed and the ranges with
+/// a value drawn from them. The values depend only on the seed.
+fn generator_args_for_testcase(args: &[GeneratorArg], seed: i32) -> Vec<String> {
+    let mut rng = fastrand::Rng::with_seed(seed as u64);
     args.iter()
-        .map(|arg| match arg.as_str() {
-            "{}" => seed.to_string(),
-            _ => arg.into(),
+        .map(|arg| match arg {
+            GeneratorArg::Literal(arg) => arg.clone(),
+            GeneratorArg::Seed => seed.to_string(),
+            GeneratorArg::Range(low, high) => rng.i64(*low..=*high).to_string(),
         })
         .collect()
 }
@@ -1,5 +1,6 @@
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, RwLock};
 
 use anyhow::{anyhow, bail, Context, Error};
 use clap::{Parser, ValueHint};
@@ -11,7 +12,9 @@ use task_maker_format::ui::{CursesUI, StdoutPrinter, UIMessage, BLUE, BOLD, RED,
 use task_maker_format::{cwrite, cwriteln, get_sanity_check_list, EvaluationConfig};
 
 use crate::context::RuntimeContext;
-use crate::tools::find_bad_case::dag::{patch_dag, patch_task_for_batch, TestcaseData};
+use crate::tools::find_bad_case::dag::{
+    patch_dag, patch_task_for_batch, GeneratorArg, TestcaseData,
+};
 use crate::tools::find_bad_case::state::{SharedUIState, UIState};
 use crate::{ExecutionOpt, FindTaskOpt, StorageOpt};
 
@@ -38,11 +41,25 @@ pub struct FindBadCaseOpt {
     #[clap(long, short, default_value = "100")]
     pub batch_size: usize,
 
+    /// Path of the generator to use instead of the one of the task.
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    pub generator: Option<PathBuf>,
+
+    /// Path of the solution to use instead of the official solution of the task.
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    pub model: Option<PathBuf>,
+
     /// Path to the solution to check against the official solution of the task.
     #[clap(value_hint = ValueHint::FilePath)]
     pub solution: PathBuf,
 
-    /// Arguments to pass to the generator. The value '{}' will be replaced with a random seed.
+    /// After finding a failing testcase, search the smallest values of the ranges in the generator
+    /// arguments that still make the solution fail.
+    #[clap(long)]
+    pub minimize: bool,
+
+    /// Arguments to pass to the generator. The value '{}' will be replaced with a random seed, and
+    /// '{A..B}' with a random integer between A and B (inclusive).
     #[clap(num_args = 0..)]
     pub generator_args: Vec<String>,
 }
@@ -57,12 +74,21 @@ pub fn main_find_bad_case(opt: FindBadCaseOpt) -> Result<(), Error> {
         booklet_solutions: false,
         no_statement: true,
         solution_paths: vec![opt.solution.clone()],
+        extra_solution_paths: vec![],
         disabled_sanity_checks: get_sanity_check_list()
             .into_iter()
             .map(|check| check.0.into())
             .collect(),
         seed: None,
+        terry_cases: None,
+        terry_seeds: None,
         dry_run: false,
+        subtask_filter: vec![],
+        testcase_filter: vec![],
+        sample_testcases: None,
+        memory_metric: opt.execution.memory_metric,
+        verify_generators: false,
+        check_checker: false,
     };
     let working_directory =
         tempfile::TempDir::new().context("Failed to create working directory")?;
@@ -105,7 +131,11 @@ pub fn main_find_bad_case(opt: FindBadCaseOpt) -> Result<(), Error> {
         let shared_state = shared_state.clone();
         let current_executor_sender = current_executor_sender.clone();
         move || {
-            shared_state.write().unwrap().should_stop = true;
+            {
+                let mut shared_state = shared_state.write().unwrap();
+                shared_state.should_stop = true;
+                shared_state.interrupted = true;
+            }
             let current_executor_sender = current_executor_sender.lock().unwrap();
             if let Some(sender) = current_executor_sender.as_ref() {
                 if sender.send(ExecutorClientMessage::Stop).is_err() {
@@ -116,25 +146,144 @@ pub fn main_find_bad_case(opt: FindBadCaseOpt) -> Result<(), Error> {
     })
     .context("Failed to set ctrl-c handler")?;
 
-    for batch_index in 0.. {
-        let mut task = opt.find_task.find_task(&eval_config)?;
+    let generator_args = GeneratorArg::parse_all(&opt.generator_args)?;
+    let runner = BatchRunner {
+        opt: &opt,
+        eval_config: &eval_config,
+        working_directory: working_directory.path(),
+        shared_state: &shared_state,
+        sender: &sender,
+        current_executor_sender: &current_executor_sender,
+    };
+    let mut batch_index = 0;
+    loop {
+        runner.run(&generator_args, batch_index)?;
+        batch_index += 1;
+        if shared_state.read().unwrap().should_stop {
+            break;
+        }
+    }
+    let failing_testcase = shared_state.read().unwrap().failing_testcase.clone();
+    if let Some((testcase, message)) = failing_testcase {
+        runner.send(UIMessage::FuzzFailure {
+            generator_args: testcase.generator_args,
+            message,
+        });
+        if opt.minimize {
+            minimize(&runner, &generator_args, batch_index)?;
+        }
+    }
+
+    let _ = sender.send(None);
+    global_ui_join_handle
+        .join()
+        .map_err(|e| anyhow!("{:?}", e))
+        .context("Global UI thread failed")?;
+
+    let mut printer = StdoutPrinter::default();
+
+    let shared_state = shared_state.read().unwrap();
+    let (testcase, message) = match shared_state.failing_testcase.clone() {
+        Some(testcase) => testcase,
+        None => {
+            cwriteln!(printer, YELLOW, "No bad case found");
+            print_failures(&shared_state, &mut printer);
+            return Ok(());
+        }
+    };
+    let (input_path, correct_output_path, failing_output_path) =
+        copy_testcase(&testcase, &task_path)?;
+
+    cwrite!(printer, BOLD, "Solution:           ");
+    println!("{}", opt.solution.display());
+    if let Some(generator) = &opt.generator {
+        cwrite!(printer, BOLD, "Generator:          ");
+        println!("{}", generator.display());
+    }
+    if let Some(model) = &opt.model {
+        cwrite!(printer, BOLD, "Model solution:     ");
+        println!("{}", model.display());
+    }
+    cwrite!(printer, BOLD, "Batch size:         ");
+    println!("{}", opt.batch_size);
+
+    cwriteln!(printer, BOLD, "Failed testcase:");
+    cwrite!(printer, BOLD, "    Generator args: ");
+    println!("{}", testcase.generator_args.join(" "));
+    cwrite!(printer, BOLD, "    Seed:           ");
+    println!("{}", testcase.seed);
+    cwrite!(printer, BOLD, "    Message:        ");
+    println!("{}", message);
+    println!();
+    print_file("Input file", &task_path, &input_path, &mut printer)?;
+    if let Some(correct_output_path) = correct_output_path {
+        print_file(
+            "Correct output file",
+            &task_path,
+            &correct_output_path,
+            &mut printer,
+        )?;
+    }
+    if let Some(failing_output_path) = failing_output_path {
+        print_file(
+            "Failing output file",
+            &task_path,
+            &failing_output_path,
+            &mut printer,
+        )?;
+    }
+
+    print_failures(&shared_state, &mut printer);
+    Ok(())
+}
+
+/// The state needed for evaluating a batch of random testcases.
+struct BatchRunner<'a> {
+    opt: &'a FindBadCaseOpt,
+    eval_config: &'a EvaluationConfig,
+    /// Where the generated files are stored temporarily.
+    working_directory: &'a Path,
+    shared_state: &'a Arc<RwLock<SharedUIState>>,
+    /// The channel of the global UI.
+    sender: &'a Sender<Option<UIMessage>>,
+    /// The sender of the current executor, used for stopping it.
+    current_executor_sender: &'a Arc<Mutex<Option<ChannelSender<ExecutorClientMessage>>>>,
+}
+
+impl BatchRunner<'_> {
+    /// Send a message to the global UI.
+    fn send(&self, message: UIMessage) {
+        let _ = self.sender.send(Some(message));
+    }
+
+    /// Evaluate a batch of testcases generated with the specified arguments, blocking until the
+    /// evaluation ends. The failing testcase, if any, is stored in the shared state.
+    fn run(&self, generator_args: &[GeneratorArg], batch_index: usize) -> Result<(), Error> {
+        self.send(UIMessage::FuzzBatch {
+            index: batch_index,
+            size: self.opt.batch_size,
+        });
+        let opt = self.opt;
+        let mut task = opt.find_task.find_task(self.eval_config)?;
         let batch = patch_task_for_batch(
             &mut task,
-            &opt.generator_args,
+            opt.generator.as_deref(),
+            opt.model.as_deref(),
+            generator_args,
             opt.batch_size,
             batch_index,
-            working_directory.path(),
+            self.working_directory,
         )?;
 
         {
-            let mut shared_state = shared_state.write().unwrap();
+            let mut shared_state = self.shared_state.write().unwrap();
             shared_state.last_batch = Some(batch.clone());
             shared_state.batch_index = batch_index;
         }
 
         // Setup the configuration and the evaluation metadata.
         let context = RuntimeContext::new(task, &opt.execution, |task, eval| {
-            task.build_dag(eval, &eval_config)
+            task.build_dag(eval, self.eval_config)
                 .context("Cannot build the task DAG")?;
             patch_dag(eval, opt.batch_size, &batch).context("Cannot patch the DAG")
         })?;
@@ -145,7 +294,7 @@ pub fn main_find_bad_case(opt: FindBadCaseOpt) -> Result<(), Error> {
         let ui_thread = std::thread::Builder::new()
             .name("UI".to_owned())
             .spawn({
-                let sender = sender.clone();
+                let sender = self.sender.clone();
                 move || {
                     while let Ok(message) = ui_receiver.recv() {
                         if let UIMessage::StopUI = message {
@@ -161,26 +310,27 @@ pub fn main_find_bad_case(opt: FindBadCaseOpt) -> Result<(), Error> {
         std::mem::swap(&mut dag, &mut executor.eval.dag);
 
         // Run the actual computation and block until it ends.
-        let sender = sender.clone();
-        *current_executor_sender.lock().unwrap() = Some(executor.tx.clone());
+        let sender = self.sender.clone();
+        *self.current_executor_sender.lock().unwrap() = Some(executor.tx.clone());
         ExecutorClient::evaluate(
             dag,
             executor.tx,
             &executor.rx,
             executor.file_store,
-            move |status| {
+            executor.compression,
+            move |update| {
                 sender
-                    .send(Some(UIMessage::ServerStatus { status }))
+                    .send(Some(update.into()))
                     .map_err(|e| anyhow!("{:?}", e))
             },
         )
         .with_context(|| {
-            shared_state.write().unwrap().should_stop = true;
+            self.shared_state.write().unwrap().should_stop = true;
             "Client failed"
         })?;
 
         // Disable the ctrl-c handler dropping the owned clone of the sender, letting the client exit.
-        current_executor_sender.lock().unwrap().take();
+        self.current_executor_sender.lock().unwrap().take();
 
         drop(executor.eval);
         drop(executor.task);
@@ -197,64 +347,84 @@ pub fn main_find_bad_case(opt: FindBadCaseOpt) -> Result<(), Error> {
             .join()
             .map_err(|e| anyhow!("UI panicked: {:?}", e))
             .unwrap();
-
-        if shared_state.read().unwrap().should_stop {
-            break;
-        }
+        Ok(())
     }
+}
 
-    let _ = sender.send(None);
-    global_ui_join_handle
-        .join()
-        .map_err(|e| anyhow!("{:?}", e))
-        .context("Global UI thread failed")?;
-
-    let mut printer = StdoutPrinter::default();
-
-    let shared_state = shared_state.read().unwrap();
-    let (testcase, message) = match shared_state.failing_testcase.clone() {
-        Some(testcase) => testcase,
-        None => {
-            cwriteln!(printer, YELLOW, "No bad case found");
-            print_failures(&shared_state, &mut printer);
-            return Ok(());
-        }
+/// Search, one parameter at a time, the smallest value of the ranges in the arguments of the
+/// generator that still makes the solution fail, binary searching between the lower bound of the
+/// range and the value of the failing testcase. This assumes that the failures are monotone in the
+/// parameters, which is usually the case for the size of the input.
+///
+/// At the end the smallest failing testcase found replaces the one in the shared state.
+fn minimize(
+    runner: &BatchRunner,
+    generator_args: &[GeneratorArg],
+    mut batch_index: usize,
+) -> Result<(), Error> {
+    let Some(mut best) = runner.shared_state.read().unwrap().failing_testcase.clone() else {
+        return Ok(());
     };
-    let (input_path, correct_output_path, failing_output_path) =
-        copy_testcase(&testcase, &task_path)?;
-
-    cwrite!(printer, BOLD, "Solution:           ");
-    println!("{}", opt.solution.display());
-    cwrite!(printer, BOLD, "Batch size:         ");
-    println!("{}", opt.batch_size);
+    let value_of = |testcase: &TestcaseData, parameter: usize| -> Result<i64, Error> {
+        let value = &testcase.generator_args[parameter];
+        value
+            .parse()
+            .with_context(|| format!("Invalid value of parameter {}: {}", parameter, value))
+    };
+    'parameters: for (parameter, arg) in generator_args.iter().enumerate() {
+        let GeneratorArg::Range(mut low, _) = arg else {
+            continue;
+        };
+        let mut high = value_of(&best.0, parameter)?;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            // Fix all the ranges to the values of the best testcase, except the one to minimize.
+            let probe_args = generator_args
+                .iter()
+                .enumerate()
+                .map(|(index, arg)| match arg {
+                    GeneratorArg::Range(_, _) => {
+                        let value = if index == parameter {
+                            mid
+                        } else {
+                            value_of(&best.0, index)?
+                        };
+                        Ok(GeneratorArg::Range(value, value))
+                    }
+                    arg => Ok(arg.clone()),
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            runner.send(UIMessage::FuzzMinimization {
+                parameter,
+                low,
+                high,
+            });
+            {
+                let mut shared_state = runner.shared_state.write().unwrap();
+                shared_state.should_stop = false;
+                shared_state.failing_testcase = None;
+            }
+            runner.run(&probe_args, batch_index)?;
+            batch_index += 1;
 
-    cwriteln!(printer, BOLD, "Failed testcase:");
-    cwrite!(printer, BOLD, "    Generator args: ");
-    println!("{}", testcase.generator_args.join(" "));
-    cwrite!(printer, BOLD, "    Seed:           ");
-    println!("{}", testcase.seed);
-    cwrite!(printer, BOLD, "    Message:        ");
-    println!("{}", message);
-    println!();
-    print_file("Input file", &task_path, &input_path, &mut printer)?;
-    if let Some(correct_output_path) = correct_output_path {
-        print_file(
-            "Correct output file",
-            &task_path,
-            &correct_output_path,
-            &mut printer,
-        )?;
-    }
-    if let Some(failing_output_path) = failing_output_path {
-        print_file(
-            "Failing output file",
-            &task_path,
-            &failing_output_path,
-            &mut printer,
-        )?;
+            let shared_state = runner.shared_state.read().unwrap();
+            if shared_state.interrupted {
+                break 'parameters;
+            }
+            match &shared_state.failing_testcase {
+                Some(testcase) => {
+                    high = mid;
+                    best = testcase.clone();
+                    runner.send(UIMessage::FuzzFailure {
+                        generator_args: best.0.generator_args.clone(),
+                        message: best.1.clone(),
+                    });
+                }
+                None => low = mid + 1,
+            }
+        }
     }
-
-    print_failures(&shared_state, &mut printer);
+    runner.shared_state.write().unwrap().failing_testcase = Some(best);
     Ok(())
 }
 
@@ -4,7 +4,7 @@ use std::sync::{Arc, RwLock};
 use std::time::SystemTime;
 use task_maker_dag::ExecutionResult;
 
-use task_maker_exec::ExecutorStatus;
+use task_maker_exec::{ExecutorStatus, FileTransfer};
 use task_maker_format::ioi::TestcaseId;
 use task_maker_format::ui::{UIExecutionStatus, UIMessage, UIStateT};
 
@@ -28,10 +28,16 @@ pub struct UIState {
 
     /// The current status of the executor, if any.
     pub executor_status: Option<ExecutorStatus<SystemTime>>,
+    /// The upload of a file to the executor in progress, if any.
+    pub file_transfer: Option<FileTransfer>,
     /// The current progress of the evaluation.
     pub progress: Progress,
     /// The set of batches that have been run.
     pub batches: Vec<CurrentBatch>,
+    /// The parameter being minimized, with the range of values still to search.
+    pub minimization: Option<(usize, i64, i64)>,
+    /// The arguments of the generator of the smallest failing testcase found so far.
+    pub smallest_failure: Option<Vec<String>>,
 
     /// The part of the state shared with the outside world (i.e. by non-UI code).
     pub shared: Arc<RwLock<SharedUIState>>,
@@ -105,6 +111,8 @@ pub struct SharedUIState {
     pub batch_index: usize,
     /// Whether the UI and the execution should stop and no further batch should be tried.
     pub should_stop: bool,
+    /// Whether the user asked to stop, in which case the failing testcase should not be minimized.
+    pub interrupted: bool,
     /// The last batch being evaluated.
     pub last_batch: Option<Batch>,
     /// A testcase that made the solution fail, together with a failing message.
@@ -121,7 +129,10 @@ impl UIState {
             generator_args: opt.generator_args.clone(),
             batch_size: opt.batch_size,
             executor_status: None,
+            file_transfer: None,
             batches: vec![],
+            minimization: None,
+            smallest_failure: None,
             progress: Default::default(),
             shared: Arc::new(RwLock::new(SharedUIState::default())),
         }
@@ -140,6 +151,17 @@ impl UIStateT for UIState {
                 self.batches.push(CurrentBatch::new(self.batch_size));
             }
             UIMessage::ServerStatus { status } => self.executor_status = Some(status),
+            UIMessage::FileTransfer { transfer } => {
+                self.file_transfer = (transfer.sent < transfer.total).then_some(transfer)
+            }
+            UIMessage::FuzzMinimization {
+                parameter,
+                low,
+                high,
+            } => self.minimization = Some((parameter, low, high)),
+            UIMessage::FuzzFailure { generator_args, .. } => {
+                self.smallest_failure = Some(generator_args)
+            }
             UIMessage::IOIGeneration {
                 testcase, status, ..
             } => match status {
@@ -0,0 +1,182 @@
+//! Scaffolding of the checkers of the IOI tasks, ready to be customized and already following the
+//! protocol task-maker expects from them.
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+
+use crate::tools::init::TemplateLanguage;
+
+/// The maximum error accepted by the float checker when `--eps` is not specified.
+const DEFAULT_EPS: f64 = 1e-6;
+
+#[derive(Parser, Debug, Clone)]
+pub struct GenCheckerOpt {
+    /// Directory of the task
+    #[clap(short = 't', long = "task-dir", default_value = ".")]
+    pub task_dir: PathBuf,
+
+    /// What the checker compares: tokens, float or yes-no.
+    ///
+    /// tokens compares the outputs token by token, float compares the numbers allowing an error up
+    /// to --eps, yes-no compares the YES/NO answers ignoring their case.
+    #[clap(long = "type")]
+    pub checker_type: CheckerType,
+
+    /// Maximum absolute or relative error of the numbers accepted by the float checker.
+    #[clap(long)]
+    pub eps: Option<f64>,
+
+    /// Language of the checker: cpp or py.
+    #[clap(long, default_value = "cpp")]
+    pub language: TemplateLanguage,
+
+    /// Replace the checker of the task, if it already has one.
+    #[clap(long)]
+    pub force: bool,
+}
+
+/// What a generated checker compares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckerType {
+    /// The outputs are compared token by token, ignoring the white space.
+    Tokens,
+    /// The outputs are numbers, compared with an absolute or relative tolerance.
+    Float,
+    /// The outputs are YES/NO answers, compared ignoring their case.
+    YesNo,
+}
+
+/// The information needed for generating a checker.
+#[derive(Debug, Clone)]
+pub struct CheckerTemplate {
+    /// What the checker compares.
+    pub checker_type: CheckerType,
+    /// The maximum error of the float checker.
+    pub eps: f64,
+    /// The language of the checker.
+    pub language: TemplateLanguage,
+}
+
+impl FromStr for CheckerType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tokens" => Ok(CheckerType::Tokens),
+            "float" => Ok(CheckerType::Float),
+            "yes-no" | "yesno" => Ok(CheckerType::YesNo),
+            _ => bail!(
+                "Unknown checker type: {} (valid are: tokens, float, yes-no)",
+                s
+            ),
+        }
+    }
+}
+
+impl CheckerTemplate {
+    /// The path of the checker inside the task and its content, with the placeholders still in
+    /// place.
+    fn file(&self) -> (&'static str, &'static str) {
+        match (self.language, self.checker_type) {
+            (TemplateLanguage::Cpp, CheckerType::Tokens) => {
+                ("check/checker.cpp", include_str!("templates/tokens.cpp"))
+            }
+            (TemplateLanguage::Cpp, CheckerType::Float) => {
+                ("check/checker.cpp", include_str!("templates/float.cpp"))
+            }
+            (TemplateLanguage::Cpp, CheckerType::YesNo) => {
+                ("check/checker.cpp", include_str!("templates/yes_no.cpp"))
+            }
+            (TemplateLanguage::Python, CheckerType::Tokens) => {
+                ("check/checker.py", include_str!("templates/tokens.py"))
+            }
+            (TemplateLanguage::Python, CheckerType::Float) => {
+                ("check/checker.py", include_str!("templates/float.py"))
+            }
+            (TemplateLanguage::Python, CheckerType::YesNo) => {
+                ("check/checker.py", include_str!("templates/yes_no.py"))
+            }
+        }
+    }
+
+    /// The source of the checker.
+    pub fn render(&self) -> String {
+        // {:e} is a valid literal both in C++ and in Python, and it doesn't lose precision
+        self.file().1.replace("%{eps}%", &format!("{:e}", self.eps))
+    }
+
+    /// Write the checker in the task, which must not have one unless `force` is set. With `force`
+    /// the existing checkers are removed, so that the task doesn't end up with two of them. Returns
+    /// the path of the checker.
+    pub fn create(&self, task_dir: &Path, force: bool) -> Result<PathBuf, Error> {
+        let existing = existing_checkers(task_dir)?;
+        if !existing.is_empty() && !force {
+            bail!(
+                "The task already has a checker: {} (use --force to replace it)",
+                existing[0].display()
+            );
+        }
+        for path in existing {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+
+        let dest = task_dir.join(self.file().0);
+        let parent = dest.parent().context("Invalid checker path")?;
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+        std::fs::write(&dest, self.render())
+            .with_context(|| format!("Failed to write {}", dest.display()))?;
+        if self.language == TemplateLanguage::Python {
+            std::fs::set_permissions(&dest, std::fs::Permissions::from_mode(0o755))
+                .with_context(|| format!("Failed to make {} executable", dest.display()))?;
+        }
+        Ok(dest)
+    }
+}
+
+/// The checkers of a task: the files named `check/checker.*` or `cor/correttore.*`.
+fn existing_checkers(task_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut checkers = vec![];
+    for (dir, name) in [("check", "checker"), ("cor", "correttore")] {
+        let dir = task_dir.join(dir);
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in
+            std::fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_file() && path.file_stem().is_some_and(|stem| stem == name) {
+                checkers.push(path);
+            }
+        }
+    }
+    checkers.sort();
+    Ok(checkers)
+}
+
+pub fn main_gen_checker(opt: GenCheckerOpt) -> Result<(), Error> {
+    if opt.eps.is_some() && opt.checker_type != CheckerType::Float {
+        bail!("--eps is used only by the float checker");
+    }
+    let eps = opt.eps.unwrap_or(DEFAULT_EPS);
+    if !eps.is_finite() || eps < 0.0 {
+        bail!("Invalid --eps: {}", eps);
+    }
+    if !opt.task_dir.is_dir() {
+        bail!("{} is not a directory", opt.task_dir.display());
+    }
+    let template = CheckerTemplate {
+        checker_type: opt.checker_type,
+        eps,
+        language: opt.language,
+    };
+    let path = template.create(&opt.task_dir, opt.force)?;
+    println!("Created {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,59 @@
+#include <algorithm>
+#include <cmath>
+#include <cstdlib>
+#include <fstream>
+#include <iostream>
+#include <string>
+
+// Usage: checker input_file correct_output test_output
+// The score, from 0.0 to 1.0, is printed to stdout and the message for the contestant to stderr.
+// The checker must always exit with 0, even when the output is wrong.
+
+// The maximum absolute or relative error of the numbers of the output.
+const double EPS = %{eps}%;
+
+// Print the score and the message, and exit. The messages "translate:success", "translate:partial"
+// and "translate:wrong" are shown to the contestants in their language.
+[[noreturn]] void verdict(double score, const std::string& message) {
+  std::cout << score << std::endl;
+  std::cerr << message << std::endl;
+  std::exit(0);
+}
+
+// Parse a whole token as a finite number, rejecting "nan", "inf" and the trailing garbage.
+bool parse(const std::string& token, double& value) {
+  char* end = nullptr;
+  value = std::strtod(token.c_str(), &end);
+  return !token.empty() && *end == '\0' && std::isfinite(value);
+}
+
+int main(int argc, char** argv) {
+  if (argc != 4) {
+    std::cerr << "Usage: " << argv[0] << " input_file correct_output test_output"
+              << std::endl;
+    return 1;
+  }
+  std::ifstream correct(argv[2]);  // NOLINT
+  std::ifstream test(argv[3]);     // NOLINT
+
+  // compare the numbers of the outputs one by one
+  std::string token;
+  int index = 0;
+  while (correct >> token) {
+    index++;
+    double expected, answer;
+    if (!parse(token, expected)) {
+      std::cerr << "The correct output has an invalid number: " << token << std::endl;
+      return 1;
+    }
+    if (!(test >> token)) verdict(0.0, "Output is too short");
+    if (!parse(token, answer)) {
+      verdict(0.0, "Number " + std::to_string(index) + " is not valid");
+    }
+    if (std::abs(answer - expected) > EPS * std::max(1.0, std::abs(expected))) {
+      verdict(0.0, "translate:wrong");
+    }
+  }
+  if (test >> token) verdict(0.0, "Output is too long");
+  verdict(1.0, "translate:success");
+}
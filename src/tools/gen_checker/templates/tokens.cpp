@@ -0,0 +1,35 @@
+#include <cstdlib>
+#include <fstream>
+#include <iostream>
+#include <string>
+
+// Usage: checker input_file correct_output test_output
+// The score, from 0.0 to 1.0, is printed to stdout and the message for the contestant to stderr.
+// The checker must always exit with 0, even when the output is wrong.
+
+// Print the score and the message, and exit. The messages "translate:success", "translate:partial"
+// and "translate:wrong" are shown to the contestants in their language.
+[[noreturn]] void verdict(double score, const std::string& message) {
+  std::cout << score << std::endl;
+  std::cerr << message << std::endl;
+  std::exit(0);
+}
+
+int main(int argc, char** argv) {
+  if (argc != 4) {
+    std::cerr << "Usage: " << argv[0] << " input_file correct_output test_output"
+              << std::endl;
+    return 1;
+  }
+  std::ifstream correct(argv[2]);  // NOLINT
+  std::ifstream test(argv[3]);     // NOLINT
+
+  // compare the outputs token by token, ignoring the white space
+  std::string expected, answer;
+  while (correct >> expected) {
+    if (!(test >> answer)) verdict(0.0, "Output is too short");
+    if (answer != expected) verdict(0.0, "translate:wrong");
+  }
+  if (test >> answer) verdict(0.0, "Output is too long");
+  verdict(1.0, "translate:success");
+}
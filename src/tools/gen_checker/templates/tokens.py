@@ -0,0 +1,33 @@
+#!/usr/bin/env python3
+
+import sys
+
+# Usage: checker input_file correct_output test_output
+# The score, from 0.0 to 1.0, is printed to stdout and the message for the contestant to stderr.
+# The checker must always exit with 0, even when the output is wrong.
+
+
+def verdict(score, message):
+    """Print the score and the message, and exit. The messages "translate:success",
+    "translate:partial" and "translate:wrong" are shown to the contestants in their language."""
+    print(score)
+    print(message, file=sys.stderr)
+    sys.exit(0)
+
+
+if len(sys.argv) != 4:
+    print("Usage: %s input_file correct_output test_output" % sys.argv[0], file=sys.stderr)
+    sys.exit(1)
+expected = open(sys.argv[2]).read().split()
+# the output of the contestant may not be valid text
+answer = open(sys.argv[3], errors="replace").read().split()
+
+# compare the outputs token by token, ignoring the white space
+for index, token in enumerate(expected):
+    if index >= len(answer):
+        verdict(0.0, "Output is too short")
+    if answer[index] != token:
+        verdict(0.0, "translate:wrong")
+if len(answer) > len(expected):
+    verdict(0.0, "Output is too long")
+verdict(1.0, "translate:success")
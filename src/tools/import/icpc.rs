@@ -0,0 +1,588 @@
+//! Conversion of the ICPC problem packages, used by Kattis and DOMjudge, to the IOI format.
+//!
+//! The tests of `data/` are copied with a `gen/cases.gen`, and their answers become the static
+//! outputs of the task. The samples are a subtask worth no points, and each test group of
+//! `data/secret` becomes a subtask. The input and output validators written in C++ are wrapped in
+//! programs that speak the protocol of task-maker.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use serde_yaml::Value;
+use walkdir::WalkDir;
+
+use crate::tools::import::copy_file;
+use crate::tools::TaskYamlOrig;
+
+/// The checker of the task, running the output validator of the package.
+const CHECKER_WRAPPER: &str = include_str!("templates/icpc_checker.cpp");
+/// The validator of the task, running the input validator of the package.
+const VALIDATOR_WRAPPER: &str = include_str!("templates/icpc_validator.cpp");
+/// The memory limit of the problems that don't specify it, in MiB.
+const DEFAULT_MEMORY_LIMIT: u64 = 2048;
+/// The time limit of the problems that don't specify it, in seconds.
+const DEFAULT_TIME_LIMIT: f64 = 1.0;
+
+/// A test of the package.
+#[derive(Debug, Clone)]
+struct IcpcTest {
+    /// The path of the input file inside the package.
+    input: PathBuf,
+    /// The path of the correct output inside the package.
+    answer: PathBuf,
+}
+
+/// A group of secret tests of the package, a directory inside `data/secret`.
+#[derive(Debug, Clone)]
+struct IcpcGroup {
+    /// The name of the group, derived from its path.
+    name: String,
+    /// The maximum score of the group, when the package specifies it.
+    score: Option<f64>,
+    /// The tests of the group.
+    tests: Vec<IcpcTest>,
+}
+
+/// The content of an ICPC problem package.
+#[derive(Debug, Clone)]
+pub struct IcpcProblem {
+    /// The short name of the problem, the name of the package.
+    pub name: String,
+    /// The title of the problem, in English if available.
+    pub title: String,
+    /// The time limit, in seconds.
+    time_limit: f64,
+    /// The memory limit, in MiB.
+    memory_limit: u64,
+    /// The samples of the problem.
+    samples: Vec<IcpcTest>,
+    /// The groups of the secret tests.
+    groups: Vec<IcpcGroup>,
+    /// Whether the problem gives a score to the solutions, instead of just accepting them.
+    scoring: bool,
+    /// The input validator: a source file, or a directory with its sources.
+    input_validator: Option<PathBuf>,
+    /// The output validator: a source file, or a directory with its sources.
+    output_validator: Option<PathBuf>,
+    /// The sources of the submissions, with their category (like `accepted` or `wrong_answer`).
+    submissions: Vec<(PathBuf, String)>,
+    /// The features of the package that are not imported.
+    pub warnings: Vec<String>,
+}
+
+/// The entries of a directory of the package, sorted by name. Missing directories are empty.
+fn entries(package_dir: &Path, dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let path = package_dir.join(dir);
+    if !path.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut entries = vec![];
+    for entry in
+        std::fs::read_dir(&path).with_context(|| format!("Failed to read {}", path.display()))?
+    {
+        entries.push(dir.join(entry?.file_name()));
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// The tests directly inside a directory of the package, skipping the inputs without an answer.
+fn tests_in(
+    package_dir: &Path,
+    dir: &Path,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<IcpcTest>, Error> {
+    let mut tests = vec![];
+    for input in entries(package_dir, dir)? {
+        if input.extension().is_none_or(|ext| ext != "in") {
+            continue;
+        }
+        let answer = input.with_extension("ans");
+        if !package_dir.join(&answer).is_file() {
+            warnings.push(format!(
+                "{} has no answer, it's not imported",
+                input.display()
+            ));
+            continue;
+        }
+        tests.push(IcpcTest { input, answer });
+    }
+    Ok(tests)
+}
+
+/// The maximum score of a test group, from the `range` of its `testdata.yaml`.
+fn group_score(dir: &Path) -> Result<Option<f64>, Error> {
+    let path = dir.join("testdata.yaml");
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let testdata: Value =
+        serde_yaml::from_str(&content).with_context(|| format!("Invalid {}", path.display()))?;
+    let Some(range) = testdata["range"].as_str() else {
+        return Ok(None);
+    };
+    let max = range
+        .split_whitespace()
+        .nth(1)
+        .with_context(|| format!("Invalid range in {}: {:?}", path.display(), range))?;
+    Ok(Some(max.parse().with_context(|| {
+        format!("Invalid range in {}: {:?}", path.display(), range)
+    })?))
+}
+
+/// A subtask name for a path, keeping only the characters allowed in the names.
+fn subtask_name(path: &Path) -> String {
+    path.to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+impl IcpcProblem {
+    /// Parse an ICPC problem package extracted in a directory, whose short name is `name`.
+    pub fn parse(package_dir: &Path, name: &str) -> Result<IcpcProblem, Error> {
+        let path = package_dir.join("problem.yaml");
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let yaml: Value = serde_yaml::from_str(&content).context("Invalid problem.yaml")?;
+        let mut warnings = vec![];
+
+        let title = match &yaml["name"] {
+            Value::String(title) => title.as_str(),
+            Value::Mapping(titles) => titles
+                .get("en")
+                .or_else(|| titles.values().next())
+                .and_then(Value::as_str)
+                .unwrap_or(name),
+            _ => name,
+        }
+        .to_string();
+
+        // the legacy format describes the problem in `validation`, the newer one in `type`
+        let mut kind = vec![];
+        for value in [&yaml["validation"], &yaml["type"]] {
+            match value {
+                Value::String(value) => kind.extend(value.split_whitespace()),
+                Value::Sequence(values) => kind.extend(values.iter().filter_map(Value::as_str)),
+                _ => {}
+            }
+        }
+        if kind.contains(&"interactive") || kind.contains(&"multi-pass") {
+            bail!("Interactive problems cannot be imported");
+        }
+        let scoring = kind.contains(&"score") || kind.contains(&"scoring");
+        if scoring {
+            warnings.push(
+                "The scores of the single tests are not imported, a test gets either all or none \
+                 of its points"
+                    .into(),
+            );
+        }
+        if let Some(flags) = yaml["validator_flags"]
+            .as_str()
+            .filter(|flags| !flags.trim().is_empty())
+        {
+            warnings.push(format!("The validator flags are ignored: {}", flags));
+        }
+
+        let limits = &yaml["limits"];
+        let memory_limit = limits["memory"].as_u64().unwrap_or(DEFAULT_MEMORY_LIMIT);
+        let time_limit = match std::fs::read_to_string(package_dir.join(".timelimit")) {
+            Ok(content) => content
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid .timelimit: {:?}", content))?,
+            Err(_) => match limits["time_limit"].as_f64() {
+                Some(time_limit) => time_limit,
+                None => {
+                    warnings.push(format!(
+                        "The time limit is not in the package, it's set to {} second",
+                        DEFAULT_TIME_LIMIT
+                    ));
+                    DEFAULT_TIME_LIMIT
+                }
+            },
+        };
+
+        let samples = tests_in(package_dir, Path::new("data/sample"), &mut warnings)?;
+        let secret = package_dir.join("data/secret");
+        if !secret.is_dir() {
+            bail!("Missing data/secret in the package");
+        }
+        let mut groups = vec![];
+        for entry in WalkDir::new(&secret).sort_by_file_name() {
+            let entry = entry?;
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+            let dir = entry.path().strip_prefix(package_dir)?;
+            let tests = tests_in(package_dir, dir, &mut warnings)?;
+            if tests.is_empty() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(&secret)?;
+            let name = if relative.as_os_str().is_empty() {
+                "secret".into()
+            } else {
+                subtask_name(relative)
+            };
+            groups.push(IcpcGroup {
+                name,
+                score: group_score(entry.path())?,
+                tests,
+            });
+        }
+        if groups.is_empty() {
+            bail!("There are no tests in data/secret");
+        }
+
+        let input_validators = entries(package_dir, Path::new("input_validators"))?;
+        if input_validators.len() > 1 {
+            warnings.push(format!(
+                "Only the first input validator is imported, ignoring {} more",
+                input_validators.len() - 1
+            ));
+        }
+        let input_validator = input_validators.into_iter().next();
+        // the legacy format has a directory of output validators, the newer one a single one
+        let output_validators = entries(package_dir, Path::new("output_validators"))?;
+        if output_validators.len() > 1 {
+            warnings.push(format!(
+                "Only the first output validator is imported, ignoring {} more",
+                output_validators.len() - 1
+            ));
+        }
+        let output_validator = output_validators.into_iter().next().or_else(|| {
+            let dir = PathBuf::from("output_validator");
+            package_dir.join(&dir).is_dir().then_some(dir)
+        });
+        if kind.first() == Some(&"custom") && output_validator.is_none() {
+            bail!("The problem uses a custom output validator, but it's not in the package");
+        }
+
+        let mut submissions = vec![];
+        for category in entries(package_dir, Path::new("submissions"))? {
+            let category_name =
+                subtask_name(Path::new(category.file_name().context("Invalid path")?));
+            for submission in entries(package_dir, &category)? {
+                if package_dir.join(&submission).is_dir() {
+                    warnings.push(format!(
+                        "The submission {} has many files, it's not imported",
+                        submission.display()
+                    ));
+                    continue;
+                }
+                submissions.push((submission, category_name.clone()));
+            }
+        }
+
+        for (dir, feature) in [
+            (
+                "generators",
+                "The generators are not imported, the tests are copied from data/",
+            ),
+            (
+                "include",
+                "The files included in the submissions are not imported",
+            ),
+        ] {
+            if package_dir.join(dir).exists() {
+                warnings.push(feature.into());
+            }
+        }
+
+        Ok(IcpcProblem {
+            name: name.to_string(),
+            title,
+            time_limit,
+            memory_limit,
+            samples,
+            groups,
+            scoring,
+            input_validator,
+            output_validator,
+            submissions,
+            warnings,
+        })
+    }
+
+    /// The files of a program of the package, and its main C++ source. The program is either a
+    /// single source file or a directory with its sources.
+    fn program_sources(
+        &mut self,
+        package_dir: &Path,
+        program: &Path,
+    ) -> Result<Option<(Vec<PathBuf>, PathBuf)>, Error> {
+        let files = if package_dir.join(program).is_dir() {
+            entries(package_dir, program)?
+                .into_iter()
+                .filter(|path| package_dir.join(path).is_file())
+                .collect()
+        } else {
+            vec![program.to_path_buf()]
+        };
+        let mut sources = files.iter().filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "cpp" || ext == "cc")
+        });
+        match (sources.next(), sources.next()) {
+            (Some(main), None) => {
+                let main = main.clone();
+                Ok(Some((files, main)))
+            }
+            _ => {
+                self.warnings.push(format!(
+                    "{} is not a single C++ source file, it's not imported",
+                    program.display()
+                ));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Copy the files of a program in a directory of the task, renaming its main source.
+    fn copy_program(
+        &mut self,
+        package_dir: &Path,
+        program: &Path,
+        dest_dir: &Path,
+        main_name: &str,
+    ) -> Result<bool, Error> {
+        let Some((files, main)) = self.program_sources(package_dir, program)? else {
+            return Ok(false);
+        };
+        for file in files {
+            let name = if file == main {
+                main_name.into()
+            } else {
+                file.file_name().context("Invalid path")?.to_os_string()
+            };
+            copy_file(&package_dir.join(&file), &dest_dir.join(name))?;
+        }
+        Ok(true)
+    }
+
+    /// Write the task in the IOI format inside `task_dir`, copying the files from the package.
+    pub fn write(&mut self, package_dir: &Path, task_dir: &Path) -> Result<(), Error> {
+        let copy = |from: &Path, to: &str| copy_file(&package_dir.join(from), &task_dir.join(to));
+
+        let mut has_validator = false;
+        if let Some(validator) = self.input_validator.clone() {
+            let gen_dir = task_dir.join("gen");
+            if self.copy_program(package_dir, &validator, &gen_dir, "icpc_validator.cpp")? {
+                std::fs::write(gen_dir.join("validator.cpp"), VALIDATOR_WRAPPER)
+                    .context("Failed to write the validator")?;
+                has_validator = true;
+            }
+        }
+        if let Some(checker) = self.output_validator.clone() {
+            let check_dir = task_dir.join("check");
+            if self.copy_program(
+                package_dir,
+                &checker,
+                &check_dir,
+                "icpc_output_validator.cpp",
+            )? {
+                std::fs::write(check_dir.join("checker.cpp"), CHECKER_WRAPPER)
+                    .context("Failed to write the checker")?;
+            } else {
+                self.warnings
+                    .push("The outputs are compared ignoring whitespaces".into());
+            }
+        }
+
+        // the samples are worth no points, the secret groups share the 100 points of the task
+        let known: f64 = self.groups.iter().filter_map(|group| group.score).sum();
+        let unknown: Vec<_> = self
+            .groups
+            .iter()
+            .filter(|group| group.score.is_none())
+            .map(|group| group.name.as_str())
+            .collect();
+        let rest = (100.0 - known).max(0.0) / unknown.len().max(1) as f64;
+        if unknown.len() > 1 || (self.scoring && !unknown.is_empty()) {
+            self.warnings.push(format!(
+                "The score of the test groups {} is not in the package, they share {} points \
+                 evenly",
+                unknown.join(", "),
+                100.0 - known
+            ));
+        }
+        let mut subtasks = vec![];
+        if !self.samples.is_empty() {
+            subtasks.push((0.0, "samples".to_string(), &self.samples));
+        }
+        for group in &self.groups {
+            subtasks.push((
+                group.score.unwrap_or(rest),
+                group.name.clone(),
+                &group.tests,
+            ));
+        }
+
+        let mut cases_gen = format!(
+            "# Generated by task-maker-tools import from the ICPC package of {}\n",
+            self.name
+        );
+        if has_validator {
+            writeln!(cases_gen, ": VAL default gen/validator.cpp $INPUT")?;
+        }
+        let mut testcase_id = 0;
+        for (score, name, tests) in subtasks {
+            writeln!(cases_gen)?;
+            writeln!(cases_gen, ": SUBTASK {} {}", score, name)?;
+            for test in tests {
+                let input = format!("gen/{}", test.input.display());
+                if input.contains(|c: char| c.is_whitespace() || c == '#') {
+                    bail!(
+                        "The path of the test {} is not supported",
+                        test.input.display()
+                    );
+                }
+                copy(&test.input, &input)?;
+                // the answers of the package are the correct outputs
+                copy(&test.answer, &format!("output/output{}.txt", testcase_id))?;
+                writeln!(cases_gen, ": COPY {}", input)?;
+                testcase_id += 1;
+            }
+        }
+        std::fs::write(task_dir.join("gen/cases.gen"), cases_gen)
+            .context("Failed to write gen/cases.gen")?;
+
+        // the submissions are not named solution.*, so the answers are not regenerated
+        for (submission, category) in &self.submissions {
+            let name = submission
+                .file_name()
+                .context("Invalid path")?
+                .to_string_lossy();
+            copy(submission, &format!("sol/{}_{}", category, name))?;
+        }
+
+        for dir in ["problem_statement", "statement", "attachments"] {
+            if !package_dir.join(dir).is_dir() {
+                continue;
+            }
+            for entry in WalkDir::new(package_dir.join(dir)) {
+                let entry = entry?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let path = entry.path().strip_prefix(package_dir)?;
+                let relative = entry.path().strip_prefix(package_dir.join(dir))?;
+                let name = relative.to_string_lossy();
+                if dir == "attachments" {
+                    copy(path, &format!("att/{}", name))?;
+                } else if let Some(pdf) = name.strip_suffix(".pdf") {
+                    // problem.en.pdf is the English statement
+                    let language = pdf.strip_prefix("problem.").unwrap_or(pdf);
+                    copy(path, &format!("statement/{}.pdf", language))?;
+                } else {
+                    // the LaTeX sources are not compiled by task-maker, they are kept aside
+                    copy(path, &format!("statement/icpc/{}", name))?;
+                }
+            }
+        }
+
+        let task_yaml = TaskYamlOrig {
+            name: &self.name,
+            title: &self.title,
+            time_limit: self.time_limit,
+            memory_limit: self.memory_limit,
+            infile: "",
+            outfile: "",
+            score_type: None,
+        };
+        std::fs::write(task_dir.join("task.yaml.orig"), task_yaml.to_yaml()?)
+            .context("Failed to write task.yaml.orig")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The score of a group whose `testdata.yaml` is `testdata`.
+    fn score(testdata: &str) -> Result<Option<f64>, Error> {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("testdata.yaml"), testdata).unwrap();
+        group_score(dir.path())
+    }
+
+    #[test]
+    fn test_group_score() {
+        assert_eq!(score("range: 0 30").unwrap(), Some(30.0));
+        assert_eq!(score("range: 0 12.5\naccept_score: 1").unwrap(), Some(12.5));
+        assert_eq!(score("on_reject: continue").unwrap(), None);
+        assert!(score("range: \"30\"").is_err());
+        assert!(score("range: 0 lots").is_err());
+    }
+
+    #[test]
+    fn test_group_score_missing() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(group_score(dir.path()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_subtask_name() {
+        assert_eq!(subtask_name(Path::new("group1")), "group1");
+        assert_eq!(subtask_name(Path::new("small/n-10")), "small_n-10");
+        assert_eq!(subtask_name(Path::new("big n.2")), "big_n_2");
+    }
+
+    #[test]
+    fn test_parse_groups() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let package = dir.path();
+        for (path, content) in [
+            ("problem.yaml", "name: Sum\ntype: pass-fail\n"),
+            ("data/sample/1.in", "1 2\n"),
+            ("data/sample/1.ans", "3\n"),
+            ("data/sample/2.in", "2 3\n"),
+            ("data/secret/1.in", "1 1\n"),
+            ("data/secret/1.ans", "2\n"),
+            ("data/secret/big/1.in", "5 5\n"),
+            ("data/secret/big/1.ans", "10\n"),
+            ("data/secret/big/testdata.yaml", "range: 0 40\n"),
+        ] {
+            let path = package.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let problem = IcpcProblem::parse(package, "sum").unwrap();
+        assert_eq!(problem.title, "Sum");
+        assert_eq!(problem.time_limit, DEFAULT_TIME_LIMIT);
+        assert_eq!(problem.memory_limit, DEFAULT_MEMORY_LIMIT);
+        assert!(!problem.scoring);
+        // the sample without an answer is skipped
+        assert_eq!(problem.samples.len(), 1);
+        let groups: Vec<_> = problem
+            .groups
+            .iter()
+            .map(|group| (group.name.as_str(), group.score, group.tests.len()))
+            .collect();
+        assert_eq!(groups, vec![("secret", None, 1), ("big", Some(40.0), 1)]);
+        assert_eq!(problem.warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_interactive() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("problem.yaml"),
+            "validation: custom interactive\n",
+        )
+        .unwrap();
+        assert!(IcpcProblem::parse(dir.path(), "guess").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Import of the tasks prepared with other systems, converting them to the IOI format.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+use zip::ZipArchive;
+
+use task_maker_format::cwriteln;
+use task_maker_format::ui::{StdoutPrinter, YELLOW};
+
+use crate::tools::import::icpc::IcpcProblem;
+use crate::tools::import::polygon::PolygonProblem;
+
+mod icpc;
+mod polygon;
+
+#[derive(Parser, Debug, Clone)]
+pub struct ImportOpt {
+    /// The format of the package to import: `polygon`, or `icpc` for the problem packages of
+    /// Kattis and DOMjudge.
+    #[clap(long, default_value = "polygon")]
+    pub format: ImportFormat,
+
+    /// The package to import: a zip file, or the directory where it's extracted.
+    pub package: PathBuf,
+
+    /// Directory where to create the task, it must not exist or be empty.
+    ///
+    /// When not specified, a directory named after the task is created in the current directory.
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+/// A format of the packages that can be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// A package of Codeforces Polygon, with its problem.xml.
+    Polygon,
+    /// A problem package of the ICPC judges, like Kattis and DOMjudge, with its problem.yaml.
+    Icpc,
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ImportFormat, Self::Err> {
+        match s {
+            "polygon" => Ok(ImportFormat::Polygon),
+            "icpc" => Ok(ImportFormat::Icpc),
+            _ => Err(format!(
+                "Unknown import format: {} (expected polygon or icpc)",
+                s
+            )),
+        }
+    }
+}
+
+pub fn main_import(opt: ImportOpt) -> Result<(), Error> {
+    // the zip files are extracted in a temporary directory
+    let tempdir;
+    let (package_dir, package_name) = if opt.package.is_dir() {
+        let package_dir = opt
+            .package
+            .canonicalize()
+            .with_context(|| format!("Failed to read {}", opt.package.display()))?;
+        let name = package_dir.file_name().map(|name| name.to_os_string());
+        (package_dir, name)
+    } else {
+        tempdir = tempfile::TempDir::new().context("Failed to create a temporary directory")?;
+        extract_zip(&opt.package, tempdir.path())?;
+        // the package may be inside a directory of the zip file, named after the problem
+        match single_directory(tempdir.path())? {
+            Some(dir) => {
+                let name = dir.file_name().map(|name| name.to_os_string());
+                (dir, name)
+            }
+            None => (
+                tempdir.path().to_path_buf(),
+                opt.package.file_stem().map(|name| name.to_os_string()),
+            ),
+        }
+    };
+    let package_name = package_name
+        .context("Invalid path of the package")?
+        .to_string_lossy()
+        .to_string();
+
+    let mut problem: Box<dyn ImportedProblem> = match opt.format {
+        ImportFormat::Polygon => Box::new(PolygonProblem::parse(&package_dir)?),
+        ImportFormat::Icpc => Box::new(IcpcProblem::parse(&package_dir, &package_name)?),
+    };
+    let task_dir = opt.output.unwrap_or_else(|| PathBuf::from(problem.name()));
+    if task_dir.exists() {
+        let mut content = std::fs::read_dir(&task_dir)
+            .with_context(|| format!("Failed to read {}", task_dir.display()))?;
+        if content.next().is_some() {
+            bail!("{} already exists and it's not empty", task_dir.display());
+        }
+    }
+    std::fs::create_dir_all(task_dir.join("gen"))
+        .with_context(|| format!("Failed to create {}", task_dir.display()))?;
+    problem.write(&package_dir, &task_dir)?;
+
+    println!("Imported {} in {}", problem.name(), task_dir.display());
+    let mut printer = StdoutPrinter::default();
+    for warning in problem.warnings() {
+        cwriteln!(printer, YELLOW, "Warning: {}", warning);
+    }
+    Ok(())
+}
+
+/// A problem parsed from a package, that can be written as a task in the IOI format.
+trait ImportedProblem {
+    /// The short name of the problem.
+    fn name(&self) -> &str;
+
+    /// The features of the package that are not imported.
+    fn warnings(&self) -> &[String];
+
+    /// Write the task in the IOI format inside `task_dir`, copying the files from the package.
+    fn write(&mut self, package_dir: &Path, task_dir: &Path) -> Result<(), Error>;
+}
+
+impl ImportedProblem for PolygonProblem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    fn write(&mut self, package_dir: &Path, task_dir: &Path) -> Result<(), Error> {
+        PolygonProblem::write(self, package_dir, task_dir)
+    }
+}
+
+impl ImportedProblem for IcpcProblem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    fn write(&mut self, package_dir: &Path, task_dir: &Path) -> Result<(), Error> {
+        IcpcProblem::write(self, package_dir, task_dir)
+    }
+}
+
+/// Copy a file of the package in the task, creating the directory containing it.
+fn copy_file(from: &Path, to: &Path) -> Result<(), Error> {
+    let parent = to.parent().context("Invalid destination path")?;
+    std::fs::create_dir_all(parent)
+        .with_context(|| format!("Failed to create {}", parent.display()))?;
+    std::fs::copy(from, to)
+        .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+    Ok(())
+}
+
+/// The only entry of a directory, if it's a directory.
+fn single_directory(dir: &Path) -> Result<Option<PathBuf>, Error> {
+    let mut entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .collect::<Result<Vec<_>, _>>()?;
+    match entries.pop() {
+        Some(entry) if entries.is_empty() && entry.path().is_dir() => Ok(Some(entry.path())),
+        _ => Ok(None),
+    }
+}
+
+/// Extract a zip file inside a directory.
+fn extract_zip(path: &Path, dest: &Path) -> Result<(), Error> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut archive =
+        ZipArchive::new(file).with_context(|| format!("Invalid zip file {}", path.display()))?;
+    archive
+        .extract(dest)
+        .with_context(|| format!("Failed to extract {}", path.display()))
+}
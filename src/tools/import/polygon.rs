@@ -0,0 +1,763 @@
+//! Conversion of the packages of Codeforces Polygon to the IOI format.
+//!
+//! The tests are generated with a `gen/cases.gen` that runs the generators of the package, and the
+//! groups of tests become the subtasks. The testlib checker and validator are wrapped in programs
+//! that speak the protocol of task-maker.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Error};
+use roxmltree::{Document, Node};
+use walkdir::WalkDir;
+
+use crate::tools::import::copy_file;
+use crate::tools::TaskYamlOrig;
+
+/// The checker of the task, running the testlib checker of the package.
+const CHECKER_WRAPPER: &str = include_str!("templates/checker.cpp");
+/// The validator of the task, running the testlib validator of the package.
+const VALIDATOR_WRAPPER: &str = include_str!("templates/validator.cpp");
+
+/// How a test of the package is built.
+#[derive(Debug, Clone)]
+enum TestMethod {
+    /// The input file is inside the package.
+    Manual,
+    /// The input file is generated running a generator of the package with some arguments.
+    Generated { generator: String, args: String },
+}
+
+/// A test of the package.
+#[derive(Debug, Clone)]
+struct PolygonTest {
+    /// The 1-based index of the test, used in the name of its files.
+    index: usize,
+    /// How the test is built.
+    method: TestMethod,
+    /// The group of the test, if the tests are grouped.
+    group: Option<String>,
+    /// The points of the test, when the points are assigned to each test.
+    points: Option<f64>,
+}
+
+/// A group of tests of the package.
+#[derive(Debug, Clone)]
+struct PolygonGroup {
+    /// The name of the group.
+    name: String,
+    /// The points of the group.
+    points: Option<f64>,
+    /// Whether the points are given only if all the tests are correct, instead of for each test.
+    complete: bool,
+    /// The groups that must be solved for getting the points of this one.
+    dependencies: Vec<String>,
+}
+
+/// A statement of the package.
+#[derive(Debug, Clone)]
+struct PolygonStatement {
+    /// The language of the statement, like `english`.
+    language: String,
+    /// The path of the statement inside the package.
+    path: PathBuf,
+    /// Whether the statement is a PDF, otherwise it's the LaTeX source.
+    pdf: bool,
+}
+
+/// The content of the `problem.xml` of a package.
+#[derive(Debug, Clone)]
+pub struct PolygonProblem {
+    /// The short name of the problem.
+    pub name: String,
+    /// The title of the problem, in English if available.
+    pub title: String,
+    /// The time limit, in seconds.
+    time_limit: f64,
+    /// The memory limit, in MiB.
+    memory_limit: u64,
+    /// The input file of the solutions, empty for stdin.
+    infile: String,
+    /// The output file of the solutions, empty for stdout.
+    outfile: String,
+    /// The `printf`-like pattern of the paths of the input files inside the package.
+    input_pattern: String,
+    /// The tests of the main testset.
+    tests: Vec<PolygonTest>,
+    /// The groups of the tests of the main testset.
+    groups: Vec<PolygonGroup>,
+    /// The resources, like `testlib.h`.
+    resources: Vec<PathBuf>,
+    /// The sources of the executables, usually the generators.
+    executables: Vec<PathBuf>,
+    /// The source of the checker.
+    checker: Option<PathBuf>,
+    /// The source of the validator.
+    validator: Option<PathBuf>,
+    /// The sources of the solutions, with their tag (like `main` or `wrong-answer`).
+    solutions: Vec<(PathBuf, String)>,
+    /// The statements of the problem.
+    statements: Vec<PolygonStatement>,
+    /// The features of the package that are not imported.
+    pub warnings: Vec<String>,
+}
+
+/// A subtask of the imported task.
+struct ImportedSubtask<'a> {
+    /// The name of the subtask, the name of its group.
+    name: Option<String>,
+    /// The score of the subtask.
+    score: f64,
+    /// The names of the subtasks it depends on.
+    dependencies: Vec<String>,
+    /// The tests of the subtask.
+    tests: Vec<&'a PolygonTest>,
+}
+
+/// The first child element of a node with the given name.
+fn child<'a, 'i>(node: Node<'a, 'i>, name: &'a str) -> Option<Node<'a, 'i>> {
+    children(node, name).next()
+}
+
+/// The child elements of a node with the given name.
+fn children<'a, 'i: 'a>(
+    node: Node<'a, 'i>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'i>> + 'a {
+    node.children()
+        .filter(move |child| child.is_element() && child.has_tag_name(name))
+}
+
+/// The path of the `<source>` of a node, like an executable or a checker.
+fn source_path(node: Node) -> Option<PathBuf> {
+    child(node, "source")
+        .and_then(|source| source.attribute("path"))
+        .map(PathBuf::from)
+}
+
+/// Parse an optional numeric attribute of a node.
+fn parse_attribute<T: std::str::FromStr>(node: Node, name: &str) -> Result<Option<T>, Error> {
+    node.attribute(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| anyhow!("Invalid value of {}: {:?}", name, value))
+        })
+        .transpose()
+}
+
+/// Parse the text of a child element of a node.
+fn parse_child<T: std::str::FromStr>(node: Node, name: &str) -> Result<T, Error> {
+    let text = child(node, name)
+        .and_then(|child| child.text())
+        .with_context(|| format!("Missing <{}> in problem.xml", name))?;
+    text.trim()
+        .parse()
+        .map_err(|_| anyhow!("Invalid value of <{}>: {:?}", name, text))
+}
+
+/// Replace the `%d` (with an optional width, like `%02d`) of a Polygon path pattern with a number.
+fn format_pattern(pattern: &str, index: usize) -> Result<String, Error> {
+    let start = pattern
+        .find('%')
+        .with_context(|| format!("Invalid path pattern: {}", pattern))?;
+    let end = pattern[start..]
+        .find('d')
+        .map(|end| start + end)
+        .with_context(|| format!("Invalid path pattern: {}", pattern))?;
+    let spec = &pattern[start + 1..end];
+    let width: usize = if spec.is_empty() {
+        0
+    } else {
+        spec.parse()
+            .with_context(|| format!("Invalid path pattern: {}", pattern))?
+    };
+    let number = if spec.starts_with('0') {
+        format!("{:0width$}", index, width = width)
+    } else {
+        format!("{:width$}", index, width = width)
+    };
+    Ok(format!(
+        "{}{}{}",
+        &pattern[..start],
+        number,
+        &pattern[end + 1..]
+    ))
+}
+
+impl PolygonProblem {
+    /// Parse the `problem.xml` of a package extracted in a directory.
+    pub fn parse(package_dir: &Path) -> Result<PolygonProblem, Error> {
+        let path = package_dir.join("problem.xml");
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let document = Document::parse(&content).context("Invalid problem.xml")?;
+        let root = document.root_element();
+        let mut warnings = vec![];
+
+        let name = root
+            .attribute("short-name")
+            .context("Missing short-name of the problem")?
+            .to_string();
+        let names = child(root, "names").map(|names| children(names, "name").collect::<Vec<_>>());
+        let title = names
+            .unwrap_or_default()
+            .iter()
+            .min_by_key(|name| name.attribute("language") != Some("english"))
+            .and_then(|name| name.attribute("value"))
+            .unwrap_or(&name)
+            .to_string();
+
+        let judging = child(root, "judging").context("Missing <judging> in problem.xml")?;
+        let infile = judging.attribute("input-file").unwrap_or_default();
+        let outfile = judging.attribute("output-file").unwrap_or_default();
+        let testset = children(judging, "testset")
+            .find(|testset| testset.attribute("name") == Some("tests"))
+            .or_else(|| child(judging, "testset"))
+            .context("No testset in problem.xml")?;
+        let time_limit: f64 = parse_child(testset, "time-limit")?;
+        let memory_limit: u64 = parse_child(testset, "memory-limit")?;
+        let input_pattern: String = parse_child(testset, "input-path-pattern")?;
+
+        let mut tests = vec![];
+        if let Some(node) = child(testset, "tests") {
+            for (index, test) in children(node, "test").enumerate() {
+                let method = match test.attribute("method") {
+                    Some("generated") => {
+                        let cmd = test
+                            .attribute("cmd")
+                            .context("Generated test without cmd")?;
+                        let (generator, args) = cmd.trim().split_once(' ').unwrap_or((cmd, ""));
+                        TestMethod::Generated {
+                            generator: generator.to_string(),
+                            args: args.trim().to_string(),
+                        }
+                    }
+                    _ => TestMethod::Manual,
+                };
+                tests.push(PolygonTest {
+                    index: index + 1,
+                    method,
+                    group: test.attribute("group").map(String::from),
+                    points: parse_attribute(test, "points")?,
+                });
+            }
+        }
+        let mut groups = vec![];
+        if let Some(node) = child(testset, "groups") {
+            for group in children(node, "group") {
+                let dependencies = child(group, "dependencies")
+                    .map(|deps| {
+                        children(deps, "dependency")
+                            .filter_map(|dep| dep.attribute("group"))
+                            .map(String::from)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                groups.push(PolygonGroup {
+                    name: group
+                        .attribute("name")
+                        .context("Group without name")?
+                        .to_string(),
+                    points: parse_attribute(group, "points")?,
+                    complete: group.attribute("points-policy") != Some("each-test"),
+                    dependencies,
+                });
+            }
+        }
+
+        let files = child(root, "files");
+        let resources = files
+            .and_then(|files| child(files, "resources"))
+            .map(|resources| {
+                children(resources, "file")
+                    .filter_map(|file| file.attribute("path"))
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let executables = files
+            .and_then(|files| child(files, "executables"))
+            .map(|executables| {
+                children(executables, "executable")
+                    .filter_map(source_path)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let assets = child(root, "assets").context("Missing <assets> in problem.xml")?;
+        if child(assets, "interactor").is_some() {
+            bail!("Interactive problems cannot be imported");
+        }
+        let checker = child(assets, "checker").and_then(source_path);
+        let mut validators: Vec<_> = child(assets, "validators")
+            .map(|validators| {
+                children(validators, "validator")
+                    .filter_map(source_path)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if validators.len() > 1 {
+            warnings.push(format!(
+                "Only the first validator is imported, ignoring {} more",
+                validators.len() - 1
+            ));
+        }
+        let validator = (!validators.is_empty()).then(|| validators.remove(0));
+        let solutions = child(assets, "solutions")
+            .map(|solutions| {
+                children(solutions, "solution")
+                    .filter_map(|solution| {
+                        let tag = solution.attribute("tag").unwrap_or_default();
+                        Some((source_path(solution)?, tag.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let statements = child(root, "statements")
+            .map(|statements| {
+                children(statements, "statement")
+                    .filter_map(|statement| {
+                        let pdf = match statement.attribute("type") {
+                            Some("application/pdf") => true,
+                            Some("application/x-tex") => false,
+                            _ => return None,
+                        };
+                        Some(PolygonStatement {
+                            language: statement.attribute("language")?.to_string(),
+                            path: statement.attribute("path")?.into(),
+                            pdf,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(PolygonProblem {
+            name,
+            title,
+            time_limit: time_limit / 1000.0,
+            memory_limit: memory_limit / 1024 / 1024,
+            infile: infile.to_string(),
+            outfile: outfile.to_string(),
+            input_pattern,
+            tests,
+            groups,
+            resources,
+            executables,
+            checker,
+            validator,
+            solutions,
+            statements,
+            warnings,
+        })
+    }
+
+    /// The subtasks of the task: one for each group or, if the tests are not grouped, a single one.
+    fn subtasks(&self) -> Result<Vec<ImportedSubtask<'_>>, Error> {
+        let points = |tests: &[&PolygonTest]| tests.iter().filter_map(|test| test.points).sum();
+        if self.tests.iter().all(|test| test.group.is_none()) {
+            let tests: Vec<_> = self.tests.iter().collect();
+            let score: f64 = points(&tests);
+            return Ok(vec![ImportedSubtask {
+                name: None,
+                score: if score > 0.0 { score } else { 100.0 },
+                dependencies: vec![],
+                tests,
+            }]);
+        }
+        let mut groups = self.groups.clone();
+        for test in &self.tests {
+            let Some(group) = &test.group else {
+                bail!("Test {} has no group, but the other tests do", test.index);
+            };
+            if !groups.iter().any(|g| &g.name == group) {
+                groups.push(PolygonGroup {
+                    name: group.clone(),
+                    points: None,
+                    complete: false,
+                    dependencies: vec![],
+                });
+            }
+        }
+        Ok(groups
+            .into_iter()
+            .map(|group| {
+                let tests: Vec<_> = self
+                    .tests
+                    .iter()
+                    .filter(|test| test.group.as_ref() == Some(&group.name))
+                    .collect();
+                let score = match group.points {
+                    Some(score) if group.complete => score,
+                    _ => points(&tests),
+                };
+                ImportedSubtask {
+                    name: Some(group.name),
+                    score,
+                    dependencies: group.dependencies,
+                    tests,
+                }
+            })
+            .collect())
+    }
+
+    /// Whether the score of a subtask is the sum of the scores of its tests, instead of the minimum.
+    fn sum_scores(&self) -> bool {
+        if self.groups.is_empty() {
+            self.tests.iter().any(|test| test.points.is_some())
+        } else {
+            self.groups.iter().all(|group| !group.complete)
+        }
+    }
+
+    /// Write the task in the IOI format inside `task_dir`, copying the files from the package.
+    pub fn write(&mut self, package_dir: &Path, task_dir: &Path) -> Result<(), Error> {
+        let copy = |from: &Path, to: &str| copy_file(&package_dir.join(from), &task_dir.join(to));
+        let file_name = |path: &Path| -> Result<String, Error> {
+            Ok(path
+                .file_name()
+                .with_context(|| format!("Invalid path: {}", path.display()))?
+                .to_string_lossy()
+                .to_string())
+        };
+        let is_cpp = |path: &Path| path.extension().is_some_and(|ext| ext == "cpp");
+
+        // the headers, like testlib.h, are needed by the generators, the validator and the checker
+        for resource in &self.resources {
+            if resource.extension().is_some_and(|ext| ext == "h") {
+                let name = file_name(resource)?;
+                copy(resource, &format!("gen/{}", name))?;
+                if self.checker.is_some() {
+                    copy(resource, &format!("check/{}", name))?;
+                }
+            }
+        }
+        let mut generators = HashMap::new();
+        for executable in &self.executables {
+            let name = file_name(executable)?;
+            copy(executable, &format!("gen/{}", name))?;
+            if let Some(stem) = executable.file_stem() {
+                generators.insert(stem.to_string_lossy().to_string(), name);
+            }
+        }
+
+        let mut validator_args = None;
+        if let Some(validator) = &self.validator {
+            if is_cpp(validator) {
+                copy(validator, "gen/polygon_validator.cpp")?;
+                std::fs::write(task_dir.join("gen/validator.cpp"), VALIDATOR_WRAPPER)
+                    .context("Failed to write the validator")?;
+                validator_args = Some(if self.groups.is_empty() {
+                    "$INPUT"
+                } else {
+                    "$INPUT $ST_NAME"
+                });
+            } else {
+                self.warnings.push(format!(
+                    "The validator {} is not in C++, it's not imported",
+                    validator.display()
+                ));
+            }
+        }
+        if let Some(checker) = &self.checker {
+            if is_cpp(checker) {
+                copy(checker, "check/polygon_checker.cpp")?;
+                std::fs::write(task_dir.join("check/checker.cpp"), CHECKER_WRAPPER)
+                    .context("Failed to write the checker")?;
+            } else {
+                self.warnings.push(format!(
+                    "The checker {} is not in C++, the outputs are compared ignoring whitespaces",
+                    checker.display()
+                ));
+            }
+        }
+
+        // the tests, grouped in subtasks
+        let mut cases_gen = format!(
+            "# Generated by task-maker-tools import from the Polygon package of {}\n",
+            self.name
+        );
+        let mut used_generators: Vec<_> = self
+            .tests
+            .iter()
+            .filter_map(|test| match &test.method {
+                TestMethod::Generated { generator, .. } => Some(generator),
+                TestMethod::Manual => None,
+            })
+            .collect();
+        used_generators.sort();
+        used_generators.dedup();
+        for generator in used_generators {
+            let path = generators
+                .get(generator)
+                .with_context(|| format!("Unknown generator: {}", generator))?;
+            writeln!(cases_gen, ": GEN {} gen/{}", generator, path)?;
+        }
+        if let Some(args) = validator_args {
+            writeln!(cases_gen, ": VAL default gen/validator.cpp {}", args)?;
+        }
+        let subtasks = self.subtasks()?;
+        let mut unequal_points = false;
+        for subtask in &subtasks {
+            writeln!(cases_gen)?;
+            match &subtask.name {
+                Some(name) => writeln!(cases_gen, ": SUBTASK {} {}", subtask.score, name)?,
+                None => writeln!(cases_gen, ": SUBTASK {}", subtask.score)?,
+            }
+            if !subtask.dependencies.is_empty() {
+                writeln!(cases_gen, ": STDEP {}", subtask.dependencies.join(" "))?;
+            }
+            for test in &subtask.tests {
+                if test.points != subtask.tests[0].points {
+                    unequal_points = true;
+                }
+                match &test.method {
+                    TestMethod::Manual => {
+                        let input = format_pattern(&self.input_pattern, test.index)?;
+                        let dest = format!("gen/manual/{:02}.txt", test.index);
+                        copy(Path::new(&input), &dest)?;
+                        writeln!(cases_gen, ": COPY {}", dest)?;
+                    }
+                    TestMethod::Generated { generator, args } => {
+                        if args.contains('#') {
+                            bail!("The arguments of test {} contain a #", test.index);
+                        }
+                        writeln!(cases_gen, ": RUN {} {}", generator, args)?;
+                    }
+                }
+            }
+        }
+        if unequal_points && self.sum_scores() {
+            self.warnings.push(
+                "The tests of a subtask have different points, the score of the subtask is \
+                 split evenly between them"
+                    .into(),
+            );
+        }
+        std::fs::write(task_dir.join("gen/cases.gen"), cases_gen)
+            .context("Failed to write gen/cases.gen")?;
+
+        // the main solution generates the correct outputs
+        for (solution, tag) in &self.solutions {
+            let name = file_name(solution)?;
+            let dest = match solution.extension() {
+                Some(ext) if tag == "main" => format!("sol/solution.{}", ext.to_string_lossy()),
+                _ if name.starts_with("solution.") => format!("sol/{}_{}", tag, name),
+                _ => format!("sol/{}", name),
+            };
+            copy(solution, &dest)?;
+        }
+        if !self.solutions.iter().any(|(_, tag)| tag == "main") {
+            self.warnings
+                .push("There is no main solution, the outputs cannot be generated".into());
+        }
+
+        for statement in &self.statements {
+            if statement.pdf {
+                copy(
+                    &statement.path,
+                    &format!("statement/{}.pdf", statement.language),
+                )?;
+                continue;
+            }
+            // the LaTeX sources of Polygon are not compiled by task-maker, they are kept aside
+            let Some(source_dir) = statement.path.parent() else {
+                continue;
+            };
+            for entry in WalkDir::new(package_dir.join(source_dir)) {
+                let entry = entry?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let relative = entry.path().strip_prefix(package_dir.join(source_dir))?;
+                copy(
+                    entry.path(),
+                    &format!(
+                        "statement/polygon/{}/{}",
+                        statement.language,
+                        relative.display()
+                    ),
+                )?;
+            }
+        }
+
+        let task_yaml = TaskYamlOrig {
+            name: &self.name,
+            title: &self.title,
+            time_limit: self.time_limit,
+            memory_limit: self.memory_limit,
+            infile: &self.infile,
+            outfile: &self.outfile,
+            score_type: self.sum_scores().then_some("sum"),
+        };
+        std::fs::write(task_dir.join("task.yaml.orig"), task_yaml.to_yaml()?)
+            .context("Failed to write task.yaml.orig")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a package whose main testset contains `tests` and `groups`.
+    fn parse(tests: &str, groups: &str) -> PolygonProblem {
+        let dir = tempfile::TempDir::new().unwrap();
+        let xml = format!(
+            r#"<problem short-name="sum">
+                <judging input-file="" output-file="">
+                    <testset name="pretests">
+                        <time-limit>500</time-limit>
+                        <memory-limit>67108864</memory-limit>
+                        <input-path-pattern>pretests/%d</input-path-pattern>
+                    </testset>
+                    <testset name="tests">
+                        <time-limit>2000</time-limit>
+                        <memory-limit>268435456</memory-limit>
+                        <input-path-pattern>tests/%02d</input-path-pattern>
+                        <tests>{}</tests>
+                        <groups>{}</groups>
+                    </testset>
+                </judging>
+                <assets/>
+            </problem>"#,
+            tests, groups
+        );
+        std::fs::write(dir.path().join("problem.xml"), xml).unwrap();
+        PolygonProblem::parse(dir.path()).unwrap()
+    }
+
+    /// The names, the scores and the number of tests of the subtasks.
+    fn subtasks(problem: &PolygonProblem) -> Vec<(Option<String>, f64, usize)> {
+        problem
+            .subtasks()
+            .unwrap()
+            .into_iter()
+            .map(|st| (st.name, st.score, st.tests.len()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let problem = parse(
+            r#"<test method="manual"/><test method="generated" cmd="gen  1 2 "/>"#,
+            "",
+        );
+        assert_eq!(problem.name, "sum");
+        assert_eq!(problem.title, "sum");
+        assert_eq!(problem.time_limit, 2.0);
+        assert_eq!(problem.memory_limit, 256);
+        assert_eq!(problem.input_pattern, "tests/%02d");
+        assert_eq!(problem.tests.len(), 2);
+        assert!(matches!(problem.tests[0].method, TestMethod::Manual));
+        match &problem.tests[1].method {
+            TestMethod::Generated { generator, args } => {
+                assert_eq!(generator, "gen");
+                assert_eq!(args, "1 2");
+            }
+            TestMethod::Manual => panic!("Expecting a generated test"),
+        }
+        assert_eq!(problem.tests[1].index, 2);
+        assert!(problem.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_interactive() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let xml = r#"<problem short-name="guess">
+            <judging>
+                <testset name="tests">
+                    <time-limit>1000</time-limit>
+                    <memory-limit>268435456</memory-limit>
+                    <input-path-pattern>tests/%02d</input-path-pattern>
+                </testset>
+            </judging>
+            <assets><interactor><source path="files/interactor.cpp"/></interactor></assets>
+        </problem>"#;
+        std::fs::write(dir.path().join("problem.xml"), xml).unwrap();
+        assert!(PolygonProblem::parse(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_subtasks_without_groups() {
+        let problem = parse(r#"<test method="manual"/><test method="manual"/>"#, "");
+        assert_eq!(subtasks(&problem), vec![(None, 100.0, 2)]);
+        assert!(!problem.sum_scores());
+    }
+
+    #[test]
+    fn test_subtasks_points_per_test() {
+        let problem = parse(
+            r#"<test method="manual" points="30"/><test method="manual" points="20"/>"#,
+            "",
+        );
+        assert_eq!(subtasks(&problem), vec![(None, 50.0, 2)]);
+        assert!(problem.sum_scores());
+    }
+
+    #[test]
+    fn test_subtasks_point_groups() {
+        let problem = parse(
+            r#"<test method="manual" group="1" points="0"/>
+               <test method="manual" group="2" points="10"/>
+               <test method="manual" group="2" points="20"/>"#,
+            r#"<group name="1" points="0" points-policy="complete-group"/>
+               <group name="2" points="70" points-policy="complete-group">
+                   <dependencies><dependency group="1"/></dependencies>
+               </group>"#,
+        );
+        assert_eq!(
+            subtasks(&problem),
+            vec![(Some("1".into()), 0.0, 1), (Some("2".into()), 70.0, 2)]
+        );
+        assert_eq!(problem.subtasks().unwrap()[1].dependencies, vec!["1"]);
+        assert!(!problem.sum_scores());
+    }
+
+    #[test]
+    fn test_subtasks_each_test_groups() {
+        let problem = parse(
+            r#"<test method="manual" group="1" points="10"/>
+               <test method="manual" group="1" points="15"/>"#,
+            r#"<group name="1" points="70" points-policy="each-test"/>"#,
+        );
+        // the points of the group are ignored, each test gives its own
+        assert_eq!(subtasks(&problem), vec![(Some("1".into()), 25.0, 2)]);
+        assert!(problem.sum_scores());
+    }
+
+    #[test]
+    fn test_subtasks_missing_scores() {
+        let problem = parse(
+            r#"<test method="manual" group="1"/><test method="manual" group="2" points="5"/>"#,
+            r#"<group name="1" points-policy="complete-group"/>"#,
+        );
+        // group 2 is not declared, its score is the sum of the points of its tests
+        assert_eq!(
+            subtasks(&problem),
+            vec![(Some("1".into()), 0.0, 1), (Some("2".into()), 5.0, 1)]
+        );
+    }
+
+    #[test]
+    fn test_subtasks_partially_grouped() {
+        let problem = parse(
+            r#"<test method="manual" group="1"/><test method="manual"/>"#,
+            "",
+        );
+        assert!(problem.subtasks().is_err());
+    }
+
+    #[test]
+    fn test_format_pattern() {
+        assert_eq!(format_pattern("tests/%d", 7).unwrap(), "tests/7");
+        assert_eq!(format_pattern("tests/%02d", 7).unwrap(), "tests/07");
+        assert_eq!(format_pattern("tests/%02d", 123).unwrap(), "tests/123");
+        assert_eq!(format_pattern("tests/%3d.in", 7).unwrap(), "tests/  7.in");
+        assert!(format_pattern("tests/01", 1).is_err());
+        assert!(format_pattern("tests/%xd", 1).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+// Generated by task-maker-tools import: runs the testlib checker of the Polygon package, found in
+// polygon_checker.cpp, converting its verdict to the score printed for task-maker.
+#include <cstdio>
+#include <cstdlib>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#include "testlib.h"
+
+#define main polygon_checker_main
+#include "polygon_checker.cpp"
+#undef main
+
+int main(int argc, char* argv[]) {
+    if (argc != 4) {
+        fprintf(stderr, "Usage: %s input correct_output test_output\n", argv[0]);
+        return 1;
+    }
+    // testlib exits as soon as the verdict is known, so the checker runs in a child process
+    pid_t pid = fork();
+    if (pid == 0) {
+        // testlib wants the output of the contestant before the correct one
+        char* args[] = {argv[0], argv[1], argv[3], argv[2], nullptr};
+        exit(polygon_checker_main(4, args));
+    }
+    int status;
+    if (pid < 0 || waitpid(pid, &status, 0) < 0 || !WIFEXITED(status)) {
+        fprintf(stderr, "The checker crashed\n");
+        return 1;
+    }
+    switch (WEXITSTATUS(status)) {
+        case 0:  // accepted
+            printf("1.0\n");
+            return 0;
+        case 1:  // wrong answer
+        case 2:  // presentation error
+            printf("0.0\n");
+            return 0;
+        default:  // failure of the checker, or a verdict without a score
+            return 1;
+    }
+}
@@ -0,0 +1,65 @@
+// Generated by task-maker-tools import: runs the output validator of the ICPC package, found in
+// icpc_output_validator.cpp, converting its verdict to the score printed for task-maker.
+#include <cstdio>
+#include <cstdlib>
+#include <filesystem>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define main icpc_output_validator_main
+#include "icpc_output_validator.cpp"
+#undef main
+
+// the programs of the package may declare main with or without its arguments
+static int call_main(int (*program)(), int, char**) { return program(); }
+static int call_main(int (*program)(int, char**), int argc, char** argv) {
+    return program(argc, argv);
+}
+
+int main(int argc, char* argv[]) {
+    if (argc != 4) {
+        fprintf(stderr, "Usage: %s input correct_output test_output\n", argv[0]);
+        return 1;
+    }
+    // the output validator writes its messages in a feedback directory
+    char feedback_dir[] = "feedbackXXXXXX";
+    if (!mkdtemp(feedback_dir)) {
+        fprintf(stderr, "Cannot create the feedback directory\n");
+        return 1;
+    }
+    // the output validator exits as soon as the verdict is known, so it runs in a child process
+    pid_t pid = fork();
+    if (pid == 0) {
+        // the output of the contestant is read from stdin
+        if (!freopen(argv[3], "r", stdin)) {
+            fprintf(stderr, "Cannot open %s\n", argv[3]);
+            exit(1);
+        }
+        char* args[] = {argv[0], argv[1], argv[2], feedback_dir, nullptr};
+        exit(call_main(icpc_output_validator_main, 4, args));
+    }
+    int status;
+    if (pid < 0 || waitpid(pid, &status, 0) < 0 || !WIFEXITED(status)) {
+        fprintf(stderr, "The checker crashed\n");
+        return 1;
+    }
+    // the message for the contestant is forwarded to task-maker
+    char path[sizeof(feedback_dir) + 32];
+    snprintf(path, sizeof(path), "%s/judgemessage.txt", feedback_dir);
+    if (FILE* message = fopen(path, "r")) {
+        int c;
+        while ((c = fgetc(message)) != EOF) fputc(c, stderr);
+        fclose(message);
+    }
+    std::filesystem::remove_all(feedback_dir);
+    switch (WEXITSTATUS(status)) {
+        case 42:  // accepted
+            printf("1.0\n");
+            return 0;
+        case 43:  // wrong answer
+            printf("0.0\n");
+            return 0;
+        default:  // failure of the output validator
+            return 1;
+    }
+}
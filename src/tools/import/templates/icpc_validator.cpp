@@ -0,0 +1,44 @@
+// Generated by task-maker-tools import: runs the input validator of the ICPC package, found in
+// icpc_validator.cpp, on the input file passed by task-maker.
+#include <cstdio>
+#include <cstdlib>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define main icpc_validator_main
+#include "icpc_validator.cpp"
+#undef main
+
+// the programs of the package may declare main with or without its arguments
+static int call_main(int (*program)(), int, char**) { return program(); }
+static int call_main(int (*program)(int, char**), int argc, char** argv) {
+    return program(argc, argv);
+}
+
+int main(int argc, char* argv[]) {
+    if (argc < 2) {
+        fprintf(stderr, "Usage: %s input\n", argv[0]);
+        return 1;
+    }
+    // the validator may exit as soon as the verdict is known, so it runs in a child process
+    pid_t pid = fork();
+    if (pid == 0) {
+        // the validators of the ICPC format read the input file from stdin
+        if (!freopen(argv[1], "r", stdin)) {
+            fprintf(stderr, "Cannot open %s\n", argv[1]);
+            exit(1);
+        }
+        char* args[] = {argv[0], nullptr};
+        exit(call_main(icpc_validator_main, 1, args));
+    }
+    int status;
+    if (pid < 0 || waitpid(pid, &status, 0) < 0 || !WIFEXITED(status)) {
+        fprintf(stderr, "The validator crashed\n");
+        return 1;
+    }
+    if (WEXITSTATUS(status) != 42) {
+        fprintf(stderr, "The input file is not valid\n");
+        return 1;
+    }
+    return 0;
+}
@@ -0,0 +1,25 @@
+// Generated by task-maker-tools import: runs the testlib validator of the Polygon package, found
+// in polygon_validator.cpp, on the input file and the group passed by task-maker.
+#include <cstdio>
+#include <vector>
+
+#include "testlib.h"
+
+#define main polygon_validator_main
+#include "polygon_validator.cpp"
+#undef main
+
+int main(int argc, char* argv[]) {
+    // testlib reads the input file from stdin
+    if (argc < 2 || !freopen(argv[1], "r", stdin)) {
+        fprintf(stderr, "Usage: %s input [group]\n", argv[0]);
+        return 1;
+    }
+    std::vector<char*> args = {argv[0]};
+    if (argc >= 3 && argv[2][0] != '\0') {
+        args.push_back(const_cast<char*>("--group"));
+        args.push_back(argv[2]);
+    }
+    args.push_back(nullptr);
+    return polygon_validator_main(args.size() - 1, args.data());
+}
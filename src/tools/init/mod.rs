@@ -0,0 +1,278 @@
+//! Scaffolding of new tasks, with a working example of every file the format needs.
+
+use std::fmt::Display;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Error};
+use clap::Parser;
+
+use crate::tools::yaml_quote;
+
+#[derive(Parser, Debug, Clone)]
+pub struct InitOpt {
+    /// Directory where to create the task, it must not exist or be empty.
+    ///
+    /// When not specified, a directory named after the task is created in the current directory.
+    pub task_dir: Option<PathBuf>,
+
+    /// Format of the task: ioi or terry.
+    #[clap(long)]
+    pub format: Option<TaskFormat>,
+
+    /// Short name of the task.
+    ///
+    /// When not specified, the name of the task directory is used.
+    #[clap(long)]
+    pub name: Option<String>,
+
+    /// Title of the task.
+    #[clap(long)]
+    pub title: Option<String>,
+
+    /// Language of the example generator, validator, checker and solution: cpp or py.
+    #[clap(long)]
+    pub language: Option<TemplateLanguage>,
+
+    /// Don't ask anything, use the default values for the options not specified.
+    #[clap(short = 'y', long)]
+    pub yes: bool,
+}
+
+/// The format of the task to create.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskFormat {
+    /// A task in the IOI format, with subtasks and a GEN file.
+    IOI,
+    /// A task in the Terry format, where the solutions run on the machine of the contestant.
+    Terry,
+}
+
+/// The language of the example programs of the task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateLanguage {
+    /// C++.
+    Cpp,
+    /// Python 3.
+    Python,
+}
+
+/// The information needed for creating a new task.
+#[derive(Debug, Clone)]
+pub struct TaskTemplate {
+    /// The format of the task.
+    pub format: TaskFormat,
+    /// The short name of the task.
+    pub name: String,
+    /// The title of the task.
+    pub title: String,
+    /// The language of the example programs.
+    pub language: TemplateLanguage,
+}
+
+/// A file of the template: its path inside the task, its content and whether it's executable.
+type TemplateFile = (&'static str, &'static str, bool);
+
+impl FromStr for TaskFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ioi" => Ok(TaskFormat::IOI),
+            "terry" => Ok(TaskFormat::Terry),
+            _ => bail!("Unknown task format: {} (valid are: ioi, terry)", s),
+        }
+    }
+}
+
+impl FromStr for TemplateLanguage {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cpp" | "c++" => Ok(TemplateLanguage::Cpp),
+            "py" | "python" => Ok(TemplateLanguage::Python),
+            _ => bail!("Unknown language: {} (valid are: cpp, py)", s),
+        }
+    }
+}
+
+/// Include the template file of a format in the language of the template, together with its path
+/// inside the task.
+macro_rules! template_file {
+    ($format:literal, $dir:literal, $file:literal, $language:expr) => {
+        match $language {
+            TemplateLanguage::Cpp => (
+                concat!($dir, "/", $file, ".cpp"),
+                include_str!(concat!("templates/", $format, "/", $file, ".cpp")),
+                false,
+            ),
+            TemplateLanguage::Python => (
+                concat!($dir, "/", $file, ".py"),
+                include_str!(concat!("templates/", $format, "/", $file, ".py")),
+                true,
+            ),
+        }
+    };
+}
+
+impl TaskTemplate {
+    /// The files of the task, with the placeholders still in place.
+    fn files(&self) -> Vec<TemplateFile> {
+        let language = self.language;
+        match self.format {
+            TaskFormat::IOI => vec![
+                ("task.yaml", include_str!("templates/ioi/task.yaml"), false),
+                ("gen/GEN", include_str!("templates/ioi/GEN"), false),
+                template_file!("ioi", "gen", "generator", language),
+                template_file!("ioi", "gen", "validator", language),
+                template_file!("ioi", "sol", "solution", language),
+                template_file!("ioi", "check", "checker", language),
+                (
+                    "statement/english.tex",
+                    include_str!("templates/ioi/statement.tex"),
+                    false,
+                ),
+            ],
+            TaskFormat::Terry => vec![
+                (
+                    "task.yaml",
+                    include_str!("templates/terry/task.yaml"),
+                    false,
+                ),
+                template_file!("terry", "managers", "generator", language),
+                template_file!("terry", "managers", "validator", language),
+                template_file!("terry", "managers", "checker", language),
+                template_file!("terry", "managers", "solution", language),
+                template_file!("terry", "solutions", "solution", language),
+                (
+                    "statement/statement.md",
+                    include_str!("templates/terry/statement.md"),
+                    false,
+                ),
+            ],
+        }
+    }
+
+    /// Replace the placeholders of a template file. In the YAML files the values are quoted.
+    fn render(&self, path: &str, content: &str) -> String {
+        let quote = |value: &str| {
+            if path.ends_with(".yaml") {
+                yaml_quote(value)
+            } else {
+                value.to_string()
+            }
+        };
+        content
+            .replace("%{name}%", &quote(&self.name))
+            .replace("%{title}%", &quote(&self.title))
+    }
+
+    /// Create the task inside `task_dir`, which must not exist or be empty. Returns the list of the
+    /// created files.
+    pub fn create(&self, task_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+        if task_dir.exists() {
+            let mut content = std::fs::read_dir(task_dir)
+                .with_context(|| format!("Failed to read {}", task_dir.display()))?;
+            if content.next().is_some() {
+                bail!("{} already exists and it's not empty", task_dir.display());
+            }
+        }
+        let mut created = Vec::new();
+        for (path, content, executable) in self.files() {
+            let dest = task_dir.join(path);
+            let parent = dest.parent().context("Invalid template path")?;
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+            std::fs::write(&dest, self.render(path, content))
+                .with_context(|| format!("Failed to write {}", dest.display()))?;
+            if executable {
+                std::fs::set_permissions(&dest, std::fs::Permissions::from_mode(0o755))
+                    .with_context(|| format!("Failed to make {} executable", dest.display()))?;
+            }
+            created.push(dest);
+        }
+        Ok(created)
+    }
+}
+
+/// Ask a question to the user, returning the default value if the answer is empty.
+fn ask<T: FromStr>(question: &str, default: &str) -> Result<T, Error>
+where
+    T::Err: Display,
+{
+    loop {
+        print!("{} [{}]: ", question, default);
+        let _ = std::io::stdout().flush();
+        let mut line = String::new();
+        let read = std::io::stdin()
+            .read_line(&mut line)
+            .context("Failed to read stdin")?;
+        if read == 0 {
+            return Err(anyhow!("No answer for: {}", question));
+        }
+        let answer = match line.trim() {
+            "" => default,
+            answer => answer,
+        };
+        match answer.parse() {
+            Ok(value) => return Ok(value),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+/// Get the value of an option, asking it to the user if it's missing and `yes` is not set.
+fn option_or_ask<T: FromStr>(
+    value: Option<T>,
+    yes: bool,
+    question: &str,
+    default: &str,
+) -> Result<T, Error>
+where
+    T::Err: Display,
+{
+    match value {
+        Some(value) => Ok(value),
+        None if yes => default.parse().map_err(|e| anyhow!("{}", e)),
+        None => ask(question, default),
+    }
+}
+
+pub fn main_init(opt: InitOpt) -> Result<(), Error> {
+    let format = option_or_ask(opt.format, opt.yes, "Task format (ioi, terry)", "ioi")?;
+    let default_name = opt
+        .task_dir
+        .as_ref()
+        .and_then(|dir| dir.canonicalize().ok().or_else(|| Some(dir.clone())))
+        .and_then(|dir| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| "task".to_string());
+    let name: String = option_or_ask(opt.name, opt.yes, "Task name", &default_name)?;
+    if name.is_empty() || name.contains(char::is_whitespace) || name.contains('/') {
+        bail!("Invalid task name: {:?}", name);
+    }
+    let title = option_or_ask(opt.title, opt.yes, "Task title", &name)?;
+    let language = option_or_ask(
+        opt.language,
+        opt.yes,
+        "Language of the examples (cpp, py)",
+        "cpp",
+    )?;
+    let task_dir = opt.task_dir.unwrap_or_else(|| PathBuf::from(&name));
+
+    let template = TaskTemplate {
+        format,
+        name,
+        title,
+        language,
+    };
+    for path in template.create(&task_dir)? {
+        println!("Created {}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,27 @@
+#include <fstream>
+#include <iostream>
+
+// Usage: checker input_file correct_output test_output
+// The score, from 0.0 to 1.0, is printed to stdout and the message for the contestant to stderr.
+int main(int argc, char** argv) {
+  if (argc != 4) {
+    std::cerr << "Usage: " << argv[0] << " input_file correct_output test_output"
+              << std::endl;
+    return 1;
+  }
+  std::ifstream correct(argv[2]);  // NOLINT
+  std::ifstream test(argv[3]);     // NOLINT
+
+  long long expected, answer;
+  correct >> expected;
+  if (!(test >> answer)) {
+    std::cout << 0.0 << std::endl;
+    std::cerr << "Output is not a number" << std::endl;
+  } else if (answer != expected) {
+    std::cout << 0.0 << std::endl;
+    std::cerr << "Output is incorrect" << std::endl;
+  } else {
+    std::cout << 1.0 << std::endl;
+    std::cerr << "Output is correct" << std::endl;
+  }
+}
@@ -0,0 +1,23 @@
+#!/usr/bin/env python3
+
+import sys
+
+# Usage: checker input_file correct_output test_output
+# The score, from 0.0 to 1.0, is printed to stdout and the message for the contestant to stderr.
+if len(sys.argv) != 4:
+    print("Usage: %s input_file correct_output test_output" % sys.argv[0], file=sys.stderr)
+    sys.exit(1)
+expected = int(open(sys.argv[2]).read().split()[0])
+try:
+    answer = int(open(sys.argv[3]).read().split()[0])
+except (ValueError, IndexError):
+    print(0.0)
+    print("Output is not a number", file=sys.stderr)
+    sys.exit(0)
+
+if answer == expected:
+    print(1.0)
+    print("Output is correct", file=sys.stderr)
+else:
+    print(0.0)
+    print("Output is incorrect", file=sys.stderr)
@@ -0,0 +1,19 @@
+#include <cstdlib>
+#include <iostream>
+#include <random>
+
+// Usage: generator N seed
+int main(int argc, char** argv) {
+  if (argc != 3) {
+    std::cerr << "Usage: " << argv[0] << " N seed" << std::endl;
+    return 1;
+  }
+  int N = std::atoi(argv[1]);
+  std::mt19937 rng(std::atoi(argv[2]));
+  std::uniform_int_distribution<int> value(0, 1000000000);
+
+  std::cout << N << "\n";
+  for (int i = 0; i < N; i++) {
+    std::cout << value(rng) << " \n"[i == N - 1];
+  }
+}
@@ -0,0 +1,14 @@
+#!/usr/bin/env python3
+
+import random
+import sys
+
+# Usage: generator N seed
+if len(sys.argv) != 3:
+    print("Usage: %s N seed" % sys.argv[0], file=sys.stderr)
+    sys.exit(1)
+N = int(sys.argv[1])
+random.seed(int(sys.argv[2]))
+
+print(N)
+print(" ".join(str(random.randint(0, 10**9)) for _ in range(N)))
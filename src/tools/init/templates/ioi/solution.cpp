@@ -0,0 +1,13 @@
+#include <iostream>
+
+int main() {
+  int N;
+  std::cin >> N;
+  long long sum = 0;
+  for (int i = 0; i < N; i++) {
+    long long value;
+    std::cin >> value;
+    sum += value;
+  }
+  std::cout << sum << std::endl;
+}
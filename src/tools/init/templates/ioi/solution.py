@@ -0,0 +1,4 @@
+#!/usr/bin/env python3
+
+N = int(input())
+print(sum(int(value) for value in input().split()[:N]))
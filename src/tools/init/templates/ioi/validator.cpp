@@ -0,0 +1,33 @@
+#include <cstdlib>
+#include <fstream>
+#include <iostream>
+
+// Usage: validator input_file subtask
+// The subtask number starts from 1, it is 0 for the input files outside the subtasks.
+int main(int argc, char** argv) {
+  if (argc != 3) {
+    std::cerr << "Usage: " << argv[0] << " input_file subtask" << std::endl;
+    return 1;
+  }
+  std::ifstream input(argv[1]);  // NOLINT
+  int subtask = std::atoi(argv[2]);
+  int max_n = subtask == 1 ? 1000 : 100000;
+
+  int N;
+  if (!(input >> N) || N < 1 || N > max_n) {
+    std::cerr << "N is out of range" << std::endl;
+    return 1;
+  }
+  for (int i = 0; i < N; i++) {
+    long long value;
+    if (!(input >> value) || value < 0 || value > 1000000000) {
+      std::cerr << "Value " << i << " is out of range" << std::endl;
+      return 1;
+    }
+  }
+  std::string garbage;
+  if (input >> garbage) {
+    std::cerr << "Garbage at the end of the file" << std::endl;
+    return 1;
+  }
+}
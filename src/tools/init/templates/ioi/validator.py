@@ -0,0 +1,18 @@
+#!/usr/bin/env python3
+
+import sys
+
+# Usage: validator input_file subtask
+# The subtask number starts from 1, it is 0 for the input files outside the subtasks.
+if len(sys.argv) != 3:
+    print("Usage: %s input_file subtask" % sys.argv[0], file=sys.stderr)
+    sys.exit(1)
+tokens = open(sys.argv[1]).read().split()
+subtask = int(sys.argv[2])
+max_n = 1000 if subtask == 1 else 100000
+
+N = int(tokens[0])
+assert 1 <= N <= max_n, "N is out of range"
+assert len(tokens) == N + 1, "Wrong number of values"
+for value in tokens[1:]:
+    assert 0 <= int(value) <= 10**9, "Value out of range"
@@ -0,0 +1,62 @@
+#include <cstdio>
+#include <fstream>
+#include <iostream>
+#include <map>
+#include <sstream>
+#include <string>
+#include <vector>
+
+// Usage: checker input_file output_file
+// The outcome is printed to stdout as JSON.
+int main(int argc, char** argv) {
+  if (argc != 3) {
+    std::cerr << "Usage: " << argv[0] << " input_file output_file" << std::endl;
+    return 1;
+  }
+  std::ifstream input(argv[1]);   // NOLINT
+  std::ifstream output(argv[2]);  // NOLINT
+
+  int T;
+  input >> T;
+  std::vector<long long> expected(T);
+  for (int t = 0; t < T; t++) {
+    int N;
+    input >> N;
+    expected[t] = 0;
+    for (int i = 0; i < N; i++) {
+      long long value;
+      input >> value;
+      expected[t] += value;
+    }
+  }
+
+  std::map<int, long long> answers;
+  std::string line;
+  while (std::getline(output, line)) {
+    int case_num;
+    long long answer;
+    if (std::sscanf(line.c_str(), "Case #%d: %lld", &case_num, &answer) == 2) {
+      answers[case_num] = answer;
+    }
+  }
+
+  std::stringstream validation, feedback;
+  int correct = 0;
+  for (int t = 1; t <= T; t++) {
+    const char* sep = t == 1 ? "" : ", ";
+    auto answer = answers.find(t);
+    if (answer == answers.end()) {
+      validation << sep << R"({"status": "missing", "message": null})";
+      feedback << sep << R"({"correct": false, "message": null})";
+    } else {
+      bool ok = answer->second == expected[t - 1];
+      correct += ok;
+      validation << sep << R"({"status": "parsed", "message": null})";
+      feedback << sep << R"({"correct": )" << (ok ? "true" : "false")
+               << R"(, "message": null})";
+    }
+  }
+  std::cout << R"({"score": )" << (double)correct / T << R"(, "validation": {"cases": [)"
+            << validation.str() << R"(], "alerts": []}, "feedback": {"cases": [)"
+            << feedback.str() << R"(], "alerts": []}})" << std::endl;
+}
@@ -0,0 +1,41 @@
+#!/usr/bin/env python3
+
+import json
+import sys
+
+# Usage: checker input_file output_file
+# The outcome is printed to stdout as JSON.
+if len(sys.argv) != 3:
+    print("Usage: %s input_file output_file" % sys.argv[0], file=sys.stderr)
+    sys.exit(1)
+
+tokens = iter(open(sys.argv[1]).read().split())
+expected = []
+for _ in range(int(next(tokens))):
+    N = int(next(tokens))
+    expected.append(sum(int(next(tokens)) for _ in range(N)))
+
+answers = {}
+for line in open(sys.argv[2]).read().splitlines():
+    parts = line.split(":")
+    try:
+        case = int(parts[0].split("#")[1])
+        answers[case] = int(parts[1])
+    except (ValueError, IndexError):
+        continue
+
+validation = []
+feedback = []
+for case, value in enumerate(expected, start=1):
+    if case not in answers:
+        validation.append({"status": "missing", "message": None})
+        feedback.append({"correct": False, "message": None})
+    else:
+        validation.append({"status": "parsed", "message": None})
+        feedback.append({"correct": answers[case] == value, "message": None})
+
+print(json.dumps({
+    "score": sum(case["correct"] for case in feedback) / len(feedback),
+    "validation": {"cases": validation, "alerts": []},
+    "feedback": {"cases": feedback, "alerts": []},
+}))
@@ -0,0 +1,25 @@
+#include <cstdlib>
+#include <iostream>
+#include <random>
+
+// Usage: generator seed 0
+// The input file is printed to stdout.
+int main(int argc, char** argv) {
+  if (argc != 3) {
+    std::cerr << "Usage: " << argv[0] << " seed 0" << std::endl;
+    return 1;
+  }
+  std::mt19937 rng(std::atoi(argv[1]));
+  std::uniform_int_distribution<int> length(1, 1000);
+  std::uniform_int_distribution<int> value(0, 1000000000);
+
+  const int T = 10;
+  std::cout << T << "\n";
+  for (int t = 0; t < T; t++) {
+    int N = length(rng);
+    std::cout << N << "\n";
+    for (int i = 0; i < N; i++) {
+      std::cout << value(rng) << " \n"[i == N - 1];
+    }
+  }
+}
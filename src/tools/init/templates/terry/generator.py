@@ -0,0 +1,18 @@
+#!/usr/bin/env python3
+
+import random
+import sys
+
+# Usage: generator seed 0
+# The input file is printed to stdout.
+if len(sys.argv) != 3:
+    print("Usage: %s seed 0" % sys.argv[0], file=sys.stderr)
+    sys.exit(1)
+random.seed(int(sys.argv[1]))
+
+T = 10
+print(T)
+for _ in range(T):
+    N = random.randint(1, 1000)
+    print(N)
+    print(" ".join(str(random.randint(0, 10**9)) for _ in range(N)))
@@ -0,0 +1,17 @@
+#include <iostream>
+
+int main() {
+  int T;
+  std::cin >> T;
+  for (int t = 1; t <= T; t++) {
+    int N;
+    std::cin >> N;
+    long long sum = 0;
+    for (int i = 0; i < N; i++) {
+      long long value;
+      std::cin >> value;
+      sum += value;
+    }
+    std::cout << "Case #" << t << ": " << sum << "\n";
+  }
+}
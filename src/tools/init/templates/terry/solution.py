@@ -0,0 +1,6 @@
+#!/usr/bin/env python3
+
+T = int(input())
+for t in range(1, T + 1):
+    N = int(input())
+    print("Case #%d: %d" % (t, sum(int(value) for value in input().split()[:N])))
@@ -0,0 +1,24 @@
+#include <iostream>
+
+// The input file is read from stdin, a non-zero exit code means that it's invalid.
+int main() {
+  int T;
+  if (!(std::cin >> T) || T < 1 || T > 100) {
+    std::cerr << "T is out of range" << std::endl;
+    return 1;
+  }
+  for (int t = 0; t < T; t++) {
+    int N;
+    if (!(std::cin >> N) || N < 1 || N > 1000) {
+      std::cerr << "N of case " << t + 1 << " is out of range" << std::endl;
+      return 1;
+    }
+    for (int i = 0; i < N; i++) {
+      long long value;
+      if (!(std::cin >> value) || value < 0 || value > 1000000000) {
+        std::cerr << "Value of case " << t + 1 << " is out of range" << std::endl;
+        return 1;
+      }
+    }
+  }
+}
@@ -0,0 +1,14 @@
+#!/usr/bin/env python3
+
+import sys
+
+# The input file is read from stdin, a non-zero exit code means that it's invalid.
+tokens = iter(sys.stdin.read().split())
+T = int(next(tokens))
+assert 1 <= T <= 100, "T is out of range"
+for t in range(T):
+    N = int(next(tokens))
+    assert 1 <= N <= 1000, "N of case %d is out of range" % (t + 1)
+    for _ in range(N):
+        assert 0 <= int(next(tokens)) <= 10**9, "Value of case %d is out of range" % (t + 1)
+assert next(tokens, None) is None, "Garbage at the end of the file"
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+
+use task_maker_diagnostics::DiagnosticLevel;
+use task_maker_format::lint::{lint_task, LintCode};
+
+#[derive(Parser, Debug, Clone)]
+pub struct LintOpt {
+    /// Directory of the task
+    #[clap(short = 't', long = "task-dir", default_value = ".")]
+    pub task_dir: PathBuf,
+
+    /// Print the diagnostics as JSON, one per line
+    #[clap(long)]
+    pub json: bool,
+
+    /// Don't report the problems with these codes (comma separated)
+    #[clap(long, value_delimiter = ',', long_help = allow_long_help())]
+    pub allow: Vec<String>,
+}
+
+/// The long help of `--allow`, with the list of the lint codes.
+fn allow_long_help() -> String {
+    let mut help = "Don't report the problems with these codes (comma separated).\n\n\
+        The available codes are:"
+        .to_string();
+    for code in LintCode::ALL {
+        help += &format!("\n- {}: {}", code.as_str(), code.description());
+    }
+    help
+}
+
+pub fn main_lint(opt: LintOpt) -> Result<(), Error> {
+    for code in &opt.allow {
+        if !LintCode::ALL.iter().any(|c| c.as_str() == code) {
+            bail!("Unknown lint code: {}", code);
+        }
+    }
+    if !opt.task_dir.is_dir() {
+        bail!("{} is not a directory", opt.task_dir.display());
+    }
+    let diagnostics: Vec<_> = lint_task(&opt.task_dir)
+        .into_iter()
+        .filter(|d| !opt.allow.iter().any(|code| d.code() == Some(code)))
+        .collect();
+    for diagnostic in &diagnostics {
+        if opt.json {
+            let json = serde_json::to_string(diagnostic).context("Failed to serialize")?;
+            println!("{}", json);
+        } else {
+            print!("{}", diagnostic);
+        }
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.level() == DiagnosticLevel::Error)
+        .count();
+    if !opt.json {
+        println!("{} errors, {} warnings", errors, diagnostics.len() - errors);
+    }
+    if errors > 0 {
+        bail!("The task has {} errors", errors);
+    }
+    Ok(())
+}
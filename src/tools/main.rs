@@ -1,22 +1,45 @@
 use clap::Parser;
 
+use task_maker_rust::config::apply_config_files;
 use task_maker_rust::error::NiceError;
 use task_maker_rust::tools::add_solution_checks::main_add_solution_checks;
+use task_maker_rust::tools::admin::main_admin;
+use task_maker_rust::tools::att::main_att;
 use task_maker_rust::tools::booklet::main_booklet;
+use task_maker_rust::tools::bundle_artifacts::main_bundle_artifacts;
+use task_maker_rust::tools::cache::main_cache;
+use task_maker_rust::tools::check::main_check;
 use task_maker_rust::tools::clear::main_clear;
+use task_maker_rust::tools::convert::main_convert;
 use task_maker_rust::tools::copy_competition_files::copy_competition_files_main;
+use task_maker_rust::tools::daemon::main_daemon;
+use task_maker_rust::tools::diff::main_diff;
+use task_maker_rust::tools::evaluate_contest::main_evaluate_contest;
+use task_maker_rust::tools::export::main_export;
 use task_maker_rust::tools::find_bad_case::main_find_bad_case;
 use task_maker_rust::tools::fuzz_checker::main_fuzz_checker;
 use task_maker_rust::tools::gen_autocompletion::main_get_autocompletion;
+use task_maker_rust::tools::gen_checker::main_gen_checker;
+use task_maker_rust::tools::import::main_import;
+use task_maker_rust::tools::init::main_init;
+use task_maker_rust::tools::lint::main_lint;
 use task_maker_rust::tools::opt::{Opt, Tool};
 use task_maker_rust::tools::reset::main_reset;
 use task_maker_rust::tools::sandbox::main_sandbox;
+use task_maker_rust::tools::sandbox_run::main_sandbox_run;
 use task_maker_rust::tools::server::main_server;
+use task_maker_rust::tools::store::main_store;
+use task_maker_rust::tools::stress::main_stress;
+use task_maker_rust::tools::suggest_limits::{main_suggest_memory_limit, main_suggest_time_limit};
 use task_maker_rust::tools::task_info::main_task_info;
 use task_maker_rust::tools::typescriptify::main_typescriptify;
 use task_maker_rust::tools::worker::main_worker;
 
 fn main() {
+    // the sandbox is spawned by task-maker itself, which has already read the configuration
+    if std::env::args().nth(1).as_deref() != Some("internal-sandbox") {
+        apply_config_files().nice_unwrap();
+    }
     let base_opt = Opt::parse();
     base_opt.logger.enable_log();
 
@@ -25,15 +48,34 @@ fn main() {
         Tool::GenAutocompletion(opt) => main_get_autocompletion(opt),
         Tool::Server(opt) => main_server(opt),
         Tool::Worker(opt) => main_worker(opt),
+        Tool::Admin(opt) => main_admin(opt),
         Tool::Typescriptify => main_typescriptify(),
         Tool::Reset(opt) => main_reset(opt),
+        Tool::Cache(opt) => main_cache(opt),
+        Tool::Store(opt) => main_store(opt),
         Tool::Sandbox(opt) => main_sandbox(opt),
+        Tool::SandboxRun(opt) => main_sandbox_run(opt),
         Tool::TaskInfo(opt) => main_task_info(opt),
         Tool::Booklet(opt) => main_booklet(opt, base_opt.logger),
         Tool::CopyCompetitionFiles(opt) => copy_competition_files_main(opt, base_opt.logger),
         Tool::FuzzChecker(opt) => main_fuzz_checker(opt),
         Tool::FindBadCase(opt) => main_find_bad_case(opt),
+        Tool::Stress(opt) => main_stress(opt),
+        Tool::EvaluateContest(opt) => main_evaluate_contest(opt),
+        Tool::Daemon(opt) => main_daemon(opt),
+        Tool::Export(opt) => main_export(opt, base_opt.logger),
+        Tool::Import(opt) => main_import(opt),
+        Tool::Convert(opt) => main_convert(opt),
         Tool::AddSolutionChecks(opt) => main_add_solution_checks(opt, base_opt.logger),
+        Tool::SuggestTimeLimit(opt) => main_suggest_time_limit(opt, base_opt.logger),
+        Tool::SuggestMemoryLimit(opt) => main_suggest_memory_limit(opt, base_opt.logger),
+        Tool::Init(opt) => main_init(opt),
+        Tool::GenChecker(opt) => main_gen_checker(opt),
+        Tool::Att(opt) => main_att(opt, base_opt.logger),
+        Tool::Lint(opt) => main_lint(opt),
+        Tool::Check(opt) => main_check(opt),
+        Tool::Diff(opt) => main_diff(opt),
+        Tool::BundleArtifacts(opt) => main_bundle_artifacts(opt),
         Tool::InternalSandbox => return task_maker_rust::main_sandbox(),
     }
     .nice_unwrap()
@@ -1,14 +1,70 @@
 pub mod add_solution_checks;
+pub mod admin;
+pub mod att;
 pub mod booklet;
+pub mod bundle_artifacts;
+pub mod cache;
+pub mod check;
 pub mod clear;
+pub mod convert;
 pub mod copy_competition_files;
+pub mod daemon;
+pub mod diff;
+pub mod evaluate_contest;
+pub mod export;
 pub mod find_bad_case;
 pub mod fuzz_checker;
 pub mod gen_autocompletion;
+pub mod gen_checker;
+pub mod import;
+pub mod init;
+pub mod lint;
 pub mod opt;
 pub mod reset;
 pub mod sandbox;
+pub mod sandbox_run;
 pub mod server;
+pub mod store;
+pub mod stress;
+pub mod suggest_limits;
 pub mod task_info;
 pub mod typescriptify;
 pub mod worker;
+
+use anyhow::{Context, Error};
+use serde::Serialize;
+
+/// The `task.yaml.orig` of an IOI task, as written by the tools that make a new task from a package
+/// of another format.
+#[derive(Debug, Serialize)]
+pub(crate) struct TaskYamlOrig<'a> {
+    /// The name of the task (the short one).
+    pub name: &'a str,
+    /// The title of the task (the long one).
+    pub title: &'a str,
+    /// The time limit of the solutions, in seconds.
+    pub time_limit: f64,
+    /// The memory limit of the solutions, in MiB.
+    pub memory_limit: u64,
+    /// The input file of the solutions, empty for stdin.
+    pub infile: &'a str,
+    /// The output file of the solutions, empty for stdout.
+    pub outfile: &'a str,
+    /// The score type, if not the default one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_type: Option<&'a str>,
+}
+
+impl TaskYamlOrig<'_> {
+    /// Serialize the `task.yaml.orig` to YAML.
+    pub fn to_yaml(&self) -> Result<String, Error> {
+        serde_yaml::to_string(self).context("Failed to serialize task.yaml.orig")
+    }
+}
+
+/// Quote a string so that it can be written as a value of a hand-written YAML file, whatever
+/// characters it contains.
+pub(crate) fn yaml_quote(value: &str) -> String {
+    // a JSON string is also a valid YAML scalar
+    serde_json::to_string(value).expect("Strings are serializable")
+}
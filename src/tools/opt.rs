@@ -1,15 +1,33 @@
 use clap::Parser;
 
 use crate::tools::add_solution_checks::AddSolutionChecksOpt;
+use crate::tools::admin::AdminOpt;
+use crate::tools::att::AttOpt;
 use crate::tools::booklet::BookletOpt;
+use crate::tools::bundle_artifacts::BundleArtifactsOpt;
+use crate::tools::cache::CacheOpt;
+use crate::tools::check::CheckOpt;
 use crate::tools::clear::ClearOpt;
+use crate::tools::convert::ConvertOpt;
 use crate::tools::copy_competition_files::CopyCompetitionFilesOpt;
+use crate::tools::daemon::DaemonOpt;
+use crate::tools::diff::DiffOpt;
+use crate::tools::evaluate_contest::EvaluateContestOpt;
+use crate::tools::export::ExportOpt;
 use crate::tools::find_bad_case::FindBadCaseOpt;
 use crate::tools::fuzz_checker::FuzzCheckerOpt;
 use crate::tools::gen_autocompletion::GenAutocompletionOpt;
+use crate::tools::gen_checker::GenCheckerOpt;
+use crate::tools::import::ImportOpt;
+use crate::tools::init::InitOpt;
+use crate::tools::lint::LintOpt;
 use crate::tools::reset::ResetOpt;
 use crate::tools::sandbox::SandboxOpt;
+use crate::tools::sandbox_run::SandboxRunOpt;
 use crate::tools::server::ServerOpt;
+use crate::tools::store::StoreOpt;
+use crate::tools::stress::StressOpt;
+use crate::tools::suggest_limits::{SuggestMemoryLimitOpt, SuggestTimeLimitOpt};
 use crate::tools::task_info::TaskInfoOpt;
 use crate::tools::worker::WorkerOpt;
 use crate::LoggerOpt;
@@ -27,7 +45,8 @@ pub struct Opt {
 
 #[derive(Parser, Debug)]
 pub enum Tool {
-    /// Clear a task directory
+    /// Clear a task directory, removing all the files generated by task-maker
+    #[clap(alias = "clean")]
     Clear(ClearOpt),
     /// Generate the autocompletion files for the shell
     GenAutocompletion(GenAutocompletionOpt),
@@ -35,14 +54,24 @@ pub enum Tool {
     Server(ServerOpt),
     /// Spawn an instance of a worker
     Worker(WorkerOpt),
+    /// Administer a running server: list workers, clients and queue, cancel evaluations and drain
+    /// workers
+    Admin(AdminOpt),
     /// Print the TypeScript type definitions
     Typescriptify,
     /// Wipe the internal storage of task-maker
     ///
     /// Warning: no other instances of task-maker should be running when this flag is provided.
     Reset(ResetOpt),
+    /// Manage the cache of task-maker, freeing space from its storage
+    Cache(CacheOpt),
+    /// Check the integrity of the files in the storage of task-maker
+    Store(StoreOpt),
     /// Run a command inside a sandbox similar to the one used by task-maker
     Sandbox(SandboxOpt),
+    /// Run a command inside the same sandbox used for the evaluations, printing the resources it
+    /// used
+    SandboxRun(SandboxRunOpt),
     /// Obtain the information about a task.
     TaskInfo(TaskInfoOpt),
     /// Compile just the booklet for a task or a contest.
@@ -53,8 +82,57 @@ pub enum Tool {
     FuzzChecker(FuzzCheckerOpt),
     /// Generate and search for an input file that make a solution fail.
     FindBadCase(FindBadCaseOpt),
+    /// Compare a solution against a model solution on random inputs, until they disagree.
+    Stress(StressOpt),
+    /// Compare the results of two evaluations, written with --results, reporting what changed.
+    Diff(DiffOpt),
+    /// Collect the results, the booklets, the logs and optionally the testcases of an evaluation in
+    /// a directory or a zip file, with a manifest, ready to be uploaded as a CI artifact.
+    BundleArtifacts(BundleArtifactsOpt),
+    /// Suggest a time limit, from the times of the reference solutions evaluated without one.
+    ///
+    /// The reference solutions are the ones selected on the command line or, if none is selected,
+    /// the ones whose @check comments expect them to be accepted on all the subtasks.
+    SuggestTimeLimit(SuggestTimeLimitOpt),
+    /// Suggest a memory limit, from the peak memory of the reference solutions evaluated without
+    /// one, flagging the testcases that don't fit in the current limit.
+    ///
+    /// The reference solutions are selected like in suggest-time-limit.
+    SuggestMemoryLimit(SuggestMemoryLimitOpt),
+    /// Evaluate all the tasks of a contest at the same time, printing a summary of each task.
+    ///
+    /// The evaluations share the same executor, so the executions of different tasks run in
+    /// parallel.
+    EvaluateContest(EvaluateContestOpt),
+    /// Run in background evaluating the tasks on request, controlled with JSON-RPC over a Unix
+    /// domain socket.
+    Daemon(DaemonOpt),
+    /// Export the task in the format of another contest system, like a package importable in cms.
+    Export(ExportOpt),
+    /// Import a task prepared with another system, like a package of Polygon, in the IOI format.
+    Import(ImportOpt),
+    /// Convert an IOI task to a Terry one or vice versa, reporting what must be finished by hand.
+    Convert(ConvertOpt),
     /// Add the @check comments to the solutions.
     AddSolutionChecks(AddSolutionChecksOpt),
+    /// Create a new task, with an example of all the files it needs.
+    Init(InitOpt),
+    /// Write a checker for the task, ready to be customized, comparing tokens, floats or YES/NO
+    /// answers.
+    GenChecker(GenCheckerOpt),
+    /// Build the att/ directory of the task and verify it.
+    ///
+    /// The templates are generated from sol/templates.yaml, the graders and the sample cases are
+    /// linked, then the templates are compiled with the graders and the samples are checked.
+    Att(AttOpt),
+    /// Check the structure of a task without running anything.
+    Lint(LintOpt),
+    /// Lint the task, compile everything, validate all the testcases and evaluate the official
+    /// solution, reporting only the problems.
+    ///
+    /// With --quick the official solution is evaluated only on a sample of the testcases, for a
+    /// check fast enough for a git hook.
+    Check(CheckOpt),
     /// Run the sandbox instead of the normal task-maker.
     ///
     /// This option is left as undocumented as it's not part of the public API.
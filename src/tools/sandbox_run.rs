@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+
+use task_maker_dag::{Execution, ExecutionCommand, ExecutionLimits, File, FileUuid};
+use task_maker_exec::sandbox::{Sandbox, SandboxResult};
+use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey, ReadFileIterator};
+
+use crate::{StorageOpt, ToolsSandboxRunner};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(trailing_var_arg = true)]
+pub struct SandboxRunOpt {
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+
+    /// CPU time limit, in seconds.
+    #[clap(long)]
+    pub time: Option<f64>,
+
+    /// Wall time limit, in seconds.
+    #[clap(long)]
+    pub wall_time: Option<f64>,
+
+    /// Memory limit, in KiB.
+    #[clap(long)]
+    pub memory: Option<u64>,
+
+    /// Stack limit, in KiB.
+    #[clap(long)]
+    pub stack: Option<u64>,
+
+    /// Allow the command to fork, like the compilers do.
+    #[clap(long)]
+    pub multiprocess: bool,
+
+    /// Leave the box directory writable, like the generators and the compilers have it.
+    #[clap(long)]
+    pub writable: bool,
+
+    /// Mount /tmp and /dev/null inside the sandbox.
+    #[clap(long)]
+    pub mount_tmpfs: bool,
+
+    /// Mount /proc inside the sandbox.
+    #[clap(long)]
+    pub mount_proc: bool,
+
+    /// Additional directory mounted read-only inside the sandbox.
+    #[clap(long = "readable-dir")]
+    pub readable_dirs: Vec<PathBuf>,
+
+    /// File to copy inside the box directory, with the same name.
+    #[clap(long = "input", short)]
+    pub inputs: Vec<PathBuf>,
+
+    /// File to use as standard input of the command.
+    #[clap(long)]
+    pub stdin: Option<PathBuf>,
+
+    /// Keep the sandbox directory after the execution, printing its path.
+    #[clap(long)]
+    pub keep_sandbox: bool,
+
+    /// Command to execute inside the sandbox.
+    ///
+    /// If the command is a local file it's copied inside the box directory, otherwise it's
+    /// searched in the PATH.
+    #[clap(required = true)]
+    pub command: Vec<String>,
+}
+
+/// Store a local file, returning the handle to it.
+fn store_file(file_store: &FileStore, path: &Path) -> Result<FileStoreHandle, Error> {
+    let key = FileStoreKey::from_file(path)
+        .with_context(|| format!("Failed to hash {}", path.display()))?;
+    let content = ReadFileIterator::new(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    file_store
+        .store(&key, content)
+        .with_context(|| format!("Failed to store {}", path.display()))
+}
+
+/// Add a local file to the inputs of the execution, at `/box/<file name>`.
+fn add_input(
+    execution: &mut Execution,
+    file_store: &FileStore,
+    dep_keys: &mut HashMap<FileUuid, FileStoreHandle>,
+    path: &Path,
+) -> Result<(), Error> {
+    let name = PathBuf::from(path.file_name().context("Invalid input file")?);
+    let metadata =
+        std::fs::metadata(path).with_context(|| format!("Failed to stat {}", path.display()))?;
+    let file = File::new(format!("Input {}", path.display()));
+    dep_keys.insert(file.uuid, store_file(file_store, path)?);
+    execution.input(&file, &name, metadata.permissions().mode() & 0o111 != 0);
+    Ok(())
+}
+
+pub fn main_sandbox_run(opt: SandboxRunOpt) -> Result<(), Error> {
+    let file_store = opt.storage.file_store().context(
+        "Cannot create the file store (You can try wiping it with task-maker-tools reset)",
+    )?;
+    let mut dep_keys = HashMap::new();
+
+    let program = Path::new(&opt.command[0]);
+    let command = if program.is_file() {
+        ExecutionCommand::local(program.file_name().context("Invalid command")?)
+    } else {
+        ExecutionCommand::system(program)
+    };
+    let mut execution = Execution::new("Command run by sandbox-run", command);
+    execution.args(opt.command.iter().skip(1));
+    if program.is_file() {
+        add_input(&mut execution, &file_store, &mut dep_keys, program)?;
+    }
+    for input in &opt.inputs {
+        add_input(&mut execution, &file_store, &mut dep_keys, input)?;
+    }
+    if let Some(stdin) = &opt.stdin {
+        let file = File::new("Standard input");
+        dep_keys.insert(file.uuid, store_file(&file_store, stdin)?);
+        execution.stdin(&file);
+    }
+    execution.stdout();
+    execution.stderr();
+
+    // start from the same limits the evaluations start from
+    let mut limits = ExecutionLimits::default();
+    if let Some(time) = opt.time {
+        limits.cpu_time(time);
+    }
+    if let Some(wall_time) = opt.wall_time {
+        limits.wall_time(wall_time);
+    }
+    if let Some(memory) = opt.memory {
+        limits.memory(memory);
+    }
+    if let Some(stack) = opt.stack {
+        limits.stack(stack);
+    }
+    if opt.multiprocess {
+        limits.allow_multiprocess();
+    }
+    limits.read_only(!opt.writable);
+    limits.mount_tmpfs(opt.mount_tmpfs);
+    limits.mount_proc(opt.mount_proc);
+    for dir in &opt.readable_dirs {
+        limits.add_extra_readable_dir(dir);
+    }
+    *execution.limits_mut() = limits;
+
+    let mut sandbox = Sandbox::new(&opt.storage.sandbox_dir(), &execution, &dep_keys, None)
+        .context("Failed to create the sandbox")?;
+    if opt.keep_sandbox {
+        sandbox.keep()?;
+    }
+    let result = sandbox
+        .run(&ToolsSandboxRunner::default())
+        .context("Failed to run the sandbox")?;
+
+    for (name, path) in [
+        ("stdout", sandbox.stdout_path()),
+        ("stderr", sandbox.stderr_path()),
+    ] {
+        let content =
+            std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if !content.is_empty() {
+            println!("--- {} ---", name);
+            print!("{}", String::from_utf8_lossy(&content));
+            if !content.ends_with(b"\n") {
+                println!();
+            }
+        }
+    }
+    println!("---");
+
+    match result {
+        SandboxResult::Success {
+            exit_status,
+            signal,
+            resources,
+            was_killed,
+        } => {
+            println!("Exit status: {}", exit_status);
+            if let Some((signal, name)) = signal {
+                println!("Signal:      {} ({})", signal, name);
+            }
+            println!("Killed:      {}", was_killed);
+            println!("CPU time:    {:.3}s", resources.cpu_time);
+            println!("Sys time:    {:.3}s", resources.sys_time);
+            println!("Wall time:   {:.3}s", resources.wall_time);
+            println!("Memory:      {} KiB", resources.memory);
+        }
+        SandboxResult::Failed { error } => bail!("The sandbox failed: {}", error),
+    }
+    if opt.keep_sandbox {
+        println!(
+            "Sandbox kept at {}",
+            sandbox
+                .stdout_path()
+                .parent()
+                .context("Invalid sandbox path")?
+                .display()
+        );
+    }
+    Ok(())
+}
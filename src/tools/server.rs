@@ -1,21 +1,30 @@
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use clap::Parser;
 
-use task_maker_cache::Cache;
+use task_maker_exec::autoscale::AutoscaleConfig;
 use task_maker_exec::executors::RemoteExecutor;
-use task_maker_store::FileStore;
+use task_maker_exec::gc::GcConfig;
+use task_maker_exec::quota::ClientQuotas;
 
 use crate::StorageOpt;
 
 #[derive(Parser, Debug, Clone)]
 pub struct ServerOpt {
-    /// Address to bind the server on for listening for the clients
+    /// Address to bind the server on for listening for the clients.
+    ///
+    /// Either an IPv4 or IPv6 socket address (e.g. `[::]:27182`), or `unix://path` for a Unix
+    /// domain socket.
     #[clap(default_value = "0.0.0.0:27182")]
     pub client_addr: String,
 
-    /// Address to bind the server on for listening for the workers
+    /// Address to bind the server on for listening for the workers.
+    ///
+    /// Either an IPv4 or IPv6 socket address (e.g. `[::]:27183`), or `unix://path` for a Unix
+    /// domain socket.
     #[clap(default_value = "0.0.0.0:27183")]
     pub worker_addr: String,
 
@@ -27,6 +36,66 @@ pub struct ServerOpt {
     #[clap(long = "worker-password")]
     pub worker_password: Option<String>,
 
+    /// Password of the administrators, for `task-maker-tools admin`. Without it the server cannot
+    /// be administered
+    #[clap(
+        long = "admin-password",
+        env = "TM_ADMIN_PASSWORD",
+        hide_env_values = true
+    )]
+    pub admin_password: Option<String>,
+
+    /// Do not compress the files sent over the network
+    #[clap(long)]
+    pub no_compression: bool,
+
+    /// Do not persist the queued evaluations: they are lost if the server restarts
+    #[clap(long)]
+    pub no_persistent_queue: bool,
+
+    /// Maximum number of short executions (e.g. checkers and validators) sent to a worker in a
+    /// single message, 1 disables the batches
+    #[clap(long, default_value = "8")]
+    pub batch_size: usize,
+
+    /// Script to execute when the number of ready executions crosses the autoscaling thresholds.
+    ///
+    /// The script is called with either `scale-up` or `scale-down` as argument, and receives the
+    /// metrics of the queue as JSON in its standard input.
+    #[clap(long, help_heading = Some("AUTOSCALING"))]
+    pub autoscale_script: Option<PathBuf>,
+
+    /// Run the autoscaling script with `scale-up` when the ready executions are at least this many
+    #[clap(long, default_value = "100", help_heading = Some("AUTOSCALING"))]
+    pub autoscale_up: usize,
+
+    /// Run the autoscaling script with `scale-down` when the ready executions are at most this many
+    #[clap(long, default_value = "0", help_heading = Some("AUTOSCALING"))]
+    pub autoscale_down: usize,
+
+    /// Maximum number of executions of the same client running at the same time
+    #[clap(long, help_heading = Some("QUOTAS"))]
+    pub max_running_execs: Option<usize>,
+
+    /// Maximum number of evaluations of the same client the server works on, the others are
+    /// rejected
+    #[clap(long, help_heading = Some("QUOTAS"))]
+    pub max_evaluations: Option<usize>,
+
+    /// Maximum number of MiB of files a client can send in a single connection
+    #[clap(long, help_heading = Some("QUOTAS"))]
+    pub max_transferred_mb: Option<u64>,
+
+    /// Minimum number of seconds between two rounds of garbage collection of the stores of the
+    /// server and of the workers, zero disables it
+    #[clap(long, default_value = "600", help_heading = Some("STORAGE"))]
+    pub gc_interval: u64,
+
+    /// Size, in MiB, the store of the server is shrunk to by the garbage collection. Defaults to
+    /// `--min-cache`
+    #[clap(long, help_heading = Some("STORAGE"))]
+    pub gc_retention: Option<u64>,
+
     #[clap(flatten, next_help_heading = Some("STORAGE"))]
     pub storage: StorageOpt,
 }
@@ -36,16 +105,40 @@ pub fn main_server(opt: ServerOpt) -> Result<(), Error> {
     // setup the executor
     let store_path = opt.storage.store_dir();
     let file_store = Arc::new(
-        FileStore::new(
-            store_path.join("store"),
-            opt.storage.max_cache * 1024 * 1024,
-            opt.storage.min_cache * 1024 * 1024,
-        )
-        .context("Cannot create the file store")?,
+        opt.storage
+            .file_store()
+            .context("Cannot create the file store")?,
     );
-    let cache = Cache::new(store_path.join("cache")).context("Cannot create the cache")?;
+    let cache = opt.storage.cache().context("Cannot create the cache")?;
 
-    let remote_executor = RemoteExecutor::new(file_store);
+    let mut remote_executor = RemoteExecutor::new(file_store);
+    remote_executor.set_transfer_compression(!opt.no_compression);
+    if !opt.no_persistent_queue {
+        remote_executor.set_queue_dir(store_path.join("queue"));
+    }
+    remote_executor.set_batch_size(opt.batch_size);
+    remote_executor.set_admin_password(opt.admin_password);
+    if opt.gc_interval > 0 {
+        remote_executor.set_gc(GcConfig {
+            interval: Duration::from_secs(opt.gc_interval),
+            retention_size: opt.gc_retention.unwrap_or(opt.storage.min_cache) * 1024 * 1024,
+        });
+    }
+    remote_executor.set_quotas(ClientQuotas {
+        max_running_execs: opt.max_running_execs,
+        max_evaluations: opt.max_evaluations,
+        max_transferred_bytes: opt.max_transferred_mb.map(|mb| mb * 1024 * 1024),
+    });
+    if let Some(script) = opt.autoscale_script {
+        if opt.autoscale_down >= opt.autoscale_up {
+            bail!("--autoscale-down must be smaller than --autoscale-up");
+        }
+        remote_executor.set_autoscale(AutoscaleConfig {
+            script,
+            scale_up_threshold: opt.autoscale_up,
+            scale_down_threshold: opt.autoscale_down,
+        });
+    }
 
     remote_executor.start(
         &opt.client_addr,
@@ -0,0 +1,58 @@
+use anyhow::{Context, Error};
+use clap::Parser;
+
+use crate::StorageOpt;
+
+#[derive(Parser, Debug, Clone)]
+pub struct StoreOpt {
+    /// What to do with the storage
+    #[clap(subcommand)]
+    pub command: StoreCommand,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub enum StoreCommand {
+    /// Hash again all the stored files, removing the corrupted and the missing ones so that they
+    /// are produced again by the next evaluations
+    Verify(StoreVerifyOpt),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct StoreVerifyOpt {
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+}
+
+/// Entry point for the store tool.
+pub fn main_store(opt: StoreOpt) -> Result<(), Error> {
+    match opt.command {
+        StoreCommand::Verify(opt) => main_store_verify(opt),
+    }
+}
+
+/// Verify the files in the store, and then drop the cache entries that refer to the removed ones.
+fn main_store_verify(opt: StoreVerifyOpt) -> Result<(), Error> {
+    let file_store = opt.storage.file_store().context(
+        "Cannot create the file store (You can try wiping it with task-maker-tools reset)",
+    )?;
+    let mut cache = opt.storage.cache().context("Cannot create the cache")?;
+
+    let verification = file_store
+        .verify()
+        .context("Failed to verify the file store")?;
+    for key in &verification.corrupted {
+        println!("corrupted {}", key);
+    }
+    for key in &verification.missing {
+        println!("missing   {}", key);
+    }
+    let removed_entries = cache.collect_garbage(&file_store);
+    println!(
+        "Checked {} files: {} corrupted, {} missing. Removed {} cache entries",
+        verification.checked,
+        verification.corrupted.len(),
+        verification.missing.len(),
+        removed_entries
+    );
+    Ok(())
+}
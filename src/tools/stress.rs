@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Error};
+use clap::{Parser, ValueHint};
+
+use crate::tools::find_bad_case::{main_find_bad_case, FindBadCaseOpt};
+use crate::{ExecutionOpt, FindTaskOpt, StorageOpt};
+
+#[derive(Parser, Debug, Clone)]
+pub struct StressOpt {
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+
+    #[clap(flatten, next_help_heading = Some("EXECUTION"))]
+    pub execution: ExecutionOpt,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+
+    /// Number of input files to generate for each batch.
+    #[clap(long, short, default_value = "100")]
+    pub batch_size: usize,
+
+    /// Path of the generator, when not specified the one of the task is used.
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    pub generator: Option<PathBuf>,
+
+    /// Arguments to pass to the generator, split like a shell would do.
+    ///
+    /// The value '{}' will be replaced with a random seed. When it's missing, the seed is passed
+    /// as the last argument. The values like '{A..B}' are replaced with a random integer between A
+    /// and B (inclusive), for exploring the space of the parameters.
+    #[clap(long, default_value = "", allow_hyphen_values = true)]
+    pub args: String,
+
+    /// Path of the model solution, when not specified the official solution of the task is used.
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    pub model: Option<PathBuf>,
+
+    /// After finding a failing input, search the smallest values of the '{A..B}' arguments that
+    /// still make the candidate fail.
+    #[clap(long)]
+    pub minimize: bool,
+
+    /// Path of the solution to compare against the model solution.
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    pub candidate: PathBuf,
+}
+
+impl StressOpt {
+    /// The arguments of the generator, with the placeholder for the seed.
+    fn generator_args(&self) -> Result<Vec<String>, Error> {
+        let mut args = shell_words::split(&self.args).context("Invalid generator arguments")?;
+        if !args.iter().any(|arg| arg == "{}") {
+            args.push("{}".into());
+        }
+        Ok(args)
+    }
+}
+
+pub fn main_stress(opt: StressOpt) -> Result<(), Error> {
+    let generator_args = opt.generator_args()?;
+    main_find_bad_case(FindBadCaseOpt {
+        find_task: opt.find_task,
+        execution: opt.execution,
+        storage: opt.storage,
+        batch_size: opt.batch_size,
+        minimize: opt.minimize,
+        generator: opt.generator,
+        model: opt.model,
+        solution: opt.candidate,
+        generator_args,
+    })
+}
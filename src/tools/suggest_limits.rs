@@ -0,0 +1,414 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+
+use task_maker_format::ioi::{IOITask, SubtaskId};
+use task_maker_format::results::EvaluationResults;
+use task_maker_format::ui::{StdoutPrinter, BOLD, GREEN, RED, YELLOW};
+use task_maker_format::{cwrite, cwriteln, EvaluationConfig, TaskFormat};
+
+use crate::context::RuntimeContext;
+use crate::{ExecutionOpt, FilterOpt, FindTaskOpt, LoggerOpt, StorageOpt, UIOpt};
+
+/// The options for evaluating the reference solutions.
+#[derive(Parser, Debug, Clone)]
+pub struct ReferenceSolutionsOpt {
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+
+    #[clap(flatten, next_help_heading = Some("UI"))]
+    pub ui: UIOpt,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+
+    #[clap(flatten, next_help_heading = Some("FILTER"))]
+    pub filter: FilterOpt,
+
+    #[clap(flatten, next_help_heading = Some("EXECUTION"))]
+    pub execution: ExecutionOpt,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct SuggestTimeLimitOpt {
+    #[clap(flatten)]
+    pub reference: ReferenceSolutionsOpt,
+
+    /// Number of times the solutions are evaluated.
+    #[clap(long, short = 'n', default_value = "3")]
+    pub repetitions: usize,
+
+    /// The time limit is the slowest time of the reference solutions multiplied by this factor.
+    #[clap(long, default_value = "2")]
+    pub multiplier: f64,
+
+    /// Round the time limit up to a multiple of this value, in seconds.
+    #[clap(long, default_value = "0.1")]
+    pub round: f64,
+
+    /// Never suggest a time limit lower than this value, in seconds.
+    #[clap(long, default_value = "0.5")]
+    pub min_time_limit: f64,
+
+    /// Kill the solutions after this number of seconds, instead of running them without a time
+    /// limit.
+    #[clap(long)]
+    pub time_cap: Option<f64>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct SuggestMemoryLimitOpt {
+    #[clap(flatten)]
+    pub reference: ReferenceSolutionsOpt,
+
+    /// The memory limit is the peak memory of the reference solutions multiplied by this factor.
+    #[clap(long, default_value = "1.5")]
+    pub headroom: f64,
+
+    /// Round the memory limit up to a multiple of this value, in MiB.
+    #[clap(long, default_value = "16")]
+    pub round: u64,
+
+    /// Never suggest a memory limit lower than this value, in MiB.
+    #[clap(long, default_value = "64")]
+    pub min_memory_limit: u64,
+
+    /// Kill the solutions using more than this memory, in MiB, instead of running them without a
+    /// memory limit.
+    #[clap(long)]
+    pub memory_cap: Option<u64>,
+}
+
+/// The evaluations of the reference solutions, with the limits of the task changed.
+struct ReferenceRuns {
+    /// The task as it is on disk, with its original limits.
+    task: IOITask,
+    /// The paths of the reference solutions, relative to the task directory.
+    references: Vec<PathBuf>,
+    /// The results of each repetition.
+    runs: Vec<EvaluationResults>,
+}
+
+/// The accepted subtask of a reference solution with the slowest testcase.
+struct SlowestSubtask {
+    /// The id of the subtask.
+    id: SubtaskId,
+    /// The largest CPU time used by the solution on the subtask in each repetition.
+    times: Vec<f64>,
+}
+
+impl SlowestSubtask {
+    /// The largest time of the repetitions.
+    fn max(&self) -> f64 {
+        self.times.iter().copied().fold(0.0, f64::max)
+    }
+
+    /// The smallest time of the repetitions.
+    fn min(&self) -> f64 {
+        self.times.iter().copied().fold(f64::INFINITY, f64::min)
+    }
+}
+
+/// Evaluate the solutions `repetitions` times, changing the limits of the task with `set_limits`,
+/// and select the reference ones.
+fn run_references<F>(
+    mut opt: ReferenceSolutionsOpt,
+    logger_opt: LoggerOpt,
+    repetitions: usize,
+    set_limits: F,
+) -> Result<ReferenceRuns, Error>
+where
+    F: Fn(&mut IOITask),
+{
+    opt.ui.disable_if_needed(&logger_opt);
+    if repetitions == 0 {
+        bail!("At least one repetition is needed");
+    }
+    // the evaluations are repeated, so they cannot come from the cache
+    match &mut opt.execution.no_cache {
+        None => opt.execution.no_cache = Some(Some("evaluation".into())),
+        Some(Some(tags)) if !tags.split(',').any(|tag| tag == "evaluation") => {
+            tags.push_str(",evaluation")
+        }
+        Some(_) => {}
+    }
+    let eval_config = EvaluationConfig {
+        solution_filter: opt.filter.filter.clone(),
+        booklet_solutions: false,
+        no_statement: true,
+        solution_paths: opt.filter.solution.clone(),
+        extra_solution_paths: opt.filter.add_solution.clone(),
+        disabled_sanity_checks: Default::default(),
+        seed: Default::default(),
+        terry_cases: None,
+        terry_seeds: None,
+        dry_run: opt.execution.dry_run,
+        subtask_filter: opt.filter.subtask_ids(),
+        testcase_filter: opt.filter.testcase_ids(),
+        sample_testcases: None,
+        memory_metric: opt.execution.memory_metric,
+        verify_generators: false,
+        check_checker: false,
+    };
+
+    let mut original_task = None;
+    let mut runs = Vec::new();
+    for _ in 0..repetitions {
+        let task = opt
+            .find_task
+            .find_task(&eval_config)
+            .context("Failed to locate the task")?;
+        let TaskFormat::IOI(ioi_task) = &task else {
+            bail!("The limits can be suggested only for IOI-tasks for now");
+        };
+        original_task = Some(ioi_task.clone());
+
+        let context = RuntimeContext::new(task, &opt.execution, |task, eval| {
+            if let TaskFormat::IOI(task) = task {
+                set_limits(task);
+            }
+            task.build_dag(eval, &eval_config)
+                .context("Cannot build the task DAG")
+        })?;
+
+        let executor = context.connect_executor(&opt.execution, &opt.storage)?;
+        let collector =
+            executor.collect_results(&opt.ui.ui, |ui, message| ui.on_message(message))?;
+        runs.push(collector.results());
+    }
+
+    let explicit = !opt.filter.filter.is_empty()
+        || !opt.filter.solution.is_empty()
+        || !opt.filter.add_solution.is_empty();
+    let references = reference_solutions(&runs[0], explicit);
+    if references.is_empty() {
+        bail!(
+            "No reference solution: select them on the command line, or mark them with \
+            @check-accepted"
+        );
+    }
+    Ok(ReferenceRuns {
+        task: original_task.expect("At least one repetition"),
+        references,
+        runs,
+    })
+}
+
+pub fn main_suggest_time_limit(
+    opt: SuggestTimeLimitOpt,
+    logger_opt: LoggerOpt,
+) -> Result<(), Error> {
+    if opt.multiplier <= 0.0 || opt.round < 0.0 {
+        bail!("The multiplier must be positive and the rounding must not be negative");
+    }
+    let time_cap = opt.time_cap;
+    let ReferenceRuns {
+        task,
+        references,
+        runs,
+    } = run_references(opt.reference, logger_opt, opt.repetitions, |task| {
+        task.time_limit = time_cap;
+    })?;
+
+    let mut printer = StdoutPrinter::default();
+    let mut slowest_time: Option<f64> = None;
+    for path in &references {
+        cwrite!(printer, BOLD, "{}", path.display());
+        let mut slowest: Option<SlowestSubtask> = None;
+        let mut not_accepted = Vec::new();
+        let first = runs[0].solutions.iter().find(|s| &s.path == path);
+        for subtask in first.map(|s| s.subtasks.as_slice()).unwrap_or_default() {
+            let mut times = Vec::new();
+            for run in &runs {
+                let summary = run
+                    .solutions
+                    .iter()
+                    .find(|s| &s.path == path)
+                    .and_then(|s| s.subtasks.iter().find(|st| st.id == subtask.id))
+                    .map(|st| &st.summary);
+                match summary.and_then(|summary| Some((summary.verdict.as_ref()?, summary))) {
+                    Some((verdict, summary)) if verdict == "accepted" => {
+                        times.extend(summary.cpu_time);
+                    }
+                    Some((verdict, _)) => not_accepted.push((subtask.id, verdict.clone())),
+                    None => {}
+                }
+            }
+            let candidate = SlowestSubtask {
+                id: subtask.id,
+                times,
+            };
+            if !candidate.times.is_empty()
+                && !matches!(&slowest, Some(s) if candidate.max() <= s.max())
+            {
+                slowest = Some(candidate);
+            }
+        }
+        match &slowest {
+            Some(slowest) => {
+                println!(
+                    ": slowest on subtask {}, {:.3}s (between {:.3}s and {:.3}s in {} runs)",
+                    slowest.id,
+                    slowest.max(),
+                    slowest.min(),
+                    slowest.max(),
+                    slowest.times.len()
+                );
+                slowest_time = Some(slowest_time.unwrap_or(0.0).max(slowest.max()));
+            }
+            None => println!(": never accepted"),
+        }
+        for (subtask, verdict) in not_accepted {
+            cwrite!(printer, YELLOW, "    Warning");
+            println!(": {} on subtask {}", verdict, subtask);
+        }
+    }
+
+    let Some(slowest_time) = slowest_time else {
+        bail!("The reference solutions have never been accepted");
+    };
+    let mut time_limit = slowest_time * opt.multiplier;
+    if opt.round > 0.0 {
+        time_limit = (time_limit / opt.round - 1e-9).ceil() * opt.round;
+    }
+    time_limit = time_limit.max(opt.min_time_limit);
+    println!();
+    cwrite!(printer, BOLD, "Suggested time limit: ");
+    cwriteln!(printer, GREEN, "{}s", format_seconds(time_limit));
+    println!(
+        "{:.3}s x {}, rounded up to a multiple of {}s, at least {}s",
+        slowest_time, opt.multiplier, opt.round, opt.min_time_limit
+    );
+    if let Some(current) = task.time_limit {
+        println!("The current time limit is {}s", format_seconds(current));
+    }
+    Ok(())
+}
+
+pub fn main_suggest_memory_limit(
+    opt: SuggestMemoryLimitOpt,
+    logger_opt: LoggerOpt,
+) -> Result<(), Error> {
+    if opt.headroom < 1.0 {
+        bail!("The headroom must be at least 1");
+    }
+    let memory_cap = opt.memory_cap;
+    // the memory usage is stable between runs, a single evaluation is enough
+    let ReferenceRuns {
+        task,
+        references,
+        runs,
+    } = run_references(opt.reference, logger_opt, 1, |task| {
+        task.memory_limit = memory_cap;
+    })?;
+    let current_limit = task.memory_limit.map(|limit| limit * 1024);
+
+    let mut printer = StdoutPrinter::default();
+    let mut peak_memory: Option<u64> = None;
+    for path in &references {
+        cwrite!(printer, BOLD, "{}", path.display());
+        let Some(solution) = runs[0].solutions.iter().find(|s| &s.path == path) else {
+            println!(": not evaluated");
+            continue;
+        };
+        let mut peak: Option<(SubtaskId, u64)> = None;
+        let mut over_limit = Vec::new();
+        let mut not_accepted = Vec::new();
+        for subtask in &solution.subtasks {
+            let Some(verdict) = &subtask.summary.verdict else {
+                continue;
+            };
+            if verdict != "accepted" {
+                not_accepted.push((subtask.id, verdict.clone()));
+                continue;
+            }
+            let Some(memory) = subtask.summary.memory else {
+                continue;
+            };
+            if matches!(current_limit, Some(limit) if memory > limit) {
+                over_limit.push((subtask.id, memory));
+            }
+            if !matches!(peak, Some((_, peak)) if memory <= peak) {
+                peak = Some((subtask.id, memory));
+            }
+        }
+        match peak {
+            Some((subtask, memory)) => {
+                println!(": peak on subtask {}, {}", subtask, format_memory(memory));
+                peak_memory = Some(peak_memory.unwrap_or(0).max(memory));
+            }
+            None => println!(": never accepted"),
+        }
+        for (subtask, memory) in over_limit {
+            cwrite!(printer, RED, "    Over the limit");
+            println!(
+                ": {} on subtask {}, it fails with the current memory limit",
+                format_memory(memory),
+                subtask
+            );
+        }
+        for (subtask, verdict) in not_accepted {
+            cwrite!(printer, YELLOW, "    Warning");
+            println!(": {} on subtask {}", verdict, subtask);
+        }
+    }
+
+    let Some(peak_memory) = peak_memory else {
+        bail!("The reference solutions have never been accepted");
+    };
+    let mut memory_limit = (peak_memory as f64 / 1024.0 * opt.headroom).ceil() as u64;
+    if opt.round > 0 {
+        memory_limit = memory_limit.div_ceil(opt.round) * opt.round;
+    }
+    memory_limit = memory_limit.max(opt.min_memory_limit);
+    println!();
+    cwrite!(printer, BOLD, "Suggested memory limit: ");
+    cwriteln!(printer, GREEN, "{} MiB", memory_limit);
+    println!(
+        "{} x {}, rounded up to a multiple of {} MiB, at least {} MiB",
+        format_memory(peak_memory),
+        opt.headroom,
+        opt.round,
+        opt.min_memory_limit
+    );
+    if let Some(current) = task.memory_limit {
+        println!("The current memory limit is {} MiB", current);
+    }
+    Ok(())
+}
+
+/// The paths of the reference solutions: all the evaluated ones if they have been selected
+/// explicitly, otherwise the ones whose checks all expect them to be accepted.
+fn reference_solutions(results: &EvaluationResults, explicit: bool) -> Vec<PathBuf> {
+    results
+        .solutions
+        .iter()
+        .filter(|solution| {
+            if explicit {
+                return true;
+            }
+            let mut checks = results
+                .checks
+                .iter()
+                .filter(|check| check.solution == solution.path)
+                .peekable();
+            checks.peek().is_some() && checks.all(|check| check.expected == "accepted")
+        })
+        .map(|solution| solution.path.clone())
+        .collect()
+}
+
+/// Format a number of seconds, without the trailing zeros.
+fn format_seconds(seconds: f64) -> String {
+    let formatted = format!("{:.3}", seconds);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Format an amount of memory in KiB as MiB.
+fn format_memory(kib: u64) -> String {
+    format!("{:.1} MiB", kib as f64 / 1024.0)
+}
@@ -7,11 +7,17 @@ use anyhow::Error;
 use regex::Regex;
 use typescript_definitions::TypeScriptifyTrait;
 
-use task_maker_dag::{ExecutionResourcesUsage, ExecutionResult, ExecutionStatus, File};
-use task_maker_exec::{ClientInfo, ExecutorStatus, ExecutorWorkerStatus, WorkerCurrentJobStatus};
+use task_maker_dag::{
+    ExecutionResourcesUsage, ExecutionResult, ExecutionStatus, File, MemoryMetric, TimeClock,
+};
+use task_maker_exec::{
+    ClientInfo, ExecutorStatus, ExecutorWorkerStatus, FileTransfer, WorkerCurrentJobStatus,
+    WorkerTelemetry,
+};
 use task_maker_format::ioi::{
-    BatchTypeData, Booklet, BookletConfig, Checker, CommunicationTypeData, IOITask, InputGenerator,
-    InputValidator, OutputGenerator, Statement, StatementConfig, SubtaskInfo, TaskInfoScoring,
+    BatchTypeData, Booklet, BookletConfig, Checker, CommunicationTypeData, IOITask,
+    InputFormatLevel, InputFormatPolicy, InputGenerator, InputValidator, LanguageMultiplier,
+    OutputGenerator, OutputNormalization, Statement, StatementConfig, SubtaskInfo, TaskInfoScoring,
     TaskInfoStatement, TaskType, TestcaseInfo,
 };
 use task_maker_format::ioi::{IOITaskInfo, TaskInfoAttachment, TaskInfoLimits, TaskInfoSubtask};
@@ -54,10 +60,15 @@ pub fn main_typescriptify() -> Result<(), Error> {
     export_ts!(UIMessage);
     export_ts!(UIExecutionStatus);
     export_ts!(ExecutorStatus<SystemTime>);
+    export_ts!(FileTransfer);
     export_ts!(ExecutorWorkerStatus<SystemTime>);
     export_ts!(WorkerCurrentJobStatus<SystemTime>);
+    export_ts!(WorkerTelemetry);
     export_ts!(ClientInfo);
     export_ts!(IOITask);
+    export_ts!(LanguageMultiplier);
+    export_ts!(InputFormatPolicy);
+    export_ts!(InputFormatLevel);
     export_ts!(TerryTask);
     export_ts!(SolutionOutcome);
     export_ts!(ExecutionResult);
@@ -79,9 +90,12 @@ pub fn main_typescriptify() -> Result<(), Error> {
     export_ts!(SolutionFeedbackCase);
     export_ts!(ExecutionStatus);
     export_ts!(ExecutionResourcesUsage);
+    export_ts!(TimeClock);
+    export_ts!(MemoryMetric);
     export_ts!(BatchTypeData);
     export_ts!(CommunicationTypeData);
     export_ts!(Checker);
+    export_ts!(OutputNormalization);
     export_ts!(SourceFile);
     export_ts!(InputGenerator);
     export_ts!(InputValidator);
@@ -3,9 +3,10 @@ use std::sync::Arc;
 use anyhow::{bail, Context, Error};
 use clap::Parser;
 
+use task_maker_exec::benchmark::{benchmark_time, time_scale, REFERENCE_BENCHMARK_TIME};
+use task_maker_exec::cpu_pinning::CpuPinning;
 use task_maker_exec::executors::{RemoteEntityMessage, RemoteEntityMessageResponse};
-use task_maker_exec::Worker;
-use task_maker_store::FileStore;
+use task_maker_exec::{SandboxPool, Worker};
 
 use crate::remote::connect_to_remote_server;
 use crate::sandbox::ToolsSandboxRunner;
@@ -23,6 +24,35 @@ pub struct WorkerOpt {
     #[clap(long)]
     pub name: Option<String>,
 
+    /// Multiply the time limits of the executions by this factor, to compensate for a machine
+    /// slower (> 1) or faster (< 1) than the reference contest hardware.
+    #[clap(long, conflicts_with = "benchmark")]
+    pub time_scale: Option<f64>,
+
+    /// Measure the speed of this machine at startup and scale the time limits accordingly.
+    #[clap(long)]
+    pub benchmark: bool,
+
+    /// Time, in seconds, the benchmark takes on the reference contest hardware.
+    #[clap(long, default_value_t = REFERENCE_BENCHMARK_TIME, requires = "benchmark")]
+    pub benchmark_reference: f64,
+
+    /// Keep this many sandbox directories ready to be reused, reducing the overhead of tasks with
+    /// many tiny executions. Zero disables the pool.
+    #[clap(long, default_value_t = 0)]
+    pub sandbox_pool: usize,
+
+    /// Pin the executions to a dedicated CPU, chosen by the ID of the worker, to make the measured
+    /// times more stable: none, core (a logical CPU) or isolated (a physical core, leaving its
+    /// hyperthread siblings unused).
+    #[clap(long, default_value = "none")]
+    pub cpu_pinning: CpuPinning,
+
+    /// Size, in MiB, the store is shrunk to when the server asks to collect garbage. Defaults to
+    /// `--min-cache`
+    #[clap(long, help_heading = Some("STORAGE"))]
+    pub gc_retention: Option<u64>,
+
     #[clap(flatten, next_help_heading = Some("STORAGE"))]
     pub storage: StorageOpt,
 }
@@ -32,16 +62,27 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Entry point for the worker.
 pub fn main_worker(opt: WorkerOpt) -> Result<(), Error> {
-    let store_path = opt.storage.store_dir();
     let file_store = Arc::new(
-        FileStore::new(
-            store_path.join("store"),
-            opt.storage.max_cache * 1024 * 1024,
-            opt.storage.min_cache * 1024 * 1024,
-        )
-        .context("Cannot create the file store")?,
+        opt.storage
+            .file_store()
+            .context("Cannot create the file store")?,
     );
-    let sandbox_path = store_path.join("sandboxes");
+    let sandbox_path = opt.storage.sandbox_dir();
+
+    let time_scale = if opt.benchmark {
+        let measured = benchmark_time();
+        let scale = time_scale(measured, opt.benchmark_reference);
+        info!(
+            "Benchmark took {:.3}s (reference {:.3}s), time limits scaled by {:.3}",
+            measured, opt.benchmark_reference, scale
+        );
+        scale
+    } else {
+        opt.time_scale.unwrap_or(1.0)
+    };
+    if time_scale <= 0.0 || !time_scale.is_finite() {
+        bail!("Invalid time scale: {}", time_scale);
+    }
 
     let name = opt.name.unwrap_or_else(|| {
         format!(
@@ -58,12 +99,15 @@ pub fn main_worker(opt: WorkerOpt) -> Result<(), Error> {
             version: VERSION.into(),
         })
         .context("Cannot send welcome to the server")?;
-    if let RemoteEntityMessageResponse::Rejected(err) = executor_rx
+    let compression = match executor_rx
         .recv()
         .context("Remote executor didn't reply to the welcome message")?
     {
-        bail!("The server rejected the worker connection: {}", err);
-    }
+        RemoteEntityMessageResponse::Accepted { compression } => compression,
+        RemoteEntityMessageResponse::Rejected(err) => {
+            bail!("The server rejected the worker connection: {}", err);
+        }
+    };
 
     let name = if let Some(wid) = opt.worker_id {
         format!("{} {}", name, wid)
@@ -71,14 +115,30 @@ pub fn main_worker(opt: WorkerOpt) -> Result<(), Error> {
         name
     };
 
-    let worker = Worker::new_with_channel(
+    let mut worker = Worker::new_with_channel(
         name,
         file_store,
-        sandbox_path,
+        &sandbox_path,
         executor_tx.change_type(),
         executor_rx.change_type(),
         Arc::new(ToolsSandboxRunner::default()),
     )
     .context("Failed to start worker")?;
+    worker.set_time_scale(time_scale);
+    worker.set_advertise_store(true);
+    worker.set_transfer_compression(compression);
+    worker.set_gc_retention(opt.gc_retention.unwrap_or(opt.storage.min_cache) * 1024 * 1024);
+    if opt.sandbox_pool > 0 {
+        let pool = SandboxPool::new(&sandbox_path, opt.sandbox_pool)
+            .context("Failed to create the sandbox pool")?;
+        worker.set_sandbox_pool(Arc::new(pool));
+    }
+    if let Some(cpu) = opt
+        .cpu_pinning
+        .assign_cpu(opt.worker_id.unwrap_or(0) as usize)
+    {
+        info!("Pinning the executions to CPU {}", cpu);
+        worker.set_pinned_cpu(cpu);
+    }
     worker.work()
 }
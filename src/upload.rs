@@ -0,0 +1,230 @@
+//! Upload of the results of the evaluations to a central collector.
+//!
+//! When the evaluation finishes a [`ResultsUpload`] is sent with a POST request to the collector,
+//! authenticated with a bearer token. Alongside the results it identifies the task (its name and
+//! its path in the git repository), the commit being evaluated and the client, so that the health
+//! of a whole task archive can be tracked over time.
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Error};
+use serde::Serialize;
+
+use task_maker_format::results::EvaluationResults;
+
+/// Timeout of the request to the collector.
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The body of the request sent to the collector.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResultsUpload<'a> {
+    /// The name of the task.
+    pub task: &'a str,
+    /// The git repository of the task, if it's inside one.
+    pub git: Option<GitInfo>,
+    /// The name of the client that evaluated the task.
+    pub client: String,
+    /// The version of task-maker.
+    pub version: &'static str,
+    /// When the evaluation started, in seconds since the Unix epoch.
+    pub started_at: u64,
+    /// How long the evaluation took, in seconds.
+    pub duration: f64,
+    /// The results of the evaluation, with the timings of each testcase.
+    pub results: &'a EvaluationResults,
+}
+
+/// The state of the git repository containing the task.
+#[derive(Debug, Clone, Serialize)]
+pub struct GitInfo {
+    /// The hash of the commit checked out.
+    pub commit: String,
+    /// Whether the task has uncommitted changes.
+    pub dirty: bool,
+    /// The path of the task directory, relative to the root of the repository.
+    pub path: String,
+}
+
+impl GitInfo {
+    /// The state of the git repository containing the task directory, if git is available and the
+    /// directory is inside a repository.
+    pub fn of_task(task_dir: &Path) -> Option<GitInfo> {
+        let git = |args: &[&str]| -> Option<String> {
+            let output = Command::new("git")
+                .args(args)
+                .current_dir(task_dir)
+                .output()
+                .ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        };
+        let commit = git(&["rev-parse", "HEAD"])?;
+        let prefix = git(&["rev-parse", "--show-prefix"])?;
+        let status = git(&["status", "--porcelain", "--", "."])?;
+        Some(GitInfo {
+            commit,
+            dirty: !status.is_empty(),
+            path: prefix.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+/// Upload the results of an evaluation of the task in `task_dir` to the collector at `url`.
+pub fn upload_results(
+    url: &str,
+    token: Option<&str>,
+    task_dir: &Path,
+    client: Option<&str>,
+    started_at: SystemTime,
+    results: &EvaluationResults,
+) -> Result<(), Error> {
+    let body = ResultsUpload {
+        task: &results.task,
+        git: GitInfo::of_task(task_dir),
+        client: client.map(String::from).unwrap_or_else(|| {
+            format!(
+                "{}@{}",
+                whoami::username(),
+                whoami::fallible::hostname().unwrap_or_default()
+            )
+        }),
+        version: env!("CARGO_PKG_VERSION"),
+        started_at: started_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        duration: started_at.elapsed().unwrap_or_default().as_secs_f64(),
+        results,
+    };
+    let agent = ureq::AgentBuilder::new().timeout(UPLOAD_TIMEOUT).build();
+    let mut request = agent.post(url);
+    if let Some(token) = token {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+    request.send_json(&body).map_err(|e| match e {
+        ureq::Error::Status(401 | 403, _) => {
+            anyhow!("The collector {} rejected the token", url)
+        }
+        ureq::Error::Status(code, _) => {
+            anyhow!("The collector {} replied with status {}", url, code)
+        }
+        ureq::Error::Transport(e) => {
+            anyhow!("Failed to upload the results to {}: {}", url, e)
+        }
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    use super::*;
+
+    fn results() -> EvaluationResults {
+        EvaluationResults {
+            task: "poldo".into(),
+            max_score: 100.0,
+            solutions: vec![],
+            checks: vec![],
+            errors: vec![],
+            warnings: vec![],
+        }
+    }
+
+    /// Accept a single request, reply with `status` and return the raw request received.
+    fn serve_once(status: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/results", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            loop {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text[..end]
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length || n == 0 {
+                        break;
+                    }
+                }
+            }
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_upload_request() {
+        let (url, handle) = serve_once("200 OK");
+        let dir = tempfile::TempDir::new().unwrap();
+        let results = results();
+        upload_results(
+            &url,
+            Some("secret"),
+            dir.path(),
+            Some("alice"),
+            SystemTime::now(),
+            &results,
+        )
+        .unwrap();
+        let request = handle.join().unwrap();
+        assert!(request.starts_with("POST /results "));
+        assert!(request.contains("Authorization: Bearer secret\r\n"));
+        let (_, body) = request.split_once("\r\n\r\n").unwrap();
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["task"], "poldo");
+        assert_eq!(body["client"], "alice");
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(body["results"]["task"], "poldo");
+    }
+
+    #[test]
+    fn test_upload_rejected_token() {
+        let (url, handle) = serve_once("401 Unauthorized");
+        let dir = tempfile::TempDir::new().unwrap();
+        let err = upload_results(
+            &url,
+            Some("wrong"),
+            dir.path(),
+            None,
+            SystemTime::now(),
+            &results(),
+        )
+        .unwrap_err();
+        handle.join().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!("The collector {} rejected the token", url)
+        );
+    }
+
+    #[test]
+    fn test_upload_server_error() {
+        let (url, handle) = serve_once("500 Internal Server Error");
+        let dir = tempfile::TempDir::new().unwrap();
+        let err = upload_results(&url, None, dir.path(), None, SystemTime::now(), &results())
+            .unwrap_err();
+        handle.join().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!("The collector {} replied with status 500", url)
+        );
+    }
+}
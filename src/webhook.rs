@@ -0,0 +1,126 @@
+//! Notification of the results of an evaluation to a webhook.
+//!
+//! When the evaluation finishes the results are sent with a POST request to the URL of the webhook.
+//! The body of the request is the JSON of the results, the same written by `--results`, unless a
+//! template is specified. In the template the placeholders are replaced by their JSON value:
+//!
+//! - `{task}`: the name of the task;
+//! - `{scores}`: the score table, a list of objects with the `solution` and its `score`;
+//! - `{errors}` and `{warnings}`: the lists of the messages emitted during the evaluation;
+//! - `{results}`: all the results of the evaluation.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Error};
+use serde_json::json;
+
+use task_maker_format::results::EvaluationResults;
+
+/// Timeout of the request to the webhook.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Send the results of an evaluation to the webhook at `url`, using the template of the body at
+/// `template`, if any.
+pub fn send_webhook(
+    url: &str,
+    template: Option<&Path>,
+    results: &EvaluationResults,
+) -> Result<(), Error> {
+    let body = match template {
+        Some(path) => {
+            let template = std::fs::read_to_string(path).with_context(|| {
+                format!("Failed to read the webhook template {}", path.display())
+            })?;
+            render_template(&template, results)?
+        }
+        None => serde_json::to_string(results).context("Failed to serialize the results")?,
+    };
+    let agent = ureq::AgentBuilder::new().timeout(WEBHOOK_TIMEOUT).build();
+    agent
+        .post(url)
+        .set("Content-Type", "application/json")
+        .send_string(&body)
+        .with_context(|| format!("Failed to send the results to the webhook {}", url))?;
+    Ok(())
+}
+
+/// Replace the placeholders of the template with the JSON of their values.
+///
+/// The template is scanned once from left to right, so the placeholders inside the inserted values
+/// (e.g. a warning mentioning `{results}`) are left as they are.
+fn render_template(template: &str, results: &EvaluationResults) -> Result<String, Error> {
+    let scores: Vec<_> = results
+        .solutions
+        .iter()
+        .map(|solution| json!({ "solution": solution.path, "score": solution.score }))
+        .collect();
+    let placeholders = [
+        ("{task}", json!(results.task)),
+        ("{scores}", json!(scores)),
+        ("{errors}", json!(results.errors)),
+        ("{warnings}", json!(results.warnings)),
+        ("{results}", serde_json::to_value(results)?),
+    ];
+    let mut body = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        body.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match placeholders
+            .iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+        {
+            Some((placeholder, value)) => {
+                body.push_str(&value.to_string());
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                body.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    body.push_str(rest);
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results() -> EvaluationResults {
+        EvaluationResults {
+            task: "poldo".into(),
+            max_score: 100.0,
+            solutions: vec![],
+            checks: vec![],
+            errors: vec![],
+            warnings: vec!["the template uses {results} and {task}".into()],
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        let body = render_template(r#"{"name": {task}, "scores": {scores}}"#, &results()).unwrap();
+        assert_eq!(body, r#"{"name": "poldo", "scores": []}"#);
+    }
+
+    #[test]
+    fn test_render_template_repeated() {
+        let body = render_template("{task}{task}", &results()).unwrap();
+        assert_eq!(body, r#""poldo""poldo""#);
+    }
+
+    #[test]
+    fn test_render_template_unknown_braces() {
+        let body = render_template("{ {unknown} {task}", &results()).unwrap();
+        assert_eq!(body, r#"{ {unknown} "poldo""#);
+    }
+
+    #[test]
+    fn test_render_template_no_substitution_in_values() {
+        let body = render_template("{warnings}", &results()).unwrap();
+        assert_eq!(body, r#"["the template uses {results} and {task}"]"#);
+    }
+}
@@ -0,0 +1,267 @@
+//! Portable bundles of cache entries, for moving a warm cache between machines without sharing the
+//! whole store (e.g. as the artifacts of the jobs of a CI).
+//!
+//! A bundle is a zstd-compressed stream containing the magic string of the cache, the entries, and
+//! then the content of each file produced by them, preceded by its key. The stream ends with a
+//! `None` in place of the next file.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Error};
+
+use task_maker_store::{FileStore, FileStoreKey};
+
+use crate::entry::CacheEntry;
+use crate::key::CacheKey;
+use crate::storage::{share_file, MAGIC};
+use crate::Cache;
+
+/// The compression level of the bundles.
+const BUNDLE_COMPRESSION_LEVEL: i32 = 3;
+
+/// The number of entries and of files exported to, or imported from, a bundle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BundleStats {
+    /// The number of cache entries.
+    pub entries: usize,
+    /// The number of files produced by the entries.
+    pub files: usize,
+}
+
+impl Cache {
+    /// Write to `writer` a bundle with the entries used by the last run that touched the cache, or
+    /// with all the entries if `all` is set. The entries whose files are not in the store anymore
+    /// are skipped.
+    pub fn export_bundle<W: Write>(
+        &self,
+        file_store: &FileStore,
+        writer: W,
+        all: bool,
+    ) -> Result<BundleStats, Error> {
+        let last_run: HashSet<CacheKey> = if all {
+            HashSet::new()
+        } else {
+            load_last_run(&self.last_run_path)?.into_iter().collect()
+        };
+        let mut entries: Vec<(&CacheKey, Vec<&CacheEntry>)> = Vec::new();
+        let mut files = HashSet::new();
+        for (key, set) in self.file.iter() {
+            if !all && !last_run.contains(key) {
+                continue;
+            }
+            let available: Vec<_> = set
+                .iter()
+                .filter(|entry| entry.is_available(file_store))
+                .collect();
+            if available.is_empty() {
+                continue;
+            }
+            files.extend(available.iter().flat_map(|entry| entry.files()));
+            entries.push((key, available));
+        }
+
+        let mut encoder = zstd::Encoder::new(writer, BUNDLE_COMPRESSION_LEVEL)
+            .context("Failed to create the bundle encoder")?;
+        encoder
+            .write_all(MAGIC)
+            .context("Failed to write the bundle magic number")?;
+        bincode::serialize_into(&mut encoder, &entries)
+            .context("Failed to write the bundle entries")?;
+        for key in &files {
+            let handle = file_store
+                .get(key)
+                .with_context(|| format!("File {} disappeared from the store", key))?;
+            let content: Vec<u8> = handle.read()?.flatten().collect();
+            bincode::serialize_into(&mut encoder, &Some((key, content)))
+                .context("Failed to write a file in the bundle")?;
+        }
+        bincode::serialize_into(&mut encoder, &None::<(FileStoreKey, Vec<u8>)>)
+            .context("Failed to write the end of the bundle")?;
+        encoder
+            .finish()
+            .context("Failed to finish the bundle")?
+            .flush()
+            .context("Failed to flush the bundle")?;
+        Ok(BundleStats {
+            entries: entries.iter().map(|(_, set)| set.len()).sum(),
+            files: files.len(),
+        })
+    }
+
+    /// Read a bundle written by [`export_bundle`](struct.Cache.html#method.export_bundle), adding
+    /// its files to the store and its entries to the cache.
+    pub fn import_bundle<R: Read>(
+        &mut self,
+        file_store: &FileStore,
+        reader: R,
+    ) -> Result<BundleStats, Error> {
+        let mut decoder =
+            zstd::Decoder::new(reader).context("Failed to create the bundle decoder")?;
+        let mut magic = vec![0; MAGIC.len()];
+        decoder
+            .read_exact(&mut magic)
+            .context("Failed to read the bundle magic number")?;
+        if magic != MAGIC {
+            bail!(
+                "The bundle has been exported by another version of task-maker ({:?})",
+                String::from_utf8_lossy(&magic).trim()
+            );
+        }
+        let entries: Vec<(CacheKey, Vec<CacheEntry>)> =
+            bincode::deserialize_from(&mut decoder).context("Failed to read the bundle entries")?;
+        // keep the handles until the entries are added, so that the files are not flushed away
+        let mut handles = HashMap::new();
+        while let Some((key, content)) =
+            bincode::deserialize_from::<_, Option<(FileStoreKey, Vec<u8>)>>(&mut decoder)
+                .context("Failed to read a file of the bundle")?
+        {
+            if FileStoreKey::from_content(&content) != key {
+                bail!("The bundle contains a corrupted file: {}", key);
+            }
+            let handle = file_store
+                .store(&key, std::iter::once(content))
+                .with_context(|| format!("Failed to store file {}", key))?;
+            handles.insert(key, handle);
+        }
+
+        let mut stats = BundleStats {
+            entries: 0,
+            files: handles.len(),
+        };
+        for (key, set) in entries {
+            let set: Vec<_> = set
+                .into_iter()
+                .filter(|entry| entry.is_available(file_store))
+                .collect();
+            stats.entries += set.len();
+            if self.file.merge_entries(key, set) {
+                self.file.mark_dirty();
+            }
+        }
+        Ok(stats)
+    }
+}
+
+/// Read the keys used by the last run that touched the cache.
+fn load_last_run(path: &Path) -> Result<Vec<CacheKey>, Error> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    match content.strip_prefix(MAGIC) {
+        Some(content) => bincode::deserialize(content)
+            .with_context(|| format!("Failed to deserialize {}", path.display())),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Write the keys used by this run, replacing the ones of the previous run.
+pub(crate) fn store_last_run(
+    path: &Path,
+    keys: &HashSet<CacheKey>,
+    shared: bool,
+) -> Result<(), Error> {
+    let mut content = MAGIC.to_vec();
+    bincode::serialize_into(&mut content, &keys.iter().collect::<Vec<_>>())
+        .context("Failed to serialize the keys of the last run")?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, content).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    if shared {
+        share_file(&tmp);
+    }
+    std::fs::rename(&tmp, path)
+        .with_context(|| format!("Failed to move {} -> {}", tmp.display(), path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use task_maker_dag::{
+        Execution, ExecutionCommand, ExecutionGroup, ExecutionResult, ExecutionStatus,
+    };
+    use task_maker_store::ReadFileIterator;
+
+    use crate::CacheResult;
+
+    #[test]
+    fn test_export_import() {
+        let mut exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        let stdout = exec.stdout().uuid;
+        let group: ExecutionGroup = exec.into();
+        let result = ExecutionResult {
+            status: ExecutionStatus::Success,
+            was_killed: false,
+            was_cached: false,
+            resources: Default::default(),
+            stdout: None,
+            stderr: None,
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
+        };
+
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path().join("store"), 1000, 1000).unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let path = tmpdir.path().join("stdout");
+        std::fs::write(&path, "hello").unwrap();
+        let key = FileStoreKey::from_file(&path).unwrap();
+        let handle = store
+            .store(&key, ReadFileIterator::new(&path).unwrap())
+            .unwrap();
+        let mut file_keys = HashMap::new();
+        file_keys.insert(stdout, handle);
+        cache.insert(&group, &file_keys, vec![result]);
+        drop(cache);
+
+        // the entries inserted by the previous run are exported
+        let cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let mut bundle = Vec::new();
+        let stats = cache.export_bundle(&store, &mut bundle, false).unwrap();
+        assert_eq!(
+            stats,
+            BundleStats {
+                entries: 1,
+                files: 1
+            }
+        );
+
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path().join("store"), 1000, 1000).unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let stats = cache.import_bundle(&store, &bundle[..]).unwrap();
+        assert_eq!(
+            stats,
+            BundleStats {
+                entries: 1,
+                files: 1
+            }
+        );
+        match cache.get(&group, &HashMap::new(), &store) {
+            CacheResult::Hit { outputs, .. } => assert_eq!(outputs[&stdout].key(), &key),
+            CacheResult::Miss => panic!("Expecting a hit from the imported bundle"),
+        }
+    }
+
+    #[test]
+    fn test_export_nothing_without_last_run() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path().join("store"), 1000, 1000).unwrap();
+        let cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let mut bundle = Vec::new();
+        let stats = cache.export_bundle(&store, &mut bundle, false).unwrap();
+        assert_eq!(stats, BundleStats::default());
+        let mut cache = Cache::new(tmpdir.path().join("other")).unwrap();
+        assert_eq!(
+            cache.import_bundle(&store, &bundle[..]).unwrap(),
+            BundleStats::default()
+        );
+        assert!(cache.import_bundle(&store, &b"garbage"[..]).is_err());
+    }
+}
@@ -149,6 +149,22 @@ impl CacheEntry {
         Some(outputs)
     }
 
+    /// Whether all the files produced by the executions are still in the file store. Unlike
+    /// `outputs`, this doesn't need the group and doesn't count as an access to the files.
+    pub fn is_available(&self, file_store: &FileStore) -> bool {
+        self.files().all(|key| file_store.contains(key))
+    }
+
+    /// The keys of all the files produced by the executions.
+    pub fn files(&self) -> impl Iterator<Item = &FileStoreKey> {
+        self.items.iter().flat_map(|item| {
+            item.stdout
+                .iter()
+                .chain(item.stderr.iter())
+                .chain(item.outputs.values())
+        })
+    }
+
     /// Checks whether a given execution is compatible with the limits stored in this entry. See the
     /// docs of the crate for the definition of _compatible_.
     pub fn is_compatible(&self, group: &ExecutionGroup) -> bool {
@@ -187,6 +203,13 @@ impl CacheEntry {
                 if $left.mount_tmpfs > $right.mount_tmpfs {
                     return false;
                 }
+                // the same resources may have a different verdict with another clock
+                if $left.time_clock != $right.time_clock {
+                    return false;
+                }
+                if $left.memory_metric != $right.memory_metric {
+                    return false;
+                }
                 let left_readable_dirs: HashSet<PathBuf> =
                     $left.extra_readable_dirs.iter().cloned().collect();
                 let right_readable_dirs: HashSet<PathBuf> =
@@ -265,6 +288,10 @@ mod tests {
                         },
                         stdout: None,
                         stderr: None,
+                        time_scale: 1.0,
+                        cpu: None,
+                        run_times: vec![],
+                        memory_metric: Default::default(),
                     },
                     limits: Default::default(),
                     extra_time: exec.config().extra_time,
@@ -315,6 +342,22 @@ mod tests {
         assert_eq!(entry.outputs(&store, &exec.into()), None);
     }
 
+    #[test]
+    fn test_is_available() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path(), 1000, 1000).unwrap();
+
+        let (mut entry, _) = empty_entry();
+        assert!(entry.is_available(&store));
+        let hdl = fake_file(tmpdir.path().join("file"), "file", &store);
+        entry.items[0]
+            .outputs
+            .insert(PathBuf::from("out"), hdl.key().clone());
+        assert!(entry.is_available(&store));
+        entry.items[0].stderr = Some(FileStoreKey::from_content(&[1, 2, 3]));
+        assert!(!entry.is_available(&store));
+    }
+
     #[test]
     fn test_outputs_stderr() {
         let tmpdir = tempfile::TempDir::new().unwrap();
@@ -21,6 +21,10 @@ struct CacheKeyItem {
     pub inputs: Vec<(PathBuf, FileStoreKey, bool)>,
     /// The list of environment variables to set. Sorted by the variable name.
     pub env: Vec<(String, String)>,
+    /// The fingerprint of the toolchain of a system command (e.g. a compiler or an interpreter),
+    /// as reported by the workers, so that upgrading it invalidates the executions that used the
+    /// old one. `None` if the command is not a system one, or if no worker reported it.
+    pub toolchain: Option<String>,
 }
 
 /// The cache key used to address the cache entries. It is composed by a key item for each execution
@@ -33,11 +37,13 @@ pub struct CacheKey {
 
 impl CacheKeyItem {
     /// Make a new `CacheKeyItem` based on an `Execution` and on the mapping of its input files,
-    /// from the UUIDs of the current DAG to the persisted `FileStoreKey`s.
+    /// from the UUIDs of the current DAG to the persisted `FileStoreKey`s. `toolchains` maps the
+    /// system commands to the fingerprint of their toolchain.
     pub fn from_execution(
         execution: &Execution,
         file_keys: &HashMap<FileUuid, FileStoreHandle>,
         group: Option<&ExecutionGroup>,
+        toolchains: &HashMap<PathBuf, String>,
     ) -> CacheKeyItem {
         let stdin = execution.stdin.as_ref().map(|f| file_keys[f].key().clone());
         let inputs = execution
@@ -67,6 +73,10 @@ impl CacheKeyItem {
             execution.args.clone()
         };
         CacheKeyItem {
+            toolchain: match &execution.command {
+                ExecutionCommand::System(command) => toolchains.get(command).cloned(),
+                ExecutionCommand::Local(_) | ExecutionCommand::Builtin(_) => None,
+            },
             command: execution.command.clone(),
             args,
             stdin,
@@ -78,16 +88,18 @@ impl CacheKeyItem {
 
 impl CacheKey {
     /// Make a new `CacheKey` based on an `Execution` and on the mapping of its input files, from
-    /// the UUIDs of the current DAG to the persisted `FileStoreKey`s.
+    /// the UUIDs of the current DAG to the persisted `FileStoreKey`s, and from the system commands
+    /// to the fingerprint of their toolchain.
     pub fn from_execution_group(
         group: &ExecutionGroup,
         file_keys: &HashMap<FileUuid, FileStoreHandle>,
+        toolchains: &HashMap<PathBuf, String>,
     ) -> CacheKey {
         CacheKey {
             items: group
                 .executions
                 .iter()
-                .map(|e| CacheKeyItem::from_execution(e, file_keys, Some(group)))
+                .map(|e| CacheKeyItem::from_execution(e, file_keys, Some(group), toolchains))
                 .collect(),
         }
     }
@@ -126,10 +138,10 @@ mod tests {
         let exec2 = Execution::new("exec2", ExecutionCommand::local("foo"));
         let exec3 = Execution::new("exec3", ExecutionCommand::local("bar"));
         let exec4 = Execution::new("exec4", ExecutionCommand::system("foo"));
-        let key1 = CacheKeyItem::from_execution(&exec1, &HashMap::new(), None);
-        let key2 = CacheKeyItem::from_execution(&exec2, &HashMap::new(), None);
-        let key3 = CacheKeyItem::from_execution(&exec3, &HashMap::new(), None);
-        let key4 = CacheKeyItem::from_execution(&exec4, &HashMap::new(), None);
+        let key1 = CacheKeyItem::from_execution(&exec1, &HashMap::new(), None, &HashMap::new());
+        let key2 = CacheKeyItem::from_execution(&exec2, &HashMap::new(), None, &HashMap::new());
+        let key3 = CacheKeyItem::from_execution(&exec3, &HashMap::new(), None, &HashMap::new());
+        let key4 = CacheKeyItem::from_execution(&exec4, &HashMap::new(), None, &HashMap::new());
         assert_eq!(key1, key2);
         assert_ne!(key1, key3);
         assert_ne!(key1, key4);
@@ -148,10 +160,10 @@ mod tests {
         exec3.args(vec!["baz", "bar"]);
         let mut exec4 = Execution::new("exec4", ExecutionCommand::local("foo"));
         exec4.args(vec!["bar", "bar"]);
-        let key1 = CacheKeyItem::from_execution(&exec1, &HashMap::new(), None);
-        let key2 = CacheKeyItem::from_execution(&exec2, &HashMap::new(), None);
-        let key3 = CacheKeyItem::from_execution(&exec3, &HashMap::new(), None);
-        let key4 = CacheKeyItem::from_execution(&exec4, &HashMap::new(), None);
+        let key1 = CacheKeyItem::from_execution(&exec1, &HashMap::new(), None, &HashMap::new());
+        let key2 = CacheKeyItem::from_execution(&exec2, &HashMap::new(), None, &HashMap::new());
+        let key3 = CacheKeyItem::from_execution(&exec3, &HashMap::new(), None, &HashMap::new());
+        let key4 = CacheKeyItem::from_execution(&exec4, &HashMap::new(), None, &HashMap::new());
         assert_eq!(key1, key2);
         assert_ne!(key1, key3);
         assert_ne!(key1, key4);
@@ -179,10 +191,10 @@ mod tests {
         let mut exec3 = Execution::new("exec3", ExecutionCommand::local("foo"));
         exec3.stdin(file2.uuid);
         let exec4 = Execution::new("exec4", ExecutionCommand::local("foo"));
-        let key1 = CacheKeyItem::from_execution(&exec1, &map, None);
-        let key2 = CacheKeyItem::from_execution(&exec2, &map, None);
-        let key3 = CacheKeyItem::from_execution(&exec3, &map, None);
-        let key4 = CacheKeyItem::from_execution(&exec4, &map, None);
+        let key1 = CacheKeyItem::from_execution(&exec1, &map, None, &HashMap::new());
+        let key2 = CacheKeyItem::from_execution(&exec2, &map, None, &HashMap::new());
+        let key3 = CacheKeyItem::from_execution(&exec3, &map, None, &HashMap::new());
+        let key4 = CacheKeyItem::from_execution(&exec4, &map, None, &HashMap::new());
         assert_eq!(key1, key2);
         assert_ne!(key1, key3);
         assert_ne!(key1, key4);
@@ -214,10 +226,10 @@ mod tests {
         let mut exec4 = Execution::new("exec4", ExecutionCommand::local("foo"));
         exec4.input(file1.uuid, "file1", true);
         exec4.input(file2.uuid, "file2", false);
-        let key1 = CacheKeyItem::from_execution(&exec1, &map, None);
-        let key2 = CacheKeyItem::from_execution(&exec2, &map, None);
-        let key3 = CacheKeyItem::from_execution(&exec3, &map, None);
-        let key4 = CacheKeyItem::from_execution(&exec4, &map, None);
+        let key1 = CacheKeyItem::from_execution(&exec1, &map, None, &HashMap::new());
+        let key2 = CacheKeyItem::from_execution(&exec2, &map, None, &HashMap::new());
+        let key3 = CacheKeyItem::from_execution(&exec3, &map, None, &HashMap::new());
+        let key4 = CacheKeyItem::from_execution(&exec4, &map, None, &HashMap::new());
         assert_eq!(key1, key2);
         assert_ne!(key1, key3);
         assert_ne!(key1, key4);
@@ -239,10 +251,10 @@ mod tests {
         exec3.env("baz", "bizarre");
         let mut exec4 = Execution::new("exec4", ExecutionCommand::local("foo"));
         exec4.env("foo", "bar");
-        let key1 = CacheKeyItem::from_execution(&exec1, &HashMap::new(), None);
-        let key2 = CacheKeyItem::from_execution(&exec2, &HashMap::new(), None);
-        let key3 = CacheKeyItem::from_execution(&exec3, &HashMap::new(), None);
-        let key4 = CacheKeyItem::from_execution(&exec4, &HashMap::new(), None);
+        let key1 = CacheKeyItem::from_execution(&exec1, &HashMap::new(), None, &HashMap::new());
+        let key2 = CacheKeyItem::from_execution(&exec2, &HashMap::new(), None, &HashMap::new());
+        let key3 = CacheKeyItem::from_execution(&exec3, &HashMap::new(), None, &HashMap::new());
+        let key4 = CacheKeyItem::from_execution(&exec4, &HashMap::new(), None, &HashMap::new());
         assert_eq!(key1, key2);
         assert_ne!(key1, key3);
         assert_ne!(key1, key4);
@@ -262,7 +274,8 @@ mod tests {
         ]);
         group.add_execution(exec);
         let exec = &group.executions[0];
-        let key = CacheKeyItem::from_execution(exec, &HashMap::new(), Some(&group));
+        let key =
+            CacheKeyItem::from_execution(exec, &HashMap::new(), Some(&group), &HashMap::new());
         assert_eq!(key.args[0], "tm_fifo_0");
         assert_eq!(key.args[1], "lol");
     }
@@ -275,7 +288,8 @@ mod tests {
         exec1.args(vec![fifo1.sandbox_path().to_string_lossy()]);
         group1.add_execution(exec1);
         let exec1 = &group1.executions[0];
-        let key1 = CacheKeyItem::from_execution(exec1, &HashMap::new(), Some(&group1));
+        let key1 =
+            CacheKeyItem::from_execution(exec1, &HashMap::new(), Some(&group1), &HashMap::new());
 
         let mut group2 = ExecutionGroup::new("group 2");
         let fifo2 = group2.new_fifo();
@@ -287,12 +301,36 @@ mod tests {
         exec2.args(vec![fifo2.sandbox_path().to_string_lossy()]);
         group2.add_execution(exec2);
         let exec2 = &group2.executions[0];
-        let key2 = CacheKeyItem::from_execution(exec2, &HashMap::new(), Some(&group2));
+        let key2 =
+            CacheKeyItem::from_execution(exec2, &HashMap::new(), Some(&group2), &HashMap::new());
 
         assert_eq!(key1, key2);
 
-        let key1 = CacheKey::from_execution_group(&group1, &HashMap::new());
-        let key2 = CacheKey::from_execution_group(&group2, &HashMap::new());
+        let key1 = CacheKey::from_execution_group(&group1, &HashMap::new(), &HashMap::new());
+        let key2 = CacheKey::from_execution_group(&group2, &HashMap::new(), &HashMap::new());
         assert_eq!(key1, key2);
     }
+
+    #[test]
+    fn test_toolchain() {
+        let exec = Execution::new("exec", ExecutionCommand::system("compiler"));
+        let old: HashMap<_, _> = [(PathBuf::from("compiler"), "old version".to_string())]
+            .into_iter()
+            .collect();
+        let new: HashMap<_, _> = [(PathBuf::from("compiler"), "new version".to_string())]
+            .into_iter()
+            .collect();
+        let key1 = CacheKeyItem::from_execution(&exec, &HashMap::new(), None, &old);
+        let key2 = CacheKeyItem::from_execution(&exec, &HashMap::new(), None, &new);
+        let key3 = CacheKeyItem::from_execution(&exec, &HashMap::new(), None, &HashMap::new());
+        assert_eq!(key1.toolchain.as_deref(), Some("old version"));
+        assert_ne!(key1, key2);
+        assert_ne!(hash(&key1), hash(&key2));
+        assert_ne!(key1, key3);
+        assert_eq!(key3.toolchain, None);
+
+        let exec = Execution::new("exec", ExecutionCommand::local("compiler"));
+        let key = CacheKeyItem::from_execution(&exec, &HashMap::new(), None, &old);
+        assert_eq!(key.toolchain, None);
+    }
 }
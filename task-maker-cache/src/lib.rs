@@ -17,6 +17,12 @@
 //! than the corresponding one in `L2`. If a limit is not present, its value is assumed to be
 //! _infinite_.
 //!
+//! The key of the executions of the system commands (e.g. the compilers) includes the fingerprint
+//! of their toolchain, which the workers compute from the version of the command and of the
+//! sandbox. Those fingerprints are reported by the workers with
+//! [`update_toolchains`](struct.Cache.html#method.update_toolchains) and stored in the cache, so
+//! that upgrading a compiler invalidates the executions that used the old one.
+//!
 //! # Example
 //!
 //! ```
@@ -53,6 +59,10 @@
 //!     was_cached: false,
 //!     stderr: None,
 //!     stdout: None,
+//!     time_scale: 1.0,
+//!     cpu: None,
+//!     run_times: vec![],
+//!     memory_metric: Default::default(),
 //! };
 //!
 //! // make the FileUuid -> FileStoreHandle map
@@ -80,17 +90,21 @@
 #[macro_use]
 extern crate log;
 
+mod bundle;
 mod entry;
 mod key;
+mod remote;
 mod storage;
+pub use bundle::BundleStats;
 use entry::CacheEntry;
 use key::CacheKey;
+pub use remote::RemoteCache;
 use storage::CacheFile;
 
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::create_dir_all;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Error};
 use itertools::Itertools;
@@ -100,12 +114,20 @@ use task_maker_store::{FileStore, FileStoreHandle};
 
 /// The name of the file which holds the cache data.
 const CACHE_FILE: &str = "cache.bin";
+/// The name of the file which holds the keys used by the last run.
+const LAST_RUN_FILE: &str = "last-run.bin";
 
 /// Handle the cached executions, loading and storing them to disk.
 #[derive(Debug)]
 pub struct Cache {
     /// Cache entries.
     file: CacheFile,
+    /// The remote cache to query for the executions missing from this one, if any.
+    remote: Option<RemoteCache>,
+    /// Where the keys used by the last run are stored.
+    last_run_path: PathBuf,
+    /// The keys used by this run, either found in the cache or inserted.
+    touched: HashSet<CacheKey>,
 }
 
 /// The result of a cache query, can be either successful (`Hit`) or unsuccessful (`Miss`).
@@ -131,7 +153,41 @@ impl Cache {
         })?;
         let path = cache_dir.join(CACHE_FILE);
         let file = CacheFile::load(path).context("Failed to load cache file")?;
-        Ok(Self { file })
+        Ok(Self {
+            file,
+            remote: None,
+            last_run_path: cache_dir.join(LAST_RUN_FILE),
+            touched: HashSet::new(),
+        })
+    }
+
+    /// Make the cache usable by all the users of the group of its directory: the cache file is
+    /// made writable by the group. The entries written by the other users while this cache is
+    /// open are merged with the new ones when the cache is stored.
+    pub fn set_shared(&mut self, shared: bool) {
+        self.file.set_shared(shared);
+    }
+
+    /// Use a remote cache for the executions that are not in this cache, and upload there the new
+    /// executions.
+    pub fn set_remote(&mut self, remote: RemoteCache) {
+        self.remote = Some(remote);
+    }
+
+    /// Update the fingerprints of the toolchains of some system commands, as reported by a worker.
+    /// The executions of those commands will use the new fingerprints in their keys.
+    pub fn update_toolchains(&mut self, toolchains: HashMap<PathBuf, String>) {
+        self.file.update_toolchains(toolchains);
+    }
+
+    /// The system commands with a known fingerprint of their toolchain, which may be stale until a
+    /// worker reports them again.
+    pub fn toolchain_commands(&self) -> Vec<&Path> {
+        self.file
+            .toolchains()
+            .keys()
+            .map(PathBuf::as_path)
+            .collect()
     }
 
     /// Insert a new entry inside the cache. They key is computed based on the execution's metadata
@@ -143,8 +199,8 @@ impl Cache {
         file_keys: &HashMap<FileUuid, FileStoreHandle>,
         result: Vec<ExecutionResult>,
     ) {
-        let key = CacheKey::from_execution_group(group, file_keys);
-        let set = self.file.entry(key).or_default();
+        let key = CacheKey::from_execution_group(group, file_keys, self.file.toolchains());
+        let set = self.file.entry(key.clone()).or_default();
         let entry = CacheEntry::from_execution_group(group, file_keys, result);
         // Do not insert duplicated keys, replace if the limits are the same.
         let pos = set.iter().find_position(|e| e.same_limits(&entry));
@@ -153,6 +209,10 @@ impl Cache {
         } else {
             set.push(entry);
         }
+        if let Some(remote) = &self.remote {
+            remote.upload(&key, set, group, file_keys);
+        }
+        self.touched.insert(key);
         self.file.mark_dirty();
     }
 
@@ -161,24 +221,74 @@ impl Cache {
     ///
     /// The result contains the handles to the files in the `FileStore`, preventing the flushing
     /// from erasing them.
+    ///
+    /// If the execution is not in this cache, the remote cache is queried, downloading the entries
+    /// and their files.
     pub fn get(
         &mut self,
         group: &ExecutionGroup,
         file_keys: &HashMap<FileUuid, FileStoreHandle>,
         file_store: &FileStore,
     ) -> CacheResult {
-        let key = CacheKey::from_execution_group(group, file_keys);
-        let entry = self.file.entry(key);
-        let entry = match &entry {
+        let key = CacheKey::from_execution_group(group, file_keys, self.file.toolchains());
+        let result = self.lookup(&key, group, file_store);
+        if let CacheResult::Hit { .. } = result {
+            self.touched.insert(key);
+        }
+        result
+    }
+
+    /// Search in the cache like [`get`](#method.get), without marking the entry as used by this
+    /// run (e.g. when only planning an evaluation).
+    pub fn peek(
+        &mut self,
+        group: &ExecutionGroup,
+        file_keys: &HashMap<FileUuid, FileStoreHandle>,
+        file_store: &FileStore,
+    ) -> CacheResult {
+        let key = CacheKey::from_execution_group(group, file_keys, self.file.toolchains());
+        self.lookup(&key, group, file_store)
+    }
+
+    /// Search the key in this cache, and then in the remote one.
+    fn lookup(
+        &mut self,
+        key: &CacheKey,
+        group: &ExecutionGroup,
+        file_store: &FileStore,
+    ) -> CacheResult {
+        let mut result = self.get_local(key, group, file_store);
+        if let (CacheResult::Miss, Some(remote)) = (&result, &mut self.remote) {
+            // keep the downloaded files until the entries are used
+            if let Some((entries, _files)) = remote.fetch(key, file_store) {
+                if self.file.merge_entries(key.clone(), entries) {
+                    info!("Execution {} found in the remote cache", group.uuid);
+                    self.file.mark_dirty();
+                    result = self.get_local(key, group, file_store);
+                }
+            }
+        }
+        result
+    }
+
+    /// Search in this cache for a valid entry with that key.
+    fn get_local(
+        &mut self,
+        key: &CacheKey,
+        group: &ExecutionGroup,
+        file_store: &FileStore,
+    ) -> CacheResult {
+        let entries = match self.file.entry(key.clone()) {
             Entry::Vacant(_) => return CacheResult::Miss,
-            Entry::Occupied(entry) => entry.get(),
+            Entry::Occupied(entry) => entry.into_mut(),
         };
 
-        for entry in entry.iter() {
+        let mut result = CacheResult::Miss;
+        // the entries whose outputs have been flushed away from the store
+        let mut invalid = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
             match entry.outputs(file_store, group) {
-                None => {
-                    // TODO: remove the entry because it's not valid anymore
-                }
+                None => invalid.push(index),
                 Some(outputs) => {
                     if entry.is_compatible(group) {
                         let mut results = Vec::new();
@@ -195,17 +305,36 @@ impl Cache {
                                 resources: item.result.resources.clone(),
                                 stdout: item.result.stdout.clone(),
                                 stderr: item.result.stderr.clone(),
+                                time_scale: item.result.time_scale,
+                                cpu: item.result.cpu,
+                                run_times: item.result.run_times.clone(),
+                                memory_metric: item.result.memory_metric,
                             });
                         }
-                        return CacheResult::Hit {
+                        result = CacheResult::Hit {
                             result: results,
                             outputs,
                         };
+                        break;
                     }
                 }
             }
         }
-        CacheResult::Miss
+        // those entries will never be valid again, so remove them to keep the cache small
+        if !invalid.is_empty() {
+            for index in invalid.into_iter().rev() {
+                entries.remove(index);
+            }
+            self.file.mark_dirty();
+        }
+        result
+    }
+
+    /// Remove all the entries whose output files are not in the file store anymore, for example
+    /// because they have been flushed away to free some space. Returns the number of removed
+    /// entries.
+    pub fn collect_garbage(&mut self, file_store: &FileStore) -> usize {
+        self.file.retain(|entry| entry.is_available(file_store))
     }
 
     /// Checks whether a result is allowed in the cache.
@@ -219,5 +348,11 @@ impl Drop for Cache {
         if let Err(e) = self.file.store() {
             warn!("Failed to store cache file: {:?}", e);
         }
+        if !self.touched.is_empty() {
+            let shared = self.file.is_shared();
+            if let Err(e) = bundle::store_last_run(&self.last_run_path, &self.touched, shared) {
+                warn!("Failed to store the keys of the last run: {:?}", e);
+            }
+        }
     }
 }
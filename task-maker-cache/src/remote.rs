@@ -0,0 +1,419 @@
+//! A cache shared between machines through an HTTP server.
+//!
+//! When an execution is not in the local cache, the remote cache is queried for its entries, and
+//! the files they produce are downloaded into the local file store. The executions that run
+//! locally are then uploaded in background, so that the other machines (e.g. the ephemeral runners
+//! of a CI) don't have to run them again.
+//!
+//! The server is only required to support `GET`, `HEAD` and `PUT` requests:
+//! - `{url}/ac/{hash}` holds the entries of a cache key, encoded like the local cache file. The hash
+//!   covers the cache key and the version of the format, so different versions never mix.
+//! - `{url}/cas/{key}` holds the content of a file, addressed by its `FileStoreKey`.
+//!
+//! This is the same layout of the HTTP cache of Bazel, so servers like `bazel-remote` (with the
+//! validation of the action cache disabled), or a plain `nginx` with WebDAV, can be used. An S3
+//! bucket can be used through a proxy that signs the requests.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::sync::mpsc::{channel, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use anyhow::{Context, Error};
+
+use task_maker_dag::{ExecutionGroup, FileUuid};
+use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
+
+use crate::entry::CacheEntry;
+use crate::key::CacheKey;
+use crate::storage::MAGIC;
+
+/// The timeout for connecting to the remote cache.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// The timeout of a whole request to the remote cache, including the transfer of the file.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The entries downloaded from the remote cache, together with the handles of their files.
+type FetchedEntries = (Vec<CacheEntry>, Vec<FileStoreHandle>);
+
+/// The connection to a remote cache.
+#[derive(Debug)]
+pub struct RemoteCache {
+    /// The client used for the queries.
+    client: RemoteClient,
+    /// The keys already queried, so that a missing key is not queried again every time the
+    /// executions are scheduled.
+    queried: HashSet<CacheKey>,
+    /// Whether the remote cache stopped working, and it's not used anymore.
+    disabled: bool,
+    /// The channel to the thread that uploads the new entries, if the uploads are enabled.
+    uploads: Option<Sender<Upload>>,
+    /// The thread that uploads the new entries.
+    uploader: Option<JoinHandle<()>>,
+}
+
+/// The HTTP client of the remote cache.
+#[derive(Debug, Clone)]
+struct RemoteClient {
+    /// The HTTP agent, which keeps the connections open.
+    agent: ureq::Agent,
+    /// The base URL of the remote cache, without the trailing slash.
+    url: String,
+    /// The token to send as bearer authorization, if any.
+    token: Option<String>,
+}
+
+/// The entries of a key to upload, together with the files they produce.
+struct Upload {
+    /// The URL of the entries.
+    url: String,
+    /// The encoded entries.
+    entries: Vec<u8>,
+    /// The files produced by the entries. Holding the handles prevents the file store from flushing
+    /// them before they are uploaded.
+    files: Vec<FileStoreHandle>,
+}
+
+impl RemoteCache {
+    /// Connect to the remote cache at the specified URL, authenticating with the bearer token, if
+    /// any. When `read_only` is set the executions are never uploaded.
+    pub fn new<S: Into<String>>(url: S, token: Option<String>, read_only: bool) -> RemoteCache {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build();
+        let client = RemoteClient {
+            agent,
+            url: url.into().trim_end_matches('/').to_string(),
+            token,
+        };
+        let (uploads, uploader) = if read_only {
+            (None, None)
+        } else {
+            let (sender, receiver) = channel::<Upload>();
+            let client = client.clone();
+            let uploader = std::thread::Builder::new()
+                .name("Remote cache uploader".into())
+                .spawn(move || {
+                    for upload in receiver {
+                        if let Err(e) = client.upload(&upload) {
+                            warn!("Failed to upload to the remote cache: {:?}", e);
+                        }
+                    }
+                })
+                .expect("Failed to spawn the remote cache uploader");
+            (Some(sender), Some(uploader))
+        };
+        RemoteCache {
+            client,
+            queried: HashSet::new(),
+            disabled: false,
+            uploads,
+            uploader,
+        }
+    }
+
+    /// Query the remote cache for the entries of a key, downloading in the file store the files
+    /// they produce. Each key is queried at most once. The handles of the downloaded files are
+    /// returned as well, and they should be kept until the entries are used.
+    pub(crate) fn fetch(
+        &mut self,
+        key: &CacheKey,
+        file_store: &FileStore,
+    ) -> Option<FetchedEntries> {
+        if self.disabled || !self.queried.insert(key.clone()) {
+            return None;
+        }
+        match self.client.fetch(key, file_store) {
+            Ok(Some((entries, files))) => {
+                debug!("Found {} entries in the remote cache", entries.len());
+                Some((entries, files))
+            }
+            Ok(None) => None,
+            Err(e) => {
+                warn!("The remote cache is not available anymore: {:?}", e);
+                self.disabled = true;
+                None
+            }
+        }
+    }
+
+    /// Upload in background the entries of a key, together with the files produced by the
+    /// executions of the group.
+    pub(crate) fn upload(
+        &self,
+        key: &CacheKey,
+        entries: &[CacheEntry],
+        group: &ExecutionGroup,
+        file_keys: &HashMap<FileUuid, FileStoreHandle>,
+    ) {
+        let uploads = match (&self.uploads, self.disabled) {
+            (Some(uploads), false) => uploads,
+            _ => return,
+        };
+        let encoded = match encode_entries(entries) {
+            Ok(encoded) => encoded,
+            Err(e) => {
+                warn!("Cannot encode the cache entries: {:?}", e);
+                return;
+            }
+        };
+        let mut files = Vec::new();
+        for exec in &group.executions {
+            let produced = exec
+                .stdout
+                .iter()
+                .chain(exec.stderr.iter())
+                .chain(exec.outputs.values());
+            for file in produced {
+                if let Some(handle) = file_keys.get(&file.uuid) {
+                    files.push(handle.clone());
+                }
+            }
+        }
+        let upload = Upload {
+            url: self.client.entries_url(key),
+            entries: encoded,
+            files,
+        };
+        if uploads.send(upload).is_err() {
+            warn!("The remote cache uploader is gone");
+        }
+    }
+}
+
+impl Drop for RemoteCache {
+    fn drop(&mut self) {
+        // wait for the pending uploads
+        self.uploads.take();
+        if let Some(uploader) = self.uploader.take() {
+            if uploader.join().is_err() {
+                warn!("The remote cache uploader panicked");
+            }
+        }
+    }
+}
+
+impl RemoteClient {
+    /// The URL of the entries of a key.
+    fn entries_url(&self, key: &CacheKey) -> String {
+        let mut content = MAGIC.to_vec();
+        content.extend(bincode::serialize(key).expect("Cache keys are serializable"));
+        format!("{}/ac/{}", self.url, FileStoreKey::from_content(&content))
+    }
+
+    /// The URL of the content of a file.
+    fn file_url(&self, key: &FileStoreKey) -> String {
+        format!("{}/cas/{}", self.url, key)
+    }
+
+    /// Prepare a request to the remote cache.
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let request = self.agent.request(method, url);
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    /// Download the content at that URL, returning `None` if it's not present.
+    fn download(&self, url: &str) -> Result<Option<Vec<u8>>, Error> {
+        let response = match self.request("GET", url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to download {}", url)),
+        };
+        let mut content = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut content)
+            .with_context(|| format!("Failed to download {}", url))?;
+        Ok(Some(content))
+    }
+
+    /// Download the entries of a key and the files they produce. The entries whose files are not
+    /// in the remote cache are skipped.
+    fn fetch(
+        &self,
+        key: &CacheKey,
+        file_store: &FileStore,
+    ) -> Result<Option<FetchedEntries>, Error> {
+        let entries = match self.download(&self.entries_url(key))? {
+            Some(content) => match decode_entries(&content) {
+                Some(entries) => entries,
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        let mut available = Vec::new();
+        let mut handles = Vec::new();
+        'entries: for entry in entries {
+            for key in entry.files() {
+                if let Some(handle) = file_store.get(key) {
+                    handles.push(handle);
+                    continue;
+                }
+                let content = match self.download(&self.file_url(key))? {
+                    Some(content) => content,
+                    None => continue 'entries,
+                };
+                if FileStoreKey::from_content(&content) != *key {
+                    warn!("The remote cache sent a corrupted file for {}", key);
+                    continue 'entries;
+                }
+                handles.push(file_store.store(key, std::iter::once(content))?);
+            }
+            available.push(entry);
+        }
+        if available.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some((available, handles)))
+    }
+
+    /// Upload the files that the remote cache doesn't have yet, and then the entries that use them.
+    fn upload(&self, upload: &Upload) -> Result<(), Error> {
+        for file in &upload.files {
+            let url = self.file_url(file.key());
+            match self.request("HEAD", &url).call() {
+                Ok(_) => continue,
+                Err(ureq::Error::Status(404, _)) => {}
+                Err(e) => return Err(e).with_context(|| format!("Failed to query {}", url)),
+            }
+            let content: Vec<u8> = file.read()?.flatten().collect();
+            self.request("PUT", &url)
+                .send_bytes(&content)
+                .with_context(|| format!("Failed to upload {}", url))?;
+        }
+        self.request("PUT", &upload.url)
+            .send_bytes(&upload.entries)
+            .with_context(|| format!("Failed to upload {}", upload.url))?;
+        Ok(())
+    }
+}
+
+/// Encode the entries of a key like in the cache file, with the magic string in front.
+fn encode_entries(entries: &[CacheEntry]) -> Result<Vec<u8>, Error> {
+    let mut content = MAGIC.to_vec();
+    bincode::serialize_into(&mut content, entries).context("Failed to serialize the entries")?;
+    Ok(content)
+}
+
+/// Decode the entries of a key, returning `None` if they are invalid.
+fn decode_entries(content: &[u8]) -> Option<Vec<CacheEntry>> {
+    match content.strip_prefix(MAGIC) {
+        Some(content) => match bincode::deserialize(content) {
+            Ok(entries) => Some(entries),
+            Err(e) => {
+                warn!("Invalid entries in the remote cache: {:?}", e);
+                None
+            }
+        },
+        None => {
+            warn!("The remote cache sent entries of another version");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use task_maker_dag::{
+        Execution, ExecutionCommand, ExecutionResourcesUsage, ExecutionResult, ExecutionStatus,
+    };
+    use task_maker_store::ReadFileIterator;
+
+    use crate::{Cache, CacheResult};
+
+    /// The files uploaded to the fake server, indexed by their path.
+    type ServerFiles = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+    /// Start an HTTP server that keeps the uploaded files in memory, returning its URL.
+    fn fake_server() -> (String, ServerFiles) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr());
+        let files = Arc::new(Mutex::new(HashMap::new()));
+        let content = files.clone();
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let path = request.url().to_string();
+                let mut files = content.lock().unwrap();
+                let response = match request.method() {
+                    tiny_http::Method::Put => {
+                        let mut data = Vec::new();
+                        request.as_reader().read_to_end(&mut data).unwrap();
+                        files.insert(path, data);
+                        tiny_http::Response::from_data(vec![])
+                    }
+                    _ => match files.get(&path) {
+                        Some(data) => tiny_http::Response::from_data(data.clone()),
+                        None => tiny_http::Response::from_data(vec![]).with_status_code(404),
+                    },
+                };
+                request.respond(response).unwrap();
+            }
+        });
+        (url, files)
+    }
+
+    #[test]
+    fn test_remote_cache() {
+        let (url, files) = fake_server();
+        let mut exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        let stdout = exec.stdout().uuid;
+        let group = exec.into();
+        let result = ExecutionResult {
+            status: ExecutionStatus::Success,
+            was_killed: false,
+            was_cached: false,
+            resources: ExecutionResourcesUsage::default(),
+            stdout: None,
+            stderr: None,
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
+        };
+
+        // the first machine runs the execution and uploads it
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path().join("store"), 1000, 1000).unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        cache.set_remote(RemoteCache::new(url.clone(), None, false));
+        assert!(matches!(
+            cache.get(&group, &HashMap::new(), &store),
+            CacheResult::Miss
+        ));
+        let path = tmpdir.path().join("stdout");
+        std::fs::write(&path, "hello").unwrap();
+        let key = FileStoreKey::from_file(&path).unwrap();
+        let handle = store
+            .store(&key, ReadFileIterator::new(&path).unwrap())
+            .unwrap();
+        let mut file_keys = HashMap::new();
+        file_keys.insert(stdout, handle);
+        cache.insert(&group, &file_keys, vec![result]);
+        drop(cache);
+        assert!(files.lock().unwrap().contains_key(&format!("/cas/{}", key)));
+
+        // the second machine finds it in the remote cache
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path().join("store"), 1000, 1000).unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        cache.set_remote(RemoteCache::new(url, None, true));
+        match cache.get(&group, &HashMap::new(), &store) {
+            CacheResult::Hit { result, outputs } => {
+                assert_eq!(result[0].status, ExecutionStatus::Success);
+                assert!(result[0].was_cached);
+                assert_eq!(outputs[&stdout].key(), &key);
+            }
+            CacheResult::Miss => panic!("Expecting a hit from the remote cache"),
+        }
+        assert!(store.contains(&key));
+    }
+}
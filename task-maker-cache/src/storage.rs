@@ -1,10 +1,14 @@
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::fs::Permissions;
 use std::io::{BufReader, BufWriter, Read, Write};
-use std::path::PathBuf;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use anyhow::{Context, Error};
 use const_format::formatcp;
+use fslock::LockFile;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
@@ -17,31 +21,76 @@ use crate::key::CacheKey;
 /// The newline at the end of the string is required. For example, let's say there are 2 versions:
 /// v0.1 and v0.11; running v0.11 first, and then v0.1, without the newline the magic of the old
 /// version is a prefix of the magic of the new version.
-const MAGIC: &[u8] = formatcp!("task-maker-cache v{}\n", env!("CARGO_PKG_VERSION")).as_bytes();
+///
+/// The sandbox is built into task-maker, so a different version of the sandbox also invalidates the
+/// cache through the version of task-maker.
+pub(crate) const MAGIC: &[u8] = formatcp!(
+    "task-maker-cache v{} r{}\n",
+    env!("CARGO_PKG_VERSION"),
+    FORMAT_REVISION
+)
+.as_bytes();
+
+/// Revision of the format of the cache entries, bumped every time they change without a new
+/// version of task-maker, so that the incompatible cache files are discarded.
+const FORMAT_REVISION: u32 = 2;
+
+/// The permissions of the cache file when the cache is shared with the other users of the group.
+const SHARED_FILE_MODE: u32 = 0o664;
+/// The permissions of the cache directory when the cache is shared with the other users of the
+/// group: writable by the group, and with the setgid bit so that the new files inherit the group.
+const SHARED_DIR_MODE: u32 = 0o2775;
+
+/// The entries of a cache file, grouped by their key.
+type CacheEntries = HashMap<CacheKey, Vec<CacheEntry>>;
+
+/// The fingerprints of the toolchains of the system commands, as reported by the workers.
+pub(crate) type Toolchains = HashMap<PathBuf, String>;
 
 /// A cache file.
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct CacheFile {
     /// The set of entries in this cache file.
     entries: HashMap<CacheKey, Vec<CacheEntry>>,
+    /// The last known fingerprints of the toolchains, used for the keys of the system commands
+    /// until a worker reports them again.
+    toolchains: Toolchains,
     /// Where this file is stored.
     path: PathBuf,
     /// Whether this file should be flushed.
     dirty: bool,
+    /// The last modification time of the file when it was loaded. If the file changes, another
+    /// process has written it in the meantime, and its entries should be merged with these.
+    #[serde(skip)]
+    loaded_at: Option<SystemTime>,
+    /// Whether the file is shared with the other users of the group.
+    #[serde(skip)]
+    shared: bool,
 }
 
 impl CacheFile {
     /// Read the cache file, check the magic string and deserialize all the entries in it.
     pub fn load(path: PathBuf) -> Result<CacheFile, Error> {
+        let loaded_at = modification_time(&path);
+        let (toolchains, entries) = CacheFile::read_entries(&path)?.unwrap_or_default();
+        Ok(Self {
+            entries,
+            toolchains,
+            path,
+            dirty: false,
+            loaded_at,
+            shared: false,
+        })
+    }
+
+    /// Read the toolchains and the entries of the cache file at that path. Returns `None` if the
+    /// file doesn't exist or it has been written by another version.
+    fn read_entries(path: &Path) -> Result<Option<(Toolchains, CacheEntries)>, Error> {
         if !path.exists() {
-            return Ok(Self {
-                entries: Default::default(),
-                path,
-                dirty: false,
-            });
+            return Ok(None);
         }
 
-        let file = std::fs::File::open(&path)
+        let file = std::fs::File::open(path)
             .with_context(|| format!("Cannot open cache file at {}", path.display()))?;
         let mut reader = BufReader::new(file);
         let mut magic = [0u8; MAGIC.len()];
@@ -54,33 +103,55 @@ impl CacheFile {
                 "Cache version mismatch:\nExpected: {:?}\nFound: {:?}",
                 MAGIC, magic
             );
-            return Ok(Self {
-                entries: Default::default(),
-                path,
-                dirty: false,
-            });
+            return Ok(None);
         }
 
-        let entries = bincode::deserialize_from::<_, HashMap<CacheKey, Vec<CacheEntry>>>(reader)
+        let content = bincode::deserialize_from::<_, (Toolchains, CacheEntries)>(reader)
             .context("Failed to deserialize cache content")?;
+        Ok(Some(content))
+    }
 
-        Ok(Self {
-            entries,
-            path,
-            dirty: false,
-        })
+    /// Make the cache file, and its directory, writable by the other users of the group.
+    pub fn set_shared(&mut self, shared: bool) {
+        self.shared = shared;
+        if let (true, Some(dir)) = (shared, self.path.parent()) {
+            if let Err(e) = std::fs::set_permissions(dir, Permissions::from_mode(SHARED_DIR_MODE)) {
+                debug!("Cannot share {}: {}", dir.display(), e);
+            }
+        }
     }
 
     /// Store the content of the cache to the cache file, including the magic string.
-    pub fn store(&self) -> Result<(), Error> {
+    ///
+    /// If the file has been written by another process after it was loaded, its entries are merged
+    /// with the ones of this cache, so that they are not lost.
+    pub fn store(&mut self) -> Result<(), Error> {
         // Do not write the file if it's not dirty.
         if !self.dirty {
             return Ok(());
         }
 
-        let path = &self.path;
+        let path = self.path.clone();
         std::fs::create_dir_all(path.parent().context("Invalid cache file")?)
             .with_context(|| format!("Failed to create cache directory for {}", path.display()))?;
+        let lock_path = path.with_extension("lock");
+        let mut lock = LockFile::open(&lock_path)
+            .with_context(|| format!("Failed to create lock file at {}", lock_path.display()))?;
+        lock.lock()
+            .context("Failed to obtain exclusive lock on the cache")?;
+        if self.shared {
+            share_file(&lock_path);
+        }
+
+        if modification_time(&path) != self.loaded_at {
+            debug!("Cache file changed since it was loaded, merging it");
+            match CacheFile::read_entries(&path) {
+                Ok(Some((toolchains, entries))) => self.merge(toolchains, entries),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read the changed cache file: {:?}", e),
+            }
+        }
+
         let tmp = path.with_extension("tmp");
         let file = std::fs::File::create(&tmp).context("Failed to create cache file")?;
         let mut writer = BufWriter::new(file);
@@ -89,18 +160,71 @@ impl CacheFile {
             .write_all(MAGIC)
             .context("Failed to write cache magic number")?;
 
-        bincode::serialize_into(writer, &self.entries.iter().collect_vec())
-            .context("Failed to write cache content")?;
-        std::fs::rename(&tmp, &self.path).with_context(|| {
-            format!(
-                "Failed to move {} -> {}",
-                tmp.display(),
-                self.path.display()
-            )
-        })?;
+        bincode::serialize_into(
+            writer,
+            &(&self.toolchains, self.entries.iter().collect_vec()),
+        )
+        .context("Failed to write cache content")?;
+        if self.shared {
+            share_file(&tmp);
+        }
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to move {} -> {}", tmp.display(), path.display()))?;
+        self.loaded_at = modification_time(&path);
+        self.dirty = false;
         Ok(())
     }
 
+    /// Add the toolchains and the entries written by another process, keeping the ones of this
+    /// cache when they have the same command or the same limits.
+    fn merge(&mut self, toolchains: Toolchains, entries: CacheEntries) {
+        for (command, fingerprint) in toolchains {
+            self.toolchains.entry(command).or_insert(fingerprint);
+        }
+        for (key, entries) in entries {
+            self.merge_entries(key, entries);
+        }
+    }
+
+    /// Add the entries of a key that are not already present with the same limits. Returns whether
+    /// some entries were added.
+    pub fn merge_entries(&mut self, key: CacheKey, entries: Vec<CacheEntry>) -> bool {
+        let set = self.entries.entry(key).or_default();
+        let mut added = false;
+        for entry in entries {
+            if !set.iter().any(|e| e.same_limits(&entry)) {
+                set.push(entry);
+                added = true;
+            }
+        }
+        added
+    }
+
+    /// Whether the file is shared with the other users of the group.
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    /// Iterate over all the keys and their entries.
+    pub fn iter(&self) -> impl Iterator<Item = (&CacheKey, &Vec<CacheEntry>)> {
+        self.entries.iter()
+    }
+
+    /// The last known fingerprints of the toolchains.
+    pub fn toolchains(&self) -> &Toolchains {
+        &self.toolchains
+    }
+
+    /// Update the fingerprints of some toolchains, marking the file dirty if they changed.
+    pub fn update_toolchains(&mut self, toolchains: Toolchains) {
+        for (command, fingerprint) in toolchains {
+            if self.toolchains.get(&command) != Some(&fingerprint) {
+                self.toolchains.insert(command, fingerprint);
+                self.mark_dirty();
+            }
+        }
+    }
+
     pub fn entry(&mut self, key: CacheKey) -> Entry<CacheKey, Vec<CacheEntry>> {
         self.entries.entry(key)
     }
@@ -108,12 +232,45 @@ impl CacheFile {
     pub fn mark_dirty(&mut self) {
         self.dirty = true;
     }
+
+    /// Keep only the entries for which the predicate returns true, dropping the keys left without
+    /// entries. Returns the number of removed entries.
+    pub fn retain<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&CacheEntry) -> bool,
+    {
+        let mut removed = 0;
+        self.entries.retain(|_, set| {
+            let before = set.len();
+            set.retain(|entry| f(entry));
+            removed += before - set.len();
+            !set.is_empty()
+        });
+        if removed > 0 {
+            self.mark_dirty();
+        }
+        removed
+    }
+}
+
+/// The last modification time of a file, if it exists.
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Make a file writable by the group. Only the owner of the file can change its permissions, so
+/// the failures are ignored.
+pub(crate) fn share_file(path: &Path) {
+    if let Err(e) = std::fs::set_permissions(path, Permissions::from_mode(SHARED_FILE_MODE)) {
+        debug!("Cannot share {}: {}", path.display(), e);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs::File;
+    use task_maker_dag::{Execution, ExecutionCommand};
 
     #[test]
     fn test_load_reject_wrong_magic() {
@@ -135,4 +292,34 @@ mod tests {
 
         assert!(CacheFile::load(path).is_err());
     }
+
+    #[test]
+    fn test_store_merge_concurrent() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("cache");
+        let mut file1 = CacheFile::load(path.clone()).unwrap();
+        let mut file2 = CacheFile::load(path.clone()).unwrap();
+
+        let exec1 = Execution::new("exec1", ExecutionCommand::local("foo"));
+        let exec2 = Execution::new("exec2", ExecutionCommand::local("bar"));
+        let (group1, group2) = (exec1.into(), exec2.into());
+        let key1 = CacheKey::from_execution_group(&group1, &HashMap::new(), &HashMap::new());
+        let key2 = CacheKey::from_execution_group(&group2, &HashMap::new(), &HashMap::new());
+        let entry1 = CacheEntry::from_execution_group(&group1, &HashMap::new(), vec![]);
+        let entry2 = CacheEntry::from_execution_group(&group2, &HashMap::new(), vec![]);
+        file1.entry(key1.clone()).or_default().push(entry1);
+        file1.update_toolchains([(PathBuf::from("foo"), "1.0".to_string())].into());
+        file1.mark_dirty();
+        file1.store().unwrap();
+        // the second process loaded the file before the first one stored it
+        file2.entry(key2.clone()).or_default().push(entry2);
+        file2.update_toolchains([(PathBuf::from("bar"), "2.0".to_string())].into());
+        file2.mark_dirty();
+        file2.store().unwrap();
+
+        let file = CacheFile::load(path).unwrap();
+        assert!(file.entries.contains_key(&key1));
+        assert!(file.entries.contains_key(&key2));
+        assert_eq!(file.toolchains.len(), 2);
+    }
 }
@@ -3,16 +3,21 @@ use std::path::PathBuf;
 
 use anyhow::{bail, Context, Error};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use task_maker_store::*;
 
 use crate::file::*;
 use crate::*;
 
+/// The identifier of an evaluation on a remote server.
+pub type EvaluationUuid = Uuid;
+
 /// The setting of the cache level.
-#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Eq, Debug, Clone, Default, Serialize, Deserialize)]
 pub enum CacheMode {
     /// Use the cache as much as possible.
+    #[default]
     Everything,
     /// Never use the cache.
     Nothing,
@@ -20,6 +25,42 @@ pub enum CacheMode {
     Except(HashSet<ExecutionTag>),
 }
 
+/// Which of the runs of a repeated execution is used for its result, ordering them by CPU time.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum RunsPolicy {
+    /// The fastest run.
+    Min,
+    /// The run in the middle, the faster one of the two if the number of runs is even.
+    #[default]
+    Median,
+    /// The slowest run.
+    Max,
+}
+
+impl std::str::FromStr for RunsPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RunsPolicy, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "min" => Ok(RunsPolicy::Min),
+            "median" => Ok(RunsPolicy::Median),
+            "max" => Ok(RunsPolicy::Max),
+            _ => Err(format!("Unknown runs policy: {}", s)),
+        }
+    }
+}
+
+impl RunsPolicy {
+    /// The index of the run to use, among `runs` runs sorted by CPU time.
+    pub fn index(&self, runs: usize) -> usize {
+        match self {
+            RunsPolicy::Min => 0,
+            RunsPolicy::Median => runs.saturating_sub(1) / 2,
+            RunsPolicy::Max => runs.saturating_sub(1),
+        }
+    }
+}
+
 /// Configuration setting of an `ExecutionDAG`, some of the values set here will be inherited in the
 /// configuration of the executions added.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +73,10 @@ pub struct ExecutionDAGConfig {
     pub dry_run: bool,
     /// The cache mode for this DAG.
     pub cache_mode: CacheMode,
+    /// Which failed executions are stored in the cache, and used from it. The failures excluded by
+    /// this mode are executed again every time.
+    #[serde(default)]
+    pub failure_cache_mode: CacheMode,
     /// Extra time to give to the sandbox before killing the process, in seconds.
     pub extra_time: f64,
     /// Extra memory to give to the sandbox before killing the process, in KiB.
@@ -42,6 +87,22 @@ pub struct ExecutionDAGConfig {
     pub copy_logs: bool,
     /// Priority of this DAG.
     pub priority: DagPriority,
+    /// The identifier of the evaluation on a remote server. When it's set the server keeps
+    /// evaluating the DAG even if the client disconnects, and a client sending a DAG with the same
+    /// identifier reattaches to it.
+    #[serde(default)]
+    pub evaluation_id: Option<EvaluationUuid>,
+    /// How many times the repeated executions are run.
+    #[serde(default = "default_runs")]
+    pub runs: usize,
+    /// Which of the runs of a repeated execution is used for its result.
+    #[serde(default)]
+    pub runs_policy: RunsPolicy,
+}
+
+/// The default value of `ExecutionDAGConfig::runs`, for configurations that don't set it.
+fn default_runs() -> usize {
+    1
 }
 
 /// A wrapper around a `File` provided by the client, this means that the client knows the
@@ -213,7 +274,7 @@ impl ExecutionDAG {
     /// future).
     pub fn get_file_content<G: Into<FileUuid>, F>(&mut self, file: G, limit: usize, callback: F)
     where
-        F: (FnOnce(Vec<u8>) -> Result<(), Error>) + 'static,
+        F: (FnOnce(Vec<u8>) -> Result<(), Error>) + Send + 'static,
     {
         let file = file.into();
         // FIXME: add support for multiple get_content file callbacks on the same file. This may be
@@ -234,7 +295,7 @@ impl ExecutionDAG {
     /// unsuccessful) the callback **is called** anyways with the content of the file, if any.
     pub fn get_file_content_chunked<G: Into<FileUuid>, F>(&mut self, file: G, callback: F)
     where
-        F: (FnMut(&[u8]) -> Result<(), Error>) + 'static,
+        F: (FnMut(&[u8]) -> Result<(), Error>) + Send + 'static,
     {
         let file = file.into();
         self.file_callback(file)
@@ -245,7 +306,7 @@ impl ExecutionDAG {
     /// Add a callback that will be called when the execution starts.
     pub fn on_execution_start<F>(&mut self, execution: &ExecutionUuid, callback: F)
     where
-        F: (FnOnce(WorkerUuid) -> Result<(), Error>) + 'static,
+        F: (FnOnce(WorkerUuid) -> Result<(), Error>) + Send + 'static,
     {
         self.execution_callback(execution)
             .on_start
@@ -255,7 +316,7 @@ impl ExecutionDAG {
     /// Add a callback that will be called when the execution ends.
     pub fn on_execution_done<F>(&mut self, execution: &ExecutionUuid, callback: F)
     where
-        F: (FnOnce(ExecutionResult) -> Result<(), Error>) + 'static,
+        F: (FnOnce(ExecutionResult) -> Result<(), Error>) + Send + 'static,
     {
         self.execution_callback(execution)
             .on_done
@@ -265,13 +326,82 @@ impl ExecutionDAG {
     /// Add a callback that will be called when the execution is skipped.
     pub fn on_execution_skip<F>(&mut self, execution: &ExecutionUuid, callback: F)
     where
-        F: (FnOnce() -> Result<(), Error>) + 'static,
+        F: (FnOnce() -> Result<(), Error>) + Send + 'static,
     {
         self.execution_callback(execution)
             .on_skip
             .push(Box::new(callback));
     }
 
+    /// Add a callback that will be called when the execution ends, either completing or being
+    /// skipped, adding more executions to the DAG while it's being evaluated. The callback is given
+    /// an empty DAG with the same configuration of this one: the executions, the files and the
+    /// callbacks it adds are then sent to the executor. They can depend on the files of the rest of
+    /// the DAG, even if they are already ready.
+    pub fn extend_after_execution<F>(&mut self, execution: &ExecutionUuid, callback: F)
+    where
+        F: (FnOnce(&mut ExecutionDAG) -> Result<(), Error>) + Send + 'static,
+    {
+        self.execution_callback(execution)
+            .extend_dag
+            .push(Box::new(callback));
+    }
+
+    /// Make an empty DAG with the same configuration of this one, for extending it.
+    pub fn extension(&self) -> ExecutionDAG {
+        let mut extension = ExecutionDAG::new();
+        extension.data.config = self.data.config.clone();
+        extension
+    }
+
+    /// Add to this DAG the executions, the files and the callbacks of an extension of it.
+    pub fn merge_extension(&mut self, extension: ExecutionDAG) {
+        self.data
+            .provided_files
+            .extend(extension.data.provided_files);
+        self.data
+            .execution_groups
+            .extend(extension.data.execution_groups);
+        let extension = extension
+            .callbacks
+            .expect("Cannot merge an extension after cloning it");
+        let callbacks = self
+            .callbacks
+            .as_mut()
+            .expect("Cannot change callbacks after cloning");
+        for (uuid, mut execution) in extension.execution_callbacks {
+            let entry = callbacks.execution_callbacks.entry(uuid).or_default();
+            entry.on_start.append(&mut execution.on_start);
+            entry.on_done.append(&mut execution.on_done);
+            entry.on_skip.append(&mut execution.on_skip);
+            entry.extend_dag.append(&mut execution.extend_dag);
+        }
+        for (uuid, mut file) in extension.file_callbacks {
+            let entry = callbacks.file_callbacks.entry(uuid).or_default();
+            if file.write_to.is_some() {
+                entry.write_to = file.write_to;
+            }
+            if file.get_content.is_some() {
+                entry.get_content = file.get_content;
+            }
+            entry
+                .get_content_chunked
+                .append(&mut file.get_content_chunked);
+        }
+        callbacks.urgent_files.extend(extension.urgent_files);
+    }
+
+    /// The number of callbacks that will extend this DAG while it's being evaluated.
+    pub fn num_extensions(&self) -> usize {
+        self.callbacks.as_ref().map_or(0, |callbacks| {
+            callbacks
+                .execution_callbacks
+                .values()
+                .map(|callbacks| callbacks.extend_dag.len())
+                .sum()
+        })
+    }
+
     /// Get a mutable reference to the config of this DAG.
     pub fn config_mut(&mut self) -> &mut ExecutionDAGConfig {
         &mut self.data.config
@@ -340,11 +470,15 @@ impl ExecutionDAGConfig {
             keep_sandboxes: false,
             dry_run: false,
             cache_mode: CacheMode::Everything,
+            failure_cache_mode: CacheMode::Everything,
             extra_time: 0.5,
             extra_memory: 8 * 1024, // 8 MiB
             copy_exe: false,
             copy_logs: false,
             priority: 0,
+            evaluation_id: None,
+            runs: 1,
+            runs_policy: RunsPolicy::default(),
         }
     }
 
@@ -366,6 +500,12 @@ impl ExecutionDAGConfig {
         self
     }
 
+    /// Set which failed executions of this DAG are cached.
+    pub fn failure_cache_mode(&mut self, failure_cache_mode: CacheMode) -> &mut Self {
+        self.failure_cache_mode = failure_cache_mode;
+        self
+    }
+
     /// Set the extra time to give to the executions before being killed by the sandbox.
     pub fn extra_time(&mut self, extra_time: f64) -> &mut Self {
         assert!(extra_time >= 0.0);
@@ -396,6 +536,20 @@ impl ExecutionDAGConfig {
         self.priority = priority;
         self
     }
+
+    /// Set the identifier of the evaluation on the remote server.
+    pub fn evaluation_id(&mut self, evaluation_id: Option<EvaluationUuid>) -> &mut Self {
+        self.evaluation_id = evaluation_id;
+        self
+    }
+
+    /// Set how many times the repeated executions are run, and which run is used for their result.
+    pub fn runs(&mut self, runs: usize, runs_policy: RunsPolicy) -> &mut Self {
+        assert!(runs >= 1);
+        self.runs = runs;
+        self.runs_policy = runs_policy;
+        self
+    }
 }
 
 impl Default for ExecutionDAGConfig {
@@ -438,6 +592,30 @@ impl CacheMode {
             }
         }
     }
+
+    /// Disable the cache also for these tags.
+    pub fn disable<I: IntoIterator<Item = ExecutionTag>>(&mut self, tags: I) {
+        match self {
+            CacheMode::Everything => {
+                let tags: HashSet<_> = tags.into_iter().collect();
+                if !tags.is_empty() {
+                    *self = CacheMode::Except(tags);
+                }
+            }
+            CacheMode::Nothing => {}
+            CacheMode::Except(set) => set.extend(tags),
+        }
+    }
+
+    /// Whether the cache is enabled for the executions with that tag.
+    pub fn is_enabled(&self, tag: Option<&ExecutionTag>) -> bool {
+        match (self, tag) {
+            (CacheMode::Everything, _) => true,
+            (CacheMode::Nothing, _) => false,
+            (CacheMode::Except(set), Some(tag)) => !set.contains(tag),
+            (CacheMode::Except(_), None) => true,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -606,6 +784,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_merge_extension() {
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut().extra_time(123.0);
+        let comp = Execution::new("comp", ExecutionCommand::local("foo"));
+        let comp_uuid = comp.uuid;
+        dag.add_execution(comp);
+        dag.extend_after_execution(&comp_uuid, |_| Ok(()));
+        dag.extend_after_execution(&comp_uuid, |_| Ok(()));
+        assert_eq!(dag.num_extensions(), 2);
+
+        let mut extension = dag.extension();
+        assert!(extension.data.execution_groups.is_empty());
+        let exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        let exec_uuid = exec.uuid;
+        extension.add_execution(exec);
+        extension.on_execution_done(&exec_uuid, |_| Ok(()));
+        extension.extend_after_execution(&exec_uuid, |_| Ok(()));
+        dag.merge_extension(extension);
+
+        assert_eq!(dag.data.execution_groups.len(), 2);
+        assert_eq!(dag.num_extensions(), 3);
+        let group = dag
+            .data
+            .execution_groups
+            .values()
+            .find(|g| g.executions[0].uuid == exec_uuid)
+            .unwrap();
+        assert_abs_diff_eq!(123.0, group.executions[0].config.extra_time);
+        assert_eq!(dag.execution_callbacks()[&exec_uuid].on_done.len(), 1);
+    }
+
     #[test]
     fn test_config_mut() {
         let mut dag = ExecutionDAG::new();
@@ -637,4 +847,33 @@ mod tests {
         );
         assert!(CacheMode::try_from(&Some(Some("tag1".to_string())), &[]).is_err());
     }
+
+    #[test]
+    fn test_cache_mode_disable() {
+        let tag1 = ExecutionTag::from("tag1");
+        let tag2 = ExecutionTag::from("tag2");
+        let mut mode = CacheMode::Everything;
+        mode.disable(vec![]);
+        assert_eq!(mode, CacheMode::Everything);
+        mode.disable(vec![tag1.clone()]);
+        assert!(!mode.is_enabled(Some(&tag1)));
+        assert!(mode.is_enabled(Some(&tag2)));
+        assert!(mode.is_enabled(None));
+        mode.disable(vec![tag2.clone()]);
+        assert!(!mode.is_enabled(Some(&tag2)));
+        let mut mode = CacheMode::Nothing;
+        mode.disable(vec![tag1]);
+        assert!(!mode.is_enabled(None));
+    }
+
+    #[test]
+    fn test_runs_policy_index() {
+        assert_eq!(RunsPolicy::Min.index(5), 0);
+        assert_eq!(RunsPolicy::Median.index(5), 2);
+        assert_eq!(RunsPolicy::Median.index(4), 1);
+        assert_eq!(RunsPolicy::Max.index(5), 4);
+        assert_eq!(RunsPolicy::Median.index(1), 0);
+        assert_eq!("max".parse(), Ok(RunsPolicy::Max));
+        assert!("mean".parse::<RunsPolicy>().is_err());
+    }
 }
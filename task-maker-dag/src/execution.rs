@@ -7,7 +7,7 @@ use typescript_definitions::TypeScriptify;
 use uuid::Uuid;
 
 use crate::file::*;
-use crate::ExecutionDAGConfig;
+use crate::{ExecutionDAG, ExecutionDAGConfig};
 
 /// The identifier of an execution, it's globally unique and it identifies an execution only during
 /// a single evaluation.
@@ -20,13 +20,18 @@ pub type ExecutionUuid = Uuid;
 pub type WorkerUuid = Uuid;
 
 /// Type of the callback called when an [`Execution`](struct.Execution.html) starts.
-pub type OnStartCallback = Box<dyn FnOnce(WorkerUuid) -> Result<(), Error> + 'static>;
+pub type OnStartCallback = Box<dyn FnOnce(WorkerUuid) -> Result<(), Error> + Send + 'static>;
 
 /// Type of the callback called when an [`Execution`](struct.Execution.html) ends.
-pub type OnDoneCallback = Box<dyn FnOnce(ExecutionResult) -> Result<(), Error> + 'static>;
+pub type OnDoneCallback = Box<dyn FnOnce(ExecutionResult) -> Result<(), Error> + Send + 'static>;
 
 /// Type of the callback called when an [`Execution`](struct.Execution.html) is skipped.
-pub type OnSkipCallback = Box<dyn FnOnce() -> Result<(), Error> + 'static>;
+pub type OnSkipCallback = Box<dyn FnOnce() -> Result<(), Error> + Send + 'static>;
+
+/// Type of the callback called when an [`Execution`](struct.Execution.html) ends or is skipped,
+/// adding more executions to the DAG while it's being evaluated.
+pub type ExtendDAGCallback =
+    Box<dyn FnOnce(&mut ExecutionDAG) -> Result<(), Error> + Send + 'static>;
 
 /// Type of the priority value of an `Execution`.
 pub type Priority = i64;
@@ -49,7 +54,8 @@ pub struct ExecutionTag {
 /// Command of an [`Execution`](struct.Execution.html) to execute.
 ///
 /// There is a distinction between a `System` command, which has to be searched in the `PATH`
-/// env var, and a `Local` command, which is relative to the sandbox directory.
+/// env var, a `Local` command, which is relative to the sandbox directory, and a `Builtin` command,
+/// which is implemented by the workers themselves.
 ///
 /// ```
 /// use task_maker_dag::ExecutionCommand;
@@ -65,6 +71,20 @@ pub enum ExecutionCommand {
     System(PathBuf),
     /// A command relative to the sandbox directory, not to be searched in the `$PATH`.
     Local(PathBuf),
+    /// A command implemented by the workers, run directly without spawning a sandboxed process.
+    Builtin(BuiltinCommand),
+}
+
+/// The commands implemented by the workers. They read their inputs from the sandbox directory and
+/// only produce an exit status, without any output file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum BuiltinCommand {
+    /// Compare two files, whose paths are the first two arguments, exiting with 0 if they are equal
+    /// and with 1 otherwise. The following arguments are the normalizations applied to the lines
+    /// before comparing them: `space_change` (collapse and trim the white space),
+    /// `trailing_whitespace` (trim the white space at the end), `blank_lines` (skip the blank
+    /// lines) and `unicode_nfc` (convert to the Unicode NFC).
+    WhiteDiff,
 }
 
 /// An input file of an [`Execution`](struct.Execution.html), can be marked as executable if it has
@@ -86,6 +106,9 @@ pub struct ExecutionCallbacks {
     pub on_done: Vec<OnDoneCallback>,
     /// The callbacks called when the execution has been skipped.
     pub on_skip: Vec<OnSkipCallback>,
+    /// The callbacks called when the execution has completed or has been skipped, that extend the
+    /// DAG.
+    pub extend_dag: Vec<ExtendDAGCallback>,
 }
 
 /// An [`Execution`](struct.Execution.html) is a process that will be executed by a worker inside a
@@ -177,6 +200,24 @@ pub struct Execution {
 
     /// The tag associated with this execution.
     pub tag: Option<ExecutionTag>,
+    /// Whether the execution is run `ExecutionDAGConfig::runs` times to measure its resources more
+    /// accurately. The outputs are the ones of the last run.
+    #[serde(default)]
+    pub repeated: bool,
+    /// Whether the execution is not timing sensitive (e.g. the compilation of a booklet), so the
+    /// workers run it with a lower CPU and I/O priority than the other executions.
+    #[serde(default)]
+    pub background: bool,
+    /// Whether the execution is expected to be very short (e.g. a checker or a validator), so the
+    /// server can send it to a worker together with other short executions in a single message.
+    #[serde(default)]
+    pub batchable: bool,
+    /// The paths of the inputs that make up the program run by the execution, when it doesn't need
+    /// a compilation (e.g. an interpreted script and the modules it imports). A worker keeps them in
+    /// a prepared sandbox directory for the next executions of the same program, instead of setting
+    /// up the sandbox from scratch each time.
+    #[serde(default)]
+    pub runtime_inputs: Vec<PathBuf>,
     /// A priority index for this execution. Higher values correspond to higher priorities. The
     /// priority order is followed only between ready executions, i.e. a lower priority one can be
     /// executed before if its dependencies are ready earlier.
@@ -215,6 +256,63 @@ pub struct ExecutionLimits {
     pub mount_proc: bool,
     /// Extra directory that can be read inside the sandbox.
     pub extra_readable_dirs: Vec<PathBuf>,
+    /// The clock whose limit, when exceeded, makes the execution `TimeLimitExceeded`.
+    #[serde(default)]
+    pub time_clock: TimeClock,
+    /// The measure of the memory of the process that is limited by `memory`.
+    #[serde(default)]
+    pub memory_metric: MemoryMetric,
+}
+
+/// The clock that drives the time limit verdict of an execution.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, TypeScriptify)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeClock {
+    /// Exceeding the cpu time limit is a `TimeLimitExceeded`, while the wall time limit is just a
+    /// safety margin for the programs waiting without using the cpu.
+    #[default]
+    Cpu,
+    /// Exceeding the wall time limit is a `TimeLimitExceeded`, like for the programs that interact
+    /// with other processes and spend most of their time waiting.
+    Wall,
+}
+
+impl std::str::FromStr for TimeClock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TimeClock, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cpu" => Ok(TimeClock::Cpu),
+            "wall" => Ok(TimeClock::Wall),
+            _ => Err(format!("Unknown time clock: {}", s)),
+        }
+    }
+}
+
+/// The measure of the memory of an execution that is checked against its memory limit.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, TypeScriptify)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryMetric {
+    /// The virtual address space of the process is limited, so the allocations above the limit
+    /// fail. The programs that reserve large ranges of memory without using them, like the JVM or
+    /// the builds with the sanitizers, fail even if they use little memory.
+    #[default]
+    AddressSpace,
+    /// The peak resident set size of the process is checked after the execution, without limiting
+    /// its address space.
+    Rss,
+}
+
+impl std::str::FromStr for MemoryMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MemoryMetric, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "address-space" | "address_space" | "as" => Ok(MemoryMetric::AddressSpace),
+            "rss" => Ok(MemoryMetric::Rss),
+            _ => Err(format!("Unknown memory metric: {}", s)),
+        }
+    }
 }
 
 /// Status of a completed [`Execution`](struct.Execution.html).
@@ -267,6 +365,25 @@ pub struct ExecutionResult {
     pub stdout: Option<Vec<u8>>,
     /// Captured standard error of the execution, if the capture was requested.
     pub stderr: Option<Vec<u8>>,
+    /// Factor the time limits were scaled by on the worker that ran the execution. The times in
+    /// `resources` are already normalized to the reference hardware.
+    #[serde(default = "default_time_scale")]
+    pub time_scale: f64,
+    /// The CPU the worker pinned the execution to, if the worker pins its executions.
+    #[serde(default)]
+    pub cpu: Option<usize>,
+    /// The CPU time of each run, when the execution has been run more times. The other fields are
+    /// the ones of the run chosen by `ExecutionDAGConfig::runs_policy`.
+    #[serde(default)]
+    pub run_times: Vec<f64>,
+    /// The measure of the memory that has been checked against the memory limit.
+    #[serde(default)]
+    pub memory_metric: MemoryMetric,
+}
+
+/// The default value of `ExecutionResult::time_scale`, for results that were not scaled.
+fn default_time_scale() -> f64 {
+    1.0
 }
 
 impl ExecutionLimits {
@@ -287,6 +404,8 @@ impl ExecutionLimits {
             mount_tmpfs: true,
             mount_proc: true,
             extra_readable_dirs: Vec::new(),
+            time_clock: TimeClock::Cpu,
+            memory_metric: MemoryMetric::AddressSpace,
         }
     }
 
@@ -374,6 +493,18 @@ impl ExecutionLimits {
         self.extra_readable_dirs.push(dir.into());
         self
     }
+
+    /// Set the clock that drives the time limit verdict.
+    pub fn time_clock(&mut self, clock: TimeClock) -> &mut Self {
+        self.time_clock = clock;
+        self
+    }
+
+    /// Set the measure of the memory that is limited.
+    pub fn memory_metric(&mut self, metric: MemoryMetric) -> &mut Self {
+        self.memory_metric = metric;
+        self
+    }
 }
 
 impl Default for ExecutionLimits {
@@ -394,6 +525,8 @@ impl Default for ExecutionLimits {
             mount_tmpfs: false,
             mount_proc: false,
             extra_readable_dirs: Vec::new(),
+            time_clock: TimeClock::Cpu,
+            memory_metric: MemoryMetric::AddressSpace,
         }
     }
 }
@@ -425,6 +558,11 @@ impl ExecutionCommand {
     pub fn local<P: Into<PathBuf>>(path: P) -> ExecutionCommand {
         ExecutionCommand::Local(path.into())
     }
+
+    /// Make a new `ExecutionCommand::Builtin`.
+    pub fn builtin(command: BuiltinCommand) -> ExecutionCommand {
+        ExecutionCommand::Builtin(command)
+    }
 }
 
 impl Execution {
@@ -462,6 +600,10 @@ impl Execution {
             config: ExecutionDAGConfig::new(),
 
             tag: None,
+            repeated: false,
+            background: false,
+            batchable: false,
+            runtime_inputs: Vec::new(),
             priority: Priority::default(),
         }
     }
@@ -761,6 +903,34 @@ impl Execution {
         self
     }
 
+    /// Set whether this `Execution` is run more times, as configured in the DAG, to measure its
+    /// resources more accurately.
+    pub fn repeated(&mut self, repeated: bool) -> &mut Self {
+        self.repeated = repeated;
+        self
+    }
+
+    /// Set whether this `Execution` runs in background, with a lower CPU and I/O priority than the
+    /// timing sensitive executions.
+    pub fn background(&mut self, background: bool) -> &mut Self {
+        self.background = background;
+        self
+    }
+
+    /// Set whether this `Execution` is short enough to be sent to a worker in a batch together with
+    /// other short executions.
+    pub fn batchable(&mut self, batchable: bool) -> &mut Self {
+        self.batchable = batchable;
+        self
+    }
+
+    /// Mark an input of this `Execution` as part of the program it runs, so that the workers can
+    /// reuse a sandbox already prepared with the program for the executions of the same program.
+    pub fn runtime_input<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.runtime_inputs.push(path.into());
+        self
+    }
+
     /// Set the priority of this `Execution`.
     pub fn priority(&mut self, priority: Priority) -> &mut Self {
         self.priority = priority;
@@ -777,6 +947,13 @@ impl Execution {
     ) -> ExecutionStatus {
         // it's important to check those before the signals because exceeding those
         // limits may trigger a SIGKILL from the sandbox
+        let wall_time_exceeded = self
+            .limits
+            .wall_time
+            .is_some_and(|limit| resources.wall_time > limit);
+        if self.limits.time_clock == TimeClock::Wall && wall_time_exceeded {
+            return ExecutionStatus::TimeLimitExceeded;
+        }
         if let Some(cpu_time_limit) = self.limits.cpu_time {
             if resources.cpu_time > cpu_time_limit {
                 return ExecutionStatus::TimeLimitExceeded;
@@ -787,10 +964,8 @@ impl Execution {
                 return ExecutionStatus::SysTimeLimitExceeded;
             }
         }
-        if let Some(wall_time_limit) = self.limits.wall_time {
-            if resources.wall_time > wall_time_limit {
-                return ExecutionStatus::WallTimeLimitExceeded;
-            }
+        if wall_time_exceeded {
+            return ExecutionStatus::WallTimeLimitExceeded;
         }
         if let Some(memory_limit) = self.limits.memory {
             if resources.memory > memory_limit {
@@ -814,6 +989,7 @@ impl std::fmt::Debug for ExecutionCallbacks {
             .field("on_start", &self.on_start.len())
             .field("on_done", &self.on_done.len())
             .field("on_skip", &self.on_skip.len())
+            .field("extend_dag", &self.extend_dag.len())
             .finish()?;
         Ok(())
     }
@@ -826,6 +1002,7 @@ impl std::fmt::Debug for ExecutionResult {
             .field("was_killed", &self.was_killed)
             .field("was_cached", &self.was_cached)
             .field("resources", &self.resources)
+            .field("time_scale", &self.time_scale)
             .field(
                 "stdout",
                 &self
@@ -921,6 +1098,27 @@ mod tests {
         assert_eq!(ExecutionStatus::WallTimeLimitExceeded, status);
     }
 
+    #[test]
+    fn test_status_wall_time_clock() {
+        let mut exec = Execution::new("foo", ExecutionCommand::local("foo"));
+        exec.limits_mut()
+            .cpu_time(1.0)
+            .wall_time(1.0)
+            .time_clock(TimeClock::Wall);
+        let resources = |cpu_time, wall_time| ExecutionResourcesUsage {
+            cpu_time,
+            sys_time: 0.0,
+            wall_time,
+            memory: 0,
+        };
+        let status = exec.status(0, None, &resources(0.1, 1.1));
+        assert_eq!(ExecutionStatus::TimeLimitExceeded, status);
+        let status = exec.status(0, None, &resources(1.1, 0.9));
+        assert_eq!(ExecutionStatus::TimeLimitExceeded, status);
+        let status = exec.status(0, None, &resources(0.1, 0.9));
+        assert_eq!(ExecutionStatus::Success, status);
+    }
+
     #[test]
     fn test_status_memory() {
         let mut exec = Execution::new("foo", ExecutionCommand::local("foo"));
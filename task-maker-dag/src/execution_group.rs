@@ -102,6 +102,12 @@ impl ExecutionGroup {
             .tag
             .clone()
     }
+
+    /// Whether this group can be sent to a worker in a batch with other groups: all its executions
+    /// are batchable and they don't communicate using pipes.
+    pub fn is_batchable(&self) -> bool {
+        self.fifo.is_empty() && self.executions.iter().all(|e| e.batchable)
+    }
 }
 
 impl From<Execution> for ExecutionGroup {
@@ -55,6 +55,8 @@ pub struct Diagnostic {
     level: DiagnosticLevel,
     /// The main message to report.
     message: String,
+    /// The code that identifies the kind of problem, allowing tools to filter the diagnostics.
+    code: Option<String>,
     /// Additional notes to show next to the main message.
     note: Option<String>,
     /// Some help for diagnosing the problem.
@@ -72,6 +74,7 @@ impl Diagnostic {
         Self {
             level: DiagnosticLevel::Error,
             message: message.into(),
+            code: None,
             note: None,
             help: None,
             help_attachment: None,
@@ -84,6 +87,7 @@ impl Diagnostic {
         Self {
             level: DiagnosticLevel::Warning,
             message: message.into(),
+            code: None,
             note: None,
             help: None,
             help_attachment: None,
@@ -91,6 +95,12 @@ impl Diagnostic {
         }
     }
 
+    /// Attach a code to the diagnostic, identifying the kind of problem.
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
     /// Attach a note to the diagnostic.
     pub fn with_note(mut self, note: impl Into<String>) -> Self {
         self.note = Some(note.into());
@@ -120,10 +130,14 @@ impl Diagnostic {
         // TODO: additional printing options (e.g. no colors, compact, ...)
         let level = self.level.as_str();
         let pad = level.len();
+        let header = match &self.code {
+            Some(code) => format!("{}[{}]", level, code),
+            None => level.to_string(),
+        };
         writeln!(
             f,
             "{}: {}",
-            level.color(self.level.color()).bold(),
+            header.color(self.level.color()).bold(),
             self.message
         )?;
         if let Some(note) = &self.note {
@@ -174,6 +188,11 @@ impl Diagnostic {
     pub fn message(&self) -> &str {
         &self.message
     }
+
+    /// Get the code of this diagnostic, if any.
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
 }
 
 impl Display for Diagnostic {
@@ -182,6 +201,12 @@ impl Display for Diagnostic {
     }
 }
 
+/// Enable or disable the colors when printing the diagnostics, overriding the detection from the
+/// environment.
+pub fn set_colors(enabled: bool) {
+    colored::control::set_override(enabled);
+}
+
 /// The context that contains all the emitted diagnostic messages.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct DiagnosticContext {
@@ -205,3 +230,22 @@ impl DiagnosticContext {
         &self.diagnostics
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostic_code() {
+        colored::control::set_override(false);
+        let diagnostic = Diagnostic::warning("Something is off").with_code("some-code");
+        assert_eq!(diagnostic.code(), Some("some-code"));
+        assert_eq!(
+            diagnostic.to_string(),
+            "Warning[some-code]: Something is off\n"
+        );
+        let diagnostic = Diagnostic::error("Something is wrong");
+        assert_eq!(diagnostic.code(), None);
+        assert_eq!(diagnostic.to_string(), "Error: Something is wrong\n");
+    }
+}
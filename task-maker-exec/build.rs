@@ -0,0 +1,18 @@
+use std::path::Path;
+
+/// Find the version of tabox in the lock file of the workspace, if any.
+fn get_tabox_version(lock_file: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(lock_file).ok()?;
+    let mut lines = content.lines();
+    lines.find(|line| line.trim() == r#"name = "tabox""#)?;
+    let version = lines.next()?.trim().strip_prefix("version = ")?;
+    Some(version.trim_matches('"').to_string())
+}
+
+fn main() {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").expect("missing CARGO_MANIFEST_DIR");
+    let lock_file = Path::new(&manifest_dir).join("../Cargo.lock");
+    let version = get_tabox_version(&lock_file).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=TABOX_VERSION={}", version);
+    println!("cargo:rerun-if-changed=../Cargo.lock");
+}
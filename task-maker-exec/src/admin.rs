@@ -0,0 +1,107 @@
+//! Administration of a remote server.
+//!
+//! An administrator connects to the server like a normal client, but instead of sending a DAG to
+//! evaluate it sends an [`AdminRequest`](enum.AdminRequest.html), receiving back an
+//! [`AdminResponse`](enum.AdminResponse.html). The requests are accepted only if the server has an
+//! admin password, and the request carries it.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use task_maker_dag::{DagPriority, EvaluationUuid, WorkerUuid};
+
+use crate::executor::ExecutorWorkerStatus;
+use crate::scheduler::ClientInfo;
+
+/// A request of an administrator of the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AdminRequest {
+    /// Get the list of the workers and of the evaluations of the server.
+    Status,
+    /// Stop an evaluation, identified either by the uuid of its client or by its evaluation id.
+    CancelEvaluation(Uuid),
+    /// Stop sending new jobs to a worker, identified either by its uuid or by its name. When the
+    /// worker completes its current job, it's asked to exit.
+    DrainWorker(String),
+}
+
+/// The response of the server to an [`AdminRequest`](enum.AdminRequest.html).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AdminResponse {
+    /// The status of the server.
+    Status(AdminStatus),
+    /// The request has been fulfilled, with a message describing the outcome.
+    Ok(String),
+    /// The request cannot be fulfilled.
+    Error(String),
+}
+
+/// Check the password sent with an admin request against the admin password of the server,
+/// returning why the request is refused. Without an admin password the administration is
+/// disabled.
+pub(crate) fn check_admin_password(
+    admin_password: Option<&str>,
+    password: Option<&str>,
+) -> Result<(), String> {
+    let Some(admin_password) = admin_password else {
+        return Err("The administration of the server is disabled".into());
+    };
+    // the hashes are compared in constant time
+    match password {
+        Some(password)
+            if blake3::hash(password.as_bytes()) == blake3::hash(admin_password.as_bytes()) =>
+        {
+            Ok(())
+        }
+        _ => Err("Wrong admin password".into()),
+    }
+}
+
+/// The status of the server, as seen by an administrator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminStatus {
+    /// The workers connected to the server.
+    pub workers: Vec<ExecutorWorkerStatus<Duration>>,
+    /// The workers that are being drained.
+    pub draining_workers: Vec<WorkerUuid>,
+    /// The evaluations known to the server, either running or waiting for the workers.
+    pub evaluations: Vec<EvaluationStatus>,
+}
+
+/// The status of an evaluation of the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluationStatus {
+    /// The client that sent the evaluation.
+    pub client: ClientInfo,
+    /// The identifier of the evaluation, if it can be resumed.
+    pub evaluation_id: Option<EvaluationUuid>,
+    /// The priority of the evaluation.
+    pub priority: DagPriority,
+    /// Whether the client has lost the connection and the evaluation runs detached.
+    pub detached: bool,
+    /// Whether the evaluation has been recovered after a restart of the server.
+    pub recovered: bool,
+    /// The number of execution groups waiting for a free worker.
+    pub ready_execs: usize,
+    /// The number of execution groups running on a worker.
+    pub running_execs: usize,
+    /// The number of execution groups waiting for some dependencies.
+    pub waiting_execs: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_admin_password() {
+        assert!(check_admin_password(Some("secret"), Some("secret")).is_ok());
+        assert!(check_admin_password(Some("secret"), Some("wrong")).is_err());
+        assert!(check_admin_password(Some("secret"), None).is_err());
+        // without a password on the server nobody can administer it
+        assert!(check_admin_password(None, None).is_err());
+        assert!(check_admin_password(None, Some("")).is_err());
+    }
+}
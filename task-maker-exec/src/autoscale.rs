@@ -0,0 +1,162 @@
+//! Hooks fired by the server when the depth of the ready queue crosses some thresholds.
+//!
+//! This allows an external script to spin up more workers (for example in the cloud) when a big
+//! evaluation is sent to the server, and to turn them down when the queue drains.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Error};
+use serde::{Deserialize, Serialize};
+
+/// The configuration of the autoscaling hook.
+#[derive(Debug, Clone)]
+pub struct AutoscaleConfig {
+    /// The script to execute when the ready queue crosses one of the thresholds. It is called with
+    /// the event name (`scale-up` or `scale-down`) as the only argument and the JSON serialized
+    /// [`QueueMetrics`](struct.QueueMetrics.html) as standard input.
+    pub script: PathBuf,
+    /// When the number of ready executions reaches this value, the `scale-up` event is fired.
+    pub scale_up_threshold: usize,
+    /// When the number of ready executions drops to this value, the `scale-down` event is fired.
+    pub scale_down_threshold: usize,
+}
+
+/// An event fired by the autoscaler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AutoscaleEvent {
+    /// The ready queue is too long, more workers are needed.
+    ScaleUp,
+    /// The ready queue has drained, some workers can be turned down.
+    ScaleDown,
+}
+
+/// The metrics about the queue of the server, sent to the autoscaling hook.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueMetrics {
+    /// The event that triggered the hook.
+    pub event: AutoscaleEvent,
+    /// The number of executions ready to be run, waiting for a free worker.
+    pub ready_execs: usize,
+    /// The number of executions waiting for some dependencies.
+    pub waiting_execs: usize,
+    /// The number of executions currently running on a worker.
+    pub running_execs: usize,
+    /// The number of workers connected to the server.
+    pub connected_workers: usize,
+    /// The number of clients connected to the server.
+    pub connected_clients: usize,
+}
+
+impl AutoscaleEvent {
+    /// The name of the event, passed as argument to the script.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AutoscaleEvent::ScaleUp => "scale-up",
+            AutoscaleEvent::ScaleDown => "scale-down",
+        }
+    }
+}
+
+/// Keep track of the state of the queue, firing the hook only when a threshold is crossed.
+#[derive(Debug)]
+pub(crate) struct Autoscaler {
+    /// The configuration of the hook.
+    config: AutoscaleConfig,
+    /// The last fired event. The queue is considered drained at startup.
+    last_event: AutoscaleEvent,
+}
+
+impl Autoscaler {
+    /// Make a new `Autoscaler` with the provided configuration.
+    pub fn new(config: AutoscaleConfig) -> Autoscaler {
+        Autoscaler {
+            config,
+            last_event: AutoscaleEvent::ScaleDown,
+        }
+    }
+
+    /// Check the number of ready executions, returning the event to fire, if any.
+    pub fn check(&mut self, ready_execs: usize) -> Option<AutoscaleEvent> {
+        let event = match self.last_event {
+            AutoscaleEvent::ScaleDown if ready_execs >= self.config.scale_up_threshold => {
+                AutoscaleEvent::ScaleUp
+            }
+            AutoscaleEvent::ScaleUp if ready_execs <= self.config.scale_down_threshold => {
+                AutoscaleEvent::ScaleDown
+            }
+            _ => return None,
+        };
+        self.last_event = event;
+        Some(event)
+    }
+
+    /// Run the hook in the background with the provided metrics. The scheduler does not wait for
+    /// the script to complete, errors are only logged.
+    pub fn fire(&self, metrics: QueueMetrics) {
+        info!(
+            "Firing autoscaling hook {} with {:?}",
+            metrics.event.name(),
+            metrics
+        );
+        let script = self.config.script.clone();
+        let res = std::thread::Builder::new()
+            .name("Autoscaling hook".to_string())
+            .spawn(move || {
+                if let Err(e) = run_hook(&script, &metrics) {
+                    warn!("Autoscaling hook failed: {:?}", e);
+                }
+            });
+        if let Err(e) = res {
+            warn!("Cannot spawn the autoscaling hook thread: {:?}", e);
+        }
+    }
+}
+
+/// Execute the hook script and wait for its completion.
+fn run_hook(script: &Path, metrics: &QueueMetrics) -> Result<(), Error> {
+    let payload = serde_json::to_vec(metrics).context("Failed to serialize the metrics")?;
+    let mut child = Command::new(script)
+        .arg(metrics.event.name())
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to spawn {}", script.display()))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(&payload)
+            .context("Failed to send the metrics to the hook")?;
+    }
+    let status = child.wait().context("Failed to wait for the hook")?;
+    if !status.success() {
+        warn!(
+            "Autoscaling hook {} exited with {}",
+            script.display(),
+            status
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_autoscaler_hysteresis() {
+        let mut autoscaler = Autoscaler::new(AutoscaleConfig {
+            script: "true".into(),
+            scale_up_threshold: 10,
+            scale_down_threshold: 2,
+        });
+        assert_eq!(autoscaler.check(0), None);
+        assert_eq!(autoscaler.check(9), None);
+        assert_eq!(autoscaler.check(10), Some(AutoscaleEvent::ScaleUp));
+        assert_eq!(autoscaler.check(100), None);
+        assert_eq!(autoscaler.check(5), None);
+        assert_eq!(autoscaler.check(2), Some(AutoscaleEvent::ScaleDown));
+        assert_eq!(autoscaler.check(0), None);
+        assert_eq!(autoscaler.check(10), Some(AutoscaleEvent::ScaleUp));
+    }
+}
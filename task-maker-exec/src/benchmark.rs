@@ -0,0 +1,74 @@
+//! Measure the single-core performance of a worker.
+//!
+//! A pool of heterogeneous machines would give inconsistent time limit verdicts: a solution that
+//! barely fits the time limit on the reference contest machine may exceed it on a slower worker.
+//! Each worker can run a fixed CPU-bound workload and compare its duration with the one measured on
+//! the reference hardware, obtaining a factor that scales the time limits of the executions it runs.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+/// Time, in seconds, the benchmark takes on the reference hardware. Run
+/// `task-maker-tools worker --benchmark` on the contest machine to measure a more accurate value.
+pub const REFERENCE_BENCHMARK_TIME: f64 = 0.5;
+
+/// Number of times the benchmark workload is repeated, the fastest run is kept.
+const BENCHMARK_RUNS: usize = 5;
+
+/// Number of iterations of the workload of a single run.
+const BENCHMARK_ITERATIONS: u64 = 100_000_000;
+
+/// The CPU-bound workload: a mix of integer arithmetic and memory accesses on a small buffer that
+/// fits in the L1 cache.
+fn workload(iterations: u64) -> u64 {
+    let mut buffer = [0u64; 1024];
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    for i in 0..iterations {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let index = (state as usize) & (buffer.len() - 1);
+        buffer[index] = buffer[index].wrapping_add(state ^ i);
+    }
+    buffer.iter().fold(state, |acc, x| acc.wrapping_add(*x))
+}
+
+/// Run the benchmark on the current thread and return the time, in seconds, taken by the fastest
+/// run.
+pub fn benchmark_time() -> f64 {
+    (0..BENCHMARK_RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(workload(black_box(BENCHMARK_ITERATIONS)));
+            start.elapsed().as_secs_f64()
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Compute the factor the time limits should be multiplied by on a machine where the benchmark
+/// takes `measured` seconds, compared to the reference machine where it takes `reference` seconds.
+pub fn time_scale(measured: f64, reference: f64) -> f64 {
+    if measured <= 0.0 || reference <= 0.0 || !measured.is_finite() || !reference.is_finite() {
+        return 1.0;
+    }
+    measured / reference
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_scale() {
+        assert_eq!(time_scale(1.0, 0.5), 2.0);
+        assert_eq!(time_scale(0.25, 0.5), 0.5);
+        assert_eq!(time_scale(0.0, 0.5), 1.0);
+        assert_eq!(time_scale(1.0, 0.0), 1.0);
+        assert_eq!(time_scale(f64::INFINITY, 0.5), 1.0);
+    }
+
+    #[test]
+    fn test_workload_is_deterministic() {
+        assert_eq!(workload(1000), workload(1000));
+    }
+}
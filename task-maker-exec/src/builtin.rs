@@ -0,0 +1,686 @@
+//! The commands implemented by the workers themselves. They are run directly by the worker, inside
+//! the sandbox directory prepared for the execution, without spawning a sandboxed process: for the
+//! short and frequent executions, like the white diff checker, spawning the process costs much more
+//! than the execution itself.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Error};
+use unicode_normalization::{Recompositions, UnicodeNormalization};
+
+use task_maker_dag::{BuiltinCommand, ExecutionResourcesUsage};
+
+use crate::sandbox::SandboxResult;
+
+/// The size of the chunks the files are read in. The memory used by the white diff doesn't depend on
+/// the size of the files, nor on the length of their lines.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The longest run of white space kept as is while looking for the end of the line, the longer runs
+/// are kept as their hash.
+const MAX_INLINE_RUN: usize = 32;
+
+/// Every how many tokens the time limit is checked.
+const TIME_CHECK_INTERVAL: usize = 64 * 1024;
+
+/// Run a builtin command with the specified arguments. The relative paths in the arguments are
+/// relative to `box_dir`, the directory of the sandbox the execution sees as its working directory.
+/// If the command runs for more than `time_limit` seconds it is killed, like the sandbox does with
+/// the processes.
+pub fn run_builtin(
+    command: BuiltinCommand,
+    args: &[String],
+    box_dir: &Path,
+    time_limit: Option<f64>,
+) -> SandboxResult {
+    let start = Instant::now();
+    let deadline = time_limit.map(|limit| start + Duration::from_secs_f64(limit.max(0.0)));
+    let exit_status = match command {
+        BuiltinCommand::WhiteDiff => white_diff(args, box_dir, deadline),
+    };
+    let elapsed = start.elapsed().as_secs_f64();
+    let resources = ExecutionResourcesUsage {
+        cpu_time: elapsed,
+        sys_time: 0.0,
+        wall_time: elapsed,
+        memory: 0,
+    };
+    match exit_status {
+        Ok(Some(equal)) => SandboxResult::Success {
+            exit_status: u32::from(!equal),
+            signal: None,
+            resources,
+            was_killed: false,
+        },
+        Ok(None) => SandboxResult::Success {
+            exit_status: 1,
+            signal: Some((9, "Killed by sandbox".into())),
+            resources,
+            was_killed: true,
+        },
+        Err(e) => SandboxResult::Failed {
+            error: format!("Builtin command {:?} failed: {:?}", command, e),
+        },
+    }
+}
+
+/// How the lines are normalized by the white diff before comparing them.
+#[derive(Debug, Clone, Copy, Default)]
+struct WhiteDiffOptions {
+    /// Collapse the runs of white space and trim the white space at the end of the lines.
+    space_change: bool,
+    /// Trim the white space at the end of the lines.
+    trailing_whitespace: bool,
+    /// Skip the lines that are blank.
+    blank_lines: bool,
+    /// Convert the runs of valid UTF-8 to the Unicode NFC.
+    unicode_nfc: bool,
+}
+
+impl WhiteDiffOptions {
+    /// Parse the names of the normalizations to apply.
+    fn parse(options: &[String]) -> Result<WhiteDiffOptions, Error> {
+        let mut result = WhiteDiffOptions::default();
+        for option in options {
+            match option.as_str() {
+                "space_change" => result.space_change = true,
+                "trailing_whitespace" => result.trailing_whitespace = true,
+                "blank_lines" => result.blank_lines = true,
+                "unicode_nfc" => result.unicode_nfc = true,
+                _ => bail!("Unknown white diff option: {}", option),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Whether the white space at the end of the lines is removed.
+    fn trims_lines(&self) -> bool {
+        self.space_change || self.trailing_whitespace
+    }
+}
+
+/// Whether the byte is white space for the white diff. The line feed never is, since it separates
+/// the lines.
+fn is_space(c: u8) -> bool {
+    matches!(c, b' ' | b'\t' | b'\r' | b'\x0b' | b'\x0c')
+}
+
+/// Compare the two files in the arguments after their normalization, returning whether they are
+/// equal, or `None` if the deadline passed before the end of the comparison. The files are read in
+/// chunks of `CHUNK_SIZE` bytes, so neither their size nor the length of their lines matter.
+fn white_diff(
+    args: &[String],
+    box_dir: &Path,
+    deadline: Option<Instant>,
+) -> Result<Option<bool>, Error> {
+    let (correct, test, options) = match args {
+        [correct, test, options @ ..] => (correct, test, options),
+        _ => bail!("The white diff needs the paths of the two files to compare"),
+    };
+    let options = WhiteDiffOptions::parse(options)?;
+    let mut correct = Normalizer::open(&box_dir.join(correct), options)?;
+    let mut test = Normalizer::open(&box_dir.join(test), options)?;
+    let mut count = 0usize;
+    loop {
+        match (correct.next_token()?, test.next_token()?) {
+            (None, None) => return Ok(Some(true)),
+            (Some(left), Some(right)) if left == right => {}
+            _ => return Ok(Some(false)),
+        }
+        count += 1;
+        if count == TIME_CHECK_INTERVAL {
+            count = 0;
+            if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// A stream of bytes read from a file.
+trait ByteSource {
+    /// The next byte of the stream, `None` at its end.
+    fn next_byte(&mut self) -> Result<Option<u8>, Error>;
+}
+
+/// The bytes of a file, read in chunks of `CHUNK_SIZE` bytes.
+struct FileBytes {
+    /// The path of the file, for the error messages.
+    path: PathBuf,
+    /// The reader of the file.
+    reader: BufReader<File>,
+}
+
+impl FileBytes {
+    /// Open the file at the specified path.
+    fn open(path: &Path) -> Result<FileBytes, Error> {
+        let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+        Ok(FileBytes {
+            path: path.to_owned(),
+            reader: BufReader::with_capacity(CHUNK_SIZE, file),
+        })
+    }
+}
+
+impl ByteSource for FileBytes {
+    fn next_byte(&mut self) -> Result<Option<u8>, Error> {
+        let chunk = self
+            .reader
+            .fill_buf()
+            .with_context(|| format!("Cannot read {}", self.path.display()))?;
+        let byte = chunk.first().copied();
+        if byte.is_some() {
+            self.reader.consume(1);
+        }
+        Ok(byte)
+    }
+}
+
+/// An item decoded from a stream of bytes that may not be valid UTF-8.
+#[derive(Debug, Clone, Copy)]
+enum Decoded {
+    /// A valid UTF-8 character.
+    Char(char),
+    /// A byte that doesn't start a valid UTF-8 character.
+    Raw(u8),
+}
+
+/// Decode the UTF-8 characters of a stream of bytes, keeping the bytes that are not valid UTF-8.
+struct Utf8Decoder<S> {
+    /// Where the bytes come from.
+    source: S,
+    /// The bytes read from the source but not decoded yet, at most 3.
+    pushback: VecDeque<u8>,
+    /// The item decoded but not consumed yet.
+    peeked: Option<Decoded>,
+    /// The error found while decoding the characters for the normalization, it's reported as soon
+    /// as the normalization stops.
+    error: Option<Error>,
+}
+
+impl<S: ByteSource> Utf8Decoder<S> {
+    /// Read the next byte, from the pushed back ones if any.
+    fn next_byte(&mut self) -> Result<Option<u8>, Error> {
+        match self.pushback.pop_front() {
+            Some(byte) => Ok(Some(byte)),
+            None => self.source.next_byte(),
+        }
+    }
+
+    /// Look at the next decoded item without consuming it.
+    fn peek(&mut self) -> Result<Option<Decoded>, Error> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        if self.peeked.is_none() {
+            self.peeked = self.decode()?;
+        }
+        Ok(self.peeked)
+    }
+
+    /// Consume the item returned by `peek`.
+    fn consume(&mut self) {
+        self.peeked = None;
+    }
+
+    /// Decode the next item from the stream.
+    fn decode(&mut self) -> Result<Option<Decoded>, Error> {
+        let first = match self.next_byte()? {
+            Some(first) => first,
+            None => return Ok(None),
+        };
+        let len = match first {
+            0x00..=0x7f => return Ok(Some(Decoded::Char(first as char))),
+            0xc2..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf4 => 4,
+            _ => return Ok(Some(Decoded::Raw(first))),
+        };
+        let mut bytes = [first, 0, 0, 0];
+        let mut read = 1;
+        while read < len {
+            match self.next_byte()? {
+                Some(byte) => bytes[read] = byte,
+                None => break,
+            }
+            read += 1;
+        }
+        match std::str::from_utf8(&bytes[..read]) {
+            Ok(text) if read == len => Ok(text.chars().next().map(Decoded::Char)),
+            _ => {
+                // only the first byte is surely invalid, the others may start a character
+                for &byte in bytes[1..read].iter().rev() {
+                    self.pushback.push_front(byte);
+                }
+                Ok(Some(Decoded::Raw(first)))
+            }
+        }
+    }
+}
+
+/// The characters of the current run of valid UTF-8, it ends before the first invalid byte.
+struct CharRun<S> {
+    /// The decoder shared with the `NfcBytes` that normalizes the run.
+    decoder: Rc<RefCell<Utf8Decoder<S>>>,
+}
+
+impl<S: ByteSource> Iterator for CharRun<S> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let mut decoder = self.decoder.borrow_mut();
+        match decoder.peek() {
+            Ok(Some(Decoded::Char(c))) => {
+                decoder.consume();
+                Some(c)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                decoder.error = Some(e);
+                None
+            }
+        }
+    }
+}
+
+/// The bytes of a stream with its runs of valid UTF-8 converted to the Unicode NFC. The invalid
+/// bytes are kept as they are.
+struct NfcBytes<S> {
+    /// The decoder of the stream.
+    decoder: Rc<RefCell<Utf8Decoder<S>>>,
+    /// The normalization of the current run of valid UTF-8, if any.
+    run: Option<Recompositions<CharRun<S>>>,
+    /// The UTF-8 encoding of the last normalized character.
+    encoded: [u8; 4],
+    /// The bytes of `encoded` not returned yet.
+    pending: std::ops::Range<usize>,
+}
+
+impl<S: ByteSource> NfcBytes<S> {
+    /// Normalize the bytes of the specified source.
+    fn new(source: S) -> NfcBytes<S> {
+        NfcBytes {
+            decoder: Rc::new(RefCell::new(Utf8Decoder {
+                source,
+                pushback: VecDeque::new(),
+                peeked: None,
+                error: None,
+            })),
+            run: None,
+            encoded: [0; 4],
+            pending: 0..0,
+        }
+    }
+}
+
+impl<S: ByteSource> ByteSource for NfcBytes<S> {
+    fn next_byte(&mut self) -> Result<Option<u8>, Error> {
+        loop {
+            if let Some(index) = self.pending.next() {
+                return Ok(Some(self.encoded[index]));
+            }
+            if let Some(run) = &mut self.run {
+                if let Some(c) = run.next() {
+                    self.pending = 0..c.encode_utf8(&mut self.encoded).len();
+                    continue;
+                }
+                self.run = None;
+            }
+            let mut decoder = self.decoder.borrow_mut();
+            match decoder.peek()? {
+                None => return Ok(None),
+                Some(Decoded::Raw(byte)) => {
+                    decoder.consume();
+                    return Ok(Some(byte));
+                }
+                Some(Decoded::Char(_)) => {
+                    let decoder = self.decoder.clone();
+                    self.run = Some(CharRun { decoder }.nfc());
+                }
+            }
+        }
+    }
+}
+
+/// A run of white space inside a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Spaces {
+    /// A run at most `MAX_INLINE_RUN` bytes long.
+    Inline(Vec<u8>),
+    /// The hash of a longer run.
+    Hashed(blake3::Hash),
+}
+
+/// The unit of comparison of the normalized files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// A byte that is not white space.
+    Byte(u8),
+    /// A maximal run of white space.
+    Spaces(Spaces),
+    /// The end of a line.
+    Newline,
+}
+
+/// The run of white space read since the last byte of text, kept until it's known whether the line
+/// continues after it.
+#[derive(Default)]
+struct SpaceRun {
+    /// The bytes of the run, while it's at most `MAX_INLINE_RUN` bytes long.
+    bytes: Vec<u8>,
+    /// The hasher of the run, once it becomes longer.
+    hasher: Option<blake3::Hasher>,
+}
+
+impl SpaceRun {
+    /// Append a byte to the run.
+    fn push(&mut self, byte: u8) {
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&[byte]);
+        } else if self.bytes.len() < MAX_INLINE_RUN {
+            self.bytes.push(byte);
+        } else {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update(&self.bytes);
+            hasher.update(&[byte]);
+            self.hasher = Some(hasher);
+            self.bytes.clear();
+        }
+    }
+
+    /// Whether the run is empty.
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty() && self.hasher.is_none()
+    }
+
+    /// Take the run, leaving it empty.
+    fn take(&mut self) -> Spaces {
+        match self.hasher.take() {
+            Some(hasher) => Spaces::Hashed(hasher.finalize()),
+            None => Spaces::Inline(std::mem::take(&mut self.bytes)),
+        }
+    }
+}
+
+/// The normalized tokens of a file. The normalization state is kept across the chunks of the file,
+/// so only the current run of white space is buffered. Like `diff`, the line feed missing at the
+/// end of the file is a difference unless the white space at the end of the lines is ignored.
+struct Normalizer {
+    /// The bytes of the file.
+    source: Box<dyn ByteSource>,
+    /// The normalizations to apply.
+    options: WhiteDiffOptions,
+    /// The white space read since the last byte of text of the line.
+    run: SpaceRun,
+    /// Whether the current line has at least a byte that is not white space.
+    has_text: bool,
+    /// Whether at least a byte of the current line has been read.
+    started: bool,
+    /// The tokens ready to be returned, at most 3.
+    ready: VecDeque<Token>,
+    /// Whether the end of the file has been reached.
+    done: bool,
+}
+
+impl Normalizer {
+    /// Open the file at the specified path.
+    fn open(path: &Path, options: WhiteDiffOptions) -> Result<Normalizer, Error> {
+        let bytes = FileBytes::open(path)?;
+        let source: Box<dyn ByteSource> = if options.unicode_nfc {
+            Box::new(NfcBytes::new(bytes))
+        } else {
+            Box::new(bytes)
+        };
+        Ok(Normalizer {
+            source,
+            options,
+            run: SpaceRun::default(),
+            has_text: false,
+            started: false,
+            ready: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// The next normalized token of the file, `None` at its end.
+    fn next_token(&mut self) -> Result<Option<Token>, Error> {
+        loop {
+            if let Some(token) = self.ready.pop_front() {
+                return Ok(Some(token));
+            }
+            if self.done {
+                return Ok(None);
+            }
+            match self.source.next_byte()? {
+                None => {
+                    self.done = true;
+                    if self.started {
+                        self.end_line(false);
+                    }
+                }
+                Some(b'\n') => self.end_line(true),
+                Some(byte) if is_space(byte) => {
+                    self.started = true;
+                    if !self.options.space_change {
+                        self.run.push(byte);
+                    } else if self.run.is_empty() {
+                        self.run.push(b' ');
+                    }
+                }
+                Some(byte) => {
+                    self.started = true;
+                    self.has_text = true;
+                    if !self.run.is_empty() {
+                        self.ready.push_back(Token::Spaces(self.run.take()));
+                    }
+                    self.ready.push_back(Token::Byte(byte));
+                }
+            }
+        }
+    }
+
+    /// Emit the end of the current line, skipping it if it's blank and the blank lines are ignored.
+    fn end_line(&mut self, terminated: bool) {
+        if !self.options.blank_lines || self.has_text {
+            if !self.run.is_empty() && !self.options.trims_lines() {
+                self.ready.push_back(Token::Spaces(self.run.take()));
+            }
+            if terminated || self.options.trims_lines() {
+                self.ready.push_back(Token::Newline);
+            }
+        }
+        self.run = SpaceRun::default();
+        self.has_text = false;
+        self.started = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn diff(correct: impl AsRef<[u8]>, test: impl AsRef<[u8]>, options: &[&str]) -> u32 {
+        let tmpdir = TempDir::new().unwrap();
+        std::fs::write(tmpdir.path().join("correct"), correct).unwrap();
+        std::fs::write(tmpdir.path().join("test"), test).unwrap();
+        let args = ["correct", "test"]
+            .iter()
+            .chain(options)
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>();
+        match run_builtin(BuiltinCommand::WhiteDiff, &args, tmpdir.path(), None) {
+            SandboxResult::Success { exit_status, .. } => exit_status,
+            SandboxResult::Failed { error } => panic!("White diff failed: {}", error),
+        }
+    }
+
+    #[test]
+    fn test_white_diff_exact() {
+        assert_eq!(diff("1 2\n3\n", "1 2\n3\n", &[]), 0);
+        assert_eq!(diff("1 2\n3\n", "1 2\n4\n", &[]), 1);
+        assert_eq!(diff("1 2\n3\n", "1  2\n3\n", &[]), 1);
+        assert_eq!(diff("1 2\n3\n", "1 2\n3", &[]), 1);
+        assert_eq!(diff("1 2\n3\n", "1 2\n3\n\n", &[]), 1);
+        assert_eq!(diff("", "\n", &[]), 1);
+    }
+
+    #[test]
+    fn test_white_diff_space_change() {
+        let options = ["space_change"];
+        assert_eq!(diff("1 2\n3\n", "1 \t 2  \r\n3\n", &options), 0);
+        assert_eq!(diff("1 2\n3\n", "12\n3\n", &options), 1);
+        assert_eq!(diff("1 2\n3\n", " 1 2\n3\n", &options), 1);
+    }
+
+    #[test]
+    fn test_white_diff_trailing_whitespace() {
+        let options = ["trailing_whitespace"];
+        assert_eq!(diff("1 2\n3\n", "1 2 \t\n3  \n", &options), 0);
+        assert_eq!(diff("1 2\n3\n", "1  2\n3\n", &options), 1);
+    }
+
+    #[test]
+    fn test_white_diff_blank_lines() {
+        let options = ["blank_lines"];
+        assert_eq!(diff("1 2\n3\n", "\n1 2\n\n3\n", &options), 0);
+        assert_eq!(diff("1 2\n3\n", "1 2\n3\n\n\n", &options), 0);
+        assert_eq!(diff("1 2\n3\n", "\n1 2\n\n3", &options), 1);
+        assert_eq!(diff("1 2\n3\n", "1 2\n  \n3\n", &options), 0);
+        assert_eq!(diff("1 2\n3\n", "1 2 3\n", &options), 1);
+    }
+
+    #[test]
+    fn test_white_diff_unicode_nfc() {
+        assert_eq!(diff("\u{e8}\n", "e\u{300}\n", &[]), 1);
+        assert_eq!(diff("\u{e8}\n", "e\u{300}\n", &["unicode_nfc"]), 0);
+    }
+
+    #[test]
+    fn test_white_diff_unicode_nfc_invalid_utf8() {
+        let correct = b"\xc3\xa8\xff\xc3\xa8\n";
+        let test = b"e\xcc\x80\xffe\xcc\x80\n";
+        assert_eq!(diff(correct, test, &[]), 1);
+        assert_eq!(diff(correct, test, &["unicode_nfc"]), 0);
+        assert_eq!(diff(b"\xc3\n", b"\xc3\n", &["unicode_nfc"]), 0);
+        assert_eq!(diff(b"\xc3\n", b"\xc4\n", &["unicode_nfc"]), 1);
+    }
+
+    #[test]
+    fn test_white_diff_long_lines() {
+        let line = "1 ".repeat(CHUNK_SIZE) + "2";
+        let spaces = " ".repeat(CHUNK_SIZE * 3);
+        let correct = format!("{}\n{}3\n", line, spaces);
+        assert_eq!(diff(&correct, &correct, &[]), 0);
+        assert_eq!(diff(&correct, format!("{}\n{} 3\n", line, spaces), &[]), 1);
+        assert_eq!(diff(&correct, format!("{}4\n{}3\n", line, spaces), &[]), 1);
+        assert_eq!(diff(&correct, format!("{}\n{}3", line, spaces), &[]), 1);
+        let test = format!("{}{}\n 3\t\n", line.replace(' ', "\t "), spaces);
+        assert_eq!(diff(&correct, &test, &["space_change"]), 0);
+        let test = format!("{}{}\n{}3{}", line, spaces, spaces, spaces);
+        assert_eq!(diff(&correct, &test, &["trailing_whitespace"]), 0);
+        let test = format!("{}\n{}\n{}3\n", line, spaces, spaces);
+        assert_eq!(diff(&correct, &test, &["blank_lines"]), 0);
+    }
+
+    #[test]
+    fn test_white_diff_time_limit() {
+        let tmpdir = TempDir::new().unwrap();
+        let content = "1\n".repeat(TIME_CHECK_INTERVAL);
+        std::fs::write(tmpdir.path().join("correct"), &content).unwrap();
+        std::fs::write(tmpdir.path().join("test"), &content).unwrap();
+        let args = vec!["correct".to_string(), "test".to_string()];
+        let result = run_builtin(BuiltinCommand::WhiteDiff, &args, tmpdir.path(), Some(0.0));
+        assert!(matches!(
+            result,
+            SandboxResult::Success {
+                was_killed: true,
+                ..
+            }
+        ));
+        let result = run_builtin(BuiltinCommand::WhiteDiff, &args, tmpdir.path(), Some(10.0));
+        assert!(matches!(
+            result,
+            SandboxResult::Success {
+                exit_status: 0,
+                was_killed: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_white_diff_agrees_with_diff() {
+        if std::process::Command::new("diff")
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            return;
+        }
+        let files = [
+            "",
+            "\n",
+            "\n\n",
+            "1 2\n3\n",
+            "1 2\n3",
+            "1 2\n3\n\n",
+            "1 2\n3 \n",
+            "1 2\n3 ",
+            "1  2\n3\n",
+            "1 2\n\n3\n",
+            " 1 2\n3\n",
+            "1\t2\n3\n",
+        ];
+        // the options of diff used by the checker for each normalization. The blank lines are not
+        // compared: diff ignores only the changes made entirely of blank lines, so moving them
+        // around may be a difference depending on how diff matches the lines.
+        let options: [(&[&str], &[&str]); 3] = [
+            (&[], &[]),
+            (&["space_change"], &["--ignore-space-change"]),
+            (&["trailing_whitespace"], &["--ignore-trailing-space"]),
+        ];
+        for correct in &files {
+            for test in &files {
+                for (builtin_options, diff_options) in &options {
+                    let tmpdir = TempDir::new().unwrap();
+                    std::fs::write(tmpdir.path().join("correct"), correct).unwrap();
+                    std::fs::write(tmpdir.path().join("test"), test).unwrap();
+                    let status = std::process::Command::new("diff")
+                        .arg("--brief")
+                        .args(diff_options.iter())
+                        .args(["correct", "test"])
+                        .current_dir(tmpdir.path())
+                        .output()
+                        .unwrap()
+                        .status;
+                    assert_eq!(
+                        diff(correct, test, builtin_options),
+                        status.code().unwrap() as u32,
+                        "{:?} vs {:?} with {:?}",
+                        correct,
+                        test,
+                        builtin_options
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_white_diff_invalid_args() {
+        let tmpdir = TempDir::new().unwrap();
+        let result = run_builtin(BuiltinCommand::WhiteDiff, &[], tmpdir.path(), None);
+        assert!(matches!(result, SandboxResult::Failed { .. }));
+        let args = vec!["correct".to_string(), "test".to_string()];
+        let result = run_builtin(BuiltinCommand::WhiteDiff, &args, tmpdir.path(), None);
+        assert!(matches!(result, SandboxResult::Failed { .. }));
+    }
+}
@@ -67,6 +67,18 @@ pub enum DAGError {
 /// It's checked that no duplicated UUID are present, no files are missing, all the executions are
 /// reachable and no cycles are present.
 pub fn check_dag(dag: &ExecutionDAGData, callbacks: &ExecutionDAGWatchSet) -> Result<(), DAGError> {
+    check_dag_extension(dag, callbacks, &HashSet::new(), &HashSet::new())
+}
+
+/// Validate an extension of a DAG, like [`check_dag`](fn.check_dag.html) does. The extension can
+/// depend on the files of the DAG it extends (`base_files`), and register callbacks on them and on
+/// its executions (`base_execs`), but it cannot redefine them.
+pub fn check_dag_extension(
+    dag: &ExecutionDAGData,
+    callbacks: &ExecutionDAGWatchSet,
+    base_files: &HashSet<FileUuid>,
+    base_execs: &HashSet<ExecutionUuid>,
+) -> Result<(), DAGError> {
     let mut dependencies: HashMap<FileUuid, Vec<ExecutionGroupUuid>> = HashMap::new();
     let mut num_dependencies: HashMap<ExecutionGroupUuid, usize> = HashMap::new();
     let mut known_files: HashSet<FileUuid> = HashSet::new();
@@ -92,11 +104,12 @@ pub fn check_dag(dag: &ExecutionDAGData, callbacks: &ExecutionDAGWatchSet) -> Re
         let mut count = 0;
         for exec in &group.executions {
             let deps = exec.dependencies();
-            if !known_execs.insert(exec.uuid) {
+            if base_execs.contains(&exec.uuid) || !known_execs.insert(exec.uuid) {
                 return Err(DAGError::DuplicateExecutionUUID { uuid: exec.uuid });
             }
-            count += deps.len();
-            for dep in deps.into_iter() {
+            // the files of the extended DAG are already known
+            for dep in deps.into_iter().filter(|dep| !base_files.contains(dep)) {
+                count += 1;
                 add_dependency(dep, *group_uuid);
             }
             if exec.capture_stdout.is_some() && exec.stdout.is_none() {
@@ -114,7 +127,7 @@ pub fn check_dag(dag: &ExecutionDAGData, callbacks: &ExecutionDAGWatchSet) -> Re
                 });
             }
             for out in exec.outputs().into_iter() {
-                if !known_files.insert(out) {
+                if base_files.contains(&out) || !known_files.insert(out) {
                     return Err(DAGError::DuplicateFileUUID { uuid: out });
                 }
             }
@@ -127,7 +140,7 @@ pub fn check_dag(dag: &ExecutionDAGData, callbacks: &ExecutionDAGWatchSet) -> Re
     // add the provided files
     for uuid in dag.provided_files.keys() {
         ready_files.push_back(*uuid);
-        if !known_files.insert(*uuid) {
+        if base_files.contains(uuid) || !known_files.insert(*uuid) {
             return Err(DAGError::DuplicateFileUUID { uuid: *uuid });
         }
     }
@@ -172,7 +185,7 @@ pub fn check_dag(dag: &ExecutionDAGData, callbacks: &ExecutionDAGWatchSet) -> Re
         let group = &dag.execution_groups[group_uuid];
         for exec in &group.executions {
             for dep in exec.dependencies().iter() {
-                if !known_files.contains(dep) {
+                if !known_files.contains(dep) && !base_files.contains(dep) {
                     return Err(DAGError::MissingFile {
                         uuid: *dep,
                         description: format!("Dependency of '{}'", exec.description),
@@ -186,7 +199,7 @@ pub fn check_dag(dag: &ExecutionDAGData, callbacks: &ExecutionDAGWatchSet) -> Re
     }
     // check the file callbacks
     for file in callbacks.files.iter() {
-        if !known_files.contains(file) {
+        if !known_files.contains(file) && !base_files.contains(file) {
             return Err(DAGError::MissingFile {
                 uuid: *file,
                 description: "File required by a callback".to_owned(),
@@ -195,7 +208,7 @@ pub fn check_dag(dag: &ExecutionDAGData, callbacks: &ExecutionDAGWatchSet) -> Re
     }
     // check the execution callbacks
     for exec in callbacks.executions.iter() {
-        if !known_execs.contains(exec) {
+        if !known_execs.contains(exec) && !base_execs.contains(exec) {
             return Err(DAGError::MissingExecution { uuid: *exec });
         }
     }
@@ -225,6 +238,7 @@ mod tests {
             executions: Default::default(),
             files: [file.uuid].iter().cloned().collect(),
             urgent_files: Default::default(),
+            extensions: 0,
         };
         assert!(check_dag(&dag.data, &watch).is_err());
     }
@@ -237,6 +251,7 @@ mod tests {
             executions: [exec.uuid].iter().cloned().collect(),
             files: Default::default(),
             urgent_files: Default::default(),
+            extensions: 0,
         };
         assert!(check_dag(&dag.data, &watch).is_err());
     }
@@ -285,4 +300,38 @@ mod tests {
         dag.provide_file(file, "/dev/null").unwrap();
         assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default()).is_err());
     }
+
+    #[test]
+    fn test_extension_depends_on_base() {
+        let mut base = Execution::new("base", ExecutionCommand::local("foo"));
+        let base_output = base.stdout();
+        let base_files = [base_output.uuid].into_iter().collect();
+        let base_execs = [base.uuid].into_iter().collect();
+
+        let mut dag = ExecutionDAG::new();
+        let mut exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        exec.stdin(&base_output);
+        dag.add_execution(exec);
+        let watch = ExecutionDAGWatchSet {
+            executions: [base.uuid].into_iter().collect(),
+            ..Default::default()
+        };
+        assert!(check_dag(&dag.data, &watch).is_err());
+        assert!(check_dag_extension(&dag.data, &watch, &base_files, &base_execs).is_ok());
+    }
+
+    #[test]
+    fn test_extension_duplicate_file() {
+        let mut base = Execution::new("base", ExecutionCommand::local("foo"));
+        let base_output = base.stdout();
+        let base_files = [base_output.uuid].into_iter().collect();
+
+        let mut dag = ExecutionDAG::new();
+        let mut exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        exec.stdout = Some(base_output);
+        dag.add_execution(exec);
+        let watch = ExecutionDAGWatchSet::default();
+        assert!(check_dag(&dag.data, &watch).is_ok());
+        assert!(check_dag_extension(&dag.data, &watch, &base_files, &HashSet::new()).is_err());
+    }
 }
@@ -5,19 +5,36 @@ use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
-use anyhow::{anyhow, Context, Error};
+use anyhow::{anyhow, bail, Context, Error};
 use ductile::{ChannelReceiver, ChannelSender};
 
-use task_maker_dag::{ExecutionDAG, FileCallbacks, FileUuid, ProvidedFile, WriteToCallback};
+use task_maker_dag::{
+    ExecutionDAG, ExecutionUuid, FileCallbacks, FileUuid, ProvidedFile, WriteToCallback,
+};
 use task_maker_store::*;
 
-use crate::executor::{ExecutionDAGWatchSet, ExecutorStatus, ExecutorWorkerStatus};
+use crate::admin::{AdminRequest, AdminResponse};
+use crate::executor::{ExecutionDAGWatchSet, ExecutorStatus, ExecutorWorkerStatus, FileTransfer};
 use crate::proto::*;
 
 /// Interval between each Status message is sent asking for server status updates.
 const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+/// Files smaller than this are sent without reporting the progress of the upload.
+const TRANSFER_PROGRESS_MIN_SIZE: u64 = 1024 * 1024;
+/// Minimum interval between two reports of the progress of an upload.
+const TRANSFER_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// An update on the evaluation, sent by the client to the caller of `ExecutorClient::evaluate`.
+#[derive(Debug, Clone)]
+pub enum ClientUpdate {
+    /// The status of the executor, polled every `STATUS_POLL_INTERVAL`.
+    Status(ExecutorStatus<SystemTime>),
+    /// The progress of the upload of a large file to the executor. The last update of each file
+    /// has `sent == total`.
+    Transfer(FileTransfer),
+}
 
 /// This is a client of the `Executor`, the client is who sends a DAG for an evaluation, provides
 /// some files and receives the callbacks from the server. When the server notifies a callback
@@ -33,6 +50,9 @@ impl ExecutorClient {
     /// * `eval` - The EvaluationData to evaluate.
     /// * `sender` - A channel that sends messages to the server.
     /// * `receiver` - A channel that receives messages from the server.
+    /// * `file_store` - The store where the files from the server are kept.
+    /// * `compression` - Whether to compress the files sent, as told by the server on connection.
+    /// * `update_callback` - Called with the status of the server and the progress of the uploads.
     ///
     /// ```
     /// use task_maker_dag::ExecutionDAG;
@@ -63,7 +83,7 @@ impl ExecutorClient {
     ///     executor.evaluate(tx_remote, rx_remote).unwrap();
     /// });
     ///
-    /// ExecutorClient::evaluate(dag, tx, &rx, file_store, |_| Ok(())).unwrap(); // this will block!
+    /// ExecutorClient::evaluate(dag, tx, &rx, file_store, false, |_| Ok(())).unwrap(); // this will block!
     ///
     /// server.join().expect("Server paniced");
     /// ```
@@ -73,10 +93,11 @@ impl ExecutorClient {
         sender: ChannelSender<ExecutorClientMessage>,
         receiver: &ChannelReceiver<ExecutorServerMessage>,
         file_store: Arc<FileStore>,
-        mut status_callback: F,
+        compression: bool,
+        mut update_callback: F,
     ) -> Result<(), Error>
     where
-        F: FnMut(ExecutorStatus<SystemTime>) -> Result<(), Error>,
+        F: FnMut(ClientUpdate) -> Result<(), Error>,
     {
         trace!("ExecutorClient started");
         ExecutorClient::start_evaluation(&mut dag, &sender)?;
@@ -99,14 +120,22 @@ impl ExecutorClient {
         let mut missing_files = None;
         while missing_files.unwrap_or(1) > 0 {
             match receiver.recv() {
-                Ok(ExecutorServerMessage::AskFile(uuid)) => {
-                    info!("Server is asking for {}", uuid);
+                Ok(ExecutorServerMessage::AskFile(uuid, offset)) => {
+                    info!("Server is asking for {} from {}", uuid, offset);
                     // prevent the status poller for sending messages while sending the file
                     let _lock = file_mode
                         .lock()
                         .map_err(|_| anyhow!("Failed to obtain file_mode lock"))?;
                     let provided_files = &dag.data.provided_files;
-                    handle_server_ask_file(uuid, provided_files, &sender).with_context(|| {
+                    handle_server_ask_file(
+                        uuid,
+                        offset,
+                        provided_files,
+                        &sender,
+                        ChannelFileSender::new(compression),
+                        &mut update_callback,
+                    )
+                    .with_context(|| {
                         format!("Failed to process AskFile({}) from the server", uuid)
                     })?;
                 }
@@ -116,13 +145,20 @@ impl ExecutorClient {
                         missing_files = Some(missing - 1);
                     }
                     let iterator = ChannelFileIterator::new(receiver);
-                    process_provided_file(dag.file_callbacks(), uuid, success, iterator, None)
-                        .with_context(|| {
-                            format!(
-                                "Failed to process ProvideFile({}, {}) from the server",
-                                uuid, success
-                            )
-                        })?;
+                    process_provided_file(
+                        dag.file_callbacks(),
+                        uuid,
+                        success,
+                        iterator,
+                        None,
+                        None,
+                    )
+                    .with_context(|| {
+                        format!(
+                            "Failed to process ProvideFile({}, {}) from the server",
+                            uuid, success
+                        )
+                    })?;
                 }
                 Ok(ExecutorServerMessage::NotifyStart(uuid, worker)) => {
                     info!("Execution {} started on {}", uuid, worker);
@@ -145,6 +181,7 @@ impl ExecutorClient {
                             }
                         }
                     }
+                    ExecutorClient::extend_dag(&mut dag, uuid, &sender)?;
                 }
                 Ok(ExecutorServerMessage::NotifySkip(uuid)) => {
                     info!("Execution {} skipped", uuid);
@@ -156,6 +193,7 @@ impl ExecutorClient {
                             }
                         }
                     }
+                    ExecutorClient::extend_dag(&mut dag, uuid, &sender)?;
                 }
                 Ok(ExecutorServerMessage::Error(error)) => {
                     error!("Error occurred: {}", error);
@@ -164,9 +202,30 @@ impl ExecutorClient {
                         .context("Failed to send Stop message to the server after an error")?;
                     break;
                 }
+                Ok(ExecutorServerMessage::QuotaExceeded(quota)) => {
+                    error!("Quota exceeded: {}", quota);
+                    let _ = sender.send(ExecutorClientMessage::Stop);
+                    return Err(quota.into());
+                }
+                Ok(ExecutorServerMessage::Reattached {
+                    evaluation,
+                    recovered,
+                }) => {
+                    if recovered {
+                        warn!(
+                            "Reattached to evaluation {}, recovered after a restart of the server",
+                            evaluation
+                        );
+                    } else {
+                        info!("Reattached to evaluation {}", evaluation);
+                    }
+                }
+                Ok(ExecutorServerMessage::Admin(response)) => {
+                    warn!("Unexpected admin response: {:?}", response);
+                }
                 Ok(ExecutorServerMessage::Status(status)) => {
                     info!("Server status: {:#?}", status);
-                    handle_server_status(status, &mut status_callback)
+                    handle_server_status(status, &mut update_callback)
                         .context("Failed to process Status() from the server")?;
                 }
                 Ok(ExecutorServerMessage::Done(result)) => {
@@ -174,19 +233,19 @@ impl ExecutorClient {
                     let mut missing = 0;
                     for (uuid, key, success) in result {
                         if let Some(handle) = file_store.get(&key) {
-                            let iterator =
-                                ReadFileIterator::new(handle.path()).with_context(|| {
-                                    format!(
-                                        "Failed to read produced file ({}) from the local storage",
-                                        handle
-                                    )
-                                })?;
+                            let iterator = handle.read().with_context(|| {
+                                format!(
+                                    "Failed to read produced file ({}) from the local storage",
+                                    handle
+                                )
+                            })?;
                             process_provided_file(
                                 dag.file_callbacks(),
                                 uuid,
                                 success,
                                 iterator,
                                 None,
+                                Some(&handle),
                             )
                             .with_context(|| {
                                 format!(
@@ -212,6 +271,14 @@ impl ExecutorClient {
                     } else {
                         error!("Connection error: {}", cause);
                     }
+                    // the server keeps evaluating the DAG, tell the user how to get back to it
+                    if let Some(evaluation_id) = dag.data.config.evaluation_id {
+                        bail!(
+                            "Connection to the server lost, the evaluation continues remotely: \
+                            reattach with --resume {}",
+                            evaluation_id
+                        );
+                    }
                     break;
                 }
             }
@@ -219,17 +286,81 @@ impl ExecutorClient {
         Ok(())
     }
 
+    /// Send a request to the server as an administrator, waiting for its response. The server
+    /// refuses the requests without its admin password.
+    pub fn admin(
+        request: AdminRequest,
+        password: Option<String>,
+        sender: &ChannelSender<ExecutorClientMessage>,
+        receiver: &ChannelReceiver<ExecutorServerMessage>,
+    ) -> Result<AdminResponse, Error> {
+        sender
+            .send(ExecutorClientMessage::Admin(request, password))
+            .context("Failed to send the admin request")?;
+        loop {
+            match receiver
+                .recv()
+                .context("Failed to receive the admin response")?
+            {
+                ExecutorServerMessage::Admin(response) => return Ok(response),
+                ExecutorServerMessage::Error(error) => bail!("Server error: {}", error),
+                message => warn!("Unexpected message from the server: {:?}", message),
+            }
+        }
+    }
+
     /// Start the evaluation calling the file callbacks on the input files and sending the start
     /// message to the Executor.
     fn start_evaluation(
         dag: &mut ExecutionDAG,
         sender: &ChannelSender<ExecutorClientMessage>,
     ) -> Result<(), Error> {
+        let dag_callbacks = ExecutorClient::provide_files(dag)?;
+        sender.send(ExecutorClientMessage::Evaluate {
+            dag: Box::new(dag.data.clone()),
+            callbacks: Box::new(dag_callbacks),
+        })
+    }
+
+    /// Call the callbacks that extend the DAG after the end of an execution, sending to the
+    /// Executor each new part and adding it to the DAG.
+    fn extend_dag(
+        dag: &mut ExecutionDAG,
+        execution: ExecutionUuid,
+        sender: &ChannelSender<ExecutorClientMessage>,
+    ) -> Result<(), Error> {
+        let extensions = match dag.execution_callbacks().get_mut(&execution) {
+            Some(callbacks) => std::mem::take(&mut callbacks.extend_dag),
+            None => return Ok(()),
+        };
+        for extension in extensions {
+            let mut part = dag.extension();
+            extension(&mut part)
+                .with_context(|| format!("Failed to extend the DAG after {}", execution))?;
+            let callbacks = ExecutorClient::provide_files(&mut part)?;
+            info!(
+                "Extending the DAG after {} with {} executions",
+                execution,
+                part.data.execution_groups.len()
+            );
+            sender.send(ExecutorClientMessage::Extend {
+                dag: Box::new(part.data.clone()),
+                callbacks: Box::new(callbacks),
+            })?;
+            dag.merge_extension(part);
+        }
+        Ok(())
+    }
+
+    /// Call the file callbacks on the input files of the DAG, returning the set of the files and
+    /// the executions that want callbacks.
+    fn provide_files(dag: &mut ExecutionDAG) -> Result<ExecutionDAGWatchSet, Error> {
         // list all the files/executions that want callbacks
         let dag_callbacks = ExecutionDAGWatchSet {
             executions: dag.execution_callbacks().keys().cloned().collect(),
             files: dag.file_callbacks().keys().cloned().collect(),
             urgent_files: dag.urgent_files().clone(),
+            extensions: dag.num_extensions(),
         };
         for (uuid, file) in dag.data.provided_files.iter() {
             match file {
@@ -243,6 +374,7 @@ impl ExecutorClient {
                         true,
                         iterator,
                         Some(local_path),
+                        None,
                     )
                     .context("Failed to process local file")?;
                 }
@@ -253,15 +385,13 @@ impl ExecutorClient {
                         true,
                         vec![content.clone()],
                         None,
+                        None,
                     )
                     .context("Failed to process file content")?;
                 }
             }
         }
-        sender.send(ExecutorClientMessage::Evaluate {
-            dag: Box::new(dag.data.clone()),
-            callbacks: Box::new(dag_callbacks),
-        })
+        Ok(dag_callbacks)
     }
 
     /// Spawn a thread that will ask the server status every `STATUS_POLL_INTERVAL_MS`, making sure
@@ -295,30 +425,77 @@ impl ExecutorClient {
     }
 }
 
-/// Server is asking for a file, handle the request sending the local file or the provided content.
+/// Server is asking for a file, handle the request sending the local file or the provided content,
+/// skipping the first `offset` bytes the server already has.
 /// Note that this will trigger a protocol change for sending the file, no messages should be sent
 /// meanwhile.
-fn handle_server_ask_file(
+///
+/// The progress of the upload of the large local files is reported to `update_callback`.
+fn handle_server_ask_file<F>(
     uuid: FileUuid,
+    offset: u64,
     provided_files: &HashMap<FileUuid, ProvidedFile>,
     sender: &ChannelSender<ExecutorClientMessage>,
-) -> Result<(), Error> {
+    file_sender: ChannelFileSender,
+    update_callback: &mut F,
+) -> Result<(), Error>
+where
+    F: FnMut(ClientUpdate) -> Result<(), Error>,
+{
     match &provided_files[&uuid] {
         ProvidedFile::LocalFile {
-            local_path, key, ..
+            file,
+            local_path,
+            key,
         } => {
             sender
-                .send(ExecutorClientMessage::ProvideFile(uuid, key.clone()))
+                .send(ExecutorClientMessage::ProvideFile(
+                    uuid,
+                    key.clone(),
+                    offset,
+                ))
                 .context("Failed to send ExecutorClientMessage::ProvideFile")?;
-            ChannelFileSender::send(local_path, sender).with_context(|| {
-                format!("Failed to send local file from {}", local_path.display())
-            })?;
+            let total = std::fs::metadata(local_path).map_or(0, |m| m.len());
+            let mut report = |sent| {
+                update_callback(ClientUpdate::Transfer(FileTransfer {
+                    file: file.description.clone(),
+                    sent,
+                    total,
+                }))
+            };
+            let large = total >= TRANSFER_PROGRESS_MIN_SIZE;
+            if large {
+                report(offset)?;
+            }
+            let mut last_report = Instant::now();
+            file_sender
+                .send_from_with_progress(local_path, offset, sender, |sent| {
+                    let sent = offset + sent;
+                    if large && sent < total && last_report.elapsed() >= TRANSFER_PROGRESS_INTERVAL
+                    {
+                        last_report = Instant::now();
+                        report(sent)?;
+                    }
+                    Ok(())
+                })
+                .with_context(|| {
+                    format!("Failed to send local file from {}", local_path.display())
+                })?;
+            if large {
+                report(total)?;
+            }
         }
         ProvidedFile::Content { content, key, .. } => {
+            let offset = offset.min(content.len() as u64);
             sender
-                .send(ExecutorClientMessage::ProvideFile(uuid, key.clone()))
+                .send(ExecutorClientMessage::ProvideFile(
+                    uuid,
+                    key.clone(),
+                    offset,
+                ))
                 .context("Failed to send ExecutorClientMessage::ProvideFile")?;
-            ChannelFileSender::send_data(content.clone(), sender)
+            file_sender
+                .send_data(content[offset as usize..].to_vec(), sender)
                 .context("Failed to send file content")?;
         }
     }
@@ -328,12 +505,12 @@ fn handle_server_ask_file(
 /// Handle the server response to the status request.
 fn handle_server_status<F>(
     status: ExecutorStatus<Duration>,
-    status_callback: &mut F,
+    update_callback: &mut F,
 ) -> Result<(), Error>
 where
-    F: FnMut(ExecutorStatus<SystemTime>) -> Result<(), Error>,
+    F: FnMut(ClientUpdate) -> Result<(), Error>,
 {
-    status_callback(ExecutorStatus {
+    update_callback(ClientUpdate::Status(ExecutorStatus {
         connected_workers: status
             .connected_workers
             .into_iter()
@@ -341,11 +518,14 @@ where
                 uuid: worker.uuid,
                 name: worker.name,
                 current_job: worker.current_job.map(|status| status.into_system_time()),
+                telemetry: worker.telemetry,
             })
             .collect(),
         ready_execs: status.ready_execs,
         waiting_execs: status.waiting_execs,
-    })
+        cache_hits: status.cache_hits,
+        cache_misses: status.cache_misses,
+    }))
 }
 
 /// Process a file provided either by the client or by the server, calling the callback and writing
@@ -355,12 +535,17 @@ where
 /// truncated, for this reason a best-effort approach is implemented: if the iterator reads a local
 /// file pass to this function also the path to the file. The file wont be truncated if write_to
 /// points to the same file as the hint.
+///
+/// If the file is in the local store, its handle is used for writing it to the `write_to` path,
+/// reflinking it when possible instead of copying the content chunk by chunk, and the iterator is
+/// consumed only if some callback needs the content.
 fn process_provided_file<I: IntoIterator<Item = Vec<u8>>>(
     file_callbacks: &mut HashMap<FileUuid, FileCallbacks>,
     uuid: FileUuid,
     success: bool,
     iterator: I,
     source_path_hint: Option<&Path>,
+    store_handle: Option<&FileStoreHandle>,
 ) -> Result<(), Error> {
     if let Some(callback) = file_callbacks.get_mut(&uuid) {
         let limit = callback
@@ -402,16 +587,27 @@ fn process_provided_file<I: IntoIterator<Item = Vec<u8>>>(
                                 dest.display()
                             )
                         })?;
-                        let file = std::fs::File::create(dest).with_context(|| {
-                            format!("Failed to create file: {}", dest.display())
-                        })?;
-                        (Some(file), Some(dest.clone()))
+                        if let Some(handle) = store_handle {
+                            handle.copy_to(dest).with_context(|| {
+                                format!("Failed to write file: {}", dest.display())
+                            })?;
+                            (None, None)
+                        } else {
+                            let file = std::fs::File::create(dest).with_context(|| {
+                                format!("Failed to create file: {}", dest.display())
+                            })?;
+                            (Some(file), Some(dest.clone()))
+                        }
                     }
                 }
             }
             _ => (None, None),
         };
-        for chunk in iterator {
+        // when the file has been written from the store, the content may not be needed at all
+        let needs_content = file.is_some()
+            || callback.get_content.is_some()
+            || !callback.get_content_chunked.is_empty();
+        for chunk in needs_content.then_some(iterator).into_iter().flatten() {
             if let (Some(file), Some(dest)) = (&mut file, &dest) {
                 file.write_all(&chunk)
                     .with_context(|| format!("Failed to write chunk to {}", dest.display()))?;
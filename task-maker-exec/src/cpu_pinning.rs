@@ -0,0 +1,152 @@
+//! Pinning of the executions of a worker to a dedicated CPU.
+//!
+//! When many workers run on the same machine the kernel moves their processes between the cores,
+//! and two processes running on the hyperthreads of the same core slow each other down. Pinning
+//! each worker to its own CPU makes the measured times more stable.
+
+use std::str::FromStr;
+
+use anyhow::{Context, Error};
+use nix::sched::{sched_getaffinity, sched_setaffinity, CpuSet};
+use nix::unistd::Pid;
+
+/// How the executions of a worker are pinned to the CPUs of the machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CpuPinning {
+    /// The executions can run on any CPU.
+    #[default]
+    None,
+    /// Each worker runs its executions on a dedicated logical CPU.
+    Core,
+    /// Each worker runs its executions on a dedicated physical core, the hyperthread siblings of
+    /// that core are left unused.
+    Isolated,
+}
+
+impl FromStr for CpuPinning {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CpuPinning, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(CpuPinning::None),
+            "core" => Ok(CpuPinning::Core),
+            "isolated" => Ok(CpuPinning::Isolated),
+            _ => Err(format!("Unknown CPU pinning policy: {}", s)),
+        }
+    }
+}
+
+impl CpuPinning {
+    /// The CPU the worker with the given index should run its executions on, or `None` if the
+    /// executions are not pinned. The workers with an index larger than the number of usable CPUs
+    /// wrap around, sharing the CPUs.
+    pub fn assign_cpu(&self, index: usize) -> Option<usize> {
+        let cpus = match self {
+            CpuPinning::None => return None,
+            CpuPinning::Core => allowed_cpus(),
+            CpuPinning::Isolated => one_cpu_per_core(&allowed_cpus(), thread_siblings),
+        };
+        if cpus.is_empty() {
+            warn!("Cannot find the CPUs of this machine, the executions won't be pinned");
+            return None;
+        }
+        Some(cpus[index % cpus.len()])
+    }
+}
+
+/// Restrict the current thread, and all the processes it will spawn, to run only on `cpu`.
+pub fn pin_current_thread(cpu: usize) -> Result<(), Error> {
+    let mut cpu_set = CpuSet::new();
+    cpu_set
+        .set(cpu)
+        .with_context(|| format!("Invalid CPU {}", cpu))?;
+    sched_setaffinity(Pid::from_raw(0), &cpu_set)
+        .with_context(|| format!("Failed to pin the thread to CPU {}", cpu))?;
+    Ok(())
+}
+
+/// The CPUs this process is allowed to run on, in increasing order.
+fn allowed_cpus() -> Vec<usize> {
+    match sched_getaffinity(Pid::from_raw(0)) {
+        Ok(cpu_set) => (0..CpuSet::count())
+            .filter(|&cpu| cpu_set.is_set(cpu).unwrap_or(false))
+            .collect(),
+        Err(e) => {
+            warn!("Cannot get the CPU affinity of the process: {:?}", e);
+            vec![]
+        }
+    }
+}
+
+/// The logical CPUs sharing the same physical core of `cpu`, including `cpu` itself.
+fn thread_siblings(cpu: usize) -> Vec<usize> {
+    let path = format!(
+        "/sys/devices/system/cpu/cpu{}/topology/thread_siblings_list",
+        cpu
+    );
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|list| parse_cpu_list(&list))
+        .unwrap_or_else(|| vec![cpu])
+}
+
+/// Keep only the first of the CPUs of each physical core, given a function that returns the
+/// siblings of a CPU.
+fn one_cpu_per_core<F>(cpus: &[usize], siblings: F) -> Vec<usize>
+where
+    F: Fn(usize) -> Vec<usize>,
+{
+    let mut result: Vec<usize> = Vec::new();
+    for &cpu in cpus {
+        if !siblings(cpu).iter().any(|sibling| result.contains(sibling)) {
+            result.push(cpu);
+        }
+    }
+    result
+}
+
+/// Parse a list of CPUs in the format of the kernel, like `0-3,8,10-11`.
+fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let from: usize = from.parse().ok()?;
+                let to: usize = to.parse().ok()?;
+                cpus.extend(from..=to);
+            }
+            None => cpus.push(part.parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("0-3\n"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(parse_cpu_list("0,4"), Some(vec![0, 4]));
+        assert_eq!(parse_cpu_list("0-1,8,10-11"), Some(vec![0, 1, 8, 10, 11]));
+        assert_eq!(parse_cpu_list("garbage"), None);
+    }
+
+    #[test]
+    fn test_one_cpu_per_core() {
+        // 4 cores with 2 hyperthreads each: cpu N is sibling of cpu N+4
+        let siblings = |cpu: usize| vec![cpu % 4, cpu % 4 + 4];
+        let cpus: Vec<_> = (0..8).collect();
+        assert_eq!(one_cpu_per_core(&cpus, siblings), vec![0, 1, 2, 3]);
+        assert_eq!(one_cpu_per_core(&[1, 5, 6], siblings), vec![1, 6]);
+    }
+
+    #[test]
+    fn test_parse_cpu_pinning() {
+        assert_eq!("none".parse(), Ok(CpuPinning::None));
+        assert_eq!("Core".parse(), Ok(CpuPinning::Core));
+        assert_eq!("isolated".parse(), Ok(CpuPinning::Isolated));
+        assert!("foo".parse::<CpuPinning>().is_err());
+    }
+}
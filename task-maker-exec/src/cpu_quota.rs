@@ -0,0 +1,195 @@
+//! Detection of the number of cores the local executor can use.
+//!
+//! Inside a container the CPU time is often limited by a cgroup quota, while the container still
+//! sees all the cores of the host. Running one worker per core of the host would make the
+//! executions compete for the CPU time, making their timings unreliable.
+
+use std::path::{Path, PathBuf};
+
+/// Root of the cgroup filesystem.
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// The number of cores the local executor should use: the number of physical cores of the
+/// machine, limited by the CPUs the process is allowed to run on (e.g. the cpuset of a container)
+/// and by the CPU quota of its cgroup, if any. It's always at least 1.
+pub fn available_cores() -> usize {
+    let physical = num_cpus::get_physical();
+    let mut cores = physical;
+    // the affinity of the process, which honours the cpuset of the cgroup
+    if let Ok(parallelism) = std::thread::available_parallelism() {
+        if parallelism.get() < cores {
+            info!(
+                "The process can run on {} CPUs, using {} cores instead of {}",
+                parallelism, parallelism, physical
+            );
+            cores = parallelism.get();
+        }
+    }
+    if let Some(limit) = cgroup_cpu_limit() {
+        if (limit as usize) < cores {
+            let limited = (limit as usize).max(1);
+            info!(
+                "The cgroup CPU quota allows {:.2} CPUs, using {} cores instead of {}",
+                limit, limited, cores
+            );
+            cores = limited;
+        }
+    }
+    cores
+}
+
+/// The number of CPUs the cgroup of this process is allowed to use, if limited.
+fn cgroup_cpu_limit() -> Option<f64> {
+    let cgroups = std::fs::read_to_string("/proc/self/cgroup").unwrap_or_default();
+    cgroup_cpu_limit_in(Path::new(CGROUP_ROOT), &cgroups)
+}
+
+/// The strictest CPU quota of the cgroup of this process and of all its ancestors, with the
+/// cgroup filesystem mounted at `root`: a quota set on a parent cgroup limits all its children,
+/// even if they don't have one.
+fn cgroup_cpu_limit_in(root: &Path, cgroups: &str) -> Option<f64> {
+    cgroup_dirs(root, cgroups)
+        .iter()
+        .flat_map(|dir| dir.ancestors().take_while(|dir| dir.starts_with(root)))
+        .filter_map(cpu_quota)
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+/// The CPU quota set in a single cgroup directory, either of cgroup v2 or v1.
+fn cpu_quota(dir: &Path) -> Option<f64> {
+    // cgroup v2
+    if let Ok(cpu_max) = std::fs::read_to_string(dir.join("cpu.max")) {
+        return parse_cpu_max(&cpu_max);
+    }
+    // cgroup v1
+    let quota = std::fs::read_to_string(dir.join("cpu.cfs_quota_us")).ok()?;
+    let period = std::fs::read_to_string(dir.join("cpu.cfs_period_us")).ok()?;
+    parse_cfs_quota(&quota, &period)
+}
+
+/// The directories where the CPU controller of the cgroup of this process may be, given the content
+/// of `/proc/self/cgroup`. When the cgroup namespace is used (e.g. in a container), the cgroup of
+/// the process is mounted directly at the root.
+fn cgroup_dirs(root: &Path, cgroups: &str) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for line in cgroups.lines() {
+        // each line is hierarchy-ID:controller-list:cgroup-path
+        let mut parts = line.splitn(3, ':');
+        let (controllers, path) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(_), Some(controllers), Some(path)) => (controllers, path),
+            _ => continue,
+        };
+        let path = path.trim_start_matches('/');
+        if controllers.is_empty() {
+            dirs.push(root.join(path));
+        } else if controllers.split(',').any(|c| c == "cpu") {
+            dirs.push(root.join(controllers).join(path));
+            dirs.push(root.join("cpu").join(path));
+        }
+    }
+    dirs.push(root.to_owned());
+    dirs.push(root.join("cpu"));
+    dirs
+}
+
+/// Parse the content of `cpu.max` of cgroup v2: `$MAX $PERIOD`, where `$MAX` can be `max`.
+fn parse_cpu_max(cpu_max: &str) -> Option<f64> {
+    let mut parts = cpu_max.split_whitespace();
+    let max = parts.next()?;
+    let period = parts.next().unwrap_or("100000");
+    if max == "max" {
+        return None;
+    }
+    parse_cfs_quota(max, period)
+}
+
+/// Parse the quota and the period of cgroup v1, a negative quota means no limit.
+fn parse_cfs_quota(quota: &str, period: &str) -> Option<f64> {
+    let quota: i64 = quota.trim().parse().ok()?;
+    let period: i64 = period.trim().parse().ok()?;
+    if quota <= 0 || period <= 0 {
+        return None;
+    }
+    Some(quota as f64 / period as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_cgroup_limits() {
+        assert_eq!(parse_cpu_max("max 100000\n"), None);
+        assert_eq!(parse_cpu_max("200000 100000\n"), Some(2.0));
+        assert_eq!(parse_cpu_max("150000 100000"), Some(1.5));
+        assert_eq!(parse_cfs_quota("-1\n", "100000\n"), None);
+        assert_eq!(parse_cfs_quota("50000\n", "100000\n"), Some(0.5));
+        assert_eq!(parse_cfs_quota("garbage", "100000"), None);
+    }
+
+    #[test]
+    fn test_cgroup_dirs() {
+        let root = Path::new(CGROUP_ROOT);
+        let dirs = cgroup_dirs(root, "0::/user.slice/session.scope\n");
+        assert_eq!(
+            dirs[0],
+            Path::new("/sys/fs/cgroup/user.slice/session.scope")
+        );
+        let dirs = cgroup_dirs(root, "4:cpu,cpuacct:/docker/abc\n3:memory:/docker/abc\n");
+        assert_eq!(dirs[0], Path::new("/sys/fs/cgroup/cpu,cpuacct/docker/abc"));
+        assert_eq!(dirs[1], Path::new("/sys/fs/cgroup/cpu/docker/abc"));
+        assert_eq!(dirs.len(), 4);
+    }
+
+    /// Write `content` in the file `name` of the cgroup at `path` below `root`.
+    fn write_cgroup(root: &Path, path: &str, name: &str, content: &str) {
+        let dir = root.join(path);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(name), content).unwrap();
+    }
+
+    #[test]
+    fn test_cgroup_limit_of_parent() {
+        let tmpdir = TempDir::new().unwrap();
+        let root = tmpdir.path();
+        write_cgroup(root, "", "cpu.max", "max 100000\n");
+        write_cgroup(root, "limited", "cpu.max", "150000 100000\n");
+        write_cgroup(root, "limited/job", "cpu.max", "max 100000\n");
+        write_cgroup(root, "limited/job/task", "cpu.max", "400000 100000\n");
+        assert_eq!(cgroup_cpu_limit_in(root, "0::/limited/job\n"), Some(1.5));
+        // the strictest quota wins, wherever it is in the hierarchy
+        assert_eq!(
+            cgroup_cpu_limit_in(root, "0::/limited/job/task\n"),
+            Some(1.5)
+        );
+        write_cgroup(root, "limited/job/task", "cpu.max", "50000 100000\n");
+        assert_eq!(
+            cgroup_cpu_limit_in(root, "0::/limited/job/task\n"),
+            Some(0.5)
+        );
+        assert_eq!(cgroup_cpu_limit_in(root, "0::/\n"), None);
+    }
+
+    #[test]
+    fn test_cgroup_v1_limit_of_parent() {
+        let tmpdir = TempDir::new().unwrap();
+        let root = tmpdir.path();
+        write_cgroup(root, "cpu/docker", "cpu.cfs_quota_us", "200000\n");
+        write_cgroup(root, "cpu/docker", "cpu.cfs_period_us", "100000\n");
+        write_cgroup(root, "cpu/docker/abc", "cpu.cfs_quota_us", "-1\n");
+        write_cgroup(root, "cpu/docker/abc", "cpu.cfs_period_us", "100000\n");
+        assert_eq!(
+            cgroup_cpu_limit_in(root, "4:cpu:/docker/abc\n3:memory:/docker/abc\n"),
+            Some(2.0)
+        );
+    }
+
+    #[test]
+    fn test_available_cores() {
+        let cores = available_cores();
+        assert!(cores >= 1);
+        assert!(cores <= std::thread::available_parallelism().unwrap().get());
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -11,17 +12,25 @@ use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
 use task_maker_cache::Cache;
-use task_maker_dag::{ExecutionGroup, ExecutionUuid, FileUuid, ProvidedFile, WorkerUuid};
+use task_maker_dag::{
+    ExecutionDAGData, ExecutionGroup, ExecutionUuid, FileUuid, ProvidedFile, WorkerUuid,
+};
 use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
 
-use crate::check_dag::check_dag;
+use crate::admin::{check_admin_password, AdminResponse};
+use crate::autoscale::{AutoscaleConfig, Autoscaler};
+use crate::check_dag::{check_dag, check_dag_extension};
+use crate::gc::GcConfig;
+use crate::persistent_queue::PersistentQueue;
 use crate::proto::{
     ChannelFileIterator, ChannelFileSender, ExecutorClientMessage, ExecutorServerMessage,
 };
+use crate::quota::{ClientQuotas, QuotaExceeded};
 use crate::scheduler::{
     ClientInfo, ClientUuid, Scheduler, SchedulerExecutorMessage, SchedulerExecutorMessageData,
     SchedulerInMessage,
 };
+use crate::timing_stability::current_timing_warnings;
 use crate::worker_manager::{WorkerManager, WorkerManagerInMessage};
 use crate::WorkerConn;
 
@@ -37,6 +46,10 @@ pub struct ExecutionDAGWatchSet {
     /// others will be sent at the end of the evaluation. Note that sending big files during the
     /// evaluation can cause performance degradations.
     pub urgent_files: HashSet<FileUuid>,
+    /// The number of extensions of the DAG the client will send while it's evaluated. The
+    /// evaluation is not completed until all of them are received.
+    #[serde(default)]
+    pub extensions: usize,
 }
 
 /// A job that is sent to a worker, this should include all the information the worker needs to
@@ -72,8 +85,45 @@ impl WorkerCurrentJobStatus<Duration> {
     }
 }
 
+/// Information about the machine a worker is running on, reported by the worker itself every time
+/// it asks for a new job.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, TypeScriptify)]
+pub struct WorkerTelemetry {
+    /// The load average of the machine in the last minute, if known.
+    pub load_average: Option<f64>,
+    /// The memory available for starting new processes, in KiB, if known.
+    pub free_memory: Option<u64>,
+    /// The conditions of the machine that make the measured times unreliable, like the CPU
+    /// frequency scaling or the thermal throttling.
+    #[serde(default)]
+    pub timing_warnings: Vec<String>,
+}
+
+impl WorkerTelemetry {
+    /// Measure the telemetry of the current machine. The values that cannot be measured are left
+    /// empty.
+    pub fn current() -> WorkerTelemetry {
+        let load_average = std::fs::read_to_string("/proc/loadavg")
+            .ok()
+            .and_then(|loadavg| loadavg.split_whitespace().next()?.parse().ok());
+        let free_memory = std::fs::read_to_string("/proc/meminfo")
+            .ok()
+            .and_then(|meminfo| {
+                meminfo
+                    .lines()
+                    .find_map(|line| line.strip_prefix("MemAvailable:"))
+                    .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+            });
+        WorkerTelemetry {
+            load_average,
+            free_memory,
+            timing_warnings: current_timing_warnings(load_average),
+        }
+    }
+}
+
 /// Status of a worker of an `Executor`.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TypeScriptify)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TypeScriptify)]
 pub struct ExecutorWorkerStatus<T> {
     /// UUID of the worker.
     pub uuid: WorkerUuid,
@@ -81,13 +131,15 @@ pub struct ExecutorWorkerStatus<T> {
     pub name: String,
     /// What the worker is currently working on.
     pub current_job: Option<WorkerCurrentJobStatus<T>>,
+    /// The last telemetry reported by the worker.
+    pub telemetry: WorkerTelemetry,
 }
 
 /// The current status of the `Executor`, this is sent to the user when the server status is asked.
 ///
 /// The type parameter `T` is either `SystemTime` for local usage or `Duration` for serialization.
 /// Unfortunately since `Instant` is not serializable by design, it cannot be used.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, TypeScriptify)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TypeScriptify)]
 pub struct ExecutorStatus<T> {
     /// List of the connected workers with their uuid, name and if they have some work.
     pub connected_workers: Vec<ExecutorWorkerStatus<T>>,
@@ -95,6 +147,25 @@ pub struct ExecutorStatus<T> {
     pub ready_execs: usize,
     /// Number of executions waiting for dependencies.
     pub waiting_execs: usize,
+    /// Number of executions found in the cache since the executor started, by all the clients.
+    ///
+    /// This is a global count and not a per-worker one: the cache is looked up by the server
+    /// before assigning a job, so the executions found in it never reach a worker.
+    pub cache_hits: usize,
+    /// Number of executions not found in the cache, and sent to the workers, since the executor
+    /// started. Like `cache_hits`, this is a global count.
+    pub cache_misses: usize,
+}
+
+/// The progress of the upload of a file from the client to the executor.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TypeScriptify)]
+pub struct FileTransfer {
+    /// The description of the file.
+    pub file: String,
+    /// Number of bytes the executor has received so far.
+    pub sent: u64,
+    /// The size of the file, in bytes.
+    pub total: u64,
 }
 
 /// Message telling the executor that a new client connected or a new worker connected. The handling
@@ -131,17 +202,34 @@ pub(crate) struct Executor {
     /// The receiver of the messages for the `Executor`. The actual `LocalExecutor`/`RemoteExecutor`
     /// use this channel for the communication.
     receiver: Receiver<ExecutorInMessage>,
-    /// Whether this executor is running for more than a single client (aka not locally). When this
-    /// flag is set to false, after the first client is done the Scheduler, the WorkerManager and
-    /// this Executor will exit.
+    /// Whether this executor is running for the clients connecting over time (aka not locally).
+    /// When this flag is set to false, after the clients connected before closing the channel are
+    /// done the Scheduler, the WorkerManager and this Executor will exit.
     long_running: bool,
+    /// The configuration of the autoscaling hook of the scheduler, if any.
+    autoscale: Option<AutoscaleConfig>,
+    /// The directory where the scheduler persists the resumable evaluations, if any.
+    queue_dir: Option<PathBuf>,
+    /// The limits on the resources used by each client.
+    quotas: ClientQuotas,
+    /// The configuration of the garbage collection of the stores, if enabled.
+    gc: Option<GcConfig>,
+    /// The maximum number of batchable jobs sent to a worker in a single message.
+    batch_size: usize,
+    /// Whether the clients run on the same machine, so the local files they provide can be read
+    /// directly from their path.
+    read_local_files: bool,
+    /// Sends the files to the clients and to the workers, with the compression told to them.
+    file_sender: ChannelFileSender,
+    /// The password of the administrators, without it the admin requests are refused.
+    admin_password: Option<String>,
 }
 
 impl Executor {
     /// Create a new `Executor` using the specified `FileStore` for the Scheduler and WorkerManager,
     /// the receiver for communicating with this Executor and if it should be "long running".
-    /// When this flag is set to false, after the first client is done the Scheduler, the
-    /// WorkerManager and this Executor will exit.
+    /// When this flag is set to false, after the clients connected before closing the channel are
+    /// done the Scheduler, the WorkerManager and this Executor will exit.
     pub fn new(
         file_store: Arc<FileStore>,
         cache: Cache,
@@ -153,11 +241,66 @@ impl Executor {
             cache,
             receiver,
             long_running,
+            autoscale: None,
+            queue_dir: None,
+            quotas: ClientQuotas::default(),
+            gc: None,
+            batch_size: 1,
+            read_local_files: false,
+            file_sender: ChannelFileSender::default(),
+            admin_password: None,
         }
     }
 
+    /// Fire the autoscaling hook with this configuration when the ready queue of the scheduler
+    /// crosses the thresholds.
+    pub fn set_autoscale(&mut self, config: AutoscaleConfig) {
+        self.autoscale = Some(config);
+    }
+
+    /// Persist the resumable evaluations inside this directory, so that they are recovered after
+    /// a restart.
+    pub fn set_queue_dir(&mut self, queue_dir: PathBuf) {
+        self.queue_dir = Some(queue_dir);
+    }
+
+    /// Limit the resources used by each client with these quotas.
+    pub fn set_quotas(&mut self, quotas: ClientQuotas) {
+        self.quotas = quotas;
+    }
+
+    /// Periodically remove the files not referenced by the evaluations from the stores of the
+    /// server and of the workers.
+    pub fn set_gc(&mut self, config: GcConfig) {
+        self.gc = Some(config);
+    }
+
+    /// Send to the workers up to this number of short executions in a single message.
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size;
+    }
+
+    /// Add the local files provided by the clients to the store reading them from their path,
+    /// instead of receiving their content from the client. This is possible only when the clients
+    /// run on the same machine of the executor.
+    pub fn set_read_local_files(&mut self, read_local_files: bool) {
+        self.read_local_files = read_local_files;
+    }
+
+    /// Compress the files sent to the clients and to the workers. They should be told so when they
+    /// connect.
+    pub fn set_transfer_compression(&mut self, compression: bool) {
+        self.file_sender = ChannelFileSender::new(compression);
+    }
+
+    /// Accept the admin requests of the clients that send this password. Without a password the
+    /// admin requests are refused.
+    pub fn set_admin_password(&mut self, admin_password: Option<String>) {
+        self.admin_password = admin_password;
+    }
+
     /// Run the `Executor`, listening for client and worker connections. This will block until the
-    /// first client is done (if `long_running` is false) or until the scheduler is stopped.
+    /// clients are done (if `long_running` is false) or until the scheduler is stopped.
     pub fn run(self) -> Result<(), Error> {
         let (scheduler_tx, scheduler_rx) = channel();
         let (worker_manager_tx, worker_manager_rx) = channel();
@@ -165,15 +308,27 @@ impl Executor {
 
         let clients = Arc::new(Mutex::new(HashMap::new()));
 
-        let scheduler = Scheduler::new(
+        let mut scheduler = Scheduler::new(
             self.file_store.clone(),
             self.cache,
             scheduler_rx,
             sched_executor_tx,
             worker_manager_tx.clone(),
         );
+        if let Some(config) = self.autoscale {
+            scheduler.set_autoscaler(Autoscaler::new(config));
+        }
+        if let Some(queue_dir) = self.queue_dir {
+            scheduler.set_queue(PersistentQueue::new(queue_dir)?);
+        }
+        scheduler.set_quotas(self.quotas.clone());
+        if let Some(config) = self.gc {
+            scheduler.set_gc(config);
+        }
+        scheduler.set_batch_size(self.batch_size);
         let worker_manager = WorkerManager::new(
             self.file_store.clone(),
+            self.file_sender,
             scheduler_tx.clone(),
             worker_manager_tx.clone(),
             worker_manager_rx,
@@ -187,11 +342,15 @@ impl Executor {
             .spawn(move || worker_manager.run())
             .expect("Failed to spawn worker manager");
         let clients2 = clients.clone();
+        let file_sender = self.file_sender;
         let scheduler_binder_thread = thread::Builder::new()
             .name("Scheduler binder".to_string())
-            .spawn(move || Executor::handle_scheduler_messages(sched_executor_rx, clients2))
+            .spawn(move || {
+                Executor::handle_scheduler_messages(sched_executor_rx, clients2, file_sender)
+            })
             .expect("Failed to spawn scheduler binder");
 
+        let mut client_managers = vec![];
         while let Ok(message) = self.receiver.recv() {
             match message {
                 ExecutorInMessage::ClientConnected {
@@ -205,10 +364,13 @@ impl Executor {
                     }
                     let scheduler = scheduler_tx.clone();
                     let file_store = self.file_store.clone();
-                    let long_running = self.long_running;
+                    let max_transferred_bytes = self.quotas.max_transferred_bytes;
+                    let read_local_files = self.read_local_files;
+                    let file_sender = self.file_sender;
+                    let admin_password = self.admin_password.clone();
                     // handle the new client in a new thread called "Client Manager"
-                    // FIXME: this thread is leaked, maybe we can join it as well
-                    thread::Builder::new()
+                    // FIXME: in long running mode this thread is leaked, maybe we can join it too
+                    let client_manager = thread::Builder::new()
                         .name(format!(
                             "Client manager for {} ({})",
                             client.name, client.uuid
@@ -220,20 +382,18 @@ impl Executor {
                                 sender,
                                 receiver,
                                 scheduler.clone(),
+                                max_transferred_bytes,
+                                read_local_files,
+                                file_sender,
+                                admin_password,
                             )
                             .unwrap();
-                            // if not in long running mode, the first client should tear down the
-                            // executor. To do so it's just required to tell the scheduler to exit,
-                            // it will bring down the WorkerManager and all should exit.
-                            if !long_running {
-                                scheduler
-                                    .send(SchedulerInMessage::Exit)
-                                    .map_err(|e| anyhow!("Cannot stop the scheduler: {:?}", e))
-                                    .unwrap();
-                            }
                             Ok(())
                         })
                         .expect("Failed to spawn client manager");
+                    if !self.long_running {
+                        client_managers.push(client_manager);
+                    }
                 }
                 ExecutorInMessage::WorkerConnected { worker } => {
                     worker_manager_tx
@@ -244,6 +404,20 @@ impl Executor {
         }
         debug!("Executor no longer waits for clients/workers");
 
+        // if not in long running mode, the clients should tear down the executor when they are all
+        // done. To do so it's just required to tell the scheduler to exit, it will bring down the
+        // WorkerManager and all should exit.
+        if !self.long_running {
+            for client_manager in client_managers {
+                client_manager
+                    .join()
+                    .map_err(|e| anyhow!("Client manager panicked: {:?}", e))??;
+            }
+            scheduler_tx
+                .send(SchedulerInMessage::Exit)
+                .map_err(|e| anyhow!("Cannot stop the scheduler: {:?}", e))?;
+        }
+
         scheduler_thread
             .join()
             .map_err(|e| anyhow!("Scheduler thread panicked: {:?}", e))?
@@ -264,6 +438,7 @@ impl Executor {
     fn handle_scheduler_messages(
         receiver: Receiver<SchedulerExecutorMessage>,
         clients: Arc<Mutex<HashMap<ClientUuid, ChannelSender<ExecutorServerMessage>>>>,
+        file_sender: ChannelFileSender,
     ) -> Result<(), Error> {
         let mut ready_files: HashMap<ClientUuid, Vec<(FileUuid, FileStoreHandle, bool)>> =
             HashMap::new();
@@ -296,7 +471,7 @@ impl Executor {
                             client.send(ExecutorServerMessage::ProvideFile(file, successful))
                         {
                             warn!("Failed to send urgent file: {:?}", e);
-                        } else if let Err(e) = ChannelFileSender::send(handle.path(), client) {
+                        } else if let Err(e) = file_sender.send_handle(&handle, client) {
                             warn!("Failed to send urgent file content: {:?}", e);
                         }
                     } else {
@@ -310,6 +485,22 @@ impl Executor {
                 SchedulerExecutorMessageData::Status { status } => {
                     ExecutorServerMessage::Status(status)
                 }
+                SchedulerExecutorMessageData::Admin { response } => {
+                    ExecutorServerMessage::Admin(response)
+                }
+                SchedulerExecutorMessageData::EvaluationCancelled => ExecutorServerMessage::Error(
+                    "The evaluation has been cancelled by the administrator of the server".into(),
+                ),
+                SchedulerExecutorMessageData::QuotaExceeded(quota) => {
+                    ExecutorServerMessage::QuotaExceeded(quota)
+                }
+                SchedulerExecutorMessageData::Reattached {
+                    evaluation,
+                    recovered,
+                } => ExecutorServerMessage::Reattached {
+                    evaluation,
+                    recovered,
+                },
                 SchedulerExecutorMessageData::EvaluationDone => {
                     let files = ready_files
                         .remove(&client_uuid)
@@ -328,15 +519,26 @@ impl Executor {
         Ok(())
     }
 
-    /// Handle the messages from a client.
+    /// Handle the messages from a client. The connection is closed if the client sends more than
+    /// `max_transferred_bytes` bytes of files. With `read_local_files` the local files of the
+    /// client are read from their path, without asking the client.
+    #[allow(clippy::too_many_arguments)]
     fn handle_client_messages(
         file_store: Arc<FileStore>,
         client: ClientInfo,
         sender: ChannelSender<ExecutorServerMessage>,
         receiver: ChannelReceiver<ExecutorClientMessage>,
         scheduler: Sender<SchedulerInMessage>,
+        max_transferred_bytes: Option<u64>,
+        read_local_files: bool,
+        file_sender: ChannelFileSender,
+        admin_password: Option<String>,
     ) -> Result<(), Error> {
         let mut scheduler = Some(scheduler);
+        let mut transferred_bytes = 0u64;
+        // the files and the executions of the DAG of the client, for validating its extensions
+        let mut dag_files = HashSet::new();
+        let mut dag_execs = HashSet::new();
         while let Ok(message) = receiver.recv() {
             match message {
                 ExecutorClientMessage::Evaluate { dag, callbacks } => {
@@ -349,23 +551,13 @@ impl Executor {
                     } else {
                         trace!("DAG looks valid!");
                     }
-                    // for each file marked as provided check if a local copy is present, otherwise
-                    // ask the client to send it.
-                    let mut ready_files = Vec::new();
-                    for (uuid, file) in dag.provided_files.iter() {
-                        let key = match file {
-                            ProvidedFile::Content { key, .. } => key,
-                            ProvidedFile::LocalFile { key, .. } => key,
-                        };
-                        let handle = file_store.get(key);
-                        if let Some(handle) = handle {
-                            ready_files.push((*uuid, handle));
-                        } else {
-                            sender
-                                .send(ExecutorServerMessage::AskFile(*uuid))
-                                .with_context(|| format!("Failed to send AskFile({})", uuid))?;
-                        }
-                    }
+                    Executor::add_dag_items(&dag, &mut dag_files, &mut dag_execs);
+                    let ready_files = Executor::find_provided_files(
+                        &file_store,
+                        &dag,
+                        &sender,
+                        read_local_files,
+                    )?;
                     // tell the scheduler that a new DAG is ready to be executed.
                     scheduler
                         .as_ref()
@@ -390,15 +582,80 @@ impl Executor {
                             .context("Failed to send FileReady to the scheduler")?;
                     }
                 }
-                ExecutorClientMessage::ProvideFile(uuid, key) => {
-                    info!("Client provided file {}", uuid);
+                ExecutorClientMessage::Extend { mut dag, callbacks } => {
+                    // the files needed by many parts of the DAG may be provided again
+                    dag.provided_files
+                        .retain(|uuid, _| !dag_files.contains(uuid));
+                    if let Err(e) = check_dag_extension(&dag, &callbacks, &dag_files, &dag_execs) {
+                        warn!("Invalid DAG extension: {:?}", e);
+                        sender
+                            .send(ExecutorServerMessage::Error(e.to_string()))
+                            .context("Failed to send Error message to client")?;
+                        break;
+                    }
+                    Executor::add_dag_items(&dag, &mut dag_files, &mut dag_execs);
+                    let ready_files = Executor::find_provided_files(
+                        &file_store,
+                        &dag,
+                        &sender,
+                        read_local_files,
+                    )?;
+                    scheduler
+                        .as_ref()
+                        .ok_or_else(|| anyhow!("Stopped execution"))?
+                        .send(SchedulerInMessage::ExtendDAG {
+                            client: client.uuid,
+                            dag,
+                            callbacks,
+                        })
+                        .context("Failed to send ExtendDAG to the scheduler")?;
+                    for (uuid, handle) in ready_files.into_iter() {
+                        scheduler
+                            .as_ref()
+                            .ok_or_else(|| anyhow!("Stopped execution"))?
+                            .send(SchedulerInMessage::FileReady {
+                                client: client.uuid,
+                                uuid,
+                                handle,
+                            })
+                            .context("Failed to send FileReady to the scheduler")?;
+                    }
+                }
+                ExecutorClientMessage::ProvideFile(uuid, key, offset) => {
+                    info!("Client provided file {} from {}", uuid, offset);
                     // the client provided a file that was not present locally, store it and tell
-                    // the scheduler that it's now ready.
-                    let handle = file_store
-                        .store(&key, ChannelFileIterator::new(&receiver))
-                        .with_context(|| {
-                            format!("Failed to store client provided file {} ({})", uuid, key)
-                        })?;
+                    // the scheduler that it's now ready. If the connection drops, the received
+                    // part is kept for resuming the transfer later.
+                    let iterator = ChannelFileIterator::new(&receiver).fallible().map(|chunk| {
+                        let chunk = chunk?;
+                        transferred_bytes += chunk.len() as u64;
+                        match max_transferred_bytes {
+                            Some(limit) if transferred_bytes > limit => {
+                                Err(QuotaExceeded::TooManyBytes { limit }.into())
+                            }
+                            _ => Ok(chunk),
+                        }
+                    });
+                    let handle = match file_store.store_resumable(&key, offset, iterator) {
+                        Ok(handle) => handle,
+                        Err(e) => {
+                            if let Some(limit) = max_transferred_bytes {
+                                if transferred_bytes > limit {
+                                    warn!("Client '{}' sent too many bytes", client.name);
+                                    let quota = QuotaExceeded::TooManyBytes { limit };
+                                    sender
+                                        .send(ExecutorServerMessage::QuotaExceeded(quota))
+                                        .context("Failed to send QuotaExceeded to the client")?;
+                                    break;
+                                }
+                            }
+                            warn!(
+                                "Failed to store client provided file {} ({}): {:?}",
+                                uuid, key, e
+                            );
+                            break;
+                        }
+                    };
                     scheduler
                         .as_ref()
                         .ok_or_else(|| anyhow!("Stopped execution"))?
@@ -417,7 +674,7 @@ impl Executor {
                         sender
                             .send(ExecutorServerMessage::ProvideFile(uuid, success))
                             .context("Failed to send ProvideFile to the client")?;
-                        ChannelFileSender::send(handle.path(), &sender).with_context(|| {
+                        file_sender.send_handle(&handle, &sender).with_context(|| {
                             format!("Failed to send file {} to the client", handle)
                         })?;
                     } else {
@@ -438,6 +695,24 @@ impl Executor {
                         });
                     }
                 }
+                ExecutorClientMessage::Admin(request, password) => {
+                    info!("Client sent an admin request");
+                    if let Err(error) =
+                        check_admin_password(admin_password.as_deref(), password.as_deref())
+                    {
+                        warn!("Admin request of {} refused: {}", client.name, error);
+                        sender
+                            .send(ExecutorServerMessage::Admin(AdminResponse::Error(error)))
+                            .context("Failed to send Admin to the client")?;
+                    } else if let Some(scheduler) = scheduler.as_ref() {
+                        scheduler
+                            .send(SchedulerInMessage::Admin {
+                                client: client.uuid,
+                                request,
+                            })
+                            .context("Failed to send Admin to the scheduler")?;
+                    }
+                }
                 ExecutorClientMessage::Stop => {
                     info!("Client asking to stop");
                     if let Some(scheduler) = scheduler.take() {
@@ -450,13 +725,66 @@ impl Executor {
                 }
             }
         }
+        // the connection dropped without the client asking to stop: the scheduler may keep the
+        // evaluation running, waiting for the client to reattach.
         if let Some(scheduler) = scheduler.take() {
             scheduler
-                .send(SchedulerInMessage::ClientDisconnected {
+                .send(SchedulerInMessage::ClientDetached {
                     client: client.uuid,
                 })
-                .context("Failed to send ClientDisconnected to the scheduler")?;
+                .context("Failed to send ClientDetached to the scheduler")?;
         }
         Ok(())
     }
+
+    /// Add the files and the executions of a DAG sent by a client to the known ones.
+    fn add_dag_items(
+        dag: &ExecutionDAGData,
+        files: &mut HashSet<FileUuid>,
+        execs: &mut HashSet<ExecutionUuid>,
+    ) {
+        files.extend(dag.provided_files.keys());
+        for group in dag.execution_groups.values() {
+            for exec in &group.executions {
+                execs.insert(exec.uuid);
+                files.extend(exec.outputs());
+            }
+        }
+    }
+
+    /// For each file marked as provided check if a local copy is present, otherwise ask the client
+    /// to send it. The files already present are returned.
+    fn find_provided_files(
+        file_store: &FileStore,
+        dag: &ExecutionDAGData,
+        sender: &ChannelSender<ExecutorServerMessage>,
+        read_local_files: bool,
+    ) -> Result<Vec<(FileUuid, FileStoreHandle)>, Error> {
+        let mut ready_files = Vec::new();
+        for (uuid, file) in dag.provided_files.iter() {
+            let key = match file {
+                ProvidedFile::Content { key, .. } => key,
+                ProvidedFile::LocalFile { key, .. } => key,
+            };
+            let mut handle = file_store.get(key);
+            if let ProvidedFile::LocalFile { local_path, .. } = file {
+                if handle.is_none() && read_local_files {
+                    handle = file_store
+                        .store_file(key, local_path)
+                        .map_err(|e| warn!("Cannot read {}: {:?}", uuid, e))
+                        .ok();
+                }
+            }
+            if let Some(handle) = handle {
+                ready_files.push((*uuid, handle));
+            } else {
+                // resume the transfer if it was interrupted by a previous connection
+                let offset = file_store.partial_size(key);
+                sender
+                    .send(ExecutorServerMessage::AskFile(*uuid, offset))
+                    .with_context(|| format!("Failed to send AskFile({})", uuid))?;
+            }
+        }
+        Ok(ready_files)
+    }
 }
@@ -14,7 +14,7 @@ use crate::executor::{Executor, ExecutorInMessage};
 use crate::proto::{ExecutorClientMessage, ExecutorServerMessage};
 use crate::sandbox_runner::SandboxRunner;
 use crate::scheduler::ClientInfo;
-use crate::Worker;
+use crate::{SandboxPool, Worker};
 
 /// An Executor that runs locally by spawning a number of threads with the workers inside.
 pub struct LocalExecutor {
@@ -38,12 +38,45 @@ impl LocalExecutor {
         sandbox_path: P,
         sandbox_runner: R,
     ) -> Result<LocalExecutor, Error>
+    where
+        R: SandboxRunner + 'static,
+    {
+        LocalExecutor::new_with_sandbox_pool(
+            file_store,
+            cache,
+            num_workers,
+            sandbox_path,
+            0,
+            sandbox_runner,
+        )
+    }
+
+    /// Make a new [`LocalExecutor`] like [`LocalExecutor::new`], but with the workers sharing a
+    /// pool of `sandbox_pool_size` pre-created sandbox directories. With a size of zero the pool
+    /// is disabled.
+    pub fn new_with_sandbox_pool<P: Into<PathBuf>, R>(
+        file_store: Arc<FileStore>,
+        cache: Cache,
+        num_workers: usize,
+        sandbox_path: P,
+        sandbox_pool_size: usize,
+        sandbox_runner: R,
+    ) -> Result<LocalExecutor, Error>
     where
         R: SandboxRunner + 'static,
     {
         let sandbox_path = sandbox_path.into();
+        let sandbox_pool = if sandbox_pool_size > 0 {
+            let pool = SandboxPool::new(&sandbox_path, sandbox_pool_size)
+                .context("Failed to create the sandbox pool")?;
+            Some(Arc::new(pool))
+        } else {
+            None
+        };
         let (executor_tx, executor_rx) = channel();
-        let executor = Executor::new(file_store.clone(), cache, executor_rx, false);
+        let mut executor = Executor::new(file_store.clone(), cache, executor_rx, false);
+        // the client is in the same process, its files are stored without sending them around
+        executor.set_read_local_files(true);
 
         // share the runner for all the workers
         let sandbox_runner = Arc::new(sandbox_runner);
@@ -53,7 +86,7 @@ impl LocalExecutor {
         // spawn the workers and connect them to the executor
         for i in 0..num_workers {
             let runner = sandbox_runner.clone();
-            let (worker, conn) = Worker::new(
+            let (mut worker, conn) = Worker::new(
                 format!("Local worker {}", i),
                 file_store.clone(),
                 #[allow(clippy::needless_borrow)]
@@ -61,6 +94,9 @@ impl LocalExecutor {
                 runner,
             )
             .context("Failed to start local worker")?;
+            if let Some(pool) = &sandbox_pool {
+                worker.set_sandbox_pool(pool.clone());
+            }
             executor_tx
                 .send(ExecutorInMessage::WorkerConnected { worker: conn })
                 .map_err(|e| anyhow!("Failed to send WorkerConnected: {:?}", e))?;
@@ -93,18 +129,38 @@ impl LocalExecutor {
         sender: ChannelSender<ExecutorServerMessage>,
         receiver: ChannelReceiver<ExecutorClientMessage>,
     ) -> Result<(), Error> {
-        // tell the executor that it has a new (local) client. Since the executor is not in
-        // long_running mode, after this client is done the executor will exit.
-        self.executor_tx
-            .send(ExecutorInMessage::ClientConnected {
-                client: ClientInfo {
-                    uuid: Uuid::new_v4(),
-                    name: "Local client".to_string(),
-                },
-                sender,
-                receiver,
-            })
-            .map_err(|e| anyhow!("Failed to send ClientConnected: {:?}", e))?;
+        self.evaluate_clients(vec![(sender, receiver)])
+    }
+
+    /// Like [`LocalExecutor::evaluate`], but serving more clients at the same time, sharing the
+    /// workers between them. This blocks until all the clients are done.
+    pub fn evaluate_clients(
+        self,
+        clients: Vec<(
+            ChannelSender<ExecutorServerMessage>,
+            ChannelReceiver<ExecutorClientMessage>,
+        )>,
+    ) -> Result<(), Error> {
+        // tell the executor that it has new (local) clients. Since the executor is not in
+        // long_running mode, after these clients are done the executor will exit.
+        let num_clients = clients.len();
+        for (i, (sender, receiver)) in clients.into_iter().enumerate() {
+            let name = if num_clients == 1 {
+                "Local client".to_string()
+            } else {
+                format!("Local client {}", i)
+            };
+            self.executor_tx
+                .send(ExecutorInMessage::ClientConnected {
+                    client: ClientInfo {
+                        uuid: Uuid::new_v4(),
+                        name,
+                    },
+                    sender,
+                    receiver,
+                })
+                .map_err(|e| anyhow!("Failed to send ClientConnected: {:?}", e))?;
+        }
 
         // no new client/worker can connect, make the executor stop accepting connections
         drop(self.executor_tx);
@@ -1,3 +1,7 @@
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Sender};
 use std::sync::Arc;
 
@@ -8,7 +12,10 @@ use uuid::Uuid;
 use task_maker_cache::Cache;
 use task_maker_store::FileStore;
 
+use crate::autoscale::AutoscaleConfig;
 use crate::executor::{Executor, ExecutorInMessage};
+use crate::gc::GcConfig;
+use crate::quota::ClientQuotas;
 use crate::scheduler::ClientInfo;
 use crate::{derive_key_from_password, WorkerConn};
 use anyhow::{anyhow, Context, Error};
@@ -19,6 +26,13 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 /// An executor that accepts remote connections from clients and workers.
 pub struct RemoteExecutor {
     file_store: Arc<FileStore>,
+    autoscale: Option<AutoscaleConfig>,
+    queue_dir: Option<PathBuf>,
+    quotas: ClientQuotas,
+    gc: Option<GcConfig>,
+    batch_size: usize,
+    transfer_compression: bool,
+    admin_password: Option<String>,
 }
 
 /// Message sent only by remote clients and workers for connecting to the server.
@@ -37,7 +51,11 @@ pub enum RemoteEntityMessage {
 #[derive(Debug, Serialize, Deserialize)]
 pub enum RemoteEntityMessageResponse {
     /// The server accepted the connection of the client, the communication can continue.
-    Accepted,
+    Accepted {
+        /// Whether the files sent over this connection should be compressed. The server has it
+        /// disabled when running on a fast network, where compressing is just a waste of CPU.
+        compression: bool,
+    },
     /// The server rejected the connection of the client, the channel will be closed.
     Rejected(String),
 }
@@ -45,7 +63,57 @@ pub enum RemoteEntityMessageResponse {
 impl RemoteExecutor {
     /// Make a new `RemoteExecutor`.
     pub fn new(file_store: Arc<FileStore>) -> Self {
-        RemoteExecutor { file_store }
+        RemoteExecutor {
+            file_store,
+            autoscale: None,
+            queue_dir: None,
+            quotas: ClientQuotas::default(),
+            gc: None,
+            batch_size: 1,
+            transfer_compression: false,
+            admin_password: None,
+        }
+    }
+
+    /// Run the autoscaling hook with this configuration when the ready queue crosses its
+    /// thresholds.
+    pub fn set_autoscale(&mut self, config: AutoscaleConfig) {
+        self.autoscale = Some(config);
+    }
+
+    /// Persist the resumable evaluations inside this directory. When the server restarts, they are
+    /// recovered and their clients can reattach to them.
+    pub fn set_queue_dir<P: Into<PathBuf>>(&mut self, queue_dir: P) {
+        self.queue_dir = Some(queue_dir.into());
+    }
+
+    /// Limit the resources that each client can use on the server.
+    pub fn set_quotas(&mut self, quotas: ClientQuotas) {
+        self.quotas = quotas;
+    }
+
+    /// Periodically remove the files not referenced by the evaluations from the stores of the
+    /// server and of the workers.
+    pub fn set_gc(&mut self, config: GcConfig) {
+        self.gc = Some(config);
+    }
+
+    /// Send to the workers up to this number of short executions, like the checkers and the
+    /// validators, in a single message, cutting the latency between them.
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size;
+    }
+
+    /// Compress the files sent over the connections with the clients and the workers, they are
+    /// told to do the same when they connect.
+    pub fn set_transfer_compression(&mut self, compression: bool) {
+        self.transfer_compression = compression;
+    }
+
+    /// Accept the admin requests that carry this password. Without a password the server cannot be
+    /// administered.
+    pub fn set_admin_password(&mut self, admin_password: Option<String>) {
+        self.admin_password = admin_password;
     }
 
     /// Start the executor binding the TCP sockets and waiting for clients and workers connections.
@@ -62,18 +130,39 @@ impl RemoteExecutor {
         let bind_worker_addr = bind_worker_addr.into();
 
         let (executor_tx, executor_rx) = channel();
-        let executor = Executor::new(file_store, cache, executor_rx, true);
+        let mut executor = Executor::new(file_store, cache, executor_rx, true);
+        if let Some(config) = self.autoscale {
+            executor.set_autoscale(config);
+        }
+        if let Some(queue_dir) = self.queue_dir {
+            executor.set_queue_dir(queue_dir);
+        }
+        executor.set_quotas(self.quotas);
+        if let Some(config) = self.gc {
+            executor.set_gc(config);
+        }
+        executor.set_batch_size(self.batch_size);
+        executor.set_transfer_compression(self.transfer_compression);
+        executor.set_admin_password(self.admin_password);
+        let compression = self.transfer_compression;
 
         let client_executor_tx = executor_tx.clone();
         let client_listener_thread = std::thread::Builder::new()
             .name("Client listener".to_string())
             .spawn(move || {
-                Self::client_listener(client_password, bind_client_addr, client_executor_tx)
+                Self::client_listener(
+                    client_password,
+                    bind_client_addr,
+                    client_executor_tx,
+                    compression,
+                )
             })
             .context("Cannot spawn client listener thread")?;
         let worker_listener_thread = std::thread::Builder::new()
             .name("Worker listener".to_string())
-            .spawn(move || Self::worker_listener(worker_password, bind_worker_addr, executor_tx))
+            .spawn(move || {
+                Self::worker_listener(worker_password, bind_worker_addr, executor_tx, compression)
+            })
             .context("Cannot spawn worker listener thread")?;
 
         executor.run()?;
@@ -93,21 +182,10 @@ impl RemoteExecutor {
         client_password: Option<String>,
         bind_client_addr: String,
         client_executor_tx: Sender<ExecutorInMessage>,
+        compression: bool,
     ) -> Result<(), Error> {
-        let server = if let Some(path) = bind_client_addr.strip_prefix("unix://") {
-            ChannelServer::bind_unix(path)
-                .with_context(|| format!("Failed to bind client unix socket at {}", path))?
-        } else {
-            match client_password {
-                Some(password) => {
-                    let key = derive_key_from_password(password);
-                    ChannelServer::bind_with_enc(&bind_client_addr, key)
-                        .context("Failed to bind client address")?
-                }
-                None => ChannelServer::bind(&bind_client_addr)
-                    .context("Failed to bind client address")?,
-            }
-        };
+        let server = bind_channel_server(&bind_client_addr, client_password)
+            .context("Failed to bind client address")?;
 
         let local_addr = server
             .local_addr()
@@ -127,7 +205,7 @@ impl RemoteExecutor {
             info!("Client connected from {}", addr);
             let uuid = Uuid::new_v4();
             let name = if let Ok(RemoteEntityMessage::Welcome { name, version }) = receiver.recv() {
-                if !validate_welcome(&addr, &name, version, &sender, "Client") {
+                if !validate_welcome(&addr, &name, version, &sender, "Client", compression) {
                     continue;
                 }
                 name
@@ -154,21 +232,10 @@ impl RemoteExecutor {
         worker_password: Option<String>,
         bind_worker_addr: String,
         executor_tx: Sender<ExecutorInMessage>,
+        compression: bool,
     ) -> Result<(), Error> {
-        let server = if let Some(path) = bind_worker_addr.strip_prefix("unix://") {
-            ChannelServer::bind_unix(path)
-                .with_context(|| format!("Failed to bind worker unix socket at {}", path))?
-        } else {
-            match worker_password {
-                Some(password) => {
-                    let key = derive_key_from_password(password);
-                    ChannelServer::bind_with_enc(&bind_worker_addr, key)
-                        .context("Failed to bind worker address")?
-                }
-                None => ChannelServer::bind(&bind_worker_addr)
-                    .context("Failed to bind worker address")?,
-            }
-        };
+        let server = bind_channel_server(&bind_worker_addr, worker_password)
+            .context("Failed to bind worker address")?;
         let local_addr = server
             .local_addr()
             .context("Failed to get worker address")?;
@@ -187,7 +254,7 @@ impl RemoteExecutor {
             info!("Worker connected from {}", addr);
             let uuid = Uuid::new_v4();
             let name = if let Ok(RemoteEntityMessage::Welcome { name, version }) = receiver.recv() {
-                if !validate_welcome(&addr, &name, version, &sender, "Worker") {
+                if !validate_welcome(&addr, &name, version, &sender, "Worker", compression) {
                     continue;
                 }
                 name
@@ -212,12 +279,64 @@ impl RemoteExecutor {
     }
 }
 
+/// Bind a `ChannelServer` at the specified address. The address is either `unix://path` for a Unix
+/// domain socket, or an IPv4 or IPv6 socket address (e.g. `0.0.0.0:27182` or `[::]:27182`),
+/// optionally prefixed by `tcp://`. The connections over a Unix socket are not encrypted: the access
+/// to them is controlled by the permissions of the socket file.
+fn bind_channel_server<S, R>(
+    addr: &str,
+    password: Option<String>,
+) -> Result<ChannelServer<S, R>, Error> {
+    if let Some(path) = addr.strip_prefix("unix://") {
+        if password.is_some() {
+            warn!("The password is ignored for the Unix socket at {}", path);
+        }
+        remove_stale_socket(Path::new(path))?;
+        return ChannelServer::bind_unix(path)
+            .with_context(|| format!("Failed to bind Unix socket at {}", path));
+    }
+    let addr = addr.strip_prefix("tcp://").unwrap_or(addr);
+    let socket_addrs: Vec<SocketAddr> = addr
+        .to_socket_addrs()
+        .with_context(|| format!("Invalid address to bind: {}", addr))?
+        .collect();
+    match password {
+        Some(password) => {
+            let key = derive_key_from_password(password);
+            ChannelServer::bind_with_enc(&socket_addrs[..], key)
+        }
+        None => ChannelServer::bind(&socket_addrs[..]),
+    }
+    .with_context(|| format!("Failed to bind {}", addr))
+}
+
+/// Remove the Unix socket left at `path` by a server that is not running anymore, so that it can be
+/// bound again. Sockets with a server still listening, and any other kind of file, are left there.
+fn remove_stale_socket(path: &Path) -> Result<(), Error> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return Ok(()),
+    };
+    if !metadata.file_type().is_socket() {
+        return Ok(());
+    }
+    match UnixStream::connect(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            info!("Removing stale Unix socket at {}", path.display());
+            std::fs::remove_file(path)
+                .with_context(|| format!("Failed to remove stale socket {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
 fn validate_welcome(
     addr: &str,
     name: &str,
     version: String,
     sender: &ChannelSender<RemoteEntityMessageResponse>,
     client: &str,
+    compression: bool,
 ) -> bool {
     if version != VERSION {
         warn!(
@@ -230,7 +349,35 @@ fn validate_welcome(
         )));
         false
     } else {
-        let _ = sender.send(RemoteEntityMessageResponse::Accepted);
+        let _ = sender.send(RemoteEntityMessageResponse::Accepted { compression });
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixListener;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_remove_stale_socket() {
+        let tmpdir = TempDir::new().unwrap();
+        let stale = tmpdir.path().join("stale.sock");
+        drop(UnixListener::bind(&stale).unwrap());
+        let alive = tmpdir.path().join("alive.sock");
+        let _listener = UnixListener::bind(&alive).unwrap();
+        let file = tmpdir.path().join("file");
+        std::fs::write(&file, "not a socket").unwrap();
+
+        remove_stale_socket(&stale).unwrap();
+        remove_stale_socket(&alive).unwrap();
+        remove_stale_socket(&file).unwrap();
+        remove_stale_socket(&tmpdir.path().join("missing")).unwrap();
+        assert!(!stale.exists());
+        assert!(alive.exists());
+        assert!(file.exists());
+    }
+}
@@ -0,0 +1,18 @@
+//! Coordinated garbage collection of the files stored by the server and by the workers.
+//!
+//! When an evaluation ends, and enough time passed since the last round, the server computes the
+//! set of files referenced by the evaluations it's still working on. It removes from its own store
+//! the least recently used files outside this set, and then asks each worker to do the same with
+//! its store the next time the worker is idle. Each node shrinks its store down to its own
+//! retention size.
+
+use std::time::Duration;
+
+/// The configuration of the garbage collection of the server.
+#[derive(Debug, Clone)]
+pub struct GcConfig {
+    /// The minimum time between two rounds of garbage collection.
+    pub interval: Duration,
+    /// The size, in bytes, the store of the server is shrunk to.
+    pub retention_size: u64,
+}
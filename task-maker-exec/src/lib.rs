@@ -81,9 +81,13 @@ use anyhow::Error;
 pub use ductile;
 use ductile::new_local_channel;
 
-pub use client::ExecutorClient;
-pub use executor::{ExecutorStatus, ExecutorWorkerStatus, WorkerCurrentJobStatus};
+pub use client::{ClientUpdate, ExecutorClient};
+pub use cpu_quota::available_cores;
+pub use executor::{
+    ExecutorStatus, ExecutorWorkerStatus, FileTransfer, WorkerCurrentJobStatus, WorkerTelemetry,
+};
 pub use sandbox::RawSandboxResult;
+pub use sandbox_pool::SandboxPool;
 pub use sandbox_runner::{ErrorSandboxRunner, SandboxRunner, SuccessSandboxRunner};
 pub use scheduler::ClientInfo;
 use task_maker_cache::Cache;
@@ -91,16 +95,30 @@ use task_maker_dag::ExecutionDAG;
 use task_maker_store::FileStore;
 pub use worker::{Worker, WorkerConn};
 
+pub mod admin;
+pub mod autoscale;
+pub mod benchmark;
+mod builtin;
 mod check_dag;
 mod client;
+pub mod cpu_pinning;
+mod cpu_quota;
 mod detect_exe;
 mod executor;
 pub mod executors;
 pub mod find_tools;
+pub mod gc;
+mod persistent_queue;
+pub mod plan;
+pub mod process_priority;
 pub mod proto;
+pub mod quota;
 pub mod sandbox;
+mod sandbox_pool;
 mod sandbox_runner;
 mod scheduler;
+mod timing_stability;
+mod toolchain;
 mod worker;
 mod worker_manager;
 
@@ -150,7 +168,7 @@ pub fn eval_dag_locally<P: Into<PathBuf>, P2: Into<PathBuf>, R>(
                 .expect("Executor failed");
         })
         .expect("Failed to spawn local executor thread");
-    ExecutorClient::evaluate(dag, tx, &rx, file_store, |_| Ok(())).expect("Client failed");
+    ExecutorClient::evaluate(dag, tx, &rx, file_store, false, |_| Ok(())).expect("Client failed");
     server.join().expect("Server panicked");
 }
 
@@ -0,0 +1,145 @@
+//! Persist the evaluations accepted by the server, so that they survive a restart of the server.
+//!
+//! Only the evaluations that can be resumed (i.e. the ones with an evaluation identifier) are
+//! persisted. After a restart the scheduler recovers them as detached evaluations, dispatching
+//! their executions as soon as the workers reconnect; the clients can then reattach to them with
+//! `--resume`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use serde::{Deserialize, Serialize};
+
+use task_maker_dag::{EvaluationUuid, ExecutionDAGData};
+
+use crate::executor::ExecutionDAGWatchSet;
+use crate::scheduler::ClientInfo;
+
+/// Extension of the files with the persisted evaluations.
+const QUEUE_FILE_EXTENSION: &str = "bin";
+
+/// An evaluation accepted by the server, as stored on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct QueuedEvaluation {
+    /// The client that sent the evaluation.
+    pub client: ClientInfo,
+    /// The DAG to evaluate.
+    pub dag: ExecutionDAGData,
+    /// The set of callbacks the client is interested in.
+    pub callbacks: ExecutionDAGWatchSet,
+}
+
+/// The directory where the queued evaluations are stored, one file per evaluation.
+#[derive(Debug)]
+pub(crate) struct PersistentQueue {
+    /// The directory with the evaluations.
+    dir: PathBuf,
+}
+
+impl PersistentQueue {
+    /// Make a new `PersistentQueue` storing the evaluations inside the specified directory.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Result<PersistentQueue, Error> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create queue directory {}", dir.display()))?;
+        Ok(PersistentQueue { dir })
+    }
+
+    /// Store an evaluation on disk, replacing the previous one with the same identifier.
+    pub fn save(
+        &self,
+        evaluation_id: EvaluationUuid,
+        evaluation: &QueuedEvaluation,
+    ) -> Result<(), Error> {
+        let data = bincode::serialize(evaluation).context("Failed to serialize the evaluation")?;
+        let path = self.path(evaluation_id);
+        // write to a temporary file first, so a crash does not leave a truncated evaluation
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, data).with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to rename {} -> {}", tmp.display(), path.display()))
+    }
+
+    /// Remove an evaluation from the disk.
+    pub fn remove(&self, evaluation_id: EvaluationUuid) {
+        let path = self.path(evaluation_id);
+        if let Err(e) = std::fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to remove {}: {:?}", path.display(), e);
+            }
+        }
+    }
+
+    /// Load all the evaluations stored on disk. The files that cannot be loaded are removed.
+    pub fn load(&self) -> Result<Vec<QueuedEvaluation>, Error> {
+        let mut evaluations = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to list {}", self.dir.display()))?
+        {
+            let path = entry.context("Failed to read directory entry")?.path();
+            if path.extension() != Some(QUEUE_FILE_EXTENSION.as_ref()) {
+                continue;
+            }
+            match load_evaluation(&path) {
+                Ok(evaluation) => evaluations.push(evaluation),
+                Err(e) => {
+                    warn!(
+                        "Discarding queued evaluation at {}: {:?}",
+                        path.display(),
+                        e
+                    );
+                    let _ = std::fs::remove_file(&path);
+                }
+            }
+        }
+        Ok(evaluations)
+    }
+
+    /// The path of the file with the evaluation.
+    fn path(&self, evaluation_id: EvaluationUuid) -> PathBuf {
+        self.dir
+            .join(evaluation_id.to_string())
+            .with_extension(QUEUE_FILE_EXTENSION)
+    }
+}
+
+/// Read and deserialize an evaluation from a file.
+fn load_evaluation(path: &Path) -> Result<QueuedEvaluation, Error> {
+    let data = std::fs::read(path).context("Failed to read the file")?;
+    bincode::deserialize(&data).context("Failed to deserialize the evaluation")
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    use task_maker_dag::ExecutionDAG;
+
+    use super::*;
+
+    #[test]
+    fn test_save_load_remove() {
+        let tmpdir = TempDir::new().unwrap();
+        let queue = PersistentQueue::new(tmpdir.path().join("queue")).unwrap();
+        let evaluation_id = Uuid::new_v4();
+        let evaluation = QueuedEvaluation {
+            client: ClientInfo {
+                uuid: Uuid::new_v4(),
+                name: "client".into(),
+            },
+            dag: ExecutionDAG::new().data,
+            callbacks: Default::default(),
+        };
+        queue.save(evaluation_id, &evaluation).unwrap();
+        std::fs::write(tmpdir.path().join("queue/broken.bin"), "nope").unwrap();
+
+        let loaded = queue.load().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].client, evaluation.client);
+        assert!(!tmpdir.path().join("queue/broken.bin").exists());
+
+        queue.remove(evaluation_id);
+        assert!(queue.load().unwrap().is_empty());
+    }
+}
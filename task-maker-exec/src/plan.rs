@@ -0,0 +1,204 @@
+//! Planning of the evaluation of a DAG without running anything: which executions would be run and
+//! which would come from the cache.
+//!
+//! An execution can be looked up in the cache only when all its inputs are known, so the DAG is
+//! visited in topological order. The outputs of the cache hits are known, while everything that
+//! depends on an execution that would run has to run as well.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::{Context, Error};
+
+use task_maker_cache::{Cache, CacheResult};
+use task_maker_dag::{
+    ExecutionDAGData, ExecutionGroup, ExecutionGroupUuid, FileUuid, ProvidedFile,
+};
+use task_maker_store::{FileStore, FileStoreHandle, ReadFileIterator};
+
+use crate::scheduler::Scheduler;
+
+/// What would happen evaluating a DAG.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionPlan {
+    /// The groups that would be executed, in an order compatible with their dependencies.
+    pub to_run: Vec<ExecutionGroup>,
+    /// The groups whose results would come from the cache.
+    pub cached: Vec<ExecutionGroup>,
+}
+
+/// Compute which executions of the DAG would run and which would come from the cache. The files
+/// provided by the client are added to the store, like an actual evaluation would do.
+pub fn plan_dag(
+    dag: &ExecutionDAGData,
+    cache: &mut Cache,
+    file_store: &FileStore,
+) -> Result<ExecutionPlan, Error> {
+    // the files whose content is known, and the ones produced by executions that would run
+    let mut known: HashMap<FileUuid, FileStoreHandle> = HashMap::new();
+    let mut unknown: HashSet<FileUuid> = HashSet::new();
+    for (uuid, file) in dag.provided_files.iter() {
+        let handle = match file {
+            ProvidedFile::LocalFile {
+                key, local_path, ..
+            } => {
+                let iterator = ReadFileIterator::new(local_path)
+                    .with_context(|| format!("Failed to read {}", local_path.display()))?;
+                file_store.store(key, iterator)
+            }
+            ProvidedFile::Content { key, content, .. } => {
+                file_store.store(key, vec![content.clone()])
+            }
+        }
+        .context("Failed to store a provided file")?;
+        known.insert(*uuid, handle);
+    }
+
+    let dependencies = |group: &ExecutionGroup| -> Vec<FileUuid> {
+        let outputs: HashSet<_> = group.executions.iter().flat_map(|e| e.outputs()).collect();
+        group
+            .executions
+            .iter()
+            .flat_map(|e| e.dependencies())
+            .filter(|file| !outputs.contains(file))
+            .collect()
+    };
+    // the groups that are waiting for each file
+    let mut waiting: HashMap<FileUuid, Vec<ExecutionGroupUuid>> = HashMap::new();
+    let mut missing: HashMap<ExecutionGroupUuid, usize> = HashMap::new();
+    let mut ready = VecDeque::new();
+    for (uuid, group) in dag.execution_groups.iter() {
+        let deps: HashSet<_> = dependencies(group)
+            .into_iter()
+            .filter(|file| !known.contains_key(file))
+            .collect();
+        if deps.is_empty() {
+            ready.push_back(*uuid);
+        }
+        for file in &deps {
+            waiting.entry(*file).or_default().push(*uuid);
+        }
+        missing.insert(*uuid, deps.len());
+    }
+
+    let mut plan = ExecutionPlan::default();
+    while let Some(uuid) = ready.pop_front() {
+        let group = &dag.execution_groups[&uuid];
+        let outputs: Vec<_> = group.executions.iter().flat_map(|e| e.outputs()).collect();
+        let depends_on_run = dependencies(group)
+            .iter()
+            .any(|file| unknown.contains(file));
+        let cacheable = Scheduler::is_cacheable(group, &dag.config.cache_mode);
+        let result = if depends_on_run || !cacheable {
+            CacheResult::Miss
+        } else {
+            cache.peek(group, &known, file_store)
+        };
+        match result {
+            // the failures excluded from the cache are always executed again
+            CacheResult::Hit { result, .. }
+                if !result.iter().all(|r| r.status.is_success())
+                    && !dag
+                        .config
+                        .failure_cache_mode
+                        .is_enabled(group.tag().as_ref()) =>
+            {
+                unknown.extend(outputs.iter().copied());
+                plan.to_run.push(group.clone());
+            }
+            CacheResult::Hit {
+                outputs: handles, ..
+            } => {
+                known.extend(handles);
+                plan.cached.push(group.clone());
+            }
+            CacheResult::Miss => {
+                unknown.extend(outputs.iter().copied());
+                plan.to_run.push(group.clone());
+            }
+        }
+        for file in outputs {
+            for waiting in waiting.remove(&file).unwrap_or_default() {
+                let missing = missing.get_mut(&waiting).expect("Unknown group");
+                *missing -= 1;
+                if *missing == 0 {
+                    ready.push_back(waiting);
+                }
+            }
+        }
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use task_maker_dag::{
+        Execution, ExecutionCommand, ExecutionDAG, ExecutionResult, ExecutionStatus, File,
+    };
+    use task_maker_store::FileStoreKey;
+
+    fn descriptions(groups: &[ExecutionGroup]) -> Vec<&str> {
+        groups.iter().map(|g| g.description.as_str()).collect()
+    }
+
+    #[test]
+    fn test_plan_dag() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path().join("store"), 1000, 1000).unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+
+        let mut dag = ExecutionDAG::new();
+        let input = File::new("input");
+        let mut first = Execution::new("first", ExecutionCommand::local("foo"));
+        first.stdin(&input);
+        let output = first.stdout();
+        let mut second = Execution::new("second", ExecutionCommand::local("bar"));
+        second.stdin(&output);
+        dag.provide_content(input.clone(), b"hello".to_vec());
+        dag.add_execution(first);
+        dag.add_execution(second);
+
+        // nothing is in the cache
+        let plan = plan_dag(&dag.data, &mut cache, &store).unwrap();
+        assert_eq!(descriptions(&plan.to_run), vec!["first", "second"]);
+        assert!(plan.cached.is_empty());
+
+        // the first execution is cached, so the second one is looked up with its output
+        let group = dag
+            .data
+            .execution_groups
+            .values()
+            .find(|group| group.description == "first")
+            .unwrap();
+        let content = b"world".to_vec();
+        let output_key = FileStoreKey::from_content(&content);
+        let mut handles = HashMap::new();
+        handles.insert(
+            input.uuid,
+            store
+                .get(&FileStoreKey::from_content(b"hello"))
+                .expect("The provided file is not in the store"),
+        );
+        handles.insert(
+            output.uuid,
+            store.store(&output_key, vec![content]).unwrap(),
+        );
+        let result = ExecutionResult {
+            status: ExecutionStatus::Success,
+            was_killed: false,
+            was_cached: false,
+            resources: Default::default(),
+            stdout: None,
+            stderr: None,
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
+        };
+        cache.insert(group, &handles, vec![result]);
+        let plan = plan_dag(&dag.data, &mut cache, &store).unwrap();
+        assert_eq!(descriptions(&plan.cached), vec!["first"]);
+        assert_eq!(descriptions(&plan.to_run), vec!["second"]);
+    }
+}
@@ -0,0 +1,81 @@
+//! Lowering of the CPU and I/O priority of the background executions.
+//!
+//! The executions that are not timing sensitive, like the compilation of the booklets, can run on
+//! the same machine of the evaluations. Giving them the lowest priority makes the kernel prefer the
+//! evaluations, so their measured times are not affected.
+
+use anyhow::{bail, Error};
+use nix::libc;
+
+/// The niceness of the background executions, the highest allowed.
+const BACKGROUND_NICENESS: libc::c_int = 19;
+/// `ioprio_set` applies to a single process.
+#[cfg(target_os = "linux")]
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+/// The idle I/O scheduling class: the process accesses the disk only when no one else needs it.
+#[cfg(target_os = "linux")]
+const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+/// The I/O scheduling class is stored in the bits above this one of the I/O priority.
+#[cfg(target_os = "linux")]
+const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+/// Give the lowest CPU and I/O priority to the process with the given PID. The processes it spawns
+/// afterwards inherit the priority.
+pub fn lower_process_priority(pid: u32) -> Result<(), Error> {
+    // SAFETY: setpriority doesn't access the memory of this process
+    let res =
+        unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, BACKGROUND_NICENESS) };
+    if res != 0 {
+        bail!(
+            "Failed to set the niceness of process {}: {}",
+            pid,
+            std::io::Error::last_os_error()
+        );
+    }
+    // the I/O priority is supported only on Linux
+    #[cfg(target_os = "linux")]
+    set_idle_io_priority(pid)?;
+    Ok(())
+}
+
+/// Move the process with the given PID to the idle I/O scheduling class.
+#[cfg(target_os = "linux")]
+fn set_idle_io_priority(pid: u32) -> Result<(), Error> {
+    // SAFETY: ioprio_set doesn't access the memory of this process
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            pid as libc::c_int,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        )
+    };
+    if res != 0 {
+        bail!(
+            "Failed to set the I/O priority of process {}: {}",
+            pid,
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lower_process_priority() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("10")
+            .spawn()
+            .unwrap();
+        let res = lower_process_priority(child.id());
+        // SAFETY: getpriority doesn't access the memory of this process
+        let niceness = unsafe { libc::getpriority(libc::PRIO_PROCESS, child.id() as libc::id_t) };
+        child.kill().unwrap();
+        child.wait().unwrap();
+        res.unwrap();
+        assert_eq!(niceness, BACKGROUND_NICENESS);
+    }
+}
@@ -30,19 +30,48 @@
 //! - `B` answers with `ProvideFile` which triggers a protocol switch for sending the file
 //! - `B` sends raw data (`send_raw`) zero or more times
 //! - `B` sends empty raw data which triggers a protocol switch, back into normal mode
+//!
+//! The files sent from the client to the server can be resumed: if the connection drops during the
+//! transfer, the server keeps the bytes received so far and, when the client reconnects, it asks
+//! the file again specifying in `AskFile` how many bytes it already has. The client then sends
+//! only the rest of the file, and the server checks the hash of the complete file.
+//!
+//! Each chunk of raw data starts with a byte telling whether the rest of the chunk is compressed
+//! with zstd. The chunks are compressed only if the compression is enabled for the connection (see
+//! [`ChannelFileSender::new`](struct.ChannelFileSender.html#method.new)), which is negotiated when
+//! the connection with a remote server is established; chunks that don't shrink enough (for
+//! example already compressed data) are always sent uncompressed.
+//!
+//! The cache keys of the system commands include the fingerprints of their toolchains, which only
+//! the workers can compute. The server asks them with `Toolchains` to the first worker that
+//! connects, and it doesn't use the cache before the answer; the workers also send them, unasked,
+//! before the results of the jobs that ran a command for the first time.
 
-use crate::executor::{ExecutionDAGWatchSet, ExecutorStatus, WorkerJob};
+use crate::admin::{AdminRequest, AdminResponse};
+use crate::executor::{ExecutionDAGWatchSet, ExecutorStatus, WorkerJob, WorkerTelemetry};
+use crate::quota::QuotaExceeded;
 use crate::*;
-use anyhow::Context;
+use anyhow::{bail, Context};
 use ductile::{ChannelReceiver, ChannelSender};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use task_maker_dag::*;
 use task_maker_store::*;
 
+/// Chunks smaller than this number of bytes are never compressed.
+const COMPRESSION_MIN_SIZE: usize = 4096;
+/// A compressed chunk is sent only if it's smaller than this fraction of the original size.
+const COMPRESSION_MAX_RATIO: f64 = 0.9;
+/// The zstd compression level to use.
+const COMPRESSION_LEVEL: i32 = 3;
+/// Tag of a chunk sent uncompressed.
+const CHUNK_PLAIN: u8 = 0;
+/// Tag of a chunk compressed with zstd.
+const CHUNK_ZSTD: u8 = 1;
+
 /// Messages that the client sends to the server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ExecutorClientMessage {
@@ -53,9 +82,17 @@ pub enum ExecutorClientMessage {
         /// The list of the executions and files to keep track of.
         callbacks: Box<ExecutionDAGWatchSet>,
     },
-    /// The client is providing a file. After this message there is a protocol switch for the file
-    /// transmission.
-    ProvideFile(FileUuid, FileStoreKey),
+    /// The client is extending the DAG it's evaluating, adding more executions and files. The new
+    /// executions can depend on the files of the DAG already sent.
+    Extend {
+        /// The new part of the DAG.
+        dag: Box<ExecutionDAGData>,
+        /// The executions and the files of the new part to keep track of.
+        callbacks: Box<ExecutionDAGWatchSet>,
+    },
+    /// The client is providing a file, starting from the specified offset. After this message
+    /// there is a protocol switch for the file transmission.
+    ProvideFile(FileUuid, FileStoreKey, u64),
     /// The client is asking the server to send a file. After this message there is a protocol
     /// switch for the file transmission.
     AskFile(FileUuid, FileStoreKey, bool),
@@ -65,14 +102,20 @@ pub enum ExecutorClientMessage {
     /// The client is asking for the server status. After this message the client should expect a
     /// [`Status`](enum.ExecutorServerMessage.html#variant.Status) message back.
     Status,
+    /// An administrator of the server sent a request, with the admin password of the server. After
+    /// this message the client should expect an
+    /// [`Admin`](enum.ExecutorServerMessage.html#variant.Admin) message back.
+    Admin(AdminRequest, Option<String>),
 }
 
 /// Messages that the server sends to the client.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ExecutorServerMessage {
     /// The server needs the file with that Uuid. The client must send back that file in order to
-    /// proceed with the execution.
-    AskFile(FileUuid),
+    /// proceed with the execution. The second entry is the number of bytes of the file the server
+    /// already received in a previous, interrupted, transfer: the client should send only the
+    /// content after them.
+    AskFile(FileUuid, u64),
     /// The server is sending a file. After this message there is a protocol switch for the file
     /// transmission protocol. The second entry is true if the generation of the file was
     /// successful.
@@ -85,8 +128,21 @@ pub enum ExecutorServerMessage {
     NotifySkip(ExecutionUuid),
     /// There was an error during the evaluation.
     Error(String),
+    /// The evaluation has been rejected because the client exceeded one of the quotas of the
+    /// server. The connection will be closed.
+    QuotaExceeded(QuotaExceeded),
     /// The server status as asked by the client.
     Status(ExecutorStatus<Duration>),
+    /// The response to a request of an administrator.
+    Admin(AdminResponse),
+    /// The client has been reattached to the evaluation it was disconnected from. The executions
+    /// completed meanwhile are replayed from the cache.
+    Reattached {
+        /// The identifier of the evaluation.
+        evaluation: EvaluationUuid,
+        /// Whether the server has been restarted, and the evaluation recovered from its queue.
+        recovered: bool,
+    },
     /// The evaluation of the DAG is complete, this message will close the connection.
     Done(Vec<(FileUuid, FileStoreKey, bool)>),
 }
@@ -95,8 +151,9 @@ pub enum ExecutorServerMessage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WorkerClientMessage {
     /// The worker is ready for some job. The worker will wait for a
-    /// [`Work`](enum.WorkerServerMessage.html#variant.Work) message.
-    GetWork,
+    /// [`Work`](enum.WorkerServerMessage.html#variant.Work) message. The telemetry of the machine
+    /// of the worker is sent alongside.
+    GetWork(WorkerTelemetry),
     /// The worker completed the job with this result producing those files. The actual files will
     /// be sent immediately after using `ProvideFile` messages.
     /// The list of `ExecutionResult` contains the results of all the executions inside the group,
@@ -108,6 +165,13 @@ pub enum WorkerClientMessage {
     /// The worker needs a file from the server. The server should send back that file in order to
     /// run the execution on the worker.
     AskFile(FileStoreKey),
+    /// The keys of the files in the store of the worker, sent when the worker connects and after
+    /// each garbage collection. The server sends the dependencies of the jobs that are not listed
+    /// here before the jobs themselves.
+    StoredFiles(Vec<FileStoreKey>),
+    /// The fingerprints of the toolchains of some system commands, sent in reply to `Toolchains`
+    /// and before the `WorkerDone` of a job that ran a command not fingerprinted before.
+    Toolchains(HashMap<PathBuf, String>),
 }
 
 /// Messages sent by the server to the worker.
@@ -115,14 +179,23 @@ pub enum WorkerClientMessage {
 pub enum WorkerServerMessage {
     /// The job the worker should do. Boxed to reduce the enum size.
     Work(Box<WorkerJob>),
+    /// Some short jobs the worker should do one after the other, in this order. The worker sends a
+    /// `WorkerDone` after each of them, and asks for more work only after completing all of them.
+    WorkBatch(Vec<WorkerJob>),
     /// Stop the current worker sandbox if currently running the specified execution.
     KillJob(ExecutionGroupUuid),
-    /// The file the workers as asked. After this message there is a protocol switch for the file
-    /// transmission.
+    /// The file the workers as asked, or a dependency of the next job the worker doesn't have.
+    /// After this message there is a protocol switch for the file transmission.
     ProvideFile(FileStoreKey),
     /// The worker completed the execution and produced some files, the server asks the ones that
     /// are missing using this message.
     AskFiles(Vec<FileUuid>),
+    /// Ask the worker to remove from its store the least recently used files, except the listed
+    /// ones that are still referenced by some evaluation. This is sent only when the worker is idle.
+    CollectGarbage(Vec<FileStoreKey>),
+    /// Ask the worker the fingerprints of the toolchains of these system commands, so that the
+    /// cache doesn't use stale ones.
+    Toolchains(Vec<PathBuf>),
     /// Ask the worker to exit.
     Exit,
 }
@@ -144,6 +217,33 @@ where
     pub fn new(reader: &'a ChannelReceiver<T>) -> ChannelFileIterator<'a, T> {
         ChannelFileIterator { reader }
     }
+
+    /// Receive the next chunk of the file, `None` if the transfer is completed.
+    pub fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let data = self
+            .reader
+            .recv_raw()
+            .context("Failed to receive file chunk")?;
+        if data.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(decode_chunk(data)?))
+        }
+    }
+
+    /// Make an iterator that yields the errors of the transfer instead of panicking, stopping
+    /// after the first one.
+    pub fn fallible(mut self) -> impl Iterator<Item = Result<Vec<u8>, Error>> + 'a {
+        let mut failed = false;
+        std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let chunk = self.next_chunk();
+            failed = chunk.is_err();
+            chunk.transpose()
+        })
+    }
 }
 
 impl<'a, T> Iterator for ChannelFileIterator<'a, T>
@@ -152,30 +252,142 @@ where
 {
     type Item = Vec<u8>;
     fn next(&mut self) -> Option<Self::Item> {
-        // errors cannot be handled in this iterator yet
-        let data = self.reader.recv_raw().expect("deserialize error");
-        if data.is_empty() {
-            None
-        } else {
-            Some(data)
+        // errors cannot be handled in this iterator, use `fallible` for that
+        self.next_chunk().expect("invalid file chunk")
+    }
+}
+
+/// Prepare a chunk of a file for sending, compressing it if enabled and worth it.
+fn encode_chunk(data: &[u8], compression: bool) -> Vec<u8> {
+    if compression && data.len() >= COMPRESSION_MIN_SIZE {
+        match zstd::bulk::compress(data, COMPRESSION_LEVEL) {
+            Ok(compressed)
+                if (compressed.len() as f64) < data.len() as f64 * COMPRESSION_MAX_RATIO =>
+            {
+                let mut chunk = Vec::with_capacity(compressed.len() + 1);
+                chunk.push(CHUNK_ZSTD);
+                chunk.extend_from_slice(&compressed);
+                return chunk;
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to compress file chunk: {:?}", e),
+        }
+    }
+    let mut chunk = Vec::with_capacity(data.len() + 1);
+    chunk.push(CHUNK_PLAIN);
+    chunk.extend_from_slice(data);
+    chunk
+}
+
+/// Extract the content of a chunk of a file received from a channel.
+fn decode_chunk(mut chunk: Vec<u8>) -> Result<Vec<u8>, Error> {
+    match chunk.first() {
+        Some(&CHUNK_PLAIN) => Ok(chunk.split_off(1)),
+        Some(&CHUNK_ZSTD) => {
+            let mut data = Vec::new();
+            zstd::stream::copy_decode(&chunk[1..], &mut data)
+                .context("Failed to decompress file chunk")?;
+            Ok(data)
         }
+        Some(tag) => bail!("Unknown file chunk tag: {}", tag),
+        None => bail!("Empty file chunk"),
     }
 }
 
-/// Utility function to send a file to a channel using [`send_raw`](https://docs.rs/ductile/0.1.0/ductile/struct.ChannelSender.html#method.send_raw).
-pub struct ChannelFileSender;
+/// Utility to send a file to a channel using [`send_raw`](https://docs.rs/ductile/0.1.0/ductile/struct.ChannelSender.html#method.send_raw),
+/// with the settings negotiated for that connection. The received files are decompressed
+/// regardless of these settings.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChannelFileSender {
+    /// Whether the chunks of the files are compressed.
+    compression: bool,
+}
 
 impl ChannelFileSender {
+    /// Make a sender for a connection, compressing the files sent if `compression` is set.
+    pub fn new(compression: bool) -> ChannelFileSender {
+        ChannelFileSender { compression }
+    }
+
+    /// Whether the files sent are compressed.
+    pub fn compression(&self) -> bool {
+        self.compression
+    }
+
     /// Send a local file to a channel using `send_raw`.
-    pub fn send<P: AsRef<Path>, T>(path: P, sender: &ChannelSender<T>) -> Result<(), Error>
+    pub fn send<P: AsRef<Path>, T>(&self, path: P, sender: &ChannelSender<T>) -> Result<(), Error>
+    where
+        T: 'static + Send + Sync + Serialize,
+    {
+        self.send_from(path, 0, sender)
+    }
+
+    /// Send a local file to a channel using `send_raw`, skipping the first `offset` bytes.
+    pub fn send_from<P: AsRef<Path>, T>(
+        &self,
+        path: P,
+        offset: u64,
+        sender: &ChannelSender<T>,
+    ) -> Result<(), Error>
+    where
+        T: 'static + Send + Sync + Serialize,
+    {
+        self.send_from_with_progress(path, offset, sender, |_| Ok(()))
+    }
+
+    /// Send a local file to a channel using `send_raw`, skipping the first `offset` bytes, and
+    /// calling `progress` with the number of bytes sent after each chunk.
+    pub fn send_from_with_progress<P: AsRef<Path>, T, F>(
+        &self,
+        path: P,
+        offset: u64,
+        sender: &ChannelSender<T>,
+        progress: F,
+    ) -> Result<(), Error>
     where
         T: 'static + Send + Sync + Serialize,
+        F: FnMut(u64) -> Result<(), Error>,
     {
         let path = path.as_ref();
-        let iterator = ReadFileIterator::new(path)
+        let iterator = ReadFileIterator::new_at(path, offset)
             .with_context(|| format!("Failed to read file to send: {}", path.display()))?;
+        self.send_chunks(iterator, sender, progress)
+    }
+
+    /// Send a file of the store to a channel using `send_raw`, decompressing it if needed.
+    pub fn send_handle<T>(
+        &self,
+        handle: &FileStoreHandle,
+        sender: &ChannelSender<T>,
+    ) -> Result<(), Error>
+    where
+        T: 'static + Send + Sync + Serialize,
+    {
+        let iterator = handle
+            .read()
+            .with_context(|| format!("Failed to read file to send: {}", handle))?;
+        self.send_chunks(iterator, sender, |_| Ok(()))
+    }
+
+    /// Send the chunks of a file to a channel using `send_raw`, followed by the terminator, calling
+    /// `progress` with the number of bytes sent after each chunk.
+    fn send_chunks<T, F>(
+        &self,
+        iterator: ReadFileIterator,
+        sender: &ChannelSender<T>,
+        mut progress: F,
+    ) -> Result<(), Error>
+    where
+        T: 'static + Send + Sync + Serialize,
+        F: FnMut(u64) -> Result<(), Error>,
+    {
+        let mut sent = 0;
         for buf in iterator {
-            sender.send_raw(&buf).context("Failed to send file chunk")?;
+            sender
+                .send_raw(&encode_chunk(&buf, self.compression))
+                .context("Failed to send file chunk")?;
+            sent += buf.len() as u64;
+            progress(sent)?;
         }
         sender
             .send_raw(&[])
@@ -184,20 +396,19 @@ impl ChannelFileSender {
     }
 
     /// Send the file content to a channel using `send_raw`.
-    pub fn send_data<T>(data: Vec<u8>, sender: &ChannelSender<T>) -> Result<(), Error>
+    pub fn send_data<T>(&self, data: Vec<u8>, sender: &ChannelSender<T>) -> Result<(), Error>
     where
         T: 'static + Send + Sync + Serialize,
     {
-        sender
-            .send_raw(&data)
-            .context("Failed to send file chunk")?;
-        // Send the EOF chunk only if the buffer is not empty (otherwise we would send EOF twice
-        // breaking the protocol).
+        // An empty file is sent as just the EOF chunk.
         if !data.is_empty() {
             sender
-                .send_raw(&[])
-                .context("Failed to send file terminator")?;
+                .send_raw(&encode_chunk(&data, self.compression))
+                .context("Failed to send file chunk")?;
         }
+        sender
+            .send_raw(&[])
+            .context("Failed to send file terminator")?;
         Ok(())
     }
 }
@@ -213,17 +424,103 @@ mod tests {
 
         let (sender, receiver) = new_local_channel::<()>();
         let receiver = ChannelFileIterator::new(&receiver);
-        ChannelFileSender::send(tmpdir.path().join("file.txt"), &sender).unwrap();
+        ChannelFileSender::default()
+            .send(tmpdir.path().join("file.txt"), &sender)
+            .unwrap();
         let data: Vec<u8> = receiver.flat_map(|d| d.into_iter()).collect();
         assert_eq!(String::from_utf8(data).unwrap(), "hello world");
     }
 
+    #[test]
+    fn test_send_file_from_offset() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        std::fs::write(tmpdir.path().join("file.txt"), "hello world").unwrap();
+
+        let (sender, receiver) = new_local_channel::<()>();
+        let receiver = ChannelFileIterator::new(&receiver).fallible();
+        ChannelFileSender::default()
+            .send_from(tmpdir.path().join("file.txt"), 6, &sender)
+            .unwrap();
+        let data: Vec<u8> = receiver.flat_map(|d| d.unwrap().into_iter()).collect();
+        assert_eq!(String::from_utf8(data).unwrap(), "world");
+    }
+
+    #[test]
+    fn test_send_file_with_progress() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        std::fs::write(tmpdir.path().join("file.txt"), "hello world").unwrap();
+
+        let (sender, receiver) = new_local_channel::<()>();
+        let receiver = ChannelFileIterator::new(&receiver);
+        let mut progress = vec![];
+        ChannelFileSender::default()
+            .send_from_with_progress(tmpdir.path().join("file.txt"), 6, &sender, |sent| {
+                progress.push(sent);
+                Ok(())
+            })
+            .unwrap();
+        let data: Vec<u8> = receiver.flat_map(|d| d.into_iter()).collect();
+        assert_eq!(String::from_utf8(data).unwrap(), "world");
+        assert_eq!(progress, vec![5]);
+    }
+
     #[test]
     fn test_send_content() {
         let (sender, receiver) = new_local_channel::<()>();
         let receiver = ChannelFileIterator::new(&receiver);
-        ChannelFileSender::send_data(b"hello world".to_vec(), &sender).unwrap();
+        ChannelFileSender::default()
+            .send_data(b"hello world".to_vec(), &sender)
+            .unwrap();
         let data: Vec<u8> = receiver.flat_map(|d| d.into_iter()).collect();
         assert_eq!(String::from_utf8(data).unwrap(), "hello world");
     }
+
+    #[test]
+    fn test_chunk_compression() {
+        let compressible = vec![42u8; 100_000];
+        let chunk = encode_chunk(&compressible, true);
+        assert_eq!(chunk[0], CHUNK_ZSTD);
+        assert!(chunk.len() < compressible.len());
+        assert_eq!(decode_chunk(chunk).unwrap(), compressible);
+
+        // already compressed data is sent as is
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let incompressible: Vec<u8> = (0..100_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 56) as u8
+            })
+            .collect();
+        let chunk = encode_chunk(&incompressible, true);
+        assert_eq!(chunk[0], CHUNK_PLAIN);
+        assert_eq!(decode_chunk(chunk).unwrap(), incompressible);
+
+        let small = b"hello world".to_vec();
+        assert_eq!(encode_chunk(&small, true)[0], CHUNK_PLAIN);
+        assert_eq!(encode_chunk(&compressible, false)[0], CHUNK_PLAIN);
+    }
+
+    #[test]
+    fn test_compression_per_connection() {
+        let content = vec![42u8; 100_000];
+        let (compressed_sender, compressed_receiver) = new_local_channel::<()>();
+        let (plain_sender, plain_receiver) = new_local_channel::<()>();
+        ChannelFileSender::new(true)
+            .send_data(content.clone(), &compressed_sender)
+            .unwrap();
+        ChannelFileSender::new(false)
+            .send_data(content.clone(), &plain_sender)
+            .unwrap();
+
+        let compressed = compressed_receiver.recv_raw().unwrap();
+        assert_eq!(compressed[0], CHUNK_ZSTD);
+        let plain = plain_receiver.recv_raw().unwrap();
+        assert_eq!(plain[0], CHUNK_PLAIN);
+        assert_eq!(decode_chunk(compressed).unwrap(), content);
+        assert_eq!(decode_chunk(plain).unwrap(), content);
+        assert!(compressed_receiver.recv_raw().unwrap().is_empty());
+        assert!(plain_receiver.recv_raw().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,52 @@
+//! Limits on the resources a single client can use on a shared server.
+//!
+//! The limits on the executions and on the evaluations are applied to all the connections with the
+//! same client name (i.e. `user@host`), so a script that opens many connections cannot bypass them.
+//! The limit on the transferred bytes is applied to each connection.
+
+use serde::{Deserialize, Serialize};
+
+/// The limits that the server applies to each of its clients. `None` means no limit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientQuotas {
+    /// The maximum number of execution groups of the same client running at the same time. The
+    /// other executions of the client wait in the queue, even if there are free workers.
+    pub max_running_execs: Option<usize>,
+    /// The maximum number of evaluations of the same client the server is working on. The new
+    /// evaluations are rejected.
+    pub max_evaluations: Option<usize>,
+    /// The maximum number of bytes a client can send to the server in a single connection.
+    pub max_transferred_bytes: Option<u64>,
+}
+
+/// The reason why the server rejected the evaluation of a client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+pub enum QuotaExceeded {
+    /// The client has too many evaluations running on the server.
+    #[error("the server is already running {limit} evaluations of this client")]
+    TooManyEvaluations {
+        /// The maximum number of evaluations of a client.
+        limit: usize,
+    },
+    /// The client sent too many bytes to the server.
+    #[error("the client sent more than {limit} bytes to the server")]
+    TooManyBytes {
+        /// The maximum number of bytes a client can send.
+        limit: u64,
+    },
+}
+
+impl QuotaExceeded {
+    /// A suggestion for the user on how to get the evaluation accepted.
+    pub fn help(&self) -> &'static str {
+        match self {
+            QuotaExceeded::TooManyEvaluations { .. } => {
+                "Wait for your other evaluations to complete, or stop them, before trying again"
+            }
+            QuotaExceeded::TooManyBytes { .. } => {
+                "Reduce the size of the files of the task (e.g. the static input files), or \
+                evaluate it locally"
+            }
+        }
+    }
+}
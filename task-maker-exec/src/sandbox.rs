@@ -21,7 +21,9 @@ use tempfile::TempDir;
 use task_maker_dag::*;
 use task_maker_store::*;
 
+use crate::builtin::run_builtin;
 use crate::detect_exe::detect_exe;
+use crate::sandbox_pool::{can_prepare, create_skeleton, PreparedFiles, SandboxPool};
 use crate::sandbox_runner::SandboxRunner;
 
 /// The list of all the system-wide readable directories inside the sandbox.
@@ -74,6 +76,11 @@ struct SandboxData {
     fifo_dir: Option<PathBuf>,
     /// The PID of the sandbox process, zero if not available or not spawned yet.
     box_pid: Arc<AtomicU32>,
+    /// The pool the sandbox directory is given back to when the sandbox is dropped.
+    pool: Option<Arc<SandboxPool>>,
+    /// The files of the program kept in the directory when it's given back to the pool, if the
+    /// program doesn't need a compilation.
+    prepared: Option<PreparedFiles>,
 }
 
 /// Response of the internal implementation of the sandbox.
@@ -113,27 +120,102 @@ impl Sandbox {
         })?;
         let boxdir = TempDir::new_in(sandboxes_dir)
             .context("Failed to create sandbox temporary directory")?;
+        create_skeleton(boxdir.path())?;
         Sandbox::setup(boxdir.path(), execution, dep_keys).context("Sandbox setup failed")?;
-        Ok(Sandbox {
+        Ok(Sandbox::from_boxdir(boxdir, execution, fifo_dir, None))
+    }
+
+    /// Make a new sandbox for the specified execution using a directory taken from the pool. When
+    /// the sandbox is dropped the directory is given back to the pool.
+    ///
+    /// If the program of the execution doesn't need a compilation, a directory already prepared
+    /// with its files is used when available, and the directory is given back to the pool still
+    /// prepared.
+    pub fn new_pooled(
+        pool: &Arc<SandboxPool>,
+        execution: &Execution,
+        dep_keys: &HashMap<FileUuid, FileStoreHandle>,
+        fifo_dir: Option<PathBuf>,
+    ) -> Result<Sandbox, Error> {
+        let prepared = Sandbox::prepared_files(execution, dep_keys);
+        let (boxdir, skip) = match prepared.as_ref().and_then(|p| pool.acquire_prepared(p)) {
+            Some(boxdir) => (boxdir, execution.runtime_inputs.as_slice()),
+            None => (pool.acquire()?, [].as_slice()),
+        };
+        Sandbox::setup_skipping(boxdir.path(), execution, dep_keys, skip)
+            .context("Sandbox setup failed")?;
+        let sandbox = Sandbox::from_boxdir(boxdir, execution, fifo_dir, Some(pool.clone()));
+        sandbox.data.lock().unwrap().prepared = prepared;
+        Ok(sandbox)
+    }
+
+    /// The files of the program of the execution that can be kept in a prepared sandbox directory,
+    /// if the program doesn't need a compilation.
+    fn prepared_files(
+        execution: &Execution,
+        dep_keys: &HashMap<FileUuid, FileStoreHandle>,
+    ) -> Option<PreparedFiles> {
+        let mut files = execution
+            .runtime_inputs
+            .iter()
+            .map(|path| {
+                let input = execution.inputs.get(path)?;
+                let handle = dep_keys.get(&input.file)?;
+                Some((path.clone(), handle.key().clone(), input.executable))
+            })
+            .collect::<Option<PreparedFiles>>()?;
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        files.dedup_by(|a, b| a.0 == b.0);
+        can_prepare(&files).then_some(files)
+    }
+
+    /// Wrap an already set up sandbox directory.
+    fn from_boxdir(
+        boxdir: TempDir,
+        execution: &Execution,
+        fifo_dir: Option<PathBuf>,
+        pool: Option<Arc<SandboxPool>>,
+    ) -> Sandbox {
+        Sandbox {
             data: Arc::new(Mutex::new(SandboxData {
                 boxdir: Some(boxdir),
                 execution: execution.clone(),
                 keep_sandbox: false,
                 fifo_dir,
                 box_pid: Arc::new(AtomicU32::new(0)),
+                pool,
+                prepared: None,
             })),
-        })
+        }
     }
 
-    /// Starts the sandbox and blocks the thread until the sandbox exits.
+    /// Starts the sandbox and blocks the thread until the sandbox exits. The builtin commands are
+    /// run directly by this thread, without spawning the sandbox.
     pub fn run(&self, runner: &dyn SandboxRunner) -> Result<SandboxResult, Error> {
+        let builtin = {
+            let data = self.data.lock().unwrap();
+            match &data.execution.command {
+                ExecutionCommand::Builtin(command) => Some((
+                    *command,
+                    data.execution.args.clone(),
+                    data.path().join("box"),
+                    builtin_time_limit(&data.execution),
+                )),
+                _ => None,
+            }
+        };
+        if let Some((command, args, box_dir, time_limit)) = builtin {
+            trace!("Running builtin command {:?} at {:?}", command, box_dir);
+            return Ok(run_builtin(command, &args, &box_dir, time_limit));
+        }
         let mut config = SandboxConfiguration::default();
-        let (boxdir, pid, keep, cmd) = {
+        let (boxdir, pid, keep, background, cmd) = {
             let data = self.data.lock().unwrap();
             (
                 data.path().to_owned(),
                 data.box_pid.clone(),
                 data.keep_sandbox,
+                data.execution.background,
                 self.build_command(
                     data.path(),
                     &data.execution,
@@ -151,7 +233,7 @@ impl Sandbox {
         }
         trace!("Sandbox configuration: {:#?}", config);
 
-        let raw_result = runner.run(config.build(), pid);
+        let raw_result = runner.run(config.build(), pid, background);
         if keep {
             let target = boxdir.join("result.txt");
             std::fs::write(&target, format!("{:#?}", raw_result))
@@ -377,9 +459,12 @@ impl Sandbox {
             let wall = wall + execution.config().extra_time;
             config.wall_time_limit(wall.ceil() as u64);
         }
+        // with the RSS metric the memory is checked only after the execution, from its peak usage
         if let Some(mem) = execution.limits.memory {
-            let mem = mem + execution.config().extra_memory;
-            config.memory_limit(mem * 1024);
+            if execution.limits.memory_metric == MemoryMetric::AddressSpace {
+                let mem = mem + execution.config().extra_memory;
+                config.memory_limit(mem * 1024);
+            }
         }
         if let Some(stack) = execution.limits.stack {
             config.stack_limit(stack * 1024);
@@ -442,6 +527,9 @@ impl Sandbox {
                 })?;
                 config.executable(box_root.join(cmd));
             }
+            ExecutionCommand::Builtin(cmd) => {
+                bail!("Builtin command {:?} is not run in the sandbox", cmd);
+            }
         };
         for arg in execution.args.iter() {
             config.arg(arg);
@@ -452,11 +540,22 @@ impl Sandbox {
         Ok(())
     }
 
-    /// Setup the sandbox directory with all the files required for the execution.
+    /// Setup the sandbox directory with all the files required for the execution. The skeleton of
+    /// the sandbox (`box/` and `etc/`) should be already present.
     fn setup<P: AsRef<Path>>(
         box_dir: P,
         execution: &Execution,
         dep_keys: &HashMap<FileUuid, FileStoreHandle>,
+    ) -> Result<(), Error> {
+        Sandbox::setup_skipping(box_dir, execution, dep_keys, &[])
+    }
+
+    /// Same as `setup`, but the inputs at the `skip` paths are already in the sandbox directory.
+    fn setup_skipping<P: AsRef<Path>>(
+        box_dir: P,
+        execution: &Execution,
+        dep_keys: &HashMap<FileUuid, FileStoreHandle>,
+        skip: &[PathBuf],
     ) -> Result<(), Error> {
         let box_dir = box_dir.as_ref();
         trace!(
@@ -464,25 +563,10 @@ impl Sandbox {
             box_dir,
             execution.description
         );
-        Self::create_sandbox_dir(box_dir, "box")?;
-        // put /etc/passwd inside the sandbox
-        Self::create_sandbox_dir(box_dir, "etc")?;
-        std::fs::write(
-            box_dir.join("etc").join("passwd"),
-            "root::0:0::/:/bin/sh\n\
-            nobody::1000:1000::/:/bin/sh\n",
-        )
-        .with_context(|| {
-            format!(
-                "Failed to write /etc/passwd in the sandbox {}",
-                box_dir.display()
-            )
-        })?;
-
         if let Some(stdin) = execution.stdin {
             Sandbox::write_sandbox_file(
                 &box_dir.join("stdin"),
-                dep_keys.get(&stdin).context("stdin not provided")?.path(),
+                dep_keys.get(&stdin).context("stdin not provided")?,
                 false,
             )?;
         }
@@ -493,12 +577,12 @@ impl Sandbox {
             Sandbox::touch_file(&box_dir.join("stderr"), 0o600)?;
         }
         for (path, input) in execution.inputs.iter() {
+            if skip.contains(path) {
+                continue;
+            }
             Sandbox::write_sandbox_file(
                 &box_dir.join("box").join(path),
-                dep_keys
-                    .get(&input.file)
-                    .context("file not provided")?
-                    .path(),
+                dep_keys.get(&input.file).context("file not provided")?,
                 input.executable,
             )?;
         }
@@ -513,31 +597,27 @@ impl Sandbox {
         Ok(())
     }
 
-    /// Create a directory inside the sandbox.
-    fn create_sandbox_dir<P: AsRef<Path>>(box_dir: &Path, path: P) -> Result<(), Error> {
-        let target = box_dir.join(path.as_ref());
-        std::fs::create_dir_all(&target)
-            .with_context(|| format!("Failed to create sandbox directory: {}", target.display()))
-    }
-
     /// Put a file inside the sandbox, creating the directories if needed and making it executable
     /// if needed.
     ///
     /// The file will have the most restrictive permissions possible:
     /// - `r--------` (0o400) if not executable.
     /// - `r-x------` (0o500) if executable.
-    fn write_sandbox_file(dest: &Path, source: &Path, executable: bool) -> Result<(), Error> {
+    fn write_sandbox_file(
+        dest: &Path,
+        source: &FileStoreHandle,
+        executable: bool,
+    ) -> Result<(), Error> {
         std::fs::create_dir_all(dest.parent().context("Invalid destination path")?)
             .with_context(|| format!("Failed to create parent directory of {}", dest.display()))?;
-        // First try to hardlink the file to the destination, this is faster and less prone to race
-        // conditions. If another thread forks while copying the executable (for example spawning a
-        // sandbox of another worker) the file descriptor won't be closed while this sandbox tries
-        // to exec the process, failing with "Text file busy".
-        if std::fs::hard_link(source, dest).is_err() {
-            std::fs::copy(source, dest).with_context(|| {
-                format!("Failed to copy {} -> {}", source.display(), dest.display())
-            })?;
-        }
+        // The uncompressed files are hardlinked to the destination, this is faster and less prone
+        // to race conditions. If another thread forks while copying the executable (for example
+        // spawning a sandbox of another worker) the file descriptor won't be closed while this
+        // sandbox tries to exec the process, failing with "Text file busy". The executables are
+        // binary files, which are never compressed.
+        source
+            .materialize(dest)
+            .with_context(|| format!("Failed to write sandbox file {}", dest.display()))?;
         if executable {
             Sandbox::set_permissions(dest, 0o500)?;
         } else {
@@ -587,6 +667,21 @@ impl SandboxData {
     }
 }
 
+/// The time limit of a builtin command, in seconds. The builtin commands run on a single thread of
+/// the worker, so their cpu time is their wall time: the tighter of the two limits applies.
+fn builtin_time_limit(execution: &Execution) -> Option<f64> {
+    let limits = &execution.limits;
+    let cpu_limit = match (limits.cpu_time, limits.sys_time) {
+        (Some(cpu), Some(sys)) => Some(cpu + sys),
+        (cpu, sys) => cpu.or(sys),
+    };
+    let limit = match (cpu_limit, limits.wall_time) {
+        (Some(cpu), Some(wall)) => Some(cpu.min(wall)),
+        (cpu, wall) => cpu.or(wall),
+    };
+    limit.map(|limit| limit + execution.config().extra_time)
+}
+
 impl Drop for SandboxData {
     fn drop(&mut self) {
         if self.keep_sandbox {
@@ -594,6 +689,11 @@ impl Drop for SandboxData {
             self.boxdir.take().map(TempDir::into_path);
         } else if Sandbox::set_permissions(&self.path().join("box"), 0o700).is_err() {
             warn!("Cannot 'chmod 700' the sandbox directory");
+        } else if let (Some(pool), Some(boxdir)) = (self.pool.take(), self.boxdir.take()) {
+            match self.prepared.take() {
+                Some(files) => pool.release_prepared(boxdir, files),
+                None => pool.release(boxdir),
+            }
         }
     }
 }
@@ -602,14 +702,16 @@ impl Drop for SandboxData {
 mod tests {
     use std::collections::HashMap;
     use std::path::Path;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::Arc;
 
     use tabox::configuration::{DirectoryMount, SandboxConfiguration};
     use tabox::syscall_filter::SyscallFilterAction;
 
-    use task_maker_dag::{Execution, ExecutionCommand};
+    use task_maker_dag::{BuiltinCommand, Execution, ExecutionCommand, MemoryMetric};
 
-    use crate::sandbox::Sandbox;
-    use crate::ErrorSandboxRunner;
+    use crate::sandbox::{Sandbox, SandboxResult};
+    use crate::{ErrorSandboxRunner, RawSandboxResult, SandboxRunner};
 
     #[test]
     fn test_remove_sandbox_on_drop() {
@@ -630,6 +732,19 @@ mod tests {
         assert!(!outfile.parent().unwrap().parent().unwrap().exists()); // the sandbox dir
     }
 
+    #[test]
+    fn test_builtin_command_not_sandboxed() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let mut exec = Execution::new("test", ExecutionCommand::builtin(BuiltinCommand::WhiteDiff));
+        exec.args(vec!["correct", "test"]);
+        let sandbox = Sandbox::new(tmpdir.path(), &exec, &HashMap::new(), None).unwrap();
+        // the files are missing, but the sandbox runner is not called
+        match sandbox.run(&ErrorSandboxRunner) {
+            Ok(SandboxResult::Failed { error }) => assert!(error.contains("Cannot open")),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
     #[cfg(not(target_os = "macos"))]
     #[test]
     fn test_command_args() {
@@ -679,4 +794,44 @@ mod tests {
         );
         assert_eq!(config.args, vec!["bar", "baz"]);
     }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_memory_metric_rss() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let mut exec = Execution::new("test", ExecutionCommand::system("/bin/sh"));
+        exec.limits_mut()
+            .memory(1234)
+            .memory_metric(MemoryMetric::Rss);
+        let sandbox = Sandbox::new(tmpdir.path(), &exec, &HashMap::new(), None).unwrap();
+        let mut config = SandboxConfiguration::default();
+        sandbox
+            .build_command(tmpdir.path(), &exec, &mut config, None)
+            .unwrap();
+        assert_eq!(config.memory_limit, None);
+    }
+
+    #[test]
+    fn test_background_execution() {
+        struct BackgroundRunner(AtomicBool);
+        impl SandboxRunner for BackgroundRunner {
+            fn run(
+                &self,
+                _config: SandboxConfiguration,
+                _pid: Arc<AtomicU32>,
+                background: bool,
+            ) -> RawSandboxResult {
+                self.0.store(background, Ordering::SeqCst);
+                RawSandboxResult::Error("Nope".to_owned())
+            }
+        }
+
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let mut exec = Execution::new("test", ExecutionCommand::system("true"));
+        exec.background(true);
+        let sandbox = Sandbox::new(tmpdir.path(), &exec, &HashMap::new(), None).unwrap();
+        let runner = BackgroundRunner(AtomicBool::new(false));
+        assert!(sandbox.run(&runner).is_err());
+        assert!(runner.0.load(Ordering::SeqCst));
+    }
 }
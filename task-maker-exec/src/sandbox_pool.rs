@@ -0,0 +1,449 @@
+//! A pool of pre-created sandbox directories, reused between the executions of a worker.
+//!
+//! Creating a fresh sandbox directory for each execution adds some latency, which is noticeable
+//! for tasks with thousands of tiny executions. The pool keeps a number of empty sandbox
+//! directories ready to be used; when a sandbox is dropped its directory is reset and put back in
+//! the pool, but only if after the reset it looks exactly like a fresh one. Otherwise it's deleted.
+//!
+//! The programs that don't need a compilation (e.g. the Python generators and validators) are run
+//! many times with the same files. The sandbox directories of their executions are kept prepared
+//! with those files, so the next executions of the same program on this worker only have to add
+//! their own inputs.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs::Permissions;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{bail, Context, Error};
+use tempfile::TempDir;
+
+use task_maker_store::FileStoreKey;
+
+/// Content of the `/etc/passwd` file inside the sandbox.
+pub(crate) const SANDBOX_PASSWD: &str = "root::0:0::/:/bin/sh\nnobody::1000:1000::/:/bin/sh\n";
+
+/// A file of a program kept in a prepared sandbox directory: its path inside `box/`, the key of its
+/// content and whether it's executable.
+pub(crate) type PreparedFile = (PathBuf, FileStoreKey, bool);
+
+/// All the files of a program kept in a prepared sandbox directory, sorted by path.
+pub(crate) type PreparedFiles = Vec<PreparedFile>;
+
+/// A pool of sandbox directories ready to be used.
+#[derive(Debug)]
+pub struct SandboxPool {
+    /// The directory where the sandboxes are created.
+    sandboxes_dir: PathBuf,
+    /// The maximum number of directories to keep in the pool, and separately the maximum number of
+    /// prepared directories.
+    capacity: usize,
+    /// The directories ready to be used.
+    ready: Mutex<Vec<TempDir>>,
+    /// The directories already containing the files of a program, ready to be used for running it.
+    prepared: Mutex<HashMap<PreparedFiles, Vec<TempDir>>>,
+}
+
+impl SandboxPool {
+    /// Make a new pool of sandboxes inside `sandboxes_dir`, pre-creating `capacity` directories.
+    pub fn new<P: Into<PathBuf>>(sandboxes_dir: P, capacity: usize) -> Result<SandboxPool, Error> {
+        let sandboxes_dir = sandboxes_dir.into();
+        std::fs::create_dir_all(&sandboxes_dir).with_context(|| {
+            format!(
+                "Failed to create sandbox directory at {}",
+                sandboxes_dir.display()
+            )
+        })?;
+        let pool = SandboxPool {
+            sandboxes_dir,
+            capacity,
+            ready: Mutex::new(Vec::with_capacity(capacity)),
+            prepared: Mutex::new(HashMap::new()),
+        };
+        let ready = (0..capacity)
+            .map(|_| pool.create())
+            .collect::<Result<Vec<_>, _>>()?;
+        *pool.ready.lock().unwrap() = ready;
+        Ok(pool)
+    }
+
+    /// The directory where the sandboxes are created.
+    pub fn sandboxes_dir(&self) -> &Path {
+        &self.sandboxes_dir
+    }
+
+    /// The number of directories currently ready in the pool.
+    pub fn len(&self) -> usize {
+        self.ready.lock().unwrap().len()
+    }
+
+    /// Whether the pool has no directories ready.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take a sandbox directory from the pool, creating a new one if the pool is empty.
+    pub(crate) fn acquire(&self) -> Result<TempDir, Error> {
+        if let Some(dir) = self.ready.lock().unwrap().pop() {
+            return Ok(dir);
+        }
+        self.create()
+    }
+
+    /// Give back a sandbox directory to the pool. The directory is reset and checked, if anything
+    /// is off or the pool is full it's deleted instead.
+    pub(crate) fn release(&self, dir: TempDir) {
+        if self.len() >= self.capacity {
+            return;
+        }
+        if let Err(e) =
+            reset_sandbox_dir(dir.path(), &[]).and_then(|_| check_sandbox_dir(dir.path(), &[]))
+        {
+            warn!(
+                "Sandbox at {} cannot be reused: {:?}",
+                dir.path().display(),
+                e
+            );
+            return;
+        }
+        let mut ready = self.ready.lock().unwrap();
+        if ready.len() < self.capacity {
+            ready.push(dir);
+        }
+    }
+
+    /// Take from the pool a sandbox directory already containing the specified files, if any.
+    pub(crate) fn acquire_prepared(&self, files: &[PreparedFile]) -> Option<TempDir> {
+        self.prepared.lock().unwrap().get_mut(files)?.pop()
+    }
+
+    /// Give back to the pool a sandbox directory, keeping the specified files of the program that
+    /// ran in it. The directory is reset and checked: if it's not a fresh one with exactly those
+    /// files, or if there are too many prepared directories, it's deleted instead.
+    pub(crate) fn release_prepared(&self, dir: TempDir, files: PreparedFiles) {
+        if self.prepared_len() >= self.capacity {
+            return;
+        }
+        if let Err(e) = reset_sandbox_dir(dir.path(), &files)
+            .and_then(|_| check_sandbox_dir(dir.path(), &files))
+        {
+            warn!(
+                "Prepared sandbox at {} cannot be reused: {:?}",
+                dir.path().display(),
+                e
+            );
+            return;
+        }
+        self.prepared
+            .lock()
+            .unwrap()
+            .entry(files)
+            .or_default()
+            .push(dir);
+    }
+
+    /// The number of prepared directories currently in the pool.
+    fn prepared_len(&self) -> usize {
+        self.prepared.lock().unwrap().values().map(Vec::len).sum()
+    }
+
+    /// Create a new sandbox directory with the skeleton of the sandbox.
+    fn create(&self) -> Result<TempDir, Error> {
+        let dir = TempDir::new_in(&self.sandboxes_dir)
+            .context("Failed to create sandbox temporary directory")?;
+        create_skeleton(dir.path())?;
+        Ok(dir)
+    }
+}
+
+/// Create the directories and files that every sandbox has, independently of the execution.
+pub(crate) fn create_skeleton(box_dir: &Path) -> Result<(), Error> {
+    for dir in ["box", "etc"] {
+        let target = box_dir.join(dir);
+        std::fs::create_dir_all(&target)
+            .with_context(|| format!("Failed to create sandbox directory: {}", target.display()))?;
+    }
+    std::fs::write(box_dir.join("etc").join("passwd"), SANDBOX_PASSWD).with_context(|| {
+        format!(
+            "Failed to write /etc/passwd in the sandbox {}",
+            box_dir.display()
+        )
+    })?;
+    Ok(())
+}
+
+/// Whether the files can be kept in a prepared sandbox directory, i.e. there is at least one and
+/// their paths are relative and inside `box/`.
+pub(crate) fn can_prepare(files: &[PreparedFile]) -> bool {
+    !files.is_empty()
+        && files.iter().all(|(path, _, _)| {
+            path.components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        })
+}
+
+/// The paths inside `box/` of the prepared files and of the directories containing them.
+fn prepared_paths(files: &[PreparedFile]) -> (HashSet<PathBuf>, HashSet<PathBuf>) {
+    let paths: HashSet<_> = files.iter().map(|(path, _, _)| path.clone()).collect();
+    let dirs = paths
+        .iter()
+        .flat_map(|path| path.ancestors().skip(1))
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .collect();
+    (paths, dirs)
+}
+
+/// Remove everything the execution left in the sandbox directory, leaving only the skeleton and
+/// the prepared files.
+fn reset_sandbox_dir(box_dir: &Path, files: &[PreparedFile]) -> Result<(), Error> {
+    let (paths, dirs) = prepared_paths(files);
+    for entry in std::fs::read_dir(box_dir)
+        .with_context(|| format!("Failed to list {}", box_dir.display()))?
+    {
+        let entry = entry.context("Failed to read directory entry")?;
+        let name = entry.file_name();
+        if name == "etc" {
+            continue;
+        }
+        if name == "box" && !entry.file_type()?.is_symlink() {
+            make_writable(&entry.path())?;
+            reset_box_dir(&entry.path(), Path::new(""), &paths, &dirs)?;
+            continue;
+        }
+        remove_entry(&entry.path())?;
+    }
+    Ok(())
+}
+
+/// Remove the content of the directory `box/<relative>`, except for the prepared files and the
+/// directories containing them.
+fn reset_box_dir(
+    dir: &Path,
+    relative: &Path,
+    paths: &HashSet<PathBuf>,
+    dirs: &HashSet<PathBuf>,
+) -> Result<(), Error> {
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Failed to list {}", dir.display()))?
+    {
+        let entry = entry.context("Failed to read directory entry")?;
+        let relative = relative.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_file() && paths.contains(&relative) {
+            continue;
+        }
+        if file_type.is_dir() && dirs.contains(&relative) {
+            reset_box_dir(&entry.path(), &relative, paths, dirs)?;
+            continue;
+        }
+        remove_entry(&entry.path())?;
+    }
+    Ok(())
+}
+
+/// Check that the sandbox directory is indistinguishable from a freshly created one with only the
+/// prepared files added, with their content and permissions.
+fn check_sandbox_dir(box_dir: &Path, files: &[PreparedFile]) -> Result<(), Error> {
+    let entries = std::fs::read_dir(box_dir)
+        .with_context(|| format!("Failed to list {}", box_dir.display()))?
+        .map(|e| e.map(|e| e.file_name()))
+        .collect::<Result<HashSet<_>, _>>()
+        .context("Failed to read directory entry")?;
+    let expected: HashSet<OsString> = ["box", "etc"].iter().map(OsString::from).collect();
+    if entries != expected {
+        bail!("Unexpected entries in the sandbox: {:?}", entries);
+    }
+    let root = std::fs::symlink_metadata(box_dir.join("box")).context("Missing box/")?;
+    if !root.is_dir() || root.permissions().mode() & 0o700 != 0o700 {
+        bail!("box/ is not a writable directory");
+    }
+    let (paths, dirs) = prepared_paths(files);
+    let mut content = HashSet::new();
+    list_box_dir(&box_dir.join("box"), Path::new(""), &mut content)?;
+    if content.len() != paths.len() + dirs.len()
+        || !content
+            .iter()
+            .all(|path| paths.contains(path) || dirs.contains(path))
+    {
+        bail!("box/ contains unexpected files: {:?}", content);
+    }
+    for (path, key, executable) in files {
+        let path = box_dir.join("box").join(path);
+        let metadata = std::fs::symlink_metadata(&path)?;
+        let mode = if *executable { 0o500 } else { 0o400 };
+        if !metadata.is_file() || metadata.permissions().mode() & 0o7777 != mode {
+            bail!("{} has been tampered", path.display());
+        }
+        if &FileStoreKey::from_file(&path)? != key {
+            bail!("The content of {} has changed", path.display());
+        }
+    }
+    let etc = std::fs::symlink_metadata(box_dir.join("etc")).context("Missing etc/")?;
+    if !etc.is_dir() || std::fs::read_dir(box_dir.join("etc"))?.count() != 1 {
+        bail!("Unexpected content of etc/");
+    }
+    let passwd = box_dir.join("etc").join("passwd");
+    if !std::fs::symlink_metadata(&passwd)?.is_file()
+        || std::fs::read_to_string(&passwd)? != SANDBOX_PASSWD
+    {
+        bail!("etc/passwd has been tampered");
+    }
+    Ok(())
+}
+
+/// Add to `content` the paths, relative to `box/`, of everything inside `box/<relative>`, without
+/// following symlinks.
+fn list_box_dir(dir: &Path, relative: &Path, content: &mut HashSet<PathBuf>) -> Result<(), Error> {
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Failed to list {}", dir.display()))?
+    {
+        let entry = entry.context("Failed to read directory entry")?;
+        let relative = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            list_box_dir(&entry.path(), &relative, content)?;
+        }
+        content.insert(relative);
+    }
+    Ok(())
+}
+
+/// Remove a file or a directory, making sure its content can be removed.
+fn remove_entry(path: &Path) -> Result<(), Error> {
+    let metadata = std::fs::symlink_metadata(path)
+        .with_context(|| format!("Failed to stat {}", path.display()))?;
+    if metadata.is_dir() {
+        make_writable(path)?;
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+    .with_context(|| format!("Failed to remove {}", path.display()))
+}
+
+/// Recursively add the owner permissions to all the directories, without following symlinks.
+fn make_writable(dir: &Path) -> Result<(), Error> {
+    std::fs::set_permissions(dir, Permissions::from_mode(0o700))
+        .with_context(|| format!("Failed to chmod 700 {}", dir.display()))?;
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Failed to list {}", dir.display()))?
+    {
+        let entry = entry.context("Failed to read directory entry")?;
+        if entry.file_type()?.is_dir() {
+            make_writable(&entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_reuses_clean_sandboxes() {
+        let tmpdir = TempDir::new().unwrap();
+        let pool = SandboxPool::new(tmpdir.path(), 2).unwrap();
+        assert_eq!(pool.len(), 2);
+
+        let dir = pool.acquire().unwrap();
+        let path = dir.path().to_owned();
+        assert_eq!(pool.len(), 1);
+        std::fs::create_dir_all(path.join("box/sub/dir")).unwrap();
+        std::fs::write(path.join("box/sub/dir/file"), "x").unwrap();
+        std::fs::set_permissions(path.join("box/sub"), Permissions::from_mode(0o500)).unwrap();
+        std::fs::write(path.join("stdout"), "x").unwrap();
+        pool.release(dir);
+        assert_eq!(pool.len(), 2);
+        assert!(path.join("box").exists());
+        assert!(!path.join("box/sub").exists());
+        assert!(!path.join("stdout").exists());
+    }
+
+    #[test]
+    fn test_pool_discards_tampered_sandboxes() {
+        let tmpdir = TempDir::new().unwrap();
+        let pool = SandboxPool::new(tmpdir.path(), 1).unwrap();
+        let dir = pool.acquire().unwrap();
+        let path = dir.path().to_owned();
+        std::fs::write(path.join("etc/passwd"), "evil").unwrap();
+        pool.release(dir);
+        assert!(pool.is_empty());
+        assert!(!path.exists());
+    }
+
+    /// Write a prepared file in the sandbox directory, like a sandbox would.
+    fn write_prepared(dir: &Path, path: &str, content: &str) -> PreparedFile {
+        let target = dir.join("box").join(path);
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        std::fs::write(&target, content).unwrap();
+        std::fs::set_permissions(&target, Permissions::from_mode(0o500)).unwrap();
+        let key = FileStoreKey::from_content(content.as_bytes());
+        (PathBuf::from(path), key, true)
+    }
+
+    #[test]
+    fn test_pool_reuses_prepared_sandboxes() {
+        let tmpdir = TempDir::new().unwrap();
+        let pool = SandboxPool::new(tmpdir.path(), 2).unwrap();
+        let dir = pool.acquire().unwrap();
+        let path = dir.path().to_owned();
+        let files = vec![
+            write_prepared(&path, "gen", "#!/bin/sh"),
+            write_prepared(&path, "lib/util.py", "x = 1"),
+        ];
+        assert!(can_prepare(&files));
+        std::fs::write(path.join("box/input.txt"), "x").unwrap();
+        std::fs::write(path.join("box/lib/other.py"), "x").unwrap();
+        pool.release_prepared(dir, files.clone());
+        assert_eq!(pool.prepared_len(), 1);
+
+        assert!(pool.acquire_prepared(&files[..1]).is_none());
+        let dir = pool.acquire_prepared(&files).unwrap();
+        assert_eq!(dir.path(), path);
+        assert!(path.join("box/gen").exists());
+        assert!(path.join("box/lib/util.py").exists());
+        assert!(!path.join("box/input.txt").exists());
+        assert!(!path.join("box/lib/other.py").exists());
+        assert_eq!(pool.prepared_len(), 0);
+    }
+
+    #[test]
+    fn test_pool_discards_tampered_prepared_sandboxes() {
+        let tmpdir = TempDir::new().unwrap();
+        let pool = SandboxPool::new(tmpdir.path(), 2).unwrap();
+        let dir = pool.acquire().unwrap();
+        let path = dir.path().to_owned();
+        let files = vec![write_prepared(&path, "gen", "#!/bin/sh")];
+        std::fs::set_permissions(path.join("box/gen"), Permissions::from_mode(0o700)).unwrap();
+        std::fs::write(path.join("box/gen"), "evil").unwrap();
+        std::fs::set_permissions(path.join("box/gen"), Permissions::from_mode(0o500)).unwrap();
+        pool.release_prepared(dir, files);
+        assert_eq!(pool.prepared_len(), 0);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_can_prepare() {
+        let key = FileStoreKey::from_content(b"");
+        assert!(!can_prepare(&[]));
+        assert!(can_prepare(&[(PathBuf::from("a/b"), key.clone(), false)]));
+        assert!(!can_prepare(&[(PathBuf::from("../a"), key.clone(), false)]));
+        assert!(!can_prepare(&[(PathBuf::from("/a"), key, false)]));
+    }
+
+    #[test]
+    fn test_pool_capacity() {
+        let tmpdir = TempDir::new().unwrap();
+        let pool = SandboxPool::new(tmpdir.path(), 1).unwrap();
+        let dir1 = pool.acquire().unwrap();
+        let dir2 = pool.acquire().unwrap();
+        let path2 = dir2.path().to_owned();
+        pool.release(dir1);
+        pool.release(dir2);
+        assert_eq!(pool.len(), 1);
+        assert!(!path2.exists());
+    }
+}
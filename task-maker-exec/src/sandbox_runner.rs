@@ -10,7 +10,14 @@ use crate::RawSandboxResult;
 pub trait SandboxRunner: Send + Sync {
     /// Spawn a sandbox with the provided configuration, set the PID as soon as possible and wait
     /// for it to exit. Parse the outcome of the sandbox and return it.
-    fn run(&self, config: SandboxConfiguration, pid: Arc<AtomicU32>) -> RawSandboxResult;
+    ///
+    /// When `background` is set the sandboxed process should run with a lower CPU and I/O priority.
+    fn run(
+        &self,
+        config: SandboxConfiguration,
+        pid: Arc<AtomicU32>,
+        background: bool,
+    ) -> RawSandboxResult;
 }
 
 /// A fake sandbox that don't actually spawn anything and always return an error.
@@ -18,7 +25,12 @@ pub trait SandboxRunner: Send + Sync {
 pub struct ErrorSandboxRunner;
 
 impl SandboxRunner for ErrorSandboxRunner {
-    fn run(&self, _config: SandboxConfiguration, _pid: Arc<AtomicU32>) -> RawSandboxResult {
+    fn run(
+        &self,
+        _config: SandboxConfiguration,
+        _pid: Arc<AtomicU32>,
+        _background: bool,
+    ) -> RawSandboxResult {
         RawSandboxResult::Error("Nope".to_owned())
     }
 }
@@ -29,7 +41,12 @@ impl SandboxRunner for ErrorSandboxRunner {
 pub struct SuccessSandboxRunner;
 
 impl SandboxRunner for SuccessSandboxRunner {
-    fn run(&self, _config: SandboxConfiguration, _pid: Arc<AtomicU32>) -> RawSandboxResult {
+    fn run(
+        &self,
+        _config: SandboxConfiguration,
+        _pid: Arc<AtomicU32>,
+        _background: bool,
+    ) -> RawSandboxResult {
         RawSandboxResult::Success(SandboxExecutionResult {
             status: ExitStatus::ExitCode(0),
             resource_usage: ResourceUsage {
@@ -50,7 +67,12 @@ pub struct UnsafeSandboxRunner;
 
 #[cfg(test)]
 impl SandboxRunner for UnsafeSandboxRunner {
-    fn run(&self, config: SandboxConfiguration, _pid: Arc<AtomicU32>) -> RawSandboxResult {
+    fn run(
+        &self,
+        config: SandboxConfiguration,
+        _pid: Arc<AtomicU32>,
+        _background: bool,
+    ) -> RawSandboxResult {
         use std::fs::{File, OpenOptions};
         use std::process::Stdio;
 
@@ -85,7 +107,12 @@ impl SandboxRunner for UnsafeSandboxRunner {
 }
 
 impl<S: SandboxRunner> SandboxRunner for Arc<S> {
-    fn run(&self, conf: SandboxConfiguration, pid: Arc<AtomicU32>) -> RawSandboxResult {
-        self.as_ref().run(conf, pid)
+    fn run(
+        &self,
+        conf: SandboxConfiguration,
+        pid: Arc<AtomicU32>,
+        background: bool,
+    ) -> RawSandboxResult {
+        self.as_ref().run(conf, pid, background)
     }
 }
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -10,14 +12,20 @@ use uuid::Uuid;
 
 use task_maker_cache::{Cache, CacheResult};
 use task_maker_dag::{
-    CacheMode, DagPriority, ExecutionDAGData, ExecutionGroup, ExecutionGroupUuid, ExecutionResult,
-    ExecutionUuid, FileUuid, Priority, WorkerUuid, HIGH_PRIORITY,
+    CacheMode, DagPriority, EvaluationUuid, ExecutionDAGData, ExecutionGroup, ExecutionGroupUuid,
+    ExecutionResult, ExecutionUuid, FileUuid, Priority, ProvidedFile, WorkerUuid, HIGH_PRIORITY,
 };
 use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
 
+use crate::admin::{AdminRequest, AdminResponse, AdminStatus, EvaluationStatus};
+use crate::autoscale::{Autoscaler, QueueMetrics};
 use crate::executor::{
     ExecutionDAGWatchSet, ExecutorStatus, ExecutorWorkerStatus, WorkerCurrentJobStatus, WorkerJob,
+    WorkerTelemetry,
 };
+use crate::gc::GcConfig;
+use crate::persistent_queue::{PersistentQueue, QueuedEvaluation};
+use crate::quota::{ClientQuotas, QuotaExceeded};
 use crate::worker_manager::WorkerManagerInMessage;
 
 pub type ClientUuid = Uuid;
@@ -42,12 +50,28 @@ pub(crate) enum SchedulerInMessage {
         /// The set of callbacks the client is interested in.
         callbacks: Box<ExecutionDAGWatchSet>,
     },
+    /// A client extended the DAG it's evaluating.
+    ExtendDAG {
+        /// The identifier of the client.
+        client: ClientUuid,
+        /// The new part of the DAG.
+        dag: Box<ExecutionDAGData>,
+        /// The set of callbacks of the new part the client is interested in.
+        callbacks: Box<ExecutionDAGWatchSet>,
+    },
     /// A client has been disconnected, all the executions of that client should be removed and the
     /// involved workers stopped.
     ClientDisconnected {
         /// The identifier of the client.
         client: ClientUuid,
     },
+    /// The connection with a client has been lost without the client asking to stop. If the
+    /// evaluation can be resumed it's kept running, otherwise this is the same as
+    /// `ClientDisconnected`.
+    ClientDetached {
+        /// The identifier of the client.
+        client: ClientUuid,
+    },
     /// A new file of the DAG of a client is ready.
     FileReady {
         /// The identifier of the client that owns the file.
@@ -72,6 +96,15 @@ pub(crate) enum SchedulerInMessage {
         uuid: WorkerUuid,
         /// The name of the worker.
         name: String,
+        /// The telemetry sent by the worker.
+        telemetry: WorkerTelemetry,
+    },
+    /// A worker reported the fingerprints of the toolchains of some system commands.
+    WorkerToolchains {
+        /// The uuid of the worker.
+        worker: WorkerUuid,
+        /// The fingerprints of the toolchains, indexed by the command.
+        toolchains: HashMap<PathBuf, String>,
     },
     /// A previously ready worker is not ready anymore.
     WorkerDisconnected {
@@ -80,6 +113,13 @@ pub(crate) enum SchedulerInMessage {
     },
     /// The executor is asking for the status of the scheduler.
     Status { client: ClientUuid },
+    /// An administrator of the server sent a request.
+    Admin {
+        /// The identifier of the client of the administrator.
+        client: ClientUuid,
+        /// The request to fulfill.
+        request: AdminRequest,
+    },
     /// The executor is asking to exit.
     Exit,
 }
@@ -118,10 +158,23 @@ pub(crate) enum SchedulerExecutorMessageData {
         /// This file is urgent, it should be sent to the client ASAP.
         urgent: bool,
     },
+    /// The client has reattached to a detached evaluation.
+    Reattached {
+        /// The identifier of the evaluation.
+        evaluation: EvaluationUuid,
+        /// Whether the evaluation has been recovered after a restart of the server.
+        recovered: bool,
+    },
     /// The evaluation has been completed.
     EvaluationDone,
+    /// The evaluation has been cancelled by an administrator of the server.
+    EvaluationCancelled,
+    /// The evaluation has been rejected because the client exceeded one of its quotas.
+    QuotaExceeded(QuotaExceeded),
     /// The status of the execution.
     Status { status: ExecutorStatus<Duration> },
+    /// The response to a request of an administrator.
+    Admin { response: AdminResponse },
 }
 
 /// The actual message sent from the Scheduler to an Executor. Since all the fields of the
@@ -137,6 +190,11 @@ struct ConnectedWorker {
     name: String,
     /// The job the worker is currently working on, with the instant of the start.
     current_job: Option<(ClientUuid, ExecutionGroupUuid, Instant)>,
+    /// The jobs sent to the worker in the same batch of the current one, that the worker will run
+    /// after it, in this order.
+    queued_jobs: VecDeque<(ClientUuid, ExecutionGroupUuid)>,
+    /// The last telemetry sent by the worker.
+    telemetry: WorkerTelemetry,
 }
 
 /// The scheduling information about the DAG of a single client.
@@ -144,6 +202,8 @@ struct ConnectedWorker {
 struct SchedulerClientData {
     /// The name of the client.
     name: String,
+    /// The priority of the evaluation of this client.
+    priority: DagPriority,
     /// The DAGs the scheduler is currently working on.
     dag: ExecutionDAGData,
     /// The set of callbacks the client is interested in.
@@ -152,9 +212,14 @@ struct SchedulerClientData {
     /// become ready.
     input_of: HashMap<FileUuid, HashSet<ExecutionGroupUuid>>,
     /// The set of executions that are ready to be executed. Note that this is not the same as
-    /// `Scheduler::ready_execs`, it's just a fast lookup for known if there is still something to
-    /// do for this client.
+    /// `ready_execs`, it's just a fast lookup for known if there is still something to do for this
+    /// client.
     ready_groups: HashSet<ExecutionGroupUuid>,
+    /// The priority queue of the ready tasks of this client, waiting for the workers.
+    ready_execs: BinaryHeap<(DagPriority, Priority, ExecutionGroupUuid)>,
+    /// Value of `Scheduler::num_assigned` when a job of this client was last assigned to a worker.
+    /// Used to interleave the jobs of the clients with the same share.
+    last_assigned: u64,
     /// The set of executions that are currently running in a worker.
     running_groups: HashSet<ExecutionGroupUuid>,
     /// The list of tasks waiting for some dependencies, each with the list of missing files, when a
@@ -163,6 +228,19 @@ struct SchedulerClientData {
     /// The list of known [`FileStoreHandle`](../task_maker_store/struct.FileStoreHandle.html)s.
     /// Storing them here prevents the `FileStore` from flushing them away.
     file_handles: HashMap<FileUuid, FileStoreHandle>,
+    /// The files already ready, with whether they have been produced successfully. The executions
+    /// added by the extensions of the DAG don't wait for them, or are skipped if they failed.
+    done_files: HashMap<FileUuid, bool>,
+    /// Whether the client has lost the connection, and the evaluation is kept running waiting for
+    /// the client to reattach.
+    detached: bool,
+    /// The detached evaluation this client has reattached to. The ready executions of this client
+    /// are not assigned to the workers until the detached evaluation ends, so that its running
+    /// executions are not run twice but replayed from the cache.
+    resumes: Option<ClientUuid>,
+    /// Whether this evaluation has been recovered from the persistent queue after a restart of the
+    /// server.
+    recovered: bool,
 }
 
 impl SchedulerClientData {
@@ -174,22 +252,53 @@ impl SchedulerClientData {
     ) -> SchedulerClientData {
         SchedulerClientData {
             name,
+            priority: dag.config.priority,
             dag,
             callbacks,
             input_of: HashMap::new(),
             ready_groups: HashSet::new(),
+            ready_execs: BinaryHeap::new(),
+            last_assigned: 0,
             running_groups: HashSet::new(),
             missing_deps: HashMap::new(),
             file_handles: HashMap::new(),
+            done_files: HashMap::new(),
+            detached: false,
+            resumes: None,
+            recovered: false,
+        }
+    }
+
+    /// Build the lookup table of the dependencies, marking as ready the executions without any.
+    fn init_dependencies(&mut self) {
+        for group in self.dag.execution_groups.values() {
+            let missing_dep = self.missing_deps.entry(group.uuid).or_default();
+            for exec in &group.executions {
+                for input in exec.dependencies() {
+                    let entry = self.input_of.entry(input).or_default();
+                    entry.insert(group.uuid);
+                    missing_dep.insert(input);
+                }
+            }
+            // if this execution does not have any dependency, schedule it immediately
+            if missing_dep.is_empty() {
+                self.missing_deps.remove(&group.uuid);
+                self.ready_groups.insert(group.uuid);
+                for exec in &group.executions {
+                    self.ready_execs
+                        .push((self.priority, exec.priority, group.uuid));
+                }
+            }
         }
     }
 
     /// True if the client has completed all the executions and there are no more ready nor running
-    /// ones.
+    /// ones, and it won't extend the DAG anymore.
     fn is_done(&self) -> bool {
         self.ready_groups.is_empty()
             && self.running_groups.is_empty()
             && self.missing_deps.is_empty()
+            && self.callbacks.extensions == 0
     }
 }
 
@@ -213,13 +322,38 @@ pub(crate) struct Scheduler {
     /// Sender of the messages to the WorkerManager, aka the messages to the workers.
     worker_manager: Sender<WorkerManagerInMessage>,
 
-    /// The priority queue of the ready tasks, waiting for the workers.
-    ready_execs: BinaryHeap<(DagPriority, Priority, ExecutionGroupUuid, ClientUuid)>,
     /// The data about the clients currently working.
     clients: HashMap<ClientUuid, SchedulerClientData>,
 
     /// The list of the workers that are either ready for some work or already working on a job.
     connected_workers: HashMap<WorkerUuid, ConnectedWorker>,
+    /// The total number of jobs assigned to the workers so far.
+    num_assigned: u64,
+    /// The number of executions found in the cache so far.
+    cache_hits: usize,
+    /// The number of executions not found in the cache and sent to the workers so far.
+    cache_misses: usize,
+    /// The hook to fire when the ready queue crosses the autoscaling thresholds, if any.
+    autoscaler: Option<Autoscaler>,
+    /// Where the resumable evaluations are persisted to survive a restart, if enabled.
+    queue: Option<PersistentQueue>,
+    /// The workers that won't receive new jobs, and that will be asked to exit as soon as they
+    /// complete the current one.
+    draining_workers: HashSet<WorkerUuid>,
+    /// The limits on the resources used by each client.
+    quotas: ClientQuotas,
+    /// The configuration of the garbage collection of the stores, if enabled.
+    gc: Option<GcConfig>,
+    /// When the last round of garbage collection happened.
+    last_gc: Instant,
+    /// The maximum number of batchable jobs sent to a worker in a single message.
+    batch_size: usize,
+    /// Whether the fingerprints of the toolchains known by the cache have been reported by a worker
+    /// since the scheduler started. Until then the cache is not used, since an upgraded toolchain
+    /// would still match the old entries, and no job is assigned.
+    toolchains_checked: bool,
+    /// The worker that has been asked the fingerprints of the toolchains, if any.
+    toolchains_asked: Option<WorkerUuid>,
 }
 
 #[allow(clippy::unnecessary_wraps)]
@@ -234,6 +368,7 @@ impl Scheduler {
         executor: Sender<SchedulerExecutorMessage>,
         worker_manager: Sender<WorkerManagerInMessage>,
     ) -> Scheduler {
+        let toolchains_checked = cache.toolchain_commands().is_empty();
         Scheduler {
             file_store,
             cache,
@@ -241,16 +376,57 @@ impl Scheduler {
             executor,
             worker_manager,
 
-            ready_execs: BinaryHeap::new(),
             clients: HashMap::new(),
 
             connected_workers: HashMap::new(),
+            num_assigned: 0,
+            cache_hits: 0,
+            cache_misses: 0,
+            autoscaler: None,
+            queue: None,
+            draining_workers: HashSet::new(),
+            quotas: ClientQuotas::default(),
+            gc: None,
+            last_gc: Instant::now(),
+            batch_size: 1,
+            toolchains_checked,
+            toolchains_asked: None,
         }
     }
 
+    /// Fire the hooks of this `Autoscaler` when the ready queue crosses its thresholds.
+    pub fn set_autoscaler(&mut self, autoscaler: Autoscaler) {
+        self.autoscaler = Some(autoscaler);
+    }
+
+    /// Persist the resumable evaluations in this queue, recovering the ones already present when
+    /// the scheduler starts.
+    pub fn set_queue(&mut self, queue: PersistentQueue) {
+        self.queue = Some(queue);
+    }
+
+    /// Limit the resources used by each client with these quotas.
+    pub fn set_quotas(&mut self, quotas: ClientQuotas) {
+        self.quotas = quotas;
+    }
+
+    /// Periodically remove the files not referenced by the evaluations from the stores of the
+    /// server and of the workers.
+    pub fn set_gc(&mut self, config: GcConfig) {
+        self.gc = Some(config);
+    }
+
+    /// Send to a worker up to this number of batchable jobs in a single message, saving the round
+    /// trip between the jobs. A value of 1 disables the batches.
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
     /// Run the `Scheduler` listening for incoming messages and blocking util the scheduler is
     /// asked to exit. When the scheduler exits it will turn down the worker manager too.
     pub fn run(mut self) -> Result<(), Error> {
+        self.recover_queue()
+            .context("Failed to recover the queued evaluations")?;
         while let Ok(message) = self.receiver.recv() {
             match message {
                 SchedulerInMessage::Exit => {
@@ -265,6 +441,14 @@ impl Scheduler {
                     self.handle_evaluate_dag(client, *dag, *callbacks)
                         .context("Failed to handle EvaluateDAG")?;
                 }
+                SchedulerInMessage::ExtendDAG {
+                    client,
+                    dag,
+                    callbacks,
+                } => {
+                    self.handle_extend_dag(client, *dag, *callbacks)
+                        .context("Failed to handle ExtendDAG")?;
+                }
                 SchedulerInMessage::FileReady {
                     client,
                     uuid,
@@ -281,10 +465,18 @@ impl Scheduler {
                     self.handle_worker_result(worker, result, outputs)
                         .context("Failed to handle WorkerResult")?;
                 }
-                SchedulerInMessage::WorkerConnected { uuid, name } => {
-                    self.handle_worker_connected(uuid, name)
+                SchedulerInMessage::WorkerConnected {
+                    uuid,
+                    name,
+                    telemetry,
+                } => {
+                    self.handle_worker_connected(uuid, name, telemetry)
                         .context("Failed to handle WorkerConnected")?;
                 }
+                SchedulerInMessage::WorkerToolchains { worker, toolchains } => {
+                    self.handle_worker_toolchains(worker, toolchains)
+                        .context("Failed to handle WorkerToolchains")?;
+                }
                 SchedulerInMessage::WorkerDisconnected { uuid } => {
                     self.handle_worker_disconnected(uuid)
                         .context("Failed to handle WorkerDisconnected")?;
@@ -293,10 +485,18 @@ impl Scheduler {
                     self.handle_client_disconnected(client)
                         .context("Failed to handle ClientDisconnected")?;
                 }
+                SchedulerInMessage::ClientDetached { client } => {
+                    self.handle_client_detached(client)
+                        .context("Failed to handle ClientDetached")?;
+                }
                 SchedulerInMessage::Status { client } => {
                     self.handle_status_request(client)
                         .context("Failed to handle Status")?;
                 }
+                SchedulerInMessage::Admin { client, request } => {
+                    self.handle_admin_request(client, request)
+                        .context("Failed to handle Admin")?;
+                }
             }
         }
         debug!("Scheduler exiting");
@@ -313,31 +513,73 @@ impl Scheduler {
         dag: ExecutionDAGData,
         callbacks: ExecutionDAGWatchSet,
     ) -> Result<(), Error> {
-        info!("Client '{}' asked to evaluate a new DAG", client.name);
+        info!(
+            "Client '{}' asked to evaluate a new DAG with priority {}",
+            client.name, dag.config.priority
+        );
+        let evaluation_id = dag.config.evaluation_id;
+        if let Some(limit) = self.quotas.max_evaluations {
+            // the detached evaluation the client is reattaching to does not count
+            let evaluations = self
+                .clients
+                .values()
+                .filter(|c| c.name == client.name && !c.is_done())
+                .filter(|c| evaluation_id.is_none() || c.dag.config.evaluation_id != evaluation_id)
+                .count();
+            if evaluations >= limit {
+                warn!(
+                    "Client '{}' has already {} evaluations, rejecting the new one",
+                    client.name, evaluations
+                );
+                let message = SchedulerExecutorMessageData::QuotaExceeded(
+                    QuotaExceeded::TooManyEvaluations { limit },
+                );
+                self.executor
+                    .send((client.uuid, message))
+                    .context("Failed to send QuotaExceeded to the executor")?;
+                return Ok(());
+            }
+        }
         // build the scheduler structures, insert the client in the list of working
         // clients and schedule all the already cached executions.
-        let dag_priority = dag.config.priority;
-        let mut client_data = SchedulerClientData::new(client.name, dag, callbacks);
-        for group in client_data.dag.execution_groups.values() {
-            let missing_dep = client_data.missing_deps.entry(group.uuid).or_default();
-            for exec in &group.executions {
-                for input in exec.dependencies() {
-                    let entry = client_data.input_of.entry(input).or_default();
-                    entry.insert(group.uuid);
-                    missing_dep.insert(input);
-                }
+        if let (Some(queue), Some(evaluation_id)) = (&self.queue, evaluation_id) {
+            let evaluation = QueuedEvaluation {
+                client: client.clone(),
+                dag: dag.clone(),
+                callbacks: callbacks.clone(),
+            };
+            if let Err(e) = queue.save(evaluation_id, &evaluation) {
+                warn!("Failed to persist evaluation {}: {:?}", evaluation_id, e);
             }
-            // if this execution does not have any dependency, schedule it immediately
-            if missing_dep.is_empty() {
-                client_data.missing_deps.remove(&group.uuid);
-                client_data.ready_groups.insert(group.uuid);
-                for exec in &group.executions {
-                    self.ready_execs
-                        .push((dag_priority, exec.priority, group.uuid, client.uuid));
-                }
+        }
+        let mut client_data = SchedulerClientData::new(client.name, dag, callbacks);
+        // reattach to the detached evaluation with the same identifier, if any: it won't start
+        // new executions, and the ones of this client will wait for the running ones to complete
+        if let Some(evaluation_id) = evaluation_id {
+            let detached = self
+                .clients
+                .iter_mut()
+                .find(|(_, c)| c.detached && c.dag.config.evaluation_id == Some(evaluation_id));
+            if let Some((detached_uuid, detached)) = detached {
+                info!(
+                    "Client '{}' reattached to evaluation {}",
+                    client_data.name, evaluation_id
+                );
+                detached.ready_execs.clear();
+                detached.ready_groups.clear();
+                client_data.resumes = Some(*detached_uuid);
+                let message = SchedulerExecutorMessageData::Reattached {
+                    evaluation: evaluation_id,
+                    recovered: detached.recovered,
+                };
+                self.executor
+                    .send((client.uuid, message))
+                    .context("Failed to send Reattached to the executor")?;
             }
         }
+        client_data.init_dependencies();
         self.clients.insert(client.uuid, client_data);
+        self.remove_stalled_detached();
         // the client may have sent and empty DAG
         self.check_completion(client.uuid)?;
 
@@ -346,6 +588,70 @@ impl Scheduler {
         Ok(())
     }
 
+    /// Handle the client request to extend the DAG it's evaluating. The new executions that depend
+    /// on files already produced don't wait for them, and the ones that depend on failed files are
+    /// skipped.
+    fn handle_extend_dag(
+        &mut self,
+        client_uuid: ClientUuid,
+        dag: ExecutionDAGData,
+        callbacks: ExecutionDAGWatchSet,
+    ) -> Result<(), Error> {
+        let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
+            client
+        } else {
+            warn!("Client extended the DAG but it's gone");
+            return Ok(());
+        };
+        info!(
+            "Client '{}' extended its DAG with {} executions",
+            client.name,
+            dag.execution_groups.len()
+        );
+        client.callbacks.extensions = client.callbacks.extensions.saturating_sub(1);
+        client.callbacks.extensions += callbacks.extensions;
+        client.callbacks.executions.extend(callbacks.executions);
+        client.callbacks.files.extend(callbacks.files);
+        client.callbacks.urgent_files.extend(callbacks.urgent_files);
+        client.dag.provided_files.extend(dag.provided_files);
+        let mut failed_deps = HashSet::new();
+        for (group_uuid, group) in dag.execution_groups {
+            let mut missing_dep = HashSet::new();
+            let mut failed = false;
+            for exec in &group.executions {
+                for input in exec.dependencies() {
+                    client.input_of.entry(input).or_default().insert(group_uuid);
+                    match client.done_files.get(&input) {
+                        Some(true) => {}
+                        Some(false) => {
+                            failed_deps.insert(input);
+                            failed = true;
+                        }
+                        None => {
+                            missing_dep.insert(input);
+                        }
+                    }
+                }
+            }
+            if missing_dep.is_empty() && !failed {
+                client.ready_groups.insert(group_uuid);
+                client
+                    .ready_execs
+                    .push((HIGH_PRIORITY, group.priority(), group_uuid));
+            } else {
+                client.missing_deps.insert(group_uuid, missing_dep);
+            }
+            client.dag.execution_groups.insert(group_uuid, group);
+        }
+        for file in failed_deps {
+            self.skip_dependents(client_uuid, file)?;
+        }
+        self.schedule_cached()?;
+        self.assign_jobs()?;
+        self.check_completion(client_uuid)?;
+        Ok(())
+    }
+
     /// Handle the message of a file being ready.
     fn handle_file_ready(
         &mut self,
@@ -370,14 +676,14 @@ impl Scheduler {
         result: Vec<ExecutionResult>,
         outputs: HashMap<FileUuid, FileStoreHandle>,
     ) -> Result<(), Error> {
-        let worker = match self.connected_workers.remove(&worker) {
+        let mut worker = match self.connected_workers.remove(&worker) {
             Some(worker) => worker,
             None => {
                 warn!("Unknown worker {} completed a job", worker);
                 return Ok(());
             }
         };
-        let (client_uuid, group_uuid) = match worker.current_job {
+        let (client_uuid, group_uuid) = match worker.current_job.take() {
             Some((client, exec, _)) => (client, exec),
             None => {
                 warn!(
@@ -387,6 +693,13 @@ impl Scheduler {
                 return Ok(());
             }
         };
+        let worker_name = format!("{} ({})", worker.name, worker.uuid);
+        // the worker continues with the next job of its batch without asking for more work
+        if let Some((next_client, next_group)) = worker.queued_jobs.pop_front() {
+            worker.current_job = Some((next_client, next_group, Instant::now()));
+            self.notify_started(next_client, next_group, worker.uuid);
+            self.connected_workers.insert(worker.uuid, worker);
+        }
         let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
             client
         } else {
@@ -397,8 +710,8 @@ impl Scheduler {
         };
         let group = client.dag.execution_groups[&group_uuid].clone();
         info!(
-            "Worker {:?} completed execution group {}",
-            worker, group.uuid
+            "Worker {} completed execution group {}",
+            worker_name, group.uuid
         );
         if group.executions.len() != result.len() {
             // FIXME: this is a pretty bad way to handle this error, it should never happen but if
@@ -408,45 +721,110 @@ impl Scheduler {
         }
         client.running_groups.remove(&group_uuid);
         self.exec_completed(client_uuid, &group, result, outputs, false)?;
+        self.remove_stalled_detached();
         self.assign_jobs()?;
         self.check_completion(client_uuid)?;
         Ok(())
     }
 
     /// Handle the connection of a worker.
-    fn handle_worker_connected(&mut self, uuid: WorkerUuid, name: String) -> Result<(), Error> {
+    fn handle_worker_connected(
+        &mut self,
+        uuid: WorkerUuid,
+        name: String,
+        telemetry: WorkerTelemetry,
+    ) -> Result<(), Error> {
         info!("Worker {} ({}) connected", name, uuid);
+        if self.draining_workers.contains(&uuid) {
+            info!("Worker {} ({}) drained, asking it to exit", name, uuid);
+            self.worker_manager
+                .send(WorkerManagerInMessage::ExitWorker { worker: uuid })
+                .map_err(|e| anyhow!("Failed to send ExitWorker to worker: {:?}", e))?;
+            return Ok(());
+        }
         self.connected_workers.insert(
             uuid,
             ConnectedWorker {
                 uuid,
                 name,
                 current_job: None,
+                queued_jobs: VecDeque::new(),
+                telemetry,
             },
         );
+        self.ask_toolchains(uuid)?;
         self.assign_jobs()?;
         Ok(())
     }
 
+    /// Ask a worker the fingerprints of the toolchains known by the cache, unless they have already
+    /// been checked or asked to another worker.
+    fn ask_toolchains(&mut self, worker: WorkerUuid) -> Result<(), Error> {
+        if self.toolchains_checked || self.toolchains_asked.is_some() {
+            return Ok(());
+        }
+        let commands = self
+            .cache
+            .toolchain_commands()
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        self.worker_manager
+            .send(WorkerManagerInMessage::AskToolchains { worker, commands })
+            .map_err(|e| anyhow!("Failed to send AskToolchains to worker: {:?}", e))?;
+        self.toolchains_asked = Some(worker);
+        Ok(())
+    }
+
+    /// Handle the fingerprints of the toolchains reported by a worker. If they are the ones asked,
+    /// the cache can be used from now on.
+    fn handle_worker_toolchains(
+        &mut self,
+        worker: WorkerUuid,
+        toolchains: HashMap<PathBuf, String>,
+    ) -> Result<(), Error> {
+        debug!("Worker {} reported {} toolchains", worker, toolchains.len());
+        self.cache.update_toolchains(toolchains);
+        if self.toolchains_asked == Some(worker) {
+            self.toolchains_asked = None;
+            self.toolchains_checked = true;
+            self.schedule_cached()?;
+            self.assign_jobs()?;
+        }
+        Ok(())
+    }
+
     /// Handle the disconnection of a worker.
     fn handle_worker_disconnected(&mut self, uuid: WorkerUuid) -> Result<(), Error> {
         info!("Worker {} disconnected", uuid);
+        self.draining_workers.remove(&uuid);
+        if self.toolchains_asked == Some(uuid) {
+            self.toolchains_asked = None;
+        }
         if let Some(worker) = self.connected_workers.remove(&uuid) {
-            // reschedule the job if the worker failed
-            if let Some((client_uuid, job, _)) = worker.current_job {
+            // reschedule the jobs if the worker failed
+            let jobs = worker
+                .current_job
+                .map(|(client_uuid, job, _)| (client_uuid, job))
+                .into_iter()
+                .chain(worker.queued_jobs);
+            for (client_uuid, job) in jobs {
                 let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
                     client
                 } else {
                     warn!("Worker was doing something for a gone client");
-                    return Ok(());
+                    continue;
                 };
                 let priority = client.dag.execution_groups[&job].priority();
-                self.ready_execs
-                    .push((HIGH_PRIORITY, priority, job, client_uuid));
+                client.ready_execs.push((HIGH_PRIORITY, priority, job));
                 client.ready_groups.insert(job);
                 client.running_groups.remove(&job);
             }
         }
+        // the toolchains are asked to another worker, if the one asked left without answering
+        if let Some(&worker) = self.connected_workers.keys().next() {
+            self.ask_toolchains(worker)?;
+        }
         Ok(())
     }
 
@@ -463,17 +841,16 @@ impl Scheduler {
                     .context("Failed to send EvaluationDone to the executor")?;
             }
         }
-        self.clients.remove(&client_uuid);
-        let mut remaining = BinaryHeap::new();
-        while let Some((dag_priority, priority, exec, client)) = self.ready_execs.pop() {
-            if self.clients.contains_key(&client) {
-                remaining.push((dag_priority, priority, exec, client));
-            }
-        }
-        self.ready_execs = remaining;
+        self.remove_client(client_uuid);
         // stop the jobs that are still running in the workers
         for (uuid, worker) in self.connected_workers.iter() {
-            if let Some((owner, exec, _)) = worker.current_job {
+            // the queued jobs are killed by the worker if it already started them
+            let jobs = worker
+                .current_job
+                .map(|(owner, exec, _)| (owner, exec))
+                .into_iter()
+                .chain(worker.queued_jobs.iter().copied());
+            for (owner, exec) in jobs {
                 if owner == client_uuid {
                     warn!(
                         "Worker {} is doing {} owned by disconnected client, killing",
@@ -491,6 +868,145 @@ impl Scheduler {
         Ok(())
     }
 
+    /// Handle the loss of the connection with a client. If the evaluation has an identifier, it is
+    /// kept running so that the client can reattach to it later.
+    fn handle_client_detached(&mut self, client_uuid: ClientUuid) -> Result<(), Error> {
+        let client = match self.clients.get_mut(&client_uuid) {
+            Some(client) if !client.is_done() => client,
+            _ => return self.handle_client_disconnected(client_uuid),
+        };
+        let evaluation_id = match client.dag.config.evaluation_id {
+            Some(evaluation_id) => evaluation_id,
+            None => return self.handle_client_disconnected(client_uuid),
+        };
+        info!(
+            "Client {} detached, evaluation {} continues",
+            client_uuid, evaluation_id
+        );
+        client.detached = true;
+        self.remove_stalled_detached();
+        self.assign_jobs()?;
+        Ok(())
+    }
+
+    /// Remove the detached evaluations that cannot make any more progress: they don't have ready
+    /// or running executions, and the files that the disconnected client was supposed to send
+    /// will never arrive.
+    fn remove_stalled_detached(&mut self) {
+        let stalled: Vec<_> = self
+            .clients
+            .iter()
+            .filter(|(_, client)| {
+                client.detached
+                    && client.ready_groups.is_empty()
+                    && client.running_groups.is_empty()
+            })
+            .map(|(uuid, _)| *uuid)
+            .collect();
+        for client_uuid in stalled {
+            info!("Detached evaluation of client {} ended", client_uuid);
+            self.remove_client(client_uuid);
+        }
+    }
+
+    /// Remove a client from the scheduler, removing its evaluation from the persistent queue if no
+    /// other client is working on it.
+    fn remove_client(&mut self, client_uuid: ClientUuid) {
+        let client = match self.clients.remove(&client_uuid) {
+            Some(client) => client,
+            None => return,
+        };
+        if let (Some(queue), Some(evaluation_id)) = (&self.queue, client.dag.config.evaluation_id) {
+            let in_use = self
+                .clients
+                .values()
+                .any(|c| c.dag.config.evaluation_id == Some(evaluation_id));
+            if !in_use {
+                queue.remove(evaluation_id);
+            }
+        }
+        self.maybe_collect_garbage();
+    }
+
+    /// Start a round of garbage collection, if enough time passed since the last one. The files
+    /// referenced by the evaluations still known to the scheduler are kept.
+    fn maybe_collect_garbage(&mut self) {
+        let config = match &self.gc {
+            Some(config) if self.last_gc.elapsed() >= config.interval => config,
+            _ => return,
+        };
+        self.last_gc = Instant::now();
+        let mut keep = HashSet::new();
+        for client in self.clients.values() {
+            keep.extend(client.file_handles.values().map(|h| h.key().clone()));
+            keep.extend(client.dag.provided_files.values().map(|f| match f {
+                ProvidedFile::Content { key, .. } => key.clone(),
+                ProvidedFile::LocalFile { key, .. } => key.clone(),
+            }));
+        }
+        match self
+            .file_store
+            .collect_garbage(config.retention_size, &keep)
+        {
+            Ok(removed) => info!(
+                "Garbage collection removed {} KiB, {} files are referenced",
+                removed / 1024,
+                keep.len()
+            ),
+            Err(e) => warn!("Garbage collection of the store failed: {:?}", e),
+        }
+        let message = WorkerManagerInMessage::CollectGarbage {
+            keep: Arc::new(keep.into_iter().collect()),
+        };
+        if let Err(e) = self.worker_manager.send(message) {
+            warn!("Cannot tell the workers to collect garbage: {:?}", e);
+        }
+    }
+
+    /// Recover the evaluations persisted before the restart of the server. They are kept running
+    /// as detached evaluations, waiting for their clients to reattach.
+    fn recover_queue(&mut self) -> Result<(), Error> {
+        let evaluations = match &self.queue {
+            Some(queue) => queue.load()?,
+            None => return Ok(()),
+        };
+        for QueuedEvaluation {
+            client,
+            dag,
+            callbacks,
+        } in evaluations
+        {
+            info!(
+                "Recovered evaluation {:?} of client '{}'",
+                dag.config.evaluation_id, client.name
+            );
+            let provided_files: Vec<_> = dag
+                .provided_files
+                .iter()
+                .map(|(uuid, file)| match file {
+                    ProvidedFile::Content { key, .. } => (*uuid, key.clone()),
+                    ProvidedFile::LocalFile { key, .. } => (*uuid, key.clone()),
+                })
+                .collect();
+            let mut client_data = SchedulerClientData::new(client.name, dag, callbacks);
+            client_data.detached = true;
+            client_data.recovered = true;
+            client_data.init_dependencies();
+            self.clients.insert(client.uuid, client_data);
+            // the files the client sent before the restart are already in the store, the others
+            // will be sent again when the client reattaches
+            for (uuid, key) in provided_files {
+                if let Some(handle) = self.file_store.get(&key) {
+                    self.handle_file_ready(client.uuid, uuid, handle)?;
+                }
+            }
+        }
+        self.remove_stalled_detached();
+        self.schedule_cached()?;
+        self.assign_jobs()?;
+        Ok(())
+    }
+
     /// Handle the status request of a client.
     fn handle_status_request(&mut self, client_uuid: ClientUuid) -> Result<(), Error> {
         let mut ready_execs = 0;
@@ -500,30 +1016,11 @@ impl Scheduler {
             waiting_execs += client.missing_deps.len();
         }
         let status = ExecutorStatus {
-            connected_workers: self
-                .connected_workers
-                .values()
-                .map(|worker| ExecutorWorkerStatus {
-                    uuid: worker.uuid,
-                    name: worker.name.clone(),
-                    current_job: worker.current_job.as_ref().and_then(
-                        |(client_uuid, exec_uuid, start)| {
-                            let client = self.clients.get(client_uuid)?;
-                            let exec = &client.dag.execution_groups[exec_uuid];
-                            Some(WorkerCurrentJobStatus {
-                                job: exec.description.clone(),
-                                client: ClientInfo {
-                                    uuid: *client_uuid,
-                                    name: client.name.clone(),
-                                },
-                                duration: start.elapsed(),
-                            })
-                        },
-                    ),
-                })
-                .collect(),
+            connected_workers: self.workers_status(),
             ready_execs,
             waiting_execs,
+            cache_hits: self.cache_hits,
+            cache_misses: self.cache_misses,
         };
 
         if let Err(e) = self
@@ -535,6 +1032,112 @@ impl Scheduler {
         Ok(())
     }
 
+    /// Handle a request of an administrator of the server.
+    fn handle_admin_request(
+        &mut self,
+        client_uuid: ClientUuid,
+        request: AdminRequest,
+    ) -> Result<(), Error> {
+        info!("Client {} sent admin request {:?}", client_uuid, request);
+        let response = match request {
+            AdminRequest::Status => AdminResponse::Status(AdminStatus {
+                workers: self.workers_status(),
+                draining_workers: self.draining_workers.iter().cloned().collect(),
+                evaluations: self
+                    .clients
+                    .iter()
+                    .map(|(uuid, client)| EvaluationStatus {
+                        client: ClientInfo {
+                            uuid: *uuid,
+                            name: client.name.clone(),
+                        },
+                        evaluation_id: client.dag.config.evaluation_id,
+                        priority: client.priority,
+                        detached: client.detached,
+                        recovered: client.recovered,
+                        ready_execs: client.ready_groups.len(),
+                        running_execs: client.running_groups.len(),
+                        waiting_execs: client.missing_deps.len(),
+                    })
+                    .collect(),
+            }),
+            AdminRequest::CancelEvaluation(id) => {
+                let targets: Vec<_> = self
+                    .clients
+                    .iter()
+                    .filter(|(uuid, client)| {
+                        **uuid == id || client.dag.config.evaluation_id == Some(id)
+                    })
+                    .map(|(uuid, _)| *uuid)
+                    .collect();
+                if targets.is_empty() {
+                    AdminResponse::Error(format!("Unknown evaluation {}", id))
+                } else {
+                    for target in &targets {
+                        self.executor
+                            .send((*target, SchedulerExecutorMessageData::EvaluationCancelled))
+                            .context("Failed to send EvaluationCancelled to the executor")?;
+                        self.handle_client_disconnected(*target)?;
+                    }
+                    self.assign_jobs()?;
+                    AdminResponse::Ok(format!("Cancelled {} evaluation(s)", targets.len()))
+                }
+            }
+            AdminRequest::DrainWorker(worker) => {
+                let target = self
+                    .connected_workers
+                    .values()
+                    .find(|w| w.uuid.to_string() == worker || w.name == worker)
+                    .map(|w| (w.uuid, w.current_job.is_none()));
+                match target {
+                    Some((uuid, idle)) => {
+                        self.draining_workers.insert(uuid);
+                        // an idle worker can exit immediately, the others when their job is done
+                        if idle {
+                            let worker = self.connected_workers.remove(&uuid).unwrap();
+                            self.handle_worker_connected(uuid, worker.name, worker.telemetry)?;
+                        }
+                        AdminResponse::Ok(format!("Draining worker {}", uuid))
+                    }
+                    None => AdminResponse::Error(format!("Unknown worker {}", worker)),
+                }
+            }
+        };
+        if let Err(e) = self.executor.send((
+            client_uuid,
+            SchedulerExecutorMessageData::Admin { response },
+        )) {
+            warn!("Cannot send the admin response to the client: {:?}", e);
+        }
+        Ok(())
+    }
+
+    /// The status of the connected workers.
+    fn workers_status(&self) -> Vec<ExecutorWorkerStatus<Duration>> {
+        self.connected_workers
+            .values()
+            .map(|worker| ExecutorWorkerStatus {
+                uuid: worker.uuid,
+                name: worker.name.clone(),
+                current_job: worker.current_job.as_ref().and_then(
+                    |(client_uuid, exec_uuid, start)| {
+                        let client = self.clients.get(client_uuid)?;
+                        let exec = &client.dag.execution_groups[exec_uuid];
+                        Some(WorkerCurrentJobStatus {
+                            job: exec.description.clone(),
+                            client: ClientInfo {
+                                uuid: *client_uuid,
+                                name: client.name.clone(),
+                            },
+                            duration: start.elapsed(),
+                        })
+                    },
+                ),
+                telemetry: worker.telemetry.clone(),
+            })
+            .collect()
+    }
+
     /// Check if the client has completed the evaluation, if so tell the client we are done.
     fn check_completion(&self, client_uuid: ClientUuid) -> Result<(), Error> {
         let client = if let Some(client) = self.clients.get(&client_uuid) {
@@ -561,6 +1164,14 @@ impl Scheduler {
                 file, client_uuid
             )
         })?;
+        if let Some(client) = self.clients.get_mut(&client_uuid) {
+            client.done_files.insert(file, false);
+        }
+        self.skip_dependents(client_uuid, file)
+    }
+
+    /// Skip all the executions waiting for a failed file (even transitively).
+    fn skip_dependents(&mut self, client_uuid: ClientUuid, file: FileUuid) -> Result<(), Error> {
         let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
             client
         } else {
@@ -616,6 +1227,7 @@ impl Scheduler {
             // client is gone, dont worry to much about it
             return Ok(());
         };
+        client.done_files.insert(file, true);
         if !client.input_of.contains_key(&file) {
             return Ok(());
         }
@@ -625,12 +1237,9 @@ impl Scheduler {
                 files.remove(&file);
                 if files.is_empty() {
                     client.missing_deps.remove(group_uuid);
-                    self.ready_execs.push((
-                        HIGH_PRIORITY,
-                        group.priority(),
-                        *group_uuid,
-                        client_uuid,
-                    ));
+                    client
+                        .ready_execs
+                        .push((HIGH_PRIORITY, group.priority(), *group_uuid));
                     client.ready_groups.insert(*group_uuid);
                 }
             }
@@ -705,7 +1314,12 @@ impl Scheduler {
 
         let successful = result.iter().all(|r| r.status.is_success());
         let internal_error = result.iter().any(|r| r.status.is_internal_error());
-        if !from_cache && !internal_error {
+        let failure_cached = client
+            .dag
+            .config
+            .failure_cache_mode
+            .is_enabled(group.tag().as_ref());
+        if !from_cache && !internal_error && (successful || failure_cached) {
             self.cache_execution(client_uuid, group, outputs, result);
         }
         if successful {
@@ -758,44 +1372,53 @@ impl Scheduler {
     /// Look at all the ready executions and mark as completed all the ones that are inside the
     /// cache.
     fn schedule_cached(&mut self) -> Result<(), Error> {
-        let mut not_cached = BinaryHeap::new();
+        if !self.toolchains_checked {
+            return Ok(());
+        }
         let mut cached = Vec::new();
 
-        for (dag_priority, priority, group_uuid, client_uuid) in self.ready_execs.iter() {
-            let client = if let Some(client) = self.clients.get_mut(client_uuid) {
-                client
-            } else {
-                // client is gone, dont worry to much about it
-                continue;
-            };
-            let dag = &client.dag;
-            let cache_mode = &dag.config.cache_mode;
+        for (client_uuid, client) in self.clients.iter_mut() {
+            let cache_mode = &client.dag.config.cache_mode;
             // disable the cache for the execution
             if let CacheMode::Nothing = cache_mode {
-                not_cached.push((*dag_priority, *priority, *group_uuid, *client_uuid));
-                continue;
-            }
-            let group = dag.execution_groups[group_uuid].clone();
-            if !Scheduler::is_cacheable(&group, cache_mode) {
-                not_cached.push((*dag_priority, *priority, group.uuid, *client_uuid));
                 continue;
             }
-            let result = self
-                .cache
-                .get(&group, &client.file_handles, self.file_store.as_ref());
-            match result {
-                CacheResult::Hit { result, outputs } => {
-                    info!("Execution {} is a cache hit!", group.uuid);
-                    client.ready_groups.remove(&group.uuid);
-                    cached.push((*client_uuid, group, result, outputs));
+            let mut not_cached = BinaryHeap::new();
+            for (dag_priority, priority, group_uuid) in client.ready_execs.drain() {
+                let group = client.dag.execution_groups[&group_uuid].clone();
+                if !Scheduler::is_cacheable(&group, cache_mode) {
+                    not_cached.push((dag_priority, priority, group_uuid));
+                    continue;
                 }
-                CacheResult::Miss => {
-                    not_cached.push((*dag_priority, *priority, group.uuid, *client_uuid));
+                let result = self
+                    .cache
+                    .get(&group, &client.file_handles, self.file_store.as_ref());
+                match result {
+                    // the failures excluded from the cache are always executed again
+                    CacheResult::Hit { result, .. }
+                        if !result.iter().all(|r| r.status.is_success())
+                            && !client
+                                .dag
+                                .config
+                                .failure_cache_mode
+                                .is_enabled(group.tag().as_ref()) =>
+                    {
+                        not_cached.push((dag_priority, priority, group_uuid));
+                    }
+                    CacheResult::Hit { result, outputs } => {
+                        info!("Execution {} is a cache hit!", group.uuid);
+                        self.cache_hits += 1;
+                        client.ready_groups.remove(&group.uuid);
+                        cached.push((*client_uuid, group, result, outputs));
+                    }
+                    CacheResult::Miss => {
+                        not_cached.push((dag_priority, priority, group_uuid));
+                    }
                 }
             }
+            client.ready_execs = not_cached;
         }
 
-        self.ready_execs = not_cached;
         for (client, exec, result, outputs) in cached.into_iter() {
             self.exec_completed(client, &exec, result, outputs, true)?;
         }
@@ -804,72 +1427,959 @@ impl Scheduler {
     }
 
     /// Whether an execution is eligible to be fetch from the cache.
-    fn is_cacheable(group: &ExecutionGroup, cache_mode: &CacheMode) -> bool {
-        if let (CacheMode::Except(set), Some(tag)) = (cache_mode, group.tag().as_ref()) {
-            if set.contains(tag) {
-                return false;
+    pub(crate) fn is_cacheable(group: &ExecutionGroup, cache_mode: &CacheMode) -> bool {
+        cache_mode.is_enabled(group.tag().as_ref())
+    }
+
+    /// Select the client whose ready job should be assigned next, if any.
+    ///
+    /// The clients with the highest priority are always served first. The workers are then shared
+    /// fairly among the clients with the same priority: the client with the fewest running jobs
+    /// is served first, and ties are broken in favour of the client that waited the most since its
+    /// last assignment. This interleaves the ready executions of the clients, so that a huge DAG
+    /// does not starve the small ones sent later. The clients that already reached their limit of
+    /// running executions are skipped.
+    fn next_client(&self) -> Option<ClientUuid> {
+        let mut running: HashMap<&str, usize> = HashMap::new();
+        if self.quotas.max_running_execs.is_some() {
+            for client in self.clients.values() {
+                *running.entry(client.name.as_str()).or_default() += client.running_groups.len();
             }
         }
-        true
+        self.clients
+            .iter()
+            .filter(|(_, client)| !client.ready_execs.is_empty())
+            .filter(|(_, client)| match self.quotas.max_running_execs {
+                Some(limit) => running[client.name.as_str()] < limit,
+                None => true,
+            })
+            .filter(|(_, client)| match client.resumes {
+                Some(detached) => !self.clients.contains_key(&detached),
+                None => true,
+            })
+            .min_by_key(|(_, client)| {
+                (
+                    Reverse(client.priority),
+                    client.running_groups.len(),
+                    client.last_assigned,
+                )
+            })
+            .map(|(uuid, _)| *uuid)
     }
 
-    /// Give to each free worker a job from the ready executions.
+    /// Give to each free worker a job from the ready executions. When there are more ready
+    /// executions than free workers, a worker may receive a batch of batchable jobs, which it will
+    /// run one after the other.
     fn assign_jobs(&mut self) -> Result<(), Error> {
-        for (worker_uuid, worker) in self.connected_workers.iter_mut() {
-            if worker.current_job.is_some() {
-                continue;
-            }
-            let (_, _, group_uuid, client_uuid) = match self.ready_execs.pop() {
-                Some(exec) => exec,
-                None => break,
-            };
-            trace!("Assigning {} to worker {}", group_uuid, worker_uuid);
-            worker.current_job = Some((client_uuid, group_uuid, Instant::now()));
-            let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
-                client
-            } else {
-                // client is gone, dont worry to much about it
-                continue;
-            };
-            client.ready_groups.remove(&group_uuid);
-            client.running_groups.insert(group_uuid);
-            let group = &client.dag.execution_groups[&group_uuid];
-            let mut dep_keys: HashMap<FileUuid, FileStoreKey> = HashMap::new();
-            for exec in &group.executions {
-                for file in exec.dependencies() {
-                    let handle = client
-                        .file_handles
-                        .get(&file)
-                        .unwrap_or_else(|| panic!("Unknown file key of {}", file))
-                        .key()
-                        .clone();
-                    dep_keys.insert(file, handle);
+        // the ready executions haven't been looked up in the cache yet
+        if !self.toolchains_checked {
+            return Ok(());
+        }
+        let free_workers: Vec<_> = self
+            .connected_workers
+            .iter()
+            .filter(|(_, worker)| worker.current_job.is_none())
+            .map(|(uuid, _)| *uuid)
+            .collect();
+        let num_free = free_workers.len();
+        for (index, worker_uuid) in free_workers.into_iter().enumerate() {
+            let mut batch = Vec::new();
+            let mut jobs = Vec::new();
+            while jobs.len() < self.batch_size {
+                let client_uuid = match self.next_client() {
+                    Some(client) => client,
+                    None => break,
+                };
+                if !jobs.is_empty() {
+                    // do not leave the other free workers without a job
+                    let ready: usize = self.clients.values().map(|c| c.ready_execs.len()).sum();
+                    if ready < num_free - index || !self.next_is_batchable(client_uuid) {
+                        break;
+                    }
+                }
+                let (group_uuid, job) = self.pop_job(client_uuid, worker_uuid);
+                let batchable = job.group.is_batchable();
+                batch.push((client_uuid, group_uuid));
+                jobs.push(job);
+                if !batchable {
+                    break;
                 }
             }
-            let job = WorkerJob {
-                group: group.clone(),
-                dep_keys,
-            };
+            if jobs.is_empty() {
+                break;
+            }
+            trace!("Sending {} jobs to worker {}", jobs.len(), worker_uuid);
             self.worker_manager
                 .send(WorkerManagerInMessage::WorkerJob {
-                    worker: *worker_uuid,
-                    job,
+                    worker: worker_uuid,
+                    jobs,
                 })
                 .map_err(|e| anyhow!("Failed to send WorkerJob to worker: {:?}", e))?;
-            for exec in &group.executions {
-                if client.callbacks.executions.contains(&exec.uuid) {
-                    if let Err(e) = self.executor.send((
-                        client_uuid,
-                        SchedulerExecutorMessageData::ExecutionStarted {
-                            execution: exec.uuid,
-                            worker: *worker_uuid,
-                        },
-                    )) {
-                        warn!("Cannot tell the client the execution started: {:?}", e);
+            let mut batch = VecDeque::from(batch);
+            let (client_uuid, group_uuid) = batch.pop_front().unwrap();
+            if let Some(worker) = self.connected_workers.get_mut(&worker_uuid) {
+                worker.current_job = Some((client_uuid, group_uuid, Instant::now()));
+                worker.queued_jobs = batch;
+            }
+            self.notify_started(client_uuid, group_uuid, worker_uuid);
+        }
+        self.check_autoscaler();
+        Ok(())
+    }
+
+    /// Whether the next ready execution of the client can be added to a batch.
+    fn next_is_batchable(&self, client_uuid: ClientUuid) -> bool {
+        let client = &self.clients[&client_uuid];
+        match client.ready_execs.peek() {
+            Some((_, _, group_uuid)) => client.dag.execution_groups[group_uuid].is_batchable(),
+            None => false,
+        }
+    }
+
+    /// Take the next ready execution of the client, marking it as running in the worker. Returns
+    /// the job to send to the worker.
+    fn pop_job(
+        &mut self,
+        client_uuid: ClientUuid,
+        worker_uuid: WorkerUuid,
+    ) -> (ExecutionGroupUuid, WorkerJob) {
+        self.num_assigned += 1;
+        self.cache_misses += 1;
+        let client = self
+            .clients
+            .get_mut(&client_uuid)
+            .expect("Selected client is gone");
+        let (_, _, group_uuid) = client
+            .ready_execs
+            .pop()
+            .expect("Selected client has no ready executions");
+        client.last_assigned = self.num_assigned;
+        trace!("Assigning {} to worker {}", group_uuid, worker_uuid);
+        client.ready_groups.remove(&group_uuid);
+        client.running_groups.insert(group_uuid);
+        let group = &client.dag.execution_groups[&group_uuid];
+        let mut dep_keys: HashMap<FileUuid, FileStoreKey> = HashMap::new();
+        for exec in &group.executions {
+            for file in exec.dependencies() {
+                let handle = client
+                    .file_handles
+                    .get(&file)
+                    .unwrap_or_else(|| panic!("Unknown file key of {}", file))
+                    .key()
+                    .clone();
+                dep_keys.insert(file, handle);
+            }
+        }
+        let job = WorkerJob {
+            group: group.clone(),
+            dep_keys,
+        };
+        (group_uuid, job)
+    }
+
+    /// Tell the client that the watched executions of the group started on the worker.
+    fn notify_started(
+        &self,
+        client_uuid: ClientUuid,
+        group_uuid: ExecutionGroupUuid,
+        worker_uuid: WorkerUuid,
+    ) {
+        let client = match self.clients.get(&client_uuid) {
+            Some(client) => client,
+            None => return,
+        };
+        for exec in &client.dag.execution_groups[&group_uuid].executions {
+            if client.callbacks.executions.contains(&exec.uuid) {
+                if let Err(e) = self.executor.send((
+                    client_uuid,
+                    SchedulerExecutorMessageData::ExecutionStarted {
+                        execution: exec.uuid,
+                        worker: worker_uuid,
+                    },
+                )) {
+                    warn!("Cannot tell the client the execution started: {:?}", e);
+                }
+            }
+        }
+    }
+
+    /// Fire the autoscaling hook if the number of ready executions crossed one of the thresholds.
+    fn check_autoscaler(&mut self) {
+        let ready_execs = self
+            .clients
+            .values()
+            .map(|client| client.ready_groups.len())
+            .sum();
+        let event = match self.autoscaler.as_mut() {
+            Some(autoscaler) => autoscaler.check(ready_execs),
+            None => return,
+        };
+        if let (Some(event), Some(autoscaler)) = (event, self.autoscaler.as_ref()) {
+            autoscaler.fire(QueueMetrics {
+                event,
+                ready_execs,
+                waiting_execs: self
+                    .clients
+                    .values()
+                    .map(|client| client.missing_deps.len())
+                    .sum(),
+                running_execs: self
+                    .clients
+                    .values()
+                    .map(|client| client.running_groups.len())
+                    .sum(),
+                connected_workers: self.connected_workers.len(),
+                connected_clients: self.clients.len(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use tempfile::TempDir;
+
+    use task_maker_dag::{Execution, ExecutionCommand, ExecutionDAG};
+
+    use super::*;
+
+    /// Build a scheduler with an empty cache and file store, returning also the receivers of the
+    /// messages for the executor and the worker manager.
+    fn make_scheduler(
+        tmpdir: &TempDir,
+    ) -> (
+        Scheduler,
+        Receiver<SchedulerExecutorMessage>,
+        Receiver<WorkerManagerInMessage>,
+    ) {
+        let file_store = Arc::new(FileStore::new(tmpdir.path().join("store"), 1000, 1000).unwrap());
+        let cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let (_scheduler_tx, scheduler_rx) = channel();
+        let (executor_tx, executor_rx) = channel();
+        let (worker_manager_tx, worker_manager_rx) = channel();
+        let scheduler = Scheduler::new(
+            file_store,
+            cache,
+            scheduler_rx,
+            executor_tx,
+            worker_manager_tx,
+        );
+        (scheduler, executor_rx, worker_manager_rx)
+    }
+
+    /// Build a DAG with `num` independent executions.
+    fn make_dag(num: usize, priority: DagPriority) -> ExecutionDAGData {
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut().priority(priority);
+        for i in 0..num {
+            dag.add_execution(Execution::new(
+                format!("exec {}", i),
+                ExecutionCommand::system("true"),
+            ));
+        }
+        dag.data
+    }
+
+    /// Extract the owner of the jobs sent to the workers.
+    fn assigned_clients(
+        scheduler: &Scheduler,
+        receiver: &Receiver<WorkerManagerInMessage>,
+    ) -> Vec<ClientUuid> {
+        let mut owners = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            if let WorkerManagerInMessage::WorkerJob { worker, .. } = message {
+                let (client, _, _) = scheduler.connected_workers[&worker]
+                    .current_job
+                    .expect("Worker without job");
+                owners.push(client);
+            }
+        }
+        owners
+    }
+
+    #[test]
+    fn test_fair_share_between_clients() {
+        let tmpdir = TempDir::new().unwrap();
+        let (mut scheduler, _executor_rx, receiver) = make_scheduler(&tmpdir);
+        let big = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "big".into(),
+        };
+        let small = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "small".into(),
+        };
+        scheduler
+            .handle_evaluate_dag(big.clone(), make_dag(100, 0), Default::default())
+            .unwrap();
+        scheduler
+            .handle_evaluate_dag(small.clone(), make_dag(2, 0), Default::default())
+            .unwrap();
+        for i in 0..4 {
+            scheduler
+                .handle_worker_connected(
+                    Uuid::new_v4(),
+                    format!("worker {}", i),
+                    Default::default(),
+                )
+                .unwrap();
+        }
+        let owners = assigned_clients(&scheduler, &receiver);
+        assert_eq!(owners.len(), 4);
+        assert_eq!(owners.iter().filter(|c| **c == small.uuid).count(), 2);
+        assert_eq!(owners.iter().filter(|c| **c == big.uuid).count(), 2);
+    }
+
+    #[test]
+    fn test_priority_between_clients() {
+        let tmpdir = TempDir::new().unwrap();
+        let (mut scheduler, _executor_rx, receiver) = make_scheduler(&tmpdir);
+        let background = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "background".into(),
+        };
+        let urgent = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "urgent".into(),
+        };
+        scheduler
+            .handle_evaluate_dag(background.clone(), make_dag(10, -5), Default::default())
+            .unwrap();
+        scheduler
+            .handle_evaluate_dag(urgent.clone(), make_dag(3, 10), Default::default())
+            .unwrap();
+        for i in 0..4 {
+            scheduler
+                .handle_worker_connected(
+                    Uuid::new_v4(),
+                    format!("worker {}", i),
+                    Default::default(),
+                )
+                .unwrap();
+        }
+        let owners = assigned_clients(&scheduler, &receiver);
+        assert_eq!(owners.len(), 4);
+        assert_eq!(owners.iter().filter(|c| **c == urgent.uuid).count(), 3);
+        assert_eq!(owners.iter().filter(|c| **c == background.uuid).count(), 1);
+    }
+
+    #[test]
+    fn test_detach_and_resume() {
+        let tmpdir = TempDir::new().unwrap();
+        let (mut scheduler, executor_rx, receiver) = make_scheduler(&tmpdir);
+        let evaluation_id = Uuid::new_v4();
+        // the executions have different commands, so each of them has its own cache entry
+        let make_resumable_dag = || {
+            let mut dag = ExecutionDAG::new();
+            dag.config_mut().evaluation_id = Some(evaluation_id);
+            let mut callbacks = ExecutionDAGWatchSet::default();
+            let mut uuids = Vec::new();
+            for i in 0..3 {
+                let mut exec = Execution::new(
+                    format!("exec {}", i),
+                    ExecutionCommand::system(format!("command{}", i)),
+                );
+                exec.args(vec![format!("arg{}", i)]);
+                callbacks.executions.insert(exec.uuid);
+                uuids.push(exec.uuid);
+                dag.add_execution(exec);
+            }
+            (dag.data, callbacks, uuids)
+        };
+        let first = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "first".into(),
+        };
+        let second = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "second".into(),
+        };
+        let (dag, callbacks, _) = make_resumable_dag();
+        scheduler
+            .handle_evaluate_dag(first.clone(), dag, callbacks)
+            .unwrap();
+        let workers: Vec<_> = (0..2).map(|_| Uuid::new_v4()).collect();
+        for (i, worker) in workers.iter().enumerate() {
+            scheduler
+                .handle_worker_connected(*worker, format!("worker {}", i), Default::default())
+                .unwrap();
+        }
+        assert_eq!(assigned_clients(&scheduler, &receiver), vec![first.uuid; 2]);
+
+        // the connection is lost, but the evaluation keeps running
+        scheduler.handle_client_detached(first.uuid).unwrap();
+        assert!(scheduler.clients.contains_key(&first.uuid));
+
+        // the client reattaches: it waits for the running executions of the detached evaluation
+        let (dag, callbacks, uuids) = make_resumable_dag();
+        scheduler
+            .handle_evaluate_dag(second.clone(), dag, callbacks)
+            .unwrap();
+        let idle = Uuid::new_v4();
+        scheduler
+            .handle_worker_connected(idle, "worker 2".into(), Default::default())
+            .unwrap();
+        assert!(assigned_clients(&scheduler, &receiver).is_empty());
+        executor_rx.try_iter().for_each(drop);
+
+        let result = ExecutionResult {
+            status: task_maker_dag::ExecutionStatus::Success,
+            was_killed: false,
+            was_cached: false,
+            resources: Default::default(),
+            stdout: None,
+            stderr: None,
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
+        };
+        for worker in workers {
+            scheduler
+                .handle_worker_result(worker, vec![result.clone()], HashMap::new())
+                .unwrap();
+        }
+        // the detached evaluation is over: the results it already completed are replayed to the
+        // new client from the cache, while the execution it didn't start runs now
+        assert!(!scheduler.clients.contains_key(&first.uuid));
+        assert_eq!(scheduler.cache_hits, 2);
+        assert_eq!(assigned_clients(&scheduler, &receiver), vec![second.uuid]);
+        let done = |executor_rx: &Receiver<SchedulerExecutorMessage>| {
+            let mut done: Vec<_> = executor_rx
+                .try_iter()
+                .filter(|(client, _)| *client == second.uuid)
+                .filter_map(|(_, message)| match message {
+                    SchedulerExecutorMessageData::ExecutionDone { execution, result } => {
+                        Some((uuids.iter().position(|u| *u == execution).unwrap(), result))
                     }
+                    _ => None,
+                })
+                .map(|(index, result)| (index, result.was_cached))
+                .collect();
+            done.sort();
+            done
+        };
+        let replayed = done(&executor_rx);
+        assert_eq!(replayed.len(), 2);
+        assert!(replayed.iter().all(|(_, was_cached)| *was_cached));
+        assert!(!scheduler.clients[&second.uuid].is_done());
+
+        scheduler
+            .handle_worker_result(idle, vec![result], HashMap::new())
+            .unwrap();
+        let missing = (0..3).find(|i| replayed.iter().all(|(j, _)| i != j));
+        assert_eq!(done(&executor_rx), vec![(missing.unwrap(), false)]);
+        assert!(scheduler.clients[&second.uuid].is_done());
+    }
+
+    #[test]
+    fn test_failure_cache_mode() {
+        let tmpdir = TempDir::new().unwrap();
+        let (mut scheduler, _executor_rx, receiver) = make_scheduler(&tmpdir);
+        let failure = ExecutionResult {
+            status: task_maker_dag::ExecutionStatus::ReturnCode(1),
+            was_killed: false,
+            was_cached: false,
+            resources: Default::default(),
+            stdout: None,
+            stderr: None,
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
+        };
+        // evaluate a DAG with a failing execution, returning whether it has been executed
+        let evaluate = |scheduler: &mut Scheduler, failure_cache_mode: CacheMode| {
+            let mut dag = make_dag(1, 0);
+            dag.config.failure_cache_mode = failure_cache_mode;
+            let client = ClientInfo {
+                uuid: Uuid::new_v4(),
+                name: "client".into(),
+            };
+            scheduler
+                .handle_evaluate_dag(client, dag, Default::default())
+                .unwrap();
+            let worker = Uuid::new_v4();
+            scheduler
+                .handle_worker_connected(worker, "worker".into(), Default::default())
+                .unwrap();
+            let executed = !assigned_clients(scheduler, &receiver).is_empty();
+            if executed {
+                scheduler
+                    .handle_worker_result(worker, vec![failure.clone()], HashMap::new())
+                    .unwrap();
+            }
+            executed
+        };
+
+        // the failure is not stored in the cache
+        assert!(evaluate(&mut scheduler, CacheMode::Nothing));
+        assert!(evaluate(&mut scheduler, CacheMode::Everything));
+        // now it's in the cache, but it's not used if the failures should not be cached
+        assert!(evaluate(&mut scheduler, CacheMode::Nothing));
+        assert!(!evaluate(&mut scheduler, CacheMode::Everything));
+    }
+
+    #[test]
+    fn test_toolchains_checked_before_cache() {
+        let tmpdir = TempDir::new().unwrap();
+        let success = ExecutionResult {
+            status: task_maker_dag::ExecutionStatus::Success,
+            was_killed: false,
+            was_cached: false,
+            resources: Default::default(),
+            stdout: None,
+            stderr: None,
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
+        };
+        let toolchain = |version: &str| -> HashMap<PathBuf, String> {
+            [(PathBuf::from("true"), version.to_string())].into()
+        };
+        // evaluate a DAG, returning the worker, whether the execution has been sent to it and
+        // whether it has been asked the toolchains
+        let evaluate = |scheduler: &mut Scheduler, receiver: &Receiver<WorkerManagerInMessage>| {
+            let client = ClientInfo {
+                uuid: Uuid::new_v4(),
+                name: "client".into(),
+            };
+            scheduler
+                .handle_evaluate_dag(client, make_dag(1, 0), Default::default())
+                .unwrap();
+            let worker = Uuid::new_v4();
+            scheduler
+                .handle_worker_connected(worker, "worker".into(), Default::default())
+                .unwrap();
+            let messages: Vec<_> = receiver.try_iter().collect();
+            let executed = messages
+                .iter()
+                .any(|m| matches!(m, WorkerManagerInMessage::WorkerJob { .. }));
+            let asked = messages
+                .iter()
+                .any(|m| matches!(m, WorkerManagerInMessage::AskToolchains { .. }));
+            (worker, executed, asked)
+        };
+
+        {
+            let (mut scheduler, _executor_rx, receiver) = make_scheduler(&tmpdir);
+            let (worker, executed, asked) = evaluate(&mut scheduler, &receiver);
+            assert!(executed);
+            assert!(!asked);
+            scheduler
+                .handle_worker_toolchains(worker, toolchain("1.0"))
+                .unwrap();
+            scheduler
+                .handle_worker_result(worker, vec![success.clone()], HashMap::new())
+                .unwrap();
+        }
+
+        // after a restart the toolchain may have been upgraded, nothing is run before checking it
+        let (mut scheduler, _executor_rx, receiver) = make_scheduler(&tmpdir);
+        let (worker, executed, asked) = evaluate(&mut scheduler, &receiver);
+        assert!(!executed);
+        assert!(asked);
+        scheduler
+            .handle_worker_toolchains(worker, toolchain("2.0"))
+            .unwrap();
+        // the old entry is not used with the new toolchain
+        assert_eq!(assigned_clients(&scheduler, &receiver).len(), 1);
+        scheduler
+            .handle_worker_result(worker, vec![success], HashMap::new())
+            .unwrap();
+        let (_, executed, asked) = evaluate(&mut scheduler, &receiver);
+        assert!(!executed);
+        assert!(!asked);
+    }
+
+    #[test]
+    fn test_admin_drain_and_cancel() {
+        let tmpdir = TempDir::new().unwrap();
+        let (mut scheduler, executor_rx, receiver) = make_scheduler(&tmpdir);
+        let admin = Uuid::new_v4();
+        let client = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "client".into(),
+        };
+        let busy = Uuid::new_v4();
+        let idle = Uuid::new_v4();
+        scheduler
+            .handle_evaluate_dag(client.clone(), make_dag(1, 0), Default::default())
+            .unwrap();
+        scheduler
+            .handle_worker_connected(busy, "busy".into(), Default::default())
+            .unwrap();
+        scheduler
+            .handle_worker_connected(idle, "idle".into(), Default::default())
+            .unwrap();
+        assert_eq!(assigned_clients(&scheduler, &receiver), vec![client.uuid]);
+
+        scheduler
+            .handle_admin_request(admin, AdminRequest::DrainWorker("idle".into()))
+            .unwrap();
+        assert!(!scheduler.connected_workers.contains_key(&idle));
+        assert!(receiver
+            .try_iter()
+            .any(|m| matches!(m, WorkerManagerInMessage::ExitWorker { worker } if worker == idle)));
+
+        scheduler
+            .handle_admin_request(admin, AdminRequest::CancelEvaluation(client.uuid))
+            .unwrap();
+        assert!(scheduler.clients.is_empty());
+        assert!(receiver.try_iter().any(
+            |m| matches!(m, WorkerManagerInMessage::StopWorkerJob { worker, .. } if worker == busy)
+        ));
+        let messages: Vec<_> = executor_rx.try_iter().collect();
+        assert!(messages.iter().any(|(uuid, m)| *uuid == client.uuid
+            && matches!(m, SchedulerExecutorMessageData::EvaluationCancelled)));
+        assert!(messages.iter().any(|(uuid, m)| *uuid == admin
+            && matches!(
+                m,
+                SchedulerExecutorMessageData::Admin {
+                    response: AdminResponse::Ok(_)
                 }
+            )));
+    }
+
+    #[test]
+    fn test_client_quotas() {
+        let tmpdir = TempDir::new().unwrap();
+        let (mut scheduler, executor_rx, receiver) = make_scheduler(&tmpdir);
+        scheduler.set_quotas(ClientQuotas {
+            max_running_execs: Some(2),
+            max_evaluations: Some(2),
+            max_transferred_bytes: None,
+        });
+        let make_client = |name: &str| ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: name.into(),
+        };
+        let greedy = [make_client("greedy"), make_client("greedy")];
+        let rejected = make_client("greedy");
+        let other = make_client("other");
+        for client in &greedy {
+            scheduler
+                .handle_evaluate_dag(client.clone(), make_dag(10, 10), Default::default())
+                .unwrap();
+        }
+        scheduler
+            .handle_evaluate_dag(rejected.clone(), make_dag(10, 10), Default::default())
+            .unwrap();
+        scheduler
+            .handle_evaluate_dag(other.clone(), make_dag(10, 0), Default::default())
+            .unwrap();
+        assert!(!scheduler.clients.contains_key(&rejected.uuid));
+        assert!(executor_rx.try_iter().any(|(uuid, m)| uuid == rejected.uuid
+            && matches!(
+                m,
+                SchedulerExecutorMessageData::QuotaExceeded(QuotaExceeded::TooManyEvaluations {
+                    limit: 2
+                })
+            )));
+
+        for i in 0..6 {
+            scheduler
+                .handle_worker_connected(
+                    Uuid::new_v4(),
+                    format!("worker {}", i),
+                    Default::default(),
+                )
+                .unwrap();
+        }
+        let owners = assigned_clients(&scheduler, &receiver);
+        assert_eq!(owners.len(), 4);
+        let greedy_owners = owners
+            .iter()
+            .filter(|c| greedy.iter().any(|g| g.uuid == **c))
+            .count();
+        assert_eq!(greedy_owners, 2);
+        assert_eq!(owners.iter().filter(|c| **c == other.uuid).count(), 2);
+    }
+
+    #[test]
+    fn test_collect_garbage() {
+        let tmpdir = TempDir::new().unwrap();
+        let (mut scheduler, _executor_rx, receiver) = make_scheduler(&tmpdir);
+        scheduler.set_gc(GcConfig {
+            interval: Duration::ZERO,
+            retention_size: 0,
+        });
+        let store_content = |content: &[u8]| {
+            let key = FileStoreKey::from_content(content);
+            scheduler
+                .file_store
+                .store(&key, vec![content.to_vec()])
+                .unwrap();
+            key
+        };
+        let referenced = store_content(b"referenced");
+        let unreferenced = store_content(b"unreferenced");
+
+        let mut dag = ExecutionDAG::new();
+        let file = task_maker_dag::File::new("input");
+        dag.provide_content(file, b"referenced".to_vec());
+        let client = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "client".into(),
+        };
+        let done = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "done".into(),
+        };
+        scheduler
+            .handle_evaluate_dag(client, dag.data, Default::default())
+            .unwrap();
+        scheduler
+            .handle_evaluate_dag(done.clone(), make_dag(0, 0), Default::default())
+            .unwrap();
+        scheduler.handle_client_disconnected(done.uuid).unwrap();
+
+        assert!(scheduler.file_store.get(&referenced).is_some());
+        assert!(scheduler.file_store.get(&unreferenced).is_none());
+        let keep = receiver
+            .try_iter()
+            .find_map(|m| match m {
+                WorkerManagerInMessage::CollectGarbage { keep } => Some(keep),
+                _ => None,
+            })
+            .expect("The workers are not told to collect garbage");
+        assert_eq!(*keep, vec![referenced]);
+    }
+
+    #[test]
+    fn test_recover_queue_after_restart() {
+        let tmpdir = TempDir::new().unwrap();
+        let evaluation_id = Uuid::new_v4();
+        let make_resumable_dag = || {
+            let mut dag = make_dag(3, 0);
+            dag.config.evaluation_id = Some(evaluation_id);
+            dag
+        };
+        let client = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "client".into(),
+        };
+        {
+            let (mut scheduler, _executor_rx, _receiver) = make_scheduler(&tmpdir);
+            scheduler.set_queue(PersistentQueue::new(tmpdir.path().join("queue")).unwrap());
+            scheduler
+                .handle_evaluate_dag(client.clone(), make_resumable_dag(), Default::default())
+                .unwrap();
+            // the server crashes without the evaluation being completed
+        }
+
+        let (mut scheduler, executor_rx, receiver) = make_scheduler(&tmpdir);
+        scheduler.set_queue(PersistentQueue::new(tmpdir.path().join("queue")).unwrap());
+        scheduler.recover_queue().unwrap();
+        assert!(scheduler.clients[&client.uuid].detached);
+        scheduler
+            .handle_worker_connected(Uuid::new_v4(), "worker".into(), Default::default())
+            .unwrap();
+        assert_eq!(assigned_clients(&scheduler, &receiver), vec![client.uuid]);
+
+        let reattached = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "client".into(),
+        };
+        scheduler
+            .handle_evaluate_dag(reattached.clone(), make_resumable_dag(), Default::default())
+            .unwrap();
+        let recovered = executor_rx.try_iter().any(|(uuid, message)| {
+            uuid == reattached.uuid
+                && matches!(
+                    message,
+                    SchedulerExecutorMessageData::Reattached {
+                        recovered: true,
+                        ..
+                    }
+                )
+        });
+        assert!(recovered);
+
+        scheduler
+            .handle_client_disconnected(reattached.uuid)
+            .unwrap();
+        scheduler.handle_client_disconnected(client.uuid).unwrap();
+        let queue = PersistentQueue::new(tmpdir.path().join("queue")).unwrap();
+        assert!(queue.load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_batch_small_executions() {
+        let tmpdir = TempDir::new().unwrap();
+        let (mut scheduler, _executor_rx, receiver) = make_scheduler(&tmpdir);
+        scheduler.set_batch_size(4);
+        let mut dag = make_dag(10, 0);
+        // distinct arguments, so that a result is not a cache hit for the other executions
+        for (i, group) in dag.execution_groups.values_mut().enumerate() {
+            group.executions[0].batchable = true;
+            group.executions[0].args = vec![i.to_string()];
+        }
+        let client = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "client".into(),
+        };
+        scheduler
+            .handle_evaluate_dag(client, dag, Default::default())
+            .unwrap();
+        let workers = [Uuid::new_v4(), Uuid::new_v4()];
+        for (i, worker) in workers.iter().enumerate() {
+            scheduler
+                .handle_worker_connected(*worker, format!("worker {}", i), Default::default())
+                .unwrap();
+        }
+        let mut sizes = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            if let WorkerManagerInMessage::WorkerJob { jobs, .. } = message {
+                sizes.push(jobs.len());
             }
         }
-        Ok(())
+        assert_eq!(sizes, vec![4, 4]);
+
+        // the worker goes on with its batch without receiving new jobs
+        let result = ExecutionResult {
+            status: task_maker_dag::ExecutionStatus::Success,
+            was_killed: false,
+            was_cached: false,
+            resources: Default::default(),
+            stdout: None,
+            stderr: None,
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
+        };
+        scheduler
+            .handle_worker_result(workers[0], vec![result], HashMap::new())
+            .unwrap();
+        assert!(receiver.try_recv().is_err());
+        let worker = &scheduler.connected_workers[&workers[0]];
+        assert!(worker.current_job.is_some());
+        assert_eq!(worker.queued_jobs.len(), 2);
+
+        // the batch of a disconnected worker is rescheduled
+        scheduler.handle_worker_disconnected(workers[1]).unwrap();
+        let ready: usize = scheduler
+            .clients
+            .values()
+            .map(|c| c.ready_execs.len())
+            .sum();
+        assert_eq!(ready, 6);
+    }
+
+    #[test]
+    fn test_extend_dag() {
+        let tmpdir = TempDir::new().unwrap();
+        let (mut scheduler, executor_rx, _receiver) = make_scheduler(&tmpdir);
+        let mut dag = ExecutionDAG::new();
+        let mut ok = Execution::new("ok", ExecutionCommand::system("true"));
+        let mut fail = Execution::new("fail", ExecutionCommand::system("false"));
+        let ok_output = ok.stdout();
+        let fail_output = fail.stdout();
+        let ok_uuid = ok.uuid;
+        dag.add_execution(ok);
+        dag.add_execution(fail);
+        let client = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "client".into(),
+        };
+        let callbacks = ExecutionDAGWatchSet {
+            extensions: 2,
+            ..Default::default()
+        };
+        scheduler
+            .handle_evaluate_dag(client.clone(), dag.data, callbacks)
+            .unwrap();
+        let workers = [Uuid::new_v4(), Uuid::new_v4()];
+        for (i, worker) in workers.iter().enumerate() {
+            scheduler
+                .handle_worker_connected(*worker, format!("worker {}", i), Default::default())
+                .unwrap();
+        }
+        let result = |status| ExecutionResult {
+            status,
+            was_killed: false,
+            was_cached: false,
+            resources: Default::default(),
+            stdout: None,
+            stderr: None,
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
+        };
+        for worker in workers {
+            let (_, group, _) = scheduler.connected_workers[&worker].current_job.unwrap();
+            let group = &scheduler.clients[&client.uuid].dag.execution_groups[&group];
+            let status = if group.executions[0].uuid == ok_uuid {
+                task_maker_dag::ExecutionStatus::Success
+            } else {
+                task_maker_dag::ExecutionStatus::ReturnCode(1)
+            };
+            let content = group.executions[0].uuid.to_string().into_bytes();
+            let handle = scheduler
+                .file_store
+                .store(&FileStoreKey::from_content(&content), vec![content])
+                .unwrap();
+            let outputs = group.executions[0]
+                .outputs()
+                .into_iter()
+                .map(|output| (output, handle.clone()))
+                .collect();
+            scheduler
+                .handle_worker_result(worker, vec![result(status)], outputs)
+                .unwrap();
+        }
+        // the client is still going to extend the DAG
+        assert!(!scheduler.clients[&client.uuid].is_done());
+
+        // an extension that depends on an already produced file is ready immediately
+        let mut extension = ExecutionDAG::new();
+        let mut exec = Execution::new("after ok", ExecutionCommand::system("true"));
+        exec.stdin(ok_output);
+        extension.add_execution(exec);
+        scheduler
+            .handle_extend_dag(client.uuid, extension.data, Default::default())
+            .unwrap();
+        assert_eq!(scheduler.clients[&client.uuid].ready_groups.len(), 1);
+
+        // an extension that depends on a failed file is skipped
+        let mut extension = ExecutionDAG::new();
+        let mut exec = Execution::new("after fail", ExecutionCommand::system("true"));
+        exec.stdin(fail_output);
+        let skipped = exec.uuid;
+        extension.add_execution(exec);
+        let callbacks = ExecutionDAGWatchSet {
+            executions: [skipped].into_iter().collect(),
+            ..Default::default()
+        };
+        scheduler
+            .handle_extend_dag(client.uuid, extension.data, callbacks)
+            .unwrap();
+        assert!(executor_rx.try_iter().any(|(_, message)| matches!(
+            message,
+            SchedulerExecutorMessageData::ExecutionSkipped { execution } if execution == skipped
+        )));
+        assert!(scheduler.clients[&client.uuid].missing_deps.is_empty());
+
+        // the evaluation completes when the last execution does
+        let worker = Uuid::new_v4();
+        scheduler
+            .handle_worker_connected(worker, "worker".into(), Default::default())
+            .unwrap();
+        assert!(scheduler.connected_workers[&worker].current_job.is_some());
+        scheduler
+            .handle_worker_result(
+                worker,
+                vec![result(task_maker_dag::ExecutionStatus::Success)],
+                HashMap::new(),
+            )
+            .unwrap();
+        assert!(executor_rx
+            .try_iter()
+            .any(|(_, message)| matches!(message, SchedulerExecutorMessageData::EvaluationDone)));
     }
 }
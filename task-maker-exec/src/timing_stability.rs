@@ -0,0 +1,120 @@
+//! Detection of the conditions of a machine that make the measured times unreliable.
+//!
+//! A CPU that changes its frequency, that is throttled because it's too hot, or that is shared
+//! with more processes than it has cores, runs the same program in very different times.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Directory with the information about the CPUs of the machine.
+const CPU_ROOT: &str = "/sys/devices/system/cpu";
+
+/// The number of thermal throttling events at the previous check, `u64::MAX` if never checked.
+static LAST_THROTTLE_COUNT: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// The reasons why the times measured on this machine may be unreliable, empty if none is known.
+pub fn current_timing_warnings(load_average: Option<f64>) -> Vec<String> {
+    let cpus = cpu_dirs();
+    let governors: Vec<String> = cpus
+        .iter()
+        .filter_map(|dir| std::fs::read_to_string(dir.join("cpufreq/scaling_governor")).ok())
+        .map(|governor| governor.trim().to_string())
+        .collect();
+    let throttle_count: u64 = cpus
+        .iter()
+        .flat_map(|dir| {
+            ["core_throttle_count", "package_throttle_count"]
+                .iter()
+                .map(move |file| dir.join("thermal_throttle").join(file))
+        })
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .filter_map(|count| count.trim().parse::<u64>().ok())
+        .sum();
+    let last_count = LAST_THROTTLE_COUNT.swap(throttle_count, Ordering::Relaxed);
+    let throttled = last_count != u64::MAX && throttle_count > last_count;
+    timing_warnings(&governors, throttled, load_average, num_cpus::get())
+}
+
+/// The directories of the CPUs of the machine.
+fn cpu_dirs() -> Vec<std::path::PathBuf> {
+    let Ok(entries) = std::fs::read_dir(CPU_ROOT) else {
+        return vec![];
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix("cpu")
+                .is_some_and(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(|entry| Path::new(CPU_ROOT).join(entry.file_name()))
+        .collect()
+}
+
+/// The reasons why the times may be unreliable, given the frequency governors of the CPUs,
+/// whether the CPUs have been throttled since the last check, the load average and the number of
+/// CPUs of the machine.
+fn timing_warnings(
+    governors: &[String],
+    throttled: bool,
+    load_average: Option<f64>,
+    cpus: usize,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut scaling: Vec<&str> = governors
+        .iter()
+        .map(String::as_str)
+        .filter(|governor| *governor != "performance")
+        .collect();
+    scaling.sort_unstable();
+    scaling.dedup();
+    if !scaling.is_empty() {
+        warnings.push(format!(
+            "CPU frequency scaling governor is {}",
+            scaling.join(", ")
+        ));
+    }
+    if throttled {
+        warnings.push("CPU thermal throttling detected".to_string());
+    }
+    if let Some(load_average) = load_average {
+        if load_average > cpus as f64 {
+            warnings.push(format!(
+                "CPUs overcommitted, load average {:.2} with {} CPUs",
+                load_average, cpus
+            ));
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_warnings_stable() {
+        let governors = vec!["performance".to_string(); 4];
+        assert!(timing_warnings(&governors, false, Some(1.5), 4).is_empty());
+        assert!(timing_warnings(&[], false, None, 4).is_empty());
+    }
+
+    #[test]
+    fn test_timing_warnings_unstable() {
+        let governors = vec![
+            "powersave".to_string(),
+            "performance".to_string(),
+            "powersave".to_string(),
+        ];
+        let warnings = timing_warnings(&governors, true, Some(6.0), 4);
+        assert_eq!(
+            warnings,
+            vec![
+                "CPU frequency scaling governor is powersave",
+                "CPU thermal throttling detected",
+                "CPUs overcommitted, load average 6.00 with 4 CPUs",
+            ]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! The fingerprints of the toolchains of the system commands (e.g. the compilers and the
+//! interpreters), which are part of the cache keys of their executions.
+//!
+//! The fingerprint is computed by the worker that runs the command, hashing the output of
+//! `<command> --version` together with the version of the sandbox, so that upgrading either of
+//! them invalidates the cached executions. Running the command is not free, so each process
+//! computes the fingerprint of a command only once.
+
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The version of tabox the workers use for sandboxing the executions.
+const TABOX_VERSION: &str = env!("TABOX_VERSION");
+/// How long `<command> --version` can run before giving up on the fingerprint of the command.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The fingerprints computed by this process, `None` if the command couldn't tell its version.
+static FINGERPRINTS: OnceLock<Mutex<HashMap<PathBuf, Option<String>>>> = OnceLock::new();
+
+/// The fingerprint of the toolchain of a system command, computed only the first time the command
+/// is asked. Returns `None` if the command cannot be run.
+pub fn toolchain_fingerprint(command: &Path) -> Option<String> {
+    let fingerprints = FINGERPRINTS.get_or_init(Default::default);
+    if let Some(fingerprint) = fingerprints.lock().unwrap().get(command) {
+        return fingerprint.clone();
+    }
+    // the lock is not held while the command runs, a concurrent computation is just wasted
+    let fingerprint = compute_fingerprint(command);
+    fingerprints
+        .lock()
+        .unwrap()
+        .insert(command.to_owned(), fingerprint.clone());
+    fingerprint
+}
+
+/// The fingerprints of the toolchains of the commands that this process has never fingerprinted
+/// before, so that they are reported to the server only once.
+pub(crate) fn new_toolchain_fingerprints<'a, I>(commands: I) -> HashMap<PathBuf, String>
+where
+    I: IntoIterator<Item = &'a Path>,
+{
+    let fingerprints = FINGERPRINTS.get_or_init(Default::default);
+    commands
+        .into_iter()
+        .filter(|command| !fingerprints.lock().unwrap().contains_key(*command))
+        .filter_map(|command| Some((command.to_owned(), toolchain_fingerprint(command)?)))
+        .collect()
+}
+
+/// Run `<command> --version` and hash its output, together with the version of the sandbox.
+fn compute_fingerprint(command: &Path) -> Option<String> {
+    let mut output = tempfile::tempfile().ok()?;
+    let mut child = Command::new(command)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(output.try_clone().ok()?)
+        .stderr(output.try_clone().ok()?)
+        .spawn()
+        .map_err(|e| debug!("Cannot fingerprint {}: {}", command.display(), e))
+        .ok()?;
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if start.elapsed() < VERSION_TIMEOUT => {
+                thread::sleep(Duration::from_millis(10))
+            }
+            _ => {
+                warn!("{} --version didn't exit, ignoring it", command.display());
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+    let mut version = Vec::new();
+    output.seek(SeekFrom::Start(0)).ok()?;
+    output.read_to_end(&mut version).ok()?;
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(TABOX_VERSION.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(&version);
+    Some(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn fake_compiler(dir: &Path, name: &str, version: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\necho {}\n", version)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_toolchain_fingerprint() {
+        let tmpdir = TempDir::new().unwrap();
+        let old = fake_compiler(tmpdir.path(), "old", "1.0");
+        let new = fake_compiler(tmpdir.path(), "new", "2.0");
+        let same = fake_compiler(tmpdir.path(), "same", "1.0");
+        let old_fingerprint = toolchain_fingerprint(&old).unwrap();
+        assert_ne!(Some(&old_fingerprint), toolchain_fingerprint(&new).as_ref());
+        assert_eq!(
+            Some(&old_fingerprint),
+            toolchain_fingerprint(&same).as_ref()
+        );
+        assert_eq!(toolchain_fingerprint(&tmpdir.path().join("missing")), None);
+    }
+
+    #[test]
+    fn test_new_toolchain_fingerprints() {
+        let tmpdir = TempDir::new().unwrap();
+        let compiler = fake_compiler(tmpdir.path(), "compiler", "1.0");
+        let fingerprints = new_toolchain_fingerprints([compiler.as_path()]);
+        assert_eq!(
+            fingerprints.get(&compiler),
+            toolchain_fingerprint(&compiler).as_ref()
+        );
+        // already fingerprinted, even if the version changed in the meantime
+        fake_compiler(tmpdir.path(), "compiler", "2.0");
+        assert!(new_toolchain_fingerprints([compiler.as_path()]).is_empty());
+    }
+}
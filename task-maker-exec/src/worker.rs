@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fs::Permissions;
 use std::io::Read;
 use std::os::unix::fs::PermissionsExt;
@@ -16,15 +16,21 @@ use uuid::Uuid;
 use task_maker_dag::*;
 use task_maker_store::*;
 
-use crate::executor::WorkerJob;
+use crate::cpu_pinning::pin_current_thread;
+use crate::executor::{WorkerJob, WorkerTelemetry};
 use crate::proto::*;
 use crate::sandbox::{Sandbox, SandboxResult};
+use crate::sandbox_pool::SandboxPool;
 use crate::sandbox_runner::SandboxRunner;
+use crate::toolchain::{new_toolchain_fingerprints, toolchain_fingerprint};
 
 /// The information about the current job the worker is doing.
 struct WorkerCurrentJob {
-    /// Job currently waiting for, when there is a job running this should be `None`
+    /// Job currently waiting for, when there is a job running this should be `None`. The handles
+    /// include the dependencies of the queued jobs too.
     current_job: Option<(Box<WorkerJob>, HashMap<FileUuid, FileStoreHandle>)>,
+    /// The jobs sent in the same batch of the current one, they are run after it, in order.
+    queued_jobs: VecDeque<Box<WorkerJob>>,
     /// The currently running sandbox.
     current_sandboxes: Option<Vec<Sandbox>>,
     /// The dependencies that are missing and required for the execution start.
@@ -55,6 +61,19 @@ pub struct Worker {
     sandbox_runner: Arc<dyn SandboxRunner>,
     /// The join handle of the currently running sandbox, if any.
     current_sandbox_thread: Option<JoinHandle<()>>,
+    /// Factor the time limits are multiplied by, to compensate for the speed of this machine
+    /// compared to the reference hardware.
+    time_scale: f64,
+    /// The pool of pre-created sandbox directories, if enabled.
+    sandbox_pool: Option<Arc<SandboxPool>>,
+    /// The size the store is shrunk to when the server asks to collect garbage, if enabled.
+    gc_retention: Option<u64>,
+    /// The CPU the executions are pinned to, if enabled.
+    pinned_cpu: Option<usize>,
+    /// Whether to tell the server the files in the store, so that it sends them only if missing.
+    advertise_store: bool,
+    /// Sends the output files to the server, with the compression the server asked.
+    file_sender: ChannelFileSender,
 }
 
 /// An handle of the connection to the worker.
@@ -82,6 +101,7 @@ impl WorkerCurrentJob {
     fn new() -> WorkerCurrentJob {
         WorkerCurrentJob {
             current_job: None,
+            queued_jobs: VecDeque::new(),
             current_sandboxes: None,
             missing_deps: HashMap::new(),
             server_asked_files: None,
@@ -149,20 +169,130 @@ impl Worker {
             sandbox_path,
             sandbox_runner,
             current_sandbox_thread: None,
+            time_scale: 1.0,
+            sandbox_pool: None,
+            gc_retention: None,
+            pinned_cpu: None,
+            advertise_store: false,
+            file_sender: ChannelFileSender::default(),
         })
     }
 
+    /// Set the factor the time limits of the executions are multiplied by. A worker that is twice
+    /// as slow as the reference hardware should use a factor of 2. The resources used by the
+    /// executions are divided by the same factor, so the results are comparable between workers.
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = time_scale;
+    }
+
+    /// Take the sandbox directories from a pool of pre-created ones, instead of making a new one
+    /// for each execution.
+    pub fn set_sandbox_pool(&mut self, sandbox_pool: Arc<SandboxPool>) {
+        self.sandbox_pool = Some(sandbox_pool);
+    }
+
+    /// Take part in the garbage collection rounds of the server, shrinking the store down to this
+    /// size in bytes. Without it the requests of the server are ignored.
+    pub fn set_gc_retention(&mut self, retention_size: u64) {
+        self.gc_retention = Some(retention_size);
+    }
+
+    /// Run all the executions on this CPU only, the CPU is recorded in their results.
+    pub fn set_pinned_cpu(&mut self, cpu: usize) {
+        self.pinned_cpu = Some(cpu);
+    }
+
+    /// Tell the server which files are in the store when connecting and after each garbage
+    /// collection, so that it sends along with the jobs the dependencies the worker doesn't have.
+    /// This is useful only when the worker doesn't share the store with the server.
+    pub fn set_advertise_store(&mut self, advertise_store: bool) {
+        self.advertise_store = advertise_store;
+    }
+
+    /// Compress the files sent to the server, as negotiated when connecting to it.
+    pub fn set_transfer_compression(&mut self, compression: bool) {
+        self.file_sender = ChannelFileSender::new(compression);
+    }
+
+    /// Send to the server the keys of the files in the store, if enabled.
+    fn send_stored_files(&self) -> Result<(), Error> {
+        if self.advertise_store {
+            self.sender
+                .send(WorkerClientMessage::StoredFiles(self.file_store.keys()))
+                .context("Failed to send StoredFiles")?;
+        }
+        Ok(())
+    }
+
     /// Start the sandbox thread for the current job.
     fn start_job(&mut self) -> Result<(), Error> {
         self.current_sandbox_thread = Some(execute_job(
             self.current_job.clone(),
+            self.file_store.clone(),
             &self.sender,
+            self.file_sender,
             &self.sandbox_path,
+            self.sandbox_pool.as_ref(),
             self.sandbox_runner.clone(),
+            self.time_scale,
+            self.pinned_cpu,
         )?);
         Ok(())
     }
 
+    /// Prepare the worker for running a batch of jobs, asking the server the missing dependencies.
+    /// When all the dependencies are ready the first job is started, the others are run after it.
+    fn receive_jobs(&mut self, mut jobs: VecDeque<Box<WorkerJob>>) -> Result<(), Error> {
+        assert!(self.current_job.lock().unwrap().current_job.is_none());
+        self.wait_sandbox()?;
+        let mut missing_deps: HashMap<FileStoreKey, Vec<FileUuid>> = HashMap::new();
+        let mut handles = HashMap::new();
+        for job in &jobs {
+            for exec in &job.group.executions {
+                for input in exec.dependencies().iter() {
+                    let key = job
+                        .dep_keys
+                        .get(input)
+                        .ok_or(WorkerError::MissingDependencyKey { uuid: *input })?;
+                    match self.file_store.get(key) {
+                        None => {
+                            // ask the file only once
+                            if !missing_deps.contains_key(key) {
+                                self.sender
+                                    .send(WorkerClientMessage::AskFile(key.clone()))
+                                    .context("Failed to send AskFile to server")?;
+                            }
+                            missing_deps.entry(key.clone()).or_default().push(*input);
+                        }
+                        Some(handle) => {
+                            handles.insert(*input, handle);
+                        }
+                    }
+                }
+            }
+        }
+        debug!(
+            "Worker {} has {} dependencies in its store, asked {} files to the server",
+            self,
+            handles.len(),
+            missing_deps.len()
+        );
+        let job = jobs
+            .pop_front()
+            .ok_or_else(|| anyhow!("Empty batch of jobs"))?;
+        let job_ready = missing_deps.is_empty();
+        {
+            let mut current_job = self.current_job.lock().unwrap();
+            current_job.missing_deps = missing_deps;
+            current_job.current_job = Some((job, handles));
+            current_job.queued_jobs = jobs;
+        }
+        if job_ready {
+            self.start_job()?;
+        }
+        Ok(())
+    }
+
     /// Wait for the sandbox thread to exit.
     fn wait_sandbox(&mut self) -> Result<(), Error> {
         if let Some(join_handle) = self.current_sandbox_thread.take() {
@@ -178,49 +308,20 @@ impl Worker {
     #[allow(clippy::cognitive_complexity)]
     pub fn work(mut self) -> Result<(), Error> {
         trace!("Worker {} ready, asking for work", self);
+        self.send_stored_files()?;
         self.sender
-            .send(WorkerClientMessage::GetWork)
+            .send(WorkerClientMessage::GetWork(WorkerTelemetry::current()))
             .context("Failed to send GetWork")?;
 
         loop {
             match self.receiver.recv() {
                 Ok(WorkerServerMessage::Work(job)) => {
                     trace!("Worker {} got job: {:?}", self, job);
-                    assert!(self.current_job.lock().unwrap().current_job.is_none());
-                    self.wait_sandbox()?;
-                    let mut missing_deps: HashMap<FileStoreKey, Vec<FileUuid>> = HashMap::new();
-                    let mut handles = HashMap::new();
-                    for exec in &job.group.executions {
-                        for input in exec.dependencies().iter() {
-                            let key = job
-                                .dep_keys
-                                .get(input)
-                                .ok_or(WorkerError::MissingDependencyKey { uuid: *input })?;
-                            match self.file_store.get(key) {
-                                None => {
-                                    // ask the file only once
-                                    if !missing_deps.contains_key(key) {
-                                        self.sender
-                                            .send(WorkerClientMessage::AskFile(key.clone()))
-                                            .context("Failed to send AskFile to server")?;
-                                    }
-                                    missing_deps.entry(key.clone()).or_default().push(*input);
-                                }
-                                Some(handle) => {
-                                    handles.insert(*input, handle);
-                                }
-                            }
-                        }
-                    }
-                    let job_ready = missing_deps.is_empty();
-                    {
-                        let mut current_job = self.current_job.lock().unwrap();
-                        current_job.missing_deps = missing_deps;
-                        current_job.current_job = Some((job, handles));
-                    }
-                    if job_ready {
-                        self.start_job()?;
-                    }
+                    self.receive_jobs(VecDeque::from(vec![job]))?;
+                }
+                Ok(WorkerServerMessage::WorkBatch(jobs)) => {
+                    trace!("Worker {} got a batch of {} jobs", self, jobs.len());
+                    self.receive_jobs(jobs.into_iter().map(Box::new).collect())?;
                 }
                 Ok(WorkerServerMessage::ProvideFile(key)) => {
                     info!("Server sent file {:?}", key);
@@ -231,10 +332,11 @@ impl Worker {
                         .with_context(|| format!("Failed to store server-provided file {}", key))?;
                     let should_start = {
                         let mut job = self.current_job.lock().unwrap();
-                        let uuids = job
-                            .missing_deps
-                            .remove(&key)
-                            .ok_or_else(|| anyhow!("Server sent a not required dependency"))?;
+                        // the dependencies of the next job are sent before the job itself
+                        let uuids = match job.missing_deps.remove(&key) {
+                            Some(uuids) => uuids,
+                            None => continue,
+                        };
                         for uuid in uuids {
                             job.current_job
                                 .as_mut()
@@ -248,6 +350,30 @@ impl Worker {
                         self.start_job()?;
                     }
                 }
+                Ok(WorkerServerMessage::CollectGarbage(keep)) => {
+                    if let Some(retention_size) = self.gc_retention {
+                        let keep = keep.into_iter().collect();
+                        match self.file_store.collect_garbage(retention_size, &keep) {
+                            Ok(removed) => {
+                                info!("Garbage collection removed {} KiB", removed / 1024)
+                            }
+                            Err(e) => warn!("Garbage collection of the store failed: {:?}", e),
+                        }
+                        self.send_stored_files()?;
+                    }
+                }
+                Ok(WorkerServerMessage::Toolchains(commands)) => {
+                    let toolchains = commands
+                        .into_iter()
+                        .filter_map(|command| {
+                            let fingerprint = toolchain_fingerprint(&command)?;
+                            Some((command, fingerprint))
+                        })
+                        .collect();
+                    self.sender
+                        .send(WorkerClientMessage::Toolchains(toolchains))
+                        .context("Failed to send Toolchains")?;
+                }
                 Ok(WorkerServerMessage::Exit) => {
                     info!("Worker {} ({}) is asked to exit", self.name, self.uuid);
                     break;
@@ -304,116 +430,181 @@ impl Worker {
     }
 }
 
+/// The sandboxes of the current job, ready to be run, with the directory of its FIFOs and the
+/// channel where the list of files the server is missing will be sent.
+type PreparedJob = (
+    Box<WorkerJob>,
+    Vec<Sandbox>,
+    Option<TempDir>,
+    Receiver<Vec<FileUuid>>,
+);
+
 /// Spawn a new thread that will start the sandbox and will send the results back to the server.
+/// If `cpu` is set the thread, and so all the sandboxes it spawns, is pinned to that CPU. The jobs
+/// queued after the current one are run by the same thread, one after the other.
+#[allow(clippy::too_many_arguments)]
 fn execute_job(
     current_job: Arc<Mutex<WorkerCurrentJob>>,
+    file_store: Arc<FileStore>,
     sender: &ChannelSender<WorkerClientMessage>,
+    file_sender: ChannelFileSender,
     sandbox_path: &Path,
+    sandbox_pool: Option<&Arc<SandboxPool>>,
     runner: Arc<dyn SandboxRunner>,
+    time_scale: f64,
+    cpu: Option<usize>,
 ) -> Result<JoinHandle<()>, Error> {
-    let (job, sandboxes, fifo_dir, server_asked_files) = {
-        let mut current_job = current_job.lock().unwrap();
-        let job = current_job
-            .current_job
-            .as_ref()
-            .ok_or_else(|| anyhow!("Worker job is gone"))?;
-        let mut boxes = Vec::new();
-        let group = &job.0.group;
-        let fifo_dir = if group.fifo.is_empty() {
-            None
-        } else {
-            let fifo_dir = TempDir::new_in(sandbox_path).with_context(|| {
-                format!(
-                    "Failed to create temporary directory in {}",
-                    sandbox_path.display()
-                )
-            })?;
-            for fifo in &group.fifo {
-                let path = fifo_dir
-                    .path()
-                    .join(fifo.sandbox_path().file_name().unwrap());
-                nix::unistd::mkfifo(&path, nix::sys::stat::Mode::S_IRWXU)
-                    .with_context(|| format!("Failed to create FIFO at {}", path.display()))?;
-            }
-            Some(fifo_dir)
-        };
-        let keep_sandboxes = group.config().keep_sandboxes;
-        for exec in &group.executions {
-            let mut sandbox = Sandbox::new(
-                sandbox_path,
-                exec,
-                &job.1,
-                fifo_dir.as_ref().map(|d| d.path().to_owned()),
-            )?;
-            if keep_sandboxes {
-                sandbox.keep()?;
-            }
-            boxes.push(sandbox);
-        }
-        let job = job.0.clone();
-        current_job.current_sandboxes = Some(boxes.clone());
-        let (sender, receiver) = channel();
-        current_job.server_asked_files = Some(sender);
-        (job, boxes, fifo_dir, receiver)
-    };
+    let prepared = prepare_job(&current_job, sandbox_path, sandbox_pool, time_scale)?;
     let sender = sender.clone();
-    let description = job.group.description.clone();
+    let sandbox_path = sandbox_path.to_owned();
+    let sandbox_pool = sandbox_pool.cloned();
+    let description = prepared.0.group.description.clone();
     let join_handle = std::thread::Builder::new()
         .name(format!("Sandbox group manager for {}", description))
         .spawn(move || {
-            sandbox_group_manager(
-                current_job,
-                *job,
-                sender,
-                server_asked_files,
-                sandboxes,
-                runner,
-                fifo_dir,
-            )
-            .with_context(|| format!("Sandbox group for {} failed", description))
-            // FIXME: find a better way to propagate the error to the server
-            .unwrap();
+            let cpu = cpu.filter(|&cpu| match pin_current_thread(cpu) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("Cannot pin {} to CPU {}: {:?}", description, cpu, e);
+                    false
+                }
+            });
+            let mut next = Some(prepared);
+            while let Some((job, sandboxes, fifo_dir, server_asked_files)) = next.take() {
+                let description = job.group.description.clone();
+                let has_next = sandbox_group_manager(
+                    current_job.clone(),
+                    file_store.as_ref(),
+                    *job,
+                    sender.clone(),
+                    file_sender,
+                    server_asked_files,
+                    sandboxes,
+                    runner.clone(),
+                    fifo_dir,
+                    time_scale,
+                    cpu,
+                )
+                .with_context(|| format!("Sandbox group for {} failed", description))
+                // FIXME: find a better way to propagate the error to the server
+                .unwrap();
+                if has_next {
+                    next = Some(
+                        prepare_job(
+                            &current_job,
+                            &sandbox_path,
+                            sandbox_pool.as_ref(),
+                            time_scale,
+                        )
+                        .with_context(|| format!("Failed to prepare the job after {}", description))
+                        .unwrap(),
+                    );
+                }
+            }
         })?;
     Ok(join_handle)
 }
 
+/// Create the sandboxes, and the FIFOs, of the current job of the worker.
+fn prepare_job(
+    current_job: &Arc<Mutex<WorkerCurrentJob>>,
+    sandbox_path: &Path,
+    sandbox_pool: Option<&Arc<SandboxPool>>,
+    time_scale: f64,
+) -> Result<PreparedJob, Error> {
+    let mut current_job = current_job.lock().unwrap();
+    let job = current_job
+        .current_job
+        .as_ref()
+        .ok_or_else(|| anyhow!("Worker job is gone"))?;
+    let mut boxes = Vec::new();
+    let group = &job.0.group;
+    let fifo_dir = if group.fifo.is_empty() {
+        None
+    } else {
+        let fifo_dir = TempDir::new_in(sandbox_path).with_context(|| {
+            format!(
+                "Failed to create temporary directory in {}",
+                sandbox_path.display()
+            )
+        })?;
+        for fifo in &group.fifo {
+            let path = fifo_dir
+                .path()
+                .join(fifo.sandbox_path().file_name().unwrap());
+            nix::unistd::mkfifo(&path, nix::sys::stat::Mode::S_IRWXU)
+                .with_context(|| format!("Failed to create FIFO at {}", path.display()))?;
+        }
+        Some(fifo_dir)
+    };
+    let keep_sandboxes = group.config().keep_sandboxes;
+    for exec in &group.executions {
+        let exec = scale_limits(exec, time_scale);
+        let fifo_dir = fifo_dir.as_ref().map(|d| d.path().to_owned());
+        let mut sandbox = match sandbox_pool {
+            Some(pool) => Sandbox::new_pooled(pool, &exec, &job.1, fifo_dir)?,
+            None => Sandbox::new(sandbox_path, &exec, &job.1, fifo_dir)?,
+        };
+        if keep_sandboxes {
+            sandbox.keep()?;
+        }
+        boxes.push(sandbox);
+    }
+    let job = job.0.clone();
+    current_job.current_sandboxes = Some(boxes.clone());
+    let (sender, receiver) = channel();
+    current_job.server_asked_files = Some(sender);
+    Ok((job, boxes, fifo_dir, receiver))
+}
+
 /// The sandbox group manager spawns the threads of the sandbox of all the executions in the group.
 /// Then waits for their outcome and eventually stops the sandboxes if a process fails. When all the
 /// sandboxes complete, this manager collects their results and send them back to the server.
+/// Returns whether there is another job of the same batch to run, which becomes the current one.
+///
+/// The produced files are moved from the sandboxes into the store of the worker, unless the
+/// sandboxes are kept, so they are sent to the server without reading them again from the sandbox,
+/// and the next jobs of this worker find them ready.
 ///
 /// Note that this function owns `fifo_dir`, the `TempDir` where the FIFOs are stored, it has not to
 /// be dropped before all the sandboxes end.
+#[allow(clippy::too_many_arguments)]
 fn sandbox_group_manager(
     current_job: Arc<Mutex<WorkerCurrentJob>>,
+    file_store: &FileStore,
     job: WorkerJob,
     sender: ChannelSender<WorkerClientMessage>,
+    file_sender: ChannelFileSender,
     server_asked_files_receiver: Receiver<Vec<FileUuid>>,
     mut sandboxes: Vec<Sandbox>,
     runner: Arc<dyn SandboxRunner>,
     fifo_dir: Option<TempDir>,
-) -> Result<(), Error> {
+    time_scale: f64,
+    cpu: Option<usize>,
+) -> Result<bool, Error> {
     assert_eq!(sandboxes.len(), job.group.executions.len());
     let mut results = vec![None; job.group.executions.len()];
     let mut outputs = HashMap::new();
     let mut output_paths = HashMap::new();
+    let mut output_handles = HashMap::new();
+    // the outputs are moved into the store, unless the sandboxes are kept
+    let output_store = (!job.group.config().keep_sandboxes).then_some(file_store);
 
     // in case of simple executions there's no need to spawn the sandbox in a different thread and
     // then join from here
     if job.group.executions.len() == 1 {
         let sandbox = sandboxes.pop().unwrap();
-        let result = match sandbox.run(runner.as_ref()) {
-            Ok(res) => res,
-            Err(e) => SandboxResult::Failed {
-                error: e.to_string(),
-            },
-        };
         let exec = &job.group.executions[0];
-        let mut result = compute_execution_result(exec, result, &sandbox);
+        let (result, run_times) = run_repeated(exec, &sandbox, runner.as_ref());
+        let mut result = compute_execution_result(exec, result, &sandbox, time_scale, cpu);
+        result.run_times = run_times.into_iter().map(|t| t / time_scale).collect();
         get_result_outputs(
             exec,
             &sandbox,
+            output_store,
             &mut outputs,
             &mut output_paths,
+            &mut output_handles,
             &mut result.status,
         );
 
@@ -447,7 +638,8 @@ fn sandbox_group_manager(
                     let exec = &job.group.executions[index];
                     let sandbox = &sandboxes[index];
 
-                    let mut result = compute_execution_result(exec, result, sandbox);
+                    let mut result =
+                        compute_execution_result(exec, result, sandbox, time_scale, cpu);
                     // if the process didn't exit successfully, kill the remaining sandboxes
                     if !result.status.is_success() {
                         for (i, (res, sandbox)) in results.iter().zip(sandboxes.iter()).enumerate()
@@ -462,8 +654,10 @@ fn sandbox_group_manager(
                     get_result_outputs(
                         exec,
                         sandbox,
+                        output_store,
                         &mut outputs,
                         &mut output_paths,
+                        &mut output_handles,
                         &mut result.status,
                     );
 
@@ -480,6 +674,21 @@ fn sandbox_group_manager(
                 .context("Sandbox thread failed")?;
         }
     }
+    // the cache key of the result needs the fingerprints of the toolchains of the commands
+    let commands = job
+        .group
+        .executions
+        .iter()
+        .filter_map(|exec| match &exec.command {
+            ExecutionCommand::System(command) => Some(command.as_path()),
+            ExecutionCommand::Local(_) | ExecutionCommand::Builtin(_) => None,
+        });
+    let toolchains = new_toolchain_fingerprints(commands);
+    if !toolchains.is_empty() {
+        sender
+            .send(WorkerClientMessage::Toolchains(toolchains))
+            .context("Failed to send Toolchains")?;
+    }
     // tell the server the results and the list of produced files
     sender
         .send(WorkerClientMessage::WorkerDone(
@@ -495,8 +704,11 @@ fn sandbox_group_manager(
                     sender
                         .send(WorkerClientMessage::ProvideFile(uuid, key.clone()))
                         .context("Failed to send ProvideFile")?;
-                    ChannelFileSender::send(&output_paths[&uuid], &sender)
-                        .context("Failed to send missing file")?;
+                    match output_handles.get(&uuid) {
+                        Some(handle) => file_sender.send_handle(handle, &sender),
+                        None => file_sender.send(&output_paths[&uuid], &sender),
+                    }
+                    .context("Failed to send missing file")?;
                 } else {
                     error!(
                         "Server asked for file {}, which is not known to the worker",
@@ -515,20 +727,27 @@ fn sandbox_group_manager(
             );
             let mut job = current_job.lock().unwrap();
             job.current_job = None;
+            job.queued_jobs.clear();
             job.current_sandboxes = None;
-            return Ok(());
+            return Ok(false);
         }
     }
-    // this job is completed, reset the worker and ask for more work
-    let mut job = current_job.lock().unwrap();
-    job.current_job = None;
-    job.current_sandboxes = None;
-    let _ = sender.send(WorkerClientMessage::GetWork);
     // The sandbox may chmod -r the directory, revert it to allow deletion on drop
     if let Some(fifo_dir) = fifo_dir {
         let _ = std::fs::set_permissions(fifo_dir.path(), Permissions::from_mode(0o755));
     }
-    Ok(())
+    let mut job = current_job.lock().unwrap();
+    job.current_sandboxes = None;
+    // the next job of the batch uses the files already received with this one
+    let handles = job.current_job.take().map(|(_, handles)| handles);
+    if let (Some(next), Some(handles)) = (job.queued_jobs.pop_front(), handles) {
+        job.current_job = Some((next, handles));
+        return Ok(true);
+    }
+    // this job is completed, reset the worker and ask for more work
+    job.queued_jobs.clear();
+    let _ = sender.send(WorkerClientMessage::GetWork(WorkerTelemetry::current()));
+    Ok(false)
 }
 
 /// Spawn the sandbox of an execution in a different thread and send to the group manager the
@@ -554,20 +773,94 @@ fn spawn_sandbox(
         })?)
 }
 
+/// Run the sandbox of the execution, more times if the execution is repeated. The result is the one
+/// of the run chosen by the `runs_policy` of the DAG, together with the CPU times of all the runs
+/// (empty if the sandbox ran only once). The repetitions stop at the first run that fails for a
+/// reason other than a limit.
+fn run_repeated(
+    exec: &Execution,
+    sandbox: &Sandbox,
+    runner: &dyn SandboxRunner,
+) -> (SandboxResult, Vec<f64>) {
+    let runs = if exec.repeated {
+        exec.config().runs.max(1)
+    } else {
+        1
+    };
+    let mut results = Vec::new();
+    for _ in 0..runs {
+        let result = match sandbox.run(runner) {
+            Ok(res) => res,
+            Err(e) => SandboxResult::Failed {
+                error: e.to_string(),
+            },
+        };
+        let keep_going = matches!(
+            &result,
+            SandboxResult::Success {
+                exit_status: 0,
+                signal: None,
+                ..
+            } | SandboxResult::Success {
+                was_killed: true,
+                ..
+            }
+        );
+        results.push(result);
+        if !keep_going {
+            break;
+        }
+    }
+    if results.len() == 1 {
+        return (results.pop().unwrap(), vec![]);
+    }
+    let times: Vec<f64> = results
+        .iter()
+        .map(|result| match result {
+            SandboxResult::Success { resources, .. } => resources.cpu_time,
+            SandboxResult::Failed { .. } => 0.0,
+        })
+        .collect();
+    // a failure of the sandbox can only be the last run, and it's not a valid measurement
+    if let Some(SandboxResult::Failed { .. }) = results.last() {
+        return (results.pop().unwrap(), times);
+    }
+    let mut order: Vec<usize> = (0..results.len()).collect();
+    order.sort_by(|&a, &b| times[a].total_cmp(&times[b]));
+    let chosen = order[exec.config().runs_policy.index(order.len())];
+    (results.swap_remove(chosen), times)
+}
+
+/// Make a copy of the execution with the time limits multiplied by `time_scale`.
+fn scale_limits(execution: &Execution, time_scale: f64) -> Execution {
+    let mut execution = execution.clone();
+    let limits = &mut execution.limits;
+    limits.cpu_time = limits.cpu_time.map(|t| t * time_scale);
+    limits.sys_time = limits.sys_time.map(|t| t * time_scale);
+    limits.wall_time = limits.wall_time.map(|t| t * time_scale);
+    execution
+}
+
 /// Compute the [`ExecutionResult`](../task_maker_dag/struct.ExecutionResult.html) based on the
-/// result of the sandbox.
+/// result of the sandbox. The measured times are divided by `time_scale`, so they are checked
+/// against the original limits of the execution.
 fn compute_execution_result(
     execution: &Execution,
     result: SandboxResult,
     sandbox: &Sandbox,
+    time_scale: f64,
+    cpu: Option<usize>,
 ) -> ExecutionResult {
     match result {
         SandboxResult::Success {
             exit_status,
             signal,
-            resources,
+            mut resources,
             was_killed,
         } => {
+            resources.cpu_time /= time_scale;
+            resources.sys_time /= time_scale;
+            resources.wall_time /= time_scale;
             let stdout = capture_stream(&sandbox.stdout_path(), execution.capture_stdout);
             let stderr = capture_stream(&sandbox.stderr_path(), execution.capture_stderr);
             let status = match (&stdout, &stderr) {
@@ -588,6 +881,10 @@ fn compute_execution_result(
                 was_killed,
                 was_cached: false,
                 stderr: stderr.ok().unwrap_or_default(),
+                time_scale,
+                cpu,
+                run_times: vec![],
+                memory_metric: execution.limits.memory_metric,
             }
         }
         SandboxResult::Failed { error } => ExecutionResult {
@@ -597,21 +894,36 @@ fn compute_execution_result(
             was_killed: false,
             was_cached: false,
             stderr: None,
+            time_scale,
+            cpu,
+            run_times: vec![],
+            memory_metric: execution.limits.memory_metric,
         },
     }
 }
 
 /// Extract the output files from the result of the sandbox and store them in the provided HashMaps.
+///
+/// With a `file_store` the output files are moved from the sandbox into the store, and their
+/// handles are kept in `output_handles`.
 fn get_result_outputs(
     exec: &Execution,
     sandbox: &Sandbox,
+    file_store: Option<&FileStore>,
     outputs: &mut HashMap<FileUuid, FileStoreKey>,
     output_paths: &mut HashMap<FileUuid, PathBuf>,
+    output_handles: &mut HashMap<FileUuid, FileStoreHandle>,
     status: &mut ExecutionStatus,
 ) {
     let mut add_file = |file: FileUuid, path: PathBuf| {
         if path.exists() {
-            let key = FileStoreKey::from_file(&path);
+            let key = match file_store {
+                Some(file_store) => file_store.store_moving(&path).map(|(key, handle)| {
+                    output_handles.insert(file, handle);
+                    key
+                }),
+                None => FileStoreKey::from_file(&path),
+            };
             match key {
                 Ok(key) => {
                     outputs.insert(file, key);
@@ -1,13 +1,15 @@
-use std::collections::HashMap;
-use std::sync::mpsc::{Receiver, Sender};
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::thread::JoinHandle;
 
 use anyhow::{bail, Context, Error};
 use ductile::ChannelSender;
 
 use task_maker_dag::{ExecutionGroupUuid, WorkerUuid};
-use task_maker_store::FileStore;
+use task_maker_store::{FileStore, FileStoreKey};
 
 use crate::executor::WorkerJob;
 use crate::proto::{
@@ -24,24 +26,60 @@ pub(crate) enum WorkerManagerInMessage {
     /// A worker has disconnected. This message is sent by the WorkerManager itself, from a
     /// different thread.
     WorkerDisconnected { worker: WorkerUuid },
-    /// The scheduler sent a new job for a worker. The WorkerManager will forward the job to the
-    /// actual worker.
-    WorkerJob { worker: WorkerUuid, job: WorkerJob },
+    /// The scheduler sent some new jobs for a worker. The WorkerManager will forward the jobs to
+    /// the actual worker, in a single message.
+    WorkerJob {
+        worker: WorkerUuid,
+        jobs: Vec<WorkerJob>,
+    },
     /// The scheduler is asking a worker to stop doing a job, if the worker is still working on
     /// that.
     StopWorkerJob {
         worker: WorkerUuid,
         job: ExecutionGroupUuid,
     },
+    /// The scheduler is asking a worker to exit, for example because it has been drained.
+    ExitWorker { worker: WorkerUuid },
+    /// The scheduler is asking a worker the fingerprints of the toolchains of these system
+    /// commands.
+    AskToolchains {
+        worker: WorkerUuid,
+        commands: Vec<PathBuf>,
+    },
+    /// The scheduler started a round of garbage collection: all the workers should clean their
+    /// stores, keeping only these files.
+    CollectGarbage { keep: Arc<Vec<FileStoreKey>> },
     /// The WorkerManager is asked to exit and tell all the connected worker to exit too.
     Exit,
 }
 
+/// The last round of garbage collection: its sequence number (zero means no round yet) and the
+/// files to keep.
+type GcRound = Arc<Mutex<(u64, Arc<Vec<FileStoreKey>>)>>;
+
+/// The files a worker has in its store, as far as the server knows. `None` if the worker didn't
+/// tell its files, in which case it asks the dependencies of the jobs itself.
+type KnownFiles = Arc<Mutex<Option<HashSet<FileStoreKey>>>>;
+
+/// The channel to a worker, shared by the threads that talk to it. It's kept locked during the
+/// transfer of a file, so that the chunks are not mixed with other messages.
+type SharedWorkerSender = Arc<Mutex<ChannelSender<WorkerServerMessage>>>;
+
+/// A connected worker, as seen by the main thread of the `WorkerManager`.
+struct ConnectedWorker {
+    /// The queue of the messages for the worker, they are sent in order by the dispatcher thread.
+    queue: Sender<WorkerServerMessage>,
+    /// The handle of the dispatcher thread.
+    dispatcher: JoinHandle<()>,
+}
+
 /// The entity that manages the connections with the workers, eventually writing files to disk and
 /// telling to the `Scheduler` the connection and disconnection of the workers.
 pub(crate) struct WorkerManager {
     /// A reference to the file store.
     file_store: Arc<FileStore>,
+    /// Sends the files to the workers, with the compression told to them.
+    file_sender: ChannelFileSender,
     /// A channel for sending the messages to the scheduler.
     scheduler: Sender<SchedulerInMessage>,
     /// A channel for sending the messages to the WorkerManager itself. It is used by the threads
@@ -57,12 +95,14 @@ impl WorkerManager {
     /// internally for sending back the disconnection notification from other threads.
     pub fn new(
         file_store: Arc<FileStore>,
+        file_sender: ChannelFileSender,
         scheduler: Sender<SchedulerInMessage>,
         sender: Sender<WorkerManagerInMessage>,
         receiver: Receiver<WorkerManagerInMessage>,
     ) -> WorkerManager {
         WorkerManager {
             file_store,
+            file_sender,
             scheduler,
             sender,
             receiver,
@@ -72,8 +112,8 @@ impl WorkerManager {
     /// Run the worker manager blocking until an exit message is received. On exiting the connected
     /// workers will stop.
     pub fn run(self) -> Result<(), Error> {
-        let mut connected_workers: HashMap<WorkerUuid, ChannelSender<WorkerServerMessage>> =
-            HashMap::new();
+        let mut connected_workers: HashMap<WorkerUuid, ConnectedWorker> = HashMap::new();
+        let gc_round: GcRound = Arc::new(Mutex::new((0, Arc::new(Vec::new()))));
         while let Ok(message) = self.receiver.recv() {
             match message {
                 WorkerManagerInMessage::WorkerConnected { worker } => {
@@ -81,20 +121,55 @@ impl WorkerManager {
                         warn!("Duplicate worker uuid");
                         continue;
                     }
-                    connected_workers.insert(worker.uuid, worker.sender.clone());
                     info!("Worker {} ({}) connected", worker.name, worker.uuid);
+                    let worker_sender = Arc::new(Mutex::new(worker.sender.clone()));
+                    let known_files: KnownFiles = Arc::new(Mutex::new(None));
+                    let (queue, queue_receiver) = channel();
+                    let dispatcher = {
+                        let worker_sender = worker_sender.clone();
+                        let known_files = known_files.clone();
+                        let file_store = self.file_store.clone();
+                        let file_sender = self.file_sender;
+                        thread::Builder::new()
+                            .name(format!(
+                                "Dispatcher of worker {} ({})",
+                                worker.name, worker.uuid
+                            ))
+                            .spawn(move || {
+                                if let Err(e) = WorkerManager::dispatcher_thread(
+                                    queue_receiver,
+                                    worker_sender,
+                                    known_files,
+                                    file_store,
+                                    file_sender,
+                                ) {
+                                    warn!("The dispatcher of a worker failed: {:?}", e);
+                                }
+                            })
+                            .context("Failed to spawn dispatcher for a worker")?
+                    };
+                    connected_workers.insert(worker.uuid, ConnectedWorker { queue, dispatcher });
                     let scheduler = self.scheduler.clone();
                     let file_store = self.file_store.clone();
                     let sender = self.sender.clone();
+                    let gc_round = gc_round.clone();
+                    let file_sender = self.file_sender;
                     thread::Builder::new()
                         .name(format!(
                             "Manager of worker {} ({})",
                             worker.name, worker.uuid
                         ))
                         .spawn(move || {
-                            if let Err(e) =
-                                WorkerManager::worker_thread(worker, scheduler, sender, file_store)
-                            {
+                            if let Err(e) = WorkerManager::worker_thread(
+                                worker,
+                                worker_sender,
+                                known_files,
+                                scheduler,
+                                sender,
+                                file_store,
+                                file_sender,
+                                gc_round,
+                            ) {
                                 warn!("The manager of a worker failed: {:?}", e);
                             }
                         })
@@ -105,53 +180,162 @@ impl WorkerManager {
                         .remove(&worker)
                         .context("Unknown worker disconnected")?;
                 }
-                WorkerManagerInMessage::WorkerJob { worker, job } => {
+                WorkerManagerInMessage::WorkerJob { worker, mut jobs } => {
                     // if the worker is not present, it means it has just disconnected. The
                     // scheduler should be already informed and should have resheduled the job.
-                    if let Some(sender) = connected_workers.get(&worker) {
-                        sender
-                            .send(WorkerServerMessage::Work(Box::new(job)))
-                            .context("Failed to send Work to worker")?;
+                    if let Some(connected) = connected_workers.get(&worker) {
+                        let message = if jobs.len() == 1 {
+                            WorkerServerMessage::Work(Box::new(jobs.remove(0)))
+                        } else {
+                            WorkerServerMessage::WorkBatch(jobs)
+                        };
+                        if connected.queue.send(message).is_err() {
+                            warn!("Cannot send Work to worker {}", worker);
+                        }
                     }
                 }
                 WorkerManagerInMessage::Exit => {
                     debug!("Worker manager asked to exit");
                     break;
                 }
+                WorkerManagerInMessage::ExitWorker { worker } => {
+                    if let Some(connected) = connected_workers.get(&worker) {
+                        if connected.queue.send(WorkerServerMessage::Exit).is_err() {
+                            warn!("Cannot send Exit to worker {}", worker);
+                        }
+                    }
+                }
+                WorkerManagerInMessage::CollectGarbage { keep } => {
+                    // the workers are told when they ask for work, so that the message is not sent
+                    // in the middle of a file transfer
+                    let mut gc_round = gc_round.lock().unwrap();
+                    *gc_round = (gc_round.0 + 1, keep);
+                }
+                WorkerManagerInMessage::AskToolchains { worker, commands } => {
+                    if let Some(connected) = connected_workers.get(&worker) {
+                        if connected
+                            .queue
+                            .send(WorkerServerMessage::Toolchains(commands))
+                            .is_err()
+                        {
+                            warn!("Cannot send Toolchains to worker {}", worker);
+                        }
+                    }
+                }
                 WorkerManagerInMessage::StopWorkerJob { worker, job } => {
-                    if let Some(sender) = connected_workers.get(&worker) {
-                        sender
+                    if let Some(connected) = connected_workers.get(&worker) {
+                        if connected
+                            .queue
                             .send(WorkerServerMessage::KillJob(job))
-                            .context("Failed to send KillJob to worker")?;
+                            .is_err()
+                        {
+                            warn!("Cannot send KillJob to worker {}", worker);
+                        }
                     }
                 }
             }
         }
         debug!("Worker manager exiting");
-        for (worker, sender) in connected_workers.iter() {
-            if sender.send(WorkerServerMessage::Exit).is_err() {
+        for (worker, connected) in connected_workers {
+            if connected.queue.send(WorkerServerMessage::Exit).is_err() {
                 warn!("Cannot tell worker {} to exit", worker);
             }
+            // dropping the queue makes the dispatcher exit after sending the pending messages
+            drop(connected.queue);
+            if connected.dispatcher.join().is_err() {
+                warn!("The dispatcher of worker {} panicked", worker);
+            }
         }
         Ok(())
     }
 
+    /// Thread body that sends the messages to a worker, in order. Before a job, the dependencies
+    /// the worker didn't tell to have in its store are sent to it, skipping the ones it already
+    /// has.
+    fn dispatcher_thread(
+        queue: Receiver<WorkerServerMessage>,
+        sender: SharedWorkerSender,
+        known_files: KnownFiles,
+        file_store: Arc<FileStore>,
+        file_sender: ChannelFileSender,
+    ) -> Result<(), Error> {
+        while let Ok(message) = queue.recv() {
+            let sender = sender.lock().unwrap();
+            let jobs = match &message {
+                WorkerServerMessage::Work(job) => vec![job.as_ref()],
+                WorkerServerMessage::WorkBatch(jobs) => jobs.iter().collect(),
+                _ => vec![],
+            };
+            for key in WorkerManager::files_to_send(&jobs, &known_files) {
+                // if the server doesn't have the file, the worker will ask it and fail
+                if let Some(handle) = file_store.get(&key) {
+                    sender
+                        .send(WorkerServerMessage::ProvideFile(key))
+                        .context("Failed to send ProvideFile to worker")?;
+                    file_sender
+                        .send_handle(&handle, &sender)
+                        .context("Failed to send file to worker")?;
+                }
+            }
+            sender
+                .send(message)
+                .context("Failed to send message to worker")?;
+        }
+        Ok(())
+    }
+
+    /// The dependencies of the jobs that the worker doesn't have, according to what it told. They
+    /// are marked as known, since they are going to be sent.
+    fn files_to_send(jobs: &[&WorkerJob], known_files: &KnownFiles) -> Vec<FileStoreKey> {
+        let mut known_files = known_files.lock().unwrap();
+        let known_files = match known_files.as_mut() {
+            Some(known_files) => known_files,
+            None => return vec![],
+        };
+        let mut keys = Vec::new();
+        for job in jobs {
+            for key in job.dep_keys.values() {
+                if known_files.insert(key.clone()) {
+                    keys.push(key.clone());
+                }
+            }
+        }
+        keys
+    }
+
     /// Thread body that manages the actual connection with a worker. `worker_manager` will send
     /// messages back to the `WorkerManager` main thread for the notification about the
-    /// disconnection of this worker.
+    /// disconnection of this worker. When the worker is idle it's told about the rounds of garbage
+    /// collection it hasn't seen yet.
+    #[allow(clippy::too_many_arguments)]
     fn worker_thread(
         worker: WorkerConn,
+        sender: SharedWorkerSender,
+        known_files: KnownFiles,
         scheduler: Sender<SchedulerInMessage>,
         worker_manager: Sender<WorkerManagerInMessage>,
         file_store: Arc<FileStore>,
+        file_sender: ChannelFileSender,
+        gc_round: GcRound,
     ) -> Result<(), Error> {
+        let mut last_gc_round = 0;
         while let Ok(message) = worker.receiver.recv() {
             match message {
-                WorkerClientMessage::GetWork => {
+                WorkerClientMessage::GetWork(telemetry) => {
+                    let (round, keep) = gc_round.lock().unwrap().clone();
+                    if round > last_gc_round {
+                        last_gc_round = round;
+                        sender
+                            .lock()
+                            .unwrap()
+                            .send(WorkerServerMessage::CollectGarbage(keep.to_vec()))
+                            .context("Failed to send CollectGarbage to worker")?;
+                    }
                     // the worker is asking for more work to do
                     let res = scheduler.send(SchedulerInMessage::WorkerConnected {
                         uuid: worker.uuid,
                         name: worker.name.clone(),
+                        telemetry,
                     });
                     if res.is_err() {
                         // the scheduler is gone
@@ -163,13 +347,35 @@ impl WorkerManager {
                     let handle = file_store
                         .get(&key)
                         .context("Worker is asking for an unknown file")?;
-                    worker
-                        .sender
+                    if let Some(known_files) = known_files.lock().unwrap().as_mut() {
+                        known_files.insert(key.clone());
+                    }
+                    let sender = sender.lock().unwrap();
+                    sender
                         .send(WorkerServerMessage::ProvideFile(key))
                         .context("Failed to send ProvideFile to worker")?;
-                    ChannelFileSender::send(handle.path(), &worker.sender)
+                    file_sender
+                        .send_handle(&handle, &sender)
                         .context("Failed to send file to worker")?;
                 }
+                WorkerClientMessage::StoredFiles(keys) => {
+                    debug!(
+                        "Worker {} has {} files in its store",
+                        worker.uuid,
+                        keys.len()
+                    );
+                    *known_files.lock().unwrap() = Some(keys.into_iter().collect());
+                }
+                WorkerClientMessage::Toolchains(toolchains) => {
+                    let res = scheduler.send(SchedulerInMessage::WorkerToolchains {
+                        worker: worker.uuid,
+                        toolchains,
+                    });
+                    if res.is_err() {
+                        // the scheduler is gone
+                        break;
+                    }
+                }
                 WorkerClientMessage::ProvideFile(_, _) => {
                     // the worker should not provide files unless just after a WorkerDone message is
                     // received
@@ -191,8 +397,12 @@ impl WorkerManager {
                         "Asking worker {} for {} missing files",
                         worker.uuid, num_missing
                     );
-                    worker
-                        .sender
+                    if let Some(known_files) = known_files.lock().unwrap().as_mut() {
+                        known_files.extend(outputs.values().cloned());
+                    }
+                    sender
+                        .lock()
+                        .unwrap()
                         .send(WorkerServerMessage::AskFiles(missing_files))
                         .context("Failed to send AskFiles to worker")?;
                     for _ in 0..num_missing {
@@ -242,3 +452,113 @@ impl WorkerManager {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ductile::new_local_channel;
+    use tempfile::TempDir;
+
+    use task_maker_dag::{ExecutionGroup, File};
+
+    use super::*;
+
+    /// Store a file with the given content, returning its key.
+    fn store_file(file_store: &FileStore, content: &[u8]) -> FileStoreKey {
+        let key = FileStoreKey::from_content(content);
+        file_store.store(&key, vec![content.to_vec()]).unwrap();
+        key
+    }
+
+    /// Run the dispatcher on a job depending on a file the worker has and on one it doesn't have,
+    /// returning the messages the worker received.
+    fn dispatch(known_files: Option<&[&[u8]]>) -> Vec<WorkerServerMessage> {
+        let tmpdir = TempDir::new().unwrap();
+        let file_store = Arc::new(FileStore::new(tmpdir.path(), 1 << 30, 1 << 30).unwrap());
+        let present = store_file(&file_store, b"present");
+        let missing = store_file(&file_store, b"missing");
+        let job = WorkerJob {
+            group: ExecutionGroup::new("group"),
+            dep_keys: vec![
+                (File::new("a").uuid, present),
+                (File::new("b").uuid, missing),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let known_files = known_files.map(|files| {
+            files
+                .iter()
+                .map(|c| FileStoreKey::from_content(c))
+                .collect()
+        });
+
+        let (queue, queue_receiver) = channel();
+        queue
+            .send(WorkerServerMessage::Work(Box::new(job)))
+            .unwrap();
+        drop(queue);
+        let (sender, receiver) = new_local_channel();
+        WorkerManager::dispatcher_thread(
+            queue_receiver,
+            Arc::new(Mutex::new(sender)),
+            Arc::new(Mutex::new(known_files)),
+            file_store,
+            ChannelFileSender::default(),
+        )
+        .unwrap();
+
+        let mut messages = Vec::new();
+        while let Ok(message) = receiver.recv() {
+            if let WorkerServerMessage::ProvideFile(_) = &message {
+                let data: Vec<u8> = ChannelFileIterator::new(&receiver)
+                    .flat_map(|d| d.into_iter())
+                    .collect();
+                assert_eq!(data, b"missing");
+            }
+            messages.push(message);
+        }
+        messages
+    }
+
+    #[test]
+    fn test_dispatch_skips_stored_files() {
+        let messages = dispatch(Some(&[b"present"]));
+        assert_eq!(messages.len(), 2);
+        match &messages[0] {
+            WorkerServerMessage::ProvideFile(key) => {
+                assert_eq!(key, &FileStoreKey::from_content(b"missing"))
+            }
+            message => panic!("Expecting ProvideFile, got {:?}", message),
+        }
+        assert!(matches!(messages[1], WorkerServerMessage::Work(_)));
+    }
+
+    #[test]
+    fn test_dispatch_all_stored() {
+        let messages = dispatch(Some(&[b"present", b"missing"]));
+        assert_eq!(messages.len(), 1);
+        assert!(matches!(messages[0], WorkerServerMessage::Work(_)));
+    }
+
+    #[test]
+    fn test_dispatch_without_advertised_store() {
+        let messages = dispatch(None);
+        assert_eq!(messages.len(), 1);
+        assert!(matches!(messages[0], WorkerServerMessage::Work(_)));
+    }
+
+    #[test]
+    fn test_files_to_send_once() {
+        let key = FileStoreKey::from_content(b"file");
+        let job = WorkerJob {
+            group: ExecutionGroup::new("group"),
+            dep_keys: vec![(File::new("a").uuid, key.clone())]
+                .into_iter()
+                .collect(),
+        };
+        let known_files: KnownFiles = Arc::new(Mutex::new(Some(HashSet::new())));
+        let keys = WorkerManager::files_to_send(&[&job, &job], &known_files);
+        assert_eq!(keys, vec![key]);
+        assert!(WorkerManager::files_to_send(&[&job], &known_files).is_empty());
+    }
+}
@@ -7,20 +7,25 @@ use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Paragraph};
 use ratatui::Frame;
 
-use task_maker_dag::ExecutionStatus;
+use task_maker_dag::{ExecutionResourcesUsage, ExecutionStatus};
 
-use crate::ioi::finish_ui::{FinishUI, YELLOW_RESOURCE_THRESHOLD};
+use crate::ioi::finish_ui::FinishUI;
 use crate::ioi::{
-    SolutionTestcaseEvaluationState, SubtaskId, TestcaseEvaluationStatus, TestcaseGenerationStatus,
-    UIState,
+    SolutionGroup, SolutionTestcaseEvaluationState, SubtaskId, TestcaseEvaluationStatus,
+    TestcaseGenerationStatus, UIState,
 };
 use crate::ui::curses::{
     compilation_status_text, draw_compilations, inner_block, render_block, render_server_status,
-    CursesDrawer, CursesUI as GenericCursesUI, GREEN, ORANGE, RED, YELLOW,
+    CursesDrawer, CursesUI as GenericCursesUI, CACHED, GREEN, ORANGE, RED, YELLOW,
 };
-use crate::ui::UIExecutionStatus;
+use crate::ui::{UIExecutionStatus, UIStateT};
 use crate::ScoreStatus;
 
+/// Maximum number of lines of the stderr of a failed generation shown in the UI.
+const STDERR_TAIL_LINES: usize = 6;
+/// The width of the time and memory used by the solutions in the evaluation box.
+const RESOURCES_WIDTH: usize = 19;
+
 /// An animated UI for IOI tasks, dynamically refreshing using curses as a backend.
 pub(crate) type CursesUI = GenericCursesUI<UIState, Drawer, FinishUI>;
 
@@ -74,7 +79,20 @@ fn draw_frame(state: &UIState, f: &mut Frame, loading: char, frame_index: usize)
             + 2
     };
     let generations_len = if state.generations.is_empty() { 0 } else { 3 };
-    let evaluations_len = state.evaluations.len() as u16 + 2;
+    let stderr_tail = state
+        .failed_stderr
+        .last()
+        .map(|failed| stderr_tail(&failed.content))
+        .unwrap_or_default();
+    let stderr_len = if state.failed_stderr.is_empty() {
+        0
+    } else {
+        stderr_tail.len() as u16 + 2
+    };
+    let groups = state.solution_groups();
+    let grouped = !state.task.solution_groups.is_empty();
+    let group_headers_len = if grouped { groups.len() as u16 } else { 0 };
+    let evaluations_len = state.evaluations.len() as u16 + group_headers_len + 2;
     let mut workers_len = state
         .executor_status
         .as_ref()
@@ -83,7 +101,7 @@ fn draw_frame(state: &UIState, f: &mut Frame, loading: char, frame_index: usize)
         + 2;
     let total_height = f.area().height;
     // fixed size section heights
-    let top_height = header_len + compilations_len + booklet_len + generations_len;
+    let top_height = header_len + compilations_len + booklet_len + generations_len + stderr_len;
     // if the sections don't just fit, reduce the size of the workers until they fit but
     // without shortening it more than 3 lines (aka box + 1 worker).
     if top_height + evaluations_len + workers_len > total_height {
@@ -100,6 +118,7 @@ fn draw_frame(state: &UIState, f: &mut Frame, loading: char, frame_index: usize)
                 Constraint::Length(compilations_len),
                 Constraint::Length(booklet_len),
                 Constraint::Length(generations_len),
+                Constraint::Length(stderr_len),
                 Constraint::Min(0),
                 Constraint::Length(workers_len),
             ]
@@ -128,19 +147,35 @@ fn draw_frame(state: &UIState, f: &mut Frame, loading: char, frame_index: usize)
         render_block(f, chunks[3], " Generation ");
         draw_generations(f, inner_block(chunks[3]), state, loading);
     }
+    if let Some(failed) = state.failed_stderr.last() {
+        let title = match state.failed_stderr.len() {
+            1 => format!(" Stderr of {} ", failed.step),
+            n => format!(" Stderr of {} ({} failed) ", failed.step, n),
+        };
+        render_block(f, chunks[4], &title);
+        let text: Vec<Line> = stderr_tail.into_iter().map(Line::raw).collect();
+        f.render_widget(Paragraph::new(text), inner_block(chunks[4]));
+    }
     if !state.evaluations.is_empty() {
-        render_block(f, chunks[4], " Evaluations ");
-        draw_evaluations(f, inner_block(chunks[4]), state, loading);
+        render_block(f, chunks[5], " Evaluations ");
+        draw_evaluations(f, inner_block(chunks[5]), state, &groups, grouped, loading);
     }
     render_server_status(
         f,
-        chunks[5],
+        chunks[6],
         state.executor_status.as_ref(),
+        state.file_transfer.as_ref(),
         loading,
         frame_index,
     );
 }
 
+/// The last lines of a stderr, at most `STDERR_TAIL_LINES`.
+fn stderr_tail(content: &str) -> Vec<&str> {
+    let lines: Vec<&str> = content.trim_end().lines().collect();
+    lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].to_vec()
+}
+
 /// Draw the content of the booklet box.
 fn draw_booklets(frame: &mut Frame, rect: Rect, state: &UIState, loading: char) {
     let text: Vec<Line> = state
@@ -196,7 +231,14 @@ fn draw_generations(frame: &mut Frame, rect: Rect, state: &UIState, loading: cha
                 .testcases
                 .iter()
                 .sorted_by_key(|(k, _)| *k)
-                .map(|(_, tc)| generation_status_text(&tc.status, loading))
+                .map(|(_, tc)| {
+                    let text = generation_status_text(&tc.status, loading);
+                    if tc.was_cached() {
+                        text.patch_style(*CACHED)
+                    } else {
+                        text
+                    }
+                })
                 .collect();
             let mut res = vec![Span::raw("[")];
             res.append(&mut testcases);
@@ -223,8 +265,16 @@ fn generation_status_text(status: &TestcaseGenerationStatus, loading: char) -> S
     }
 }
 
-/// Draw the content of the evaluation box.
-fn draw_evaluations(frame: &mut Frame, rect: Rect, state: &UIState, loading: char) {
+/// Draw the content of the evaluation box, with the title of each group of solutions if they are
+/// `grouped`.
+fn draw_evaluations(
+    frame: &mut Frame,
+    rect: Rect,
+    state: &UIState,
+    groups: &[SolutionGroup],
+    grouped: bool,
+    loading: char,
+) {
     let max_len = state
         .evaluations
         .keys()
@@ -232,11 +282,15 @@ fn draw_evaluations(frame: &mut Frame, rect: Rect, state: &UIState, loading: cha
         .max()
         .unwrap_or(0)
         + 4;
-    let text: Vec<Line> = state
-        .evaluations
-        .keys()
-        .sorted()
-        .map(|solution| {
+    let mut text: Vec<Line> = vec![];
+    for group in groups {
+        if grouped {
+            text.push(Line::styled(
+                group.title(state),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+        }
+        text.extend(group.solutions.iter().map(|solution| {
             let mut spans = vec![Span::raw(format!(
                 "{:<max_len$} ",
                 solution
@@ -252,10 +306,11 @@ fn draw_evaluations(frame: &mut Frame, rect: Rect, state: &UIState, loading: cha
             }
             spans.push(Span::raw(" "));
             spans.push(evaluation_score(state, solution, loading));
+            spans.append(&mut evaluation_resources(state, solution));
             spans.append(&mut evaluation_line(state, solution, loading));
-            spans.into()
-        })
-        .collect();
+            Line::from(spans)
+        }));
+    }
     let paragraph = Paragraph::new(text);
     frame.render_widget(paragraph, rect);
 }
@@ -286,6 +341,41 @@ fn evaluation_score<'a>(state: &'a UIState, solution: &Path, loading: char) -> S
     }
 }
 
+/// Get the maximum time and memory used by a solution, highlighting the ones close to the limits.
+fn evaluation_resources<'a>(state: &UIState, solution: &Path) -> Vec<Span<'a>> {
+    let results: Vec<_> = state
+        .evaluations
+        .get(solution)
+        .into_iter()
+        .flat_map(|eval| eval.testcases.values())
+        .flat_map(|testcase| testcase.results.iter().flatten())
+        .collect();
+    if results.is_empty() {
+        return vec![Span::raw(" ".repeat(RESOURCES_WIDTH))];
+    }
+    let resources = ExecutionResourcesUsage {
+        cpu_time: results
+            .iter()
+            .map(|r| r.resources.cpu_time)
+            .fold(0.0, f64::max),
+        memory: results
+            .iter()
+            .map(|r| r.resources.memory)
+            .max()
+            .unwrap_or(0),
+        ..Default::default()
+    };
+    let (close_time, close_memory) = state.close_to_limits(solution, &resources);
+    let style = |close| if close { *ORANGE } else { Style::default() };
+    vec![
+        Span::styled(format!(" {:>6.3}s", resources.cpu_time), style(close_time)),
+        Span::styled(
+            format!(" {:>6.1}MiB ", resources.memory as f64 / 1024.0),
+            style(close_memory),
+        ),
+    ]
+}
+
 /// Get the line at the right of the score of a solution.
 fn evaluation_line<'a>(state: &'a UIState, solution: &Path, loading: char) -> Vec<Span<'a>> {
     state
@@ -306,11 +396,11 @@ fn subtask_evaluation_status_text<'a>(
     loading: char,
 ) -> Vec<Span<'a>> {
     let mut texts = vec![];
-    let solution = &state.evaluations[solution];
-    if !solution.subtasks.contains_key(&subtask_id) {
+    let evaluation = &state.evaluations[solution];
+    if !evaluation.subtasks.contains_key(&subtask_id) {
         return vec![Span::raw("[---]")];
     }
-    let subtask = &solution.subtasks[&subtask_id];
+    let subtask = &evaluation.subtasks[&subtask_id];
     let par_style = if let Some(normalized_score) = subtask.normalized_score {
         match ScoreStatus::from_score(normalized_score, 1.0) {
             ScoreStatus::Accepted => *GREEN,
@@ -322,8 +412,10 @@ fn subtask_evaluation_status_text<'a>(
     };
     texts.push(Span::styled("[", par_style));
     for testcase_id in &state.task.subtasks[&subtask_id].testcases_owned {
-        let testcase = &solution.testcases[testcase_id];
-        texts.push(testcase_evaluation_status_text(testcase, loading, state));
+        let testcase = &evaluation.testcases[testcase_id];
+        texts.push(testcase_evaluation_status_text(
+            testcase, solution, loading, state,
+        ));
     }
     texts.push(Span::styled("]", par_style));
     texts
@@ -332,23 +424,24 @@ fn subtask_evaluation_status_text<'a>(
 /// Get the colored character corresponding to the status of the evaluation of a testcase.
 fn testcase_evaluation_status_text<'a>(
     testcase: &'a SolutionTestcaseEvaluationState,
+    solution: &Path,
     loading: char,
     state: &'a UIState,
 ) -> Span<'a> {
-    let time_limit = state.task.time_limit;
-    let memory_limit = state.task.memory_limit;
+    let time_limit = state.task.time_limit_for(solution);
+    let memory_limit = state.task.memory_limit_for(solution);
     let extra_time = state.config.extra_time;
     let close_color = if testcase.is_close_to_limits(
         time_limit,
         extra_time,
         memory_limit,
-        YELLOW_RESOURCE_THRESHOLD,
+        state.resource_threshold(),
     ) {
         Some(*ORANGE)
     } else {
         None
     };
-    match &testcase.status {
+    let text = match &testcase.status {
         TestcaseEvaluationStatus::Pending => Span::raw("."),
         TestcaseEvaluationStatus::Solving => Span::raw(format!("{}", loading)),
         TestcaseEvaluationStatus::Solved => Span::raw("s"),
@@ -364,6 +457,13 @@ fn testcase_evaluation_status_text<'a>(
             Span::styled("M", close_color.unwrap_or(*RED))
         }
         TestcaseEvaluationStatus::RuntimeError => Span::styled("R", *RED),
+        TestcaseEvaluationStatus::CheckerError(_) => Span::styled(
+            "C",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+        ),
         TestcaseEvaluationStatus::Failed => Span::styled(
             "F",
             Style::default()
@@ -372,5 +472,10 @@ fn testcase_evaluation_status_text<'a>(
                 .add_modifier(Modifier::BOLD),
         ),
         TestcaseEvaluationStatus::Skipped => Span::raw("X"),
+    };
+    if testcase.was_cached() {
+        text.patch_style(*CACHED)
+    } else {
+        text
     }
 }
@@ -1,11 +1,13 @@
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use anyhow::{anyhow, Context, Error};
+use anyhow::{anyhow, bail, Context, Error};
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
-use task_maker_dag::{Execution, ExecutionCommand, ExecutionStatus, FileUuid, Priority};
+use task_maker_dag::{
+    BuiltinCommand, Execution, ExecutionCommand, ExecutionStatus, FileUuid, Priority,
+};
 use task_maker_diagnostics::Diagnostic;
 
 use crate::ioi::{SubtaskId, TestcaseId, EVALUATION_PRIORITY, STDERR_CONTENT_LENGTH};
@@ -18,8 +20,10 @@ use crate::{EvaluationData, SourceFile, Tag};
 #[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
 pub enum Checker {
     /// Use a built-in white diff checker that scores 1.0 if the two output files are identical
-    /// except for white spaces. It internally uses `diff --ignore-all-spaces`
-    WhiteDiff,
+    /// after their normalization. It's run by the workers without spawning a process, unless the
+    /// external diff is requested: in that case it uses `diff`, or a Python script when the outputs
+    /// are converted to the Unicode NFC.
+    WhiteDiff(OutputNormalization),
     /// Use a custom checker based on an executable that can output a score (from 0.0 to 1.0) to
     /// stdout as well as a custom message on stderr.
     ///
@@ -28,10 +32,119 @@ pub enum Checker {
     Custom(Arc<SourceFile>),
 }
 
+/// How the outputs are normalized by the white diff checker before comparing them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TypeScriptify)]
+#[serde(default)]
+pub struct OutputNormalization {
+    /// Ignore the changes in the amount of white space, including the one at the end of the lines.
+    pub space_change: bool,
+    /// Ignore the white space at the end of the lines.
+    pub trailing_whitespace: bool,
+    /// Ignore the lines that are blank.
+    pub blank_lines: bool,
+    /// Convert the outputs to the Unicode Normalization Form C, so that the characters with many
+    /// encodings (like `è`, which is also `e` followed by a combining accent) compare equal.
+    pub unicode_nfc: bool,
+}
+
+impl Default for OutputNormalization {
+    fn default() -> Self {
+        OutputNormalization {
+            space_change: true,
+            trailing_whitespace: true,
+            blank_lines: true,
+            unicode_nfc: false,
+        }
+    }
+}
+
+/// The script comparing the outputs converted to the Unicode NFC, with the same semantics of the
+/// options of `diff`. It takes the paths of the two files followed by the normalizations to apply.
+const NFC_DIFF_SCRIPT: &str = r#"
+import re, sys, unicodedata
+options = set(sys.argv[3:])
+def lines(path):
+    with open(path, encoding="utf-8", errors="surrogateescape") as f:
+        text = unicodedata.normalize("NFC", f.read())
+    result = []
+    for line in re.findall(r"[^\n]*\n|[^\n]+", text):
+        # the missing line feed at the end is a difference, unless the trailing space is ignored
+        terminated = line.endswith("\n") or bool(options & {"space_change", "trailing_whitespace"})
+        line = line.rstrip("\n")
+        if "space_change" in options:
+            line = re.sub(r"[ \t]+", " ", line).rstrip(" \t")
+        if "trailing_whitespace" in options:
+            line = line.rstrip(" \t")
+        if "blank_lines" in options and not line.strip():
+            continue
+        result.append((line, terminated))
+    return result
+sys.exit(0 if lines(sys.argv[1]) == lines(sys.argv[2]) else 1)
+"#;
+
+impl OutputNormalization {
+    /// The command comparing two files, named `correct` and `test`, that exits with 0 if they are
+    /// equal after the normalization and with 1 otherwise. With `external_diff` the files are
+    /// compared by spawning `diff` (or `python3`) instead of using the builtin white diff.
+    fn command(&self, external_diff: bool) -> (ExecutionCommand, Vec<String>) {
+        if !external_diff {
+            let mut args = vec!["correct".to_string(), "test".to_string()];
+            let options = [
+                (self.space_change, "space_change"),
+                (self.trailing_whitespace, "trailing_whitespace"),
+                (self.blank_lines, "blank_lines"),
+                (self.unicode_nfc, "unicode_nfc"),
+            ];
+            args.extend(
+                options
+                    .iter()
+                    .filter(|(enabled, _)| *enabled)
+                    .map(|(_, name)| name.to_string()),
+            );
+            (ExecutionCommand::builtin(BuiltinCommand::WhiteDiff), args)
+        } else if self.unicode_nfc {
+            let mut args = vec![
+                "-c".to_string(),
+                NFC_DIFF_SCRIPT.to_string(),
+                "correct".to_string(),
+                "test".to_string(),
+            ];
+            let options = [
+                (self.space_change, "space_change"),
+                (self.trailing_whitespace, "trailing_whitespace"),
+                (self.blank_lines, "blank_lines"),
+            ];
+            args.extend(
+                options
+                    .iter()
+                    .filter(|(enabled, _)| *enabled)
+                    .map(|(_, name)| name.to_string()),
+            );
+            (ExecutionCommand::system("python3"), args)
+        } else {
+            let mut args = vec!["--brief".to_string(), "--speed-large-files".to_string()];
+            if self.blank_lines {
+                args.push("--ignore-blank-lines".to_string());
+            }
+            // ignoring the space changes also ignores the trailing white space
+            if self.space_change {
+                args.push("--ignore-space-change".to_string());
+            } else if self.trailing_whitespace {
+                args.push("--ignore-trailing-space".to_string());
+            }
+            args.extend(["correct".to_string(), "test".to_string()]);
+            (ExecutionCommand::system("diff"), args)
+        }
+    }
+}
+
 impl Checker {
     /// Build the execution of the checker for the specified files, the callback will be called when
     /// the result is ready. The execution does not send UI messages yet and it's not added to the
     /// DAG.
+    ///
+    /// If the checker crashes or prints an invalid score a diagnostic is emitted and the callback
+    /// is not called.
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn check<F>(
         &self,
@@ -45,26 +158,53 @@ impl Checker {
     ) -> Result<Execution, Error>
     where
         F: FnOnce(f64, String) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.check_with_error(
+            eval,
+            testcase_id,
+            description,
+            input,
+            correct_output,
+            test_output,
+            callback,
+            |_, _| Ok(()),
+        )
+    }
+
+    /// Same as `check`, but when the checker violates its protocol (i.e. it crashes or it prints
+    /// an invalid score) `on_error` is called with the error message and the callback, instead of
+    /// calling the callback. In strict mode the evaluation is aborted after `on_error`.
+    #[allow(clippy::too_many_arguments)]
+    fn check_with_error<F, E>(
+        &self,
+        eval: &mut EvaluationData,
+        testcase_id: Option<TestcaseId>,
+        description: String,
+        input: FileUuid,
+        correct_output: FileUuid,
+        test_output: FileUuid,
+        callback: F,
+        on_error: E,
+    ) -> Result<Execution, Error>
+    where
+        F: FnOnce(f64, String) -> Result<(), Error> + Send + Sync + 'static,
+        E: FnOnce(String, F) -> Result<(), Error> + Send + Sync + 'static,
     {
         match self {
-            Checker::WhiteDiff => {
-                let mut exec = Execution::new(description, ExecutionCommand::system("diff"));
-                exec.args(vec![
-                    "--brief",
-                    "--speed-large-files",
-                    "--ignore-blank-lines",
-                    "--ignore-space-change",
-                    "correct",
-                    "test",
-                ])
-                .input(correct_output, "correct", false)
-                .input(test_output, "test", false)
-                .tag(Tag::Checking.into())
-                .priority(EVALUATION_PRIORITY - testcase_id.unwrap_or_default() as Priority);
+            Checker::WhiteDiff(normalization) => {
+                let (command, args) = normalization.command(eval.external_diff);
+                let mut exec = Execution::new(description, command);
+                exec.args(args)
+                    .input(correct_output, "correct", false)
+                    .input(test_output, "test", false)
+                    .tag(Tag::Checking.into())
+                    .batchable(true)
+                    .priority(EVALUATION_PRIORITY - testcase_id.unwrap_or_default() as Priority);
 
                 eval.dag.on_execution_done(&exec.uuid, move |result| {
                     match result.status {
-                        // diff exits with 0 if the files are equal
+                        // the white diff (as well as diff and the script) exits with 0 if the
+                        // files are equal
                         ExecutionStatus::Success => callback(1.0, "Output is correct".into())
                             .context("Checker callback failed")?,
                         // return code 1 means the files are different
@@ -72,7 +212,7 @@ impl Checker {
                             callback(0.0, "Output is incorrect".into())
                                 .context("Checker callback failed")?
                         }
-                        _ => unreachable!("diff died badly? {:?}", result),
+                        _ => bail!("The white diff checker failed: {:?}", result.status),
                     };
                     Ok(())
                 });
@@ -92,9 +232,16 @@ impl Checker {
                     .tag(Tag::Checking.into())
                     .capture_stdout(128)
                     .capture_stderr(STDERR_CONTENT_LENGTH)
+                    .batchable(true)
                     .priority(EVALUATION_PRIORITY - testcase_id.unwrap_or_default() as Priority);
                 exec.limits_mut().allow_multiprocess();
                 let sender = eval.sender.clone();
+                let strict = eval.strict;
+                let checker_name = source_file.name();
+                let testcase = match testcase_id {
+                    Some(testcase_id) => format!("testcase {}", testcase_id),
+                    None => "a testcase".to_string(),
+                };
                 eval.dag.on_execution_done(&exec.uuid, move |res| {
                     let stdout = res
                         .stdout
@@ -104,51 +251,118 @@ impl Checker {
                         .ok_or_else(|| anyhow!("Checker stderr not captured"))?;
                     let message = String::from_utf8_lossy(&stderr).trim().to_string();
                     let message = Self::translate_checker_message(message);
-                    if !res.status.is_success() {
-                        let message = if let Some(testcase_id) = testcase_id {
-                            format!(
-                                "Checker failed while computing a score for testcase {}",
-                                testcase_id
-                            )
-                        } else {
-                            "Checker failed while computing a score for a testcase".into()
-                        };
-                        let diagnostic = Diagnostic::error(message)
+                    let error = if !res.status.is_success() {
+                        let error = format!(
+                            "Checker {} failed while computing a score for {}",
+                            checker_name, testcase
+                        );
+                        let diagnostic = Diagnostic::error(&error)
                             .with_note(description)
                             .with_help(format!("The checker crashed with: {:?}", res.status))
                             .with_help_attachment(stderr);
                         sender.add_diagnostic(diagnostic)?;
-                        return Ok(());
-                    }
-                    let score = String::from_utf8_lossy(&stdout);
-                    let score: f64 = match score.trim().parse() {
-                        Ok(score) => score,
-                        Err(e) => {
-                            let message = if let Some(testcase_id) = testcase_id {
-                                format!(
-                                    "Checker returned an invalid score ({:?}) for testcase {}",
-                                    score, testcase_id
-                                )
-                            } else {
-                                format!("Checker returned an invalid score ({:?})", score)
-                            };
-                            let diagnostic = Diagnostic::error(message)
-                                .with_note(description)
-                                .with_help(format!("The parse error is: {:?}", e))
-                                .with_help_attachment(stdout);
-                            sender.add_diagnostic(diagnostic)?;
-                            return Ok(());
+                        error
+                    } else {
+                        match Self::parse_score(&String::from_utf8_lossy(&stdout)) {
+                            Ok(score) => return callback(score, message),
+                            Err(e) => {
+                                let error = format!(
+                                    "Checker {} returned an invalid score for {}",
+                                    checker_name, testcase
+                                );
+                                let diagnostic = Diagnostic::error(&error)
+                                    .with_note(description)
+                                    .with_help(format!(
+                                        "The score must be a number between 0.0 and 1.0, but {}",
+                                        e
+                                    ))
+                                    .with_help_attachment(stdout);
+                                sender.add_diagnostic(diagnostic)?;
+                                error
+                            }
                         }
                     };
-                    callback(score, message)
+                    on_error(error.clone(), callback)?;
+                    if strict {
+                        bail!("{} (strict mode)", error);
+                    }
+                    Ok(())
                 });
                 Ok(exec)
             }
         }
     }
 
+    /// Add to the DAG two checkings of the official output against itself, the second one is never
+    /// cached. An error is emitted if the checker doesn't give full score to the official output or
+    /// if the two scores differ, i.e. the checker is not deterministic.
+    pub(crate) fn verify_and_bind(
+        &self,
+        eval: &mut EvaluationData,
+        subtask_id: SubtaskId,
+        testcase_id: TestcaseId,
+        input: FileUuid,
+        correct_output: FileUuid,
+    ) -> Result<(), Error> {
+        // the scores of the two checkings, compared when both are known
+        let scores: Arc<Mutex<[Option<f64>; 2]>> = Default::default();
+        for (index, description) in ["Verification", "Second verification"]
+            .into_iter()
+            .enumerate()
+        {
+            let scores = scores.clone();
+            let sender = eval.sender.clone();
+            let mut exec = self.check(
+                eval,
+                Some(testcase_id),
+                format!(
+                    "{} of the checker on the official output of testcase {}, subtask {}",
+                    description, testcase_id, subtask_id
+                ),
+                input,
+                correct_output,
+                correct_output,
+                move |score, message| {
+                    if index == 0 && abs_diff_ne!(score, 1.0) {
+                        sender.add_diagnostic(
+                            Diagnostic::error(format!(
+                                "The checker doesn't give full score to the official output of \
+                                testcase {}",
+                                testcase_id
+                            ))
+                            .with_note(format!("It scores {}: {}", score, message)),
+                        )?;
+                    }
+                    let mut scores = scores.lock().unwrap();
+                    scores[index] = Some(score);
+                    if let [Some(first), Some(second)] = *scores {
+                        if abs_diff_ne!(first, second) {
+                            sender.add_diagnostic(
+                                Diagnostic::error(format!(
+                                    "The checker is not deterministic on testcase {}",
+                                    testcase_id
+                                ))
+                                .with_note(format!(
+                                    "Checking the official output twice the scores were {} and {}",
+                                    first, second
+                                )),
+                            )?;
+                        }
+                    }
+                    Ok(())
+                },
+            )?;
+            if index == 1 {
+                exec.tag(Tag::Determinism.into());
+            }
+            eval.dag.add_execution(exec);
+        }
+        Ok(())
+    }
+
     /// Add the checking of the output file to the DAG, binding the callbacks for sending to the UI
-    /// the messages as well as calling `callback` with the outcome of the checker.
+    /// the messages as well as calling `callback` with the outcome of the checker. If the checker
+    /// violates its protocol the testcase scores zero with a `CheckerError`.
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn check_and_bind<S: Into<PathBuf>, F>(
         &self,
@@ -165,7 +379,9 @@ impl Checker {
         F: FnOnce(f64, String) -> Result<(), Error> + Send + Sync + 'static,
     {
         let solution = solution.into();
-        let exec = self.check(
+        let sender = eval.sender.clone();
+        let error_solution = solution.clone();
+        let exec = self.check_with_error(
             eval,
             Some(testcase_id),
             format!(
@@ -178,6 +394,15 @@ impl Checker {
             correct_output,
             test_output,
             callback,
+            move |message, callback| {
+                sender.send(UIMessage::IOICheckerError {
+                    subtask: subtask_id,
+                    testcase: testcase_id,
+                    solution: error_solution,
+                    message: message.clone(),
+                })?;
+                callback(0.0, message)
+            },
         )?;
         bind_exec_callbacks!(
             eval,
@@ -204,4 +429,17 @@ impl Checker {
             _ => message,
         }
     }
+
+    /// Parse the score printed by a checker to its stdout, which must be a number between 0.0 and
+    /// 1.0. The error describes what is wrong with the score.
+    pub fn parse_score(stdout: &str) -> Result<f64, String> {
+        let stdout = stdout.trim();
+        let score: f64 = stdout
+            .parse()
+            .map_err(|e| format!("{:?} is not a number ({})", stdout, e))?;
+        if !(0.0..=1.0).contains(&score) {
+            return Err(format!("{:?} is not between 0.0 and 1.0", stdout));
+        }
+        Ok(score)
+    }
 }
@@ -5,10 +5,12 @@ use anyhow::{bail, Context, Error};
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
-use task_maker_dag::{Execution, File, FileUuid, Priority};
+use task_maker_dag::{Execution, ExecutionCommand, File, FileUuid, Priority};
 use task_maker_diagnostics::Diagnostic;
 
-use crate::ioi::{SubtaskId, TestcaseId, GENERATION_PRIORITY, STDERR_CONTENT_LENGTH};
+use crate::ioi::{
+    testcase_label, SubtaskId, TestcaseId, GENERATION_PRIORITY, STDERR_CONTENT_LENGTH,
+};
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, UISender};
 use crate::{EvaluationData, SourceFile, Tag};
@@ -66,12 +68,14 @@ impl InputGenerator {
     }
 
     /// Add the generation of the input file to the DAG and the callbacks to the UI, returning the
-    /// handle to the input file.
+    /// handle to the input file. `testcase_name` is the name given to the testcase in gen/GEN, if
+    /// any.
     pub(crate) fn generate_and_bind(
         &self,
         eval: &mut EvaluationData,
         subtask_id: SubtaskId,
         testcase_id: TestcaseId,
+        testcase_name: Option<&str>,
     ) -> Result<FileUuid, Error> {
         let (input, gen) = self.generate(
             eval,
@@ -92,19 +96,39 @@ impl InputGenerator {
         // If there is an execution, bind its callbacks and store the input file.
         if let Some(mut gen) = gen {
             gen.capture_stderr(STDERR_CONTENT_LENGTH);
-            bind_exec_callbacks!(eval, gen.uuid, |status| UIMessage::IOIGeneration {
-                subtask: subtask_id,
-                testcase: testcase_id,
-                status
-            })?;
+            let name = testcase_name.map(String::from);
+            bind_exec_callbacks!(
+                eval,
+                gen.uuid,
+                |status, name| UIMessage::IOIGeneration {
+                    subtask: subtask_id,
+                    testcase: testcase_id,
+                    name,
+                    status
+                },
+                name
+            )?;
             let sender = eval.sender.clone();
             let args = gen.args.join(" ");
+            let label = match testcase_name {
+                Some(name) => format!(
+                    "{} ({})",
+                    testcase_id,
+                    testcase_label(subtask_id, testcase_id, Some(name))
+                ),
+                None => testcase_id.to_string(),
+            };
             eval.dag.on_execution_done(&gen.uuid, move |result| {
                 if !result.status.is_success() {
                     let mut diagnostic =
-                        Diagnostic::error(format!("Failed to generate input {}", testcase_id))
+                        Diagnostic::error(format!("Failed to generate input {}", label))
                             .with_note(format!("Generator arguments are: {}", args));
                     if let Some(stderr) = result.stderr {
+                        sender.send(UIMessage::IOIGenerationStderr {
+                            subtask: subtask_id,
+                            testcase: testcase_id,
+                            content: String::from_utf8_lossy(&stderr).into(),
+                        })?;
                         diagnostic = diagnostic.with_help_attachment(stderr);
                     }
                     sender.add_diagnostic(diagnostic)?;
@@ -115,4 +139,71 @@ impl InputGenerator {
         }
         Ok(input)
     }
+
+    /// Add to the DAG a second generation of the input file, with the same arguments, and the
+    /// comparison of its output with `input`, the file of the first generation. An error is emitted
+    /// if the two files are different, i.e. the generator is not deterministic.
+    ///
+    /// The comparison is done only if both the generations succeed, and the static input files
+    /// are not checked.
+    pub(crate) fn verify_and_bind(
+        &self,
+        eval: &mut EvaluationData,
+        subtask_id: SubtaskId,
+        testcase_id: TestcaseId,
+        input: FileUuid,
+    ) -> Result<(), Error> {
+        let (second, gen) = self.generate(
+            eval,
+            format!(
+                "Second generation of input file of testcase {}, subtask {}",
+                testcase_id, subtask_id
+            ),
+            subtask_id,
+            testcase_id,
+        )?;
+        let mut gen = match gen {
+            Some(gen) => gen,
+            None => return Ok(()),
+        };
+        gen.tag(Tag::Determinism.into());
+        let args = gen.args.join(" ");
+        eval.dag.add_execution(gen);
+
+        let mut cmp = Execution::new(
+            format!(
+                "Comparison of the generations of testcase {}, subtask {}",
+                testcase_id, subtask_id
+            ),
+            ExecutionCommand::system("cmp"),
+        );
+        cmp.args(vec!["first", "second"])
+            .input(input, "first", false)
+            .input(second, "second", false)
+            .capture_stdout(STDERR_CONTENT_LENGTH)
+            .tag(Tag::Determinism.into())
+            .batchable(true)
+            .priority(GENERATION_PRIORITY - testcase_id as Priority);
+        let sender = eval.sender.clone();
+        eval.dag.on_execution_done(&cmp.uuid, move |result| {
+            // cmp exits with 0 if the files are equal, with 1 if they differ
+            if !result.status.is_success() {
+                let mut diagnostic = Diagnostic::error(format!(
+                    "The generator of testcase {} is not deterministic",
+                    testcase_id
+                ))
+                .with_note(format!("Generator arguments are: {}", args));
+                if let Some(stdout) = result.stdout {
+                    diagnostic = diagnostic.with_note(format!(
+                        "Running it twice produced different files: {}",
+                        String::from_utf8_lossy(&stdout).trim()
+                    ));
+                }
+                sender.add_diagnostic(diagnostic)?;
+            }
+            Ok(())
+        });
+        eval.dag.add_execution(cmp);
+        Ok(())
+    }
 }
@@ -25,6 +25,10 @@ pub enum InputValidator {
     AssumeValid,
     /// Use a custom command to check if the input file is valid. The command should exit with
     /// non-zero return code if and only if the input is invalid.
+    ///
+    /// The validator can report the values of the variables of the constraints by printing to
+    /// stdout lines like `@value N 100000`, they take precedence over the values read from the
+    /// input file.
     Custom(Arc<SourceFile>, Vec<String>),
 }
 
@@ -49,6 +53,7 @@ impl InputValidator {
                     .context("Failed to execute validator source file")?;
                 exec.input(input, TM_VALIDATION_FILE_NAME, false)
                     .tag(Tag::Generation.into())
+                    .batchable(true)
                     .priority(GENERATION_PRIORITY - testcase_id as Priority)
                     .env("TM_SUBTASK", subtask_id.to_string())
                     .env("TM_TESTCASE", testcase_id.to_string());
@@ -100,6 +105,11 @@ impl InputValidator {
                         testcase_id, subtask_id
                     ));
                     if let Some(stderr) = result.stderr {
+                        sender.send(UIMessage::IOIValidationStderr {
+                            subtask: subtask_id,
+                            testcase: testcase_id,
+                            content: String::from_utf8_lossy(&stderr).into(),
+                        })?;
                         diagnostic = diagnostic.with_help_attachment(stderr);
                     }
                     sender.add_diagnostic(diagnostic)?;
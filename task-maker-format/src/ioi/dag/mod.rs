@@ -1,10 +1,11 @@
 use serde::{Deserialize, Serialize};
 
-pub use checker::Checker;
+pub use checker::{Checker, OutputNormalization};
 pub use input_generator::InputGenerator;
 pub use input_validator::{InputValidator, TM_VALIDATION_FILE_NAME};
 pub use output_generator::OutputGenerator;
 use task_maker_dag::Priority;
+pub(crate) use task_type::set_solution_limits;
 pub use task_type::{BatchTypeData, CommunicationTypeData, TaskType, UserIo};
 
 mod checker;
@@ -91,14 +92,17 @@ impl TestcaseScoreAggregator {
 #[cfg(test)]
 mod tests {
     use itertools::Itertools;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
     use std::sync::atomic::{AtomicBool, Ordering};
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
 
-    use task_maker_dag::{ExecutionResourcesUsage, ExecutionResult, ExecutionStatus, File};
+    use task_maker_dag::{
+        BuiltinCommand, ExecutionCommand, ExecutionResourcesUsage, ExecutionResult,
+        ExecutionStatus, File,
+    };
     use task_maker_lang::GraderMap;
 
-    use crate::ioi::IOITask;
+    use crate::ioi::{IOITask, ScoreManager};
     use crate::ui::UIMessage;
     use crate::{EvaluationData, SourceFile, Tag};
 
@@ -109,7 +113,8 @@ mod tests {
             path: path.into(),
             task_type: TaskType::Batch(BatchTypeData {
                 output_generator: None,
-                checker: Checker::WhiteDiff,
+                checker: Checker::WhiteDiff(Default::default()),
+                two_pass: false,
             }),
             name: "".to_string(),
             title: "".to_string(),
@@ -126,6 +131,14 @@ mod tests {
             booklets: vec![],
             difficulty: None,
             syllabus_level: None,
+            no_cache_failures: vec![],
+            solution_groups: vec![],
+            resource_threshold: None,
+            language_multipliers: Default::default(),
+            time_clock: None,
+            memory_metric: Default::default(),
+            input_format: Default::default(),
+            input_constraints: Default::default(),
             sanity_checks: Default::default(),
         }
     }
@@ -165,7 +178,7 @@ mod tests {
         std::fs::write(&path, "x").unwrap();
         let generator = InputGenerator::StaticFile(path);
         let (mut eval, _) = EvaluationData::new(tmpdir.path());
-        let out = generator.generate_and_bind(&mut eval, 0, 0).unwrap();
+        let out = generator.generate_and_bind(&mut eval, 0, 0, None).unwrap();
         assert!(eval.dag.data.provided_files.contains_key(&out));
         assert!(eval
             .dag
@@ -182,7 +195,7 @@ mod tests {
         let path = tmpdir.path().join("input.txt");
         let generator = InputGenerator::StaticFile(path.clone());
         let (mut eval, _) = EvaluationData::new(tmpdir.path());
-        let gen = generator.generate_and_bind(&mut eval, 0, 0);
+        let gen = generator.generate_and_bind(&mut eval, 0, 0, None);
         assert!(gen.is_err());
         let err = gen.unwrap_err().to_string();
         assert!(err.contains("COPY"));
@@ -197,7 +210,7 @@ mod tests {
         let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
         let generator = InputGenerator::Custom(Arc::new(source), vec![]);
         let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
-        let out = generator.generate_and_bind(&mut eval, 0, 0).unwrap();
+        let out = generator.generate_and_bind(&mut eval, 0, 0, None).unwrap();
         assert_eq!(eval.dag.data.provided_files.len(), 1);
         assert_eq!(eval.dag.data.execution_groups.len(), 1);
         let group = eval.dag.data.execution_groups.values().next().unwrap();
@@ -212,6 +225,76 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn test_input_generator_data_files() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("gen.py");
+        std::fs::write(&path, "x").unwrap();
+        std::fs::write(tmpdir.path().join("words.txt"), "foo").unwrap();
+        let mut source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        source.add_data_file(tmpdir.path().join("words.txt"), "data/words.txt");
+        let generator = InputGenerator::Custom(Arc::new(source), vec![]);
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        generator.generate_and_bind(&mut eval, 0, 0, None).unwrap();
+        generator.generate_and_bind(&mut eval, 0, 1, None).unwrap();
+        // the executable and the data file are provided only once
+        assert_eq!(eval.dag.data.provided_files.len(), 2);
+        let inputs = eval
+            .dag
+            .data
+            .execution_groups
+            .values()
+            .map(|group| group.executions[0].inputs[Path::new("data/words.txt")].file)
+            .collect_vec();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0], inputs[1]);
+        assert!(eval.dag.data.provided_files.contains_key(&inputs[0]));
+    }
+
+    #[test]
+    fn test_input_generator_verify() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("gen.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let generator = InputGenerator::Custom(Arc::new(source), vec!["42".into()]);
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        let input = generator.generate_and_bind(&mut eval, 0, 0, None).unwrap();
+        generator.verify_and_bind(&mut eval, 0, 0, input).unwrap();
+        assert_eq!(eval.dag.data.execution_groups.len(), 3);
+        let determinism = eval
+            .dag
+            .data
+            .execution_groups
+            .values()
+            .filter(|group| group.tag() == Some(Tag::Determinism.into()))
+            .collect_vec();
+        assert_eq!(determinism.len(), 2);
+        let second = determinism
+            .iter()
+            .find(|group| group.executions[0].args == vec!["42"])
+            .unwrap();
+        let second = second.executions[0].stdout.as_ref().unwrap().uuid;
+        let cmp = determinism
+            .iter()
+            .find(|group| group.executions[0].command == ExecutionCommand::system("cmp"))
+            .unwrap();
+        assert!(cmp.executions[0].dependencies().contains(&input));
+        assert!(cmp.executions[0].dependencies().contains(&second));
+    }
+
+    #[test]
+    fn test_input_generator_verify_static() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("input.txt");
+        std::fs::write(&path, "x").unwrap();
+        let generator = InputGenerator::StaticFile(path);
+        let (mut eval, _recv) = EvaluationData::new("");
+        let input = generator.generate_and_bind(&mut eval, 0, 0, None).unwrap();
+        generator.verify_and_bind(&mut eval, 0, 0, input).unwrap();
+        assert_eq!(eval.dag.data.execution_groups.len(), 0);
+    }
+
     #[test]
     fn test_input_validator_assume_valid() {
         let validator = InputValidator::AssumeValid;
@@ -343,9 +426,45 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn test_output_generator_two_pass() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("sol.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let generator = OutputGenerator::Custom(Arc::new(source), vec![]);
+        let file = File::new("input");
+        let val = File::new("validation");
+        let mut task = make_task(tmpdir.path());
+        if let TaskType::Batch(data) = &mut task.task_type {
+            data.two_pass = true;
+        }
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        let out = generator
+            .generate_and_bind(&task, &mut eval, 0, 0, file.uuid, Some(val.uuid))
+            .unwrap()
+            .unwrap();
+        assert_eq!(eval.dag.data.execution_groups.len(), 2);
+        let groups = eval.dag.data.execution_groups.values().collect_vec();
+        let first = groups
+            .iter()
+            .find(|group| group.executions[0].args == vec!["0"])
+            .unwrap();
+        let second = groups
+            .iter()
+            .find(|group| group.executions[0].args == vec!["1"])
+            .unwrap();
+        let intermediate = first.executions[0].stdout.as_ref().unwrap().uuid;
+        assert!(first.executions[0].dependencies().contains(&file.uuid));
+        assert!(first.executions[0].dependencies().contains(&val.uuid));
+        assert!(second.executions[0].dependencies().contains(&intermediate));
+        assert!(!second.executions[0].dependencies().contains(&file.uuid));
+        assert_eq!(second.executions[0].stdout.as_ref().unwrap().uuid, out);
+    }
+
     #[test]
     fn test_checker_whitediff() {
-        let checker = Checker::WhiteDiff;
+        let checker = Checker::WhiteDiff(Default::default());
         let (mut eval, _recv) = EvaluationData::new("");
         let input = File::new("input").uuid;
         let output = File::new("output").uuid;
@@ -359,6 +478,43 @@ mod tests {
         assert_eq!(eval.dag.data.execution_groups.len(), 1);
         let group = eval.dag.data.execution_groups.values().next().unwrap();
         assert_eq!(group.tag().as_ref().unwrap(), &Tag::Checking.into());
+        let exec = &group.executions[0];
+        assert_eq!(
+            exec.command,
+            ExecutionCommand::Builtin(BuiltinCommand::WhiteDiff)
+        );
+        assert_eq!(
+            exec.args,
+            vec![
+                "correct",
+                "test",
+                "space_change",
+                "trailing_whitespace",
+                "blank_lines"
+            ]
+        );
+        assert!(exec.dependencies().contains(&output));
+        assert!(exec.dependencies().contains(&test));
+    }
+
+    #[test]
+    fn test_checker_whitediff_external() {
+        let checker = Checker::WhiteDiff(Default::default());
+        let (mut eval, _recv) = EvaluationData::new("");
+        eval.external_diff = true;
+        let input = File::new("input").uuid;
+        let output = File::new("output").uuid;
+        let test = File::new("test").uuid;
+        checker
+            .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, |_, _| {
+                panic!("the callback should not be called here")
+            })
+            .unwrap();
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        assert_eq!(
+            group.executions[0].command,
+            ExecutionCommand::system("diff")
+        );
         assert!(group.executions[0]
             .args
             .contains(&"--ignore-blank-lines".into()));
@@ -369,9 +525,124 @@ mod tests {
         assert!(group.executions[0].dependencies().contains(&test));
     }
 
+    #[test]
+    fn test_checker_verify() {
+        let checker = Checker::WhiteDiff(Default::default());
+        let (mut eval, recv) = EvaluationData::new("");
+        let input = File::new("input").uuid;
+        let output = File::new("output").uuid;
+        checker
+            .verify_and_bind(&mut eval, 0, 0, input, output)
+            .unwrap();
+        assert_eq!(eval.dag.data.execution_groups.len(), 2);
+        let checks = eval
+            .dag
+            .data
+            .execution_groups
+            .values()
+            .map(|group| {
+                assert_eq!(group.executions[0].dependencies(), vec![output, output]);
+                (
+                    group.tag() == Some(Tag::Determinism.into()),
+                    group.executions[0].uuid,
+                )
+            })
+            .sorted()
+            .collect_vec();
+        assert!(!checks[0].0);
+        assert!(checks[1].0);
+
+        for (second, exec) in checks {
+            // the official output is accepted only the first time
+            let status = if second {
+                ExecutionStatus::ReturnCode(1)
+            } else {
+                ExecutionStatus::Success
+            };
+            let on_done = eval.dag.execution_callbacks().get_mut(&exec).unwrap();
+            on_done.on_done.pop().unwrap()(ExecutionResult {
+                status,
+                was_killed: false,
+                was_cached: false,
+                resources: Default::default(),
+                stdout: None,
+                stderr: None,
+                time_scale: 1.0,
+                cpu: None,
+                run_times: vec![],
+                memory_metric: Default::default(),
+            })
+            .unwrap();
+        }
+        drop(eval);
+        let diagnostics = recv
+            .into_iter()
+            .flat_map(|m| match m {
+                UIMessage::Diagnostic { diagnostic } => Some(diagnostic),
+                _ => None,
+            })
+            .collect_vec();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0]
+            .message()
+            .contains("The checker is not deterministic on testcase 0"));
+    }
+
+    #[test]
+    fn test_checker_whitediff_normalization() {
+        let checker = Checker::WhiteDiff(OutputNormalization {
+            space_change: false,
+            trailing_whitespace: true,
+            blank_lines: false,
+            unicode_nfc: false,
+        });
+        let (mut eval, _recv) = EvaluationData::new("");
+        eval.external_diff = true;
+        let input = File::new("input").uuid;
+        let output = File::new("output").uuid;
+        let test = File::new("test").uuid;
+        checker
+            .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, |_, _| {
+                panic!("the callback should not be called here")
+            })
+            .unwrap();
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        let args = &group.executions[0].args;
+        assert!(args.contains(&"--ignore-trailing-space".into()));
+        assert!(!args.contains(&"--ignore-blank-lines".into()));
+        assert!(!args.contains(&"--ignore-space-change".into()));
+    }
+
+    #[test]
+    fn test_checker_whitediff_unicode_nfc() {
+        let checker = Checker::WhiteDiff(OutputNormalization {
+            unicode_nfc: true,
+            ..Default::default()
+        });
+        let (mut eval, _recv) = EvaluationData::new("");
+        eval.external_diff = true;
+        let input = File::new("input").uuid;
+        let output = File::new("output").uuid;
+        let test = File::new("test").uuid;
+        checker
+            .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, |_, _| {
+                panic!("the callback should not be called here")
+            })
+            .unwrap();
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        let exec = &group.executions[0];
+        assert_eq!(
+            exec.command,
+            ExecutionCommand::System(PathBuf::from("python3"))
+        );
+        assert!(exec.args.contains(&"blank_lines".into()));
+        assert!(exec.dependencies().contains(&output));
+        assert!(exec.dependencies().contains(&test));
+    }
+
     #[test]
     fn test_checker_whitediff_correct() {
-        let checker = Checker::WhiteDiff;
+        let checker = Checker::WhiteDiff(Default::default());
         let (mut eval, _recv) = EvaluationData::new("");
         let input = File::new("input").uuid;
         let output = File::new("output").uuid;
@@ -401,6 +672,10 @@ mod tests {
                 },
                 stdout: None,
                 stderr: None,
+                time_scale: 1.0,
+                cpu: None,
+                run_times: vec![],
+                memory_metric: Default::default(),
             })
             .unwrap();
         });
@@ -409,7 +684,7 @@ mod tests {
 
     #[test]
     fn test_checker_whitediff_incorrect() {
-        let checker = Checker::WhiteDiff;
+        let checker = Checker::WhiteDiff(Default::default());
         let (mut eval, _recv) = EvaluationData::new("");
         let input = File::new("input").uuid;
         let output = File::new("output").uuid;
@@ -439,6 +714,10 @@ mod tests {
                 },
                 stdout: None,
                 stderr: None,
+                time_scale: 1.0,
+                cpu: None,
+                run_times: vec![],
+                memory_metric: Default::default(),
             })
             .unwrap();
         });
@@ -502,6 +781,10 @@ mod tests {
             resources: Default::default(),
             stdout: Some("1.0\n\n".into()),
             stderr: Some("Ok!\n\n".into()),
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
         })
         .unwrap();
 
@@ -540,6 +823,10 @@ mod tests {
             resources: Default::default(),
             stdout: Some("0.0\n\n".into()),
             stderr: Some("Ko!\n\n".into()),
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
         })
         .unwrap();
 
@@ -557,7 +844,14 @@ mod tests {
         let input = File::new("input").uuid;
         let output = File::new("output").uuid;
         let test = File::new("test").uuid;
-        let cb = move |_, _| panic!("the callback should not be called here");
+        let cb_called = Arc::new(AtomicBool::new(false));
+        let cb_called2 = cb_called.clone();
+        let cb = move |score, mex: String| {
+            assert_abs_diff_eq!(score, 0.0);
+            assert!(mex.contains("invalid score"));
+            cb_called2.store(true, Ordering::Relaxed);
+            Ok(())
+        };
         checker
             .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, cb)
             .unwrap();
@@ -571,21 +865,154 @@ mod tests {
             resources: Default::default(),
             stdout: Some(":<\n\n".into()),
             stderr: Some("Ko!\n\n".into()),
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
         })
         .unwrap();
         drop(eval);
+        assert!(cb_called.load(Ordering::Relaxed));
 
-        let diagnostics = recv
-            .into_iter()
+        let messages = recv.into_iter().collect_vec();
+        let diagnostics = messages
+            .iter()
             .flat_map(|m| match m {
                 UIMessage::Diagnostic { diagnostic } => Some(diagnostic),
                 _ => None,
             })
-            .collect_vec();
-        let diagnostics = diagnostics
-            .iter()
             .map(|d| d.message())
-            .any(|m| m.contains("Checker returned an invalid score"));
+            .any(|m| m.contains("Checker check.py returned an invalid score for testcase 0"));
         assert!(diagnostics);
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, UIMessage::IOICheckerError { testcase: 0, .. })));
+    }
+
+    #[test]
+    fn test_checker_custom_invalid_score_strict() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("check.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let checker = Checker::Custom(Arc::new(source));
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        eval.strict = true;
+        let input = File::new("input").uuid;
+        let output = File::new("output").uuid;
+        let test = File::new("test").uuid;
+        checker
+            .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, |_, _| Ok(()))
+            .unwrap();
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        let exec = group.executions[0].uuid;
+        let on_done = eval.dag.execution_callbacks().get_mut(&exec).unwrap();
+        let res = on_done.on_done.remove(0)(ExecutionResult {
+            status: ExecutionStatus::Success,
+            was_killed: false,
+            was_cached: false,
+            resources: Default::default(),
+            stdout: Some("1.5\n".into()),
+            stderr: Some("".into()),
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
+        });
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_checker_parse_score() {
+        assert_abs_diff_eq!(Checker::parse_score("1.0\n\n").unwrap(), 1.0);
+        assert_abs_diff_eq!(Checker::parse_score(" 0.25 ").unwrap(), 0.25);
+        assert!(Checker::parse_score(":<").is_err());
+        assert!(Checker::parse_score("").is_err());
+        assert!(Checker::parse_score("NaN").is_err());
+        assert!(Checker::parse_score("-0.5").is_err());
+        assert!(Checker::parse_score("1.01").is_err());
+        assert!(Checker::parse_score("inf").is_err());
+    }
+
+    #[test]
+    fn test_batch_detect_flaky() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("sol.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let task = make_task(tmpdir.path());
+        let (mut eval, recv) = EvaluationData::new(tmpdir.path());
+        eval.detect_flaky = true;
+        let score_manager = ScoreManager::new(&task, path, eval.sender.clone()).unwrap();
+        let input = File::new("input").uuid;
+        let output = File::new("output").uuid;
+        task.task_type
+            .evaluate(
+                &task,
+                &mut eval,
+                0,
+                0,
+                &source,
+                input,
+                None,
+                Some(output),
+                Arc::new(Mutex::new(score_manager)),
+            )
+            .unwrap();
+        let evaluations = eval
+            .dag
+            .data
+            .execution_groups
+            .values()
+            .flat_map(|group| group.executions.iter())
+            .filter(|exec| exec.command == ExecutionCommand::local("sol"))
+            .map(|exec| (exec.tag == Some(Tag::Determinism.into()), exec.uuid))
+            .collect_vec();
+        assert_eq!(evaluations.len(), 2);
+        let cmp = eval
+            .dag
+            .data
+            .execution_groups
+            .values()
+            .flat_map(|group| group.executions.iter())
+            .find(|exec| exec.command == ExecutionCommand::system("cmp"))
+            .unwrap();
+        assert_eq!(cmp.tag, Some(Tag::Determinism.into()));
+        assert_eq!(cmp.dependencies().len(), 2);
+
+        for (second, exec) in evaluations {
+            let status = if second {
+                ExecutionStatus::TimeLimitExceeded
+            } else {
+                ExecutionStatus::Success
+            };
+            // the comparison of the verdicts is the last callback of the evaluations
+            let on_done = eval.dag.execution_callbacks().get_mut(&exec).unwrap();
+            on_done.on_done.pop().unwrap()(ExecutionResult {
+                status,
+                was_killed: false,
+                was_cached: false,
+                resources: Default::default(),
+                stdout: None,
+                stderr: None,
+                time_scale: 1.0,
+                cpu: None,
+                run_times: vec![],
+                memory_metric: Default::default(),
+            })
+            .unwrap();
+        }
+        drop(eval);
+        let flaky = recv
+            .into_iter()
+            .flat_map(|m| match m {
+                UIMessage::Diagnostic { diagnostic } => Some(diagnostic),
+                _ => None,
+            })
+            .any(|d| {
+                d.message()
+                    .contains("Solution sol.py is flaky on testcase 0")
+            });
+        assert!(flaky);
     }
 }
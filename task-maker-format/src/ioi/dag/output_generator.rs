@@ -27,9 +27,9 @@ pub enum OutputGenerator {
 }
 
 impl OutputGenerator {
-    /// Build the execution for the generation of the output file. Return the handle to the output
-    /// file and the `Execution` if any. The execution does not send UI messages yet and it's not
-    /// added to the DAG.
+    /// Build the executions for the generation of the output file. Return the handle to the output
+    /// file and the `Execution`s, if any: two chained ones in a two-pass task. The executions do
+    /// not send UI messages yet and they are not added to the DAG.
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn generate(
         &self,
@@ -40,13 +40,13 @@ impl OutputGenerator {
         testcase_id: TestcaseId,
         input: FileUuid,
         validation_handle: Option<FileUuid>,
-    ) -> Result<(Option<FileUuid>, Option<Execution>), Error> {
+    ) -> Result<(Option<FileUuid>, Vec<Execution>), Error> {
         match self {
             OutputGenerator::NotAvailable => {
                 let file = File::new("Empty file");
                 let uuid = file.uuid;
                 eval.dag.provide_content(file, vec![]);
-                Ok((Some(uuid), None))
+                Ok((Some(uuid), vec![]))
             }
             OutputGenerator::StaticFile(path) => {
                 if !path.exists() {
@@ -63,16 +63,31 @@ impl OutputGenerator {
                         path.display()
                     )
                 })?;
-                Ok((Some(uuid), None))
+                Ok((Some(uuid), vec![]))
             }
             OutputGenerator::Custom(source_file, args) => {
-                let mut exec = source_file
-                    .execute(eval, description, args.clone())
-                    .context("Failed to execute output generator source file")?;
-                exec.tag(Tag::Generation.into());
-                exec.priority(GENERATION_PRIORITY - testcase_id as Priority);
-                let output = bind_exec_io!(exec, task, input, validation_handle);
-                Ok((Some(output.uuid), Some(exec)))
+                let num_parts = if task.is_two_pass() { 2 } else { 1 };
+                let mut execs = vec![];
+                let mut pass_input = input;
+                let mut pass_validation_handle = validation_handle;
+                for part in 0..num_parts {
+                    let mut args = args.clone();
+                    let mut description = description.clone();
+                    if num_parts > 1 {
+                        args.push(part.to_string());
+                        description = format!("{} (pass {})", description, part + 1);
+                    }
+                    let mut exec = source_file
+                        .execute(eval, description, args)
+                        .context("Failed to execute output generator source file")?;
+                    exec.tag(Tag::Generation.into());
+                    exec.priority(GENERATION_PRIORITY - testcase_id as Priority);
+                    let output = bind_exec_io!(exec, task, pass_input, pass_validation_handle);
+                    execs.push(exec);
+                    pass_input = output.uuid;
+                    pass_validation_handle = None;
+                }
+                Ok((Some(pass_input), execs))
             }
         }
     }
@@ -88,7 +103,7 @@ impl OutputGenerator {
         input: FileUuid,
         validation_handle: Option<FileUuid>,
     ) -> Result<Option<FileUuid>, Error> {
-        let (output, sol) = self.generate(
+        let (output, sols) = self.generate(
             task,
             eval,
             format!(
@@ -100,7 +115,7 @@ impl OutputGenerator {
             input,
             validation_handle,
         )?;
-        if let Some(mut sol) = sol {
+        for mut sol in sols {
             sol.capture_stderr(STDERR_CONTENT_LENGTH);
             bind_exec_callbacks!(eval, sol.uuid, |status| UIMessage::IOISolution {
                 subtask: subtask_id,
@@ -6,6 +6,7 @@ use typescript_definitions::TypeScriptify;
 
 use task_maker_dag::{ExecutionStatus, FileUuid, Priority};
 
+use crate::ioi::dag::task_type::{detect_flakiness, set_solution_limits};
 use crate::ioi::{
     Checker, IOITask, OutputGenerator, ScoreManager, SubtaskId, TestcaseId, EVALUATION_PRIORITY,
 };
@@ -21,9 +22,16 @@ pub struct BatchTypeData {
     pub output_generator: Option<OutputGenerator>,
     /// The checker to use for this task.
     pub checker: Checker,
+    /// Whether the solution is run twice on each testcase: the first pass reads the input file,
+    /// the second pass reads the output of the first one, and its output is checked.
+    #[serde(default)]
+    pub two_pass: bool,
 }
 
 /// Evaluate a solution in a task of Batch type.
+///
+/// In a two-pass task the solution is executed twice, with the number of the pass (`0` or `1`) as
+/// argument. The output of the first pass is kept in the store and it is the input of the second.
 #[allow(clippy::too_many_arguments)]
 pub fn evaluate(
     task: &IOITask,
@@ -38,57 +46,103 @@ pub fn evaluate(
     data: &BatchTypeData,
 ) -> Result<(), Error> {
     let correct_output = correct_output.ok_or_else(|| anyhow!("Missing official solution"))?;
-    let mut exec = source_file
-        .execute(
-            eval,
-            format!(
-                "Evaluation of {} on testcase {}, subtask {}",
-                source_file.name(),
-                testcase_id,
-                subtask_id
-            ),
-            Vec::<String>::new(),
-        )
-        .context("Failed to execute solution source file")?;
-    exec.tag(Tag::Evaluation.into());
-    exec.priority(EVALUATION_PRIORITY - testcase_id as Priority);
-    let output = bind_exec_io!(exec, task, input, validation_handle);
+    let num_parts = if data.two_pass { 2 } else { 1 };
     let path = source_file.path.clone();
-    let limits = exec.limits_mut();
-    if let Some(time_limit) = task.time_limit {
-        limits.cpu_time(time_limit);
-        limits.wall_time(time_limit * 1.5 + 1.0); // some margin
+    let mut pass_input = input;
+    let mut pass_validation_handle = validation_handle;
+    let mut last_pass = None;
+    for part in 0..num_parts {
+        let (description, args) = if data.two_pass {
+            (
+                format!(
+                    "Evaluation of {} (pass {}) on testcase {}, subtask {}",
+                    source_file.name(),
+                    part + 1,
+                    testcase_id,
+                    subtask_id
+                ),
+                vec![part.to_string()],
+            )
+        } else {
+            (
+                format!(
+                    "Evaluation of {} on testcase {}, subtask {}",
+                    source_file.name(),
+                    testcase_id,
+                    subtask_id
+                ),
+                vec![],
+            )
+        };
+        let mut exec = source_file
+            .execute(eval, description, args)
+            .context("Failed to execute solution source file")?;
+        exec.tag(Tag::Evaluation.into());
+        exec.repeated(true);
+        exec.priority(EVALUATION_PRIORITY - testcase_id as Priority);
+        let output = bind_exec_io!(exec, task, pass_input, pass_validation_handle);
+        set_solution_limits(task, &path, exec.limits_mut());
+        bind_exec_callbacks!(
+            eval,
+            exec.uuid,
+            |status, solution| UIMessage::IOIEvaluation {
+                subtask: subtask_id,
+                testcase: testcase_id,
+                solution,
+                status,
+                part,
+                num_parts,
+            },
+            path
+        )?;
+        let sender = eval.sender.clone();
+        let score_manager_err = score_manager.clone();
+        // a failed pass skips the following ones, so the testcase is scored only once
+        eval.dag
+            .on_execution_done(&exec.uuid, move |result| match result.status {
+                ExecutionStatus::Success => Ok(()),
+                _ => score_manager_err.lock().unwrap().score(
+                    subtask_id,
+                    testcase_id,
+                    0.0,
+                    format!("{:?}", result.status),
+                    sender,
+                ),
+            });
+        last_pass = Some((exec.uuid, output.uuid));
+        eval.dag.add_execution(exec);
+        pass_input = output.uuid;
+        pass_validation_handle = None;
     }
-    if let Some(memory_limit) = task.memory_limit {
-        limits.memory(memory_limit * 1024); // MiB -> KiB
+    let (exec_uuid, output) = last_pass.expect("The solution is executed at least once");
+
+    // the second evaluation of a two-pass solution would need both the passes
+    if eval.detect_flaky && !data.two_pass {
+        let mut second = source_file
+            .execute(
+                eval,
+                format!(
+                    "Second evaluation of {} on testcase {}, subtask {}",
+                    source_file.name(),
+                    testcase_id,
+                    subtask_id
+                ),
+                Vec::<String>::new(),
+            )
+            .context("Failed to execute solution source file")?;
+        let second_output = bind_exec_io!(second, task, input, validation_handle);
+        set_solution_limits(task, &path, second.limits_mut());
+        detect_flakiness(
+            eval,
+            source_file.name(),
+            subtask_id,
+            testcase_id,
+            exec_uuid,
+            output,
+            second,
+            second_output.uuid,
+        )?;
     }
-    bind_exec_callbacks!(
-        eval,
-        exec.uuid,
-        |status, solution| UIMessage::IOIEvaluation {
-            subtask: subtask_id,
-            testcase: testcase_id,
-            solution,
-            status,
-            part: 0,
-            num_parts: 1,
-        },
-        path
-    )?;
-    let sender = eval.sender.clone();
-    let score_manager_err = score_manager.clone();
-    eval.dag
-        .on_execution_done(&exec.uuid, move |result| match result.status {
-            ExecutionStatus::Success => Ok(()),
-            _ => score_manager_err.lock().unwrap().score(
-                subtask_id,
-                testcase_id,
-                0.0,
-                format!("{:?}", result.status),
-                sender,
-            ),
-        });
-    eval.dag.add_execution(exec);
 
     let sender = eval.sender.clone();
     data.checker.check_and_bind(
@@ -98,7 +152,7 @@ pub fn evaluate(
         source_file.path.clone(),
         input,
         correct_output,
-        output.uuid,
+        output,
         move |score, message| {
             score_manager
                 .lock()
@@ -1,14 +1,16 @@
 use std::sync::{Arc, Mutex};
 
-use anyhow::{anyhow, Context, Error};
+use anyhow::{anyhow, bail, Context, Error};
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
 use task_maker_dag::{ExecutionGroup, FileUuid, Priority};
+use task_maker_diagnostics::Diagnostic;
 
+use crate::ioi::dag::task_type::set_solution_limits;
 use crate::ioi::{Checker, IOITask, ScoreManager, SubtaskId, TestcaseId, EVALUATION_PRIORITY};
 use crate::ui::{UIMessage, UIMessageSender};
-use crate::{bind_exec_callbacks, bind_exec_io};
+use crate::{bind_exec_callbacks, bind_exec_io, UISender};
 use crate::{EvaluationData, SourceFile, Tag};
 
 /// The type of communication for the solution in a communication task.
@@ -142,14 +144,7 @@ pub fn evaluate(
         }
         sol_exec.tag(Tag::Evaluation.into());
         sol_exec.priority(EVALUATION_PRIORITY - testcase_id as Priority);
-        let limits = sol_exec.limits_mut();
-        if let Some(time_limit) = task.time_limit {
-            limits.cpu_time(time_limit);
-            limits.wall_time(time_limit * 1.5 + 1.0); // some margin
-        }
-        if let Some(memory_limit) = task.memory_limit {
-            limits.memory(memory_limit * 1024); // MiB -> KiB
-        }
+        set_solution_limits(task, &path, sol_exec.limits_mut());
         bind_exec_callbacks!(
             eval,
             sol_exec.uuid,
@@ -201,7 +196,8 @@ pub fn evaluate(
         .capture_stderr(1024);
     bind_exec_io!(manager_exec, task, input, validation_handle);
     let limits = manager_exec.limits_mut();
-    if let Some(time_limit) = task.time_limit {
+    // the manager waits for the solutions, so it has their time limit
+    if let Some(time_limit) = task.time_limit_for(&path) {
         let cpu_time = (time_limit + 1.0) * num_processes as f64;
         let wall_time = cpu_time * 1.5 + 1.0; // some margin
         limits.cpu_time(cpu_time);
@@ -221,6 +217,9 @@ pub fn evaluate(
         },
         path
     )?;
+    let sender = eval.sender.clone();
+    let strict = eval.strict;
+    let manager_name = data.manager.name();
     eval.dag
         .on_execution_done(&manager_exec.uuid, move |result| {
             if !result.status.is_success() {
@@ -233,8 +232,33 @@ pub fn evaluate(
             let stderr = result
                 .stderr
                 .ok_or_else(|| anyhow!("Checker stderr not captured"))?;
-            let score = String::from_utf8_lossy(&stdout);
-            let score: f64 = score.trim().parse().context("Invalid score from checker")?;
+            let score = match Checker::parse_score(&String::from_utf8_lossy(&stdout)) {
+                Ok(score) => score,
+                Err(e) => {
+                    let error = format!(
+                        "Manager {} returned an invalid score for testcase {}",
+                        manager_name, testcase_id
+                    );
+                    let diagnostic = Diagnostic::error(&error)
+                        .with_help(format!(
+                            "The score must be a number between 0.0 and 1.0, but {}",
+                            e
+                        ))
+                        .with_help_attachment(stdout);
+                    sender.add_diagnostic(diagnostic)?;
+                    sender.send(UIMessage::IOICheckerError {
+                        subtask: subtask_id,
+                        testcase: testcase_id,
+                        solution: path,
+                        message: error.clone(),
+                    })?;
+                    score_sender.send(0.0, error.clone())?;
+                    if strict {
+                        bail!("{} (strict mode)", error);
+                    }
+                    return Ok(());
+                }
+            };
             let message = String::from_utf8_lossy(&stderr).trim().to_string();
             let message = Checker::translate_checker_message(message);
             score_sender.send(score, message)?;
@@ -1,3 +1,4 @@
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 use anyhow::Error;
@@ -6,10 +7,17 @@ use typescript_definitions::TypeScriptify;
 
 pub use batch::BatchTypeData;
 pub use communication::{CommunicationTypeData, UserIo};
-use task_maker_dag::FileUuid;
+use task_maker_dag::{
+    Execution, ExecutionCommand, ExecutionLimits, ExecutionStatus, ExecutionUuid, FileUuid,
+    Priority, TimeClock,
+};
+use task_maker_diagnostics::Diagnostic;
 
-use crate::ioi::{Checker, IOITask, ScoreManager, SubtaskId, TestcaseId};
-use crate::{EvaluationData, SourceFile};
+use crate::ioi::{
+    Checker, IOITask, ScoreManager, SubtaskId, TestcaseId, EVALUATION_PRIORITY,
+    STDERR_CONTENT_LENGTH,
+};
+use crate::{EvaluationData, SourceFile, Tag, UISender};
 
 mod batch;
 mod communication;
@@ -82,7 +90,7 @@ impl TaskType {
                 Checker::Custom(checker) => {
                     checker.prepare(eval)?;
                 }
-                Checker::WhiteDiff => {}
+                Checker::WhiteDiff(_) => {}
             },
             TaskType::Communication(communication) => {
                 communication.manager.prepare(eval)?;
@@ -92,3 +100,107 @@ impl TaskType {
         Ok(())
     }
 }
+
+/// Set the time and memory limits of an execution of a solution, following the multipliers of its
+/// language and the clock of the task.
+pub(crate) fn set_solution_limits(task: &IOITask, solution: &Path, limits: &mut ExecutionLimits) {
+    if let Some(time_limit) = task.time_limit_for(solution) {
+        match task.time_clock() {
+            TimeClock::Cpu => {
+                limits.cpu_time(time_limit);
+                limits.wall_time(time_limit * 1.5 + 1.0); // some margin
+            }
+            TimeClock::Wall => {
+                limits.wall_time(time_limit);
+            }
+        }
+        limits.time_clock(task.time_clock());
+    }
+    if let Some(memory_limit) = task.memory_limit_for(solution) {
+        limits.memory(memory_limit * 1024); // MiB -> KiB
+        limits.memory_metric(task.memory_metric);
+    }
+}
+
+/// Add to the DAG the second evaluation of a solution on a testcase, comparing its verdict and its
+/// output with the ones of the first evaluation. A warning is emitted for each difference.
+///
+/// The outputs are compared only if both the evaluations succeed.
+#[allow(clippy::too_many_arguments)]
+fn detect_flakiness(
+    eval: &mut EvaluationData,
+    name: String,
+    subtask_id: SubtaskId,
+    testcase_id: TestcaseId,
+    first: ExecutionUuid,
+    first_output: FileUuid,
+    mut second: Execution,
+    second_output: FileUuid,
+) -> Result<(), Error> {
+    second
+        .tag(Tag::Determinism.into())
+        .priority(EVALUATION_PRIORITY - testcase_id as Priority);
+
+    // the statuses of the two evaluations, compared when both are known
+    let statuses: Arc<Mutex<[Option<ExecutionStatus>; 2]>> = Default::default();
+    for (index, uuid) in [first, second.uuid].into_iter().enumerate() {
+        let statuses = statuses.clone();
+        let sender = eval.sender.clone();
+        let name = name.clone();
+        eval.dag.on_execution_done(&uuid, move |result| {
+            let mut statuses = statuses.lock().unwrap();
+            statuses[index] = Some(result.status);
+            if let [Some(first), Some(second)] = &*statuses {
+                if first != second {
+                    sender.add_diagnostic(
+                        Diagnostic::warning(format!(
+                            "Solution {} is flaky on testcase {}",
+                            name, testcase_id
+                        ))
+                        .with_note(format!(
+                            "Evaluating it twice the verdicts were {:?} and {:?}",
+                            first, second
+                        )),
+                    )?;
+                }
+            }
+            Ok(())
+        });
+    }
+    eval.dag.add_execution(second);
+
+    let mut cmp = Execution::new(
+        format!(
+            "Comparison of the evaluations of {} on testcase {}, subtask {}",
+            name, testcase_id, subtask_id
+        ),
+        ExecutionCommand::system("cmp"),
+    );
+    cmp.args(vec!["first", "second"])
+        .input(first_output, "first", false)
+        .input(second_output, "second", false)
+        .capture_stdout(STDERR_CONTENT_LENGTH)
+        .tag(Tag::Determinism.into())
+        .batchable(true)
+        .priority(EVALUATION_PRIORITY - testcase_id as Priority);
+    let sender = eval.sender.clone();
+    eval.dag.on_execution_done(&cmp.uuid, move |result| {
+        // cmp exits with 0 if the files are equal, with 1 if they differ
+        if !result.status.is_success() {
+            let mut diagnostic = Diagnostic::warning(format!(
+                "Solution {} is flaky on testcase {}",
+                name, testcase_id
+            ));
+            if let Some(stdout) = result.stdout {
+                diagnostic = diagnostic.with_note(format!(
+                    "Evaluating it twice produced different outputs: {}",
+                    String::from_utf8_lossy(&stdout).trim()
+                ));
+            }
+            sender.add_diagnostic(diagnostic)?;
+        }
+        Ok(())
+    });
+    eval.dag.add_execution(cmp);
+    Ok(())
+}
@@ -4,15 +4,17 @@ use std::path::{Path, PathBuf};
 use itertools::Itertools;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream};
 
-use task_maker_dag::ExecutionStatus;
+use task_maker_dag::{ExecutionStatus, MemoryMetric};
 
-use crate::ioi::ui_state::{SolutionEvaluationState, TestcaseEvaluationStatus, UIState};
+use crate::ioi::ui_state::{
+    SolutionEvaluationState, TestcaseEvaluationStatus, TestcaseResources, UIState,
+};
 use crate::ioi::{
     IOITask, SolutionCheckOutcome, SolutionTestcaseEvaluationState, SubtaskId, TestcaseId,
 };
 use crate::ui::{
-    FinishUI as FinishUITrait, FinishUIUtils, UIExecutionStatus, BLUE, BOLD, GREEN, ORANGE, RED,
-    YELLOW,
+    color_choice, FinishUI as FinishUITrait, FinishUIUtils, UIExecutionStatus, UIStateT, BLUE,
+    BOLD, GREEN, ORANGE, RED, YELLOW,
 };
 use crate::{cwrite, cwriteln, ScoreStatus};
 
@@ -24,6 +26,8 @@ pub const BOLD_RESOURCE_THRESHOLD: f64 = 0.9;
 /// a solution is >= time limit of the task * YELLOW_RESOURCE_THRESHOLD, it is shown in yellow. Same
 /// for the memory usage.
 pub const YELLOW_RESOURCE_THRESHOLD: f64 = 0.6;
+/// Number of evaluations listed in the rankings of the slowest and of the largest ones.
+const RANKING_LENGTH: usize = 5;
 
 /// UI that prints to `stdout` the ending result of the evaluation of a IOI task.
 pub struct FinishUI {
@@ -34,7 +38,7 @@ pub struct FinishUI {
 impl FinishUITrait<UIState> for FinishUI {
     fn print(state: &UIState) {
         let mut ui = FinishUI {
-            stream: StandardStream::stdout(ColorChoice::Auto),
+            stream: StandardStream::stdout(color_choice()),
         };
         ui.print_task_info(state);
         if !state.compilations.is_empty() {
@@ -56,6 +60,16 @@ impl FinishUITrait<UIState> for FinishUI {
                 ui.print_subtask_checks_table(state);
             }
             ui.print_summary(state);
+            println!();
+            ui.print_rankings(state);
+        }
+        if state.cache_stats.total() > 0 {
+            println!();
+            FinishUIUtils::new(&mut ui.stream).print_cache_stats(&state.cache_stats);
+        }
+        if !state.timing_stability.affected_workers.is_empty() {
+            println!();
+            FinishUIUtils::new(&mut ui.stream).print_timing_stability(&state.timing_stability);
         }
         FinishUIUtils::new(&mut ui.stream).print_diagnostic_messages(&state.diagnostics);
     }
@@ -85,9 +99,28 @@ impl FinishUI {
             state
                 .task
                 .memory_limit
-                .map(|t| format!("{}MiB", t))
+                .map(|t| match state.task.memory_metric {
+                    MemoryMetric::AddressSpace => format!("{}MiB", t),
+                    MemoryMetric::Rss => format!("{}MiB (peak RSS)", t),
+                })
                 .unwrap_or_else(|| "unlimited".to_string())
         );
+        if !state.task.language_multipliers.is_empty() {
+            cwrite!(self, BOLD, "Multipliers:  ");
+            println!(
+                "{}",
+                state
+                    .task
+                    .language_multipliers
+                    .iter()
+                    .sorted_by_key(|(language, _)| *language)
+                    .map(|(language, multiplier)| format!(
+                        "{} (time {}x, memory {}x)",
+                        language, multiplier.time, multiplier.memory
+                    ))
+                    .join(", ")
+            );
+        }
     }
 
     /// Print all the booklet states.
@@ -126,6 +159,9 @@ impl FinishUI {
             println!(": {} points", state.task.subtasks[st_num].max_score);
             for (tc_num, testcase) in subtask.testcases.iter().sorted_by_key(|(n, _)| *n) {
                 print!("#{:<3} ", tc_num);
+                if let Some(name) = state.task.testcase_name(*tc_num) {
+                    print!("[{}] ", name);
+                }
 
                 let mut first = true;
                 let mut gen_failed = false;
@@ -189,10 +225,16 @@ impl FinishUI {
     /// Print all the evaluation states.
     fn print_evaluations(&mut self, state: &UIState) {
         cwriteln!(self, BLUE, "Evaluations");
-        for path in state.evaluations.keys().sorted() {
-            let eval = &state.evaluations[path];
-            self.print_evaluation(path, state.max_score, eval, state);
-            println!();
+        let grouped = !state.task.solution_groups.is_empty();
+        for group in state.solution_groups() {
+            if grouped {
+                cwriteln!(self, BOLD, "{}", group.title(state));
+            }
+            for path in &group.solutions {
+                let eval = &state.evaluations[path];
+                self.print_evaluation(path, state.max_score, eval, state);
+                println!();
+            }
         }
     }
 
@@ -239,7 +281,7 @@ impl FinishUI {
             println!();
             for tc_num in &state.task.subtasks[st_num].testcases_owned {
                 let testcase = &eval.testcases[tc_num];
-                self.print_testcase_outcome(&name, *tc_num, testcase, max_time, max_memory, state);
+                self.print_testcase_outcome(path, *tc_num, testcase, max_time, max_memory, state);
             }
         }
     }
@@ -247,7 +289,7 @@ impl FinishUI {
     /// Print the testcase info line for a single solution.
     fn print_testcase_outcome(
         &mut self,
-        name: &str,
+        path: &Path,
         tc_num: TestcaseId,
         testcase: &SolutionTestcaseEvaluationState,
         max_time: f64,
@@ -268,20 +310,20 @@ impl FinishUI {
             print!("[X.{:X<prec$}]", "", prec = score_precision);
         }
         // print the time and memory info
+        let time_limit = state.task.time_limit_for(path);
+        let memory_limit = state.task.memory_limit_for(path);
         for result in &testcase.results {
             if let Some(result) = result {
                 print!(" [");
                 let time_color = FinishUI::resource_color(
                     result.resources.cpu_time,
                     max_time * BOLD_RESOURCE_THRESHOLD,
-                    state.task.time_limit.unwrap_or(f64::INFINITY) * YELLOW_RESOURCE_THRESHOLD,
+                    time_limit.unwrap_or(f64::INFINITY) * state.resource_threshold(),
                 );
                 let memory_color = FinishUI::resource_color(
                     result.resources.memory as f64,
                     max_memory as f64 * BOLD_RESOURCE_THRESHOLD,
-                    state.task.memory_limit.unwrap_or(u64::MAX) as f64
-                        * 1024.0
-                        * YELLOW_RESOURCE_THRESHOLD,
+                    memory_limit.unwrap_or(u64::MAX) as f64 * 1024.0 * state.resource_threshold(),
                 );
                 cwrite!(self, time_color, "{:2.3}s", result.resources.cpu_time);
                 print!(" | ");
@@ -297,12 +339,7 @@ impl FinishUI {
             }
         }
         print!(" {}", testcase.status.message());
-        let mut was_killed = false;
-        let mut was_cached = true;
-        for res in testcase.results.iter().flatten() {
-            was_killed |= res.was_killed;
-            was_cached &= res.was_cached;
-        }
+        let was_killed = testcase.results.iter().flatten().any(|r| r.was_killed);
         for result in testcase.results.iter().flatten() {
             match &result.status {
                 ExecutionStatus::ReturnCode(code) => print!(": Exited with {}", code),
@@ -314,11 +351,15 @@ impl FinishUI {
         if was_killed {
             print!(" (killed)");
         }
-        if was_cached {
+        if testcase.was_cached() {
             print!(" (from cache)");
         }
+        if let Some(name) = state.task.testcase_name(tc_num) {
+            print!(" [{}]", name);
+        }
         if FinishUI::is_ansi() {
-            self.print_right(format!("[{}]", name));
+            let name = path.file_name().expect("Invalid file name");
+            self.print_right(format!("[{}]", name.to_string_lossy()));
         }
         println!();
     }
@@ -353,86 +394,146 @@ impl FinishUI {
             cwrite!(self, BOLD, " {:^3.0} ", subtask.max_score);
         }
         println!();
-        for path in state.evaluations.keys().sorted() {
-            let eval = &state.evaluations[path];
-            print!(
-                "{:>width$} ",
-                path.file_name()
-                    .expect("Invalid file name")
-                    .to_string_lossy(),
-                width = max_len
-            );
-            if let Some(score) = eval.score {
-                print!(
-                    "{:>width$.prec$} | ",
-                    score,
-                    width = column_width,
-                    prec = score_precision
-                );
-            } else if score_precision == 0 {
-                print!("{:>width$} | ", "X", width = column_width);
-            } else {
+        let grouped = !state.task.solution_groups.is_empty();
+        for group in state.solution_groups() {
+            if grouped {
+                cwriteln!(self, BOLD, "{}", group.title(state));
+            }
+            for path in &group.solutions {
+                let eval = &state.evaluations[path];
                 print!(
-                    "{:>width$}{:X>prec$} | ",
-                    "X.",
-                    "",
-                    width = column_width - score_precision,
-                    prec = score_precision
+                    "{:>width$} ",
+                    path.file_name()
+                        .expect("Invalid file name")
+                        .to_string_lossy(),
+                    width = max_len
                 );
-            }
-            for st_num in eval.subtasks.keys().sorted() {
-                let subtask = &eval.subtasks[st_num];
-                let score = subtask.score;
-                let normalized_score = subtask.normalized_score;
-                if let (Some(score), Some(normalized_score)) = (score, normalized_score) {
-                    let color = self.score_color(normalized_score);
-                    cwrite!(self, color, " {:^3.0} ", score);
+                if let Some(score) = eval.score {
+                    print!(
+                        "{:>width$.prec$} | ",
+                        score,
+                        width = column_width,
+                        prec = score_precision
+                    );
+                } else if score_precision == 0 {
+                    print!("{:>width$} | ", "X", width = column_width);
                 } else {
-                    print!(" {:^3} ", "X");
+                    print!(
+                        "{:>width$}{:X>prec$} | ",
+                        "X.",
+                        "",
+                        width = column_width - score_precision,
+                        prec = score_precision
+                    );
                 }
-            }
-            print!("  ");
-            for st_num in eval.subtasks.keys().sorted() {
-                let subtask = &eval.subtasks[st_num];
-                let normalized_score = subtask.normalized_score.unwrap_or(0.0);
-                let color = self.score_color(normalized_score);
-                cwrite!(self, color, "[");
-                let time_limit = state.task.time_limit;
-                let memory_limit = state.task.memory_limit;
-                let extra_time = state.config.extra_time;
-                for tc_num in &state.task.subtasks[st_num].testcases_owned {
-                    let testcase = &eval.testcases[tc_num];
-                    let close_color = if testcase.is_close_to_limits(
-                        time_limit,
-                        extra_time,
-                        memory_limit,
-                        YELLOW_RESOURCE_THRESHOLD,
-                    ) {
-                        Some(&*ORANGE)
+                for st_num in eval.subtasks.keys().sorted() {
+                    let subtask = &eval.subtasks[st_num];
+                    let score = subtask.score;
+                    let normalized_score = subtask.normalized_score;
+                    if let (Some(score), Some(normalized_score)) = (score, normalized_score) {
+                        let color = self.score_color(normalized_score);
+                        cwrite!(self, color, " {:^3.0} ", score);
                     } else {
-                        None
-                    };
-                    use TestcaseEvaluationStatus::*;
-                    match testcase.status {
-                        Accepted(_) => cwrite!(self, close_color.unwrap_or(&*GREEN), "A"),
-                        WrongAnswer(_) => cwrite!(self, RED, "W"),
-                        Partial(_) => cwrite!(self, close_color.unwrap_or(&*YELLOW), "P"),
-                        TimeLimitExceeded => cwrite!(self, close_color.unwrap_or(&*RED), "T"),
-                        WallTimeLimitExceeded => cwrite!(self, RED, "T"),
-                        MemoryLimitExceeded => cwrite!(self, close_color.unwrap_or(&*RED), "M"),
-                        RuntimeError => cwrite!(self, RED, "R"),
-                        Failed => cwrite!(self, BOLD, "F"),
-                        Skipped => cwrite!(self, BOLD, "S"),
-                        _ => cwrite!(self, BOLD, "X"),
+                        print!(" {:^3} ", "X");
                     }
                 }
-                cwrite!(self, color, "]");
+                print!("  ");
+                for st_num in eval.subtasks.keys().sorted() {
+                    let subtask = &eval.subtasks[st_num];
+                    let normalized_score = subtask.normalized_score.unwrap_or(0.0);
+                    let color = self.score_color(normalized_score);
+                    cwrite!(self, color, "[");
+                    let time_limit = state.task.time_limit_for(path);
+                    let memory_limit = state.task.memory_limit_for(path);
+                    let extra_time = state.config.extra_time;
+                    for tc_num in &state.task.subtasks[st_num].testcases_owned {
+                        let testcase = &eval.testcases[tc_num];
+                        let close_color = if testcase.is_close_to_limits(
+                            time_limit,
+                            extra_time,
+                            memory_limit,
+                            state.resource_threshold(),
+                        ) {
+                            Some(&*ORANGE)
+                        } else {
+                            None
+                        };
+                        use TestcaseEvaluationStatus::*;
+                        match testcase.status {
+                            Accepted(_) => cwrite!(self, close_color.unwrap_or(&*GREEN), "A"),
+                            WrongAnswer(_) => cwrite!(self, RED, "W"),
+                            Partial(_) => cwrite!(self, close_color.unwrap_or(&*YELLOW), "P"),
+                            TimeLimitExceeded => cwrite!(self, close_color.unwrap_or(&*RED), "T"),
+                            WallTimeLimitExceeded => cwrite!(self, RED, "T"),
+                            MemoryLimitExceeded => cwrite!(self, close_color.unwrap_or(&*RED), "M"),
+                            RuntimeError => cwrite!(self, RED, "R"),
+                            CheckerError(_) => cwrite!(self, BOLD, "C"),
+                            Failed => cwrite!(self, BOLD, "F"),
+                            Skipped => cwrite!(self, BOLD, "S"),
+                            _ => cwrite!(self, BOLD, "X"),
+                        }
+                    }
+                    cwrite!(self, color, "]");
+                }
+                println!();
             }
-            println!();
         }
         println!();
     }
 
+    /// Print the evaluations that used the most time and the most memory, highlighting the ones
+    /// close to the limits.
+    fn print_rankings(&mut self, state: &UIState) {
+        let slowest = state.slowest_evaluations(RANKING_LENGTH);
+        if slowest.is_empty() {
+            return;
+        }
+        let name_len = |ranking: &[TestcaseResources]| {
+            ranking
+                .iter()
+                .map(|e| e.solution.file_name().map_or(0, |name| name.len()))
+                .max()
+                .unwrap_or(0)
+        };
+        cwriteln!(self, BLUE, "Slowest testcases");
+        let width = name_len(&slowest);
+        for evaluation in &slowest {
+            let name = evaluation.solution.file_name().unwrap_or_default();
+            print!("{:>width$} ", name.to_string_lossy(), width = width);
+            let time = format!("{:>7.3}s", evaluation.resources.cpu_time);
+            let (close_time, _) =
+                state.close_to_limits(&evaluation.solution, &evaluation.resources);
+            if close_time {
+                cwrite!(self, YELLOW, "{}", time);
+            } else {
+                print!("{}", time);
+            }
+            println!(
+                " on testcase {}",
+                state.task.testcase_label(evaluation.testcase)
+            );
+        }
+        let largest = state.largest_evaluations(RANKING_LENGTH);
+        cwriteln!(self, BLUE, "Largest memory usage");
+        let width = name_len(&largest);
+        for evaluation in &largest {
+            let name = evaluation.solution.file_name().unwrap_or_default();
+            print!("{:>width$} ", name.to_string_lossy(), width = width);
+            let memory = format!("{:>7.1}MiB", evaluation.resources.memory as f64 / 1024.0);
+            let (_, close_memory) =
+                state.close_to_limits(&evaluation.solution, &evaluation.resources);
+            if close_memory {
+                cwrite!(self, YELLOW, "{}", memory);
+            } else {
+                print!("{}", memory);
+            }
+            println!(
+                " on testcase {}",
+                state.task.testcase_label(evaluation.testcase)
+            );
+        }
+    }
+
     /// Print the score fraction of a solution using colors.
     fn print_score_frac(
         &mut self,
@@ -492,9 +593,12 @@ impl FinishUI {
         print!("\x1b[1000C\x1b[{}D{}", what.len() - 1, what);
     }
 
-    /// Check if ANSI is supported: if not in windows and not in a "dumb" terminal.
+    /// Check if ANSI is supported: if not in windows, not in a "dumb" terminal and the colors are
+    /// not disabled.
     fn is_ansi() -> bool {
-        !cfg!(windows) && std::env::var("TERM").map(|v| v != "dumb").unwrap_or(false)
+        !cfg!(windows)
+            && color_choice() != ColorChoice::Never
+            && std::env::var("TERM").map(|v| v != "dumb").unwrap_or(false)
     }
 
     /// Print the status of an `UIExecutionStatus` using colors.
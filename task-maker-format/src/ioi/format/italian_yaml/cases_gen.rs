@@ -10,10 +10,12 @@ use pest::Parser;
 
 use task_maker_diagnostics::CodeSpan;
 
-use crate::ioi::italian_yaml::{is_tm_deletable, TaskInputEntry, TM_ALLOW_DELETE_COOKIE};
+use crate::ioi::italian_yaml::{
+    generator_data_files, is_tm_deletable, TaskInputEntry, TM_ALLOW_DELETE_COOKIE,
+};
 use crate::ioi::{
-    InputGenerator, InputValidator, OutputGenerator, SubtaskId, SubtaskInfo, TestcaseId,
-    TestcaseInfo, TM_VALIDATION_FILE_NAME,
+    ConstraintOperator, InputGenerator, InputValidator, OutputGenerator, SubtaskId, SubtaskInfo,
+    TestcaseId, TestcaseInfo, TM_VALIDATION_FILE_NAME,
 };
 use crate::SourceFile;
 
@@ -51,21 +53,6 @@ enum ConstraintOperand {
     Variable(String),
 }
 
-/// The operator of a constraint.
-#[derive(Debug, PartialEq, Eq)]
-enum ConstraintOperator {
-    /// Operator `<`.
-    Less,
-    /// Operator `<=`.
-    LessEqual,
-    /// Operator `>`.
-    Greater,
-    /// Operator `>=`.
-    GreaterEqual,
-    /// Operator `=`.
-    Equal,
-}
-
 /// A constraint between the variables. It is in the following format:
 ///     operand (operator operand)+
 /// Note that the number of operands is one more than the operators.
@@ -364,12 +351,14 @@ where
 
     /// Add a new generator/validator to the list
     /// Since they have the same internal format their parsing function is abstracted in this.
+    /// The `data_files` are provided to every execution of the program.
     fn define_gen_val(
         line: Vec<Pair>,
         task_dir: &Path,
         default: &mut Option<String>,
         managers: &mut HashMap<String, Manager>,
         kind: &str,
+        data_files: Vec<(PathBuf, PathBuf)>,
     ) -> Result<(), Error> {
         let name = line[0].as_str();
         let path = line[1].as_str();
@@ -382,7 +371,7 @@ where
                 path.display()
             );
         }
-        let source = SourceFile::new(
+        let mut source = SourceFile::new(
             &path,
             task_dir,
             format!("The {} named {}", kind, name),
@@ -393,8 +382,11 @@ where
                     .join(path.file_name().context("invalid file name")?),
             ),
         )
-        .map(Arc::new)
         .ok_or_else(|| anyhow!("Cannot use {} '{}': unknown language", kind, path.display()))?;
+        for (path, sandbox_path) in data_files {
+            source.add_data_file(path, sandbox_path);
+        }
+        let source = Arc::new(source);
         let args = shell_words::split(line[2].as_str())
             .with_context(|| format!("Invalid arguments of '{}'", name))?;
         if managers.contains_key(name) {
@@ -426,6 +418,7 @@ where
                 &mut self.default_generator,
                 &mut self.generators,
                 "generator",
+                generator_data_files(&self.task_dir)?,
             )?;
         }
         Ok(())
@@ -452,6 +445,7 @@ where
                 &mut self.default_validator,
                 &mut self.validators,
                 "validator",
+                vec![],
             )?;
         }
 
@@ -686,46 +680,6 @@ where
     }
 }
 
-impl ConstraintOperator {
-    /// Apply the operator to the provided values and return the result of the comparison.
-    fn is_valid(&self, lhs: i64, rhs: i64) -> bool {
-        match self {
-            ConstraintOperator::Less => lhs < rhs,
-            ConstraintOperator::LessEqual => lhs <= rhs,
-            ConstraintOperator::Equal => lhs == rhs,
-            ConstraintOperator::Greater => lhs > rhs,
-            ConstraintOperator::GreaterEqual => lhs >= rhs,
-        }
-    }
-}
-
-impl FromStr for ConstraintOperator {
-    type Err = Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "<" => Ok(ConstraintOperator::Less),
-            "<=" => Ok(ConstraintOperator::LessEqual),
-            ">" => Ok(ConstraintOperator::Greater),
-            ">=" => Ok(ConstraintOperator::GreaterEqual),
-            "=" => Ok(ConstraintOperator::Equal),
-            _ => bail!("Invalid operator: {}", s),
-        }
-    }
-}
-
-impl Display for ConstraintOperator {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ConstraintOperator::Less => write!(f, "<"),
-            ConstraintOperator::LessEqual => write!(f, "<="),
-            ConstraintOperator::Greater => write!(f, ">"),
-            ConstraintOperator::GreaterEqual => write!(f, ">="),
-            ConstraintOperator::Equal => write!(f, "="),
-        }
-    }
-}
-
 impl ConstraintOperand {
     /// Return the value of this operand which is either the constant or the integer value of the
     /// variable contained in it. If the variable is not present, or it's not a valid integer `None`
@@ -792,12 +746,11 @@ mod tests {
     use speculoos::{assert_that, AssertionFailure, Spec};
     use tempfile::TempDir;
 
-    use crate::ioi::format::italian_yaml::cases_gen::{
-        CasesGen, ConstraintOperand, ConstraintOperator,
-    };
+    use crate::ioi::format::italian_yaml::cases_gen::{CasesGen, ConstraintOperand};
     use crate::ioi::format::italian_yaml::TaskInputEntry;
     use crate::ioi::{
-        InputGenerator, InputValidator, OutputGenerator, TestcaseId, TM_VALIDATION_FILE_NAME,
+        ConstraintOperator, InputGenerator, InputValidator, OutputGenerator, TestcaseId,
+        TM_VALIDATION_FILE_NAME,
     };
 
     struct TestHelper(TempDir);
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::sync::Arc;
 
@@ -7,7 +7,10 @@ use pest::Parser;
 
 use task_maker_diagnostics::CodeSpan;
 
-use crate::ioi::format::italian_yaml::TaskInputEntry;
+use crate::ioi::format::italian_yaml::gen_macros::{
+    GenLoop, GenStatement, GenTestcase, GenVariables,
+};
+use crate::ioi::format::italian_yaml::{generator_data_files, TaskInputEntry};
 use crate::ioi::italian_yaml::cleanup_subtask_name;
 use crate::ioi::{
     InputGenerator, InputValidator, OutputGenerator, SubtaskId, SubtaskInfo, TestcaseId,
@@ -78,18 +81,70 @@ where
     } else if generators.is_empty() {
         bail!("No generator found");
     }
-    let generator = generators.pop().map(Arc::new).unwrap();
+    let mut generator = generators.pop().unwrap();
+    for (path, sandbox_path) in generator_data_files(task_dir)? {
+        generator.add_data_file(path, sandbox_path);
+    }
+    let generator = Arc::new(generator);
     debug!("Detected input generator: {:?}", generator);
 
+    let mut variables = GenVariables::new();
+    // the loops not closed yet, with the line of their #FOR:
+    let mut loops: Vec<(usize, GenLoop)> = vec![];
+    // the name of the next testcase, with the line of its `# name:`
+    let mut next_name: Option<(usize, String)> = None;
+    let mut testcase_names = HashSet::new();
     for line in file.into_inner() {
+        let mut testcases = vec![];
         match line.as_rule() {
             parser::Rule::line => {
                 let span = line.as_span();
-                let line = line
-                    .into_inner()
+                let line_number = span.start_pos().line_col().0;
+                let mut inner = line.into_inner();
+                let line = inner.next().ok_or_else(|| anyhow!("Corrupted parser"))?;
+                let rule = line.as_rule();
+                // the name at the end of the line of a testcase
+                let inline_name = inner
                     .next()
-                    .ok_or_else(|| anyhow!("Corrupted parser"))?;
-                match line.as_rule() {
+                    .filter(|pair| pair.as_rule() == parser::Rule::testcase_name)
+                    .map(testcase_name)
+                    .transpose()?;
+                if let Some((name_line, _)) = &next_name {
+                    if inline_name.is_some() {
+                        bail!(
+                            "The testcase at line {} of gen/GEN is already named at line {}",
+                            line_number,
+                            name_line
+                        );
+                    }
+                    if !matches!(
+                        rule,
+                        parser::Rule::copy
+                            | parser::Rule::command
+                            | parser::Rule::set
+                            | parser::Rule::comment
+                            | parser::Rule::empty
+                    ) {
+                        bail!(
+                            "# name: at line {} of gen/GEN is not followed by a testcase",
+                            name_line
+                        );
+                    }
+                }
+                if !loops.is_empty()
+                    && matches!(
+                        rule,
+                        parser::Rule::subtask
+                            | parser::Rule::subtask_name
+                            | parser::Rule::subtask_dep
+                    )
+                {
+                    bail!(
+                        "The subtasks cannot be defined inside a #FOR: loop (line {} of gen/GEN)",
+                        line_number
+                    );
+                }
+                let statement = match rule {
                     parser::Rule::subtask => {
                         default_subtask.take(); // ignore the default subtask ever
                         let score = line
@@ -113,6 +168,7 @@ where
                             ..Default::default()
                         }));
                         subtask_id += 1;
+                        None
                     }
                     parser::Rule::subtask_name => {
                         let last_entry = entries.last_mut().ok_or_else(|| {
@@ -137,6 +193,7 @@ where
                         } else {
                             bail!("#STNAME: must immediately follow a #ST: in gen/GEN");
                         }
+                        None
                     }
                     parser::Rule::subtask_dep => {
                         let last_entry = entries.last_mut().ok_or_else(|| {
@@ -151,50 +208,108 @@ where
                                 .or_default()
                                 .push(dependency.as_str().to_owned());
                         }
+                        None
                     }
                     parser::Rule::copy => {
-                        if let Some(default) = default_subtask.take() {
-                            entries.push(TaskInputEntry::Subtask(default));
-                            subtask_id += 1;
-                        }
                         let what = line
                             .into_inner()
                             .next()
                             .ok_or_else(|| anyhow!("Corrupted parser"))?
                             .as_str();
-                        entries.push(TaskInputEntry::Testcase(TestcaseInfo::new(
-                            testcase_count,
-                            InputGenerator::StaticFile(task_dir.join(what)),
-                            get_output_gen(testcase_count),
-                        )));
-                        testcase_count += 1;
+                        let name = inline_name.or_else(|| next_name.take().map(|(_, name)| name));
+                        Some((line_number, GenStatement::Copy(what.to_owned(), name)))
                     }
                     parser::Rule::command => {
-                        if let Some(default) = default_subtask.take() {
-                            entries.push(TaskInputEntry::Subtask(default));
-                            subtask_id += 1;
-                        }
                         let cmd: Vec<String> =
                             line.into_inner().map(|x| x.as_str().to_owned()).collect();
-                        let output_generator = get_output_gen(testcase_count);
-                        if let OutputGenerator::StaticFile(_) = output_generator {
-                            bail!("Generator detected but no solution found. Cannot generate output files.");
-                        }
-                        entries.push(TaskInputEntry::Testcase(TestcaseInfo::new(
-                            testcase_count,
-                            InputGenerator::Custom(generator.clone(), cmd),
-                            output_generator,
-                        )));
-                        testcase_count += 1;
+                        let name = inline_name.or_else(|| next_name.take().map(|(_, name)| name));
+                        Some((line_number, GenStatement::Command(cmd, name)))
+                    }
+                    parser::Rule::set => {
+                        let mut inner = line.into_inner().map(|x| x.as_str().trim().to_owned());
+                        let mut next = || inner.next().ok_or_else(|| anyhow!("Corrupted parser"));
+                        Some((line_number, GenStatement::Set(next()?, next()?)))
+                    }
+                    parser::Rule::for_loop => {
+                        let mut inner = line.into_inner().map(|x| x.as_str().to_owned());
+                        let mut next = || inner.next().ok_or_else(|| anyhow!("Corrupted parser"));
+                        let gen_loop = GenLoop {
+                            variable: next()?,
+                            from: next()?,
+                            to: next()?,
+                            step: next().ok(),
+                            body: vec![],
+                        };
+                        loops.push((line_number, gen_loop));
+                        None
                     }
-                    parser::Rule::comment => {}
-                    parser::Rule::empty => {}
+                    parser::Rule::end => {
+                        // the loop is expanded as a statement at the line of its #FOR:
+                        let (for_line, gen_loop) = loops.pop().ok_or_else(|| {
+                            anyhow!("#END without #FOR: at line {} of gen/GEN", line_number)
+                        })?;
+                        Some((for_line, GenStatement::Loop(gen_loop)))
+                    }
+                    parser::Rule::testcase_name => {
+                        next_name = Some((line_number, testcase_name(line)?));
+                        None
+                    }
+                    parser::Rule::comment => None,
+                    parser::Rule::empty => None,
                     _ => unreachable!(),
+                };
+                if let Some((line_number, statement)) = statement {
+                    match loops.last_mut() {
+                        Some((_, gen_loop)) => gen_loop.body.push((line_number, statement)),
+                        None => statement.expand(line_number, &mut variables, &mut testcases)?,
+                    }
+                }
+            }
+            parser::Rule::EOI => {
+                if let Some((name_line, _)) = next_name {
+                    bail!(
+                        "# name: at line {} of gen/GEN is not followed by a testcase",
+                        name_line
+                    );
+                }
+                if let Some((for_line, _)) = loops.last() {
+                    bail!("#FOR: without #END at line {} of gen/GEN", for_line);
                 }
             }
-            parser::Rule::EOI => {}
             _ => unreachable!(),
         }
+
+        for testcase in testcases {
+            if let Some(default) = default_subtask.take() {
+                entries.push(TaskInputEntry::Subtask(default));
+                subtask_id += 1;
+            }
+            let (input_generator, name) = match testcase {
+                GenTestcase::Copy(what, name) => {
+                    (InputGenerator::StaticFile(task_dir.join(what)), name)
+                }
+                GenTestcase::Command(cmd, name) => {
+                    (InputGenerator::Custom(generator.clone(), cmd), name)
+                }
+            };
+            if let Some(name) = &name {
+                ensure!(
+                    testcase_names.insert(name.clone()),
+                    "Duplicate testcase name: {}",
+                    name
+                );
+            }
+            let output_generator = get_output_gen(testcase_count);
+            if let (InputGenerator::Custom(..), OutputGenerator::StaticFile(_)) =
+                (&input_generator, &output_generator)
+            {
+                bail!("Generator detected but no solution found. Cannot generate output files.");
+            }
+            let mut testcase = TestcaseInfo::new(testcase_count, input_generator, output_generator);
+            testcase.name = name;
+            entries.push(TaskInputEntry::Testcase(testcase));
+            testcase_count += 1;
+        }
     }
 
     for entry in &mut entries {
@@ -219,9 +334,20 @@ where
     Ok(entries)
 }
 
+/// Extract the name of the testcase from a `# name:` comment.
+fn testcase_name(pair: pest::iterators::Pair<parser::Rule>) -> Result<String, Error> {
+    let name = pair
+        .into_inner()
+        .next()
+        .ok_or_else(|| anyhow!("Corrupted parser"))?
+        .as_str();
+    Ok(name.to_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
+    use std::os::unix::fs::PermissionsExt;
     use std::path::Path;
     use std::sync::Arc;
 
@@ -232,7 +358,7 @@ mod tests {
     use TaskInputEntry::*;
 
     use crate::ioi::format::italian_yaml::gen_gen::parse_gen_gen;
-    use crate::ioi::format::italian_yaml::TaskInputEntry;
+    use crate::ioi::format::italian_yaml::{generator_data_files, TaskInputEntry};
     use crate::ioi::{InputGenerator, InputValidator, OutputGenerator, SubtaskId, TestcaseId};
     use crate::SourceFile;
 
@@ -582,4 +708,146 @@ mod tests {
         let res = get_parsed_gen_gen("#ST:1\n#STNAME: tree-line");
         assert!(res.is_ok());
     }
+
+    fn generator_args(entries: &[TaskInputEntry]) -> Vec<Vec<String>> {
+        entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Testcase(testcase) => match &testcase.input_generator {
+                    InputGenerator::Custom(_, args) => Some(args.clone()),
+                    InputGenerator::StaticFile(_) => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parser_variables() {
+        let entries =
+            get_parsed_gen_gen("#SET: N 1e3\n#SET: M N * 2 # comment\n${N} ${M+1} x${N}y\n")
+                .unwrap();
+        assert_eq!(
+            generator_args(&entries),
+            vec![vec!["1000", "2001", "x1000y"]]
+        );
+    }
+
+    #[test]
+    fn test_parser_loops() {
+        let entries = get_parsed_gen_gen(
+            "#ST: 0\n#FOR: i 1 2\n#COPY: att/input${i}.txt\n#END\n\
+             #ST: 100\n#FOR: n 1e3 1e5 ${(1e5 - 1e3) / 2}\n\
+             #FOR: seed 1 2\n${n} ${seed}\n#END\n#END\n",
+        )
+        .unwrap();
+        if let [Subtask(_), Testcase(copy1), Testcase(copy2), Subtask(subtask), ..] =
+            entries.as_slice()
+        {
+            assert_eq!(subtask.id, 1);
+            match (&copy1.input_generator, &copy2.input_generator) {
+                (InputGenerator::StaticFile(path1), InputGenerator::StaticFile(path2)) => {
+                    assert!(path1.ends_with("att/input1.txt"));
+                    assert!(path2.ends_with("att/input2.txt"));
+                }
+                _ => panic!("Invalid generator"),
+            }
+        } else {
+            panic!("Wrong entries returned: {:?}", entries);
+        }
+        assert_eq!(
+            generator_args(&entries),
+            vec![
+                vec!["1000", "1"],
+                vec!["1000", "2"],
+                vec!["50500", "1"],
+                vec!["50500", "2"],
+                vec!["100000", "1"],
+                vec!["100000", "2"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_loop_errors() {
+        let error = |gen_gen: &str| format!("{:?}", get_parsed_gen_gen(gen_gen).unwrap_err());
+        assert!(error("#FOR: i 1 3\n${i}\n").contains("line 1"));
+        assert!(error("1\n#END\n").contains("line 2"));
+        assert!(error("#FOR: i 1 3\n#ST: 10\n#END\n").contains("line 2"));
+        assert!(error("#SET: N 10\n#FOR: i 1 N\n${i / (N - 10)}\n#END\n").contains("line 3"));
+        assert!(error("1\n#FOR: i 1 M\n${i}\n#END\n").contains("line 2"));
+    }
+
+    #[test]
+    fn test_parser_testcase_names() {
+        let entries = get_parsed_gen_gen(
+            "# name: first\n1\n2 # name: second\n# a comment\n3\n\
+             #FOR: i 1 2\n# name: loop-${i}\n${i}\n#END\n#COPY: foo.txt # name: copied\n",
+        )
+        .unwrap();
+        let names: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Testcase(testcase) => Some(testcase.name.as_deref()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                Some("first"),
+                Some("second"),
+                None,
+                Some("loop-1"),
+                Some("loop-2"),
+                Some("copied")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_testcase_name_errors() {
+        let error = |gen_gen: &str| format!("{:?}", get_parsed_gen_gen(gen_gen).unwrap_err());
+        assert!(error("# name: foo\n1\n# name: foo\n2\n").contains("Duplicate"));
+        assert!(error("#FOR: i 1 2\n# name: foo\n${i}\n#END\n").contains("Duplicate"));
+        assert!(error("# name: foo\n1 # name: bar\n").contains("line 1"));
+        assert!(error("# name: foo\n#ST: 10\n2\n").contains("line 1"));
+        assert!(error("1\n# name: foo\n").contains("line 2"));
+        // names with spaces are just comments
+        let entries = get_parsed_gen_gen("# name: not a name\n1\n").unwrap();
+        assert!(matches!(&entries[1], Testcase(testcase) if testcase.name.is_none()));
+    }
+
+    #[test]
+    fn test_generator_data_files() {
+        let task = make_task("1234\n");
+        let gen = task.path().join("gen");
+        fs::create_dir_all(gen.join("data")).unwrap();
+        fs::write(gen.join("words.txt"), "foo").unwrap();
+        fs::write(gen.join("data/graph.txt"), "1 2").unwrap();
+        fs::write(gen.join(".words.txt.swp"), "").unwrap();
+        fs::write(gen.join("validator.cpp"), "").unwrap();
+        fs::write(gen.join("tool"), "").unwrap();
+        fs::set_permissions(gen.join("tool"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut files = generator_data_files(task.path()).unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                (gen.join("data/graph.txt"), "data/graph.txt".into()),
+                (gen.join("words.txt"), "words.txt".into()),
+            ]
+        );
+
+        fs::write(gen.join("FILES"), "# the graphs\ndata/*.txt\n").unwrap();
+        let files = generator_data_files(task.path()).unwrap();
+        assert_eq!(
+            files,
+            vec![(gen.join("data/graph.txt"), "data/graph.txt".into())]
+        );
+
+        fs::write(gen.join("FILES"), "missing.txt\n").unwrap();
+        assert!(generator_data_files(task.path()).is_err());
+    }
 }
@@ -0,0 +1,425 @@
+//! The variables, the arithmetic and the loops of `gen/GEN`, expanded into the plain lines that
+//! define the testcases.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, ensure, Context, Error};
+
+/// The maximum number of iterations of a `#FOR:` loop, to catch the loops with wrong bounds.
+const MAX_LOOP_ITERATIONS: i64 = 10_000;
+
+/// The values of the variables defined in `gen/GEN`.
+pub(crate) type GenVariables = HashMap<String, i64>;
+
+/// A line of `gen/GEN` that can be repeated by a loop.
+#[derive(Debug, Clone)]
+pub(crate) enum GenStatement {
+    /// A testcase generated by the generator with these arguments, and its name if any, before the
+    /// substitutions.
+    Command(Vec<String>, Option<String>),
+    /// A testcase copied from this path, and its name if any, before the substitutions.
+    Copy(String, Option<String>),
+    /// The definition of a variable with the value of an expression.
+    Set(String, String),
+    /// A loop repeating its body.
+    Loop(GenLoop),
+}
+
+/// A `#FOR:` loop, with the lines up to its `#END`.
+#[derive(Debug, Clone)]
+pub(crate) struct GenLoop {
+    /// The variable that takes the values from `from` to `to`.
+    pub variable: String,
+    /// The expression of the first value of the variable.
+    pub from: String,
+    /// The expression of the last value of the variable, included.
+    pub to: String,
+    /// The expression of the increment of the variable, 1 if not specified.
+    pub step: Option<String>,
+    /// The lines repeated by the loop, with their line number.
+    pub body: Vec<(usize, GenStatement)>,
+}
+
+/// A testcase of `gen/GEN` after the expansion of the loops and of the variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum GenTestcase {
+    /// A testcase generated by the generator with these arguments, and its name if any.
+    Command(Vec<String>, Option<String>),
+    /// A testcase copied from this path, and its name if any.
+    Copy(String, Option<String>),
+}
+
+impl GenStatement {
+    /// Expand the statement at line `line` of `gen/GEN`, appending the testcases to `testcases`.
+    pub(crate) fn expand(
+        &self,
+        line: usize,
+        variables: &mut GenVariables,
+        testcases: &mut Vec<GenTestcase>,
+    ) -> Result<(), Error> {
+        let context = || format!("Invalid line {} of gen/GEN", line);
+        match self {
+            GenStatement::Command(args, name) => {
+                let args = args
+                    .iter()
+                    .map(|arg| substitute(arg, variables))
+                    .collect::<Result<_, _>>()
+                    .with_context(context)?;
+                let name = substitute_name(name, variables).with_context(context)?;
+                testcases.push(GenTestcase::Command(args, name));
+            }
+            GenStatement::Copy(path, name) => {
+                let path = substitute(path, variables).with_context(context)?;
+                let name = substitute_name(name, variables).with_context(context)?;
+                testcases.push(GenTestcase::Copy(path, name));
+            }
+            GenStatement::Set(name, expression) => {
+                let value = evaluate(expression, variables).with_context(context)?;
+                variables.insert(name.clone(), value);
+            }
+            GenStatement::Loop(gen_loop) => {
+                let values = gen_loop.values(variables).with_context(context)?;
+                let previous = variables.get(&gen_loop.variable).copied();
+                for value in values {
+                    variables.insert(gen_loop.variable.clone(), value);
+                    for (line, statement) in &gen_loop.body {
+                        statement.expand(*line, variables, testcases)?;
+                    }
+                }
+                // the loop variable is visible only inside the loop
+                match previous {
+                    Some(previous) => variables.insert(gen_loop.variable.clone(), previous),
+                    None => variables.remove(&gen_loop.variable),
+                };
+            }
+        }
+        Ok(())
+    }
+}
+
+impl GenLoop {
+    /// The values taken by the variable of the loop.
+    fn values(&self, variables: &GenVariables) -> Result<Vec<i64>, Error> {
+        let bound = |expression: &str| -> Result<i64, Error> {
+            evaluate(&substitute(expression, variables)?, variables)
+        };
+        let from = bound(&self.from)?;
+        let to = bound(&self.to)?;
+        let step = match &self.step {
+            Some(step) => bound(step)?,
+            None => 1,
+        };
+        ensure!(step != 0, "The step of #FOR: cannot be zero");
+        // computed in i128 since the difference of the bounds may not fit in i64
+        let iterations = if (step > 0 && from <= to) || (step < 0 && from >= to) {
+            (to as i128 - from as i128) / step as i128 + 1
+        } else {
+            0
+        };
+        ensure!(
+            iterations <= MAX_LOOP_ITERATIONS as i128,
+            "The #FOR: loop has {} iterations, more than {}",
+            iterations,
+            MAX_LOOP_ITERATIONS
+        );
+        Ok((0..iterations as i64).map(|i| from + i * step).collect())
+    }
+}
+
+/// Replace each `${expression}` of the name of a testcase, if it has one.
+fn substitute_name(
+    name: &Option<String>,
+    variables: &GenVariables,
+) -> Result<Option<String>, Error> {
+    name.as_ref()
+        .map(|name| substitute(name, variables))
+        .transpose()
+}
+
+/// Replace each `${expression}` of `text` with the value of the expression.
+pub(crate) fn substitute(text: &str, variables: &GenVariables) -> Result<String, Error> {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        result += &rest[..start];
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("Missing }} after ${{ in '{}'", text))?;
+        let value = evaluate(&rest[start + 2..start + end], variables)?;
+        result += &value.to_string();
+        rest = &rest[start + end + 1..];
+    }
+    result += rest;
+    Ok(result)
+}
+
+/// Evaluate an integer expression with the `+`, `-`, `*`, `/` and `%` operators, the parentheses,
+/// the variables and the constants, also in the scientific notation (like `1e5`).
+pub(crate) fn evaluate(expression: &str, variables: &GenVariables) -> Result<i64, Error> {
+    let mut parser = ExpressionParser {
+        tokens: tokenize(expression)?,
+        position: 0,
+        variables,
+    };
+    let value = parser
+        .parse_sum()
+        .with_context(|| format!("Invalid expression '{}'", expression.trim()))?;
+    if parser.position != parser.tokens.len() {
+        bail!("Invalid expression '{}'", expression.trim());
+    }
+    Ok(value)
+}
+
+/// A token of an expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// An integer constant.
+    Number(i64),
+    /// The name of a variable.
+    Variable(String),
+    /// An operator or a parenthesis.
+    Symbol(char),
+}
+
+/// Split an expression into its tokens.
+fn tokenize(expression: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = vec![];
+    let mut chars = expression.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if "+-*/%()".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !c.is_ascii_alphanumeric() && c != '_' {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let word = &expression[start..end];
+            if c.is_ascii_digit() {
+                tokens.push(Token::Number(parse_number(word)?));
+            } else {
+                tokens.push(Token::Variable(word.to_string()));
+            }
+        } else {
+            bail!(
+                "Unexpected character '{}' in expression '{}'",
+                c,
+                expression
+            );
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parse an integer constant, also in the scientific notation (like `1e5`).
+fn parse_number(number: &str) -> Result<i64, Error> {
+    let value = match number.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => match (i64::from_str(mantissa), u32::from_str(exponent)) {
+            (Ok(mantissa), Ok(exponent)) => 10i64
+                .checked_pow(exponent)
+                .and_then(|power| mantissa.checked_mul(power)),
+            _ => None,
+        },
+        None => i64::from_str(number).ok(),
+    };
+    value.ok_or_else(|| anyhow!("Invalid integer constant '{}'", number))
+}
+
+/// A recursive descent parser of the expressions, evaluating them while parsing.
+struct ExpressionParser<'a> {
+    /// The tokens of the expression.
+    tokens: Vec<Token>,
+    /// The index of the next token to parse.
+    position: usize,
+    /// The values of the variables.
+    variables: &'a GenVariables,
+}
+
+impl ExpressionParser<'_> {
+    /// Consume the next token if it's the symbol `symbol`.
+    fn accept(&mut self, symbol: char) -> bool {
+        if self.tokens.get(self.position) == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parse a sequence of terms separated by `+` or `-`.
+    fn parse_sum(&mut self) -> Result<i64, Error> {
+        let mut value = self.parse_product()?;
+        loop {
+            value = if self.accept('+') {
+                value.checked_add(self.parse_product()?)
+            } else if self.accept('-') {
+                value.checked_sub(self.parse_product()?)
+            } else {
+                return Ok(value);
+            }
+            .ok_or_else(|| anyhow!("Integer overflow"))?;
+        }
+    }
+
+    /// Parse a sequence of factors separated by `*`, `/` or `%`.
+    fn parse_product(&mut self) -> Result<i64, Error> {
+        let mut value = self.parse_factor()?;
+        loop {
+            value = if self.accept('*') {
+                value
+                    .checked_mul(self.parse_factor()?)
+                    .ok_or_else(|| anyhow!("Integer overflow"))?
+            } else if self.accept('/') {
+                value
+                    .checked_div(self.parse_factor()?)
+                    .ok_or_else(|| anyhow!("Division by zero"))?
+            } else if self.accept('%') {
+                value
+                    .checked_rem(self.parse_factor()?)
+                    .ok_or_else(|| anyhow!("Division by zero"))?
+            } else {
+                return Ok(value);
+            };
+        }
+    }
+
+    /// Parse a constant, a variable, a negated factor or an expression between parentheses.
+    fn parse_factor(&mut self) -> Result<i64, Error> {
+        if self.accept('-') {
+            return self
+                .parse_factor()?
+                .checked_neg()
+                .ok_or_else(|| anyhow!("Integer overflow"));
+        }
+        if self.accept('(') {
+            let value = self.parse_sum()?;
+            ensure!(self.accept(')'), "Missing )");
+            return Ok(value);
+        }
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        match token {
+            Some(Token::Number(value)) => Ok(value),
+            Some(Token::Variable(name)) => self
+                .variables
+                .get(&name)
+                .copied()
+                .ok_or_else(|| anyhow!("Unknown variable '{}'", name)),
+            Some(Token::Symbol(symbol)) => bail!("Unexpected '{}'", symbol),
+            None => bail!("Unexpected end of the expression"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> GenVariables {
+        vec![("N".to_string(), 100), ("seed_2".to_string(), 7)]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let vars = variables();
+        assert_eq!(evaluate("1 + 2 * 3", &vars).unwrap(), 7);
+        assert_eq!(evaluate("(1 + 2) * 3", &vars).unwrap(), 9);
+        assert_eq!(evaluate("N / 3 - -seed_2 % 4", &vars).unwrap(), 36);
+        assert_eq!(evaluate("2e5 - 1E3", &vars).unwrap(), 199000);
+        assert!(evaluate("M + 1", &vars).is_err());
+        assert!(evaluate("N / (N - 100)", &vars).is_err());
+        assert!(evaluate("(N + 1", &vars).is_err());
+        assert!(evaluate("N N", &vars).is_err());
+        assert!(evaluate("1.5", &vars).is_err());
+        assert!(evaluate("1e30", &vars).is_err());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let vars = variables();
+        assert_eq!(substitute("plain", &vars).unwrap(), "plain");
+        assert_eq!(substitute("${N * 2}", &vars).unwrap(), "200");
+        assert_eq!(
+            substitute("in${seed_2}_${N}.txt", &vars).unwrap(),
+            "in7_100.txt"
+        );
+        assert!(substitute("${N", &vars).is_err());
+    }
+
+    #[test]
+    fn test_expand_loops() {
+        let statement = GenStatement::Loop(GenLoop {
+            variable: "i".to_string(),
+            from: "1".to_string(),
+            to: "N".to_string(),
+            step: Some("${N / 2}".to_string()),
+            body: vec![
+                (2, GenStatement::Set("M".to_string(), "i * 10".to_string())),
+                (
+                    3,
+                    GenStatement::Command(
+                        vec!["${M}".to_string(), "${i}".to_string()],
+                        Some("random-${i}".to_string()),
+                    ),
+                ),
+            ],
+        });
+        let mut vars: GenVariables = vec![("N".to_string(), 4)].into_iter().collect();
+        let mut testcases = vec![];
+        statement.expand(1, &mut vars, &mut testcases).unwrap();
+        assert_eq!(
+            testcases,
+            vec![
+                GenTestcase::Command(
+                    vec!["10".to_string(), "1".to_string()],
+                    Some("random-1".to_string())
+                ),
+                GenTestcase::Command(
+                    vec!["30".to_string(), "3".to_string()],
+                    Some("random-3".to_string())
+                ),
+            ]
+        );
+        assert!(!vars.contains_key("i"));
+        assert_eq!(vars["M"], 30);
+    }
+
+    #[test]
+    fn test_expand_loop_errors() {
+        let make_loop = |to: &str, step: &str| {
+            GenStatement::Loop(GenLoop {
+                variable: "i".to_string(),
+                from: "0".to_string(),
+                to: to.to_string(),
+                step: Some(step.to_string()),
+                body: vec![(2, GenStatement::Copy("${x}".to_string(), None))],
+            })
+        };
+        let mut vars = GenVariables::new();
+        let mut testcases = vec![];
+        let err = make_loop("10", "0")
+            .expand(1, &mut vars, &mut testcases)
+            .unwrap_err();
+        assert!(format!("{:?}", err).contains("line 1"));
+        assert!(make_loop("1e9", "1")
+            .expand(1, &mut vars, &mut testcases)
+            .is_err());
+        assert!(make_loop("-1", "1")
+            .expand(1, &mut vars, &mut testcases)
+            .is_ok());
+        let err = make_loop("1", "1")
+            .expand(1, &mut vars, &mut testcases)
+            .unwrap_err();
+        assert!(format!("{:?}", err).contains("line 2"));
+        assert!(testcases.is_empty());
+    }
+}
@@ -33,6 +33,36 @@
 //!
 //! If no `#ST` lines are present, a single subtask worth 100 points is automatically added.
 //!
+//! ## Testcase names
+//!
+//! A testcase can be named with a `# name: big-random-tree` line before it, or with
+//! `# name: big-random-tree` at the end of its line. The UIs show the named testcases as
+//! `st2/big-random-tree` instead of just their number. The names cannot contain spaces and must be
+//! unique in the task, inside a loop they can use `${expression}` for that.
+//!
+//! ## Variables and loops
+//!
+//! The repetitive lines can be written once, using variables and loops:
+//!
+//! - `#SET: N 2 * 1e3` defines the variable `N` with the value of an integer expression, which can
+//!   use the `+`, `-`, `*`, `/` and `%` operators, the parentheses, the variables already defined
+//!   and the constants, also in scientific notation.
+//! - `#FOR: i 1 10` repeats the lines up to the matching `#END`, with the variable `i` going from 1
+//!   to 10 included. An optional fourth value is the step, the bounds and the step can be
+//!   expressions without spaces. The loops can be nested, but they cannot contain the subtasks.
+//! - in the arguments of the generator and in the paths of `#COPY:`, `${expression}` is replaced
+//!   with the value of the expression. Example: `#COPY: att/input${i}.txt`.
+//!
+//! The errors in the expansion report the line of `gen/GEN` where they happen.
+//!
+//! ```text
+//! #ST: 60
+//! #SET: MAXN 1e5
+//! #FOR: n 1000 MAXN 11000
+//! ${n}    ${n * 2}    ${n % 7}
+//! #END
+//! ```
+//!
 //! ## Full example of `gen/GEN`
 //!
 //! ```text
@@ -60,6 +90,35 @@
 //!
 //! Then there is a third subtask with 2 testcases.
 //!
+//! # Contest defaults
+//!
+//! If the parent directory of the task contains a `contest.yaml` with a `defaults` mapping, its
+//! values are used for the keys of `task.yaml` (or `task.yaml.orig`) that the task doesn't set,
+//! like the limits and the `primary_language` shared by all the tasks of a contest.
+//!
+//! # Input constraints
+//!
+//! The `constraints` mapping of `task.yaml` declares the constraints on the input files, which are
+//! checked on every testcase in addition to the validator. The `variables` are bound, in order, to
+//! the first integers of each input file. The constraints in `all` apply to every testcase, the ones
+//! in `subtasks` only to the testcases of a subtask, referenced by name or by 0-based index.
+//!
+//! A constraint is either a chain of comparisons with the same syntax of `: CONSTRAINT` in
+//! `cases.gen`, but without the `$`, or the range of a variable. The constants can be written in
+//! scientific notation.
+//!
+//! ```yaml
+//! constraints:
+//!   variables: [N, M]
+//!   all:
+//!     - N: 1..1e5
+//!     - 1 <= M <= N
+//!   subtasks:
+//!     small: [N <= 100]
+//! ```
+//!
+//! For each subtask, the bounds of the variables that no testcase reaches are reported.
+//!
 //! # `gen/cases.gen` format
 //!
 //! The `gen/GEN` format is pretty limited regarding some important aspects of task preparation. For
@@ -258,28 +317,33 @@
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{BufWriter, Write};
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::{anyhow, bail, Context, Error};
 use itertools::Itertools;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize, Serializer};
 use unic::normal::StrNormalForm;
 use unic::ucd::category::GeneralCategory;
 
-use task_maker_lang::GraderMap;
+use task_maker_dag::{MemoryMetric, TimeClock};
+use task_maker_lang::{GraderMap, LanguageManager};
 
 use crate::ioi::sanity_checks::get_sanity_checks;
 use crate::ioi::{
-    make_task_booklets, Checker, IOITask, InputValidator, OutputGenerator, SubtaskId, SubtaskInfo,
-    TaskType, TestcaseId, TestcaseInfo, TestcaseScoreAggregator,
+    make_task_booklets, BookletConfig, Checker, IOITask, InputConstraint, InputConstraints,
+    InputFormatPolicy, InputValidator, LanguageMultiplier, OutputGenerator, OutputNormalization,
+    SubtaskId, SubtaskInfo, TaskType, TestcaseId, TestcaseInfo, TestcaseScoreAggregator,
 };
 use crate::ioi::{BatchTypeData, CommunicationTypeData, UserIo};
 use crate::ioi::{InputValidatorGenerator, TM_VALIDATION_FILE_NAME};
-use crate::{find_source_file, list_files, EvaluationConfig, WriteBinTo};
+use crate::{find_source_file, list_files, EvaluationConfig, WriteBinTo, VALID_TAGS};
 
 mod cases_gen;
 mod gen_gen;
+mod gen_macros;
 mod static_inputs;
 
 /// String placed in the auto-generated files marking them as safely deletable.
@@ -352,6 +416,9 @@ struct TaskYAML {
     pub score_precision: usize,
     /// The primary language used when importing the task.
     pub primary_language: Option<String>,
+    /// The number of testcases, used by cms when importing the task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n_input: Option<usize>,
 
     /// The time limit for the execution of the solutions, if not set it's unlimited.
     #[serde(alias = "timeout")]
@@ -376,6 +443,34 @@ struct TaskYAML {
     /// Teams). Used only in booklet compilations.
     pub syllabuslevel: Option<u8>,
 
+    /// The tags of the executions whose failures should not be cached (e.g. `booklet`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_cache_failures: Vec<String>,
+    /// The prefixes of the paths of the solutions grouping them in the tables of the UIs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub solution_groups: Vec<String>,
+    /// The fraction of the limits above which the resources used are highlighted in the UIs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_threshold: Option<f64>,
+    /// The multipliers of the limits for the solutions in some languages.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub language_multipliers: HashMap<String, LanguageMultiplier>,
+    /// The clock that drives the time limit verdict of the solutions, either `cpu` or `wall`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_clock: Option<TimeClock>,
+    /// The measure of the memory checked against the memory limit, `address_space` or `rss`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_metric: Option<MemoryMetric>,
+    /// The rules on the format of the input files, each `off`, `warn` or `fail`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_format: Option<InputFormatPolicy>,
+    /// The constraints on the values of the input files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constraints: Option<ConstraintsYAML>,
+    /// How the outputs are normalized before comparing them, when there is no custom checker.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_normalization: Option<OutputNormalization>,
+
     /// Number of solution processes to spawn in parallel in a communication task.
     pub num_processes: Option<u8>,
     /// The type of communication for the solution in a communication task.
@@ -383,6 +478,10 @@ struct TaskYAML {
     /// Can be either "std_io" for using stdin/stdout, or "fifo_io" for using pipes given in argv.
     /// Defaults to "fifo_io".
     pub user_io: Option<String>,
+    /// Whether the solution is run twice on each testcase, the second time reading the output of
+    /// the first. Defaults to false.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub two_pass: bool,
 
     /// Compatibility with cms, unused.
     pub score_mode: Option<String>,
@@ -431,6 +530,34 @@ struct TaskYAMLOrig {
     /// Teams). Used only in booklet compilations.
     pub syllabuslevel: Option<u8>,
 
+    /// The tags of the executions whose failures should not be cached (e.g. `booklet`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_cache_failures: Vec<String>,
+    /// The prefixes of the paths of the solutions grouping them in the tables of the UIs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub solution_groups: Vec<String>,
+    /// The fraction of the limits above which the resources used are highlighted in the UIs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_threshold: Option<f64>,
+    /// The multipliers of the limits for the solutions in some languages.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub language_multipliers: HashMap<String, LanguageMultiplier>,
+    /// The clock that drives the time limit verdict of the solutions, either `cpu` or `wall`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_clock: Option<TimeClock>,
+    /// The measure of the memory checked against the memory limit, `address_space` or `rss`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_metric: Option<MemoryMetric>,
+    /// The rules on the format of the input files, each `off`, `warn` or `fail`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_format: Option<InputFormatPolicy>,
+    /// The constraints on the values of the input files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constraints: Option<ConstraintsYAML>,
+    /// How the outputs are normalized before comparing them, when there is no custom checker.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_normalization: Option<OutputNormalization>,
+
     /// Number of solution processes to spawn in parallel in a communication task.
     pub num_processes: Option<u8>,
     /// The type of communication for the solution in a communication task.
@@ -438,6 +565,10 @@ struct TaskYAMLOrig {
     /// Can be either "std_io" for using stdin/stdout, or "fifo_io" for using pipes given in argv.
     /// Defaults to "fifo_io".
     pub user_io: Option<String>,
+    /// Whether the solution is run twice on each testcase, the second time reading the output of
+    /// the first. Defaults to false.
+    #[serde(default)]
+    pub two_pass: bool,
 }
 
 impl TaskYAMLOrig {
@@ -449,6 +580,7 @@ impl TaskYAMLOrig {
             score_type_parameters: None,
             score_precision: self.score_precision,
             primary_language: Some(self.primary_language.unwrap_or_else(|| "en".into())),
+            n_input: None,
             time_limit: Some(self.time_limit),
             memory_limit: Some(self.memory_limit),
             output_only: self.output_only,
@@ -458,6 +590,16 @@ impl TaskYAMLOrig {
             syllabuslevel: self.syllabuslevel,
             num_processes: self.num_processes,
             user_io: self.user_io,
+            two_pass: self.two_pass,
+            no_cache_failures: self.no_cache_failures,
+            solution_groups: self.solution_groups,
+            resource_threshold: self.resource_threshold,
+            language_multipliers: self.language_multipliers,
+            time_clock: self.time_clock,
+            memory_metric: self.memory_metric,
+            input_format: self.input_format,
+            constraints: self.constraints,
+            output_normalization: self.output_normalization,
             score_mode: Some("max_subtask".into()),
             token_mode: Some("disabled".into()),
             public_testcases: Some("all".into()),
@@ -466,6 +608,71 @@ impl TaskYAMLOrig {
     }
 }
 
+/// The constraints on the values of the input files, in the task.yaml.
+#[derive(Debug, Serialize, Deserialize)]
+struct ConstraintsYAML {
+    /// The names of the variables, bound in order to the first integers of each input file.
+    pub variables: Vec<String>,
+    /// The constraints that all the input files must satisfy.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub all: Vec<ConstraintYAML>,
+    /// The additional constraints of the subtasks, indexed by name or by 0-based index.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub subtasks: HashMap<String, Vec<ConstraintYAML>>,
+}
+
+/// A constraint in the task.yaml, either a string (like `M <= N`) or the range of a variable (like
+/// `N: 1..1e5`).
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ConstraintYAML {
+    /// A chain of comparisons, or a range as a string.
+    Expression(String),
+    /// The ranges of some variables.
+    Ranges(HashMap<String, String>),
+}
+
+impl ConstraintsYAML {
+    /// Parse the constraints of the task, with the subtasks referenced by name or by index.
+    fn parse(&self, subtasks: &HashMap<SubtaskId, SubtaskInfo>) -> Result<InputConstraints, Error> {
+        let parse = |constraints: &[ConstraintYAML]| -> Result<Vec<InputConstraint>, Error> {
+            let mut result = vec![];
+            for constraint in constraints {
+                match constraint {
+                    ConstraintYAML::Expression(expr) => {
+                        result.push(InputConstraint::parse(expr, &self.variables)?);
+                    }
+                    ConstraintYAML::Ranges(ranges) => {
+                        for (var, range) in ranges.iter().sorted() {
+                            let expr = format!("{}: {}", var, range);
+                            result.push(InputConstraint::parse(&expr, &self.variables)?);
+                        }
+                    }
+                }
+            }
+            Ok(result)
+        };
+        let mut result = InputConstraints {
+            variables: self.variables.clone(),
+            global: parse(&self.all)?,
+            subtasks: HashMap::new(),
+        };
+        for (key, constraints) in &self.subtasks {
+            let subtask = subtasks
+                .values()
+                .find(|st| st.name.as_deref() == Some(key.as_str()))
+                .or_else(|| subtasks.get(&key.parse::<SubtaskId>().ok()?))
+                .ok_or_else(|| anyhow!("Unknown subtask '{}' in the constraints", key))?;
+            result
+                .subtasks
+                .entry(subtask.id)
+                .or_default()
+                .extend(parse(constraints)?);
+        }
+        Ok(result)
+    }
+}
+
 /// The iterator item type when following the task input testcases.
 #[derive(Debug, Clone)]
 pub(crate) enum TaskInputEntry {
@@ -503,29 +710,19 @@ pub fn parse_task<P: AsRef<Path>>(
     let task_dir = task_dir.as_ref();
 
     let task_yaml_path = task_dir.join("task.yaml");
-    let task_yaml_orig_path = task_dir.join("task.yaml.orig");
-    let task_yaml_overwrite: bool;
-    let mut yaml: TaskYAML;
-    if task_yaml_orig_path.exists() {
-        task_yaml_overwrite = true;
-        let file = File::open(&task_yaml_orig_path).with_context(|| {
-            format!(
-                "Cannot open task.yaml.orig from {}",
-                task_yaml_orig_path.display()
-            )
-        })?;
-        let yaml_orig: TaskYAMLOrig =
-            serde_yaml::from_reader(file).context("Failed to deserialize task.yaml.orig")?;
-        yaml = yaml_orig.into_task_yaml(task_dir);
-    } else if task_yaml_path.exists() {
-        task_yaml_overwrite = false;
-        let file = File::open(&task_yaml_path)
-            .with_context(|| format!("Cannot open task.yaml from {}", task_yaml_path.display()))?;
-        yaml = serde_yaml::from_reader(file).context("Failed to deserialize task.yaml")?;
-    } else {
-        bail!("No task.yaml found in {}", task_dir.display());
-    }
+    let (mut yaml, task_yaml_overwrite) = read_task_yaml(task_dir)?;
     debug!("The yaml is {:#?}", yaml);
+    if let Some(tag) = yaml
+        .no_cache_failures
+        .iter()
+        .find(|tag| !VALID_TAGS.contains(tag))
+    {
+        bail!(
+            "Invalid tag in no_cache_failures: {} (valid are: {})",
+            tag,
+            VALID_TAGS.join(", ")
+        );
+    }
 
     let map_file = |file: String| -> Option<PathBuf> {
         match file.as_ref() {
@@ -540,47 +737,26 @@ pub fn parse_task<P: AsRef<Path>>(
     let grader_map = Arc::new(GraderMap::new(graders));
     debug!("The graders are: {:#?}", grader_map);
 
-    let task_type = if let Some(comm) = parse_communication_task_data(task_dir, &yaml)? {
-        comm
-    } else {
-        parse_batch_task_data(task_dir, grader_map.clone())?
-    };
-
-    let gen_gen = task_dir.join("gen").join("GEN");
-    let cases_gen = task_dir.join("gen").join("cases.gen");
-    let output_generator: Box<dyn Fn(TestcaseId) -> OutputGenerator> =
-        if let TaskType::Batch(_) = &task_type {
-            Box::new(
-                detect_output_generator(task_dir.to_path_buf(), grader_map.clone())
-                    .context("Failed to detect output generator")?,
-            )
-        } else {
-            Box::new(|_| OutputGenerator::NotAvailable)
-        };
-
-    let inputs = if cases_gen.exists() {
-        debug!("Parsing testcases from gen/cases.gen");
-        let gen = cases_gen::CasesGen::new(&cases_gen, output_generator)?;
-        if !eval_config.dry_run {
-            gen.write_gen_gen().context("Failed to write gen/GEN")?;
-        }
-        gen.get_task_entries()
-    } else if gen_gen.exists() {
-        debug!("Parsing testcases from gen/GEN");
-        gen_gen::parse_gen_gen(
-            &gen_gen,
-            detect_validator(task_dir.into()).context("Failed to detect validator")?,
-            output_generator,
-        )?
-    } else {
-        debug!("Using testcases inside input/");
-        static_inputs::static_inputs(
-            task_dir,
-            detect_validator(task_dir.into()).context("Failed to detect validator")?,
-            output_generator,
-        )
-        .collect()
-    };
+    // The testcases are parsed while the rest of the task is scanned, since on network filesystems
+    // the scanning of the directories dominates the parsing time.
+    let communication = parse_communication_task_data(task_dir, &yaml)?;
+    let batch = communication.is_none();
+    if !batch && yaml.two_pass {
+        bail!("two_pass is supported only by the batch tasks, not by the communication ones");
+    }
+    let (task_type, inputs) = rayon::join(
+        || match communication {
+            Some(comm) => Ok(comm),
+            None => parse_batch_task_data(
+                task_dir,
+                grader_map.clone(),
+                yaml.output_normalization,
+                yaml.two_pass,
+            ),
+        },
+        || parse_task_inputs(task_dir, grader_map.clone(), batch, eval_config),
+    );
+    let (task_type, inputs) = (task_type?, inputs?);
 
     let mut subtasks = HashMap::new();
     let mut testcases = HashMap::new();
@@ -638,20 +814,7 @@ pub fn parse_task<P: AsRef<Path>>(
     if task_yaml_overwrite {
         if !task_yaml_path.exists() || is_tm_deletable(&task_yaml_path)? {
             if !eval_config.dry_run {
-                yaml.score_type_parameters = Some(
-                    subtasks
-                        .iter()
-                        .sorted_by_key(|(id, _)| *id)
-                        .map(|(_, st)| {
-                            let testcases = st
-                                .testcases
-                                .iter()
-                                .map(|tc_num| format!("{tc_num:03}"))
-                                .join("|");
-                            (st.max_score, testcases)
-                        })
-                        .collect(),
-                );
+                yaml.score_type_parameters = Some(score_type_parameters(&subtasks));
 
                 let file = File::create(&task_yaml_path).with_context(|| {
                     format!("Cannot open task.yaml from {}", task_yaml_path.display())
@@ -671,6 +834,29 @@ pub fn parse_task<P: AsRef<Path>>(
     } else if subtasks.values().any(|st| !st.dependencies.is_empty()) {
         bail!("Use task.yaml.orig to use subtask dependencies");
     }
+    if let Some(threshold) = yaml.resource_threshold {
+        if !(threshold > 0.0 && threshold <= 1.0) {
+            bail!(
+                "resource_threshold in task.yaml must be between 0 and 1, not {}",
+                threshold
+            );
+        }
+    }
+    for (language, multiplier) in &yaml.language_multipliers {
+        if !(multiplier.time > 0.0 && multiplier.memory > 0.0) {
+            bail!(
+                "The language_multipliers of {} in task.yaml must be positive",
+                language
+            );
+        }
+    }
+
+    let input_constraints = match &yaml.constraints {
+        Some(constraints) => constraints
+            .parse(&subtasks)
+            .context("Invalid constraints in task.yaml")?,
+        None => Default::default(),
+    };
 
     let mut task = IOITask {
         path: task_dir.into(),
@@ -689,6 +875,17 @@ pub fn parse_task<P: AsRef<Path>>(
         booklets: Vec::new(),
         difficulty: yaml.difficulty,
         syllabus_level: yaml.syllabuslevel,
+        no_cache_failures: yaml.no_cache_failures,
+        solution_groups: yaml.solution_groups,
+        resource_threshold: yaml.resource_threshold,
+        language_multipliers: yaml.language_multipliers,
+        time_clock: yaml.time_clock,
+        memory_metric: eval_config
+            .memory_metric
+            .or(yaml.memory_metric)
+            .unwrap_or_default(),
+        input_format: yaml.input_format.unwrap_or_default(),
+        input_constraints,
         sanity_checks: Arc::new(get_sanity_checks(
             &eval_config
                 .disabled_sanity_checks
@@ -708,6 +905,154 @@ pub fn parse_task<P: AsRef<Path>>(
     Ok(task)
 }
 
+/// Parse the subtasks and the testcases of the task from `gen/cases.gen`, from `gen/GEN` or from the
+/// files inside `input/`. The official solution generates the output files only in batch tasks.
+fn parse_task_inputs(
+    task_dir: &Path,
+    grader_map: Arc<GraderMap>,
+    batch: bool,
+    eval_config: &EvaluationConfig,
+) -> Result<Vec<TaskInputEntry>, Error> {
+    let gen_gen = task_dir.join("gen").join("GEN");
+    let cases_gen = task_dir.join("gen").join("cases.gen");
+    let output_generator: Box<dyn Fn(TestcaseId) -> OutputGenerator> = if batch {
+        Box::new(
+            detect_output_generator(task_dir.to_path_buf(), grader_map)
+                .context("Failed to detect output generator")?,
+        )
+    } else {
+        Box::new(|_| OutputGenerator::NotAvailable)
+    };
+
+    let inputs = if cases_gen.exists() {
+        debug!("Parsing testcases from gen/cases.gen");
+        let gen = cases_gen::CasesGen::new(&cases_gen, output_generator)?;
+        if !eval_config.dry_run {
+            gen.write_gen_gen().context("Failed to write gen/GEN")?;
+        }
+        gen.get_task_entries()
+    } else if gen_gen.exists() {
+        debug!("Parsing testcases from gen/GEN");
+        gen_gen::parse_gen_gen(
+            &gen_gen,
+            detect_validator(task_dir.into()).context("Failed to detect validator")?,
+            output_generator,
+        )?
+    } else {
+        debug!("Using testcases inside input/");
+        static_inputs::static_inputs(
+            task_dir,
+            detect_validator(task_dir.into()).context("Failed to detect validator")?,
+            output_generator,
+        )
+        .collect()
+    };
+    Ok(inputs)
+}
+
+/// Read the task.yaml.orig of the task or, if it's missing, its task.yaml. The returned flag tells
+/// whether the task.yaml should be generated from the task.yaml.orig.
+fn read_task_yaml(task_dir: &Path) -> Result<(TaskYAML, bool), Error> {
+    let task_yaml_path = task_dir.join("task.yaml");
+    let task_yaml_orig_path = task_dir.join("task.yaml.orig");
+    let defaults = match task_dir.parent().and_then(BookletConfig::contest_yaml) {
+        Some(contest_yaml) => contest_yaml?.defaults,
+        None => Default::default(),
+    };
+    if task_yaml_orig_path.exists() {
+        let file = File::open(&task_yaml_orig_path).with_context(|| {
+            format!(
+                "Cannot open task.yaml.orig from {}",
+                task_yaml_orig_path.display()
+            )
+        })?;
+        let yaml_orig: TaskYAMLOrig = from_reader_with_defaults(file, &defaults)
+            .context("Failed to deserialize task.yaml.orig")?;
+        Ok((yaml_orig.into_task_yaml(task_dir), true))
+    } else if task_yaml_path.exists() {
+        let file = File::open(&task_yaml_path)
+            .with_context(|| format!("Cannot open task.yaml from {}", task_yaml_path.display()))?;
+        let yaml = from_reader_with_defaults(file, &defaults)
+            .context("Failed to deserialize task.yaml")?;
+        Ok((yaml, false))
+    } else {
+        bail!("No task.yaml found in {}", task_dir.display());
+    }
+}
+
+/// Deserialize a `task.yaml`, using the defaults of the contest for the keys it doesn't set, either
+/// directly or with one of their aliases.
+fn from_reader_with_defaults<T: DeserializeOwned>(
+    file: File,
+    defaults: &serde_yaml::Mapping,
+) -> Result<T, serde_yaml::Error> {
+    const ALIASES: &[(&str, &str)] = &[
+        ("name", "nome_breve"),
+        ("title", "nome"),
+        ("time_limit", "timeout"),
+        ("memory_limit", "memlimit"),
+    ];
+    let mut yaml: serde_yaml::Value = serde_yaml::from_reader(file)?;
+    if let serde_yaml::Value::Mapping(yaml) = &mut yaml {
+        for (key, value) in defaults {
+            let aliases = ALIASES
+                .iter()
+                .filter(|(name, alias)| key == *name || key == *alias)
+                .flat_map(|(name, alias)| [*name, *alias]);
+            let is_set =
+                yaml.contains_key(key) || aliases.into_iter().any(|k| yaml.contains_key(k));
+            if !is_set {
+                yaml.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    serde_yaml::from_value(yaml)
+}
+
+/// The `score_type_parameters` of cms: for each subtask its score and the regex matching the
+/// numbers of its testcases.
+fn score_type_parameters(subtasks: &HashMap<SubtaskId, SubtaskInfo>) -> Vec<(f64, String)> {
+    subtasks
+        .iter()
+        .sorted_by_key(|(id, _)| *id)
+        .map(|(_, st)| {
+            let testcases = st
+                .testcases
+                .iter()
+                .map(|tc_num| format!("{tc_num:03}"))
+                .join("|");
+            (st.max_score, testcases)
+        })
+        .collect()
+}
+
+/// Build the task.yaml to import the task in cms, with the limits, the scoring and the testcases of
+/// the parsed task and the cms settings of its task.yaml (or task.yaml.orig).
+pub fn cms_task_yaml(task: &IOITask) -> Result<String, Error> {
+    let (mut yaml, _) = read_task_yaml(&task.path)?;
+    let path_to_string = |path: &Option<PathBuf>| {
+        path.as_ref()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    yaml.name = task.name.clone();
+    yaml.title = task.title.clone();
+    yaml.time_limit = task.time_limit;
+    yaml.memory_limit = task.memory_limit;
+    yaml.infile = path_to_string(&task.infile);
+    yaml.outfile = path_to_string(&task.outfile);
+    yaml.score_type = Some(task.testcase_score_aggregator);
+    yaml.score_type_parameters = Some(score_type_parameters(&task.subtasks));
+    yaml.score_precision = task.score_precision;
+    yaml.n_input = Some(task.testcases.len());
+    yaml.primary_language.get_or_insert_with(|| "en".into());
+    yaml.score_mode.get_or_insert_with(|| "max_subtask".into());
+    yaml.token_mode.get_or_insert_with(|| "disabled".into());
+    yaml.public_testcases.get_or_insert_with(|| "all".into());
+    yaml.feedback_level.get_or_insert_with(|| "full".into());
+    serde_yaml::to_string(&yaml).context("Failed to serialize task.yaml")
+}
+
 /// Check if the file is deletable, i.e. it contains the TM_ALLOW_DELETE_COOKIE
 /// Assumes the file exists.
 pub(crate) fn is_tm_deletable(path: &Path) -> Result<bool, Error> {
@@ -716,6 +1061,64 @@ pub(crate) fn is_tm_deletable(path: &Path) -> Result<bool, Error> {
     Ok(content.contains(TM_ALLOW_DELETE_COOKIE))
 }
 
+/// The data files read by the generators, like word lists or precomputed graphs, with their path
+/// inside the sandbox, relative to `gen/`.
+///
+/// If `gen/FILES` exists, each of its lines is a glob pattern, relative to `gen/`, of the data
+/// files. Otherwise the data files are all the files inside `gen/`, except the source files, the
+/// executables, the hidden files and the files describing the testcases.
+pub(crate) fn generator_data_files(task_dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>, Error> {
+    let gen_dir = task_dir.join("gen");
+    let manifest = gen_dir.join("FILES");
+    let paths = if manifest.exists() {
+        let content = fs::read_to_string(&manifest)
+            .with_context(|| format!("Failed to read {}", manifest.display()))?;
+        let mut paths = vec![];
+        for pattern in content.lines().map(str::trim) {
+            if pattern.is_empty() || pattern.starts_with('#') {
+                continue;
+            }
+            let files: Vec<_> = list_files(&gen_dir, vec![pattern])
+                .into_iter()
+                .filter(|path| path.is_file())
+                .collect();
+            if files.is_empty() {
+                bail!("No file matches {} of gen/FILES", pattern);
+            }
+            paths.extend(files);
+        }
+        paths
+    } else {
+        list_files(&gen_dir, vec!["**/*"])
+            .into_iter()
+            .filter(|path| is_generator_data_file(&gen_dir, path))
+            .collect()
+    };
+    let mut data_files = vec![];
+    for path in paths.into_iter().unique() {
+        let sandbox_path = path.strip_prefix(&gen_dir)?.to_owned();
+        data_files.push((path, sandbox_path));
+    }
+    Ok(data_files)
+}
+
+/// Whether a file inside `gen/` is a data file of the generators, when there is no `gen/FILES`.
+fn is_generator_data_file(gen_dir: &Path, path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else {
+        return false;
+    };
+    let relative = path.strip_prefix(gen_dir).unwrap_or(path);
+    let hidden = relative
+        .components()
+        .any(|component| component.as_os_str().to_string_lossy().starts_with('.'));
+    let name = path.file_name().unwrap_or_default();
+    metadata.is_file()
+        && metadata.permissions().mode() & 0o111 == 0
+        && !hidden
+        && !["GEN", "cases.gen", "FILES"].iter().any(|n| name == *n)
+        && LanguageManager::detect_language(path).is_none()
+}
+
 /// Search for a valid input validator inside the task directory. Will return a function that, given
 /// a subtask id, returns an `InputValidator` using that validator. If no validator is found,
 /// `InputValidator::AssumeValid` is used.
@@ -797,7 +1200,12 @@ fn detect_output_generator(
 }
 
 /// Parse the task components relative to the batch task type.
-fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<TaskType, Error> {
+fn parse_batch_task_data(
+    task_dir: &Path,
+    grader_map: Arc<GraderMap>,
+    output_normalization: Option<OutputNormalization>,
+    two_pass: bool,
+) -> Result<TaskType, Error> {
     let mut checkers = find_source_file(
         task_dir,
         vec!["check/checker.*", "cor/correttore.*"],
@@ -821,7 +1229,10 @@ fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<
 
             Checker::Custom(Arc::new(c))
         })
-        .unwrap_or(Checker::WhiteDiff);
+        .unwrap_or_else(|| Checker::WhiteDiff(output_normalization.unwrap_or_default()));
+    if matches!(checker, Checker::Custom(_)) && output_normalization.is_some() {
+        warn!("The output_normalization of task.yaml is ignored since there is a custom checker");
+    }
 
     let official_solution = detect_output_generator(task_dir.to_path_buf(), grader_map)
         .context("Failed to detect output generator")?;
@@ -832,6 +1243,7 @@ fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<
     Ok(TaskType::Batch(BatchTypeData {
         output_generator: official_solution,
         checker,
+        two_pass,
     }))
 }
 
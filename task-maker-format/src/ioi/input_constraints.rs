@@ -0,0 +1,442 @@
+//! The constraints on the values of the input files, declared in the `task.yaml`.
+//!
+//! The first integers of each input file are bound, in order, to the variables of the task, and
+//! each testcase is checked against the constraints of the task and of its subtasks.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Error};
+use itertools::Itertools;
+
+use crate::ioi::SubtaskId;
+
+/// The operator of a constraint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintOperator {
+    /// Operator `<`.
+    Less,
+    /// Operator `<=`.
+    LessEqual,
+    /// Operator `>`.
+    Greater,
+    /// Operator `>=`.
+    GreaterEqual,
+    /// Operator `=`.
+    Equal,
+}
+
+/// An operand of an `InputConstraint`, either a constant or a variable of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputConstraintOperand {
+    /// A constant integer value.
+    Constant(i64),
+    /// The name of a variable of the input.
+    Variable(String),
+}
+
+/// A constraint between the variables of the input, in the format `operand (operator operand)+`,
+/// with all the inequalities in the same direction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputConstraint {
+    /// The operands of the constraint, one more than the operators.
+    pub operands: Vec<InputConstraintOperand>,
+    /// The operators between the operands.
+    pub operators: Vec<ConstraintOperator>,
+}
+
+/// The constraints on the input files of a task.
+#[derive(Debug, Clone, Default)]
+pub struct InputConstraints {
+    /// The names of the variables, bound in order to the first integers of each input file.
+    pub variables: Vec<String>,
+    /// The constraints that all the input files must satisfy.
+    pub global: Vec<InputConstraint>,
+    /// The additional constraints of the input files of some subtasks.
+    pub subtasks: HashMap<SubtaskId, Vec<InputConstraint>>,
+}
+
+impl ConstraintOperator {
+    /// Apply the operator to the provided values and return the result of the comparison.
+    pub fn is_valid(&self, lhs: i64, rhs: i64) -> bool {
+        match self {
+            ConstraintOperator::Less => lhs < rhs,
+            ConstraintOperator::LessEqual => lhs <= rhs,
+            ConstraintOperator::Equal => lhs == rhs,
+            ConstraintOperator::Greater => lhs > rhs,
+            ConstraintOperator::GreaterEqual => lhs >= rhs,
+        }
+    }
+}
+
+impl FromStr for ConstraintOperator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "<" => Ok(ConstraintOperator::Less),
+            "<=" => Ok(ConstraintOperator::LessEqual),
+            ">" => Ok(ConstraintOperator::Greater),
+            ">=" => Ok(ConstraintOperator::GreaterEqual),
+            "=" => Ok(ConstraintOperator::Equal),
+            _ => bail!("Invalid operator: {}", s),
+        }
+    }
+}
+
+impl Display for ConstraintOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstraintOperator::Less => write!(f, "<"),
+            ConstraintOperator::LessEqual => write!(f, "<="),
+            ConstraintOperator::Greater => write!(f, ">"),
+            ConstraintOperator::GreaterEqual => write!(f, ">="),
+            ConstraintOperator::Equal => write!(f, "="),
+        }
+    }
+}
+
+impl InputConstraintOperand {
+    /// Parse an operand, which is either one of the `variables` or an integer constant, also in
+    /// the scientific notation (like `1e5`).
+    fn parse(operand: &str, variables: &[String]) -> Result<Self, Error> {
+        if operand.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            if !variables.iter().any(|var| var == operand) {
+                bail!(
+                    "Unknown variable '{}', the variables of the input are: {}",
+                    operand,
+                    variables.join(", ")
+                );
+            }
+            return Ok(InputConstraintOperand::Variable(operand.into()));
+        }
+        let value = match operand.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => {
+                let mantissa = i64::from_str(mantissa);
+                let exponent = u32::from_str(exponent);
+                match (mantissa, exponent) {
+                    (Ok(mantissa), Ok(exponent)) => 10i64
+                        .checked_pow(exponent)
+                        .and_then(|power| mantissa.checked_mul(power)),
+                    _ => None,
+                }
+            }
+            None => i64::from_str(operand).ok(),
+        };
+        value
+            .map(InputConstraintOperand::Constant)
+            .ok_or_else(|| anyhow!("Invalid integer constant '{}'", operand))
+    }
+
+    /// The value of this operand given the values of the variables, `None` if it's a variable
+    /// without value.
+    fn get_val(&self, values: &HashMap<String, i64>) -> Option<i64> {
+        match self {
+            InputConstraintOperand::Constant(k) => Some(*k),
+            InputConstraintOperand::Variable(var) => values.get(var).copied(),
+        }
+    }
+}
+
+impl Display for InputConstraintOperand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputConstraintOperand::Constant(k) => write!(f, "{}", k),
+            InputConstraintOperand::Variable(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl InputConstraint {
+    /// Parse a constraint between the provided variables. The constraint is either a chain of
+    /// comparisons (like `1 <= N <= M`) or a range of a variable (like `N: 1..1e5`, the same as
+    /// `1 <= N <= 1e5`).
+    pub fn parse(constraint: &str, variables: &[String]) -> Result<Self, Error> {
+        Self::parse_inner(constraint, variables)
+            .with_context(|| format!("Malformed constraint '{}'", constraint))
+    }
+
+    fn parse_inner(constraint: &str, variables: &[String]) -> Result<Self, Error> {
+        if let Some((variable, range)) = constraint.split_once(':') {
+            let Some((low, high)) = range.split_once("..") else {
+                bail!("The range of a variable must be like 1..100");
+            };
+            let chain = format!("{} <= {} <= {}", low.trim(), variable.trim(), high.trim());
+            return Self::parse_inner(&chain, variables);
+        }
+        let is_operator = |c: char| matches!(c, '<' | '>' | '=');
+        // split the constraint in operands and operators, also when they are not separated by
+        // spaces
+        let mut tokens: Vec<String> = vec![];
+        let mut current = String::new();
+        for c in constraint.chars() {
+            let continues = current
+                .chars()
+                .last()
+                .map(|last| is_operator(last) == is_operator(c))
+                .unwrap_or(true);
+            if (c.is_whitespace() || !continues) && !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            if !c.is_whitespace() {
+                current.push(c);
+            }
+        }
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+
+        let mut result = InputConstraint {
+            operands: vec![],
+            operators: vec![],
+        };
+        let mut direction = None;
+        for (index, token) in tokens.iter().enumerate() {
+            if index % 2 == 0 {
+                result
+                    .operands
+                    .push(InputConstraintOperand::parse(token, variables)?);
+                continue;
+            }
+            let operator = ConstraintOperator::from_str(token)?;
+            let dir = match operator {
+                ConstraintOperator::Less | ConstraintOperator::LessEqual => Some(true),
+                ConstraintOperator::Greater | ConstraintOperator::GreaterEqual => Some(false),
+                ConstraintOperator::Equal => None,
+            };
+            if direction.is_none() {
+                direction = dir;
+            }
+            if dir.is_some() && direction != dir {
+                bail!("Inequality direction must be the same");
+            }
+            result.operators.push(operator);
+        }
+        if result.operators.len() + 1 != result.operands.len() {
+            bail!("Invalid number of operators");
+        }
+        if result.operands.len() < 2 {
+            bail!("Too few operands");
+        }
+        Ok(result)
+    }
+
+    /// Check if the values of the variables satisfy this constraint, `None` if some of the
+    /// variables of the constraint don't have a value.
+    pub fn is_valid(&self, values: &HashMap<String, i64>) -> Option<bool> {
+        let mut last = self.operands[0].get_val(values)?;
+        for (operator, operand) in self.operators.iter().zip(&self.operands[1..]) {
+            let this = operand.get_val(values)?;
+            if !operator.is_valid(last, this) {
+                return Some(false);
+            }
+            last = this;
+        }
+        Some(true)
+    }
+
+    /// The variables used in this constraint.
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        self.operands.iter().filter_map(|operand| match operand {
+            InputConstraintOperand::Variable(var) => Some(var.as_str()),
+            InputConstraintOperand::Constant(_) => None,
+        })
+    }
+}
+
+impl Display for InputConstraint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.operands[0])?;
+        for (operator, operand) in self.operators.iter().zip(&self.operands[1..]) {
+            write!(f, " {} {}", operator, operand)?;
+        }
+        Ok(())
+    }
+}
+
+impl InputConstraints {
+    /// Whether no constraint is declared for the task.
+    pub fn is_empty(&self) -> bool {
+        self.global.is_empty() && self.subtasks.values().all(Vec::is_empty)
+    }
+
+    /// The additional constraints of the input files of a subtask.
+    pub fn subtask_constraints(&self, subtask: SubtaskId) -> &[InputConstraint] {
+        self.subtasks
+            .get(&subtask)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The smallest and the largest value allowed for each variable in a subtask, considering only
+    /// the comparisons of the variables with the constants.
+    pub fn bounds(&self, subtask: SubtaskId) -> HashMap<&str, (Option<i64>, Option<i64>)> {
+        use InputConstraintOperand::{Constant, Variable};
+        let mut bounds: HashMap<&str, (Option<i64>, Option<i64>)> = HashMap::new();
+        let constraints = self.global.iter().chain(self.subtask_constraints(subtask));
+        for constraint in constraints {
+            let pairs = constraint.operands.iter().tuple_windows();
+            for ((lhs, rhs), operator) in pairs.zip(&constraint.operators) {
+                // normalize the comparison to `lhs op rhs` with `op` either `<`, `<=` or `=`
+                let (lhs, rhs, operator) = match operator {
+                    ConstraintOperator::Greater => (rhs, lhs, ConstraintOperator::Less),
+                    ConstraintOperator::GreaterEqual => (rhs, lhs, ConstraintOperator::LessEqual),
+                    _ => (lhs, rhs, *operator),
+                };
+                let strict = (operator == ConstraintOperator::Less) as i64;
+                let (var, lower, upper) = match (lhs, rhs) {
+                    (Variable(var), Constant(k)) => {
+                        let lower = (operator == ConstraintOperator::Equal).then_some(*k);
+                        (var, lower, Some(k - strict))
+                    }
+                    (Constant(k), Variable(var)) => {
+                        let upper = (operator == ConstraintOperator::Equal).then_some(*k);
+                        (var, Some(k + strict), upper)
+                    }
+                    _ => continue,
+                };
+                let entry = bounds.entry(var.as_str()).or_default();
+                entry.0 = entry.0.max(lower);
+                entry.1 = match (entry.1, upper) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+        }
+        bounds
+    }
+
+    /// Bind the variables to the first integers of the content of an input file. An error is
+    /// returned if the file starts with fewer integers than the variables.
+    pub fn parse_values(&self, content: &str) -> Result<HashMap<String, i64>, String> {
+        let mut tokens = content.split_whitespace();
+        let mut values = HashMap::new();
+        for var in &self.variables {
+            let Some(token) = tokens.next() else {
+                return Err(format!("The file ends before the value of {}", var));
+            };
+            let value = i64::from_str(token)
+                .map_err(|_| format!("The value of {} is not an integer: '{}'", var, token))?;
+            values.insert(var.clone(), value);
+        }
+        Ok(values)
+    }
+
+    /// Extract the values of the variables reported by the validator in its standard output, with
+    /// lines like `@value N 100000`. The lines with unknown variables or invalid values are
+    /// ignored.
+    pub fn parse_reported_values(&self, stdout: &str) -> HashMap<String, i64> {
+        let mut values = HashMap::new();
+        for line in stdout.lines() {
+            let mut tokens = line.split_whitespace();
+            if tokens.next() != Some("@value") {
+                continue;
+            }
+            let (Some(var), Some(value), None) = (tokens.next(), tokens.next(), tokens.next())
+            else {
+                continue;
+            };
+            if !self.variables.iter().any(|v| v == var) {
+                continue;
+            }
+            if let Ok(value) = i64::from_str(value) {
+                values.insert(var.to_string(), value);
+            }
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> Vec<String> {
+        vec!["N".into(), "M".into()]
+    }
+
+    #[test]
+    fn test_parse_constraint_chain() {
+        let constraint = InputConstraint::parse("1 <= N<M <= 1e5", &variables()).unwrap();
+        assert_eq!(constraint.to_string(), "1 <= N < M <= 100000");
+        assert_eq!(constraint.variables().collect_vec(), vec!["N", "M"]);
+    }
+
+    #[test]
+    fn test_parse_constraint_range() {
+        let constraint = InputConstraint::parse("N: -5..2e3", &variables()).unwrap();
+        assert_eq!(constraint.to_string(), "-5 <= N <= 2000");
+    }
+
+    #[test]
+    fn test_parse_constraint_invalid() {
+        let vars = variables();
+        assert!(InputConstraint::parse("K <= 5", &vars).is_err());
+        assert!(InputConstraint::parse("N <= 5 >= M", &vars).is_err());
+        assert!(InputConstraint::parse("N <=", &vars).is_err());
+        assert!(InputConstraint::parse("N", &vars).is_err());
+        assert!(InputConstraint::parse("N <= 1x", &vars).is_err());
+        assert!(InputConstraint::parse("N: 1...", &vars).is_err());
+        assert!(InputConstraint::parse("N <= 1e30", &vars).is_err());
+    }
+
+    #[test]
+    fn test_constraint_is_valid() {
+        let constraint = InputConstraint::parse("1 <= N < M", &variables()).unwrap();
+        let values = |n, m| HashMap::from([("N".to_string(), n), ("M".to_string(), m)]);
+        assert_eq!(constraint.is_valid(&values(1, 2)), Some(true));
+        assert_eq!(constraint.is_valid(&values(2, 2)), Some(false));
+        assert_eq!(constraint.is_valid(&values(0, 2)), Some(false));
+        assert_eq!(constraint.is_valid(&HashMap::new()), None);
+    }
+
+    #[test]
+    fn test_constraints_bounds() {
+        let vars = variables();
+        let constraints = InputConstraints {
+            variables: vars.clone(),
+            global: vec![
+                InputConstraint::parse("N: 1..1e5", &vars).unwrap(),
+                InputConstraint::parse("M <= N", &vars).unwrap(),
+            ],
+            subtasks: HashMap::from([(
+                1,
+                vec![
+                    InputConstraint::parse("N < 100", &vars).unwrap(),
+                    InputConstraint::parse("M > 3", &vars).unwrap(),
+                ],
+            )]),
+        };
+        let bounds = constraints.bounds(0);
+        assert_eq!(bounds["N"], (Some(1), Some(100000)));
+        assert!(!bounds.contains_key("M"));
+        let bounds = constraints.bounds(1);
+        assert_eq!(bounds["N"], (Some(1), Some(99)));
+        assert_eq!(bounds["M"], (Some(4), None));
+    }
+
+    #[test]
+    fn test_constraints_parse_values() {
+        let constraints = InputConstraints {
+            variables: variables(),
+            ..Default::default()
+        };
+        let values = constraints.parse_values("3 5\n1 2 3\n").unwrap();
+        assert_eq!(values["N"], 3);
+        assert_eq!(values["M"], 5);
+        assert!(constraints.parse_values("3\n").is_err());
+        assert!(constraints.parse_values("3 abc\n").is_err());
+    }
+
+    #[test]
+    fn test_constraints_parse_reported_values() {
+        let constraints = InputConstraints {
+            variables: variables(),
+            ..Default::default()
+        };
+        let values = constraints.parse_reported_values("ok\n@value N 42\n@value K 1\n@value M x\n");
+        assert_eq!(values.len(), 1);
+        assert_eq!(values["N"], 42);
+    }
+}
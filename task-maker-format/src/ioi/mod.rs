@@ -15,13 +15,14 @@
 //! a `Checker`, a program that computes the score of the testcase given the input file, the output
 //! file and the _correct_ output file (the one produced by the jury).
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use itertools::Itertools;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 use unic::normal::StrNormalForm;
@@ -30,11 +31,12 @@ use wildmatch::WildMatch;
 use curses_ui::CursesUI;
 pub use dag::*;
 pub use format::italian_yaml;
+pub use input_constraints::*;
 pub use statement::*;
 pub use task_info::*;
-use task_maker_dag::{ExecutionDAGConfig, FileUuid};
-use task_maker_diagnostics::CodeSpan;
-use task_maker_lang::GraderMap;
+use task_maker_dag::{ExecutionDAGConfig, ExecutionTag, FileUuid, MemoryMetric, TimeClock};
+use task_maker_diagnostics::{CodeSpan, Diagnostic};
+use task_maker_lang::{GraderMap, LanguageManager};
 pub use ui_state::*;
 
 use crate::ioi::format::italian_yaml::TM_ALLOW_DELETE_COOKIE;
@@ -42,12 +44,16 @@ use crate::ioi::italian_yaml::is_tm_deletable;
 use crate::sanity_checks::SanityChecks;
 use crate::solution::SolutionInfo;
 use crate::ui::*;
-use crate::{EvaluationConfig, EvaluationData, TaskInfo, UISender};
+use crate::{
+    list_files, remove_artifact, EvaluationConfig, EvaluationData, Solution, SourceFile, Tag,
+    TaskInfo, UISender,
+};
 
 mod curses_ui;
 mod dag;
 pub(crate) mod finish_ui;
 mod format;
+mod input_constraints;
 pub mod sanity_checks;
 mod statement;
 pub(crate) mod task_info;
@@ -58,6 +64,16 @@ pub type SubtaskId = u32;
 /// In IOI tasks the testcase numbers are non-negative 0-based integers.
 pub type TestcaseId = u32;
 
+/// A testcase a solution is evaluated on: its subtask, its number, and its input, validation and
+/// official output files.
+type EvaluatedTestcase = (
+    SubtaskId,
+    TestcaseId,
+    FileUuid,
+    Option<FileUuid>,
+    Option<FileUuid>,
+);
+
 /// This struct will manage the scores of a solution in a task and will emit the ui messages when
 /// a new score is ready.
 #[derive(Debug, Clone)]
@@ -70,6 +86,8 @@ pub struct ScoreManager {
     max_subtask_scores: HashMap<SubtaskId, f64>,
     /// The scores of each testcase.
     testcase_scores: HashMap<TestcaseId, Option<f64>>,
+    /// The names of the testcases named in gen/GEN.
+    testcase_names: HashMap<TestcaseId, String>,
     /// The list of testcases of each subtask.
     subtask_testcases: HashMap<SubtaskId, Vec<TestcaseId>>,
     /// The aggregator to use for computing the subtask scores.
@@ -145,6 +163,35 @@ pub struct IOITask {
     /// An integer that defines the level inside a _syllabus_ (for example for the Olympiads in
     /// Teams). Used only in booklet compilations.
     pub syllabus_level: Option<u8>,
+    /// The tags of the executions whose failures are not cached, in addition to the ones disabled
+    /// from the command line.
+    #[serde(default)]
+    pub no_cache_failures: Vec<String>,
+    /// The prefixes of the paths of the solutions, like `sol/wrong/` or `sol/slow_`, grouping the
+    /// solutions in the tables of the UIs, in this order.
+    #[serde(default)]
+    pub solution_groups: Vec<String>,
+    /// The fraction of the time and memory limits above which the resources used by the solutions
+    /// are highlighted in the UIs as close to the limits, `YELLOW_RESOURCE_THRESHOLD` if not set.
+    #[serde(default)]
+    pub resource_threshold: Option<f64>,
+    /// The multipliers of the limits for the solutions in some languages, indexed by the extension
+    /// of the source files (e.g. `py`) or by the name of their language (e.g. `Python3`).
+    #[serde(default)]
+    pub language_multipliers: HashMap<String, LanguageMultiplier>,
+    /// The clock that drives the time limit verdict of the solutions, if `None` the default of the
+    /// task type is used.
+    #[serde(default)]
+    pub time_clock: Option<TimeClock>,
+    /// The measure of the memory of the solutions that is checked against the memory limit.
+    #[serde(default)]
+    pub memory_metric: MemoryMetric,
+    /// The rules on the format of the input files, and how their violations are reported.
+    #[serde(default)]
+    pub input_format: InputFormatPolicy,
+    /// The constraints on the values of the input files declared in the task.yaml.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub input_constraints: InputConstraints,
     /// The sanity checks attached to this task. Wrapped in Arc since `SanityChecks` is not Clone.
     /// It's also not `Serialize` nor `Deserialize`, all the sanity checks will be lost on
     /// serialization.
@@ -152,6 +199,84 @@ pub struct IOITask {
     pub sanity_checks: Arc<SanityChecks<IOITask>>,
 }
 
+/// The multipliers of the time and memory limits of the solutions written in a language, for the
+/// languages that are slower or use more memory than the others.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TypeScriptify)]
+pub struct LanguageMultiplier {
+    /// The multiplier of the time limit.
+    #[serde(default = "default_multiplier")]
+    pub time: f64,
+    /// The multiplier of the memory limit.
+    #[serde(default = "default_multiplier")]
+    pub memory: f64,
+}
+
+impl Default for LanguageMultiplier {
+    fn default() -> Self {
+        LanguageMultiplier {
+            time: 1.0,
+            memory: 1.0,
+        }
+    }
+}
+
+/// The multiplier of the limits that are not set, which doesn't change them.
+fn default_multiplier() -> f64 {
+    1.0
+}
+
+/// How a violation of a rule of the format of the input files is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, TypeScriptify)]
+#[serde(rename_all = "snake_case")]
+pub enum InputFormatLevel {
+    /// The rule is not checked.
+    #[default]
+    Off,
+    /// The violations are reported as warnings.
+    Warn,
+    /// The violations are reported as errors.
+    Fail,
+}
+
+/// The rules on the format of the input files, checked after their generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TypeScriptify)]
+#[serde(default)]
+pub struct InputFormatPolicy {
+    /// The lines must not end with `\r\n`.
+    pub crlf: InputFormatLevel,
+    /// The lines must not end with spaces or tabs.
+    pub trailing_spaces: InputFormatLevel,
+    /// The file must end with `\n`.
+    pub final_newline: InputFormatLevel,
+    /// The file must contain only ASCII characters.
+    pub ascii: InputFormatLevel,
+}
+
+impl Default for InputFormatPolicy {
+    fn default() -> Self {
+        InputFormatPolicy {
+            crlf: InputFormatLevel::Off,
+            trailing_spaces: InputFormatLevel::Off,
+            final_newline: InputFormatLevel::Warn,
+            ascii: InputFormatLevel::Off,
+        }
+    }
+}
+
+impl InputFormatPolicy {
+    /// Whether none of the rules is checked.
+    pub fn is_off(&self) -> bool {
+        [
+            self.crlf,
+            self.trailing_spaces,
+            self.final_newline,
+            self.ascii,
+        ]
+        .iter()
+        .all(|level| *level == InputFormatLevel::Off)
+    }
+}
+
 /// A subtask of a IOI task.
 #[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify, Default)]
 pub struct SubtaskInfo {
@@ -188,12 +313,17 @@ pub struct SubtaskInfo {
 pub struct TestcaseInfo {
     /// The id of the testcase.
     pub id: TestcaseId,
+    /// The name given to the testcase in gen/GEN, if any.
+    pub name: Option<String>,
     /// The generator of the input file for this testcase.
     pub input_generator: InputGenerator,
     /// The generator of the output file for this testcase.
     pub output_generator: OutputGenerator,
     /// The generated input file UUID. This is set only after the DAG is built.
     pub input_file: Option<FileUuid>,
+    /// The standard output of the validation of the input file in the subtask owning the testcase,
+    /// if it's validated. This is set only after the DAG is built.
+    pub validation_file: Option<FileUuid>,
     /// The generated official output file UUID. This is set only after the DAG is built.
     pub official_output_file: Option<FileUuid>,
 }
@@ -228,6 +358,14 @@ impl IOITask {
             booklets: vec![],
             difficulty: None,
             syllabus_level: None,
+            no_cache_failures: vec![],
+            solution_groups: vec![],
+            resource_threshold: None,
+            language_multipliers: Default::default(),
+            time_clock: None,
+            memory_metric: MemoryMetric::AddressSpace,
+            input_format: Default::default(),
+            input_constraints: Default::default(),
             sanity_checks: Arc::new(Default::default()),
         }
     }
@@ -237,6 +375,77 @@ impl IOITask {
         path.as_ref().join("task.yaml").exists() || path.as_ref().join("task.yaml.orig").exists()
     }
 
+    /// The multipliers of the limits for the given solution, based on its language.
+    pub fn language_multiplier(&self, solution: &Path) -> LanguageMultiplier {
+        if self.language_multipliers.is_empty() {
+            return LanguageMultiplier::default();
+        }
+        let extension = solution
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        let language = LanguageManager::detect_language(solution).map(|l| l.name().to_lowercase());
+        self.language_multipliers
+            .iter()
+            .find(|(key, _)| {
+                let key = Some(key.to_lowercase());
+                key == extension || key == language
+            })
+            .map(|(_, multiplier)| *multiplier)
+            .unwrap_or_default()
+    }
+
+    /// The time limit for the given solution, after applying the multiplier of its language.
+    pub fn time_limit_for(&self, solution: &Path) -> Option<f64> {
+        let multiplier = self.language_multiplier(solution);
+        self.time_limit
+            .map(|time_limit| time_limit * multiplier.time)
+    }
+
+    /// The memory limit in MiB for the given solution, after applying the multiplier of its
+    /// language.
+    pub fn memory_limit_for(&self, solution: &Path) -> Option<u64> {
+        let multiplier = self.language_multiplier(solution);
+        self.memory_limit
+            .map(|memory_limit| (memory_limit as f64 * multiplier.memory).round() as u64)
+    }
+
+    /// The clock that drives the time limit verdict of the solutions. Unless set in the task, it's
+    /// the wall time for the communication tasks, where the solutions spend most of their time
+    /// waiting for the manager, and the cpu time for the others.
+    pub fn time_clock(&self) -> TimeClock {
+        self.time_clock.unwrap_or(match self.task_type {
+            TaskType::Communication(_) => TimeClock::Wall,
+            _ => TimeClock::Cpu,
+        })
+    }
+
+    /// Whether the solutions are run twice on each testcase, the second time on the output of the
+    /// first.
+    pub fn is_two_pass(&self) -> bool {
+        matches!(&self.task_type, TaskType::Batch(data) if data.two_pass)
+    }
+
+    /// The name given to a testcase in gen/GEN, if any.
+    pub fn testcase_name(&self, testcase: TestcaseId) -> Option<&str> {
+        self.testcases
+            .get(&testcase)
+            .and_then(|testcase| testcase.name.as_deref())
+    }
+
+    /// How the UIs refer to a testcase: `st2/big-random-tree` if the testcase is named in gen/GEN,
+    /// just its id otherwise. See [`testcase_label`].
+    pub fn testcase_label(&self, testcase: TestcaseId) -> String {
+        let name = self.testcase_name(testcase);
+        let subtask = self
+            .subtasks
+            .values()
+            .find(|subtask| subtask.testcases_owned.contains(&testcase));
+        match subtask {
+            Some(subtask) => testcase_label(subtask.id, testcase, name),
+            None => testcase.to_string(),
+        }
+    }
+
     /// Get the root directory of the task.
     pub fn path(&self) -> &Path {
         &self.path
@@ -266,15 +475,34 @@ impl IOITask {
         eval: &mut EvaluationData,
         config: &EvaluationConfig,
     ) -> Result<(), Error> {
+        let filtered = !config.subtask_filter.is_empty() || !config.testcase_filter.is_empty();
+        if filtered {
+            self.filter_testcases(&config.subtask_filter, &config.testcase_filter)?;
+        }
         eval.sender.send(UIMessage::IOITask {
             task: Box::new(self.clone()),
         })?;
+        eval.dag.config_mut().failure_cache_mode.disable(
+            self.no_cache_failures
+                .iter()
+                .map(|tag| ExecutionTag::from(tag.as_str())),
+        );
         eval.solutions = config.find_solutions(
             &self.path,
             vec!["sol/*"],
             Some(self.grader_map.clone()),
             eval,
         );
+        // The subtasks may be evaluated only partially, so their outcome is not meaningful.
+        let partial = filtered || config.sample_testcases.is_some();
+        if partial && eval.solutions.iter().any(|sol| !sol.checks.is_empty()) {
+            eval.add_diagnostic(Diagnostic::warning(
+                "Only part of the task is evaluated, the checks of the solutions are skipped",
+            ))?;
+            for solution in eval.solutions.iter_mut() {
+                solution.checks.clear();
+            }
+        }
 
         let solutions: Vec<_> = eval
             .solutions
@@ -304,11 +532,35 @@ impl IOITask {
         self.task_type
             .prepare_dag(eval)
             .context("Failed to prepare DAG")?;
+        if eval.detect_flaky && !matches!(self.task_type, TaskType::Batch(_)) {
+            eval.add_diagnostic(Diagnostic::warning(
+                "Only the batch tasks support --detect-flaky, the solutions are evaluated once",
+            ))?;
+        }
+        if config.check_checker && !matches!(self.task_type, TaskType::Batch(_)) {
+            eval.add_diagnostic(Diagnostic::warning(
+                "Only the batch tasks support --check-checker, the checker is not verified",
+            ))?;
+        }
+        if config.verify_generators || config.check_checker || eval.detect_flaky {
+            // the repeated executions must run again, not come from the cache
+            eval.dag
+                .config_mut()
+                .cache_mode
+                .disable([Tag::Determinism.into()]);
+        }
 
         let mut generated_io: HashMap<_, _> = HashMap::new();
+        // The testcases the solutions are evaluated on, with their input, validation and official
+        // output files.
+        let mut evaluated_testcases = Vec::new();
 
         for subtask in self.subtasks.values() {
             trace!("Executing the generation of subtask {}", subtask.id);
+            let evaluated = match config.sample_testcases {
+                Some(sample) => subtask.sampled_testcases(sample),
+                None => subtask.testcases_owned.clone(),
+            };
 
             for &testcase_id in subtask.testcases_owned.iter() {
                 trace!(
@@ -323,8 +575,14 @@ impl IOITask {
                     .expect("Testcase not found in the task");
                 let input = testcase
                     .input_generator
-                    .generate_and_bind(eval, subtask.id, testcase.id)
+                    .generate_and_bind(eval, subtask.id, testcase.id, testcase.name.as_deref())
                     .context("Failed to bind input generator")?;
+                if config.verify_generators {
+                    testcase
+                        .input_generator
+                        .verify_and_bind(eval, subtask.id, testcase.id, input)
+                        .context("Failed to bind the verification of the generator")?;
+                }
                 let val_handle = subtask
                     .input_validator
                     .validate_and_bind(
@@ -339,42 +597,59 @@ impl IOITask {
                     .output_generator
                     .generate_and_bind(self, eval, subtask.id, testcase.id, input, val_handle)
                     .context("Failed to bind output generator")?;
-                // Store the generated input and output files for setting them into the task
-                // outside the loop.
-                generated_io.insert(testcase.id, (input, output));
-
-                for (solution, score_manager) in solutions.iter() {
-                    trace!(
-                        "Evaluation of the solution {:?} against subtask {} / testcase {}",
-                        solution.source_file.name(),
-                        subtask.id,
-                        testcase.id
-                    );
+                if let (true, TaskType::Batch(data), Some(output)) =
+                    (config.check_checker, &self.task_type, output)
+                {
+                    data.checker
+                        .verify_and_bind(eval, subtask.id, testcase.id, input, output)
+                        .context("Failed to bind the verification of the checker")?;
+                }
+                // Store the generated input and output files, and the output of the validator, for
+                // setting them into the task outside the loop.
+                generated_io.insert(testcase.id, (input, val_handle, output));
 
-                    self.task_type
-                        .evaluate(
-                            self,
-                            eval,
-                            subtask.id,
-                            testcase.id,
-                            &solution.source_file,
-                            input,
-                            val_handle,
-                            output,
-                            score_manager.clone(),
-                        )
-                        .context("Failed to bind evaluation")?;
+                if evaluated.contains(&testcase.id) {
+                    evaluated_testcases.push((subtask.id, testcase.id, input, val_handle, output));
                 }
             }
         }
-        // Store inside the task the FileUuid of the input and official output files. This cannot
-        // be done while generating because task cannot be borrowed mutably in the loop.
-        for (testcase_id, (input, output)) in generated_io {
+        // Store inside the task the FileUuid of the input, validation and official output files.
+        // This cannot be done while generating because task cannot be borrowed mutably in the loop.
+        for (testcase_id, (input, validation, output)) in generated_io {
             let testcase = self.testcases.get_mut(&testcase_id).unwrap();
             testcase.input_file = Some(input);
+            testcase.validation_file = validation;
             testcase.official_output_file = output;
         }
 
+        // The parts of the DAG of the solutions don't depend on each other, so they are built in
+        // parallel and then merged into the DAG. With an incremental DAG the evaluations of a
+        // compiled solution are added to the DAG only after its compilation, so the generation of
+        // the testcases can start without waiting for the evaluations of all the solutions to be
+        // built.
+        let evaluated_testcases = Arc::new(evaluated_testcases);
+        let shared_task = eval.incremental.then(|| Arc::new(self.clone()));
+        let task = &*self;
+        let slices = solutions
+            .into_iter()
+            .map(|solution| (solution, eval.extension()))
+            .collect_vec()
+            .into_par_iter()
+            .map(|((solution, score_manager), mut slice)| {
+                task.build_solution_dag(
+                    &mut slice,
+                    solution,
+                    score_manager,
+                    &evaluated_testcases,
+                    shared_task.as_ref(),
+                )?;
+                Ok(slice)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        for slice in slices {
+            eval.merge_extension(slice);
+        }
+
         for subtask in self.subtasks.values() {
             for &testcase_id in subtask.testcases.iter() {
                 if !subtask.testcases_owned.contains(&testcase_id) {
@@ -407,14 +682,133 @@ impl IOITask {
         Ok(())
     }
 
+    /// Add to the DAG the compilation of a solution and its evaluation on the testcases. If the task
+    /// is shared, the evaluation of a compiled solution is added to the DAG only after its
+    /// compilation.
+    fn build_solution_dag(
+        &self,
+        eval: &mut EvaluationData,
+        solution: Solution,
+        score_manager: Arc<Mutex<ScoreManager>>,
+        testcases: &Arc<Vec<EvaluatedTestcase>>,
+        shared_task: Option<&Arc<IOITask>>,
+    ) -> Result<(), Error> {
+        let comp = solution
+            .source_file
+            .prepare(eval)
+            .context("Failed to prepare solution")?;
+        match (comp, shared_task) {
+            (Some(comp), Some(task)) => {
+                let task = task.clone();
+                let testcases = testcases.clone();
+                let task_root = eval.task_root.clone();
+                let sender = eval.sender.clone();
+                let (strict, detect_flaky, external_diff) =
+                    (eval.strict, eval.detect_flaky, eval.external_diff);
+                eval.dag.extend_after_execution(&comp, move |dag| {
+                    let mut eval = EvaluationData {
+                        task_root,
+                        dag: std::mem::take(dag),
+                        solutions: vec![],
+                        sender,
+                        strict,
+                        detect_flaky,
+                        incremental: true,
+                        external_diff,
+                    };
+                    let result = task.evaluate_solution(
+                        &mut eval,
+                        &solution.source_file,
+                        &testcases,
+                        score_manager,
+                    );
+                    *dag = eval.dag;
+                    result
+                });
+                Ok(())
+            }
+            _ => self.evaluate_solution(eval, &solution.source_file, testcases, score_manager),
+        }
+    }
+
+    /// Add to the DAG the evaluation of a solution on the testcases, given with their input,
+    /// validation and official output files.
+    fn evaluate_solution(
+        &self,
+        eval: &mut EvaluationData,
+        source_file: &SourceFile,
+        testcases: &[EvaluatedTestcase],
+        score_manager: Arc<Mutex<ScoreManager>>,
+    ) -> Result<(), Error> {
+        for &(subtask_id, testcase_id, input, val_handle, output) in testcases {
+            trace!(
+                "Evaluation of the solution {:?} against subtask {} / testcase {}",
+                source_file.name(),
+                subtask_id,
+                testcase_id
+            );
+            self.task_type
+                .evaluate(
+                    self,
+                    eval,
+                    subtask_id,
+                    testcase_id,
+                    source_file,
+                    input,
+                    val_handle,
+                    output,
+                    score_manager.clone(),
+                )
+                .context("Failed to bind evaluation")?;
+        }
+        Ok(())
+    }
+
+    /// Keep only the subtasks and the testcases matching the filters, an empty filter matches
+    /// everything. The testcases are kept only in the subtask that owns them, and the subtasks left
+    /// without testcases are removed.
+    pub fn filter_testcases(
+        &mut self,
+        subtasks: &[SubtaskId],
+        testcases: &[TestcaseId],
+    ) -> Result<(), Error> {
+        for subtask in self.subtasks.values_mut() {
+            if !subtasks.is_empty() && !subtasks.contains(&subtask.id) {
+                subtask.testcases_owned.clear();
+            }
+            subtask
+                .testcases_owned
+                .retain(|tc| testcases.is_empty() || testcases.contains(tc));
+            subtask.testcases.clone_from(&subtask.testcases_owned);
+        }
+        self.subtasks
+            .retain(|_, subtask| !subtask.testcases_owned.is_empty());
+        if self.subtasks.is_empty() {
+            bail!("No testcase matches the subtask and testcase filters");
+        }
+        let kept: HashSet<_> = self.subtasks.keys().copied().collect();
+        for subtask in self.subtasks.values_mut() {
+            subtask.dependencies.retain(|dep| kept.contains(dep));
+        }
+        let owned: HashSet<_> = self
+            .subtasks
+            .values()
+            .flat_map(|subtask| subtask.testcases_owned.iter().copied())
+            .collect();
+        self.testcases.retain(|id, _| owned.contains(id));
+        Ok(())
+    }
+
     /// Hook called after the execution completed, useful for sending messages to the UI about the
     /// results of the sanity checks with data available only after the evaluation.
     pub fn sanity_check_post_hook(&self, eval: &mut EvaluationData) -> Result<(), Error> {
         self.sanity_checks.post_hook(self, eval)
     }
 
-    /// Clean the task folder removing the files that can be generated automatically.
-    pub fn clean(&self) -> Result<(), Error> {
+    /// The files and the directories inside the task folder that can be generated automatically,
+    /// and that are removed by [`clean`](#method.clean).
+    pub fn artifacts(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut artifacts = vec![];
         for dir in &["input", "output"] {
             let dir = self.path.join(dir);
             if !dir.exists() {
@@ -440,52 +834,38 @@ impl IOITask {
                 }) {
                     continue;
                 }
-                info!("Removing {}", file.display());
-                std::fs::remove_file(&file)
-                    .with_context(|| format!("Failed to clean file {}", file.display()))?;
-            }
-            info!("Removing {}", dir.display());
-            if let Err(e) = std::fs::remove_dir(&dir) {
-                // FIXME: this should be `e.kind() == ErrorKind::DirectoryNotEmpty`, but it is not
-                //        stable yet.
-                if e.to_string().contains("Directory not empty") {
-                    warn!("Directory {} not empty!", dir.display());
-                } else {
-                    Err(e).with_context(|| format!("Cannot remove {}", dir.display()))?;
-                }
+                artifacts.push(file);
             }
         }
-        // remove the bin/ folder
+        // the bin/ folder
         let bin_path = self.path.join("bin");
         if bin_path.exists() {
-            info!("Removing {}", bin_path.display());
-            std::fs::remove_dir_all(&bin_path).with_context(|| {
-                format!("Failed to remove bin/ directory at {}", bin_path.display())
-            })?;
+            artifacts.push(bin_path);
         }
-        // remove the compiled checkers
+        // the compiled checkers
         if let TaskType::Batch(data) = &self.task_type {
             if let Checker::Custom(_) = data.checker {
                 for checker in &["check/checker", "cor/correttore"] {
                     let path = self.path.join(checker);
                     if path.exists() {
-                        info!("Removing {}", path.display());
-                        std::fs::remove_file(&path).with_context(|| {
-                            format!("Failed to remove compiled checker at {}", path.display())
-                        })?;
+                        artifacts.push(path);
                     }
                 }
             }
         }
-        // remove the gen/GEN if there is cases.gen
+        // the statements compiled from their .tex source
+        for tex in list_files(&self.path, vec!["statement/*.tex", "testo/*.tex"]) {
+            let pdf = tex.with_extension("pdf");
+            if pdf.symlink_metadata().is_ok_and(|m| m.is_file()) {
+                artifacts.push(pdf);
+            }
+        }
+        // the gen/GEN if there is cases.gen
         let gen_gen_path = self.path.join("gen/GEN");
         let cases_gen_path = self.path.join("gen/cases.gen");
         if cases_gen_path.exists() && gen_gen_path.exists() {
             if is_tm_deletable(&gen_gen_path)? {
-                info!("Removing {}", gen_gen_path.display());
-                std::fs::remove_file(&gen_gen_path).with_context(|| {
-                    format!("Failed to remove gen/GEN at {}", gen_gen_path.display())
-                })?;
+                artifacts.push(gen_gen_path);
             } else {
                 warn!(
                     "Won't remove gen/GEN since it doesn't contain {}",
@@ -493,15 +873,12 @@ impl IOITask {
                 );
             }
         }
-        // remove task.yaml if there is task.yaml.orig
+        // the task.yaml if there is task.yaml.orig
         let task_yaml_path = self.path.join("task.yaml");
         let task_yaml_orig_path = self.path.join("task.yaml.orig");
         if task_yaml_orig_path.exists() && task_yaml_path.exists() {
             if is_tm_deletable(&task_yaml_path)? {
-                info!("Removing {}", task_yaml_path.display());
-                std::fs::remove_file(&task_yaml_path).with_context(|| {
-                    format!("Failed to remove task.yaml at {}", task_yaml_path.display())
-                })?;
+                artifacts.push(task_yaml_path);
             } else {
                 warn!(
                     "Won't remove task.yaml since it doesn't contain {}",
@@ -509,6 +886,30 @@ impl IOITask {
                 );
             }
         }
+        Ok(artifacts)
+    }
+
+    /// Clean the task folder removing the files that can be generated automatically.
+    pub fn clean(&self) -> Result<(), Error> {
+        for artifact in self.artifacts()? {
+            remove_artifact(&artifact)?;
+        }
+        for dir in &["input", "output"] {
+            let dir = self.path.join(dir);
+            if !dir.exists() {
+                continue;
+            }
+            info!("Removing {}", dir.display());
+            if let Err(e) = std::fs::remove_dir(&dir) {
+                // FIXME: this should be `e.kind() == ErrorKind::DirectoryNotEmpty`, but it is not
+                //        stable yet.
+                if e.to_string().contains("Directory not empty") {
+                    warn!("Directory {} not empty!", dir.display());
+                } else {
+                    Err(e).with_context(|| format!("Cannot remove {}", dir.display()))?;
+                }
+            }
+        }
         Ok(())
     }
 
@@ -520,7 +921,10 @@ impl IOITask {
     }
 
     /// Find the list of all the subtasks that match the given pattern.
-    fn find_subtasks_by_pattern_name(&self, pattern: impl AsRef<str>) -> Vec<&SubtaskInfo> {
+    pub(crate) fn find_subtasks_by_pattern_name(
+        &self,
+        pattern: impl AsRef<str>,
+    ) -> Vec<&SubtaskInfo> {
         // Normalize the pattern; the subtask names are already normalized.
         let pattern = pattern.as_ref().nfkc().collect::<String>();
         let pattern = WildMatch::new(&pattern);
@@ -535,6 +939,16 @@ impl IOITask {
 }
 
 impl SubtaskInfo {
+    /// At most `sample` of the testcases owned by the subtask, evenly spaced and including the last
+    /// one, which is usually the largest.
+    pub fn sampled_testcases(&self, sample: usize) -> Vec<TestcaseId> {
+        let len = self.testcases_owned.len();
+        let sample = sample.min(len);
+        (0..sample)
+            .map(|k| self.testcases_owned[(k + 1) * len / sample - 1])
+            .collect()
+    }
+
     /// Check if the pattern matches the subtaks name.
     fn name_matches(&self, pattern: &WildMatch) -> bool {
         if let Some(name) = &self.name {
@@ -554,14 +968,25 @@ impl TestcaseInfo {
     ) -> Self {
         Self {
             id,
+            name: None,
             input_generator,
             output_generator,
             input_file: None,
+            validation_file: None,
             official_output_file: None,
         }
     }
 }
 
+/// How the UIs refer to a testcase of a subtask: `st2/big-random-tree` if the testcase is named in
+/// gen/GEN, just its id otherwise.
+pub fn testcase_label(subtask: SubtaskId, testcase: TestcaseId, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("st{}/{}", subtask, name),
+        None => testcase.to_string(),
+    }
+}
+
 impl ScoreManager {
     /// Make a new `ScoreManager` based on the subtasks and testcases of the specified task.
     pub fn new(
@@ -578,6 +1003,11 @@ impl ScoreManager {
                 .map(|st| (st.id, st.max_score))
                 .collect(),
             testcase_scores: task.testcases.keys().map(|tc| (*tc, None)).collect(),
+            testcase_names: task
+                .testcases
+                .values()
+                .filter_map(|tc| Some((tc.id, tc.name.clone()?)))
+                .collect(),
             subtask_testcases: task
                 .subtasks
                 .iter()
@@ -608,6 +1038,7 @@ impl ScoreManager {
         sender.send(UIMessage::IOITestcaseScore {
             subtask: subtask_id,
             testcase: testcase_id,
+            name: self.testcase_names.get(&testcase_id).cloned(),
             solution: self.solution.clone(),
             score,
             message,
@@ -7,14 +7,14 @@ use anyhow::{anyhow, Context, Error};
 use itertools::Itertools;
 use regex::Regex;
 
-use task_maker_dag::File;
+use task_maker_dag::{Execution, ExecutionCommand, File, FileUuid};
 use task_maker_diagnostics::Diagnostic;
 use task_maker_lang::GraderMap;
 
 use crate::ioi::sanity_checks::check_missing_graders;
 use crate::ioi::{IOITask, InputGenerator, TaskType, TestcaseId};
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
-use crate::{list_files, EvaluationData, SolutionCheck, SourceFile, UISender};
+use crate::{list_files, EvaluationData, SolutionCheck, SourceFile, Tag, UISender};
 
 use super::io::CheckEndWithNewLine;
 
@@ -197,133 +197,206 @@ impl SanityCheck for AttSampleFilesValid {
     }
 
     fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
-        let validator = &task.input_validator_generator;
-        let task_type = if let TaskType::Batch(data) = &task.task_type {
-            data
-        } else {
+        if !matches!(task.task_type, TaskType::Batch(_)) {
             return Ok(());
-        };
-        let official_solution = &task_type.output_generator;
+        }
         let samples = get_sample_files(task, eval).context("Failed to get sample files")?;
         for (input, output) in samples {
-            let input_name = task.path_of(&input).to_owned();
-            let input_handle = File::new(format!("Sample input file at {}", input_name.display()));
+            let input_name = task.path_of(&input).display().to_string();
+            let input_handle = File::new(format!("Sample input file at {}", input_name));
             let input_uuid = input_handle.uuid;
             eval.dag
                 .provide_file(input_handle, input)
                 .context("Failed to provide sample input file")?;
+            let output_name = task.path_of(&output).display().to_string();
+            let output_handle = File::new(format!("Sample output file at {}", output_name));
+            let output_uuid = output_handle.uuid;
+            eval.dag
+                .provide_file(output_handle, output)
+                .context("Failed to provide sample output file")?;
+            check_sample_case(task, eval, input_name, input_uuid, output_name, output_uuid)?;
+        }
+        Ok(())
+    }
+}
 
-            // validate the input file
-            let (val_handle, val) = validator
-                .generate(None)
-                .validate(
-                    eval,
-                    format!("Validation of sample case {}", input_name.display()),
-                    0,
-                    Some("att"),
-                    0,
-                    input_uuid,
-                )
-                .context("Failed to validate sample input file")?;
-            if let Some(mut val) = val {
-                let input_name = input_name.clone();
-                let sender = eval.sender.clone();
-                val.capture_stderr(1024);
-                eval.dag.on_execution_done(&val.uuid, move |res| {
-                    if !res.status.is_success() {
-                        let mut diagnostic = Diagnostic::error(format!(
-                            "Sample input file {} is not valid",
-                            input_name.display()
-                        ))
+/// Check a sample case of a batch task: the input file should be valid, the official solution
+/// should not crash on it and should reproduce the sample output, and the input file should be
+/// the same as the one of a testcase of the first subtask, where the sample cases are expected to
+/// be.
+pub(super) fn check_sample_case(
+    task: &IOITask,
+    eval: &mut EvaluationData,
+    input_name: String,
+    input_uuid: FileUuid,
+    output_name: String,
+    output_uuid: FileUuid,
+) -> Result<(), Error> {
+    let task_type = if let TaskType::Batch(data) = &task.task_type {
+        data
+    } else {
+        return Ok(());
+    };
+    let validator = &task.input_validator_generator;
+    let official_solution = &task_type.output_generator;
+
+    // validate the input file
+    let (val_handle, val) = validator
+        .generate(None)
+        .validate(
+            eval,
+            format!("Validation of sample case {}", input_name),
+            0,
+            Some("att"),
+            0,
+            input_uuid,
+        )
+        .context("Failed to validate sample input file")?;
+    if let Some(mut val) = val {
+        let input_name = input_name.clone();
+        let sender = eval.sender.clone();
+        val.capture_stderr(1024);
+        eval.dag.on_execution_done(&val.uuid, move |res| {
+            if !res.status.is_success() {
+                let mut diagnostic =
+                    Diagnostic::error(format!("Sample input file {} is not valid", input_name))
                         .with_note(format!("The validator failed with: {:?}", res.status));
-                        if let Some(stderr) = res.stderr {
-                            diagnostic = diagnostic
-                                .with_help("The validator stderr is:")
-                                .with_help_attachment(stderr);
-                        }
-                        sender.add_diagnostic(diagnostic)?;
-                    }
-                    Ok(())
-                });
-                eval.dag.add_execution(val);
+                if let Some(stderr) = res.stderr {
+                    diagnostic = diagnostic
+                        .with_help("The validator stderr is:")
+                        .with_help_attachment(stderr);
+                }
+                sender.add_diagnostic(diagnostic)?;
             }
+            Ok(())
+        });
+        eval.dag.add_execution(val);
+    }
 
-            if let Some(solution) = &official_solution {
-                let output_name = task.path_of(&output).to_owned();
-                let output_handle =
-                    File::new(format!("Sample output file at {}", output_name.display()));
-                let output_uuid = output_handle.uuid;
-                eval.dag
-                    .provide_file(output_handle, output)
-                    .context("Failed to provide sample output file")?;
-
-                // generate the output file
-                let (correct_output, sol) = solution
-                    .generate(
-                        task,
-                        eval,
-                        format!(
-                            "Generation of output file relative to {}",
-                            input_name.display()
-                        ),
-                        0,
-                        0,
-                        input_uuid,
-                        val_handle,
-                    )
-                    .context("Failed to generate correct sample output file")?;
-                let correct_output =
-                    correct_output.ok_or_else(|| anyhow!("Missing official solution"))?;
-                if let Some(mut sol) = sol {
-                    sol.capture_stderr(1024);
-                    let sender = eval.sender.clone();
-                    eval.dag.on_execution_done(&sol.uuid, move |res| {
-                        if !res.status.is_success() {
-                            let mut diagnostic = Diagnostic::error(format!(
-                                "Solution failed on sample input file {}",
-                                input_name.display()
-                            ))
-                            .with_note(format!("The solution failed with: {:?}", res.status));
-                            if let Some(stderr) = res.stderr {
-                                diagnostic = diagnostic
-                                    .with_help("The solution stderr is:")
-                                    .with_help_attachment(stderr);
-                            }
-                            sender.add_diagnostic(diagnostic)?;
-                        }
-                        Ok(())
-                    });
-                    eval.dag.add_execution(sol);
+    check_sample_in_testcases(task, eval, &input_name, input_uuid);
+
+    if let Some(solution) = &official_solution {
+        // generate the output file
+        let (correct_output, sols) = solution
+            .generate(
+                task,
+                eval,
+                format!("Generation of output file relative to {}", input_name),
+                0,
+                0,
+                input_uuid,
+                val_handle,
+            )
+            .context("Failed to generate correct sample output file")?;
+        let correct_output = correct_output.ok_or_else(|| anyhow!("Missing official solution"))?;
+        for mut sol in sols {
+            sol.capture_stderr(1024);
+            let input_name = input_name.clone();
+            let sender = eval.sender.clone();
+            eval.dag.on_execution_done(&sol.uuid, move |res| {
+                if !res.status.is_success() {
+                    let mut diagnostic = Diagnostic::error(format!(
+                        "Solution failed on sample input file {}",
+                        input_name
+                    ))
+                    .with_note(format!("The solution failed with: {:?}", res.status));
+                    if let Some(stderr) = res.stderr {
+                        diagnostic = diagnostic
+                            .with_help("The solution stderr is:")
+                            .with_help_attachment(stderr);
+                    }
+                    sender.add_diagnostic(diagnostic)?;
                 }
+                Ok(())
+            });
+            eval.dag.add_execution(sol);
+        }
+
+        // validate the output with the correct one
+        let sender = eval.sender.clone();
+        let chk = task_type
+            .checker
+            .check(
+                eval,
+                None,
+                format!("Checking sample output {}", output_name),
+                input_uuid,
+                correct_output,
+                output_uuid,
+                move |score, message| {
+                    if abs_diff_ne!(score, 1.0) {
+                        sender.add_diagnostic(Diagnostic::warning(format!(
+                            "Sample output file {} scores {}: {}",
+                            output_name, score, message
+                        )))?;
+                    }
+                    Ok(())
+                },
+            )
+            .context("Failed to check sample files")?;
+        eval.dag.add_execution(chk);
+    }
+    Ok(())
+}
 
-                // validate the output with the correct one
-                let sender = eval.sender.clone();
-                let chk = task_type
-                    .checker
-                    .check(
-                        eval,
-                        None,
-                        format!("Checking sample output {}", output_name.display()),
-                        input_uuid,
-                        correct_output,
-                        output_uuid,
-                        move |score, message| {
-                            if abs_diff_ne!(score, 1.0) {
-                                sender.add_diagnostic(Diagnostic::warning(format!(
-                                    "Sample output file {} scores {}: {}",
-                                    output_name.display(),
-                                    score,
-                                    message
-                                )))?;
-                            }
-                            Ok(())
-                        },
-                    )
-                    .context("Failed to check sample files")?;
-                eval.dag.add_execution(chk);
+/// Compare the sample input file with the inputs of the testcases of the first subtask, emitting a
+/// warning if none of them is equal to it.
+fn check_sample_in_testcases(
+    task: &IOITask,
+    eval: &mut EvaluationData,
+    input_name: &str,
+    input_uuid: FileUuid,
+) {
+    let Some(subtask) = task.subtasks.values().min_by_key(|st| st.id) else {
+        return;
+    };
+    let inputs = subtask
+        .testcases
+        .iter()
+        .filter_map(|tc| task.testcases.get(tc)?.input_file.map(|input| (*tc, input)))
+        .collect_vec();
+    if inputs.is_empty() {
+        return;
+    }
+    // the number of comparisons still running, and whether a testcase matched
+    let state = Arc::new(Mutex::new((inputs.len(), false)));
+    for (testcase_id, testcase_input) in inputs {
+        let mut cmp = Execution::new(
+            format!(
+                "Comparison of sample input {} with testcase {}",
+                input_name, testcase_id
+            ),
+            ExecutionCommand::system("cmp"),
+        );
+        cmp.args(vec!["sample", "testcase"])
+            .input(input_uuid, "sample", false)
+            .input(testcase_input, "testcase", false)
+            .tag(Tag::Checking.into())
+            .batchable(true);
+        let state = state.clone();
+        let sender = eval.sender.clone();
+        let input_name = input_name.to_string();
+        let subtask_id = subtask.id;
+        eval.dag.on_execution_done(&cmp.uuid, move |result| {
+            let mut state = state.lock().unwrap();
+            state.0 -= 1;
+            // cmp exits with 0 if the files are equal
+            state.1 |= result.status.is_success();
+            if state.0 == 0 && !state.1 {
+                sender.add_diagnostic(
+                    Diagnostic::warning(format!(
+                        "Sample input file {} is not a testcase of subtask {}",
+                        input_name, subtask_id
+                    ))
+                    .with_help(
+                        "The sample cases should be the testcases of the first subtask, so that \
+                        the solutions are evaluated on them",
+                    ),
+                )?;
             }
-        }
-        Ok(())
+            Ok(())
+        });
+        eval.dag.add_execution(cmp);
     }
 }
 
@@ -424,7 +497,9 @@ impl SanityCheck for AttWithNoCheck {
     fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
         for att in list_files(&task.path, vec!["att/*"]) {
             let path = task.path_of(&att);
-            if let Ok(checks) = SolutionCheck::extract_check_list(&att, eval) {
+            if let Ok(checks) =
+                SolutionCheck::extract_check_list(&att, &eval.task_root, eval.sender.as_ref())
+            {
                 if let Some(check) = checks.first() {
                     eval.add_diagnostic(
                         Diagnostic::error(format!(
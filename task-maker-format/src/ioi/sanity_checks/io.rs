@@ -1,7 +1,7 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
-use crate::ioi::IOITask;
+use crate::ioi::{IOITask, InputConstraints, InputFormatLevel, InputFormatPolicy, TestcaseId};
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
 use crate::EvaluationData;
 use anyhow::Error;
@@ -9,11 +9,9 @@ use itertools::Itertools;
 use task_maker_dag::FileUuid;
 use task_maker_diagnostics::Diagnostic;
 
-/// Check that the input and output files end with `\n`.
+/// Check that the output files end with `\n`, the input files are checked by `InputFormat`.
 #[derive(Debug, Default)]
 pub struct IOEndWithNewLine {
-    /// The list of input files that triggered the warning.
-    inputs: Arc<Mutex<Vec<String>>>,
     /// The list of output files that triggered the warning.
     outputs: Arc<Mutex<Vec<String>>>,
 }
@@ -100,14 +98,6 @@ impl SanityCheck for IOEndWithNewLine {
 
     fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
         for (&testcase_id, testcase) in &task.testcases {
-            if let Some(input_file) = testcase.input_file {
-                CheckEndWithNewLine::bind(
-                    eval,
-                    input_file,
-                    format!("input/input{}.txt", testcase_id),
-                    self.inputs.clone(),
-                );
-            }
             if let Some(output_file) = testcase.official_output_file {
                 CheckEndWithNewLine::bind(
                     eval,
@@ -121,11 +111,451 @@ impl SanityCheck for IOEndWithNewLine {
     }
 
     fn post_hook(&self, _task: &Self::Task, eval: &mut EvaluationData) -> Result<(), Error> {
-        let inputs = self.inputs.lock().unwrap();
-        CheckEndWithNewLine::emit_warning(eval, &inputs, "input")?;
         let outputs = self.outputs.lock().unwrap();
         CheckEndWithNewLine::emit_warning(eval, &outputs, "official output")?;
 
         Ok(())
     }
 }
+
+/// The rules broken by each input file, with the first line breaking them.
+type InputFormatViolations = Arc<Mutex<HashMap<TestcaseId, Vec<(InputFormatRule, usize)>>>>;
+
+/// Check that the input files follow the rules on their format set in the task.yaml.
+#[derive(Debug, Default)]
+pub struct InputFormat {
+    /// The rules broken by each input file, with the first line breaking them.
+    violations: InputFormatViolations,
+}
+make_sanity_check!(InputFormat);
+
+/// A rule on the format of the input files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputFormatRule {
+    /// The lines must not end with `\r\n`.
+    Crlf,
+    /// The lines must not end with spaces or tabs.
+    TrailingSpaces,
+    /// The file must end with `\n`.
+    FinalNewline,
+    /// The file must contain only ASCII characters.
+    Ascii,
+}
+
+impl InputFormatRule {
+    /// How the violations of this rule are reported according to the policy.
+    fn level(&self, policy: &InputFormatPolicy) -> InputFormatLevel {
+        match self {
+            InputFormatRule::Crlf => policy.crlf,
+            InputFormatRule::TrailingSpaces => policy.trailing_spaces,
+            InputFormatRule::FinalNewline => policy.final_newline,
+            InputFormatRule::Ascii => policy.ascii,
+        }
+    }
+
+    /// A description of the violation of this rule at the given line.
+    fn describe(&self, line: usize) -> String {
+        match self {
+            InputFormatRule::Crlf => format!("line {} ends with \\r\\n", line),
+            InputFormatRule::TrailingSpaces => format!("line {} ends with spaces", line),
+            InputFormatRule::FinalNewline => "the file doesn't end with a new line".to_string(),
+            InputFormatRule::Ascii => format!("line {} contains non-ASCII characters", line),
+        }
+    }
+}
+
+/// Find the rules of the format broken by a file, reading it in chunks.
+#[derive(Debug, Default)]
+struct InputFormatScanner {
+    /// The 1-based number of the current line.
+    line: usize,
+    /// The last two bytes read, the last one at the end.
+    last: [Option<u8>; 2],
+    /// Whether the file is binary, if so the format is not checked.
+    is_binary: bool,
+    /// The rules broken, with the first line breaking them.
+    violations: Vec<(InputFormatRule, usize)>,
+}
+
+impl InputFormatScanner {
+    fn new() -> Self {
+        Self {
+            line: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Record the violation of a rule, if it's the first one.
+    fn violation(&mut self, rule: InputFormatRule) {
+        if !self.violations.iter().any(|(r, _)| *r == rule) {
+            self.violations.push((rule, self.line));
+        }
+    }
+
+    /// Check the end of the current line, either at a `\n` or at the end of the file.
+    fn end_of_line(&mut self) {
+        let content_end = match self.last {
+            [before, Some(b'\r')] => {
+                self.violation(InputFormatRule::Crlf);
+                before
+            }
+            [_, last] => last,
+        };
+        if matches!(content_end, Some(b' ') | Some(b'\t')) {
+            self.violation(InputFormatRule::TrailingSpaces);
+        }
+    }
+
+    fn add_chunk(&mut self, chunk: &[u8]) {
+        self.is_binary |= chunk.contains(&0); // UTF-8 never contains NULL bytes.
+        for &byte in chunk {
+            if byte == b'\n' {
+                self.end_of_line();
+                self.line += 1;
+                self.last = [None, None];
+                continue;
+            }
+            if !byte.is_ascii() {
+                self.violation(InputFormatRule::Ascii);
+            }
+            self.last = [self.last[1], Some(byte)];
+        }
+    }
+
+    /// The rules broken by the file, after reading all of it.
+    fn finish(mut self) -> Vec<(InputFormatRule, usize)> {
+        if self.is_binary {
+            return vec![];
+        }
+        if self.last[1].is_some() {
+            self.end_of_line();
+            self.violation(InputFormatRule::FinalNewline);
+        }
+        self.violations
+    }
+}
+
+impl SanityCheck for InputFormat {
+    type Task = IOITask;
+
+    fn name(&self) -> &'static str {
+        "InputFormat"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Io
+    }
+
+    fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        if task.input_format.is_off() {
+            return Ok(());
+        }
+        for (&testcase_id, testcase) in &task.testcases {
+            let Some(input_file) = testcase.input_file else {
+                continue;
+            };
+            let mut scanner = Some(InputFormatScanner::new());
+            let violations = self.violations.clone();
+            eval.dag.get_file_content_chunked(input_file, move |chunk| {
+                if chunk.is_empty() {
+                    if let Some(scanner) = scanner.take() {
+                        let found = scanner.finish();
+                        violations.lock().unwrap().insert(testcase_id, found);
+                    }
+                } else if let Some(scanner) = scanner.as_mut() {
+                    scanner.add_chunk(chunk);
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    fn post_hook(&self, task: &Self::Task, eval: &mut EvaluationData) -> Result<(), Error> {
+        let violations = self.violations.lock().unwrap();
+        for (testcase_id, violations) in violations.iter().sorted_by_key(|(id, _)| **id) {
+            for level in [InputFormatLevel::Fail, InputFormatLevel::Warn] {
+                let broken = violations
+                    .iter()
+                    .filter(|(rule, _)| rule.level(&task.input_format) == level)
+                    .map(|(rule, line)| rule.describe(*line))
+                    .join(", ");
+                if broken.is_empty() {
+                    continue;
+                }
+                let message = format!(
+                    "input/input{}.txt doesn't follow the input format: {}",
+                    testcase_id, broken
+                );
+                let diagnostic = if level == InputFormatLevel::Fail {
+                    Diagnostic::error(message)
+                } else {
+                    Diagnostic::warning(message)
+                };
+                eval.add_diagnostic(
+                    diagnostic.with_note("The rules are set in the input_format of task.yaml"),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The values of the variables in each input file, or why they cannot be read.
+type InputValues = Arc<Mutex<HashMap<TestcaseId, Result<HashMap<String, i64>, String>>>>;
+
+/// Check that the input files satisfy the constraints declared in the task.yaml, and report the
+/// bounds of the constraints that no testcase of a subtask reaches.
+///
+/// The values of the variables are the first integers of the input files, or the ones reported by
+/// the validator with `@value` lines.
+#[derive(Debug, Default)]
+pub struct InputConstraintsSatisfied {
+    /// The values of the variables in each input file, or why they cannot be read.
+    values: InputValues,
+    /// The standard output of the validator of each input file.
+    reported: Arc<Mutex<HashMap<TestcaseId, Vec<u8>>>>,
+}
+make_sanity_check!(InputConstraintsSatisfied);
+
+/// Read the values of the variables from the start of an input file.
+#[derive(Debug)]
+struct InputValuesReader {
+    /// The testcase of the input file.
+    testcase: TestcaseId,
+    /// The start of the input file, until the values of all the variables are known.
+    prefix: Vec<u8>,
+    /// Whether `prefix` already contains the values of all the variables.
+    complete: bool,
+    /// The number of variables to read.
+    num_variables: usize,
+    /// Where to insert the values read.
+    values: InputValues,
+}
+
+impl InputValuesReader {
+    fn add_chunk(&mut self, chunk: &[u8], constraints: &InputConstraints) -> Result<(), Error> {
+        if chunk.is_empty() {
+            let prefix = String::from_utf8_lossy(&self.prefix);
+            let values = constraints.parse_values(&prefix);
+            self.values.lock().unwrap().insert(self.testcase, values);
+        } else if !self.complete {
+            self.prefix.extend_from_slice(chunk);
+            let prefix = String::from_utf8_lossy(&self.prefix);
+            // the last token may continue in the next chunk
+            let tokens = prefix.split_whitespace().count();
+            let ends_with_space = prefix.ends_with(|c: char| c.is_whitespace());
+            self.complete =
+                tokens > self.num_variables || (tokens == self.num_variables && ends_with_space);
+        }
+        Ok(())
+    }
+}
+
+impl InputConstraintsSatisfied {
+    /// Add to the values read from the input files the ones reported by the validators, which take
+    /// precedence. The input files that cannot be read are fine if the validator reports all the
+    /// variables.
+    fn merge_reported_values(
+        values: &HashMap<TestcaseId, Result<HashMap<String, i64>, String>>,
+        reported: &HashMap<TestcaseId, Vec<u8>>,
+        constraints: &InputConstraints,
+    ) -> HashMap<TestcaseId, Result<HashMap<String, i64>, String>> {
+        let mut result = values.clone();
+        for (testcase_id, stdout) in reported {
+            let reported = constraints.parse_reported_values(&String::from_utf8_lossy(stdout));
+            if reported.is_empty() {
+                continue;
+            }
+            let complete = reported.len() == constraints.variables.len();
+            match result
+                .entry(*testcase_id)
+                .or_insert_with(|| Ok(HashMap::new()))
+            {
+                Ok(values) => values.extend(reported),
+                entry @ Err(_) if complete => *entry = Ok(reported),
+                Err(_) => {}
+            }
+        }
+        result
+    }
+}
+
+impl SanityCheck for InputConstraintsSatisfied {
+    type Task = IOITask;
+
+    fn name(&self) -> &'static str {
+        "InputConstraints"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Io
+    }
+
+    fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        if task.input_constraints.variables.is_empty() {
+            return Ok(());
+        }
+        for (&testcase_id, testcase) in &task.testcases {
+            let Some(input_file) = testcase.input_file else {
+                continue;
+            };
+            let mut reader = InputValuesReader {
+                testcase: testcase_id,
+                prefix: vec![],
+                complete: false,
+                num_variables: task.input_constraints.variables.len(),
+                values: self.values.clone(),
+            };
+            let constraints = task.input_constraints.clone();
+            eval.dag.get_file_content_chunked(input_file, move |chunk| {
+                reader.add_chunk(chunk, &constraints)
+            });
+            if let Some(validation_file) = testcase.validation_file {
+                let reported = self.reported.clone();
+                eval.dag
+                    .get_file_content_chunked(validation_file, move |chunk| {
+                        let mut reported = reported.lock().unwrap();
+                        reported
+                            .entry(testcase_id)
+                            .or_default()
+                            .extend_from_slice(chunk);
+                        Ok(())
+                    });
+            }
+        }
+        Ok(())
+    }
+
+    fn post_hook(&self, task: &Self::Task, eval: &mut EvaluationData) -> Result<(), Error> {
+        let constraints = &task.input_constraints;
+        let values = Self::merge_reported_values(
+            &self.values.lock().unwrap(),
+            &self.reported.lock().unwrap(),
+            constraints,
+        );
+        let format_values = |values: &HashMap<String, i64>| {
+            constraints
+                .variables
+                .iter()
+                .filter_map(|var| Some(format!("{} = {}", var, values.get(var)?)))
+                .join(", ")
+        };
+        for (testcase_id, testcase_values) in values.iter().sorted_by_key(|(id, _)| **id) {
+            match testcase_values {
+                Err(e) => {
+                    eval.add_diagnostic(
+                        Diagnostic::error(format!(
+                            "Cannot read the values of {} from input/input{}.txt",
+                            constraints.variables.join(", "),
+                            testcase_id
+                        ))
+                        .with_note(e.clone()),
+                    )?;
+                }
+                Ok(testcase_values) => {
+                    for constraint in &constraints.global {
+                        if constraint.is_valid(testcase_values) == Some(false) {
+                            eval.add_diagnostic(
+                                Diagnostic::error(format!(
+                                    "Testcase {} violates the constraint {}",
+                                    testcase_id, constraint
+                                ))
+                                .with_note(format!(
+                                    "The input has {}",
+                                    format_values(testcase_values)
+                                )),
+                            )?;
+                        }
+                    }
+                }
+            }
+        }
+
+        for subtask in task.subtasks.values().sorted_by_key(|st| st.id) {
+            let name = subtask
+                .name
+                .as_ref()
+                .map(|name| format!(" ({})", name))
+                .unwrap_or_default();
+            let testcases: Vec<_> = subtask
+                .testcases
+                .iter()
+                .filter_map(|id| match values.get(id) {
+                    Some(Ok(testcase_values)) => Some((*id, testcase_values)),
+                    _ => None,
+                })
+                .collect();
+            for constraint in constraints.subtask_constraints(subtask.id) {
+                for (testcase_id, testcase_values) in &testcases {
+                    if constraint.is_valid(testcase_values) == Some(false) {
+                        eval.add_diagnostic(
+                            Diagnostic::error(format!(
+                                "Testcase {} violates the constraint {} of subtask {}{}",
+                                testcase_id, constraint, subtask.id, name
+                            ))
+                            .with_note(format!("The input has {}", format_values(testcase_values))),
+                        )?;
+                    }
+                }
+            }
+
+            // the subtasks without score, like the one of the examples, usually don't test the
+            // bounds
+            if testcases.is_empty() || subtask.max_score == 0.0 {
+                continue;
+            }
+            let mut reached = vec![];
+            let mut missing = vec![];
+            for (var, (lower, upper)) in constraints.bounds(subtask.id).into_iter().sorted() {
+                for bound in [lower, upper].into_iter().flatten().dedup() {
+                    let at_bound = testcases
+                        .iter()
+                        .filter(|(_, values)| values.get(var) == Some(&bound))
+                        .map(|(id, _)| id)
+                        .join(", ");
+                    if at_bound.is_empty() {
+                        missing.push(format!("{} = {}", var, bound));
+                    } else {
+                        reached.push(format!("{} = {} in {}", var, bound, at_bound));
+                    }
+                }
+            }
+            if !missing.is_empty() {
+                let mut diagnostic = Diagnostic::warning(format!(
+                    "No testcase of subtask {}{} reaches the bounds {}",
+                    subtask.id,
+                    name,
+                    missing.join(", ")
+                ));
+                if !reached.is_empty() {
+                    diagnostic = diagnostic.with_note(format!(
+                        "The testcases at the bounds are: {}",
+                        reached.join("; ")
+                    ));
+                }
+                let ranges = constraints
+                    .variables
+                    .iter()
+                    .filter_map(|var| {
+                        let (min, max) = testcases
+                            .iter()
+                            .filter_map(|(_, values)| values.get(var))
+                            .minmax()
+                            .into_option()?;
+                        Some(format!("{} in [{}, {}]", var, min, max))
+                    })
+                    .join(", ");
+                // the note already lists the testcases at the bounds
+                if !ranges.is_empty() {
+                    diagnostic = diagnostic.with_help(format!(
+                        "The values reached by the testcases are: {}",
+                        ranges
+                    ));
+                }
+                eval.add_diagnostic(diagnostic)?;
+            }
+        }
+        Ok(())
+    }
+}
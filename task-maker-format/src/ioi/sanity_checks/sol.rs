@@ -1,10 +1,11 @@
-use anyhow::{anyhow, Error};
+use anyhow::{anyhow, Context, Error};
+use task_maker_dag::{FileUuid, Priority};
 use task_maker_diagnostics::Diagnostic;
 
 use crate::ioi::sanity_checks::check_missing_graders;
-use crate::ioi::IOITask;
+use crate::ioi::{set_solution_limits, IOITask, TaskType, EVALUATION_PRIORITY};
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
-use crate::{list_files, EvaluationData};
+use crate::{bind_exec_io, list_files, EvaluationData, SourceFile, Tag, UISender};
 
 /// Check that all the graders inside sol are present.
 #[derive(Debug, Default)]
@@ -64,6 +65,122 @@ impl SanityCheck for SolTemplateSymlink {
     }
 }
 
+/// Check that the templates of all the languages with a grader compile and run with the grader,
+/// even if no solution is written in that language. The templates evaluated as solutions are
+/// skipped.
+#[derive(Debug, Default)]
+pub struct SolTemplatesShouldRun;
+make_sanity_check!(SolTemplatesShouldRun);
+
+impl SanityCheck for SolTemplatesShouldRun {
+    type Task = IOITask;
+
+    fn name(&self) -> &'static str {
+        "SolTemplatesShouldRun"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Solutions
+    }
+
+    fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        if !matches!(task.task_type, TaskType::Batch(_)) {
+            return Ok(());
+        }
+        // the templates are run on the first testcase
+        let (testcase_id, input) = match task
+            .testcases
+            .values()
+            .filter_map(|testcase| Some((testcase.id, testcase.input_file?)))
+            .min_by_key(|(id, _)| *id)
+        {
+            Some(testcase) => testcase,
+            None => return Ok(()),
+        };
+        for grader in task.grader_map.all_paths() {
+            let ext = grader
+                .extension()
+                .ok_or_else(|| anyhow!("Grader has no extension"))?
+                .to_string_lossy();
+            let template = [
+                format!("sol/template.{}", ext),
+                format!("att/{}.{}", task.name, ext),
+            ]
+            .into_iter()
+            .map(|name| task.path.join(name))
+            .find(|path| path.exists());
+            let template = match template {
+                Some(template) => template,
+                None => continue,
+            };
+            if eval
+                .solutions
+                .iter()
+                .any(|solution| solution.source_file.path == template)
+            {
+                continue;
+            }
+
+            let template_name = task.path_of(&template).to_owned();
+            let grader_name = task.path_of(grader).to_owned();
+            let source_file = match SourceFile::new(
+                &template,
+                &task.path,
+                format!(
+                    "Template {} compiled with grader {}",
+                    template_name.display(),
+                    grader_name.display()
+                ),
+                Some(task.grader_map.clone()),
+                None::<String>,
+            ) {
+                Some(source_file) => source_file,
+                None => continue,
+            };
+            let mut exec = source_file
+                .execute(
+                    eval,
+                    format!(
+                        "Execution of template {} on testcase {}",
+                        template_name.display(),
+                        testcase_id
+                    ),
+                    Vec::<String>::new(),
+                )
+                .context("Failed to execute the template")?;
+            exec.tag(Tag::Evaluation.into())
+                .priority(EVALUATION_PRIORITY - testcase_id as Priority)
+                .capture_stderr(1024);
+            bind_exec_io!(exec, task, input, None::<FileUuid>);
+            set_solution_limits(task, &template, exec.limits_mut());
+            let sender = eval.sender.clone();
+            eval.dag.on_execution_done(&exec.uuid, move |result| {
+                if !result.status.is_success() {
+                    let mut diagnostic = Diagnostic::error(format!(
+                        "Template {} failed on testcase {}",
+                        template_name.display(),
+                        testcase_id
+                    ))
+                    .with_note(format!(
+                        "Running it with {} the result was: {:?}",
+                        grader_name.display(),
+                        result.status
+                    ));
+                    if let Some(stderr) = result.stderr {
+                        diagnostic = diagnostic
+                            .with_help("The stderr is:")
+                            .with_help_attachment(stderr);
+                    }
+                    sender.add_diagnostic(diagnostic)?;
+                }
+                Ok(())
+            });
+            eval.dag.add_execution(exec);
+        }
+        Ok(())
+    }
+}
+
 /// Check that all the solutions (that are not symlinks) contain at least one check.
 #[derive(Debug, Default)]
 pub struct SolutionsWithNoChecks;
@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::io::Read;
 use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
@@ -6,11 +7,14 @@ use std::process::Command;
 use anyhow::{Context, Error};
 use itertools::Itertools;
 use regex::Regex;
+use task_maker_dag::File;
 use task_maker_diagnostics::{CodeSpan, Diagnostic};
 
-use crate::ioi::{IOITask, SubtaskId};
+use crate::ioi::{IOITask, SubtaskId, TaskType};
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
-use crate::EvaluationData;
+use crate::{list_files, EvaluationData};
+
+use super::att::check_sample_case;
 
 /// Check that the subtasks in the statement are consistent with the ones of the task.
 #[derive(Debug, Default)]
@@ -107,6 +111,147 @@ impl SanityCheck for StatementSubtasks {
     }
 }
 
+/// Check that the limits and the input/output files written in the statements are the ones of the
+/// task.
+#[derive(Debug, Default)]
+pub struct StatementMetadata;
+make_sanity_check!(StatementMetadata);
+
+impl SanityCheck for StatementMetadata {
+    type Task = IOITask;
+
+    fn name(&self) -> &'static str {
+        "StatementMetadata"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Statement
+    }
+
+    fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        let mut checked = HashSet::new();
+        for statement in task.booklets.iter().flat_map(|b| b.statements.iter()) {
+            // the same statement can be in many booklets
+            if !checked.insert(&statement.path) {
+                continue;
+            }
+            let statement_path = task.path_of(&statement.path);
+            for mention in extract_mentions(statement_path, statement.content()) {
+                let Some(note) = mention.kind.mismatch(task) else {
+                    continue;
+                };
+                let mut diagnostic = Diagnostic::warning(format!(
+                    "{} in {} doesn't match task.yaml",
+                    mention.kind.description(),
+                    statement_path.display()
+                ))
+                .with_note(note);
+                if let Some(span) = mention.span {
+                    diagnostic = diagnostic.with_code_span(span);
+                }
+                eval.add_diagnostic(diagnostic)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check the sample cases written in the statements, either as files with `\exmpfile{in}{out}` or
+/// inline with `\exmp{in}{out}`, the same way of the ones in att.
+#[derive(Debug, Default)]
+pub struct StatementSamples;
+make_sanity_check!(StatementSamples);
+
+impl SanityCheck for StatementSamples {
+    type Task = IOITask;
+
+    fn name(&self) -> &'static str {
+        "StatementSamples"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Statement
+    }
+
+    fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        if !matches!(task.task_type, TaskType::Batch(_)) {
+            return Ok(());
+        }
+        // the samples in att are already checked by AttSampleFilesValid
+        let mut checked_files: HashSet<_> = list_files(&task.path, vec!["att/*input*.txt"])
+            .into_iter()
+            .filter_map(|path| path.canonicalize().ok())
+            .collect();
+        let mut checked = HashSet::new();
+        for statement in task.booklets.iter().flat_map(|b| b.statements.iter()) {
+            // the same statement can be in many booklets
+            if !checked.insert(&statement.path) {
+                continue;
+            }
+            let statement_path = task.path_of(&statement.path);
+            let statement_dir = statement.path.parent().unwrap_or(&task.path);
+            let samples = extract_samples(statement_path, statement.content());
+            for (index, sample) in samples.into_iter().enumerate() {
+                let (input_name, input_uuid, output_name, output_uuid) = match sample.kind {
+                    SampleKind::Files(input, output) => {
+                        let input = statement_dir.join(input);
+                        let output = statement_dir.join(output);
+                        let mut missing = false;
+                        for path in [&input, &output] {
+                            if path.exists() {
+                                continue;
+                            }
+                            missing = true;
+                            let mut diagnostic = Diagnostic::error(format!(
+                                "The sample file {} in {} doesn't exist",
+                                task.path_of(path).display(),
+                                statement_path.display()
+                            ));
+                            if let Some(span) = &sample.span {
+                                diagnostic = diagnostic.with_code_span(span.clone());
+                            }
+                            eval.add_diagnostic(diagnostic)?;
+                        }
+                        if missing || !checked_files.insert(input.canonicalize()?) {
+                            continue;
+                        }
+                        let input_name = task.path_of(&input).display().to_string();
+                        let output_name = task.path_of(&output).display().to_string();
+                        let input_handle =
+                            File::new(format!("Sample input file at {}", input_name));
+                        let output_handle =
+                            File::new(format!("Sample output file at {}", output_name));
+                        let result = (
+                            input_name,
+                            input_handle.uuid,
+                            output_name,
+                            output_handle.uuid,
+                        );
+                        eval.dag
+                            .provide_file(input_handle, input)
+                            .context("Failed to provide sample input file")?;
+                        eval.dag
+                            .provide_file(output_handle, output)
+                            .context("Failed to provide sample output file")?;
+                        result
+                    }
+                    SampleKind::Inline(input, output) => {
+                        let name = format!("#{} of {}", index + 1, statement_path.display());
+                        let input_handle = File::new(format!("Sample input {}", name));
+                        let output_handle = File::new(format!("Sample output {}", name));
+                        let result = (name.clone(), input_handle.uuid, name, output_handle.uuid);
+                        eval.dag.provide_content(input_handle, input.into_bytes());
+                        eval.dag.provide_content(output_handle, output.into_bytes());
+                        result
+                    }
+                };
+                check_sample_case(task, eval, input_name, input_uuid, output_name, output_uuid)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Check that the statement file is valid.
 #[derive(Debug, Default)]
 pub struct StatementValid;
@@ -301,6 +446,198 @@ struct ExtractedSubtask {
     subtask_score_span: Option<CodeSpan>,
 }
 
+/// A value of the task configuration written in the statement file.
+enum MentionKind {
+    /// A time limit, in seconds.
+    TimeLimit(f64),
+    /// A memory limit, in MiB.
+    MemoryLimit(f64),
+    /// The name of the input file.
+    InputFile(String),
+    /// The name of the output file.
+    OutputFile(String),
+}
+
+/// A value of the task configuration found in the statement file.
+struct StatementMention {
+    /// What was found.
+    kind: MentionKind,
+    /// Span of where the value comes from.
+    span: Option<CodeSpan>,
+}
+
+impl MentionKind {
+    /// A short description of the value, for the diagnostics.
+    fn description(&self) -> &'static str {
+        match self {
+            MentionKind::TimeLimit(_) => "The time limit",
+            MentionKind::MemoryLimit(_) => "The memory limit",
+            MentionKind::InputFile(_) => "The input file",
+            MentionKind::OutputFile(_) => "The output file",
+        }
+    }
+
+    /// Compare the value with the one of the task, returning the explanation of the difference if
+    /// they don't match.
+    fn mismatch(&self, task: &IOITask) -> Option<String> {
+        let file_mismatch = |found: &str, expected: &Option<PathBuf>, stdio: &str| match expected {
+            None => Some(format!("Found {}, but the task uses {}", found, stdio)),
+            Some(expected) if expected.as_os_str() != found => {
+                Some(format!("Found {}, expecting {}", found, expected.display()))
+            }
+            Some(_) => None,
+        };
+        match self {
+            MentionKind::TimeLimit(found) => {
+                let expected = task.time_limit?;
+                approx::abs_diff_ne!(expected, *found, epsilon = 1e-6)
+                    .then(|| format!("Found {} seconds, expecting {} seconds", found, expected))
+            }
+            MentionKind::MemoryLimit(found) => {
+                let expected = task.memory_limit? as f64;
+                approx::abs_diff_ne!(expected, *found, epsilon = 1e-6)
+                    .then(|| format!("Found {} MiB, expecting {} MiB", found, expected))
+            }
+            MentionKind::InputFile(found) => file_mismatch(found, &task.infile, "stdin"),
+            MentionKind::OutputFile(found) => file_mismatch(found, &task.outfile, "stdout"),
+        }
+    }
+}
+
+/// Extract from the statement the time and memory limits and the names of the input and output
+/// files. To avoid matching unrelated quantities, the limits are searched only in the lines that
+/// talk about them, for example:
+///
+/// `Memory limit: 256 MiB` or `Limite di tempo: 1\,s`
+fn extract_mentions(path: &Path, text: &str) -> Vec<StatementMention> {
+    lazy_static! {
+        static ref TIME_LINE: Regex =
+            Regex::new(r"(?i)time limit|limite di tempo|tempo limite").expect("Invalid regex");
+        static ref MEMORY_LINE: Regex =
+            Regex::new(r"(?i)memory limit|limite di memoria|memoria").expect("Invalid regex");
+        static ref TIME: Regex = Regex::new(
+            r"(?i)(\d+(?:[.,]\d+)?)\s*(?:\\,|~|\\ )?\s*(ms|millisecon[a-z]*|secon[a-z]*|sec|s)\b"
+        )
+        .expect("Invalid regex");
+        static ref MEMORY: Regex =
+            Regex::new(r"(?i)(\d+(?:[.,]\d+)?)\s*(?:\\,|~|\\ )?\s*(KiB|KB|MiB|MB|GiB|GB)\b")
+                .expect("Invalid regex");
+        static ref FILE: Regex =
+            Regex::new(r"\b((?:[A-Za-z_]|\\_)+\.(?:txt|in|out))\b").expect("Invalid regex");
+    }
+    let span = |start: usize, end: usize| CodeSpan::from_str(path, text, start, end - start).ok();
+    let number = |s: &str| s.replace(',', ".").parse::<f64>().ok();
+    let mut result = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if TIME_LINE.is_match(line) {
+            for captures in TIME.captures_iter(line) {
+                let Some(value) = number(&captures[1]) else {
+                    continue;
+                };
+                let unit = captures[2].to_lowercase();
+                let seconds = if unit == "ms" || unit.starts_with("milli") {
+                    value / 1000.0
+                } else {
+                    value
+                };
+                let found = captures.get(0).unwrap();
+                result.push(StatementMention {
+                    kind: MentionKind::TimeLimit(seconds),
+                    span: span(offset + found.start(), offset + found.end()),
+                });
+            }
+        }
+        if MEMORY_LINE.is_match(line) {
+            for captures in MEMORY.captures_iter(line) {
+                let Some(value) = number(&captures[1]) else {
+                    continue;
+                };
+                let mib = match captures[2].to_lowercase().as_str() {
+                    "kib" | "kb" => value / 1024.0,
+                    "gib" | "gb" => value * 1024.0,
+                    _ => value,
+                };
+                let found = captures.get(0).unwrap();
+                result.push(StatementMention {
+                    kind: MentionKind::MemoryLimit(mib),
+                    span: span(offset + found.start(), offset + found.end()),
+                });
+            }
+        }
+        for found in FILE.find_iter(line) {
+            let name = found.as_str().replace("\\_", "_");
+            let lower = name.to_lowercase();
+            let kind = if lower.starts_with("input") || lower.ends_with(".in") {
+                MentionKind::InputFile(name)
+            } else if lower.starts_with("output") || lower.ends_with(".out") {
+                MentionKind::OutputFile(name)
+            } else {
+                continue;
+            };
+            result.push(StatementMention {
+                kind,
+                span: span(offset + found.start(), offset + found.end()),
+            });
+        }
+        offset += line.len();
+    }
+    result
+}
+
+/// Where the content of a sample case written in the statement is.
+enum SampleKind {
+    /// The paths of the input and output files, relative to the statement.
+    Files(PathBuf, PathBuf),
+    /// The content of the input and output files.
+    Inline(String, String),
+}
+
+/// A sample case found in the statement file.
+struct StatementSample {
+    /// The sample case.
+    kind: SampleKind,
+    /// Span of where the sample case comes from.
+    span: Option<CodeSpan>,
+}
+
+/// Extract the sample cases from the statement, in the order they appear. They are either files:
+///
+/// `\exmpfile{input0.txt}{output0.txt}`
+///
+/// or written inline, one line of the file per line of the statement:
+///
+/// `\exmp{3 4}{7}`
+fn extract_samples(path: &Path, text: &str) -> Vec<StatementSample> {
+    lazy_static! {
+        static ref SAMPLE: Regex =
+            Regex::new(r"(?s)\\exmp(file)?\{(.*?)\}\{(.*?)\}").expect("Invalid regex");
+    }
+    let inline = |content: &str| {
+        content
+            .trim()
+            .lines()
+            .map(|line| line.trim())
+            .map(|line| format!("{}\n", line))
+            .collect::<String>()
+    };
+    SAMPLE
+        .captures_iter(text)
+        .map(|captures| {
+            let found = captures.get(0).unwrap();
+            let kind = if captures.get(1).is_some() {
+                SampleKind::Files(captures[2].trim().into(), captures[3].trim().into())
+            } else {
+                SampleKind::Inline(inline(&captures[2]), inline(&captures[3]))
+            };
+            StatementSample {
+                kind,
+                span: CodeSpan::from_str(path, text, found.start(), found.len()).ok(),
+            }
+        })
+        .collect()
+}
+
 /// Extract from the OII's usual format the subtasks. They are for example:
 ///
 /// `\item \textbf{\makebox[2cm][l]{Subtask 2} [20 punti]}: $L\leq 10$.`
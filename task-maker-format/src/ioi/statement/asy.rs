@@ -51,6 +51,7 @@ impl AsyFile {
             .mount_tmpfs(true)
             .mount_proc(true);
         comp.tag(Tag::Booklet.into());
+        comp.background(true);
         comp.input(&source_file, "tm-compilation.asy", false);
         eval.dag
             .provide_file(source_file, &source_path)
@@ -127,6 +128,7 @@ impl AsyFile {
             .add_extra_readable_dir("/etc")
             .mount_tmpfs(true);
         crop.tag(Tag::Booklet.into());
+        crop.background(true);
         crop.args(vec!["source.pdf"]);
         crop.input(compiled, "source.pdf", false);
         bind_exec_callbacks!(
@@ -72,7 +72,8 @@ pub struct Booklet {
     pub dest: PathBuf,
 }
 
-/// Part of the schema of `contest.yaml`, used for extracting the configuration of the booklet.
+/// Part of the schema of `contest.yaml`, used for extracting the configuration of the booklet and
+/// the defaults shared by the tasks of the contest.
 #[derive(Debug, Deserialize)]
 pub struct ContestYAML {
     /// The description of the contest.
@@ -89,6 +90,10 @@ pub struct ContestYAML {
     pub booklet_intro_path: Option<PathBuf>,
     /// The list of the tasks in the contest (in the correct order).
     pub tasks: Vec<String>,
+    /// The values of the `task.yaml` keys, like `time_limit` and `primary_language`, used by the
+    /// tasks of the contest that don't set them.
+    #[serde(default)]
+    pub defaults: serde_yaml::Mapping,
 }
 
 impl Booklet {
@@ -131,6 +136,7 @@ impl Booklet {
             .add_extra_readable_dir("/etc")
             .mount_tmpfs(true);
         exec.tag(Tag::Booklet.into());
+        exec.background(true);
         exec.priority(BOOKLET_PRIORITY);
         let output = exec.output("booklet.pdf");
 
@@ -163,6 +163,11 @@ impl Statement {
         template.to_string()
     }
 
+    /// Return the content of the `.tex` file of the statement, without the template.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
     /// Return a list of all the `\usepackage` used by the statement.
     pub fn packages(&self) -> Vec<String> {
         let mut packages = Vec::new();
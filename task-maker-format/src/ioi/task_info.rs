@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 use anyhow::Error;
@@ -5,7 +6,7 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
-use crate::ioi::IOITask;
+use crate::ioi::{IOITask, LanguageMultiplier};
 
 /// Task information structure.
 #[derive(Debug, Clone, Serialize, Deserialize, TypeScriptify)]
@@ -33,6 +34,9 @@ pub struct TaskInfoLimits {
     pub time: Option<f64>,
     /// Memory limit in megabytes.
     pub memory: Option<u64>,
+    /// Multipliers of the limits for the solutions in some languages.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub language_multipliers: HashMap<String, LanguageMultiplier>,
 }
 
 /// Attachment of the task.
@@ -100,6 +104,7 @@ impl IOITaskInfo {
             limits: TaskInfoLimits {
                 time: task.time_limit,
                 memory: task.memory_limit,
+                language_multipliers: task.language_multipliers.clone(),
             },
             statements: task
                 .booklets
@@ -1,13 +1,16 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 use task_maker_dag::*;
 use task_maker_diagnostics::DiagnosticContext;
-use task_maker_exec::ExecutorStatus;
+use task_maker_exec::{ExecutorStatus, FileTransfer};
 
+use crate::ioi::finish_ui::YELLOW_RESOURCE_THRESHOLD;
 use crate::solution::{SolutionCheck, SolutionInfo, TestcaseEvaluationResult};
-use crate::ui::{CompilationStatus, UIExecutionStatus, UIMessage, UIStateT};
+use crate::ui::{
+    CacheStats, CompilationStatus, TimingStability, UIExecutionStatus, UIMessage, UIStateT,
+};
 use crate::{ioi::*, ScoreStatus};
 
 /// Status of the generation of a testcase input and output.
@@ -58,6 +61,8 @@ pub enum TestcaseEvaluationStatus {
     MemoryLimitExceeded,
     /// The solution crashed.
     RuntimeError,
+    /// The checker crashed or printed an invalid score, the testcase scores zero.
+    CheckerError(String),
     /// Something went wrong.
     Failed,
     /// The evaluation has been skipped.
@@ -94,6 +99,15 @@ pub struct TestcaseGenerationState {
     pub solution: Option<ExecutionResult>,
 }
 
+impl TestcaseGenerationState {
+    /// Whether all the steps of the generation done so far come from the cache.
+    pub fn was_cached(&self) -> bool {
+        let results = [&self.generation, &self.validation, &self.solution];
+        let mut results = results.iter().filter_map(|r| r.as_ref()).peekable();
+        results.peek().is_some() && results.all(|r| r.was_cached)
+    }
+}
+
 /// State of the generation of a subtask.
 #[derive(Debug, Clone)]
 pub struct SubtaskGenerationState {
@@ -115,6 +129,16 @@ pub struct SolutionTestcaseEvaluationState {
 }
 
 impl SolutionTestcaseEvaluationState {
+    /// Whether the results of all the parts of the solution come from the cache, i.e. their
+    /// timings were not measured in this run.
+    pub fn was_cached(&self) -> bool {
+        !self.results.is_empty()
+            && self
+                .results
+                .iter()
+                .all(|r| r.as_ref().is_some_and(|r| r.was_cached))
+    }
+
     /// Checks whether the resources used by a solution on a testcase are close to the limits of
     /// time or memory.
     ///
@@ -132,29 +156,39 @@ impl SolutionTestcaseEvaluationState {
         memory_limit: Option<u64>,
         threshold: f64,
     ) -> bool {
-        for result in self.results.iter().flatten() {
-            let resources = &result.resources;
-            if let Some(time_limit) = time_limit {
-                let lower_bound = time_limit * threshold;
-                let upper_bound = time_limit / threshold;
-                if lower_bound <= resources.cpu_time
-                    && resources.cpu_time <= upper_bound
-                    && resources.cpu_time <= (time_limit + extra_time).ceil() - 0.1
-                {
-                    return true;
-                }
-            }
-            if let Some(memory_limit) = memory_limit {
-                if resources.memory as f64 >= memory_limit as f64 * 1024.0 * threshold {
-                    return true;
-                }
-            }
-        }
-
-        false
+        self.results.iter().flatten().any(|result| {
+            let (time, memory) = resources_close_to_limits(
+                &result.resources,
+                time_limit,
+                extra_time,
+                memory_limit,
+                threshold,
+            );
+            time || memory
+        })
     }
 }
 
+/// Checks whether the time and the memory used by an execution are close to the limits, as
+/// described in `SolutionTestcaseEvaluationState::is_close_to_limits`.
+fn resources_close_to_limits(
+    resources: &ExecutionResourcesUsage,
+    time_limit: Option<f64>,
+    extra_time: f64,
+    memory_limit: Option<u64>,
+    threshold: f64,
+) -> (bool, bool) {
+    let time = time_limit.is_some_and(|time_limit| {
+        time_limit * threshold <= resources.cpu_time
+            && resources.cpu_time <= time_limit / threshold
+            && resources.cpu_time <= (time_limit + extra_time).ceil() - 0.1
+    });
+    let memory = memory_limit.is_some_and(|memory_limit| {
+        resources.memory as f64 >= memory_limit as f64 * 1024.0 * threshold
+    });
+    (time, memory)
+}
+
 /// State of the evaluation of a subtask.
 #[derive(Debug, Clone)]
 pub struct SolutionSubtaskEvaluationState {
@@ -219,6 +253,57 @@ pub struct BookletDependencyState {
     pub status: UIExecutionStatus,
 }
 
+/// The stderr of a failed generation or validation of a testcase.
+#[derive(Debug, Clone)]
+pub struct FailedStepStderr {
+    /// The description of the failed step, like `Generation of testcase 3` or `Generation of
+    /// testcase st2/big-random-tree` for the named testcases.
+    pub step: String,
+    /// The content of the stderr.
+    pub content: String,
+}
+
+/// The resources used by a solution on a testcase, the maximum among the parts of the testcase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestcaseResources {
+    /// The path of the solution.
+    pub solution: PathBuf,
+    /// The id of the testcase.
+    pub testcase: TestcaseId,
+    /// The resources used by the solution.
+    pub resources: ExecutionResourcesUsage,
+}
+
+/// A group of solutions in the tables of the UIs, see `IOITask::solution_groups`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolutionGroup {
+    /// The prefix of the paths of the solutions in the group, `None` for the solutions not
+    /// matching any prefix.
+    pub prefix: Option<String>,
+    /// The solutions in the group, sorted by path.
+    pub solutions: Vec<PathBuf>,
+}
+
+impl SolutionGroup {
+    /// The title of the group, with the subtotal of its solutions that got the full score.
+    pub fn title(&self, state: &UIState) -> String {
+        let full_score = self
+            .solutions
+            .iter()
+            .filter_map(|path| state.evaluations[path].score)
+            .filter(|score| {
+                ScoreStatus::from_score(*score, state.max_score) == ScoreStatus::Accepted
+            })
+            .count();
+        format!(
+            "{} ({} solutions, {} with full score)",
+            self.prefix.as_deref().unwrap_or("other"),
+            self.solutions.len(),
+            full_score
+        )
+    }
+}
+
 /// The status of the compilation of a booklet.
 #[derive(Debug, Clone)]
 pub struct BookletState {
@@ -247,10 +332,18 @@ pub struct UIState {
     pub evaluations: HashMap<PathBuf, SolutionEvaluationState>,
     /// The status of the executor.
     pub executor_status: Option<ExecutorStatus<SystemTime>>,
+    /// The upload of a file to the executor in progress, if any.
+    pub file_transfer: Option<FileTransfer>,
     /// The status of the booklets
     pub booklets: HashMap<String, BookletState>,
     /// Diagnostic context.
     pub diagnostics: DiagnosticContext,
+    /// How many executions were served from the cache.
+    pub cache_stats: CacheStats,
+    /// The executions that ran on workers with unreliable timings.
+    pub timing_stability: TimingStability,
+    /// The stderr of the failed generations and validations, the most recent last.
+    pub failed_stderr: Vec<FailedStepStderr>,
 }
 
 impl TestcaseEvaluationStatus {
@@ -308,6 +401,7 @@ impl TestcaseEvaluationStatus {
             WallTimeLimitExceeded => "Execution took too long".into(),
             MemoryLimitExceeded => "Memory limit exceeded".into(),
             RuntimeError => "Runtime error".into(),
+            CheckerError(s) => s.clone(),
             Failed => "Execution failed".into(),
             Skipped => "Execution skipped".into(),
         }
@@ -365,8 +459,12 @@ impl UIState {
             generations,
             evaluations: HashMap::new(),
             executor_status: None,
+            file_transfer: None,
             booklets: HashMap::new(),
             diagnostics: Default::default(),
+            cache_stats: Default::default(),
+            timing_stability: Default::default(),
+            failed_stderr: Vec::new(),
         }
     }
 
@@ -412,14 +510,125 @@ impl UIState {
         }
         result
     }
+
+    /// The `n` evaluations of a testcase that used the most CPU time, the slowest first.
+    pub fn slowest_evaluations(&self, n: usize) -> Vec<TestcaseResources> {
+        let mut evaluations = self.evaluation_resources();
+        evaluations.sort_by(|a, b| b.resources.cpu_time.total_cmp(&a.resources.cpu_time));
+        evaluations.truncate(n);
+        evaluations
+    }
+
+    /// The `n` evaluations of a testcase that used the most memory, the largest first.
+    pub fn largest_evaluations(&self, n: usize) -> Vec<TestcaseResources> {
+        let mut evaluations = self.evaluation_resources();
+        evaluations.sort_by_key(|e| std::cmp::Reverse(e.resources.memory));
+        evaluations.truncate(n);
+        evaluations
+    }
+
+    /// The resources used by all the completed evaluations, sorted by solution and testcase.
+    fn evaluation_resources(&self) -> Vec<TestcaseResources> {
+        let mut evaluations = vec![];
+        for (solution, eval) in self.evaluations.iter().sorted_by_key(|(path, _)| *path) {
+            for (testcase, state) in eval.testcases.iter().sorted_by_key(|(id, _)| *id) {
+                let results: Vec<_> = state.results.iter().flatten().collect();
+                if results.is_empty() {
+                    continue;
+                }
+                evaluations.push(TestcaseResources {
+                    solution: solution.clone(),
+                    testcase: *testcase,
+                    resources: ExecutionResourcesUsage {
+                        cpu_time: results
+                            .iter()
+                            .map(|r| r.resources.cpu_time)
+                            .fold(0.0, f64::max),
+                        sys_time: results
+                            .iter()
+                            .map(|r| r.resources.sys_time)
+                            .fold(0.0, f64::max),
+                        wall_time: results
+                            .iter()
+                            .map(|r| r.resources.wall_time)
+                            .fold(0.0, f64::max),
+                        memory: results
+                            .iter()
+                            .map(|r| r.resources.memory)
+                            .max()
+                            .unwrap_or(0),
+                    },
+                });
+            }
+        }
+        evaluations
+    }
+
+    /// The fraction of the limits above which the resources used are close to the limits, from
+    /// `IOITask::resource_threshold`.
+    pub fn resource_threshold(&self) -> f64 {
+        self.task
+            .resource_threshold
+            .unwrap_or(YELLOW_RESOURCE_THRESHOLD)
+    }
+
+    /// The evaluated solutions grouped by the prefixes of `IOITask::solution_groups`, in their
+    /// order, followed by the solutions not matching any of them. Each solution is in the group of
+    /// its longest matching prefix, and the empty groups are omitted.
+    pub fn solution_groups(&self) -> Vec<SolutionGroup> {
+        let prefixes = &self.task.solution_groups;
+        let mut groups: Vec<SolutionGroup> = prefixes
+            .iter()
+            .map(|prefix| SolutionGroup {
+                prefix: Some(prefix.clone()),
+                solutions: vec![],
+            })
+            .chain(std::iter::once(SolutionGroup {
+                prefix: None,
+                solutions: vec![],
+            }))
+            .collect();
+        for path in self.evaluations.keys().sorted() {
+            let relative = path.strip_prefix(&self.task.path).unwrap_or(path);
+            let relative = relative.to_string_lossy();
+            let group = prefixes
+                .iter()
+                .enumerate()
+                .filter(|(_, prefix)| relative.starts_with(prefix.as_str()))
+                .max_by_key(|(_, prefix)| prefix.len())
+                .map_or(prefixes.len(), |(index, _)| index);
+            groups[group].solutions.push(path.clone());
+        }
+        groups.retain(|group| !group.solutions.is_empty());
+        groups
+    }
 }
 
 impl UIStateT for UIState {
+    fn close_to_limits(
+        &self,
+        solution: &Path,
+        resources: &ExecutionResourcesUsage,
+    ) -> (bool, bool) {
+        resources_close_to_limits(
+            resources,
+            self.task.time_limit_for(solution),
+            self.config.extra_time,
+            self.task.memory_limit_for(solution),
+            self.resource_threshold(),
+        )
+    }
+
     /// Apply a `UIMessage` to this state.
     fn apply(&mut self, message: UIMessage) {
+        self.cache_stats.apply(&message);
+        self.timing_stability.apply(&message);
         match message {
             UIMessage::StopUI => {}
             UIMessage::ServerStatus { status } => self.executor_status = Some(status),
+            UIMessage::FileTransfer { transfer } => {
+                self.file_transfer = (transfer.sent < transfer.total).then_some(transfer)
+            }
             UIMessage::Solutions { solutions } => {
                 self.solutions = solutions
                     .into_iter()
@@ -436,6 +645,7 @@ impl UIStateT for UIState {
                 subtask,
                 testcase,
                 status,
+                ..
             } => {
                 let gen = self
                     .generations
@@ -607,6 +817,22 @@ impl UIStateT for UIState {
                     _ => {}
                 }
             }
+            UIMessage::IOICheckerError {
+                testcase,
+                solution,
+                message,
+                ..
+            } => {
+                let task = &self.task;
+                let eval = self
+                    .evaluations
+                    .entry(solution)
+                    .or_insert_with(|| SolutionEvaluationState::new(task));
+                let testcase = eval.testcases.get_mut(&testcase).expect("Missing testcase");
+                if !testcase.status.has_completed() {
+                    testcase.status = TestcaseEvaluationStatus::CheckerError(message);
+                }
+            }
             UIMessage::IOITestcaseScore {
                 testcase,
                 solution,
@@ -687,6 +913,24 @@ impl UIStateT for UIState {
                     .expect("Statement dependency step is gone")
                     .status = status;
             }
+            UIMessage::IOIGenerationStderr {
+                testcase, content, ..
+            } => self.failed_stderr.push(FailedStepStderr {
+                step: format!(
+                    "Generation of testcase {}",
+                    self.task.testcase_label(testcase)
+                ),
+                content,
+            }),
+            UIMessage::IOIValidationStderr {
+                testcase, content, ..
+            } => self.failed_stderr.push(FailedStepStderr {
+                step: format!(
+                    "Validation of testcase {}",
+                    self.task.testcase_label(testcase)
+                ),
+                content,
+            }),
             UIMessage::Diagnostic { diagnostic } => {
                 self.diagnostics.add_diagnostic(diagnostic);
             }
@@ -695,7 +939,12 @@ impl UIStateT for UIState {
             | UIMessage::TerryValidation { .. }
             | UIMessage::TerrySolution { .. }
             | UIMessage::TerryChecker { .. }
-            | UIMessage::TerrySolutionOutcome { .. } => unreachable!("Terry message on IOI UI"),
+            | UIMessage::TerrySolutionOutcome { .. }
+            | UIMessage::TerrySeedOutcome { .. }
+            | UIMessage::TerryStatement { .. } => unreachable!("Terry message on IOI UI"),
+            UIMessage::FuzzBatch { .. }
+            | UIMessage::FuzzMinimization { .. }
+            | UIMessage::FuzzFailure { .. } => {}
         }
     }
 
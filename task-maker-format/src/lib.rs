@@ -24,8 +24,9 @@ use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-use anyhow::Error;
+use anyhow::{Context, Error};
 use itertools::Itertools;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
@@ -35,7 +36,7 @@ pub use sanity_checks::SanityCheckCategory;
 pub use source_file::SourceFile;
 pub use tag::{Tag, VALID_TAGS};
 pub use task_format::*;
-use task_maker_dag::ExecutionDAG;
+use task_maker_dag::{ExecutionDAG, MemoryMetric};
 use task_maker_diagnostics::Diagnostic;
 use task_maker_lang::{GraderMap, LanguageManager};
 
@@ -48,6 +49,8 @@ pub use testcase_score_status::ScoreStatus;
 
 mod detect_format;
 pub mod ioi;
+pub mod lint;
+pub mod results;
 mod sanity_checks;
 mod solution;
 mod source_file;
@@ -94,12 +97,36 @@ pub struct EvaluationConfig {
     /// Execute only the solution with the specified paths, that can reside anywhere in the
     /// filesystem.
     pub solution_paths: Vec<PathBuf>,
+    /// Evaluate also the solutions with the specified paths, that can reside anywhere in the
+    /// filesystem, together with the ones of the task. The filter is not applied to them.
+    pub extra_solution_paths: Vec<PathBuf>,
     /// List of disabled sanity check names.
     pub disabled_sanity_checks: Vec<String>,
     /// Force this seed in terry evaluations.
     pub seed: Option<Seed>,
+    /// Ask the generator of terry evaluations for this number of test cases, instead of the one it
+    /// chooses.
+    pub terry_cases: Option<usize>,
+    /// Evaluate each solution of terry tasks on this many input files, generated with different
+    /// seeds, instead of only one.
+    pub terry_seeds: Option<usize>,
     /// Do not write any file inside the task directory.
     pub dry_run: bool,
+    /// Evaluate only the subtasks with these ids. If left empty all the subtasks are evaluated.
+    pub subtask_filter: Vec<ioi::SubtaskId>,
+    /// Evaluate only the testcases with these ids. If left empty all the testcases are evaluated.
+    pub testcase_filter: Vec<ioi::TestcaseId>,
+    /// Evaluate the solutions only on at most this many testcases of each subtask, while all the
+    /// testcases are still generated and validated.
+    pub sample_testcases: Option<usize>,
+    /// The measure of the memory of the solutions checked against the memory limit, overriding the
+    /// one of the task.
+    pub memory_metric: Option<MemoryMetric>,
+    /// Run each generator twice with the same arguments, failing if the two input files differ.
+    pub verify_generators: bool,
+    /// Check the official outputs twice with the checker, failing if it doesn't give them full score
+    /// or if the two scores differ.
+    pub check_checker: bool,
 }
 
 /// The data for an evaluation, including the DAG and the UI channel.
@@ -112,6 +139,18 @@ pub struct EvaluationData {
     pub solutions: Vec<Solution>,
     /// The sender of the UI.
     pub sender: Arc<Mutex<ui::UIMessageSender>>,
+    /// Abort the evaluation when a checker violates its protocol, instead of scoring zero on the
+    /// testcase.
+    pub strict: bool,
+    /// Evaluate each solution twice on each testcase, warning when the verdicts or the outputs of
+    /// the two evaluations differ.
+    pub detect_flaky: bool,
+    /// Add the evaluations of the compiled solutions to the DAG only after their compilation,
+    /// while the DAG is being evaluated, instead of building the whole DAG in advance.
+    pub incremental: bool,
+    /// Compare the outputs with the white diff checker by spawning `diff`, instead of using the
+    /// builtin comparison of the workers.
+    pub external_diff: bool,
 }
 
 impl EvaluationData {
@@ -124,6 +163,10 @@ impl EvaluationData {
                 dag: ExecutionDAG::new(),
                 solutions: Default::default(),
                 sender: Arc::new(Mutex::new(sender)),
+                strict: false,
+                detect_flaky: false,
+                incremental: false,
+                external_diff: false,
             },
             receiver,
         )
@@ -133,6 +176,28 @@ impl EvaluationData {
     pub fn add_diagnostic(&self, diagnostic: Diagnostic) -> Result<(), Error> {
         self.sender.add_diagnostic(diagnostic)
     }
+
+    /// Make an `EvaluationData` with the same UI channel and options of this one, but with an empty
+    /// extension of its DAG. This allows building independent parts of the DAG on different threads,
+    /// merging them back with [`merge_extension`](#method.merge_extension).
+    pub fn extension(&self) -> EvaluationData {
+        EvaluationData {
+            task_root: self.task_root.clone(),
+            dag: self.dag.extension(),
+            solutions: vec![],
+            sender: self.sender.clone(),
+            strict: self.strict,
+            detect_flaky: self.detect_flaky,
+            incremental: self.incremental,
+            external_diff: self.external_diff,
+        }
+    }
+
+    /// Add to the DAG the executions, the files and the callbacks of an extension made with
+    /// [`extension`](#method.extension).
+    pub fn merge_extension(&mut self, extension: EvaluationData) {
+        self.dag.merge_extension(extension.dag);
+    }
 }
 
 /// What can send [`UIMessage`](ui/enum.UIMessage.html)s.
@@ -202,8 +267,8 @@ impl EvaluationConfig {
         } else {
             HashSet::new()
         };
-        solutions_paths
-            .into_iter()
+        let mut solutions_paths: Vec<_> = solutions_paths
+            .into_par_iter()
             .filter(|p| !graders.contains(p)) // the graders are not solutions
             .filter(|p| p.exists())
             .filter(|p| {
@@ -215,25 +280,70 @@ impl EvaluationConfig {
                     .iter()
                     .any(|filter| name.starts_with(filter.as_str()))
             })
-            .filter_map(|path| Solution::new(&path, base_dir, grader_map.clone(), eval))
+            .collect();
+        for path in &self.extra_solution_paths {
+            if !path.is_file() {
+                let _ = eval.add_diagnostic(Diagnostic::warning(format!(
+                    "The solution {} does not exist",
+                    path.display()
+                )));
+                continue;
+            }
+            let already_found = solutions_paths.iter().any(|p| same_file(p, path));
+            if !already_found {
+                solutions_paths.push(path.clone());
+            }
+        }
+        let task_root = &eval.task_root;
+        let sender = eval.sender.as_ref();
+        solutions_paths
+            .into_par_iter()
+            .filter_map(|path| {
+                Solution::new(&path, base_dir, grader_map.clone(), task_root, sender)
+            })
             .collect()
     }
 }
 
+/// Whether the two paths point to the same file.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Remove a file or a directory generated by task-maker inside the task folder.
+pub(crate) fn remove_artifact(path: &Path) -> Result<(), Error> {
+    info!("Removing {}", path.display());
+    if path.is_dir() && !path.is_symlink() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+    .with_context(|| format!("Failed to remove {}", path.display()))
+}
+
 /// List all the files inside `cwd` that matches a list of glob patterns. The results are in the
 /// same order of the patterns.
-pub(crate) fn list_files<P: AsRef<Path>, S: AsRef<str>>(cwd: P, patterns: Vec<S>) -> Vec<PathBuf> {
-    let mut results = Vec::new();
-    for pattern in patterns.into_iter() {
-        let pattern = cwd.as_ref().join(pattern.as_ref());
-        for path in glob::glob(&pattern.to_string_lossy())
-            .expect("Invalid pattern for list_files")
-            .flatten()
-        {
-            results.push(path);
-        }
-    }
-    results
+///
+/// The patterns are expanded in parallel, since on network filesystems walking the directories is
+/// slow.
+pub(crate) fn list_files<P: AsRef<Path>, S: AsRef<str> + Send>(
+    cwd: P,
+    patterns: Vec<S>,
+) -> Vec<PathBuf> {
+    let cwd = cwd.as_ref();
+    patterns
+        .into_par_iter()
+        .flat_map_iter(|pattern| {
+            let pattern = cwd.join(pattern.as_ref());
+            glob::glob(&pattern.to_string_lossy())
+                .expect("Invalid pattern for list_files")
+                .flatten()
+                .collect_vec()
+        })
+        .collect()
 }
 
 /// Information about where to write the binary of the `SourceFile` found by `find_source_file`.
@@ -254,14 +364,14 @@ impl WriteBinTo {
 }
 
 /// Make a `SourceFile` with each file that match the patterns provided, that is in a recognised
-/// language.
+/// language. The languages of the files are detected in parallel.
 ///
 /// The file name is appended to `description_prefix` and used as description for the source file.
 pub(crate) fn find_source_file<
     CwdPath: AsRef<Path>,
-    Pattern: AsRef<str>,
+    Pattern: AsRef<str> + Send,
     BasePath: Into<PathBuf>,
-    S: AsRef<str>,
+    S: AsRef<str> + Sync,
 >(
     cwd: CwdPath,
     patterns: Vec<Pattern>,
@@ -270,10 +380,11 @@ pub(crate) fn find_source_file<
     grader_map: Option<Arc<GraderMap>>,
     write_bin_to: WriteBinTo,
 ) -> Vec<SourceFile> {
-    let mut result = vec![];
     let base_path = base_path.into();
-    for path in list_files(cwd, patterns) {
-        if path.exists() && LanguageManager::detect_language(&path).is_some() {
+    list_files(cwd, patterns)
+        .into_par_iter()
+        .filter(|path| path.exists() && LanguageManager::detect_language(path).is_some())
+        .map(|path| {
             let write_bin_to = match &write_bin_to {
                 WriteBinTo::None => None,
                 WriteBinTo::WithoutExtension => Some(path.with_extension("")),
@@ -281,19 +392,16 @@ pub(crate) fn find_source_file<
             };
             let name = path.strip_prefix(&base_path).unwrap_or(&path);
             // SourceFile::new may fail if the language is unknown
-            result.push(
-                SourceFile::new(
-                    &path,
-                    &base_path,
-                    format!("{} {}", description_prefix.as_ref(), name.display()),
-                    grader_map.clone(),
-                    write_bin_to,
-                )
-                .unwrap(),
-            );
-        }
-    }
-    result
+            SourceFile::new(
+                &path,
+                &base_path,
+                format!("{} {}", description_prefix.as_ref(), name.display()),
+                grader_map.clone(),
+                write_bin_to,
+            )
+            .unwrap()
+        })
+        .collect()
 }
 
 /// Bind the start/done/skip callbacks of an execution to a ui message sender which sends to the UI
@@ -381,6 +489,49 @@ mod tests {
         assert!(files.contains(&tmpdir.path().join("foo/baz/uuu.bbb")));
     }
 
+    #[test]
+    fn test_list_files_order() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let names = ["e.txt", "d.txt", "c.txt", "b.txt", "a.txt"];
+        for name in names {
+            std::fs::write(tmpdir.path().join(name), "x").unwrap();
+        }
+        let files = list_files(tmpdir.path(), names.to_vec());
+        let expected: Vec<_> = names.iter().map(|name| tmpdir.path().join(name)).collect();
+        assert_eq!(files, expected);
+    }
+
+    #[test]
+    fn test_find_solutions_extra() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let task = tmpdir.path().join("task");
+        std::fs::create_dir_all(task.join("sol")).unwrap();
+        std::fs::write(task.join("sol/sol.py"), "x").unwrap();
+        std::fs::write(task.join("sol/wrong.py"), "x").unwrap();
+        std::fs::write(tmpdir.path().join("submission.py"), "x").unwrap();
+        let config = EvaluationConfig {
+            solution_filter: vec!["wrong".into()],
+            extra_solution_paths: vec![
+                tmpdir.path().join("submission.py"),
+                task.join("sol/wrong.py"),
+            ],
+            ..Default::default()
+        };
+        let (mut eval, _receiver) = EvaluationData::new(&task);
+        let solutions = config.find_solutions(&task, vec!["sol/*"], None, &mut eval);
+        let paths: Vec<_> = solutions
+            .iter()
+            .map(|solution| solution.source_file.path.clone())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                task.join("sol/wrong.py"),
+                tmpdir.path().join("submission.py")
+            ]
+        );
+    }
+
     #[test]
     fn test_find_source_file() {
         let tmpdir = tempfile::TempDir::new().unwrap();
@@ -0,0 +1,507 @@
+//! Static checks of the structure of a task, which don't compile nor run anything.
+//!
+//! Unlike the sanity checks, the lints don't need a valid task: each problem is reported as a
+//! [`Diagnostic`] with its [`LintCode`], even when the task cannot be parsed.
+
+use std::collections::HashSet;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use task_maker_diagnostics::{CodeSpan, Diagnostic};
+use task_maker_lang::LanguageManager;
+
+use crate::ioi::IOITask;
+use crate::terry::TerryTask;
+use crate::{list_files, EvaluationConfig};
+
+/// The default maximum score of a IOI task.
+const DEFAULT_TASK_MAX_SCORE: f64 = 100.0;
+
+/// The kinds of problems found by the lints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintCode {
+    /// The task cannot be parsed.
+    ParseError,
+    /// The input files are not validated.
+    MissingValidator,
+    /// The sum of the scores of the subtasks is not the maximum score of the task.
+    SubtaskScoreSum,
+    /// A solution is not written in a known language.
+    UnknownLanguage,
+    /// The testcases reference a generator that doesn't exist.
+    MissingGenerator,
+    /// A file among the solutions is neither a solution, nor a grader, nor used by them.
+    OrphanFile,
+}
+
+impl LintCode {
+    /// All the lint codes.
+    pub const ALL: [LintCode; 6] = [
+        LintCode::ParseError,
+        LintCode::MissingValidator,
+        LintCode::SubtaskScoreSum,
+        LintCode::UnknownLanguage,
+        LintCode::MissingGenerator,
+        LintCode::OrphanFile,
+    ];
+
+    /// The code of the diagnostics of this lint.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LintCode::ParseError => "parse-error",
+            LintCode::MissingValidator => "missing-validator",
+            LintCode::SubtaskScoreSum => "subtask-score-sum",
+            LintCode::UnknownLanguage => "unknown-language",
+            LintCode::MissingGenerator => "missing-generator",
+            LintCode::OrphanFile => "orphan-file",
+        }
+    }
+
+    /// What this lint checks.
+    pub fn description(&self) -> &'static str {
+        match self {
+            LintCode::ParseError => "the task can be parsed",
+            LintCode::MissingValidator => "the input files are validated",
+            LintCode::SubtaskScoreSum => "the scores of the subtasks sum to the score of the task",
+            LintCode::UnknownLanguage => "the solutions are written in a known language",
+            LintCode::MissingGenerator => "the generators used by the testcases exist",
+            LintCode::OrphanFile => "the files among the solutions are all used",
+        }
+    }
+}
+
+/// Make a warning with the code of the lint.
+fn warning(code: LintCode, message: impl Into<String>) -> Diagnostic {
+    Diagnostic::warning(message).with_code(code.as_str())
+}
+
+/// Make an error with the code of the lint.
+fn error(code: LintCode, message: impl Into<String>) -> Diagnostic {
+    Diagnostic::error(message).with_code(code.as_str())
+}
+
+/// Run all the lints on the task at the specified path, returning the problems found.
+pub fn lint_task(task_dir: &Path) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    if task_dir.join("managers").is_dir() {
+        lint_terry(task_dir, &mut diagnostics);
+    } else {
+        lint_ioi(task_dir, &mut diagnostics);
+    }
+    diagnostics
+}
+
+/// The configuration for parsing a task only for linting it.
+fn lint_eval_config() -> EvaluationConfig {
+    EvaluationConfig {
+        no_statement: true,
+        ..Default::default()
+    }
+}
+
+/// Report an error of the parsing of the task, with all its causes.
+fn parse_error(error: anyhow::Error) -> Diagnostic {
+    let causes: Vec<_> = error.chain().skip(1).map(|e| e.to_string()).collect();
+    let diagnostic = self::error(
+        LintCode::ParseError,
+        format!("The task cannot be parsed: {}", error),
+    );
+    if causes.is_empty() {
+        diagnostic
+    } else {
+        diagnostic.with_note(format!("Caused by: {}", causes.join("\nCaused by: ")))
+    }
+}
+
+/// Run the lints of a IOI task.
+fn lint_ioi(task_dir: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    let cases_gen = task_dir.join("gen/cases.gen");
+    let gen = task_dir.join("gen/GEN");
+    if cases_gen.exists() {
+        lint_cases_gen(task_dir, &cases_gen, diagnostics);
+    } else {
+        let has_validator = !list_files(
+            task_dir,
+            vec![
+                "gen/validator.*",
+                "gen/valida.*",
+                "gen/validator",
+                "gen/valida",
+            ],
+        )
+        .is_empty();
+        if !has_validator {
+            diagnostics.push(
+                warning(LintCode::MissingValidator, "The task has no validator")
+                    .with_help("Add gen/validator.xxx to check that the input files are valid"),
+            );
+        }
+        if gen.exists() {
+            lint_gen(task_dir, &gen, diagnostics);
+        }
+    }
+
+    match IOITask::new(task_dir, &lint_eval_config()) {
+        Ok(task) => {
+            let task_score: f64 = task.subtasks.values().map(|st| st.max_score).sum();
+            if approx::abs_diff_ne!(task_score, DEFAULT_TASK_MAX_SCORE) {
+                let mut subtasks: Vec<_> = task.subtasks.values().collect();
+                subtasks.sort_by_key(|st| st.id);
+                let scores: Vec<_> = subtasks.iter().map(|st| st.max_score.to_string()).collect();
+                diagnostics.push(
+                    error(
+                        LintCode::SubtaskScoreSum,
+                        format!(
+                            "The scores of the subtasks sum to {} (not {})",
+                            task_score, DEFAULT_TASK_MAX_SCORE
+                        ),
+                    )
+                    .with_note(format!("The scores are: {}", scores.join(" + "))),
+                );
+            }
+        }
+        Err(e) => diagnostics.push(parse_error(e)),
+    }
+
+    let graders: HashSet<_> = list_files(task_dir, vec!["sol/grader.*", "sol/stub.*"])
+        .into_iter()
+        .collect();
+    lint_solutions(task_dir, &task_dir.join("sol"), &graders, diagnostics);
+}
+
+/// Check that the testcases of `gen/GEN` have a generator.
+fn lint_gen(task_dir: &Path, gen: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    let content = match std::fs::read_to_string(gen) {
+        Ok(content) => content,
+        Err(_) => return,
+    };
+    let generators = list_files(
+        task_dir,
+        vec![
+            "gen/generator.*",
+            "gen/generatore.*",
+            "gen/generator",
+            "gen/generatore",
+        ],
+    );
+    if !generators.is_empty() {
+        return;
+    }
+    let mut offset = 0;
+    for line in content.split('\n') {
+        let command = line.trim();
+        if !command.is_empty() && !command.starts_with('#') {
+            let mut diagnostic = error(
+                LintCode::MissingGenerator,
+                "gen/GEN has testcases to generate, but there is no generator",
+            )
+            .with_help("Add gen/generator.xxx, or copy the input files with #COPY");
+            if let Ok(span) = CodeSpan::from_str(gen, &content, offset, line.len()) {
+                diagnostic = diagnostic.with_code_span(span);
+            }
+            diagnostics.push(diagnostic);
+            return;
+        }
+        offset += line.len() + 1;
+    }
+}
+
+/// Check the generators and the validators used by `gen/cases.gen`.
+fn lint_cases_gen(task_dir: &Path, cases_gen: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    let content = match std::fs::read_to_string(cases_gen) {
+        Ok(content) => content,
+        Err(_) => return,
+    };
+    let mut generators = HashSet::new();
+    let mut has_validator = false;
+    let mut missing_generator = false;
+    let mut offset = 0;
+    for line in content.split('\n') {
+        let line_offset = offset;
+        offset += line.len() + 1;
+        let span = || CodeSpan::from_str(cases_gen, &content, line_offset, line.len()).ok();
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let Some(command) = trimmed.strip_prefix(':') else {
+            // a testcase generated with the current generator
+            if generators.is_empty() && !missing_generator {
+                missing_generator = true;
+                let mut diagnostic = error(
+                    LintCode::MissingGenerator,
+                    "A testcase is generated, but no generator is defined",
+                )
+                .with_help("Define a generator with : GEN name path");
+                if let Some(span) = span() {
+                    diagnostic = diagnostic.with_code_span(span);
+                }
+                diagnostics.push(diagnostic);
+            }
+            continue;
+        };
+        let words: Vec<_> = command.split_whitespace().collect();
+        let (generator, defines) = match words.as_slice() {
+            ["GEN", name, path, ..] | ["VAL", name, path, ..] => {
+                let is_gen = words[0] == "GEN";
+                if is_gen {
+                    generators.insert(name.to_string());
+                } else {
+                    has_validator = true;
+                }
+                if !task_dir.join(path).exists() {
+                    let (code, kind) = if is_gen {
+                        (LintCode::MissingGenerator, "generator")
+                    } else {
+                        (LintCode::MissingValidator, "validator")
+                    };
+                    let mut diagnostic = error(
+                        code,
+                        format!("The {} '{}' does not exist: {}", kind, name, path),
+                    );
+                    if let Some(span) = span() {
+                        diagnostic = diagnostic.with_code_span(span);
+                    }
+                    diagnostics.push(diagnostic);
+                }
+                continue;
+            }
+            ["GEN", name] => (name, false),
+            ["RUN", name, ..] => (name, true),
+            _ => continue,
+        };
+        if !generators.contains(*generator) {
+            let message = if defines {
+                format!(
+                    "A testcase uses the generator '{}', never defined",
+                    generator
+                )
+            } else {
+                format!(
+                    "The generator '{}' is selected, but never defined",
+                    generator
+                )
+            };
+            let mut diagnostic = error(LintCode::MissingGenerator, message);
+            if let Some(span) = span() {
+                diagnostic = diagnostic.with_code_span(span);
+            }
+            diagnostics.push(diagnostic);
+        }
+    }
+    if !has_validator {
+        diagnostics.push(
+            warning(LintCode::MissingValidator, "The task has no validator")
+                .with_help("Define a validator in gen/cases.gen with : VAL name path"),
+        );
+    }
+}
+
+/// Run the lints of a Terry task.
+fn lint_terry(task_dir: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    if list_files(task_dir, vec!["managers/validator.*"]).is_empty() {
+        diagnostics.push(
+            warning(LintCode::MissingValidator, "The task has no validator")
+                .with_help("Add managers/validator.xxx to check that the input files are valid"),
+        );
+    }
+    if list_files(task_dir, vec!["managers/generator.*"]).is_empty() {
+        diagnostics.push(error(
+            LintCode::MissingGenerator,
+            "The task has no generator in managers/",
+        ));
+    }
+    if let Err(e) = TerryTask::new(task_dir, &lint_eval_config()) {
+        diagnostics.push(parse_error(e));
+    }
+    lint_solutions(
+        task_dir,
+        &task_dir.join("solutions"),
+        &HashSet::new(),
+        diagnostics,
+    );
+}
+
+/// Check that the files in the directory of the solutions are all solutions in a known language,
+/// graders, or files used by them (e.g. headers).
+fn lint_solutions(
+    task_dir: &Path,
+    dir: &Path,
+    graders: &HashSet<PathBuf>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut files: Vec<_> = list_files(dir, vec!["*"])
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    let contents: Vec<_> = files
+        .iter()
+        .filter(|path| LanguageManager::detect_language(path).is_some())
+        .filter_map(|path| std::fs::read(path).ok())
+        .collect();
+    let path_of = |path: &Path| path.strip_prefix(task_dir).unwrap_or(path).to_owned();
+    for path in &files {
+        if graders.contains(path) || LanguageManager::detect_language(path).is_some() {
+            continue;
+        }
+        let Some(name) = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+        else {
+            continue;
+        };
+        let used = contents.iter().any(|content| {
+            content
+                .windows(name.len())
+                .any(|window| window == name.as_bytes())
+        });
+        if used {
+            continue;
+        }
+        let executable = path
+            .metadata()
+            .map(|meta| meta.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false);
+        let shebang = std::fs::read(path)
+            .map(|content| content.starts_with(b"#!"))
+            .unwrap_or(false);
+        if executable || shebang {
+            diagnostics.push(
+                warning(
+                    LintCode::UnknownLanguage,
+                    format!(
+                        "The language of {} is unknown, it won't be evaluated",
+                        path_of(path).display()
+                    ),
+                )
+                .with_help("Use the extension of the language of the solution"),
+            );
+        } else {
+            diagnostics.push(
+                warning(
+                    LintCode::OrphanFile,
+                    format!(
+                        "{} is not a solution, a grader, nor a file used by them",
+                        path_of(path).display()
+                    ),
+                )
+                .with_help("Remove it, or use the extension of its language if it's a solution"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    /// Write a file inside the task directory, creating its parent directories.
+    fn write(task_dir: &Path, path: &str, content: &str) {
+        let path = task_dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// The codes of the diagnostics, sorted.
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<&str> {
+        let mut codes: Vec<_> = diagnostics.iter().filter_map(|d| d.code()).collect();
+        codes.sort_unstable();
+        codes
+    }
+
+    /// A valid IOI task, without any problem.
+    fn ioi_task() -> tempfile::TempDir {
+        let dir = tempfile::TempDir::new().unwrap();
+        let task_dir = dir.path();
+        write(
+            task_dir,
+            "task.yaml",
+            "name: task\ntitle: Task\ntime_limit: 1\nmemory_limit: 64\ninfile: ''\noutfile: ''\n",
+        );
+        write(task_dir, "gen/GEN", "#ST: 40\n1\n#ST: 60\n2\n");
+        write(task_dir, "gen/generator.py", "print(1)");
+        write(task_dir, "gen/validator.py", "");
+        write(task_dir, "sol/solution.py", "print(1)");
+        write(task_dir, "sol/grader.cpp", "#include \"task.h\"");
+        write(task_dir, "sol/task.h", "");
+        dir
+    }
+
+    #[test]
+    fn test_lint_ioi_clean() {
+        let dir = ioi_task();
+        let diagnostics = lint_task(dir.path());
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn test_lint_ioi_problems() {
+        let dir = ioi_task();
+        let task_dir = dir.path();
+        write(task_dir, "gen/GEN", "#ST: 40\n1\n#ST: 50\n2\n");
+        fs::remove_file(task_dir.join("gen/validator.py")).unwrap();
+        write(task_dir, "sol/notes.txt", "");
+        write(task_dir, "sol/solution.foo", "#!/usr/bin/foo\n");
+        let diagnostics = lint_task(task_dir);
+        assert_eq!(
+            codes(&diagnostics),
+            vec![
+                "missing-validator",
+                "orphan-file",
+                "subtask-score-sum",
+                "unknown-language"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_ioi_missing_generator() {
+        let dir = ioi_task();
+        let task_dir = dir.path();
+        fs::remove_file(task_dir.join("gen/generator.py")).unwrap();
+        let diagnostics = lint_task(task_dir);
+        assert_eq!(
+            codes(&diagnostics),
+            vec!["missing-generator", "parse-error"]
+        );
+    }
+
+    #[test]
+    fn test_lint_cases_gen() {
+        let dir = ioi_task();
+        let task_dir = dir.path();
+        write(
+            task_dir,
+            "gen/cases.gen",
+            ": GEN default gen/generator.py\n: GEN other gen/nope.py\n: SUBTASK 100\n: GEN lol\n: RUN foo 1 2\n1 2\n",
+        );
+        let diagnostics = lint_task(task_dir);
+        let missing: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.code() == Some(LintCode::MissingGenerator.as_str()))
+            .collect();
+        assert_eq!(missing.len(), 3);
+        assert!(diagnostics
+            .iter()
+            .any(|d| d.code() == Some(LintCode::MissingValidator.as_str())));
+    }
+
+    #[test]
+    fn test_lint_terry() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let task_dir = dir.path();
+        write(
+            task_dir,
+            "task.yaml",
+            "name: task\ndescription: Task\nmax_score: 100\n",
+        );
+        write(task_dir, "managers/generator.py", "");
+        write(task_dir, "managers/checker.py", "");
+        write(task_dir, "solutions/solution.py", "");
+        let diagnostics = lint_task(task_dir);
+        assert_eq!(codes(&diagnostics), vec!["missing-validator"]);
+    }
+}
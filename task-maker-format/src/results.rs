@@ -0,0 +1,1222 @@
+//! A machine-readable summary of the results of an evaluation, and the requirements on it that
+//! decide whether the evaluation succeeded (e.g. for failing a CI pipeline).
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, Error};
+use serde::{Deserialize, Serialize};
+
+use task_maker_dag::{ExecutionResourcesUsage, ExecutionStatus};
+use task_maker_diagnostics::{DiagnosticContext, DiagnosticLevel};
+
+use crate::ioi::{SubtaskId, TestcaseEvaluationStatus, TestcaseId};
+use crate::solution::TestcaseEvaluationResult;
+use crate::ui::{CompilationStatus, UIExecutionStatus, UIMessage, UIStateT};
+use crate::{ioi, terry, ScoreStatus, SolutionInfo, TaskFormat};
+
+/// The results of an evaluation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluationResults {
+    /// The name of the task.
+    pub task: String,
+    /// The maximum score of a solution.
+    pub max_score: f64,
+    /// The results of the solutions, sorted by path.
+    pub solutions: Vec<SolutionResults>,
+    /// The outcomes of the `@check` comments of the solutions.
+    pub checks: Vec<SolutionCheckResults>,
+    /// The error messages emitted during the evaluation.
+    pub errors: Vec<String>,
+    /// The warning messages emitted during the evaluation.
+    pub warnings: Vec<String>,
+}
+
+/// The results of a solution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolutionResults {
+    /// The name of the file of the solution.
+    pub name: String,
+    /// The path of the solution, relative to the task directory.
+    pub path: PathBuf,
+    /// Whether the solution compiled successfully.
+    pub compiled: bool,
+    /// The score of the solution, if it has been evaluated completely.
+    pub score: Option<f64>,
+    /// The worst verdict and the largest resources of the solution on all the testcases.
+    #[serde(flatten)]
+    pub summary: TestcasesSummary,
+    /// The results of the subtasks, sorted by id. Empty if the task has no subtasks.
+    pub subtasks: Vec<SubtaskResults>,
+}
+
+/// The results of a solution on a subtask.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubtaskResults {
+    /// The id of the subtask.
+    pub id: SubtaskId,
+    /// The name of the subtask, if any.
+    pub name: Option<String>,
+    /// The score of the solution on the subtask, if it has been evaluated completely.
+    pub score: Option<f64>,
+    /// The maximum score of the subtask.
+    pub max_score: f64,
+    /// The worst verdict and the largest resources of the solution on the testcases of the subtask.
+    #[serde(flatten)]
+    pub summary: TestcasesSummary,
+}
+
+/// The worst verdict and the largest resources of a solution on a group of testcases, folded as
+/// the testcases are evaluated.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TestcasesSummary {
+    /// The worst verdict of the solution, like `accepted` or `time-limit-exceeded`, if at least a
+    /// testcase has been evaluated.
+    pub verdict: Option<String>,
+    /// The largest CPU time used by the solution, in seconds.
+    pub cpu_time: Option<f64>,
+    /// The largest wall time used by the solution, in seconds.
+    pub wall_time: Option<f64>,
+    /// The largest memory used by the solution, in KiB.
+    pub memory: Option<u64>,
+}
+
+impl TestcasesSummary {
+    /// The verdicts, from the best to the worst.
+    const VERDICTS: [&'static str; 10] = [
+        "accepted",
+        "partial-score",
+        "skipped",
+        "wrong-answer",
+        "runtime-error",
+        "memory-limit-exceeded",
+        "wall-time-limit-exceeded",
+        "time-limit-exceeded",
+        "checker-error",
+        "failed",
+    ];
+
+    /// How bad a verdict is, the higher the worse.
+    fn severity(verdict: &str) -> usize {
+        Self::VERDICTS
+            .iter()
+            .position(|v| *v == verdict)
+            .unwrap_or_default()
+    }
+
+    /// Keep the verdict if it's worse than the current one.
+    fn add_verdict(&mut self, verdict: &str) {
+        let worse = match &self.verdict {
+            Some(current) => Self::severity(verdict) > Self::severity(current),
+            None => true,
+        };
+        if worse {
+            self.verdict = Some(verdict.to_string());
+        }
+    }
+
+    /// Keep the resources that are larger than the current ones.
+    fn add_resources(&mut self, resources: &ExecutionResourcesUsage) {
+        let max = |current: Option<f64>, value: f64| Some(current.map_or(value, |c| c.max(value)));
+        self.cpu_time = max(self.cpu_time, resources.cpu_time);
+        self.wall_time = max(self.wall_time, resources.wall_time);
+        self.memory = Some(
+            self.memory
+                .map_or(resources.memory, |m| m.max(resources.memory)),
+        );
+    }
+
+    /// Fold another summary into this one.
+    fn merge(&mut self, other: &TestcasesSummary) {
+        if let Some(verdict) = &other.verdict {
+            self.add_verdict(verdict);
+        }
+        let max = |a: Option<f64>, b: Option<f64>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        self.cpu_time = max(self.cpu_time, other.cpu_time);
+        self.wall_time = max(self.wall_time, other.wall_time);
+        self.memory = self.memory.max(other.memory);
+    }
+}
+
+/// The outcome of a `@check` comment of a solution on a subtask.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolutionCheckResults {
+    /// The path of the solution, relative to the task directory.
+    pub solution: PathBuf,
+    /// The id of the subtask.
+    pub subtask: SubtaskId,
+    /// The expected result, as written in the comment.
+    pub expected: String,
+    /// Whether the solution got the expected result.
+    pub success: bool,
+}
+
+/// The results of the evaluation of a task, folded as the messages sent to the UI arrive.
+pub struct ResultsCollector {
+    /// The state of the collector of the format of the task.
+    state: CollectorState,
+}
+
+/// The state of the collector, for each task format.
+enum CollectorState {
+    /// The aggregates of an IOI task.
+    IOI(Box<IOICollector>),
+    /// The state of the UI of a Terry task, that has no testcases.
+    Terry(Box<terry::ui_state::UIState>),
+}
+
+impl ResultsCollector {
+    /// Make a new `ResultsCollector` for the evaluation of a task.
+    pub fn new(task: &TaskFormat) -> ResultsCollector {
+        let state = match task {
+            TaskFormat::IOI(task) => CollectorState::IOI(Box::new(IOICollector::new(task))),
+            TaskFormat::Terry(task) => {
+                CollectorState::Terry(Box::new(terry::ui_state::UIState::new(task)))
+            }
+        };
+        ResultsCollector { state }
+    }
+
+    /// Apply a message sent to the UI, after dropping the outputs of the executions and the messages
+    /// of the checkers (see [`UIMessage::strip_outputs`]). The results of each testcase are folded
+    /// into the aggregates of its solution and subtasks as soon as its score arrives, so the memory
+    /// used doesn't grow with the number of testcases, nor with the size of their outputs.
+    pub fn apply(&mut self, mut message: UIMessage) {
+        message.strip_outputs();
+        match &mut self.state {
+            CollectorState::IOI(state) => state.apply(message),
+            CollectorState::Terry(state) => state.apply(message),
+        }
+    }
+
+    /// Write in `dir` the feedback pages of the solutions of a Terry task, the same the contestants
+    /// see after submitting their outputs. Returns the paths of the pages written.
+    pub fn write_terry_feedback(&self, dir: &Path) -> Result<Vec<PathBuf>, Error> {
+        match &self.state {
+            CollectorState::Terry(state) => terry::feedback::write_feedback_pages(state, dir),
+            CollectorState::IOI(_) => {
+                bail!("The feedback pages are available only for Terry tasks")
+            }
+        }
+    }
+
+    /// The results of the evaluation, from the messages applied so far.
+    pub fn results(&self) -> EvaluationResults {
+        let mut results = match &self.state {
+            CollectorState::IOI(state) => state.results(),
+            CollectorState::Terry(state) => terry_results(state),
+        };
+        results.solutions.sort_by(|a, b| a.path.cmp(&b.path));
+        let diagnostics = match &self.state {
+            CollectorState::IOI(state) => &state.diagnostics,
+            CollectorState::Terry(state) => &state.diagnostics,
+        };
+        for diagnostic in diagnostics.diagnostics() {
+            let message = diagnostic.message().to_string();
+            match diagnostic.level() {
+                DiagnosticLevel::Error => results.errors.push(message),
+                DiagnosticLevel::Warning => results.warnings.push(message),
+            }
+        }
+        results
+    }
+}
+
+/// The aggregates of the evaluation of an IOI task. Besides them, only the failures of the testcases
+/// not scored yet are kept, since they decide the verdict once the score arrives.
+struct IOICollector {
+    /// The task being evaluated.
+    task: ioi::IOITask,
+    /// The subtasks each testcase is part of, more than one if it's shared with other subtasks.
+    testcase_subtasks: HashMap<TestcaseId, Vec<SubtaskId>>,
+    /// The solutions being evaluated.
+    solutions: HashMap<PathBuf, SolutionInfo>,
+    /// The status of the compilations.
+    compilations: HashMap<PathBuf, CompilationStatus>,
+    /// The aggregates of each solution.
+    evaluations: HashMap<PathBuf, SolutionAggregate>,
+    /// The failures of the testcases of each solution that have not been scored yet.
+    failures: HashMap<(PathBuf, TestcaseId), TestcaseEvaluationStatus>,
+    /// The diagnostics emitted during the evaluation.
+    diagnostics: DiagnosticContext,
+}
+
+/// The aggregates of a solution of an IOI task.
+#[derive(Debug, Default)]
+struct SolutionAggregate {
+    /// The score of the solution, once all its subtasks have been scored.
+    score: Option<f64>,
+    /// The aggregates of each subtask.
+    subtasks: HashMap<SubtaskId, SubtaskAggregate>,
+}
+
+/// The aggregates of a solution on a subtask of an IOI task.
+#[derive(Debug, Default)]
+struct SubtaskAggregate {
+    /// The score of the subtask, once all its testcases have been scored.
+    score: Option<f64>,
+    /// The worst verdict and the largest resources on the testcases of the subtask.
+    summary: TestcasesSummary,
+    /// The distinct outcomes of the testcases, for the `@check` comments.
+    outcomes: HashSet<TestcaseEvaluationResult>,
+    /// The number of testcases that have been scored.
+    scored: usize,
+    /// Whether a testcase ended without an outcome (e.g. it was skipped), so the `@check`
+    /// comments cannot be evaluated on the subtask.
+    missing_outcome: bool,
+}
+
+impl IOICollector {
+    /// Make a new `IOICollector` for the evaluation of the task.
+    fn new(task: &ioi::IOITask) -> IOICollector {
+        let mut testcase_subtasks: HashMap<_, Vec<_>> = HashMap::new();
+        for subtask in task.subtasks.values() {
+            for &testcase in &subtask.testcases {
+                testcase_subtasks
+                    .entry(testcase)
+                    .or_default()
+                    .push(subtask.id);
+            }
+        }
+        IOICollector {
+            task: task.clone(),
+            testcase_subtasks,
+            solutions: HashMap::new(),
+            compilations: HashMap::new(),
+            evaluations: HashMap::new(),
+            failures: HashMap::new(),
+            diagnostics: Default::default(),
+        }
+    }
+
+    /// The aggregates of the subtasks of a solution that contain the testcase.
+    fn subtasks_of(
+        &mut self,
+        solution: &Path,
+        testcase: TestcaseId,
+    ) -> impl Iterator<Item = &mut SubtaskAggregate> {
+        let subtasks = self
+            .testcase_subtasks
+            .get(&testcase)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let evaluation = self.evaluations.entry(solution.to_path_buf()).or_default();
+        for &subtask in subtasks {
+            evaluation.subtasks.entry(subtask).or_default();
+        }
+        evaluation
+            .subtasks
+            .iter_mut()
+            .filter(move |(id, _)| subtasks.contains(id))
+            .map(|(_, subtask)| subtask)
+    }
+
+    /// Apply a message sent to the UI.
+    fn apply(&mut self, message: UIMessage) {
+        match message {
+            UIMessage::Solutions { solutions } => {
+                self.solutions = solutions
+                    .into_iter()
+                    .map(|info| (info.path.clone(), info))
+                    .collect();
+            }
+            UIMessage::Compilation { file, status } => self
+                .compilations
+                .entry(file)
+                .or_insert(CompilationStatus::Pending)
+                .apply_status(status),
+            UIMessage::IOIEvaluation {
+                testcase,
+                solution,
+                status,
+                ..
+            } => match status {
+                UIExecutionStatus::Pending | UIExecutionStatus::Started { .. } => {}
+                UIExecutionStatus::Done { result } => {
+                    for subtask in self.subtasks_of(&solution, testcase) {
+                        subtask.summary.add_resources(&result.resources);
+                    }
+                    let failure = match result.status {
+                        ExecutionStatus::Success => return,
+                        ExecutionStatus::ReturnCode(_) | ExecutionStatus::Signal(_, _) => {
+                            TestcaseEvaluationStatus::RuntimeError
+                        }
+                        ExecutionStatus::TimeLimitExceeded
+                        | ExecutionStatus::SysTimeLimitExceeded => {
+                            TestcaseEvaluationStatus::TimeLimitExceeded
+                        }
+                        ExecutionStatus::WallTimeLimitExceeded => {
+                            TestcaseEvaluationStatus::WallTimeLimitExceeded
+                        }
+                        ExecutionStatus::MemoryLimitExceeded => {
+                            TestcaseEvaluationStatus::MemoryLimitExceeded
+                        }
+                        ExecutionStatus::InternalError(_) => TestcaseEvaluationStatus::Failed,
+                    };
+                    self.failures.insert((solution, testcase), failure);
+                }
+                UIExecutionStatus::Skipped => {
+                    // a skipped testcase is never scored
+                    self.failures.remove(&(solution.clone(), testcase));
+                    for subtask in self.subtasks_of(&solution, testcase) {
+                        subtask.summary.add_verdict("skipped");
+                        subtask.missing_outcome = true;
+                    }
+                }
+            },
+            UIMessage::IOICheckerError {
+                testcase,
+                solution,
+                message,
+                ..
+            } => {
+                self.failures
+                    .entry((solution, testcase))
+                    .or_insert(TestcaseEvaluationStatus::CheckerError(message));
+            }
+            UIMessage::IOITestcaseScore {
+                testcase,
+                solution,
+                score,
+                ..
+            } => {
+                let failure = self.failures.remove(&(solution.clone(), testcase));
+                let status = failure.unwrap_or(match ScoreStatus::from_score(score, 1.0) {
+                    ScoreStatus::WrongAnswer => {
+                        TestcaseEvaluationStatus::WrongAnswer(String::new())
+                    }
+                    ScoreStatus::Accepted => TestcaseEvaluationStatus::Accepted(String::new()),
+                    ScoreStatus::PartialScore => TestcaseEvaluationStatus::Partial(String::new()),
+                });
+                let outcome: Option<TestcaseEvaluationResult> = (&status).into();
+                for subtask in self.subtasks_of(&solution, testcase) {
+                    subtask.summary.add_verdict(verdict(&status));
+                    subtask.scored += 1;
+                    match outcome {
+                        Some(outcome) => {
+                            subtask.outcomes.insert(outcome);
+                        }
+                        None => subtask.missing_outcome = true,
+                    }
+                }
+            }
+            UIMessage::IOISubtaskScore {
+                subtask,
+                solution,
+                score,
+                ..
+            } => {
+                // the testcases of a completed subtask are not evaluated anymore
+                if let Some(info) = self.task.subtasks.get(&subtask) {
+                    self.failures.retain(|(path, testcase), _| {
+                        path != &solution || !info.testcases.contains(testcase)
+                    });
+                }
+                self.evaluations
+                    .entry(solution)
+                    .or_default()
+                    .subtasks
+                    .entry(subtask)
+                    .or_default()
+                    .score = Some(score);
+            }
+            UIMessage::IOITaskScore { solution, score } => {
+                self.failures.retain(|(path, _), _| path != &solution);
+                self.evaluations.entry(solution).or_default().score = Some(score);
+            }
+            UIMessage::Diagnostic { diagnostic } => {
+                self.diagnostics.add_diagnostic(diagnostic);
+            }
+            _ => {}
+        }
+    }
+
+    /// The results of the evaluation, from the aggregates.
+    fn results(&self) -> EvaluationResults {
+        let task_path = self.task.path();
+        let solutions = self
+            .solutions
+            .keys()
+            .map(|path| {
+                let evaluation = self.evaluations.get(path);
+                let mut subtasks: Vec<_> = self
+                    .task
+                    .subtasks
+                    .values()
+                    .map(|subtask| {
+                        let aggregate =
+                            evaluation.and_then(|evaluation| evaluation.subtasks.get(&subtask.id));
+                        SubtaskResults {
+                            id: subtask.id,
+                            name: subtask.name.clone(),
+                            score: aggregate.and_then(|aggregate| aggregate.score),
+                            max_score: subtask.max_score,
+                            summary: aggregate
+                                .map(|aggregate| aggregate.summary.clone())
+                                .unwrap_or_default(),
+                        }
+                    })
+                    .collect();
+                subtasks.sort_by_key(|subtask| subtask.id);
+                let mut summary = TestcasesSummary::default();
+                for subtask in &subtasks {
+                    summary.merge(&subtask.summary);
+                }
+                SolutionResults {
+                    name: file_name(path),
+                    path: relative_path(task_path, path),
+                    compiled: is_compiled(self.compilations.get(path)),
+                    score: evaluation.and_then(|evaluation| evaluation.score),
+                    summary,
+                    subtasks,
+                }
+            })
+            .collect();
+        let mut checks = Vec::new();
+        for (path, solution) in &self.solutions {
+            // the solution was not evaluated
+            let Some(evaluation) = self.evaluations.get(path) else {
+                continue;
+            };
+            for check in &solution.checks {
+                for subtask in self
+                    .task
+                    .find_subtasks_by_pattern_name(&check.subtask_name_pattern)
+                {
+                    let Some(aggregate) = evaluation.subtasks.get(&subtask.id) else {
+                        continue;
+                    };
+                    if aggregate.missing_outcome || aggregate.scored < subtask.testcases.len() {
+                        continue;
+                    }
+                    let outcomes: Vec<_> = aggregate.outcomes.iter().copied().collect();
+                    checks.push(SolutionCheckResults {
+                        solution: relative_path(task_path, path),
+                        subtask: subtask.id,
+                        expected: check.result.as_str().to_string(),
+                        success: check.result.check(&outcomes),
+                    });
+                }
+            }
+        }
+        checks.sort_by(|a, b| (&a.solution, a.subtask).cmp(&(&b.solution, b.subtask)));
+        EvaluationResults {
+            task: self.task.name.clone(),
+            max_score: self.task.subtasks.values().map(|s| s.max_score).sum(),
+            solutions,
+            checks,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// The path of a file relative to the task directory, or the path itself if it's outside of it.
+fn relative_path(task_path: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(task_path).unwrap_or(path).to_path_buf()
+}
+
+/// Whether a file has been compiled successfully, the files without a compilation are considered
+/// compiled.
+fn is_compiled(compilation: Option<&CompilationStatus>) -> bool {
+    matches!(
+        compilation,
+        None | Some(CompilationStatus::Done { .. }) | Some(CompilationStatus::Skipped)
+    )
+}
+
+/// The name of the verdict of a solution on a testcase.
+fn verdict(status: &TestcaseEvaluationStatus) -> &'static str {
+    match status {
+        TestcaseEvaluationStatus::Pending => "pending",
+        TestcaseEvaluationStatus::Solving => "solving",
+        TestcaseEvaluationStatus::Solved => "solved",
+        TestcaseEvaluationStatus::Checking => "checking",
+        TestcaseEvaluationStatus::Accepted(_) => "accepted",
+        TestcaseEvaluationStatus::WrongAnswer(_) => "wrong-answer",
+        TestcaseEvaluationStatus::Partial(_) => "partial-score",
+        TestcaseEvaluationStatus::TimeLimitExceeded => "time-limit-exceeded",
+        TestcaseEvaluationStatus::WallTimeLimitExceeded => "wall-time-limit-exceeded",
+        TestcaseEvaluationStatus::MemoryLimitExceeded => "memory-limit-exceeded",
+        TestcaseEvaluationStatus::RuntimeError => "runtime-error",
+        TestcaseEvaluationStatus::CheckerError(_) => "checker-error",
+        TestcaseEvaluationStatus::Failed => "failed",
+        TestcaseEvaluationStatus::Skipped => "skipped",
+    }
+}
+
+/// The results of the evaluation of a Terry task.
+fn terry_results(state: &terry::ui_state::UIState) -> EvaluationResults {
+    let task_path = state.task.path.as_path();
+    let solutions = state
+        .solutions
+        .iter()
+        .map(|(path, solution)| SolutionResults {
+            name: file_name(path),
+            path: relative_path(task_path, path),
+            compiled: is_compiled(state.compilations.get(path)),
+            score: match &solution.outcome {
+                Some(Ok(outcome)) => Some(outcome.score * state.task.max_score),
+                _ => None,
+            },
+            summary: TestcasesSummary::default(),
+            subtasks: Vec::new(),
+        })
+        .collect();
+    EvaluationResults {
+        task: state.task.name.clone(),
+        max_score: state.task.max_score,
+        solutions,
+        checks: Vec::new(),
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    }
+}
+
+/// The name of the file at that path.
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// A comparison between the score of a solution and a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `>=`
+    GreaterOrEqual,
+    /// `<=`
+    LessOrEqual,
+    /// `>`
+    Greater,
+    /// `<`
+    Less,
+}
+
+impl Comparison {
+    /// The operators, sorted so that no operator is a prefix of a following one.
+    const ALL: [(&'static str, Comparison); 6] = [
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+    ];
+
+    /// The operator of this comparison.
+    pub fn as_str(&self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(_, comparison)| comparison == self)
+            .map(|(operator, _)| *operator)
+            .expect("All the comparisons have an operator")
+    }
+
+    /// Compare the two values, the scores are considered equal if they are close enough.
+    pub fn compare(&self, left: f64, right: f64) -> bool {
+        let equal = (left - right).abs() < 1e-6;
+        match self {
+            Comparison::Equal => equal,
+            Comparison::NotEqual => !equal,
+            Comparison::GreaterOrEqual => equal || left > right,
+            Comparison::LessOrEqual => equal || left < right,
+            Comparison::Greater => !equal && left > right,
+            Comparison::Less => !equal && left < right,
+        }
+    }
+}
+
+/// A requirement on the score of a solution, like `sol/sol.cpp == 100`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Requirement {
+    /// The solution, either the name of its file or its path relative to the task directory.
+    pub solution: String,
+    /// How to compare the score of the solution.
+    pub comparison: Comparison,
+    /// The value the score is compared to.
+    pub value: f64,
+}
+
+impl FromStr for Requirement {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((index, operator, comparison)) = Comparison::ALL
+            .iter()
+            .filter_map(|&(operator, comparison)| {
+                s.find(operator).map(|index| (index, operator, comparison))
+            })
+            .min_by_key(|(index, _, _)| *index)
+        else {
+            bail!(
+                "Invalid requirement {:?}: expecting something like \"sol/sol.cpp == 100\"",
+                s
+            );
+        };
+        let solution = s[..index].trim();
+        let value = s[index + operator.len()..].trim();
+        if solution.is_empty() {
+            bail!("Invalid requirement {:?}: the solution is missing", s);
+        }
+        let Ok(value) = value.parse() else {
+            bail!("Invalid requirement {:?}: {:?} is not a number", s, value);
+        };
+        Ok(Requirement {
+            solution: solution.to_string(),
+            comparison,
+            value,
+        })
+    }
+}
+
+impl Display for Requirement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.solution,
+            self.comparison.as_str(),
+            self.value
+        )
+    }
+}
+
+impl Requirement {
+    /// Check the requirement against the results of an evaluation, returning why it's not
+    /// satisfied.
+    pub fn check(&self, results: &EvaluationResults) -> Result<(), String> {
+        let solution = results
+            .solutions
+            .iter()
+            .find(|solution| solution.path == Path::new(&self.solution))
+            .or_else(|| {
+                results
+                    .solutions
+                    .iter()
+                    .find(|solution| solution.name == self.solution)
+            });
+        let Some(solution) = solution else {
+            return Err(format!("{}: the solution has not been evaluated", self));
+        };
+        match solution.score {
+            Some(score) if self.comparison.compare(score, self.value) => Ok(()),
+            Some(score) => Err(format!("{}: the score is {}", self, score)),
+            None if !solution.compiled => Err(format!("{}: the solution did not compile", self)),
+            None => Err(format!("{}: the solution has no score", self)),
+        }
+    }
+}
+
+/// The thresholds over which the resources used by a solution are considered different.
+#[derive(Debug, Clone)]
+pub struct DiffThresholds {
+    /// The minimum change of the CPU time, relative to the baseline, from 0.0 to 1.0.
+    pub time: f64,
+    /// The minimum absolute change of the CPU time, in seconds, so that the tiny times are not
+    /// reported.
+    pub min_time: f64,
+    /// The minimum change of the memory, relative to the baseline, from 0.0 to 1.0.
+    pub memory: f64,
+}
+
+/// What differs between two evaluations of a solution.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DifferenceKind {
+    /// The solution has been evaluated only in the new run.
+    Added,
+    /// The solution has been evaluated only in the baseline.
+    Removed,
+    /// The score of the solution changed.
+    Score {
+        /// The score in the baseline.
+        before: Option<f64>,
+        /// The score in the new run.
+        after: Option<f64>,
+    },
+    /// The worst verdict of the solution on a subtask changed.
+    Verdict {
+        /// The verdict in the baseline.
+        before: String,
+        /// The verdict in the new run.
+        after: String,
+    },
+    /// The largest CPU time used by the solution on a subtask changed.
+    Time {
+        /// The time in the baseline, in seconds.
+        before: f64,
+        /// The time in the new run, in seconds.
+        after: f64,
+    },
+    /// The largest memory used by the solution on a subtask changed.
+    Memory {
+        /// The memory in the baseline, in KiB.
+        before: u64,
+        /// The memory in the new run, in KiB.
+        after: u64,
+    },
+}
+
+/// A difference between two evaluations of a solution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultsDifference {
+    /// The path of the solution, relative to the task directory.
+    pub solution: PathBuf,
+    /// The subtask the difference refers to, if any.
+    pub subtask: Option<SubtaskId>,
+    /// What differs.
+    pub kind: DifferenceKind,
+    /// Whether the solution got worse.
+    pub regression: bool,
+}
+
+impl Display for DifferenceKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let score = |score: &Option<f64>| match score {
+            Some(score) => score.to_string(),
+            None => "none".to_string(),
+        };
+        let change = |before: f64, after: f64| {
+            if before > 0.0 {
+                format!(" ({:+.0}%)", (after - before) / before * 100.0)
+            } else {
+                String::new()
+            }
+        };
+        match self {
+            DifferenceKind::Added => write!(f, "only in the new results"),
+            DifferenceKind::Removed => write!(f, "only in the baseline"),
+            DifferenceKind::Score { before, after } => {
+                write!(f, "score {} -> {}", score(before), score(after))
+            }
+            DifferenceKind::Verdict { before, after } => write!(f, "{} -> {}", before, after),
+            DifferenceKind::Time { before, after } => write!(
+                f,
+                "time {:.3}s -> {:.3}s{}",
+                before,
+                after,
+                change(*before, *after)
+            ),
+            DifferenceKind::Memory { before, after } => write!(
+                f,
+                "memory {} KiB -> {} KiB{}",
+                before,
+                after,
+                change(*before as f64, *after as f64)
+            ),
+        }
+    }
+}
+
+/// Compare the results of two evaluations of the same task, listing the differences of each
+/// solution: its score, and the worst verdict and the largest resources on each subtask evaluated in
+/// both runs.
+pub fn diff_results(
+    baseline: &EvaluationResults,
+    results: &EvaluationResults,
+    thresholds: &DiffThresholds,
+) -> Vec<ResultsDifference> {
+    let mut differences = Vec::new();
+    let difference = |solution: &SolutionResults, subtask, kind, regression| ResultsDifference {
+        solution: solution.path.clone(),
+        subtask,
+        kind,
+        regression,
+    };
+    for before in &baseline.solutions {
+        if !results.solutions.iter().any(|s| s.path == before.path) {
+            differences.push(difference(before, None, DifferenceKind::Removed, false));
+        }
+    }
+    for after in &results.solutions {
+        let Some(before) = baseline.solutions.iter().find(|s| s.path == after.path) else {
+            differences.push(difference(after, None, DifferenceKind::Added, false));
+            continue;
+        };
+        let same_score = match (before.score, after.score) {
+            (Some(a), Some(b)) => Comparison::Equal.compare(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        if !same_score {
+            // a missing score is worse than any score
+            let regression = after.score.unwrap_or(f64::NEG_INFINITY)
+                < before.score.unwrap_or(f64::NEG_INFINITY);
+            let kind = DifferenceKind::Score {
+                before: before.score,
+                after: after.score,
+            };
+            differences.push(difference(after, None, kind, regression));
+        }
+        for subtask in &after.subtasks {
+            let Some(old) = before.subtasks.iter().find(|s| s.id == subtask.id) else {
+                continue;
+            };
+            let (old, new) = (&old.summary, &subtask.summary);
+            let id = Some(subtask.id);
+            if let (Some(a), Some(b)) = (&old.verdict, &new.verdict) {
+                if a != b {
+                    let regression = TestcasesSummary::severity(b) > TestcasesSummary::severity(a);
+                    let kind = DifferenceKind::Verdict {
+                        before: a.clone(),
+                        after: b.clone(),
+                    };
+                    differences.push(difference(after, id, kind, regression));
+                }
+            }
+            if let (Some(a), Some(b)) = (old.cpu_time, new.cpu_time) {
+                if (b - a).abs() >= thresholds.min_time && (b - a).abs() >= a * thresholds.time {
+                    let kind = DifferenceKind::Time {
+                        before: a,
+                        after: b,
+                    };
+                    differences.push(difference(after, id, kind, b > a));
+                }
+            }
+            if let (Some(a), Some(b)) = (old.memory, new.memory) {
+                if a != b && a.abs_diff(b) as f64 >= a as f64 * thresholds.memory {
+                    let kind = DifferenceKind::Memory {
+                        before: a,
+                        after: b,
+                    };
+                    differences.push(difference(after, id, kind, b > a));
+                }
+            }
+        }
+    }
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use task_maker_dag::ExecutionResult;
+
+    use task_maker_diagnostics::CodeSpan;
+
+    use crate::ioi::{InputGenerator, OutputGenerator, SubtaskInfo, TestcaseInfo};
+    use crate::solution::{SolutionCheck, SolutionCheckResult};
+
+    use super::*;
+
+    fn results(scores: &[(&str, Option<f64>)]) -> EvaluationResults {
+        EvaluationResults {
+            task: "task".into(),
+            max_score: 100.0,
+            solutions: scores
+                .iter()
+                .map(|(path, score)| SolutionResults {
+                    name: file_name(Path::new(path)),
+                    path: path.into(),
+                    compiled: score.is_some(),
+                    score: *score,
+                    summary: TestcasesSummary::default(),
+                    subtasks: Vec::new(),
+                })
+                .collect(),
+            checks: Vec::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// A result of an execution with some captured output.
+    fn execution_result(stdout: &str, stderr: &str) -> ExecutionResult {
+        ExecutionResult {
+            status: ExecutionStatus::Success,
+            was_killed: false,
+            was_cached: false,
+            resources: ExecutionResourcesUsage {
+                cpu_time: 0.5,
+                sys_time: 0.1,
+                wall_time: 0.7,
+                memory: 1024,
+            },
+            stdout: Some(stdout.as_bytes().to_vec()),
+            stderr: Some(stderr.as_bytes().to_vec()),
+            time_scale: 1.0,
+            cpu: None,
+            run_times: vec![],
+            memory_metric: Default::default(),
+        }
+    }
+
+    /// A task with a single subtask with the specified number of testcases.
+    fn task(num_testcases: usize) -> ioi::IOITask {
+        let mut task = ioi::IOITask::fake();
+        task.name = "task".into();
+        task.subtasks.insert(
+            0,
+            SubtaskInfo {
+                id: 0,
+                name: Some("all".into()),
+                max_score: 100.0,
+                testcases: (0..num_testcases as TestcaseId).collect(),
+                ..Default::default()
+            },
+        );
+        for id in 0..num_testcases as TestcaseId {
+            task.testcases.insert(
+                id,
+                TestcaseInfo::new(
+                    id,
+                    InputGenerator::StaticFile("input.txt".into()),
+                    OutputGenerator::StaticFile("output.txt".into()),
+                ),
+            );
+        }
+        task
+    }
+
+    /// The message with the solutions of the task, all expected to be accepted.
+    fn solutions_message(solutions: &[&str]) -> UIMessage {
+        let text = "@check-accepted: all";
+        let span = CodeSpan::from_str("sol.cpp", text, 0, text.len()).unwrap();
+        let check = SolutionCheck::new(SolutionCheckResult::Accepted, "all", span);
+        UIMessage::Solutions {
+            solutions: solutions
+                .iter()
+                .map(|path| SolutionInfo {
+                    path: path.into(),
+                    name: path.to_string(),
+                    language_name: "C++".into(),
+                    checks: vec![check.clone()],
+                })
+                .collect(),
+        }
+    }
+
+    /// The messages of the evaluation of a solution on a testcase, up to its checking.
+    fn evaluation_messages(solution: &str, testcase: TestcaseId) -> Vec<UIMessage> {
+        vec![
+            UIMessage::IOIEvaluation {
+                subtask: 0,
+                testcase,
+                solution: solution.into(),
+                status: UIExecutionStatus::Done {
+                    result: execution_result("42", "debug"),
+                },
+                part: 0,
+                num_parts: 1,
+            },
+            UIMessage::IOIChecker {
+                subtask: 0,
+                testcase,
+                solution: solution.into(),
+                status: UIExecutionStatus::Done {
+                    result: execution_result("1.0", "Output is correct"),
+                },
+            },
+        ]
+    }
+
+    /// The message with the score of a solution on a testcase.
+    fn score_message(solution: &str, testcase: TestcaseId, score: f64) -> UIMessage {
+        UIMessage::IOITestcaseScore {
+            subtask: 0,
+            testcase,
+            name: None,
+            solution: solution.into(),
+            score,
+            message: "Output is correct".into(),
+        }
+    }
+
+    #[test]
+    fn test_strip_outputs_keeps_results() {
+        let task = task(1);
+        let mut messages = vec![solutions_message(&["sol.cpp", "wrong.cpp"])];
+        messages.extend(evaluation_messages("sol.cpp", 0));
+        messages.extend(evaluation_messages("wrong.cpp", 0));
+        messages.push(score_message("sol.cpp", 0, 1.0));
+        messages.push(UIMessage::IOICheckerError {
+            subtask: 0,
+            testcase: 0,
+            solution: "wrong.cpp".into(),
+            message: "Invalid score".into(),
+        });
+        messages.push(score_message("wrong.cpp", 0, 0.0));
+        for (solution, score) in [("sol.cpp", 100.0), ("wrong.cpp", 0.0)] {
+            messages.push(UIMessage::IOISubtaskScore {
+                subtask: 0,
+                solution: solution.into(),
+                normalized_score: score / 100.0,
+                score,
+            });
+            messages.push(UIMessage::IOITaskScore {
+                solution: solution.into(),
+                score,
+            });
+        }
+
+        let mut collector = ResultsCollector::new(&TaskFormat::IOI(task));
+        for message in messages {
+            collector.apply(message);
+        }
+        let results = collector.results();
+        assert_eq!(results.max_score, 100.0);
+        let [sol, wrong] = results.solutions.as_slice() else {
+            panic!("Expecting two solutions: {:?}", results.solutions);
+        };
+        assert_eq!(sol.score, Some(100.0));
+        assert_eq!(sol.subtasks[0].score, Some(100.0));
+        let summary = TestcasesSummary {
+            verdict: Some("accepted".into()),
+            cpu_time: Some(0.5),
+            wall_time: Some(0.7),
+            memory: Some(1024),
+        };
+        assert_eq!(sol.summary, summary);
+        assert_eq!(sol.subtasks[0].summary, summary);
+        assert_eq!(wrong.score, Some(0.0));
+        assert_eq!(wrong.summary.verdict.as_deref(), Some("checker-error"));
+        let checks: Vec<_> = results
+            .checks
+            .iter()
+            .map(|check| (check.solution.to_str().unwrap(), check.success))
+            .collect();
+        // the checker error is not an outcome, so the check of wrong.cpp cannot be evaluated
+        assert_eq!(checks, vec![("sol.cpp", true)]);
+        let json = serde_json::to_value(&results).unwrap();
+        assert_eq!(json["solutions"][1]["verdict"], "checker-error");
+        assert_eq!(json["solutions"][1]["subtasks"][0]["cpu_time"], 0.5);
+    }
+
+    #[test]
+    fn test_collector_state_does_not_grow() {
+        let num_testcases = 100;
+        let task = task(num_testcases);
+        let mut collector = ResultsCollector::new(&TaskFormat::IOI(task));
+        collector.apply(solutions_message(&["sol.cpp"]));
+        let state_size = |collector: &ResultsCollector| match &collector.state {
+            CollectorState::IOI(state) => (
+                state.failures.len(),
+                state.evaluations.len(),
+                state
+                    .evaluations
+                    .values()
+                    .map(|evaluation| evaluation.subtasks.len())
+                    .sum::<usize>(),
+                state
+                    .evaluations
+                    .values()
+                    .flat_map(|evaluation| evaluation.subtasks.values())
+                    .map(|subtask| subtask.outcomes.len())
+                    .sum::<usize>(),
+            ),
+            CollectorState::Terry(_) => unreachable!(),
+        };
+        let mut sizes = Vec::new();
+        for testcase in 0..num_testcases as TestcaseId {
+            for message in evaluation_messages("sol.cpp", testcase) {
+                collector.apply(message);
+            }
+            collector.apply(score_message("sol.cpp", testcase, 1.0));
+            sizes.push(state_size(&collector));
+        }
+        assert!(
+            sizes.iter().all(|size| size == &(0, 1, 1, 1)),
+            "{:?}",
+            sizes
+        );
+
+        let results = collector.results();
+        assert_eq!(
+            results.solutions[0].summary.verdict.as_deref(),
+            Some("accepted")
+        );
+    }
+
+    #[test]
+    fn test_parse_requirement() {
+        let requirement: Requirement = "sol/sol.cpp == 100".parse().unwrap();
+        assert_eq!(requirement.solution, "sol/sol.cpp");
+        assert_eq!(requirement.comparison, Comparison::Equal);
+        assert_eq!(requirement.value, 100.0);
+        let requirement: Requirement = "wrong.py<=12.5".parse().unwrap();
+        assert_eq!(requirement.solution, "wrong.py");
+        assert_eq!(requirement.comparison, Comparison::LessOrEqual);
+        assert_eq!(requirement.value, 12.5);
+        assert_eq!(requirement.to_string(), "wrong.py <= 12.5");
+
+        assert!("sol.cpp".parse::<Requirement>().is_err());
+        assert!("== 100".parse::<Requirement>().is_err());
+        assert!("sol.cpp == full".parse::<Requirement>().is_err());
+    }
+
+    #[test]
+    fn test_check_requirement() {
+        let results = results(&[("sol/sol.cpp", Some(100.0)), ("sol/wrong.cpp", None)]);
+        let check = |requirement: &str| requirement.parse::<Requirement>().unwrap().check(&results);
+        assert!(check("sol/sol.cpp == 100").is_ok());
+        assert!(check("sol.cpp >= 99.5").is_ok());
+        assert!(check("sol.cpp < 100").is_err());
+        assert!(check("sol.cpp != 100").is_err());
+        assert!(check("wrong.cpp == 0").is_err());
+        assert!(check("missing.cpp == 0").is_err());
+    }
+
+    #[test]
+    fn test_diff_results() {
+        let subtask = |id, verdict: &str, cpu_time| SubtaskResults {
+            id,
+            name: None,
+            score: None,
+            max_score: 10.0,
+            summary: TestcasesSummary {
+                verdict: Some(verdict.into()),
+                cpu_time: Some(cpu_time),
+                wall_time: Some(cpu_time),
+                memory: Some(1000),
+            },
+        };
+        let mut baseline = results(&[("sol/sol.cpp", Some(100.0)), ("sol/old.cpp", Some(0.0))]);
+        baseline.solutions[0].subtasks = vec![
+            subtask(0, "accepted", 0.5),
+            subtask(1, "accepted", 0.5),
+            subtask(2, "accepted", 0.01),
+        ];
+        let mut results = results(&[("sol/sol.cpp", Some(50.0)), ("sol/new.cpp", Some(0.0))]);
+        results.solutions[0].subtasks = vec![
+            subtask(0, "time-limit-exceeded", 1.0),
+            subtask(1, "accepted", 0.52),
+            subtask(2, "accepted", 0.02),
+        ];
+        let thresholds = DiffThresholds {
+            time: 0.2,
+            min_time: 0.05,
+            memory: 0.2,
+        };
+        let differences = diff_results(&baseline, &results, &thresholds);
+        let summary: Vec<_> = differences
+            .iter()
+            .map(|d| (d.solution.to_str().unwrap(), d.subtask, d.regression))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("sol/old.cpp", None, false),
+                ("sol/sol.cpp", None, true),
+                ("sol/sol.cpp", Some(0), true),
+                ("sol/sol.cpp", Some(0), true),
+                ("sol/new.cpp", None, false),
+            ]
+        );
+        assert_eq!(
+            differences[2].kind.to_string(),
+            "accepted -> time-limit-exceeded"
+        );
+        assert_eq!(
+            differences[3].kind.to_string(),
+            "time 0.500s -> 1.000s (+100%)"
+        );
+    }
+}
@@ -12,7 +12,7 @@ use task_maker_diagnostics::{CodeSpan, Diagnostic};
 
 use task_maker_lang::GraderMap;
 
-use crate::{EvaluationData, SourceFile};
+use crate::{SourceFile, UISender};
 
 /// A solution to evaluate. This includes the source file and some additional metadata.
 #[derive(Clone, Debug)]
@@ -24,14 +24,16 @@ pub struct Solution {
 }
 
 impl Solution {
-    /// Create a new [`Solution`] for a given source file.
+    /// Create a new [`Solution`] for a given source file. The diagnostics about its checks are sent
+    /// to `sender`.
     ///
     /// Returns `None` if the language is unknown.
     pub fn new(
         path: &Path,
         base_dir: &Path,
         grader_map: Option<Arc<GraderMap>>,
-        eval: &mut EvaluationData,
+        task_root: &Path,
+        sender: &dyn UISender,
     ) -> Option<Self> {
         let write_to = base_dir
             .join("bin")
@@ -46,7 +48,7 @@ impl Solution {
         )?;
         Some(Self {
             source_file: Arc::new(source_file),
-            checks: SolutionCheck::extract_check_list(path, eval).ok()?,
+            checks: SolutionCheck::extract_check_list(path, task_root, sender).ok()?,
         })
     }
 }
@@ -287,10 +289,12 @@ impl SolutionCheckResult {
 }
 
 impl SolutionCheck {
-    /// Try to extract the list of [`SolutionCheck`] from a file.
+    /// Try to extract the list of [`SolutionCheck`] from a file. The paths in the diagnostics sent to
+    /// `sender` are relative to `task_root`.
     pub fn extract_check_list<P: AsRef<Path>>(
         path: P,
-        eval: &mut EvaluationData,
+        task_root: &Path,
+        sender: &dyn UISender,
     ) -> Result<Vec<Self>, Error> {
         lazy_static! {
             static ref FIND_CHECKS: Regex = Regex::new(r".*(@check-.*)").expect("Invalid regex");
@@ -324,7 +328,7 @@ impl SolutionCheck {
                 Some(found) => found,
             };
             let captures = EXTRACT_CHECKS.captures_iter(line).next();
-            let path = path.strip_prefix(&eval.task_root).unwrap_or(path);
+            let path = path.strip_prefix(task_root).unwrap_or(path);
             // file_offset includes the current line length.
             let offset = file_offset - 1 - line.len() + found.start();
 
@@ -349,7 +353,7 @@ impl SolutionCheck {
                 if let Ok(span) = CodeSpan::from_str(path, &content, offset, len) {
                     diagnostic = diagnostic.with_code_span(span);
                 }
-                let _ = eval.add_diagnostic(diagnostic);
+                let _ = sender.add_diagnostic(diagnostic);
             }
         }
         Ok(checks)
@@ -378,8 +382,8 @@ mod tests {
         let tmpdir = tempfile::TempDir::new().unwrap();
         let path = tmpdir.path().join("source.txt");
         std::fs::write(&path, source).unwrap();
-        let mut eval = EvaluationData::new(tmpdir.path()).0;
-        SolutionCheck::extract_check_list(path, &mut eval)
+        let eval = EvaluationData::new(tmpdir.path()).0;
+        SolutionCheck::extract_check_list(path, &eval.task_root, eval.sender.as_ref())
     }
 
     #[test]
@@ -1,6 +1,6 @@
 use std::ops::{Deref, DerefMut};
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use anyhow::{Context, Error};
 use serde::{Deserialize, Serialize};
@@ -21,6 +21,13 @@ pub struct SourceFile {
     #[serde(flatten)]
     base: task_maker_lang::SourceFile,
     description: String,
+    /// The data files read by the program, provided to each of its executions: their local path
+    /// and their path inside the sandbox.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    data_files: Vec<(PathBuf, PathBuf)>,
+    /// The handles of the data files, which are provided to the DAG only once.
+    #[serde(skip)]
+    data_file_handles: Arc<Mutex<Option<Vec<File>>>>,
 }
 
 impl SourceFile {
@@ -36,9 +43,21 @@ impl SourceFile {
         Some(SourceFile {
             base: task_maker_lang::SourceFile::new(path, base_path, grader_map, write_bin_to)?,
             description: description.into(),
+            data_files: vec![],
+            data_file_handles: Default::default(),
         })
     }
 
+    /// Provide the file at `path` to every execution of the source file, at `sandbox_path` inside
+    /// the sandbox.
+    pub fn add_data_file<P: Into<PathBuf>, P2: Into<PathBuf>>(
+        &mut self,
+        path: P,
+        sandbox_path: P2,
+    ) {
+        self.data_files.push((path.into(), sandbox_path.into()));
+    }
+
     /// Prepare an execution of the source file, eventually adding the compilation to the DAG.
     /// The compilation messages are sent to the UI.
     ///
@@ -53,12 +72,13 @@ impl SourceFile {
         description: S,
         args: I,
     ) -> Result<Execution, Error> {
-        let (comp, exec) = self.base.execute(
+        let (comp, mut exec) = self.base.execute(
             &mut eval.dag,
             description,
             args.into_iter().map(|s| s.into()).collect(),
         )?;
         self.bind_compilation_exe(eval, comp)?;
+        self.bind_data_files(eval, &mut exec)?;
         Ok(exec)
     }
 
@@ -77,6 +97,37 @@ impl SourceFile {
         Ok(exe)
     }
 
+    /// Add the data files to the inputs of the execution, providing them the first time.
+    fn bind_data_files(
+        &self,
+        eval: &mut EvaluationData,
+        exec: &mut Execution,
+    ) -> Result<(), Error> {
+        let mut handles = self.data_file_handles.lock().unwrap();
+        if handles.is_none() {
+            let mut files = vec![];
+            for (path, _) in &self.data_files {
+                let file = File::new(format!(
+                    "Data file {} of {}",
+                    path.display(),
+                    self.path.display()
+                ));
+                eval.dag
+                    .provide_file(file.clone(), path)
+                    .with_context(|| format!("Failed to provide data file {}", path.display()))?;
+                files.push(file);
+            }
+            *handles = Some(files);
+        }
+        let files = handles
+            .as_ref()
+            .expect("The data files have just been provided");
+        for (file, (_, sandbox_path)) in files.iter().zip(&self.data_files) {
+            exec.input(file, sandbox_path, false);
+        }
+        Ok(())
+    }
+
     /// Bind the callbacks for the compilation callbacks.
     fn bind_compilation_exe(
         &self,
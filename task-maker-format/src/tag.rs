@@ -24,6 +24,8 @@ pub enum Tag {
     Checking,
     /// Compilation of the booklet.
     Booklet,
+    /// Repetition of an execution to verify that its outputs are deterministic, never cached.
+    Determinism,
 }
 
 impl From<Tag> for ExecutionTag {
@@ -33,6 +35,7 @@ impl From<Tag> for ExecutionTag {
             Tag::Evaluation => ExecutionTag::from("evaluation"),
             Tag::Checking => ExecutionTag::from("checking"),
             Tag::Booklet => ExecutionTag::from("booklet"),
+            Tag::Determinism => ExecutionTag::from("determinism"),
         }
     }
 }
@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
@@ -60,6 +60,15 @@ impl TaskFormat {
         }
     }
 
+    /// The files and the directories inside the task folder that can be generated automatically,
+    /// and that are removed by [`clean`](#method.clean).
+    pub fn artifacts(&self) -> Result<Vec<PathBuf>, Error> {
+        match self {
+            TaskFormat::IOI(task) => task.artifacts(),
+            TaskFormat::Terry(task) => task.artifacts(),
+        }
+    }
+
     /// Clean the task folder removing the files that can be generated automatically.
     pub fn clean(&self) -> Result<(), Error> {
         match self {
@@ -99,6 +99,7 @@ fn draw_frame(state: &UIState, f: &mut Frame, loading: char, frame_index: usize)
         f,
         chunks[3],
         state.executor_status.as_ref(),
+        state.file_transfer.as_ref(),
         loading,
         frame_index,
     );
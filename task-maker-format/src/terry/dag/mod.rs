@@ -20,14 +20,14 @@ const SOLUTION_TIME_LIMIT: f64 = 20.0;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InputGenerator {
     /// The source file of the generator executable.
-    source: Arc<SourceFile>,
+    pub(crate) source: Arc<SourceFile>,
 }
 
 /// The validator of the input files.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InputValidator {
     /// The source file of the validator executable.
-    source: Arc<SourceFile>,
+    pub(crate) source: Arc<SourceFile>,
 }
 
 /// A solution to test.
@@ -38,7 +38,7 @@ pub struct Solution;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Checker {
     /// The source file of the checker executable.
-    source: Arc<SourceFile>,
+    pub(crate) source: Arc<SourceFile>,
 }
 
 impl InputGenerator {
@@ -48,17 +48,21 @@ impl InputGenerator {
     }
 
     /// Build the execution for the generation of the input file, but don't bind the execution
-    /// callbacks.
+    /// callbacks. If `cases` is provided, the generator is asked for that number of test cases with
+    /// its third argument.
     pub(crate) fn generate(
         &self,
         eval: &mut EvaluationData,
         description: String,
         seed: Seed,
+        cases: Option<usize>,
         official_solution: Option<Arc<SourceFile>>,
     ) -> Result<(FileUuid, Execution), Error> {
-        let mut exec =
-            self.source
-                .execute(eval, description, vec![seed.to_string(), "0".to_string()])?;
+        let mut args = vec![seed.to_string(), "0".to_string()];
+        if let Some(cases) = cases {
+            args.push(cases.to_string());
+        }
+        let mut exec = self.source.execute(eval, description, args)?;
         include_official_solution(eval, &mut exec, official_solution)?;
         exec.limits_mut().allow_multiprocess();
         exec.tag(Tag::Generation.into());
@@ -72,6 +76,7 @@ impl InputGenerator {
         eval: &mut EvaluationData,
         solution: &SourceFile,
         seed: Seed,
+        cases: Option<usize>,
         official_solution: Option<Arc<SourceFile>>,
     ) -> Result<FileUuid, Error> {
         let (input, mut gen) = self.generate(
@@ -82,6 +87,7 @@ impl InputGenerator {
                 seed
             ),
             seed,
+            cases,
             official_solution,
         )?;
         if eval.dag.config_mut().copy_exe {
@@ -177,6 +183,7 @@ impl Solution {
         )?;
         exec.stdin(input);
         exec.tag(Tag::Evaluation.into());
+        exec.repeated(true);
         if let Some(validation) = validation_handle {
             exec.input(validation, "wait_for_validation", false);
         }
@@ -233,7 +240,7 @@ impl Checker {
         callback: F,
     ) -> Result<Execution, Error>
     where
-        F: FnOnce(Result<SolutionOutcome, Error>) -> Result<(), Error> + 'static,
+        F: FnOnce(Result<SolutionOutcome, Error>) -> Result<(), Error> + Send + 'static,
     {
         let mut exec = self
             .source
@@ -263,7 +270,7 @@ impl Checker {
         callback: F,
     ) -> Result<(), Error>
     where
-        F: FnOnce(Result<SolutionOutcome, Error>) -> Result<(), Error> + 'static,
+        F: FnOnce(Result<SolutionOutcome, Error>) -> Result<(), Error> + Send + 'static,
     {
         let mut exec = self.check(
             eval,
@@ -0,0 +1,228 @@
+//! The HTML pages with the feedback of the solutions of a Terry task, showing what the contestants
+//! see on the web interface of the contest after submitting the same output.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use askama::Template;
+
+use crate::terry::ui_state::{SolutionState, SolutionStatus, UIState};
+use crate::terry::{CaseStatus, SolutionAlert};
+
+/// Template to use to render the feedback page of a solution.
+#[derive(Template)]
+#[template(path = "feedback.html", syntax = "default")]
+struct FeedbackTemplate {
+    /// The title of the task.
+    task: String,
+    /// The file name of the solution.
+    solution: String,
+    /// The seed of the input file, empty if unknown.
+    seed: String,
+    /// Why the solution has no outcome, empty if it has one.
+    error: String,
+    /// The score of the solution, out of the maximum score of the task.
+    score: String,
+    /// The alerts of the checker about the validation of the output.
+    validation_alerts: Vec<SolutionAlert>,
+    /// The alerts of the checker about the feedback of the output.
+    feedback_alerts: Vec<SolutionAlert>,
+    /// The outcome of each test case.
+    cases: Vec<FeedbackCase>,
+}
+
+/// The outcome of a test case in the feedback page.
+struct FeedbackCase {
+    /// The 1-based index of the test case.
+    index: usize,
+    /// The status of the validation of the test case.
+    status: &'static str,
+    /// Whether the test case is correct or wrong.
+    outcome: &'static str,
+    /// The messages of the validation and of the feedback of the test case.
+    message: String,
+}
+
+/// Write in `dir` a feedback page for each solution, named after the file of the solution with
+/// `.html` appended. Returns the paths of the pages written.
+pub fn write_feedback_pages(state: &UIState, dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+    let mut pages = vec![];
+    for (path, solution) in &state.solutions {
+        let name = path
+            .file_name()
+            .context("Invalid solution file name")?
+            .to_string_lossy()
+            .to_string();
+        let page = feedback_page(state, &name, solution)?;
+        let target = dir.join(format!("{}.html", name));
+        std::fs::write(&target, page)
+            .with_context(|| format!("Failed to write {}", target.display()))?;
+        pages.push(target);
+    }
+    pages.sort();
+    Ok(pages)
+}
+
+/// Render the feedback page of a solution.
+fn feedback_page(state: &UIState, name: &str, solution: &SolutionState) -> Result<String, Error> {
+    let mut template = FeedbackTemplate {
+        task: state.task.description.clone(),
+        solution: name.to_string(),
+        seed: solution.seed.map(|s| s.to_string()).unwrap_or_default(),
+        error: String::new(),
+        score: String::new(),
+        validation_alerts: vec![],
+        feedback_alerts: vec![],
+        cases: vec![],
+    };
+    match &solution.outcome {
+        Some(Ok(outcome)) => {
+            template.score = format!(
+                "{:.2} / {:.2}",
+                outcome.score * state.task.max_score,
+                state.task.max_score
+            );
+            template.validation_alerts = outcome.validation.alerts.clone();
+            template.feedback_alerts = outcome.feedback.alerts.clone();
+            template.cases = outcome
+                .validation
+                .cases
+                .iter()
+                .zip(outcome.feedback.cases.iter())
+                .enumerate()
+                .map(|(index, (validation, feedback))| FeedbackCase {
+                    index: index + 1,
+                    status: match validation.status {
+                        CaseStatus::Missing => "missing",
+                        CaseStatus::Parsed => "parsed",
+                        CaseStatus::Invalid => "invalid",
+                    },
+                    outcome: if feedback.correct { "correct" } else { "wrong" },
+                    message: [&validation.message, &feedback.message]
+                        .into_iter()
+                        .flatten()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()
+                        .join(" | "),
+                })
+                .collect();
+        }
+        Some(Err(e)) => template.error = format!("The checker failed: {}", e),
+        None => {
+            template.error = match &solution.status {
+                SolutionStatus::Failed(e) => e.clone(),
+                SolutionStatus::Skipped => "The evaluation has been skipped".to_string(),
+                _ => "The evaluation has not completed".to_string(),
+            }
+        }
+    }
+    template
+        .render()
+        .context("Failed to render the feedback page")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tempfile::TempDir;
+
+    use crate::terry::dag::{Checker, InputGenerator};
+    use crate::terry::{
+        SolutionFeedback, SolutionFeedbackCase, SolutionOutcome, SolutionValidation,
+        SolutionValidationCase, TerryTask,
+    };
+    use crate::SourceFile;
+
+    use super::*;
+
+    fn state() -> UIState {
+        let source = Arc::new(SourceFile::new("gen.py", "", "", None, None::<PathBuf>).unwrap());
+        let task = TerryTask {
+            path: "".into(),
+            name: "task".to_string(),
+            description: "The <task>".to_string(),
+            max_score: 10.0,
+            generator: InputGenerator::new(source.clone()),
+            validator: None,
+            checker: Checker::new(source),
+            official_solution: None,
+            statement: None,
+            sanity_checks: Default::default(),
+        };
+        UIState::new(&task)
+    }
+
+    #[test]
+    fn test_feedback_page() {
+        let state = state();
+        let mut solution = SolutionState::new();
+        solution.seed = Some(42);
+        solution.outcome = Some(Ok(SolutionOutcome {
+            score: 0.5,
+            validation: SolutionValidation {
+                cases: vec![
+                    SolutionValidationCase {
+                        status: CaseStatus::Parsed,
+                        message: None,
+                    },
+                    SolutionValidationCase {
+                        status: CaseStatus::Invalid,
+                        message: Some("Expected <int>".to_string()),
+                    },
+                ],
+                alerts: vec![SolutionAlert {
+                    severity: "warning".to_string(),
+                    message: "Too many lines".to_string(),
+                }],
+            },
+            feedback: SolutionFeedback {
+                cases: vec![
+                    SolutionFeedbackCase {
+                        correct: true,
+                        message: Some("Good".to_string()),
+                    },
+                    SolutionFeedbackCase {
+                        correct: false,
+                        message: None,
+                    },
+                ],
+                alerts: vec![],
+            },
+        }));
+        let page = feedback_page(&state, "sol.py", &solution).unwrap();
+        assert!(page.contains("The &lt;task&gt;"));
+        assert!(page.contains("Seed: 42"));
+        assert!(page.contains("5.00 / 10.00"));
+        assert!(page.contains("class=\"alert alert-warning\""));
+        assert!(page.contains("Expected &lt;int&gt;"));
+        assert!(page.contains("Good"));
+        assert!(page.contains("<td class=\"wrong\">wrong</td>"));
+    }
+
+    #[test]
+    fn test_feedback_page_failed() {
+        let state = state();
+        let mut solution = SolutionState::new();
+        solution.status = SolutionStatus::Failed("Solution failed".to_string());
+        let page = feedback_page(&state, "sol.py", &solution).unwrap();
+        assert!(page.contains("class=\"alert alert-danger\""));
+        assert!(page.contains("Solution failed"));
+        assert!(!page.contains("Score"));
+    }
+
+    #[test]
+    fn test_write_feedback_pages() {
+        let tmpdir = TempDir::new().unwrap();
+        let mut state = state();
+        state
+            .solutions
+            .insert(PathBuf::from("sol/sol.py"), SolutionState::new());
+        let dir = tmpdir.path().join("feedback");
+        let pages = write_feedback_pages(&state, &dir).unwrap();
+        assert_eq!(pages, vec![dir.join("sol.py.html")]);
+        assert!(pages[0].exists());
+    }
+}
@@ -1,13 +1,16 @@
 use std::path::Path;
 
 use itertools::Itertools;
-use termcolor::{ColorChoice, StandardStream};
+use termcolor::StandardStream;
 
 use task_maker_dag::{ExecutionResult, ExecutionStatus};
 
 use crate::terry::ui_state::{SolutionState, SolutionStatus, UIState};
 use crate::terry::CaseStatus;
-use crate::ui::{FinishUI as FinishUITrait, FinishUIUtils, BLUE, BOLD, GREEN, RED, YELLOW};
+use crate::ui::{
+    color_choice, FinishUI as FinishUITrait, FinishUIUtils, UIExecutionStatus, BLUE, BOLD, GREEN,
+    RED, YELLOW,
+};
 use crate::{cwrite, cwriteln};
 
 /// UI that prints to `stdout` the ending result of the evaluation of a IOI task.
@@ -19,15 +22,27 @@ pub struct FinishUI {
 impl FinishUITrait<UIState> for FinishUI {
     fn print(state: &UIState) {
         let mut ui = FinishUI {
-            stream: StandardStream::stdout(ColorChoice::Auto),
+            stream: StandardStream::stdout(color_choice()),
         };
 
         ui.print_task_info(state);
         println!();
         FinishUIUtils::new(&mut ui.stream).print_compilations(&state.compilations);
         println!();
+        if !state.statements.is_empty() {
+            ui.print_statements(state);
+            println!();
+        }
         ui.print_evaluations(state);
         ui.print_summary(state);
+        if state.cache_stats.total() > 0 {
+            println!();
+            FinishUIUtils::new(&mut ui.stream).print_cache_stats(&state.cache_stats);
+        }
+        if !state.timing_stability.affected_workers.is_empty() {
+            println!();
+            FinishUIUtils::new(&mut ui.stream).print_timing_stability(&state.timing_stability);
+        }
         println!();
         FinishUIUtils::new(&mut ui.stream).print_diagnostic_messages(&state.diagnostics);
     }
@@ -43,6 +58,24 @@ impl FinishUI {
         println!("{}", state.task.max_score);
     }
 
+    /// Print the status of the rendering of the statement.
+    fn print_statements(&mut self, state: &UIState) {
+        cwriteln!(self, BLUE, "Statement");
+        for name in state.statements.keys().sorted() {
+            cwrite!(self, BOLD, "{:<20}  ", name);
+            match &state.statements[name] {
+                UIExecutionStatus::Pending => print!("..."),
+                UIExecutionStatus::Skipped => print!("skipped"),
+                UIExecutionStatus::Started { .. } => cwrite!(self, YELLOW, "started"),
+                UIExecutionStatus::Done { result } => match &result.status {
+                    ExecutionStatus::Success => cwrite!(self, GREEN, "Success"),
+                    _ => cwrite!(self, RED, "{:?}", result.status),
+                },
+            }
+            println!();
+        }
+    }
+
     fn print_evaluations(&mut self, state: &UIState) {
         cwriteln!(self, BLUE, "Evaluations");
         for (path, solution) in state.solutions.iter().sorted_by_key(|(n, _)| *n) {
@@ -71,6 +104,26 @@ impl FinishUI {
             if let Some(seed) = solution.seed {
                 println!("      Seed: {}", seed);
             }
+            if let Some(stats) = solution.seed_statistics() {
+                let max_score = state.task.max_score;
+                println!(
+                    "     Seeds: {} | min {:.2} | mean {:.2} | max {:.2} | worst seed {}",
+                    stats.seeds,
+                    stats.min_score * max_score,
+                    stats.mean_score * max_score,
+                    stats.max_score * max_score,
+                    stats.worst_seed
+                );
+                print!("   Correct:");
+                for correct in &stats.correct_cases {
+                    if *correct == stats.seeds {
+                        cwrite!(self, GREEN, " {}/{}", correct, stats.seeds);
+                    } else {
+                        cwrite!(self, YELLOW, " {}/{}", correct, stats.seeds);
+                    }
+                }
+                println!();
+            }
 
             let print_result = |result: &Option<ExecutionResult>| {
                 if let Some(result) = &result {
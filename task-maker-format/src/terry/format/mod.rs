@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::terry::dag::{Checker, InputGenerator, InputValidator};
 use crate::terry::sanity_checks::get_sanity_checks;
-use crate::terry::TerryTask;
+use crate::terry::{TerryStatement, TerryTask};
 use crate::{find_source_file, EvaluationConfig, SourceFile, WriteBinTo};
 
 lazy_static! {
@@ -43,6 +43,12 @@ pub fn parse_task<P: AsRef<Path>>(
         .map(Checker::new)
         .ok_or_else(|| anyhow!("No checker found in managers/"))?;
     let official_solution = get_manager(task_dir, "solution")?;
+    let statement_path = task_dir.join("statement/statement.md");
+    let statement = if !eval_config.no_statement && statement_path.exists() {
+        Some(TerryStatement::new(statement_path))
+    } else {
+        None
+    };
 
     Ok(TerryTask {
         path: task_dir.into(),
@@ -53,6 +59,7 @@ pub fn parse_task<P: AsRef<Path>>(
         validator,
         checker,
         official_solution,
+        statement,
         sanity_checks: Arc::new(get_sanity_checks(
             &eval_config
                 .disabled_sanity_checks
@@ -3,11 +3,13 @@ use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use anyhow::Error;
+use anyhow::{Context, Error};
 use itertools::Itertools;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use typescript_definitions::TypeScriptify;
 
+pub use statement::TerryStatement;
 pub use task_info::*;
 use task_maker_dag::ExecutionDAGConfig;
 
@@ -18,13 +20,17 @@ use crate::terry::dag::{Checker, InputGenerator, InputValidator, Solution};
 use crate::terry::format::parse_task;
 use crate::terry::ui_state::UIState;
 use crate::ui::{JsonUI, PrintUI, RawUI, SilentUI, UIMessage, UIType, UI};
-use crate::{list_files, EvaluationConfig, EvaluationData, SourceFile, TaskInfo, UISender};
+use crate::{
+    list_files, remove_artifact, EvaluationConfig, EvaluationData, SourceFile, TaskInfo, UISender,
+};
 
 mod curses_ui;
 mod dag;
+pub mod feedback;
 pub(crate) mod finish_ui;
 mod format;
 pub(crate) mod sanity_checks;
+mod statement;
 pub(crate) mod task_info;
 pub(crate) mod ui_state;
 
@@ -56,6 +62,9 @@ pub struct TerryTask {
     /// the generation/validation/checking.
     #[serde(skip_serializing)]
     pub official_solution: Option<Arc<SourceFile>>,
+    /// The statement of this task to render, if any.
+    #[serde(skip_serializing)]
+    pub statement: Option<TerryStatement>,
     /// The sanity checks attached to this task. Wrapped in Arc since `SanityChecks` is not Clone.
     /// It's also not `Serialize` nor `Deserialize`, all the sanity checks will be lost on
     /// serialization.
@@ -151,6 +160,20 @@ impl TerryTask {
         &self.path
     }
 
+    /// The managers of the task, with their names (like `generator`). The official solution is
+    /// included, if present.
+    pub fn managers(&self) -> Vec<(&'static str, &Arc<SourceFile>)> {
+        let mut managers = vec![("generator", &self.generator.source)];
+        if let Some(validator) = &self.validator {
+            managers.push(("validator", &validator.source));
+        }
+        managers.push(("checker", &self.checker.source));
+        if let Some(solution) = &self.official_solution {
+            managers.push(("solution", solution));
+        }
+        managers
+    }
+
     /// Get an appropriate `UI` for this task.
     pub fn ui(&self, ui_type: &UIType, _config: ExecutionDAGConfig) -> Result<Box<dyn UI>, Error> {
         match ui_type {
@@ -178,48 +201,165 @@ impl TerryTask {
             solutions: solution_info,
         })?;
 
-        let solutions = eval.solutions.clone();
-        for solution in solutions {
-            let seed = if let Some(seed) = config.seed {
-                seed
-            } else {
-                fastrand::u64(0..(i32::MAX as u64))
-            };
-            let input_file = self.generator.generate_and_bind(
+        // The managers are shared by all the solutions, so they are compiled only once before
+        // building in parallel the parts of the DAG of the solutions.
+        if !eval.solutions.is_empty() {
+            self.generator.source.prepare(eval)?;
+            if let Some(validator) = &self.validator {
+                validator.source.prepare(eval)?;
+            }
+            self.checker.source.prepare(eval)?;
+            if let Some(official_solution) = &self.official_solution {
+                official_solution.prepare(eval)?;
+            }
+        }
+
+        let slices = eval
+            .solutions
+            .iter()
+            .map(|solution| {
+                let num_seeds = config.terry_seeds.unwrap_or(1).max(1) as u64;
+                let seeds = if let Some(seed) = config.seed {
+                    (seed..seed + num_seeds).collect_vec()
+                } else {
+                    (0..num_seeds)
+                        .map(|_| fastrand::u64(0..(i32::MAX as u64)))
+                        .collect_vec()
+                };
+                (solution.clone(), seeds, eval.extension())
+            })
+            .collect_vec()
+            .into_par_iter()
+            .map(|(solution, seeds, mut slice)| {
+                self.build_solution_dag(&mut slice, &solution, seeds[0], config.terry_cases)?;
+                for &seed in &seeds[1..] {
+                    self.build_seed_dag(&mut slice, &solution, seed, config.terry_cases)?;
+                }
+                Ok(slice)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        for slice in slices {
+            eval.merge_extension(slice);
+        }
+        if let Some(statement) = &self.statement {
+            statement
+                .build(eval)
+                .context("Failed to bind the rendering of the statement")?;
+        }
+        self.sanity_checks.pre_hook(self, eval)?;
+        Ok(())
+    }
+
+    /// Add to the DAG the generation, the validation and the checking of the input file of a
+    /// solution, generated with the provided seed and, if provided, number of test cases.
+    fn build_solution_dag(
+        &self,
+        eval: &mut EvaluationData,
+        solution: &crate::Solution,
+        seed: Seed,
+        cases: Option<usize>,
+    ) -> Result<(), Error> {
+        let input_file = self.generator.generate_and_bind(
+            eval,
+            &solution.source_file,
+            seed,
+            cases,
+            self.official_solution.clone(),
+        )?;
+        let validation_file = if let Some(validator) = self.validator.as_ref() {
+            Some(validator.validate_and_bind(
                 eval,
                 &solution.source_file,
-                seed,
+                input_file,
                 self.official_solution.clone(),
-            )?;
-            let validation_file = if let Some(validator) = self.validator.as_ref() {
-                Some(validator.validate_and_bind(
-                    eval,
-                    &solution.source_file,
-                    input_file,
-                    self.official_solution.clone(),
-                )?)
-            } else {
-                None
-            };
-            let output_file =
-                Solution::solve_and_bind(eval, &solution.source_file, input_file, validation_file)?;
-            let sender = eval.sender.clone();
-            let solution_path = solution.source_file.path.clone();
-            self.checker.check_and_bind(
+            )?)
+        } else {
+            None
+        };
+        let output_file =
+            Solution::solve_and_bind(eval, &solution.source_file, input_file, validation_file)?;
+        let sender = eval.sender.clone();
+        let solution_path = solution.source_file.path.clone();
+        self.checker.check_and_bind(
+            eval,
+            &solution.source_file,
+            input_file,
+            output_file,
+            self.official_solution.clone(),
+            move |outcome| {
+                sender.send(UIMessage::TerrySolutionOutcome {
+                    solution: solution_path,
+                    outcome: outcome.map_err(|e| format!("Invalid checker outcome: {}", e)),
+                })
+            },
+        )
+    }
+
+    /// Add to the DAG the evaluation of a solution on an additional input file, generated with the
+    /// provided seed. Only the outcome is sent to the UI, with `UIMessage::TerrySeedOutcome`.
+    fn build_seed_dag(
+        &self,
+        eval: &mut EvaluationData,
+        solution: &crate::Solution,
+        seed: Seed,
+        cases: Option<usize>,
+    ) -> Result<(), Error> {
+        let name = solution.source_file.name();
+        let (input_file, gen) = self.generator.generate(
+            eval,
+            format!("Generation of input file for {} with seed {}", name, seed),
+            seed,
+            cases,
+            self.official_solution.clone(),
+        )?;
+        let mut steps = vec![("Generator", gen)];
+        let validation_file = if let Some(validator) = self.validator.as_ref() {
+            let (handle, val) = validator.validate(
                 eval,
-                &solution.source_file,
+                format!("Validation of input file for {} with seed {}", name, seed),
                 input_file,
-                output_file,
                 self.official_solution.clone(),
-                move |outcome| {
-                    sender.send(UIMessage::TerrySolutionOutcome {
-                        solution: solution_path,
-                        outcome: outcome.map_err(|e| format!("Invalid checker outcome: {}", e)),
-                    })
-                },
             )?;
+            steps.push(("Validator", val));
+            Some(handle)
+        } else {
+            None
+        };
+        let (output_file, sol) =
+            Solution::solve(eval, &solution.source_file, input_file, validation_file)?;
+        steps.push(("Solution", sol));
+        for (step, exec) in steps {
+            let sender = eval.sender.clone();
+            let solution_path = solution.source_file.path.clone();
+            eval.dag.on_execution_done(&exec.uuid, move |res| {
+                if !res.status.is_success() {
+                    sender.send(UIMessage::TerrySeedOutcome {
+                        solution: solution_path,
+                        seed,
+                        outcome: Err(format!("{} failed", step)),
+                    })?;
+                }
+                Ok(())
+            });
+            eval.dag.add_execution(exec);
         }
-        self.sanity_checks.pre_hook(self, eval)?;
+        let sender = eval.sender.clone();
+        let solution_path = solution.source_file.path.clone();
+        let checker = self.checker.check(
+            eval,
+            format!("Checking output of {} with seed {}", name, seed),
+            input_file,
+            output_file,
+            self.official_solution.clone(),
+            move |outcome| {
+                sender.send(UIMessage::TerrySeedOutcome {
+                    solution: solution_path,
+                    seed,
+                    outcome: outcome.map_err(|e| format!("Invalid checker outcome: {}", e)),
+                })
+            },
+        )?;
+        eval.dag.add_execution(checker);
         Ok(())
     }
 
@@ -229,8 +369,10 @@ impl TerryTask {
         self.sanity_checks.post_hook(self, eval)
     }
 
-    /// Clean the task folder removing the files that can be generated automatically.
-    pub fn clean(&self) -> Result<(), Error> {
+    /// The files and the directories inside the task folder that can be generated automatically,
+    /// and that are removed by [`clean`](#method.clean).
+    pub fn artifacts(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut artifacts = vec![];
         let all_managers: HashSet<PathBuf> = list_files(&self.path, vec!["managers/*.*"])
             .iter()
             .map(|f| f.file_stem().unwrap().into())
@@ -243,15 +385,21 @@ impl TerryTask {
             //   maybe_generated == "validator.linux.x86_64"
             //   name == "validator"
             if all_managers.contains(name) {
-                info!("Removing {}", maybe_generated.display());
-                std::fs::remove_file(maybe_generated)?;
+                artifacts.push(maybe_generated);
             }
         }
-        // remove the bin/ folder
+        // the bin/ folder
         let bin_path = self.path.join("bin");
         if bin_path.exists() {
-            info!("Removing {}", bin_path.display());
-            std::fs::remove_dir_all(bin_path)?;
+            artifacts.push(bin_path);
+        }
+        Ok(artifacts)
+    }
+
+    /// Clean the task folder removing the files that can be generated automatically.
+    pub fn clean(&self) -> Result<(), Error> {
+        for artifact in self.artifacts()? {
+            remove_artifact(&artifact)?;
         }
         Ok(())
     }
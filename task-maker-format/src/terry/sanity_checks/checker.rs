@@ -41,6 +41,7 @@ impl SanityCheck for FuzzChecker {
             eval,
             "Generation of input for FuzzChecker".into(),
             seed,
+            None,
             task.official_solution.clone(),
         )?;
         gen.capture_stderr(1024);
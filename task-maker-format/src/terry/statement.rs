@@ -0,0 +1,238 @@
+//! The statement of a Terry task. It's written in Markdown and rendered by the contest server, the
+//! local build renders it with pandoc to preview it and to find the links to missing files.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use task_maker_dag::{Execution, ExecutionCommand, File};
+use task_maker_diagnostics::Diagnostic;
+
+use crate::{bind_exec_callbacks, list_files, ui::UIMessage, EvaluationData, Tag, UISender};
+
+/// The statement of a Terry task, with the files it uses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerryStatement {
+    /// The path of the `statement.md` file.
+    pub path: PathBuf,
+}
+
+/// A link found in the Markdown source of the statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StatementLink {
+    /// Whether the link is an image embedded in the statement.
+    is_image: bool,
+    /// The target of the link, as written in the statement.
+    target: String,
+}
+
+impl TerryStatement {
+    /// Make a new `TerryStatement` from the path of its `statement.md` file.
+    pub fn new<P: Into<PathBuf>>(path: P) -> TerryStatement {
+        TerryStatement { path: path.into() }
+    }
+
+    /// Check the links of the statement and add to the DAG its rendering to HTML and to PDF,
+    /// written in `bin/statement/` of the task.
+    pub fn build(&self, eval: &mut EvaluationData) -> Result<(), Error> {
+        let statement_dir = self
+            .path
+            .parent()
+            .context("Invalid statement path")?
+            .to_owned();
+        let content = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        self.check_links(eval, &statement_dir, &content)?;
+
+        // the statement may include images and other files of its directory
+        let mut files = vec![];
+        for path in list_files(&statement_dir, vec!["**/*"]) {
+            if !path.is_file() {
+                continue;
+            }
+            let file = File::new(format!("Statement file {}", path.display()));
+            eval.dag
+                .provide_file(file.clone(), &path)
+                .context("Failed to provide statement file")?;
+            files.push((file, path.strip_prefix(&statement_dir)?.to_owned()));
+        }
+
+        for extension in ["html", "pdf"] {
+            let name = format!("statement.{}", extension);
+            let mut exec = Execution::new(
+                format!("Rendering of the statement to {}", extension.to_uppercase()),
+                ExecutionCommand::system("pandoc"),
+            );
+            let mut args = vec!["statement.md", "--standalone", "-o", name.as_str()];
+            if extension == "html" {
+                args.push("--embed-resources");
+            }
+            exec.args(args);
+            exec.limits_mut()
+                .read_only(false)
+                .allow_multiprocess()
+                .add_extra_readable_dir("/etc")
+                .mount_tmpfs(true);
+            exec.tag(Tag::Booklet.into());
+            exec.background(true);
+            let output = exec.output(&name);
+            let dest = eval.task_root.join("bin/statement").join(&name);
+            for (file, path) in &files {
+                exec.input(file, path, false);
+            }
+
+            bind_exec_callbacks!(
+                eval,
+                exec.uuid,
+                |status, name| UIMessage::TerryStatement { name, status },
+                name
+            )?;
+            let sender = eval.sender.clone();
+            exec.capture_stderr(1024 * 1024);
+            eval.dag.on_execution_done(&exec.uuid, move |res| {
+                if let Some(content) = &res.stderr {
+                    let warnings = String::from_utf8_lossy(content)
+                        .lines()
+                        .filter(|line| line.starts_with("[WARNING]"))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    if !warnings.is_empty() {
+                        sender.add_diagnostic(
+                            Diagnostic::warning(format!(
+                                "Found pandoc warnings while rendering {}",
+                                name
+                            ))
+                            .with_note(warnings),
+                        )?;
+                    }
+                }
+                Ok(())
+            });
+            eval.dag.add_execution(exec);
+            // pandoc may fail to render the PDF but still produce the HTML file
+            eval.dag.write_file_to_allow_fail(output, dest, false);
+        }
+        Ok(())
+    }
+
+    /// Emit an error for each image of the statement that doesn't exist, and a warning for each
+    /// link to a file that doesn't exist.
+    fn check_links(
+        &self,
+        eval: &mut EvaluationData,
+        statement_dir: &Path,
+        content: &str,
+    ) -> Result<(), Error> {
+        for link in find_links(content) {
+            if is_external(&link.target) {
+                continue;
+            }
+            let target = link.target.split(['#', '?']).next().unwrap_or_default();
+            if target.is_empty() || statement_dir.join(target).exists() {
+                continue;
+            }
+            let diagnostic = if link.is_image {
+                Diagnostic::error(format!(
+                    "The statement includes the missing image {}",
+                    target
+                ))
+            } else {
+                Diagnostic::warning(format!("The statement links the missing file {}", target))
+            };
+            eval.add_diagnostic(diagnostic.with_note(format!(
+                "The path is relative to {}",
+                statement_dir.display()
+            )))?;
+        }
+        Ok(())
+    }
+}
+
+/// Find the links and the images in the Markdown source of a statement, including the images
+/// written as HTML tags.
+fn find_links(content: &str) -> Vec<StatementLink> {
+    lazy_static! {
+        static ref MARKDOWN_LINK: Regex =
+            Regex::new(r#"(!?)\[[^\]]*\]\(\s*<?([^)\s>]+)>?(?:\s+"[^"]*")?\s*\)"#)
+                .expect("Invalid regex");
+        static ref HTML_IMAGE: Regex =
+            Regex::new(r#"<img\s[^>]*src\s*=\s*["']([^"']+)["']"#).expect("Invalid regex");
+    }
+    let mut links: Vec<_> = MARKDOWN_LINK
+        .captures_iter(content)
+        .map(|cap| StatementLink {
+            is_image: !cap[1].is_empty(),
+            target: cap[2].to_string(),
+        })
+        .collect();
+    links.extend(HTML_IMAGE.captures_iter(content).map(|cap| StatementLink {
+        is_image: true,
+        target: cap[1].to_string(),
+    }));
+    links
+}
+
+/// Whether the target of a link is outside of the task, like a web page or an anchor of the
+/// statement itself.
+fn is_external(target: &str) -> bool {
+    target.starts_with('#') || target.starts_with("mailto:") || target.contains("://")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_links() {
+        let content = r#"
+See [the rules](https://example.com/rules) and [the sample](sample.txt "Sample").
+![A tree](images/tree.png) <img src="graph.svg" width="50%">
+Jump to [the notes](#notes).
+"#;
+        let links = find_links(content);
+        let targets: Vec<_> = links
+            .iter()
+            .map(|link| (link.is_image, link.target.as_str()))
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                (false, "https://example.com/rules"),
+                (false, "sample.txt"),
+                (true, "images/tree.png"),
+                (false, "#notes"),
+                (true, "graph.svg"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_links() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let statement_dir = tmpdir.path().join("statement");
+        std::fs::create_dir(&statement_dir).unwrap();
+        std::fs::write(statement_dir.join("tree.png"), "").unwrap();
+        let content = "![tree](tree.png) ![graph](graph.png) [sample](sample.txt)";
+        std::fs::write(statement_dir.join("statement.md"), content).unwrap();
+
+        let (mut eval, receiver) = EvaluationData::new(tmpdir.path());
+        let statement = TerryStatement::new(statement_dir.join("statement.md"));
+        statement.build(&mut eval).unwrap();
+        let diagnostics: Vec<_> = receiver
+            .try_iter()
+            .filter_map(|message| match message {
+                UIMessage::Diagnostic { diagnostic } => Some(diagnostic.message().to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                "The statement includes the missing image graph.png",
+                "The statement links the missing file sample.txt",
+            ]
+        );
+    }
+}
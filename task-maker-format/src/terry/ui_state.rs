@@ -1,14 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::time::SystemTime;
 
 use task_maker_dag::{ExecutionResult, ExecutionStatus};
 use task_maker_diagnostics::DiagnosticContext;
-use task_maker_exec::ExecutorStatus;
+use task_maker_exec::{ExecutorStatus, FileTransfer};
 
 use crate::terry::finish_ui;
 use crate::terry::{Seed, SolutionOutcome, TerryTask};
-use crate::ui::{CompilationStatus, FinishUI, UIExecutionStatus, UIMessage, UIStateT};
+use crate::ui::{
+    CacheStats, CompilationStatus, FinishUI, TimingStability, UIExecutionStatus, UIMessage,
+    UIStateT,
+};
 
 /// The state of a Terry task, all the information for the UI are stored here.
 #[derive(Debug, Clone)]
@@ -19,10 +22,18 @@ pub struct UIState {
     pub compilations: HashMap<PathBuf, CompilationStatus>,
     /// The state of the solutions known.
     pub solutions: HashMap<PathBuf, SolutionState>,
+    /// The status of the rendering of the statement, by the name of the rendered file.
+    pub statements: HashMap<String, UIExecutionStatus>,
     /// The status of the executor.
     pub executor_status: Option<ExecutorStatus<SystemTime>>,
+    /// The upload of a file to the executor in progress, if any.
+    pub file_transfer: Option<FileTransfer>,
     /// Diagnostics context.
     pub diagnostics: DiagnosticContext,
+    /// How many executions were served from the cache.
+    pub cache_stats: CacheStats,
+    /// The executions that ran on workers with unreliable timings.
+    pub timing_stability: TimingStability,
 }
 
 /// The state of the evaluation of a solution.
@@ -34,6 +45,9 @@ pub struct SolutionState {
     pub outcome: Option<Result<SolutionOutcome, String>>,
     /// The seed used for the generation.
     pub seed: Option<Seed>,
+    /// The outcomes on the additional input files, by the seed used to generate them, when
+    /// evaluating with more than one seed.
+    pub seed_outcomes: BTreeMap<Seed, Result<SolutionOutcome, String>>,
     /// The result of the generator.
     pub generator_result: Option<ExecutionResult>,
     /// The result of the validator.
@@ -44,6 +58,23 @@ pub struct SolutionState {
     pub checker_result: Option<ExecutionResult>,
 }
 
+/// The aggregate outcomes of a solution evaluated with more than one seed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeedStatistics {
+    /// The number of seeds the solution has been evaluated with.
+    pub seeds: usize,
+    /// The lowest score, from 0.0 to 1.0. A failed evaluation scores zero.
+    pub min_score: f64,
+    /// The mean score, from 0.0 to 1.0.
+    pub mean_score: f64,
+    /// The highest score, from 0.0 to 1.0.
+    pub max_score: f64,
+    /// The seed of the input file with the lowest score.
+    pub worst_seed: Seed,
+    /// For each test case, on how many input files the solution got it correct.
+    pub correct_cases: Vec<usize>,
+}
+
 /// The status of the evaluation of a solution.
 #[derive(Debug, Clone, Default)]
 pub enum SolutionStatus {
@@ -73,17 +104,70 @@ pub enum SolutionStatus {
 }
 
 impl SolutionState {
-    fn new() -> Self {
+    /// Make the state of a solution whose evaluation has not started yet.
+    pub(crate) fn new() -> Self {
         Self {
             status: Default::default(),
             outcome: Default::default(),
             seed: Default::default(),
+            seed_outcomes: Default::default(),
             generator_result: Default::default(),
             validator_result: Default::default(),
             solution_result: Default::default(),
             checker_result: Default::default(),
         }
     }
+
+    /// The aggregate outcomes of the solution on all its input files, `None` if it has been
+    /// evaluated with a single seed.
+    pub fn seed_statistics(&self) -> Option<SeedStatistics> {
+        if self.seed_outcomes.is_empty() {
+            return None;
+        }
+        let main_outcome = self
+            .outcome
+            .as_ref()
+            .and_then(|outcome| outcome.as_ref().ok());
+        let outcomes = self
+            .seed
+            .map(|seed| (seed, main_outcome))
+            .into_iter()
+            .chain(
+                self.seed_outcomes
+                    .iter()
+                    .map(|(seed, outcome)| (*seed, outcome.as_ref().ok())),
+            );
+        let mut scores = vec![];
+        let mut correct_cases: Vec<usize> = vec![];
+        for (seed, outcome) in outcomes {
+            let score = match outcome {
+                Some(outcome) => {
+                    let cases = &outcome.feedback.cases;
+                    if correct_cases.len() < cases.len() {
+                        correct_cases.resize(cases.len(), 0);
+                    }
+                    for (count, case) in correct_cases.iter_mut().zip(cases) {
+                        *count += usize::from(case.correct);
+                    }
+                    outcome.score
+                }
+                None => 0.0,
+            };
+            scores.push((seed, score));
+        }
+        let (worst_seed, min_score) = scores
+            .iter()
+            .copied()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        Some(SeedStatistics {
+            seeds: scores.len(),
+            min_score,
+            mean_score: scores.iter().map(|(_, score)| score).sum::<f64>() / scores.len() as f64,
+            max_score: scores.iter().map(|(_, score)| *score).fold(0.0, f64::max),
+            worst_seed,
+            correct_cases,
+        })
+    }
 }
 
 impl UIState {
@@ -93,8 +177,12 @@ impl UIState {
             task: task.clone(),
             compilations: HashMap::new(),
             solutions: HashMap::new(),
+            statements: HashMap::new(),
             executor_status: None,
+            file_transfer: None,
             diagnostics: Default::default(),
+            cache_stats: Default::default(),
+            timing_stability: Default::default(),
         }
     }
 }
@@ -130,9 +218,14 @@ impl UIStateT for UIState {
             }};
         }
 
+        self.cache_stats.apply(&message);
+        self.timing_stability.apply(&message);
         match message {
             UIMessage::StopUI => {}
             UIMessage::ServerStatus { status } => self.executor_status = Some(status),
+            UIMessage::FileTransfer { transfer } => {
+                self.file_transfer = (transfer.sent < transfer.total).then_some(transfer)
+            }
             UIMessage::Solutions { solutions } => {
                 self.solutions = solutions
                     .into_iter()
@@ -201,20 +294,41 @@ impl UIStateT for UIState {
                     .expect("Outcome of an unknown solution");
                 sol.outcome = Some(outcome);
             }
+            UIMessage::TerrySeedOutcome {
+                solution,
+                seed,
+                outcome,
+            } => {
+                let sol = self
+                    .solutions
+                    .get_mut(&solution)
+                    .expect("Outcome of an unknown solution");
+                // a failed execution is reported before the outcome of the checker, if any
+                sol.seed_outcomes.entry(seed).or_insert(outcome);
+            }
+            UIMessage::TerryStatement { name, status } => {
+                self.statements.insert(name, status);
+            }
             UIMessage::Diagnostic { diagnostic } => {
                 self.diagnostics.add_diagnostic(diagnostic);
             }
             UIMessage::IOITask { .. }
             | UIMessage::IOIGeneration { .. }
             | UIMessage::IOIValidation { .. }
+            | UIMessage::IOIGenerationStderr { .. }
+            | UIMessage::IOIValidationStderr { .. }
             | UIMessage::IOISolution { .. }
             | UIMessage::IOIEvaluation { .. }
             | UIMessage::IOIChecker { .. }
+            | UIMessage::IOICheckerError { .. }
             | UIMessage::IOITestcaseScore { .. }
             | UIMessage::IOISubtaskScore { .. }
             | UIMessage::IOITaskScore { .. }
             | UIMessage::IOIBooklet { .. }
             | UIMessage::IOIBookletDependency { .. } => unreachable!("IOI message on Terry UI"),
+            UIMessage::FuzzBatch { .. }
+            | UIMessage::FuzzMinimization { .. }
+            | UIMessage::FuzzFailure { .. } => {}
         }
     }
 
@@ -222,3 +336,61 @@ impl UIStateT for UIState {
         finish_ui::FinishUI::print(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::terry::{
+        CaseStatus, SolutionFeedback, SolutionFeedbackCase, SolutionValidation,
+        SolutionValidationCase,
+    };
+
+    use super::*;
+
+    fn outcome(correct: &[bool]) -> SolutionOutcome {
+        SolutionOutcome {
+            score: correct.iter().filter(|c| **c).count() as f64 / correct.len() as f64,
+            validation: SolutionValidation {
+                cases: correct
+                    .iter()
+                    .map(|_| SolutionValidationCase {
+                        status: CaseStatus::Parsed,
+                        message: None,
+                    })
+                    .collect(),
+                alerts: vec![],
+            },
+            feedback: SolutionFeedback {
+                cases: correct
+                    .iter()
+                    .map(|correct| SolutionFeedbackCase {
+                        correct: *correct,
+                        message: None,
+                    })
+                    .collect(),
+                alerts: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn test_seed_statistics() {
+        let mut solution = SolutionState::new();
+        solution.seed = Some(1);
+        solution.outcome = Some(Ok(outcome(&[true, true])));
+        assert_eq!(solution.seed_statistics(), None);
+
+        solution
+            .seed_outcomes
+            .insert(2, Ok(outcome(&[true, false])));
+        solution
+            .seed_outcomes
+            .insert(3, Err("Solution failed".to_string()));
+        let stats = solution.seed_statistics().unwrap();
+        assert_eq!(stats.seeds, 3);
+        assert_abs_diff_eq!(stats.min_score, 0.0);
+        assert_abs_diff_eq!(stats.mean_score, 0.5);
+        assert_abs_diff_eq!(stats.max_score, 1.0);
+        assert_eq!(stats.worst_seed, 3);
+        assert_eq!(stats.correct_cases, vec![2, 1]);
+    }
+}
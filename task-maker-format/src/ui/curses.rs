@@ -23,7 +23,7 @@ use termion::input::{MouseTerminal, TermRead};
 use termion::raw::IntoRawMode;
 use termion::screen::IntoAlternateScreen;
 
-use task_maker_exec::{ExecutorStatus, ExecutorWorkerStatus};
+use task_maker_exec::{ExecutorStatus, ExecutorWorkerStatus, FileTransfer, WorkerTelemetry};
 
 use crate::ui::{CompilationStatus, FinishUI, UIMessage, UIStateT, UI};
 
@@ -31,6 +31,8 @@ use crate::ui::{CompilationStatus, FinishUI, UIMessage, UIStateT, UI};
 pub(crate) const FPS: u64 = 30;
 /// After how many seconds rotate the list of workers if they don't fit on the screen.
 pub(crate) const ROTATION_DELAY: u64 = 1;
+/// The width of the telemetry of a worker, as formatted by [`worker_telemetry_text`].
+const TELEMETRY_WIDTH: usize = 14;
 
 macro_rules! define_color_inner {
     ($color:expr,) => {
@@ -69,6 +71,10 @@ lazy_static! {
     pub static ref ORANGE: Style = define_color!(basic(Yellow), rgb(255, 165, 0), bold);
     /// Bold.
     pub static ref BOLD: Style = define_color!(bold);
+    /// Dimmed, patched over the colors of the results that come from the cache.
+    pub static ref CACHED: Style = Style::default()
+        .add_modifier(Modifier::DIM)
+        .remove_modifier(Modifier::BOLD);
 }
 
 /// A generic animated UI for tasks, dynamically refreshing using curses as a backend.
@@ -271,14 +277,30 @@ pub fn render_block<S: AsRef<str>>(frame: &mut Frame, rect: Rect, title: S) {
     frame.render_widget(block, rect);
 }
 
-/// Draw the server status block.
+/// Draw the server status block, with the progress of the upload in progress on its bottom border.
+/// If there is enough space, the telemetry of the workers is drawn in a panel on its right.
 pub fn render_server_status(
     frame: &mut Frame,
     rect: Rect,
     status: Option<&ExecutorStatus<SystemTime>>,
+    transfer: Option<&FileTransfer>,
     loading: char,
     frame_index: usize,
 ) {
+    let rotation_index = frame_index / FPS as usize / ROTATION_DELAY as usize;
+    let telemetry_width = status.map_or(0, telemetry_panel_width);
+    let rect = if telemetry_width > 0 && telemetry_width * 3 <= rect.width {
+        let telemetry_rect = Rect::new(
+            rect.x + rect.width - telemetry_width,
+            rect.y,
+            telemetry_width,
+            rect.height,
+        );
+        render_workers_telemetry(frame, telemetry_rect, status, rotation_index);
+        Rect::new(rect.x, rect.y, rect.width - telemetry_width, rect.height)
+    } else {
+        rect
+    };
     let title = " Server status ";
     render_block(frame, rect, title);
     draw_server_status_summary(
@@ -291,16 +313,97 @@ pub fn render_server_status(
         ),
         status,
     );
-    draw_server_status(
-        frame,
-        inner_block(rect),
-        status,
-        loading,
-        frame_index / FPS as usize / ROTATION_DELAY as usize,
-    );
+    draw_server_status(frame, inner_block(rect), status, loading, rotation_index);
+    if let Some(transfer) = transfer {
+        draw_file_transfer(
+            frame,
+            Rect::new(
+                rect.x + 2,
+                rect.y + rect.height.saturating_sub(1),
+                rect.width.saturating_sub(4),
+                1,
+            ),
+            transfer,
+        );
+    }
 }
 
-/// Draw the summary of the server status on the border of the block.
+/// The width of the panel with the telemetry of the workers, including its borders. It's zero if
+/// there are no workers.
+fn telemetry_panel_width(status: &ExecutorStatus<SystemTime>) -> u16 {
+    let max_len = status
+        .connected_workers
+        .iter()
+        .map(|worker| worker.name.chars().count())
+        .max();
+    match max_len {
+        // the name, a space and the telemetry, inside the borders
+        Some(max_len) => (max_len + 1 + TELEMETRY_WIDTH + 2) as u16,
+        None => 0,
+    }
+}
+
+/// Draw the panel with the load average and the free memory of the machines of the workers,
+/// rotating them as the list of the workers if they don't fit.
+fn render_workers_telemetry(
+    frame: &mut Frame,
+    rect: Rect,
+    status: Option<&ExecutorStatus<SystemTime>>,
+    mut rotation_index: usize,
+) {
+    render_block(frame, rect, " Load ");
+    let status = if let Some(status) = status {
+        status
+    } else {
+        return;
+    };
+    let rect = inner_block(rect);
+    let workers: Vec<_> = status
+        .connected_workers
+        .iter()
+        .sorted_by_key(|worker| &worker.name)
+        .collect();
+    if rect.height as usize >= workers.len() {
+        rotation_index = 0;
+    }
+    let max_len = workers
+        .iter()
+        .map(|worker| worker.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let num_workers = workers.len();
+    let text: Vec<Line> = workers
+        .into_iter()
+        .cycle()
+        .skip(rotation_index)
+        .take(num_workers.min(rect.height as usize))
+        .map(|worker| {
+            Line::from(vec![
+                Span::raw(format!("{:<max_len$} ", worker.name, max_len = max_len)),
+                Span::raw(worker_telemetry_text(&worker.telemetry)),
+            ])
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(text), rect);
+}
+
+/// Draw the progress bar of the upload of a file.
+fn draw_file_transfer(frame: &mut Frame, rect: Rect, transfer: &FileTransfer) {
+    const BAR_WIDTH: u64 = 20;
+    let filled = file_transfer_percent(transfer) * BAR_WIDTH / 100;
+    let paragraph = Paragraph::new(Line::from(vec![
+        Span::styled(" Uploading ", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("["),
+        Span::styled("#".repeat(filled as usize), *BLUE),
+        Span::raw(" ".repeat((BAR_WIDTH - filled) as usize)),
+        Span::raw(format!("] {} ", file_transfer_text(transfer))),
+    ]));
+    frame.render_widget(paragraph, rect);
+}
+
+/// Draw the summary of the server status on the border of the block. The cache hits are the ones
+/// of the whole server, not of the workers shown in the block: the cache is used before the jobs
+/// are assigned.
 fn draw_server_status_summary(
     frame: &mut Frame,
     rect: Rect,
@@ -315,7 +418,16 @@ fn draw_server_status_summary(
         Span::styled(" Ready ", Style::default().add_modifier(Modifier::BOLD)),
         Span::raw(format!("{} ─", status.ready_execs)),
         Span::styled(" Waiting ", Style::default().add_modifier(Modifier::BOLD)),
-        Span::raw(format!("{} ", status.waiting_execs)),
+        Span::raw(format!("{} ─", status.waiting_execs)),
+        Span::styled(
+            " Global cache hits ",
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(
+            "{}/{} ",
+            status.cache_hits,
+            status.cache_hits + status.cache_misses
+        )),
     ]));
     frame.render_widget(paragraph, rect);
 }
@@ -417,6 +529,38 @@ fn draw_workers_chunk(
     frame.render_widget(paragraph, rect);
 }
 
+/// Format the telemetry of a worker in a fixed-width string, showing the load average and the
+/// free memory of its machine.
+pub(crate) fn worker_telemetry_text(telemetry: &WorkerTelemetry) -> String {
+    let load = telemetry
+        .load_average
+        .map(|load| format!("{:.2}", load))
+        .unwrap_or_else(|| "-".into());
+    let memory = telemetry
+        .free_memory
+        .map(|mem| format!("{:.1}G", mem as f64 / 1024.0 / 1024.0))
+        .unwrap_or_else(|| "-".into());
+    format!("[{:>5} {:>6}]", load, memory)
+}
+
+/// Format the progress of the upload of a file, like `input.txt: 12.5/50.0 MiB (25%)`.
+pub(crate) fn file_transfer_text(transfer: &FileTransfer) -> String {
+    format!(
+        "{}: {:.1}/{:.1} MiB ({}%)",
+        transfer.file,
+        transfer.sent as f64 / 1024.0 / 1024.0,
+        transfer.total as f64 / 1024.0 / 1024.0,
+        file_transfer_percent(transfer)
+    )
+}
+
+/// The percentage of a file already uploaded.
+fn file_transfer_percent(transfer: &FileTransfer) -> u64 {
+    (transfer.sent.min(transfer.total) * 100)
+        .checked_div(transfer.total)
+        .unwrap_or(100)
+}
+
 /// Send to the current process `SIGINT`, letting it exit gracefully.
 fn send_ctrl_c() {
     let pid = std::process::id();
@@ -1,21 +1,125 @@
+use std::io::Write;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
 use crate::ui::*;
 
-/// This UI will print to stdout the UI messages as json.
-#[derive(Default)]
-pub struct JsonUI;
+/// Interval between two heartbeats of the `JsonUI`.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// An event emitted by the `JsonUI` that is not an `UIMessage`. It's serialized in the same way
+/// of the messages, so that a consumer can parse every line in the same way.
+#[derive(Debug, Serialize)]
+enum JsonUIEvent {
+    /// The UI is still alive, emitted periodically even when there are no new messages.
+    Heartbeat {
+        /// Number of seconds since the start of the UI.
+        elapsed: f64,
+        /// Number of messages emitted so far.
+        messages: usize,
+        /// Number of seconds since the last emitted message.
+        idle: f64,
+    },
+}
+
+/// The counters shared between the UI and its heartbeat thread.
+#[derive(Debug)]
+struct JsonUIStats {
+    /// When the UI started.
+    start: Instant,
+    /// Number of messages emitted so far.
+    messages: usize,
+    /// When the last message has been emitted.
+    last_message: Instant,
+}
+
+/// This UI will print to stdout the UI messages as newline-delimited json, one per line, flushing
+/// after each of them. Periodically a `Heartbeat` event is emitted as well, to show that the
+/// evaluation is still alive.
+pub struct JsonUI {
+    /// The counters shared with the heartbeat thread.
+    stats: Arc<Mutex<JsonUIStats>>,
+    /// Dropping this sender stops the heartbeat thread.
+    stop: Option<Sender<()>>,
+    /// The handle of the heartbeat thread.
+    heartbeat: Option<JoinHandle<()>>,
+}
 
 impl JsonUI {
     /// Make a new `JsonUI`.
     pub fn new() -> JsonUI {
-        JsonUI {}
+        let now = Instant::now();
+        let stats = Arc::new(Mutex::new(JsonUIStats {
+            start: now,
+            messages: 0,
+            last_message: now,
+        }));
+        let (stop, stop_receiver) = channel::<()>();
+        let heartbeat_stats = stats.clone();
+        let heartbeat = std::thread::Builder::new()
+            .name("JSON UI heartbeat".to_owned())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) =
+                    stop_receiver.recv_timeout(HEARTBEAT_INTERVAL)
+                {
+                    let stats = heartbeat_stats.lock().unwrap();
+                    let event = JsonUIEvent::Heartbeat {
+                        elapsed: stats.start.elapsed().as_secs_f64(),
+                        messages: stats.messages,
+                        idle: stats.last_message.elapsed().as_secs_f64(),
+                    };
+                    JsonUI::emit(&event);
+                }
+            })
+            .map_err(|e| warn!("Cannot spawn the heartbeat thread of the JSON UI: {:?}", e))
+            .ok();
+        JsonUI {
+            stats,
+            stop: Some(stop),
+            heartbeat,
+        }
+    }
+
+    /// Write a line with the serialized event to stdout, flushing it immediately.
+    fn emit<T: Serialize>(event: &T) {
+        let mut stdout = std::io::stdout().lock();
+        // serialize directly into stdout, without building the whole line in memory first. If
+        // stdout is closed there is no one to tell
+        let _ = serde_json::to_writer(&mut stdout, event);
+        let _ = writeln!(stdout);
+        let _ = stdout.flush();
+    }
+}
+
+impl Default for JsonUI {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl UI for JsonUI {
     fn on_message(&mut self, message: UIMessage) {
-        let message = serde_json::to_string(&message).expect("Failed to serialize message");
-        println!("{}", message);
+        // keep the lock while writing, so the heartbeats are not interleaved with the messages
+        let mut stats = self.stats.lock().unwrap();
+        JsonUI::emit(&message);
+        stats.messages += 1;
+        stats.last_message = Instant::now();
     }
 
-    fn finish(&mut self) {}
+    fn finish(&mut self) {
+        self.stop.take();
+        if let Some(heartbeat) = self.heartbeat.take() {
+            let _ = heartbeat.join();
+        }
+    }
+}
+
+impl Drop for JsonUI {
+    fn drop(&mut self) {
+        self.finish();
+    }
 }
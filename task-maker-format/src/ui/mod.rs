@@ -1,14 +1,16 @@
 //! The UI functionality for the task formats.
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::RwLock;
 
 use anyhow::Error;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-pub use termcolor::WriteColor;
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream};
+use termcolor::{Color, ColorSpec, StandardStream};
+pub use termcolor::{ColorChoice, WriteColor};
 use typescript_definitions::TypeScriptify;
 
 pub use curses::{inner_block, render_block, render_server_status, CursesDrawer, CursesUI};
@@ -35,6 +37,8 @@ pub type UIChannelSender = Sender<UIMessage>;
 pub type UIChannelReceiver = Receiver<UIMessage>;
 
 lazy_static! {
+    /// Whether to print the colors, as set by `set_color_choice`.
+    static ref COLOR_CHOICE: RwLock<ColorChoice> = RwLock::new(ColorChoice::Auto);
     /// Whether the terminal supports ANSI 256 colors.
     static ref HAS_ANSI256: bool = {
         if std::env::var("TM_ANSI256").as_deref() == Ok("true") {
@@ -57,6 +61,30 @@ lazy_static! {
     };
 }
 
+/// Set whether the UIs, the `cwrite!`/`cwriteln!` macros and the diagnostics should print colors.
+/// With `ColorChoice::Auto` the colors are printed only if stdout is a terminal and the `NO_COLOR`
+/// environment variable is not set.
+pub fn set_color_choice(choice: ColorChoice) {
+    *COLOR_CHOICE.write().unwrap() = choice;
+    task_maker_diagnostics::set_colors(color_choice() != ColorChoice::Never);
+}
+
+/// The `ColorChoice` to use when printing to stdout, with `ColorChoice::Auto` already resolved
+/// against the environment.
+pub fn color_choice() -> ColorChoice {
+    match *COLOR_CHOICE.read().unwrap() {
+        ColorChoice::Auto => {
+            let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+            if no_color || !std::io::stdout().is_terminal() {
+                ColorChoice::Never
+            } else {
+                ColorChoice::Auto
+            }
+        }
+        choice => choice,
+    }
+}
+
 macro_rules! define_color_inner {
     ($color:expr,) => {};
     ($color:expr, ansi($ansi:expr), $($tt:tt)*) => {
@@ -192,6 +220,80 @@ impl CompilationStatus {
     }
 }
 
+/// How many executions of the evaluation were served from the cache, and how many were actually
+/// executed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of executions served from the cache.
+    pub hits: usize,
+    /// The number of executions actually executed.
+    pub misses: usize,
+    /// The number of executions actually executed, grouped by their kind.
+    pub misses_by_kind: BTreeMap<&'static str, usize>,
+}
+
+impl CacheStats {
+    /// Count the execution completed in this message, if any.
+    pub fn apply(&mut self, message: &UIMessage) {
+        if let Some((kind, UIExecutionStatus::Done { result })) = message.execution_status() {
+            if result.was_cached {
+                self.hits += 1;
+            } else {
+                self.misses += 1;
+                *self.misses_by_kind.entry(kind).or_default() += 1;
+            }
+        }
+    }
+
+    /// The number of completed executions.
+    pub fn total(&self) -> usize {
+        self.hits + self.misses
+    }
+}
+
+/// The workers that reported conditions making their timings unreliable, and the executions they
+/// ran while in those conditions.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimingStability {
+    /// The last timing warnings reported by the workers, only for the workers with some.
+    unstable_workers: HashMap<WorkerUuid, (String, Vec<String>)>,
+    /// For each unstable worker that ran some executions, by name, its warnings and the number of
+    /// executions it ran.
+    pub affected_workers: BTreeMap<String, (Vec<String>, usize)>,
+}
+
+impl TimingStability {
+    /// Track the warnings of the workers and the executions started on the unstable ones.
+    pub fn apply(&mut self, message: &UIMessage) {
+        if let UIMessage::ServerStatus { status } = message {
+            self.unstable_workers = status
+                .connected_workers
+                .iter()
+                .filter(|worker| !worker.telemetry.timing_warnings.is_empty())
+                .map(|worker| {
+                    let warnings = worker.telemetry.timing_warnings.clone();
+                    (worker.uuid, (worker.name.clone(), warnings))
+                })
+                .collect();
+        } else if let Some((_, UIExecutionStatus::Started { worker })) = message.execution_status()
+        {
+            if let Some((name, warnings)) = self.unstable_workers.get(worker) {
+                let entry = self
+                    .affected_workers
+                    .entry(name.clone())
+                    .or_insert_with(|| (vec![], 0));
+                entry.0.clone_from(warnings);
+                entry.1 += 1;
+            }
+        }
+    }
+
+    /// The number of executions that ran on unstable workers.
+    pub fn affected_executions(&self) -> usize {
+        self.affected_workers.values().map(|(_, count)| count).sum()
+    }
+}
+
 /// The state of a task, all the information for the UI are stored here.
 ///
 /// The `T` at the end is to disambiguate from `UIState` due to a strange behaviour of the compiler.
@@ -201,6 +303,33 @@ pub trait UIStateT {
 
     /// Print the final results using a finish UI.
     fn finish(&mut self);
+
+    /// Whether the time and the memory used by an execution of a solution are close to the limits
+    /// of the task, to highlight them.
+    fn close_to_limits(
+        &self,
+        _solution: &Path,
+        _resources: &ExecutionResourcesUsage,
+    ) -> (bool, bool) {
+        (false, false)
+    }
+}
+
+/// Format the minimum, the median and the maximum of the CPU times of the runs of a repeated
+/// execution, like `3 runs: 0.120s / 0.130s / 0.150s`. `None` if the execution ran only once.
+pub fn run_times_text(run_times: &[f64]) -> Option<String> {
+    if run_times.len() < 2 {
+        return None;
+    }
+    let mut sorted = run_times.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    Some(format!(
+        "{} runs: {:.3}s / {:.3}s / {:.3}s",
+        sorted.len(),
+        sorted[0],
+        sorted[(sorted.len() - 1) / 2],
+        sorted[sorted.len() - 1]
+    ))
 }
 
 /// UI that prints to `stdout` the ending result of the evaluation of a task.
@@ -304,6 +433,43 @@ impl<'a> FinishUIUtils<'a> {
             .unwrap_or(0)
     }
 
+    /// Print how many executions were served from the cache, and the kinds of the ones that were
+    /// executed.
+    pub fn print_cache_stats(&mut self, stats: &CacheStats) {
+        cwriteln!(self, BLUE, "Cache");
+        let percent = 100.0 * stats.hits as f64 / stats.total().max(1) as f64;
+        println!(
+            "{} of {} executions served from the cache ({:.1}%)",
+            stats.hits,
+            stats.total(),
+            percent
+        );
+        if !stats.misses_by_kind.is_empty() {
+            let executed = stats
+                .misses_by_kind
+                .iter()
+                .map(|(kind, count)| format!("{} {}", count, kind))
+                .join(", ");
+            println!("Executed: {}", executed);
+        }
+    }
+
+    /// Print a warning with the executions that ran on workers with unreliable timings, if any.
+    pub fn print_timing_stability(&mut self, stability: &TimingStability) {
+        if stability.affected_workers.is_empty() {
+            return;
+        }
+        cwriteln!(
+            self,
+            YELLOW,
+            "Warning: {} executions ran on workers with unreliable timings",
+            stability.affected_executions()
+        );
+        for (name, (warnings, count)) in &stability.affected_workers {
+            println!("{} ({} executions): {}", name, count, warnings.join("; "));
+        }
+    }
+
     /// Print the diagnostics.
     pub fn print_diagnostic_messages(&mut self, diagnostics: &DiagnosticContext) {
         let diagnostics = diagnostics.diagnostics();
@@ -382,7 +548,7 @@ pub struct StdoutPrinter {
 impl Default for StdoutPrinter {
     fn default() -> Self {
         Self {
-            stream: StandardStream::stdout(ColorChoice::Auto),
+            stream: StandardStream::stdout(color_choice()),
         }
     }
 }
@@ -1,10 +1,14 @@
+use std::path::Path;
+
 use itertools::Itertools;
-use termcolor::{ColorChoice, ColorSpec, StandardStream};
+use termcolor::{ColorSpec, StandardStream};
 
-use task_maker_dag::ExecutionStatus;
+use task_maker_dag::{ExecutionResourcesUsage, ExecutionStatus};
 
 use crate::cwrite;
+use crate::ioi::{testcase_label, SubtaskId, TestcaseId};
 use crate::terry::CaseStatus;
+use crate::ui::curses::{file_transfer_text, worker_telemetry_text};
 use crate::ui::*;
 
 lazy_static! {
@@ -24,7 +28,7 @@ impl<State: UIStateT> PrintUI<State> {
     /// Make a new PrintUI.
     pub fn new(state: State) -> Self {
         PrintUI {
-            stream: StandardStream::stdout(ColorChoice::Auto),
+            stream: StandardStream::stdout(color_choice()),
             state,
         }
     }
@@ -51,6 +55,9 @@ impl<State: UIStateT> PrintUI<State> {
             }
             UIExecutionStatus::Done { result } => {
                 self.write_execution_status(&result.status);
+                if result.was_cached {
+                    print!(" (from cache)");
+                }
             }
             UIExecutionStatus::Skipped => {}
         }
@@ -65,6 +72,27 @@ impl<State: UIStateT> PrintUI<State> {
         }
     }
 
+    /// Write the time and the memory used by an execution of a solution, highlighting the ones
+    /// close to the limits.
+    fn write_resources(&mut self, solution: &Path, resources: &ExecutionResourcesUsage) {
+        let (close_time, close_memory) = self.state.close_to_limits(solution, resources);
+        let time = format!("{:.3}s", resources.cpu_time);
+        let memory = format!("{:.1}MiB", resources.memory as f64 / 1024.0);
+        print!(" [");
+        if close_time {
+            cwrite!(self, WARNING, "{}", time);
+        } else {
+            print!("{}", time);
+        }
+        print!(" | ");
+        if close_memory {
+            cwrite!(self, WARNING, "{}", memory);
+        } else {
+            print!("{}", memory);
+        }
+        print!("]");
+    }
+
     /// Write a message, padding it to at least 80 chars.
     fn write_message(&mut self, message: String) {
         print!("{:<80}", message);
@@ -79,17 +107,27 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
             UIMessage::StopUI => {}
             UIMessage::ServerStatus { status } => {
                 println!(
-                    "[STATUS]  Server status: {} ready exec, {} waiting exec",
-                    status.ready_execs, status.waiting_execs
+                    "[STATUS]  Server status: {} ready exec, {} waiting exec, {}/{} global cache hits",
+                    status.ready_execs,
+                    status.waiting_execs,
+                    status.cache_hits,
+                    status.cache_hits + status.cache_misses
                 );
                 for worker in status.connected_workers {
+                    let telemetry = worker_telemetry_text(&worker.telemetry);
                     if let Some(job) = &worker.current_job {
-                        println!(" - {} ({}): {}", worker.name, worker.uuid, job.job);
+                        println!(
+                            " - {} ({}) {}: {}",
+                            worker.name, worker.uuid, telemetry, job.job
+                        );
                     } else {
-                        println!(" - {} ({})", worker.name, worker.uuid);
+                        println!(" - {} ({}) {}", worker.name, worker.uuid, telemetry);
                     }
                 }
             }
+            UIMessage::FileTransfer { transfer } => {
+                println!("[UPLOAD]  {}", file_transfer_text(&transfer));
+            }
             UIMessage::Solutions { solutions } => {
                 println!("[SOLUTIONS] Solutions that will be evaluated:");
                 for solution in solutions {
@@ -129,21 +167,17 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
             UIMessage::IOIGeneration {
                 subtask,
                 testcase,
+                name,
                 status,
             } => {
+                let testcase = testcase_text(subtask, testcase, name.as_deref());
                 self.write_status(&status);
-                self.write_message(format!(
-                    "Generation of testcase {} of subtask {} ",
-                    testcase, subtask
-                ));
+                self.write_message(format!("Generation of testcase {} ", testcase));
                 self.write_status_details(&status);
                 if let UIExecutionStatus::Done { result } = status {
                     if let Some(stderr) = result.stderr {
                         let stderr = String::from_utf8_lossy(&stderr);
-                        println!(
-                            "\n[STDERR]  Generation stderr of testcase {} of subtask {}",
-                            testcase, subtask
-                        );
+                        println!("\n[STDERR]  Generation stderr of testcase {}", testcase);
                         print!("{}", stderr.trim());
                     }
                 }
@@ -170,6 +204,8 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                     }
                 }
             }
+            // the stderr is already printed with the status of the execution
+            UIMessage::IOIGenerationStderr { .. } | UIMessage::IOIValidationStderr { .. } => {}
             UIMessage::IOISolution {
                 subtask,
                 testcase,
@@ -200,6 +236,12 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                     num_parts
                 ));
                 self.write_status_details(&status);
+                if let UIExecutionStatus::Done { result } = &status {
+                    self.write_resources(&solution, &result.resources);
+                    if let Some(run_times) = run_times_text(&result.run_times) {
+                        print!(" [{}]", run_times);
+                    }
+                }
             }
             UIMessage::IOIChecker {
                 subtask,
@@ -213,17 +255,33 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                     solution, testcase, subtask
                 ));
             }
+            UIMessage::IOICheckerError {
+                subtask,
+                testcase,
+                solution,
+                message,
+            } => {
+                print!("[CHECKER] ");
+                self.write_message(format!(
+                    "Checker error with {:?} on testcase {} of subtask {}: {}",
+                    solution, testcase, subtask, message
+                ));
+            }
             UIMessage::IOITestcaseScore {
                 subtask,
                 testcase,
+                name,
                 solution,
                 score,
                 message,
             } => {
                 print!("[TESTCAS] ");
                 self.write_message(format!(
-                    "Solution {:?} scored {} on testcase {} of subtask {}: {}",
-                    solution, score, testcase, subtask, message
+                    "Solution {:?} scored {} on testcase {}: {}",
+                    solution,
+                    score,
+                    testcase_text(subtask, testcase, name.as_deref()),
+                    message
                 ));
             }
             UIMessage::IOISubtaskScore {
@@ -266,6 +324,32 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                 println!("[DIAGNOSTIC]");
                 println!("{}", diagnostic);
             }
+            UIMessage::FuzzBatch { index, size } => {
+                cwrite!(self, BOLD, "[FUZZ]    ");
+                self.write_message(format!("Batch {} of {} testcases", index, size));
+            }
+            UIMessage::FuzzMinimization {
+                parameter,
+                low,
+                high,
+            } => {
+                cwrite!(self, BOLD, "[FUZZ]    ");
+                self.write_message(format!(
+                    "Minimizing parameter {} in [{}, {}]",
+                    parameter, low, high
+                ));
+            }
+            UIMessage::FuzzFailure {
+                generator_args,
+                message,
+            } => {
+                cwrite!(self, ERROR, "[FUZZ]    ");
+                self.write_message(format!(
+                    "Failing testcase with args {}: {}",
+                    generator_args.join(" "),
+                    message
+                ));
+            }
             UIMessage::TerryTask { .. } => {}
             UIMessage::TerryGeneration {
                 solution,
@@ -289,6 +373,11 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                 self.write_status(&status);
                 self.write_message(format!("Solving input for {} ", solution.display()));
                 self.write_status_details(&status);
+                if let UIExecutionStatus::Done { result } = &status {
+                    if let Some(run_times) = run_times_text(&result.run_times) {
+                        print!(" [{}]", run_times);
+                    }
+                }
             }
             UIMessage::TerryChecker { solution, status } => {
                 self.write_status(&status);
@@ -339,6 +428,34 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                     print!("Checker of {} failed: {}", solution.display(), e);
                 }
             },
+            UIMessage::TerryStatement { name, status } => {
+                self.write_status(&status);
+                self.write_message(format!("Rendering of the statement to {}", name));
+            }
+            UIMessage::TerrySeedOutcome {
+                solution,
+                seed,
+                outcome,
+            } => match outcome {
+                Ok(outcome) => {
+                    cwrite!(self, SUCCESS, "[OUTCOME] ");
+                    print!(
+                        "Solution {} scored {} with seed {}",
+                        solution.display(),
+                        outcome.score,
+                        seed
+                    );
+                }
+                Err(e) => {
+                    cwrite!(self, ERROR, "[OUTCOME] ");
+                    print!(
+                        "Solution {} with seed {} failed: {}",
+                        solution.display(),
+                        seed,
+                        e
+                    );
+                }
+            },
         };
         println!();
     }
@@ -349,3 +466,12 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
         self.state.finish();
     }
 }
+
+/// The testcase of a message, with its subtask: `st2/big-random-tree` for the testcases named in
+/// gen/GEN, `3 of subtask 2` for the others.
+fn testcase_text(subtask: SubtaskId, testcase: TestcaseId, name: Option<&str>) -> String {
+    match name {
+        Some(_) => testcase_label(subtask, testcase, name),
+        None => format!("{} of subtask {}", testcase, subtask),
+    }
+}
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use task_maker_diagnostics::Diagnostic;
 use typescript_definitions::TypeScriptify;
 
-use task_maker_exec::ExecutorStatus;
+use task_maker_exec::{ClientUpdate, ExecutorStatus, FileTransfer};
 
 use crate::ioi::{SubtaskId, TestcaseId};
 use crate::solution::SolutionInfo;
@@ -25,6 +25,12 @@ pub enum UIMessage {
         status: ExecutorStatus<SystemTime>,
     },
 
+    /// The progress of the upload of a large file to the executor.
+    FileTransfer {
+        /// The file being uploaded, with the number of bytes sent so far.
+        transfer: FileTransfer,
+    },
+
     /// The list of solutions that will be evaluated.
     Solutions {
         /// The information of the solutions to evaluate.
@@ -51,6 +57,8 @@ pub enum UIMessage {
         subtask: SubtaskId,
         /// The id of the testcase.
         testcase: TestcaseId,
+        /// The name of the testcase given in gen/GEN, if any.
+        name: Option<String>,
         /// The status of the generation.
         status: UIExecutionStatus,
     },
@@ -65,6 +73,26 @@ pub enum UIMessage {
         status: UIExecutionStatus,
     },
 
+    /// The stderr of a failed generation of a testcase in a IOI task.
+    IOIGenerationStderr {
+        /// The id of the subtask.
+        subtask: SubtaskId,
+        /// The id of the testcase.
+        testcase: TestcaseId,
+        /// The content of the stderr of the generator.
+        content: String,
+    },
+
+    /// The stderr of a failed validation of a testcase in a IOI task.
+    IOIValidationStderr {
+        /// The id of the subtask.
+        subtask: SubtaskId,
+        /// The id of the testcase.
+        testcase: TestcaseId,
+        /// The content of the stderr of the validator.
+        content: String,
+    },
+
     /// The solution of a testcase in a IOI task.
     IOISolution {
         /// The id of the subtask.
@@ -104,12 +132,27 @@ pub enum UIMessage {
         status: UIExecutionStatus,
     },
 
+    /// The checker of a IOI task violated its protocol, by crashing or by printing an invalid
+    /// score. The testcase scores zero.
+    IOICheckerError {
+        /// The id of the subtask.
+        subtask: SubtaskId,
+        /// The id of the testcase.
+        testcase: TestcaseId,
+        /// The path of the solution.
+        solution: PathBuf,
+        /// What the checker did wrong.
+        message: String,
+    },
+
     /// The score of a testcase is ready.
     IOITestcaseScore {
         /// The id of the subtask.
         subtask: SubtaskId,
         /// The id of the testcase.
         testcase: TestcaseId,
+        /// The name of the testcase given in gen/GEN, if any.
+        name: Option<String>,
         /// The path of the solution.
         solution: PathBuf,
         /// The score of the testcase.
@@ -209,9 +252,128 @@ pub enum UIMessage {
         outcome: Result<SolutionOutcome, String>,
     },
 
+    /// The outcome of a solution in a Terry task on one of the additional input files, generated
+    /// with other seeds, of an evaluation with more than one seed.
+    TerrySeedOutcome {
+        /// The path of the solution.
+        solution: PathBuf,
+        /// The seed used to generate the input file.
+        seed: Seed,
+        /// The outcome of the solution. `Err` is caused by a failed execution or by an invalid
+        /// response from the checker.
+        outcome: Result<SolutionOutcome, String>,
+    },
+
+    /// The rendering of the statement of a Terry task.
+    TerryStatement {
+        /// The name of the rendered file, like `statement.pdf`.
+        name: String,
+        /// The status of the rendering.
+        status: UIExecutionStatus,
+    },
+
     /// A diagnostic message has been emitted.
     Diagnostic {
         /// The diagnostic message.
         diagnostic: Diagnostic,
     },
+
+    /// A fuzzing tool started the evaluation of a batch of random testcases.
+    FuzzBatch {
+        /// The index of the batch.
+        index: usize,
+        /// The number of testcases in the batch.
+        size: usize,
+    },
+
+    /// A fuzzing tool is searching the smallest value of a parameter of the generator that still
+    /// makes the solution fail.
+    FuzzMinimization {
+        /// The index of the parameter in the arguments of the generator.
+        parameter: usize,
+        /// The smallest value that may still make the solution fail.
+        low: i64,
+        /// The smallest value known to make the solution fail.
+        high: i64,
+    },
+
+    /// A fuzzing tool found a testcase that makes the solution fail.
+    FuzzFailure {
+        /// The arguments passed to the generator for producing the testcase.
+        generator_args: Vec<String>,
+        /// The message of the checker.
+        message: String,
+    },
+}
+
+impl UIMessage {
+    /// The status of the execution this message is about, if any, together with the kind of the
+    /// execution (e.g. `"compilations"`).
+    pub fn execution_status(&self) -> Option<(&'static str, &UIExecutionStatus)> {
+        match self {
+            UIMessage::Compilation { status, .. } => Some(("compilations", status)),
+            UIMessage::IOIGeneration { status, .. } | UIMessage::TerryGeneration { status, .. } => {
+                Some(("generations", status))
+            }
+            UIMessage::IOIValidation { status, .. } | UIMessage::TerryValidation { status, .. } => {
+                Some(("validations", status))
+            }
+            UIMessage::IOISolution { status, .. } => Some(("official solutions", status)),
+            UIMessage::IOIEvaluation { status, .. } | UIMessage::TerrySolution { status, .. } => {
+                Some(("evaluations", status))
+            }
+            UIMessage::IOIChecker { status, .. } | UIMessage::TerryChecker { status, .. } => {
+                Some(("checkers", status))
+            }
+            UIMessage::IOIBooklet { status, .. }
+            | UIMessage::IOIBookletDependency { status, .. }
+            | UIMessage::TerryStatement { status, .. } => Some(("booklets", status)),
+            _ => None,
+        }
+    }
+
+    /// Drop the outputs of the executions and the messages of the checkers. They are not needed
+    /// for summarizing the results of an evaluation, but keeping them for every testcase of every
+    /// solution makes the memory grow with the size of the outputs. The verdicts, the scores and
+    /// the resources used are kept.
+    pub fn strip_outputs(&mut self) {
+        match self {
+            UIMessage::Compilation { status, .. }
+            | UIMessage::IOIGeneration { status, .. }
+            | UIMessage::IOIValidation { status, .. }
+            | UIMessage::IOISolution { status, .. }
+            | UIMessage::IOIEvaluation { status, .. }
+            | UIMessage::IOIChecker { status, .. }
+            | UIMessage::IOIBooklet { status, .. }
+            | UIMessage::IOIBookletDependency { status, .. }
+            | UIMessage::TerryGeneration { status, .. }
+            | UIMessage::TerryValidation { status, .. }
+            | UIMessage::TerrySolution { status, .. }
+            | UIMessage::TerryChecker { status, .. }
+            | UIMessage::TerryStatement { status, .. } => {
+                if let UIExecutionStatus::Done { result } = status {
+                    result.stdout = None;
+                    result.stderr = None;
+                }
+            }
+            UIMessage::IOIGenerationStderr { content, .. }
+            | UIMessage::IOIValidationStderr { content, .. } => {
+                std::mem::take(content);
+            }
+            UIMessage::IOICheckerError { message, .. }
+            | UIMessage::IOITestcaseScore { message, .. } => {
+                std::mem::take(message);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl From<ClientUpdate> for UIMessage {
+    fn from(update: ClientUpdate) -> Self {
+        match update {
+            ClientUpdate::Status(status) => UIMessage::ServerStatus { status },
+            ClientUpdate::Transfer(transfer) => UIMessage::FileTransfer { transfer },
+        }
+    }
 }
@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use task_maker_format::ioi::italian_yaml::cms_task_yaml;
+
+mod utils;
+
+#[test]
+fn test_ioi_cms_task_yaml() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        tmpdir.path().join("task.yaml"),
+        "name: task\ntitle: The Task\ntoken_mode: infinite\n",
+    )
+    .unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    task.time_limit = Some(1.5);
+    task.memory_limit = Some(256);
+    task.infile = Some(PathBuf::from("input.txt"));
+
+    let yaml = cms_task_yaml(&task).unwrap();
+    let yaml: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+    assert_eq!(yaml["name"], "task");
+    assert_eq!(yaml["time_limit"], 1.5);
+    assert_eq!(yaml["memory_limit"], 256);
+    assert_eq!(yaml["infile"], "input.txt");
+    assert_eq!(yaml["outfile"], "");
+    assert_eq!(yaml["n_input"], 3);
+    assert_eq!(yaml["score_type"], "GroupMin");
+    assert_eq!(
+        yaml["score_type_parameters"],
+        serde_yaml::from_str::<serde_yaml::Value>("[[10.0, '000'], [90.0, '001|002']]").unwrap()
+    );
+    // the cms settings of the task are kept
+    assert_eq!(yaml["token_mode"], "infinite");
+    assert_eq!(yaml["public_testcases"], "all");
+}
+
+#[test]
+fn test_ioi_cms_task_yaml_contest_defaults() {
+    let contest_dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        contest_dir.path().join("contest.yaml"),
+        "tasks: [task]\ndefaults:\n  primary_language: it\n  token_mode: disabled\n  time_limit: 2\n",
+    )
+    .unwrap();
+    let task_dir = contest_dir.path().join("task");
+    std::fs::create_dir(&task_dir).unwrap();
+    std::fs::write(
+        task_dir.join("task.yaml"),
+        "name: task\ntitle: The Task\ntoken_mode: infinite\ntimeout: 1\n",
+    )
+    .unwrap();
+    let task = utils::new_task_with_context(&task_dir);
+
+    let yaml = cms_task_yaml(&task).unwrap();
+    let yaml: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+    // the defaults of the contest are used only for the keys the task doesn't set
+    assert_eq!(yaml["primary_language"], "it");
+    assert_eq!(yaml["token_mode"], "infinite");
+}
@@ -1,6 +1,11 @@
+use std::collections::HashMap;
 use std::process::Command;
 use std::sync::Arc;
-use task_maker_format::ioi::{Booklet, BookletConfig, IOITask, Statement, StatementConfig};
+use task_maker_dag::File;
+use task_maker_format::ioi::{
+    Booklet, BookletConfig, IOITask, InputConstraint, InputConstraints, InputFormatLevel,
+    InputFormatPolicy, Statement, StatementConfig,
+};
 use task_maker_format::ui::UIMessage;
 use task_maker_format::EvaluationData;
 use task_maker_lang::GraderMap;
@@ -31,6 +36,32 @@ fn get_post_warnings(task: &IOITask) -> Vec<String> {
     res
 }
 
+fn get_warnings_with_input(task: &IOITask, input: File, chunks: &[&[u8]]) -> Vec<String> {
+    get_warnings_with_files(task, &[(input, chunks)])
+}
+
+fn get_warnings_with_files(task: &IOITask, files: &[(File, &[&[u8]])]) -> Vec<String> {
+    let (mut eval, recv) = EvaluationData::new("");
+    task.sanity_checks.pre_hook(task, &mut eval).unwrap();
+    for (file, chunks) in files {
+        let callbacks = eval.dag.file_callbacks().get_mut(&file.uuid).unwrap();
+        for callback in &mut callbacks.get_content_chunked {
+            for chunk in chunks.iter() {
+                callback(chunk).unwrap();
+            }
+            callback(&[]).unwrap();
+        }
+    }
+    task.sanity_checks.post_hook(task, &mut eval).unwrap();
+    let mut res = vec![];
+    while let Ok(mex) = recv.try_recv() {
+        if let UIMessage::Diagnostic { diagnostic } = mex {
+            res.push(diagnostic.to_string())
+        }
+    }
+    res
+}
+
 fn has_warning(warnings: &[String], warning: &str) {
     for warn in warnings {
         if warn.contains(warning) {
@@ -83,6 +114,45 @@ fn test_sanity_checks_att_templates() {
     has_warning(&warnings, "Missing template at att/task.cpp");
 }
 
+#[test]
+fn test_sanity_checks_sol_templates_should_run() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    std::fs::create_dir(tmpdir.path().join("sol")).unwrap();
+    std::fs::write(tmpdir.path().join("sol/grader.py"), "x").unwrap();
+    std::fs::write(tmpdir.path().join("sol/template.py"), "x").unwrap();
+    task.grader_map = Arc::new(GraderMap::new(vec![tmpdir.path().join("sol/grader.py")]));
+    let input = File::new("input");
+    task.testcases.get_mut(&0).unwrap().input_file = Some(input.uuid);
+
+    let (mut eval, recv) = EvaluationData::new("");
+    task.sanity_checks.pre_hook(&task, &mut eval).unwrap();
+    let exec = eval
+        .dag
+        .data
+        .execution_groups
+        .values()
+        .flat_map(|group| group.executions.iter())
+        .find(|exec| {
+            exec.description
+                .contains("template sol/template.py on testcase 0")
+        })
+        .unwrap();
+    assert!(exec.dependencies().contains(&input.uuid));
+    let exec = exec.uuid;
+    let callbacks = eval.dag.execution_callbacks().get_mut(&exec).unwrap();
+    callbacks.on_done.pop().unwrap()(utils::bad_result()).unwrap();
+    drop(eval);
+    let warnings: Vec<_> = recv
+        .try_iter()
+        .filter_map(|mex| match mex {
+            UIMessage::Diagnostic { diagnostic } => Some(diagnostic.to_string()),
+            _ => None,
+        })
+        .collect();
+    has_warning(&warnings, "Template sol/template.py failed on testcase 0");
+}
+
 #[test]
 fn test_sanity_checks_att_sample_files_nothing() {
     let tmpdir = tempfile::TempDir::new().unwrap();
@@ -261,6 +331,109 @@ fn test_sanity_checks_statement_subtasks_ois_wrong() {
     );
 }
 
+fn add_statement(task: &mut IOITask, dir: &std::path::Path, tex: &str) {
+    std::fs::write(dir.join("file.tex"), tex).unwrap();
+    let mut booklet = Booklet::new(BookletConfig::default(), dir.join("foo.pdf"));
+    let config = StatementConfig::from_task(task);
+    let statement = Statement::new(dir.join("file.tex"), config).unwrap();
+    booklet.add_statement(statement);
+    task.booklets.push(booklet);
+}
+
+#[test]
+fn test_sanity_checks_statement_metadata() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    task.time_limit = Some(1.0);
+    task.memory_limit = Some(128);
+    task.infile = Some("input.txt".into());
+    add_statement(
+        &mut task,
+        tmpdir.path(),
+        r"Limite di tempo: 1\,s, limite di memoria: 256~MiB.
+          Leggere da \texttt{input.txt} e scrivere su \texttt{output.txt}.",
+    );
+
+    let warnings = get_warnings(&task);
+    has_warning(
+        &warnings,
+        "The memory limit in file.tex doesn't match task.yaml",
+    );
+    has_warning(&warnings, "Found 256 MiB, expecting 128 MiB");
+    has_warning(&warnings, "Found output.txt, but the task uses stdout");
+    does_not_have_warning(&warnings, "The time limit");
+    does_not_have_warning(&warnings, "The input file");
+}
+
+#[test]
+fn test_sanity_checks_statement_metadata_valid() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    task.time_limit = Some(0.5);
+    task.memory_limit = Some(1024);
+    add_statement(
+        &mut task,
+        tmpdir.path(),
+        "Time limit: 500 ms for the 3 subtasks. Memory limit: 1 GiB.",
+    );
+
+    let warnings = get_warnings(&task);
+    does_not_have_warning(&warnings, "doesn't match task.yaml");
+}
+
+#[test]
+fn test_sanity_checks_statement_samples() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    let input = File::new("input");
+    task.testcases.get_mut(&0).unwrap().input_file = Some(input.uuid);
+    add_statement(
+        &mut task,
+        tmpdir.path(),
+        r"\exmp{1 2
+          3}{6}
+          \exmpfile{input1.txt}{output1.txt}",
+    );
+
+    let (mut eval, recv) = EvaluationData::new("");
+    task.sanity_checks.pre_hook(&task, &mut eval).unwrap();
+    let exec = eval
+        .dag
+        .data
+        .execution_groups
+        .values()
+        .flat_map(|group| group.executions.iter())
+        .find(|exec| {
+            exec.description
+                .contains("sample input #1 of file.tex with testcase 0")
+        })
+        .unwrap();
+    assert!(exec.dependencies().contains(&input.uuid));
+    let exec = exec.uuid;
+    let callbacks = eval.dag.execution_callbacks().get_mut(&exec).unwrap();
+    callbacks.on_done.pop().unwrap()(utils::bad_result()).unwrap();
+    drop(eval);
+    let warnings: Vec<_> = recv
+        .try_iter()
+        .filter_map(|mex| match mex {
+            UIMessage::Diagnostic { diagnostic } => Some(diagnostic.to_string()),
+            _ => None,
+        })
+        .collect();
+    has_warning(
+        &warnings,
+        "Sample input file #1 of file.tex is not a testcase of subtask 0",
+    );
+    has_warning(
+        &warnings,
+        "The sample file input1.txt in file.tex doesn't exist",
+    );
+    has_warning(
+        &warnings,
+        "The sample file output1.txt in file.tex doesn't exist",
+    );
+}
+
 #[test]
 fn test_sanity_checks_statement_valid_missing() {
     let tmpdir = tempfile::TempDir::new().unwrap();
@@ -413,3 +586,92 @@ fn test_sanity_checks_statement_git_known() {
     let warnings = get_post_warnings(&task);
     does_not_have_warning(&warnings, "git");
 }
+
+#[test]
+fn test_sanity_checks_input_constraints() {
+    let mut task = utils::new_task();
+    let variables = vec!["N".to_string(), "M".to_string()];
+    let parse = |constraint| InputConstraint::parse(constraint, &variables).unwrap();
+    task.input_constraints = InputConstraints {
+        variables: variables.clone(),
+        global: vec![parse("N: 1..100"), parse("1 <= M <= N")],
+        subtasks: HashMap::from([(0, vec![parse("N <= 10")])]),
+    };
+    let input = File::new("input");
+    task.testcases.get_mut(&0).unwrap().input_file = Some(input.uuid);
+
+    let warnings = get_warnings_with_input(&task, input, &[b"50 ", b"1\n1 2 3\n"]);
+    has_warning(
+        &warnings,
+        "Testcase 0 violates the constraint N <= 10 of subtask 0",
+    );
+    does_not_have_warning(&warnings, "violates the constraint 1 <= N <= 100");
+    has_warning(&warnings, "reaches the bounds N = 1, N = 10");
+    has_warning(&warnings, "M = 1 in 0");
+    has_warning(
+        &warnings,
+        "The values reached by the testcases are: N in [50, 50]",
+    );
+}
+
+#[test]
+fn test_sanity_checks_input_constraints_reported() {
+    let mut task = utils::new_task();
+    let variables = vec!["N".to_string(), "M".to_string()];
+    let parse = |constraint| InputConstraint::parse(constraint, &variables).unwrap();
+    task.input_constraints = InputConstraints {
+        variables: variables.clone(),
+        global: vec![parse("N: 1..100"), parse("1 <= M <= N")],
+        subtasks: HashMap::from([(0, vec![parse("N <= 10")])]),
+    };
+    let input = File::new("input");
+    let validation = File::new("validation");
+    let testcase = task.testcases.get_mut(&0).unwrap();
+    testcase.input_file = Some(input.uuid);
+    testcase.validation_file = Some(validation.uuid);
+
+    let warnings = get_warnings_with_files(
+        &task,
+        &[
+            (input, &[b"abc\n"]),
+            (validation, &[b"@value N 50\n", b"@value M 1\n"]),
+        ],
+    );
+    does_not_have_warning(&warnings, "Cannot read the values");
+    has_warning(
+        &warnings,
+        "Testcase 0 violates the constraint N <= 10 of subtask 0",
+    );
+}
+
+#[test]
+fn test_sanity_checks_input_format() {
+    let mut task = utils::new_task();
+    task.input_format = InputFormatPolicy {
+        crlf: InputFormatLevel::Fail,
+        trailing_spaces: InputFormatLevel::Warn,
+        final_newline: InputFormatLevel::Warn,
+        ascii: InputFormatLevel::Off,
+    };
+    let input = File::new("input");
+    task.testcases.get_mut(&0).unwrap().input_file = Some(input.uuid);
+
+    let warnings = get_warnings_with_input(&task, input, &[b"1 2\n3 \r", b"\n\xc3\xa8 4"]);
+    has_warning(&warnings, "line 2 ends with \\r\\n");
+    has_warning(
+        &warnings,
+        "line 2 ends with spaces, the file doesn't end with a new line",
+    );
+    does_not_have_warning(&warnings, "non-ASCII");
+}
+
+#[test]
+fn test_sanity_checks_input_format_valid() {
+    let mut task = utils::new_task();
+    task.input_format.ascii = InputFormatLevel::Fail;
+    let input = File::new("input");
+    task.testcases.get_mut(&0).unwrap().input_file = Some(input.uuid);
+
+    let warnings = get_warnings_with_input(&task, input, &[b"1 2\n", b"3 4\n"]);
+    does_not_have_warning(&warnings, "input format");
+}
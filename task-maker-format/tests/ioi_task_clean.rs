@@ -85,3 +85,40 @@ fn test_ioi_task_clean_checker() {
     assert!(!check.join("checker").exists());
     assert!(!cor.join("correttore").exists());
 }
+
+#[test]
+fn test_ioi_task_clean_statement() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let task = utils::new_task_with_context(tmpdir.path());
+    let statement = tmpdir.path().join("statement");
+    std::fs::create_dir(&statement).unwrap();
+    std::fs::write(statement.join("english.tex"), "x").unwrap();
+    std::fs::write(statement.join("english.pdf"), "x").unwrap();
+    std::fs::write(statement.join("italian.pdf"), "x").unwrap();
+
+    task.clean().unwrap();
+
+    assert!(statement.join("english.tex").exists());
+    assert!(!statement.join("english.pdf").exists());
+    // not compiled from a .tex, it's not generated by task-maker
+    assert!(statement.join("italian.pdf").exists());
+}
+
+#[test]
+fn test_ioi_task_artifacts() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let task = utils::new_task_with_context(tmpdir.path());
+    let input = tmpdir.path().join("input");
+    let bin = tmpdir.path().join("bin");
+    std::fs::create_dir(&input).unwrap();
+    std::fs::create_dir(&bin).unwrap();
+    std::fs::write(input.join("input0.txt"), "x").unwrap();
+    std::fs::write(input.join("notes.md"), "x").unwrap();
+
+    let mut artifacts = task.artifacts().unwrap();
+    artifacts.sort();
+    assert_eq!(artifacts, vec![bin.clone(), input.join("input0.txt")]);
+    // listing the artifacts doesn't remove them
+    assert!(input.join("input0.txt").exists());
+    assert!(bin.exists());
+}
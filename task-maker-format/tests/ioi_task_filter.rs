@@ -0,0 +1,50 @@
+mod utils;
+
+#[test]
+fn test_ioi_task_filter_subtask() {
+    let mut task = utils::new_task();
+    task.filter_testcases(&[1], &[]).unwrap();
+    assert_eq!(task.subtasks.keys().copied().collect::<Vec<_>>(), vec![1]);
+    let mut testcases: Vec<_> = task.testcases.keys().copied().collect();
+    testcases.sort();
+    assert_eq!(testcases, vec![1, 2]);
+}
+
+#[test]
+fn test_ioi_task_filter_testcase() {
+    let mut task = utils::new_task();
+    task.filter_testcases(&[], &[0, 2]).unwrap();
+    assert_eq!(task.subtasks.len(), 2);
+    assert_eq!(task.subtasks[&0].testcases_owned, vec![0]);
+    assert_eq!(task.subtasks[&1].testcases_owned, vec![2]);
+    assert_eq!(task.subtasks[&1].testcases, vec![2]);
+    assert!(!task.testcases.contains_key(&1));
+}
+
+#[test]
+fn test_ioi_task_filter_shared_testcase() {
+    let mut task = utils::new_task();
+    // subtask 1 includes the testcase of subtask 0, which is not evaluated
+    task.subtasks.get_mut(&1).unwrap().testcases.push(0);
+    task.filter_testcases(&[1], &[]).unwrap();
+    assert_eq!(task.subtasks[&1].testcases, vec![1, 2]);
+    assert!(!task.testcases.contains_key(&0));
+}
+
+#[test]
+fn test_ioi_task_filter_nothing() {
+    let mut task = utils::new_task();
+    assert!(task.filter_testcases(&[0], &[1]).is_err());
+    let mut task = utils::new_task();
+    assert!(task.filter_testcases(&[5], &[]).is_err());
+}
+
+#[test]
+fn test_ioi_task_sampled_testcases() {
+    let task = utils::new_task();
+    let subtask = &task.subtasks[&1];
+    assert_eq!(subtask.sampled_testcases(0), Vec::<u32>::new());
+    assert_eq!(subtask.sampled_testcases(1), vec![2]);
+    assert_eq!(subtask.sampled_testcases(2), vec![1, 2]);
+    assert_eq!(subtask.sampled_testcases(5), vec![1, 2]);
+}
@@ -1,8 +1,11 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use task_maker_dag::ExecutionStatus;
-use task_maker_exec::ExecutorStatus;
-use task_maker_format::ioi::{TestcaseEvaluationStatus, TestcaseGenerationStatus, UIState};
+use task_maker_dag::{ExecutionResourcesUsage, ExecutionResult, ExecutionStatus, WorkerUuid};
+use task_maker_exec::{ExecutorStatus, ExecutorWorkerStatus, FileTransfer, WorkerTelemetry};
+use task_maker_format::ioi::{
+    LanguageMultiplier, TestcaseEvaluationStatus, TestcaseGenerationStatus, TestcaseResources,
+    UIState,
+};
 use task_maker_format::ui::UIStateT;
 use task_maker_format::ui::{CompilationStatus, UIExecutionStatus, UIMessage};
 
@@ -16,6 +19,8 @@ fn test_ui_state_server_status() {
         connected_workers: vec![],
         ready_execs: 1,
         waiting_execs: 123,
+        cache_hits: 4,
+        cache_misses: 2,
     };
     assert_eq!(ui.executor_status, None);
     ui.apply(UIMessage::ServerStatus {
@@ -24,6 +29,228 @@ fn test_ui_state_server_status() {
     assert_eq!(ui.executor_status, Some(status));
 }
 
+#[test]
+fn test_ui_state_file_transfer() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    let transfer = |sent| FileTransfer {
+        file: "input.txt".into(),
+        sent,
+        total: 100,
+    };
+    ui.apply(UIMessage::FileTransfer {
+        transfer: transfer(40),
+    });
+    assert_eq!(ui.file_transfer, Some(transfer(40)));
+    ui.apply(UIMessage::FileTransfer {
+        transfer: transfer(100),
+    });
+    assert_eq!(ui.file_transfer, None);
+}
+
+#[test]
+fn test_ui_state_cache_stats() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    let mut cached = utils::good_result();
+    cached.was_cached = true;
+    ui.apply(UIMessage::Compilation {
+        file: PathBuf::from("file"),
+        status: UIExecutionStatus::Done { result: cached },
+    });
+    ui.apply(UIMessage::IOIGeneration {
+        subtask: 0,
+        testcase: 0,
+        name: None,
+        status: UIExecutionStatus::Started {
+            worker: Default::default(),
+        },
+    });
+    ui.apply(UIMessage::IOIGeneration {
+        subtask: 0,
+        testcase: 0,
+        name: None,
+        status: UIExecutionStatus::Done {
+            result: utils::good_result(),
+        },
+    });
+    assert_eq!(ui.cache_stats.hits, 1);
+    assert_eq!(ui.cache_stats.misses, 1);
+    assert_eq!(ui.cache_stats.misses_by_kind["generations"], 1);
+}
+
+#[test]
+fn test_ui_state_timing_stability() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    let worker = |uuid, name: &str, timing_warnings: Vec<String>| ExecutorWorkerStatus {
+        uuid,
+        name: name.into(),
+        current_job: None,
+        telemetry: WorkerTelemetry {
+            timing_warnings,
+            ..Default::default()
+        },
+    };
+    let stable = WorkerUuid::from_u128(1);
+    let unstable = WorkerUuid::from_u128(2);
+    ui.apply(UIMessage::ServerStatus {
+        status: ExecutorStatus {
+            connected_workers: vec![
+                worker(stable, "stable", vec![]),
+                worker(
+                    unstable,
+                    "laptop",
+                    vec!["CPU thermal throttling detected".into()],
+                ),
+            ],
+            ready_execs: 0,
+            waiting_execs: 0,
+            cache_hits: 0,
+            cache_misses: 0,
+        },
+    });
+    for (part, worker) in [stable, unstable, unstable].into_iter().enumerate() {
+        ui.apply(UIMessage::IOIEvaluation {
+            subtask: 0,
+            testcase: 0,
+            solution: PathBuf::from("sol"),
+            status: UIExecutionStatus::Started { worker },
+            part,
+            num_parts: 3,
+        });
+    }
+    assert_eq!(ui.timing_stability.affected_executions(), 2);
+    let (warnings, count) = &ui.timing_stability.affected_workers["laptop"];
+    assert_eq!(
+        warnings,
+        &vec!["CPU thermal throttling detected".to_string()]
+    );
+    assert_eq!(*count, 2);
+}
+
+#[test]
+fn test_ui_state_evaluation_cached() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    let mut cached = utils::good_result();
+    cached.was_cached = true;
+    let evaluate = |ui: &mut UIState, part, result: ExecutionResult| {
+        ui.apply(UIMessage::IOIEvaluation {
+            subtask: 0,
+            testcase: 0,
+            solution: PathBuf::from("sol"),
+            status: UIExecutionStatus::Done { result },
+            part,
+            num_parts: 2,
+        });
+    };
+    evaluate(&mut ui, 0, cached.clone());
+    // the second part is still running
+    assert!(!ui.evaluations[Path::new("sol")].testcases[&0].was_cached());
+    evaluate(&mut ui, 1, cached);
+    assert!(ui.evaluations[Path::new("sol")].testcases[&0].was_cached());
+    evaluate(&mut ui, 1, utils::good_result());
+    assert!(!ui.evaluations[Path::new("sol")].testcases[&0].was_cached());
+}
+
+#[test]
+fn test_ui_state_close_to_limits() {
+    let mut task = utils::new_task();
+    task.time_limit = Some(1.0);
+    task.memory_limit = Some(64);
+    let resources = |cpu_time, memory| ExecutionResourcesUsage {
+        cpu_time,
+        memory,
+        ..Default::default()
+    };
+    let sol = Path::new("sol.cpp");
+    let ui = UIState::new(&task, Default::default());
+    assert_eq!(
+        ui.close_to_limits(sol, &resources(0.1, 1024)),
+        (false, false)
+    );
+    assert_eq!(
+        ui.close_to_limits(sol, &resources(0.7, 1024)),
+        (true, false)
+    );
+    assert_eq!(
+        ui.close_to_limits(sol, &resources(0.1, 40 * 1024)),
+        (false, true)
+    );
+
+    task.resource_threshold = Some(0.8);
+    let ui = UIState::new(&task, Default::default());
+    assert_eq!(
+        ui.close_to_limits(sol, &resources(0.7, 40 * 1024)),
+        (false, false)
+    );
+    assert_eq!(
+        ui.close_to_limits(sol, &resources(0.85, 60 * 1024)),
+        (true, true)
+    );
+
+    task.language_multipliers.insert(
+        "py".into(),
+        LanguageMultiplier {
+            time: 2.0,
+            memory: 1.0,
+        },
+    );
+    let ui = UIState::new(&task, Default::default());
+    let py = Path::new("sol.py");
+    assert_eq!(
+        ui.close_to_limits(py, &resources(0.85, 1024)),
+        (false, false)
+    );
+    assert_eq!(ui.close_to_limits(py, &resources(1.7, 1024)), (true, false));
+    assert_eq!(
+        ui.close_to_limits(sol, &resources(1.7, 1024)),
+        (false, false)
+    );
+    assert_eq!(task.time_limit_for(py), Some(2.0));
+    assert_eq!(task.memory_limit_for(py), Some(64));
+}
+
+#[test]
+fn test_ui_state_rankings() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    for (solution, testcase, cpu_time, memory) in [
+        ("a", 0, 0.5, 100),
+        ("a", 1, 0.1, 300),
+        ("b", 0, 0.9, 200),
+        ("b", 1, 0.3, 200),
+    ] {
+        let mut result = utils::good_result();
+        result.resources.cpu_time = cpu_time;
+        result.resources.memory = memory;
+        ui.apply(UIMessage::IOIEvaluation {
+            subtask: 0,
+            testcase,
+            solution: PathBuf::from(solution),
+            status: UIExecutionStatus::Done { result },
+            part: 0,
+            num_parts: 1,
+        });
+    }
+    let ranking = |evaluations: Vec<TestcaseResources>| {
+        evaluations
+            .into_iter()
+            .map(|e| (e.solution.to_string_lossy().to_string(), e.testcase))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        ranking(ui.slowest_evaluations(3)),
+        vec![("b".into(), 0), ("a".into(), 0), ("b".into(), 1)]
+    );
+    // the ties are sorted by solution and testcase
+    assert_eq!(
+        ranking(ui.largest_evaluations(3)),
+        vec![("a".into(), 1), ("b".into(), 0), ("b".into(), 1)]
+    );
+}
+
 #[test]
 fn test_ui_state_compilation_skipped() {
     let task = utils::new_task();
@@ -135,6 +362,7 @@ fn test_ui_state_generation_skipped() {
     ui.apply(UIMessage::IOIGeneration {
         subtask: 0,
         testcase: 0,
+        name: None,
         status: UIExecutionStatus::Skipped,
     });
     assert_eq!(
@@ -150,6 +378,7 @@ fn test_ui_state_generation_started() {
     ui.apply(UIMessage::IOIGeneration {
         subtask: 0,
         testcase: 0,
+        name: None,
         status: UIExecutionStatus::Started {
             worker: Default::default(),
         },
@@ -167,6 +396,7 @@ fn test_ui_state_generation_generated() {
     ui.apply(UIMessage::IOIGeneration {
         subtask: 0,
         testcase: 0,
+        name: None,
         status: UIExecutionStatus::Done {
             result: utils::good_result(),
         },
@@ -184,6 +414,7 @@ fn test_ui_state_generation_failed() {
     ui.apply(UIMessage::IOIGeneration {
         subtask: 0,
         testcase: 0,
+        name: None,
         status: UIExecutionStatus::Done {
             result: utils::bad_result(),
         },
@@ -260,6 +491,42 @@ fn test_ui_state_validation_failed() {
     );
 }
 
+#[test]
+fn test_ui_state_failed_stderr() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    ui.apply(UIMessage::IOIGenerationStderr {
+        subtask: 0,
+        testcase: 0,
+        content: "invalid seed".into(),
+    });
+    ui.apply(UIMessage::IOIValidationStderr {
+        subtask: 1,
+        testcase: 2,
+        content: "N too big".into(),
+    });
+    assert_eq!(ui.failed_stderr.len(), 2);
+    let last = ui.failed_stderr.last().unwrap();
+    assert_eq!(last.step, "Validation of testcase 2");
+    assert_eq!(last.content, "N too big");
+}
+
+#[test]
+fn test_ui_state_failed_stderr_named_testcase() {
+    let mut task = utils::new_task();
+    task.testcases.get_mut(&2).unwrap().name = Some("big-random".into());
+    let mut ui = UIState::new(&task, Default::default());
+    ui.apply(UIMessage::IOIGenerationStderr {
+        subtask: 1,
+        testcase: 2,
+        content: "invalid seed".into(),
+    });
+    assert_eq!(
+        ui.failed_stderr[0].step,
+        "Generation of testcase st1/big-random"
+    );
+}
+
 #[test]
 fn test_ui_state_solution_skipped() {
     let task = utils::new_task();
@@ -326,6 +593,44 @@ fn test_ui_state_solution_failed() {
     );
 }
 
+#[test]
+fn test_ui_state_solution_groups() {
+    let mut task = utils::new_task();
+    task.solution_groups = vec!["sol/".into(), "sol/wrong/".into(), "sol/slow_".into()];
+    let mut ui = UIState::new(&task, Default::default());
+    for solution in [
+        "sol/b.cpp",
+        "sol/wrong/a.cpp",
+        "sol/a.cpp",
+        "other.py",
+        "sol/slow_1.cpp",
+    ] {
+        ui.apply(UIMessage::IOIEvaluation {
+            subtask: 0,
+            testcase: 0,
+            solution: PathBuf::from(solution),
+            status: UIExecutionStatus::Skipped,
+            part: 0,
+            num_parts: 1,
+        });
+    }
+    let groups: Vec<_> = ui
+        .solution_groups()
+        .into_iter()
+        .map(|group| (group.prefix, group.solutions))
+        .collect();
+    let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+    assert_eq!(
+        groups,
+        vec![
+            (Some("sol/".into()), paths(&["sol/a.cpp", "sol/b.cpp"])),
+            (Some("sol/wrong/".into()), paths(&["sol/wrong/a.cpp"])),
+            (Some("sol/slow_".into()), paths(&["sol/slow_1.cpp"])),
+            (None, paths(&["other.py"])),
+        ]
+    );
+}
+
 #[test]
 fn test_ui_state_evaluation_skipped() {
     let task = utils::new_task();
@@ -605,6 +910,7 @@ fn test_ui_state_testcase_score_wrong_answer() {
     ui.apply(UIMessage::IOITestcaseScore {
         subtask: 0,
         testcase: 0,
+        name: None,
         solution: file.clone(),
         score: 0.0,
         message: "nope".to_string(),
@@ -624,6 +930,7 @@ fn test_ui_state_testcase_score_partial() {
     ui.apply(UIMessage::IOITestcaseScore {
         subtask: 0,
         testcase: 0,
+        name: None,
         solution: file.clone(),
         score: 0.5,
         message: "almost".to_string(),
@@ -643,6 +950,7 @@ fn test_ui_state_testcase_score_accepted() {
     ui.apply(UIMessage::IOITestcaseScore {
         subtask: 0,
         testcase: 0,
+        name: None,
         solution: file.clone(),
         score: 1.0,
         message: "yep".to_string(),
@@ -654,6 +962,32 @@ fn test_ui_state_testcase_score_accepted() {
     );
 }
 
+#[test]
+fn test_ui_state_checker_error() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    let file = PathBuf::from("file");
+    ui.apply(UIMessage::IOICheckerError {
+        subtask: 0,
+        testcase: 0,
+        solution: file.clone(),
+        message: "bugged".to_string(),
+    });
+    ui.apply(UIMessage::IOITestcaseScore {
+        subtask: 0,
+        testcase: 0,
+        name: None,
+        solution: file.clone(),
+        score: 0.0,
+        message: "bugged".to_string(),
+    });
+    assert_eq!(ui.evaluations[&file].testcases[&0].score, Some(0.0));
+    assert_eq!(
+        ui.evaluations[&file].testcases[&0].status,
+        TestcaseEvaluationStatus::CheckerError("bugged".into())
+    );
+}
+
 #[test]
 fn test_ui_state_subtask_score() {
     let task = utils::new_task();
@@ -27,6 +27,7 @@ fn test_score_manager() {
                 solution,
                 score,
                 message,
+                ..
             } => {
                 assert_eq!(subtask, 0);
                 assert_eq!(testcase, 0);
@@ -70,6 +71,7 @@ fn test_score_manager() {
                 solution,
                 score,
                 message,
+                ..
             } => {
                 assert_eq!(subtask, 1);
                 assert_eq!(testcase, 1);
@@ -95,6 +97,7 @@ fn test_score_manager() {
                 solution,
                 score,
                 message,
+                ..
             } => {
                 assert_eq!(subtask, 1);
                 assert_eq!(testcase, 2);
@@ -179,6 +182,7 @@ fn test_score_manager_empty_subtask() {
                 solution,
                 score,
                 message,
+                ..
             } => {
                 assert_eq!(subtask, 0);
                 assert_eq!(testcase, 0);
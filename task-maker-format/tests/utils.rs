@@ -22,7 +22,8 @@ pub fn new_task_with_context(path: &Path) -> IOITask {
         path: path.into(),
         task_type: TaskType::Batch(BatchTypeData {
             output_generator: None,
-            checker: Checker::WhiteDiff,
+            checker: Checker::WhiteDiff(Default::default()),
+            two_pass: false,
         }),
         name: "task".to_string(),
         title: "The Task".to_string(),
@@ -39,6 +40,14 @@ pub fn new_task_with_context(path: &Path) -> IOITask {
         booklets: vec![],
         difficulty: None,
         syllabus_level: None,
+        no_cache_failures: vec![],
+        solution_groups: vec![],
+        resource_threshold: None,
+        language_multipliers: HashMap::new(),
+        time_clock: None,
+        memory_metric: Default::default(),
+        input_format: Default::default(),
+        input_constraints: Default::default(),
         sanity_checks: Arc::new(get_sanity_checks(&[])),
     };
     task.testcases.entry(0).or_insert(TestcaseInfo::new(
@@ -88,6 +97,10 @@ pub fn good_result() -> ExecutionResult {
         },
         stdout: None,
         stderr: None,
+        time_scale: 1.0,
+        cpu: None,
+        run_times: vec![],
+        memory_metric: Default::default(),
     }
 }
 
@@ -104,5 +117,9 @@ pub fn bad_result() -> ExecutionResult {
         },
         stdout: None,
         stderr: None,
+        time_scale: 1.0,
+        cpu: None,
+        run_times: vec![],
+        memory_metric: Default::default(),
     }
 }
@@ -162,19 +162,32 @@ impl SourceFile {
             }
         }
         exec.args(self.language.runtime_args(&self.path, write_to, args));
+        let executable_name = self.language.executable_name(&self.path, write_to);
         exec.input(
             self.executable.lock().unwrap().as_ref().unwrap(),
-            self.language.executable_name(&self.path, write_to),
+            &executable_name,
             true,
         );
+        // without a compilation the same script, with the same dependencies, is run every time, so
+        // the workers can keep it in a prepared sandbox
+        let interpreted = !self.language.need_compilation();
+        if interpreted {
+            exec.runtime_input(executable_name);
+        }
         for dep in self.language.runtime_dependencies(&self.path) {
             exec.input(&dep.file, &dep.sandbox_path, dep.executable);
+            if interpreted {
+                exec.runtime_input(&dep.sandbox_path);
+            }
             dag.provide_file(dep.file, &dep.local_path)
                 .context("Failed to provide dependency")?;
         }
         if let Some(grader_map) = self.grader_map.as_ref() {
             for dep in grader_map.get_runtime_deps(self.language.as_ref()) {
                 exec.input(&dep.file, &dep.sandbox_path, dep.executable);
+                if interpreted {
+                    exec.runtime_input(&dep.sandbox_path);
+                }
                 exec.args = self.language.runtime_add_file(exec.args, &dep.sandbox_path);
                 dag.provide_file(dep.file, &dep.local_path)
                     .context("Failed to provide grader dependency")?;
@@ -325,6 +338,7 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
     use std::io::Write;
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::Arc;
@@ -351,6 +365,7 @@ mod tests {
         let source = SourceFile::new(&source_path, "", None, Some(cwd.path().join("bin"))).unwrap();
         let (comp, exec) = source.execute(&mut dag, "Testing exec", vec![]).unwrap();
         assert!(comp.is_some());
+        assert!(exec.runtime_inputs.is_empty());
 
         let exec_start = Arc::new(AtomicBool::new(false));
         let exec_start2 = exec_start.clone();
@@ -387,4 +402,25 @@ mod tests {
         assert!(!exec_skipped.load(Ordering::Relaxed));
         assert!(cwd.path().join("bin").exists());
     }
+
+    #[test]
+    fn test_source_file_python_runtime_inputs() {
+        let cwd = TempDir::new().unwrap();
+        let source_path = cwd.path().join("source.py");
+        std::fs::write(&source_path, "#!/usr/bin/env python3\nimport lib\n").unwrap();
+        std::fs::write(cwd.path().join("lib.py"), "").unwrap();
+
+        let mut dag = ExecutionDAG::new();
+        let source = SourceFile::new(&source_path, "", None, None::<PathBuf>).unwrap();
+        let (comp, exec) = source.execute(&mut dag, "Testing exec", vec![]).unwrap();
+        assert!(comp.is_none());
+        let runtime_inputs: HashSet<_> = exec.runtime_inputs.iter().cloned().collect();
+        let expected: HashSet<_> = [PathBuf::from("source"), PathBuf::from("lib.py")]
+            .into_iter()
+            .collect();
+        assert_eq!(runtime_inputs, expected);
+        for path in &exec.runtime_inputs {
+            assert!(exec.inputs.contains_key(path));
+        }
+    }
 }
@@ -1,6 +1,6 @@
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 use std::collections::hash_map::Entry;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fs::{create_dir_all, remove_dir, File};
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
@@ -107,6 +107,18 @@ impl FileStoreIndex {
         Ok(())
     }
 
+    /// The keys of all the files in the index.
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &FileStoreKey> {
+        self.known_files.keys()
+    }
+
+    /// Remove a file from the index, without removing it from the disk.
+    pub(crate) fn remove(&mut self, key: &FileStoreKey) {
+        if let Some(file) = self.known_files.remove(key) {
+            self.total_size -= file.size;
+        }
+    }
+
     /// Mark a file as accessed, bumping its position in the LRU.
     pub(crate) fn touch(&mut self, key: &FileStoreKey) {
         if let Some(file) = self.known_files.get_mut(key) {
@@ -139,14 +151,21 @@ impl FileStoreIndex {
         self.total_size >= size_limit
     }
 
+    /// The sum of the size of all the files in the index.
+    pub(crate) fn total_size(&self) -> u64 {
+        self.total_size
+    }
+
     /// Perform a flushing operation, cleaning some space on the disk by removing the Least Recently
-    /// Used files. This function won't remove the files currently locked.
+    /// Used files. This function won't remove the files currently locked, nor the ones in `keep`.
+    /// Returns the number of bytes removed.
     pub(crate) fn flush(
         &mut self,
         file_store: &FileStore,
         locked_files: &LockedFiles,
+        keep: &HashSet<FileStoreKey>,
         target_size: u64,
-    ) -> Result<(), Error> {
+    ) -> Result<u64, Error> {
         debug!(
             "Starting flushing process from {}MiB to at most {}MiB",
             self.total_size / 1024 / 1024,
@@ -154,26 +173,32 @@ impl FileStoreIndex {
         );
         // list of entries that survive the flush
         let mut surviving = Vec::new();
-        let mut priority_queue: BinaryHeap<(FileStoreIndexItem, FileStoreKey)> =
-            self.known_files.drain().map(|(k, f)| (f, k)).collect();
+        // min-heap on the last access, so that the least recently used files are popped first
+        let mut priority_queue: BinaryHeap<Reverse<(FileStoreIndexItem, FileStoreKey)>> = self
+            .known_files
+            .drain()
+            .map(|(k, f)| Reverse((f, k)))
+            .collect();
         // number of removed bytes
         let mut removed = 0;
         // continue to remove until the space requirement is met
         while self.total_size > target_size {
             let (entry, key) = match priority_queue.pop() {
-                Some(e) => e,
+                Some(Reverse(e)) => e,
                 // the queue is emptied before reaching the space requirement (maybe because of
                 // locking)
                 None => break,
             };
             // cannot remove a file used by some other process
-            if locked_files.ref_counts.contains_key(&key) {
+            if locked_files.ref_counts.contains_key(&key) || keep.contains(&key) {
                 surviving.push((key, entry));
             } else {
                 self.total_size -= entry.size;
                 removed += entry.size;
 
-                let path = file_store.key_to_path(&key);
+                let path = file_store
+                    .stored_path(&key)
+                    .unwrap_or_else(|| file_store.key_to_path(&key));
                 debug!("Removing file {:?} claiming {}KiB", path, entry.size / 1024);
                 if let Err(e) = FileStore::remove_file(&path) {
                     warn!("Cannot flush file {:?}: {}", path, e.to_string());
@@ -205,15 +230,16 @@ impl FileStoreIndex {
             self.known_files.insert(key, entry);
         }
         // the files that survived the flush because are at new enough
-        for (entry, key) in priority_queue {
+        for Reverse((entry, key)) in priority_queue {
             self.known_files.insert(key, entry);
         }
-        Ok(())
+        Ok(removed)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
     use std::fs::File;
     use std::io::Write;
     use std::path::Path;
@@ -329,7 +355,7 @@ mod tests {
         // force flush because the last store did a flush removing the 90
         let mut index = store.index.lock().unwrap();
         let locked = store.locked_files.lock().unwrap();
-        index.flush(&store, &locked, 100).unwrap();
+        index.flush(&store, &locked, &HashSet::new(), 100).unwrap();
 
         assert_eq!(index.total_size, 90);
         assert_eq!(index.known_files.len(), 1);
@@ -338,6 +364,25 @@ mod tests {
         assert!(!store.key_to_path(&key3).exists());
     }
 
+    #[test]
+    fn test_flush_least_recently_used() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 200, 100).unwrap();
+        let key1 = add_file_to_store(&store, 40).key.clone();
+        let key2 = add_file_to_store(&store, 50).key.clone();
+        let key3 = add_file_to_store(&store, 60).key.clone();
+        store.get(&key1).unwrap();
+
+        let mut index = store.index.lock().unwrap();
+        let locked = store.locked_files.lock().unwrap();
+        index.flush(&store, &locked, &HashSet::new(), 90).unwrap();
+
+        assert_eq!(index.total_size, 40);
+        assert!(store.key_to_path(&key1).exists());
+        assert!(!store.key_to_path(&key2).exists());
+        assert!(!store.key_to_path(&key3).exists());
+    }
+
     #[test]
     fn test_flush_touch() {
         let cwd = get_cwd();
@@ -6,6 +6,14 @@
 //!
 //! The access to the store directory via this crate is exclusive even between processes.
 //!
+//! A store can be shared by many users of the same group: in that case all the files and the
+//! directories it creates are writable by the group, and the directories have the setgid bit so
+//! that the group is inherited.
+//!
+//! The text files (e.g. the input and output files of the testcases) are stored compressed with
+//! zstd, and decompressed when their content is read or materialized outside of the store. The
+//! binary files are stored as they are, so that they can be hard-linked.
+//!
 //! # Example
 //!
 //! Storing a file into the store and getting it back later.
@@ -41,12 +49,14 @@
 extern crate log;
 
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Formatter;
-use std::io::Write;
+use std::fs::{File, OpenOptions, Permissions};
+use std::io::{Read, Write};
 use std::os::unix::prelude::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::{bail, Context, Error};
 use blake3::{hash, Hash, Hasher};
@@ -65,6 +75,25 @@ const INTEGRITY_CHECKS_ENABLED: bool = false;
 const STORE_LOCK_FILE: &str = "exclusive.lock";
 /// The name of the index of the file store.
 const STORE_INDEX_FILE: &str = "index.bin";
+/// The name of the directory with the files received only partially.
+const STORE_PARTIAL_DIR: &str = "partial";
+/// How long the partial file of an interrupted transfer is kept, waiting for it to be resumed.
+const PARTIAL_TRANSFER_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+/// The extension of the files stored compressed.
+const COMPRESSED_EXTENSION: &str = "zst";
+/// The zstd compression level of the stored files.
+const COMPRESSION_LEVEL: i32 = 3;
+/// The files smaller than this are not worth compressing.
+const COMPRESSION_MIN_SIZE: u64 = 4 * 1024;
+/// How many bytes at the beginning of a file are checked to tell if it's a text file.
+const COMPRESSION_SNIFF_SIZE: u64 = 8 * 1024;
+/// The permissions of the directories of a shared store: writable by the group, and with the
+/// setgid bit so that the new files inherit the group.
+const SHARED_DIR_MODE: u32 = 0o2775;
+/// The permissions of the mutable files of a shared store (e.g. the index).
+const SHARED_FILE_MODE: u32 = 0o664;
+/// The permissions of the files stored in a shared store.
+const SHARED_STORED_FILE_MODE: u32 = 0o444;
 
 /// Container with the ref counts of all the handles still alive.
 #[derive(Debug)]
@@ -94,6 +123,23 @@ pub struct FileStore {
     max_store_size: u64,
     /// Target size of the file store after the flush.
     min_store_size: u64,
+    /// The files currently being received with `store_resumable`.
+    partial_transfers: Mutex<HashSet<FileStoreKey>>,
+    /// Whether the new text files are stored compressed.
+    compression: bool,
+    /// Whether the store is shared with the other users of the group.
+    shared: bool,
+}
+
+/// The outcome of the verification of the files in a `FileStore`.
+#[derive(Debug, Clone, Default)]
+pub struct StoreVerification {
+    /// The number of files checked.
+    pub checked: usize,
+    /// The files known to the index but missing from the disk.
+    pub missing: Vec<FileStoreKey>,
+    /// The files whose content doesn't match their key, removed from the disk.
+    pub corrupted: Vec<FileStoreKey>,
 }
 
 /// Handle of a file in the `FileStore`, this must be computable given the content of the file, i.e.
@@ -113,6 +159,10 @@ pub struct FileStoreHandle {
     key: FileStoreKey,
     /// The path to the file on disk.
     path: PathBuf,
+    /// Whether the file on disk is compressed.
+    compressed: bool,
+    /// Whether the file is in a shared store, so it cannot be hard-linked outside of the store.
+    shared: bool,
     /// A reference to the locked files. Will be used to remove self from the ref counts.
     locked_files: Arc<Mutex<LockedFiles>>,
 }
@@ -173,9 +223,33 @@ impl FileStore {
             index: Arc::new(Mutex::new(index)),
             max_store_size,
             min_store_size,
+            partial_transfers: Mutex::new(HashSet::new()),
+            compression: true,
+            shared: false,
         })
     }
 
+    /// Make the store usable by all the users of the group of its directory. The new files and
+    /// directories are made writable by the group, and so are the ones already created by this
+    /// user.
+    ///
+    /// The other users must be able to enter the base directory of the store, which should belong
+    /// to their group.
+    pub fn set_shared(&mut self, shared: bool) {
+        self.shared = shared;
+        if shared {
+            FileStore::set_shared_mode(&self.base_path, SHARED_DIR_MODE);
+            FileStore::set_shared_mode(&self.base_path.join(STORE_LOCK_FILE), SHARED_FILE_MODE);
+            FileStore::set_shared_mode(&self.base_path.join(STORE_INDEX_FILE), SHARED_FILE_MODE);
+        }
+    }
+
+    /// Enable or disable the compression of the text files added to the store from now on. The
+    /// files already in the store are not changed. The compression is enabled by default.
+    pub fn set_compression(&mut self, compression: bool) {
+        self.compression = compression;
+    }
+
     /// Given an iterator of `Vec<u8>` consume all of it writing the content to the disk if the file
     /// is not already present on disk. The file is stored inside the base directory and `chmod -w`.
     ///
@@ -215,15 +289,13 @@ impl FileStore {
         let path = self.key_to_path(key);
         trace!("Storing {:?}", path);
         // make the key to avoid racing while writing
-        let handle = FileStoreHandle::new(self, key);
-        if path.exists() {
+        let mut handle = FileStoreHandle::new(self, key);
+        if self.stored_path(key).is_some() {
             trace!("File {:?} already exists", path);
             content.into_iter().last(); // consume all the iterator
         } else {
             // assuming moving files is atomic this should be MT-safe
-            let dir = path.parent().unwrap();
-            std::fs::create_dir_all(dir)
-                .with_context(|| format!("Cannot create directory at {}", dir.display()))?;
+            self.create_dir(path.parent().unwrap())?;
             let tmpdir = tempfile::TempDir::new_in(path.parent().unwrap())
                 .context("Failed to create temporary directory for storing the file")?;
             let tmpfile_path = tmpdir.path().join("file");
@@ -236,29 +308,214 @@ impl FileStore {
             {
                 bail!("Failed to store file");
             }
-            std::fs::rename(&tmpfile_path, &path).with_context(|| {
+            self.add_file(key, &tmpfile_path)?;
+            handle.locate(self);
+        }
+        Ok(handle)
+    }
+
+    /// Add the file at `path` to the store without sending its content through memory, like
+    /// [`store`](struct.FileStore.html#method.store) does: the file is reflinked when the
+    /// filesystem supports it, and copied otherwise. `path` is left untouched.
+    ///
+    /// The file is never hard-linked, since the original may be changed after it's stored. The
+    /// key may have been computed before the file changed, so the stored copy is checked against
+    /// the key before adding it to the store.
+    pub fn store_file(&self, key: &FileStoreKey, path: &Path) -> Result<FileStoreHandle, Error> {
+        let mut handle = FileStoreHandle::new(self, key);
+        if self.stored_path(key).is_some() {
+            trace!("File {:?} already exists", path);
+            return Ok(handle);
+        }
+        let (_tmpdir, tmpfile_path) = self.import_file(path, false)?;
+        if FileStoreKey::from_file(&tmpfile_path)? != *key {
+            bail!(
+                "The content of {} doesn't match its key, it has changed",
+                path.display()
+            );
+        }
+        self.add_file(key, &tmpfile_path)?;
+        handle.locate(self);
+        Ok(handle)
+    }
+
+    /// Move the file at `path` inside the store, without copying its content, returning its key.
+    /// When the file is on a different filesystem it is copied instead. The file is moved even if
+    /// the store already has it.
+    ///
+    /// The key is computed after the file is moved, so it always matches the stored content.
+    pub fn store_moving(&self, path: &Path) -> Result<(FileStoreKey, FileStoreHandle), Error> {
+        let (_tmpdir, tmpfile_path) = self.import_file(path, true)?;
+        let key = FileStoreKey::from_file(&tmpfile_path)?;
+        let mut handle = FileStoreHandle::new(self, &key);
+        if self.stored_path(&key).is_some() {
+            trace!("File {:?} already exists", path);
+        } else {
+            self.add_file(&key, &tmpfile_path)?;
+            handle.locate(self);
+        }
+        Ok((key, handle))
+    }
+
+    /// Copy the file at `path`, or move it if `move_file` is set, in a temporary directory of the
+    /// store, that nobody else writes to. The temporary directory is removed when dropped.
+    fn import_file(
+        &self,
+        path: &Path,
+        move_file: bool,
+    ) -> Result<(tempfile::TempDir, PathBuf), Error> {
+        let tmpdir = tempfile::TempDir::new_in(&self.base_path)
+            .context("Failed to create temporary directory for storing the file")?;
+        let tmpfile_path = tmpdir.path().join("file");
+        // renaming works only inside the same filesystem
+        if !move_file || std::fs::rename(path, &tmpfile_path).is_err() {
+            reflink_copy::reflink_or_copy(path, &tmpfile_path).with_context(|| {
                 format!(
-                    "Failed to rename {} -> {}",
-                    tmpfile_path.display(),
-                    path.display()
+                    "Failed to copy {} -> {}",
+                    path.display(),
+                    tmpfile_path.display()
                 )
             })?;
-            FileStore::mark_readonly(&path).context("Failed to mark file as readonly")?;
-            {
-                let mut index = self.index.lock().unwrap();
-                index
-                    .add(key.clone(), path)
-                    .context("Failed to add file to index")?;
-                self.maybe_flush(&mut index)?;
-                // FIXME: maybe this can be done less frequently
-                index
-                    .store(self.base_path.join(STORE_INDEX_FILE))
-                    .context("Failed to store the index to file")?;
+        }
+        Ok((tmpdir, tmpfile_path))
+    }
+
+    /// The number of bytes of the file with that key already received by a transfer that has been
+    /// interrupted, and that can be resumed using
+    /// [`store_resumable`](struct.FileStore.html#method.store_resumable).
+    pub fn partial_size(&self, key: &FileStoreKey) -> u64 {
+        std::fs::metadata(self.partial_path(key))
+            .map(|metadata| metadata.len())
+            .unwrap_or(0)
+    }
+
+    /// Store a file whose first `offset` bytes may have been already received by an interrupted
+    /// transfer. The iterator yields the content of the file starting from `offset`.
+    ///
+    /// If the iterator fails, the data received so far is kept on disk, and a later call can
+    /// resume from [`partial_size`](struct.FileStore.html#method.partial_size). When all the
+    /// content is received, it is checked against the key before being added to the store.
+    pub fn store_resumable<I>(
+        &self,
+        key: &FileStoreKey,
+        offset: u64,
+        content: I,
+    ) -> Result<FileStoreHandle, Error>
+    where
+        I: IntoIterator<Item = Result<Vec<u8>, Error>>,
+    {
+        let mut handle = FileStoreHandle::new(self, key);
+        if let Some(path) = self.stored_path(key) {
+            trace!("File {:?} already exists", path);
+            for chunk in content {
+                chunk.context("Failed to receive file")?;
             }
+            return Ok(handle);
+        }
+        // only a transfer at the time can write to the partial file, the others start from scratch
+        let exclusive = self.partial_transfers.lock().unwrap().insert(key.clone());
+        let res = if exclusive {
+            self.receive_partial(key, &self.partial_path(key), offset, content)
+        } else {
+            let tmpdir = tempfile::TempDir::new_in(&self.base_path)
+                .context("Failed to create temporary directory for storing the file")?;
+            self.receive_partial(key, &tmpdir.path().join("file"), 0, content)
+        };
+        if exclusive {
+            self.partial_transfers.lock().unwrap().remove(key);
         }
+        res?;
+        handle.locate(self);
         Ok(handle)
     }
 
+    /// Append the content to the partial file truncated at `offset`, then move it inside the store
+    /// if the file is complete.
+    fn receive_partial<I>(
+        &self,
+        key: &FileStoreKey,
+        partial_path: &Path,
+        offset: u64,
+        content: I,
+    ) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Result<Vec<u8>, Error>>,
+    {
+        self.create_dir(partial_path.parent().unwrap())?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(partial_path)
+            .with_context(|| format!("Failed to open {}", partial_path.display()))?;
+        // another user may resume the transfer
+        if self.shared {
+            FileStore::set_shared_mode(partial_path, SHARED_FILE_MODE);
+        }
+        let received = file
+            .metadata()
+            .context("Failed to get the partial file metadata")?
+            .len();
+        if received < offset {
+            bail!(
+                "Cannot resume the transfer of {} from {}, only {} bytes were received",
+                key,
+                offset,
+                received
+            );
+        }
+        file.set_len(offset)
+            .context("Failed to truncate the partial file")?;
+        for chunk in content {
+            let chunk = chunk.context("Failed to receive file")?;
+            file.write_all(&chunk)
+                .context("Failed to write the partial file")?;
+        }
+        drop(file);
+        let actual = FileStoreKey::from_file(partial_path)?;
+        if &actual != key {
+            std::fs::remove_file(partial_path)
+                .with_context(|| format!("Failed to remove {}", partial_path.display()))?;
+            bail!("Received file has key {} instead of {}", actual, key);
+        }
+        self.add_file(key, partial_path)
+    }
+
+    /// Move a complete file inside the store, compressing it if needed, and adding it to the index.
+    fn add_file(&self, key: &FileStoreKey, source: &Path) -> Result<(), Error> {
+        // assuming moving files is atomic this should be MT-safe
+        let mut path = self.key_to_path(key);
+        self.create_dir(path.parent().unwrap())?;
+        let mut source = source.to_path_buf();
+        if self.compression && FileStore::is_compressible(&source)? {
+            let compressed = source.with_extension(COMPRESSED_EXTENSION);
+            FileStore::compress_file(&source, &compressed)?;
+            std::fs::remove_file(&source)
+                .with_context(|| format!("Failed to remove {}", source.display()))?;
+            source = compressed;
+            path = self.compressed_path(key);
+        }
+        std::fs::rename(&source, &path).with_context(|| {
+            format!(
+                "Failed to rename {} -> {}",
+                source.display(),
+                path.display()
+            )
+        })?;
+        if self.shared {
+            std::fs::set_permissions(&path, Permissions::from_mode(SHARED_STORED_FILE_MODE))
+                .with_context(|| format!("Failed to set permission of {}", path.display()))?;
+        } else {
+            FileStore::mark_readonly(&path).context("Failed to mark file as readonly")?;
+        }
+        let mut index = self.index.lock().unwrap();
+        index
+            .add(key.clone(), path)
+            .context("Failed to add file to index")?;
+        self.maybe_flush(&mut index)?;
+        // FIXME: maybe this can be done less frequently
+        self.save_index(&index)
+    }
+
     /// Returns an handle to the file with that key or `None` if it's not in the
     /// [`FileStore`](struct.FileStore.html).
     ///
@@ -291,10 +548,7 @@ impl FileStore {
     /// # }
     /// ```
     pub fn get(&self, key: &FileStoreKey) -> Option<FileStoreHandle> {
-        let path = self.key_to_path(key);
-        if !path.exists() {
-            return None;
-        }
+        let path = self.stored_path(key)?;
         if INTEGRITY_CHECKS_ENABLED && !self.check_integrity(key) {
             warn!("File {:?} failed the integrity check", path);
             if let Err(e) = FileStore::remove_file(&path) {
@@ -309,11 +563,211 @@ impl FileStore {
         Some(FileStoreHandle::new(self, key))
     }
 
+    /// Whether the file is present in the store. Unlike `get`, this doesn't count as an access to
+    /// the file.
+    pub fn contains(&self, key: &FileStoreKey) -> bool {
+        self.stored_path(key).is_some()
+    }
+
+    /// The keys of all the files in the store.
+    pub fn keys(&self) -> Vec<FileStoreKey> {
+        self.index.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// The total size of the files in the store.
+    pub fn total_size(&self) -> u64 {
+        self.index.lock().unwrap().total_size()
+    }
+
+    /// Remove the least recently used files until the store is at most `retention_size` bytes.
+    /// The files with an handle still alive and the ones in `keep` are never removed. The partial
+    /// files of the transfers interrupted long ago are removed too. Returns the number of bytes
+    /// removed.
+    pub fn collect_garbage(
+        &self,
+        retention_size: u64,
+        keep: &HashSet<FileStoreKey>,
+    ) -> Result<u64, Error> {
+        let partial = self.remove_stale_partials(PARTIAL_TRANSFER_TIMEOUT);
+        let mut index = self.index.lock().unwrap();
+        let removed = {
+            let locked = self.locked_files.lock().unwrap();
+            index
+                .flush(self, &locked, keep, retention_size)
+                .context("Failed to flush index")?
+        };
+        self.save_index(&index)?;
+        Ok(removed + partial)
+    }
+
+    /// Remove the partial files of the interrupted transfers that haven't been resumed for
+    /// `timeout`. They are not in the index, so they would never be flushed otherwise. Returns the
+    /// number of bytes removed.
+    fn remove_stale_partials(&self, timeout: Duration) -> u64 {
+        let entries = match std::fs::read_dir(self.base_path.join(STORE_PARTIAL_DIR)) {
+            Ok(entries) => entries,
+            Err(_) => return 0,
+        };
+        // keep the lock, so that no transfer starts writing a file while it's removed
+        let transfers = self.partial_transfers.lock().unwrap();
+        let mut removed = 0;
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            if transfers.iter().any(|key| *name == *key.to_string()) {
+                continue;
+            }
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.elapsed().ok());
+            let stale = age.is_some_and(|age| age >= timeout);
+            if !stale {
+                continue;
+            }
+            match std::fs::remove_file(entry.path()) {
+                Ok(()) => removed += metadata.len(),
+                Err(e) => warn!("Cannot remove {}: {:?}", entry.path().display(), e),
+            }
+        }
+        removed
+    }
+
+    /// Hash again all the files in the store, checking that their content matches their key. The
+    /// corrupted files are removed from the disk, and they are forgotten by the index together
+    /// with the files missing from the disk, so that they are produced again when needed.
+    pub fn verify(&self) -> Result<StoreVerification, Error> {
+        let keys: Vec<_> = self.index.lock().unwrap().keys().cloned().collect();
+        let mut verification = StoreVerification {
+            checked: keys.len(),
+            ..Default::default()
+        };
+        for key in keys {
+            let path = match self.stored_path(&key) {
+                Some(path) => path,
+                None => {
+                    warn!("File {} is missing from the store", key);
+                    verification.missing.push(key);
+                    continue;
+                }
+            };
+            if !matches!(FileStore::stored_key(&path), Ok(actual) if actual == key) {
+                warn!("File {:?} failed the integrity check", path);
+                FileStore::remove_file(&path).context("Cannot remove corrupted file")?;
+                verification.corrupted.push(key);
+            }
+        }
+        let mut index = self.index.lock().unwrap();
+        for key in verification.missing.iter().chain(&verification.corrupted) {
+            index.remove(key);
+        }
+        self.save_index(&index)?;
+        Ok(verification)
+    }
+
     /// Path of the file to disk.
     fn key_to_path(&self, key: &FileStoreKey) -> PathBuf {
         self.base_path.join(key.suffix())
     }
 
+    /// Path of the file to disk, when it's stored compressed.
+    fn compressed_path(&self, key: &FileStoreKey) -> PathBuf {
+        self.key_to_path(key).with_extension(COMPRESSED_EXTENSION)
+    }
+
+    /// Path of the file actually stored on disk, either compressed or not, if any.
+    fn stored_path(&self, key: &FileStoreKey) -> Option<PathBuf> {
+        let compressed = self.compressed_path(key);
+        if compressed.exists() {
+            return Some(compressed);
+        }
+        let path = self.key_to_path(key);
+        if path.exists() {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a file is worth compressing: only the text files that are big enough. The binary
+    /// files (e.g. the executables) compress less, and are hard-linked when used.
+    fn is_compressible(path: &Path) -> Result<bool, Error> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let size = file
+            .metadata()
+            .with_context(|| format!("Failed to get file metadata of {}", path.display()))?
+            .len();
+        if size < COMPRESSION_MIN_SIZE {
+            return Ok(false);
+        }
+        let mut head = Vec::new();
+        file.take(COMPRESSION_SNIFF_SIZE)
+            .read_to_end(&mut head)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(!head.contains(&0))
+    }
+
+    /// Compress the file at `source` writing it to `dest`.
+    fn compress_file(source: &Path, dest: &Path) -> Result<(), Error> {
+        let source_file =
+            File::open(source).with_context(|| format!("Failed to open {}", source.display()))?;
+        let dest_file =
+            File::create(dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+        zstd::stream::copy_encode(source_file, dest_file, COMPRESSION_LEVEL)
+            .with_context(|| format!("Failed to compress {}", source.display()))?;
+        Ok(())
+    }
+
+    /// Path of the partially received file on disk.
+    fn partial_path(&self, key: &FileStoreKey) -> PathBuf {
+        self.base_path.join(STORE_PARTIAL_DIR).join(key.to_string())
+    }
+
+    /// Create a directory inside the store, with its parents. If the store is shared, the
+    /// directories are made writable by the group.
+    fn create_dir(&self, dir: &Path) -> Result<(), Error> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Cannot create directory at {}", dir.display()))?;
+        if self.shared {
+            for dir in dir
+                .ancestors()
+                .take_while(|d| d.starts_with(&self.base_path))
+            {
+                FileStore::set_shared_mode(dir, SHARED_DIR_MODE);
+            }
+        }
+        Ok(())
+    }
+
+    /// Store the index to its file, writable by the group if the store is shared.
+    fn save_index(&self, index: &FileStoreIndex) -> Result<(), Error> {
+        let path = self.base_path.join(STORE_INDEX_FILE);
+        index
+            .store(&path)
+            .context("Failed to store the index to file")?;
+        if self.shared {
+            FileStore::set_shared_mode(&path, SHARED_FILE_MODE);
+        }
+        Ok(())
+    }
+
+    /// Change the permissions of a file of a shared store, if not already set. Only the owner of
+    /// the file can change them, so the failures are ignored: the file has already been shared by
+    /// its owner.
+    fn set_shared_mode(path: &Path, mode: u32) {
+        if let Ok(metadata) = std::fs::metadata(path) {
+            if metadata.permissions().mode() & 0o7777 != mode {
+                if let Err(e) = std::fs::set_permissions(path, Permissions::from_mode(mode)) {
+                    debug!("Cannot share {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+
     /// Mark a file as readonly.
     fn mark_readonly(path: &Path) -> Result<(), Error> {
         let mut perms = std::fs::metadata(path)
@@ -331,8 +785,11 @@ impl FileStore {
             .with_context(|| format!("Failed to get file metadata of {}", path.display()))?
             .permissions();
         perms.set_mode(0o600);
-        std::fs::set_permissions(path, perms)
-            .with_context(|| format!("Failed to set permission of {}", path.display()))?;
+        // in a shared store the file may belong to another user, but the permissions of the
+        // directory are enough to remove it
+        if let Err(e) = std::fs::set_permissions(path, perms) {
+            debug!("Failed to set permission of {}: {}", path.display(), e);
+        }
         std::fs::remove_file(path)
             .with_context(|| format!("Failed to remove {}", path.display()))?;
         Ok(())
@@ -340,7 +797,10 @@ impl FileStore {
 
     /// Check if the file is not corrupted.
     fn check_integrity(&self, key: &FileStoreKey) -> bool {
-        let path = self.key_to_path(key);
+        let path = match self.stored_path(key) {
+            Some(path) => path,
+            None => return false,
+        };
         let metadata = std::fs::metadata(&path);
         // if the last modified time is the same of creation time assume it's
         // not corrupted
@@ -353,18 +813,35 @@ impl FileStore {
                 }
             }
         }
-        match FileStoreKey::from_file(&path) {
+        match FileStore::stored_key(&path) {
             Ok(key2) => key2.hash == key.hash,
             Err(_) => false,
         }
     }
 
+    /// Compute the key of a file of the store from its content, decompressing it if needed.
+    fn stored_key(path: &Path) -> Result<FileStoreKey, Error> {
+        let content = if path.extension() == Some(COMPRESSED_EXTENSION.as_ref()) {
+            ReadFileIterator::new_compressed(path)?
+        } else {
+            ReadFileIterator::new(path)?
+        };
+        let mut hasher = Hasher::new();
+        for buf in content {
+            hasher.update(&buf);
+        }
+        Ok(FileStoreKey {
+            hash: hasher.finalize(),
+        })
+    }
+
     /// Check if the file store needs flushing, and do so if needed.
     fn maybe_flush(&self, index: &mut FileStoreIndex) -> Result<(), Error> {
         if index.need_flush(self.max_store_size) {
+            self.remove_stale_partials(PARTIAL_TRANSFER_TIMEOUT);
             let locked = self.locked_files.lock().unwrap();
             index
-                .flush(self, &locked, self.min_store_size)
+                .flush(self, &locked, &HashSet::new(), self.min_store_size)
                 .context("Failed to flush index")?;
         }
         Ok(())
@@ -383,11 +860,12 @@ impl Drop for FileStore {
                     }
                 };
                 if index.need_flush(self.max_store_size) {
-                    if let Err(e) = index.flush(self, &locked, self.min_store_size) {
+                    if let Err(e) = index.flush(self, &locked, &HashSet::new(), self.min_store_size)
+                    {
                         warn!("Cannot flush the index: {}", e.to_string());
                     }
                 }
-                if let Err(e) = index.store(self.base_path.join(STORE_INDEX_FILE)) {
+                if let Err(e) = self.save_index(&index) {
                     warn!("Cannot store the index: {}", e.to_string());
                 }
             }
@@ -486,21 +964,94 @@ impl<'de> Deserialize<'de> for FileStoreKey {
 impl FileStoreHandle {
     /// Make a new handle to a file on disk.
     fn new(store: &FileStore, key: &FileStoreKey) -> FileStoreHandle {
-        let path = store.key_to_path(key);
         let mut locked_files = store.locked_files.lock().unwrap();
         *locked_files.ref_counts.entry(key.clone()).or_default() += 1;
-        FileStoreHandle {
-            path,
+        let mut handle = FileStoreHandle {
+            path: PathBuf::new(),
+            compressed: false,
+            shared: store.shared,
             locked_files: store.locked_files.clone(),
             key: key.clone(),
-        }
+        };
+        handle.locate(store);
+        handle
     }
 
-    /// The path to the file pointed by this handle.
+    /// Update the path of the file, after it has been added to the store.
+    fn locate(&mut self, store: &FileStore) {
+        self.path = store
+            .stored_path(&self.key)
+            .unwrap_or_else(|| store.key_to_path(&self.key));
+        self.compressed = self.path.extension() == Some(COMPRESSED_EXTENSION.as_ref());
+    }
+
+    /// The path to the file pointed by this handle. If the file is compressed this is not its
+    /// actual content: use [`read`](#method.read) or [`materialize`](#method.materialize) instead.
     pub fn path(&self) -> &Path {
         &self.path
     }
 
+    /// Whether the file is stored compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// Make an iterator over the content of the file, decompressing it if needed.
+    pub fn read(&self) -> Result<ReadFileIterator, Error> {
+        if self.compressed {
+            ReadFileIterator::new_compressed(&self.path)
+        } else {
+            ReadFileIterator::new(&self.path)
+        }
+    }
+
+    /// Write the content of the file at `dest`, decompressing it if needed. The files not
+    /// compressed are hard-linked when possible, so `dest` may share the permissions with the file
+    /// in the store. The files of a shared store are never hard-linked, so that changing the
+    /// permissions of `dest` doesn't affect the other users.
+    pub fn materialize(&self, dest: &Path) -> Result<(), Error> {
+        if self.compressed || self.shared || std::fs::hard_link(&self.path, dest).is_err() {
+            self.copy_to(dest)?;
+        }
+        Ok(())
+    }
+
+    /// Write an independent copy of the file at `dest`, decompressing it if needed. Unlike
+    /// [`materialize`](struct.FileStoreHandle.html#method.materialize), `dest` can be safely
+    /// modified afterwards. The files not compressed are reflinked when the filesystem supports it
+    /// (e.g. btrfs and xfs), sharing the blocks with the store until one of them is changed, and
+    /// copied otherwise.
+    pub fn copy_to(&self, dest: &Path) -> Result<(), Error> {
+        // the destination may be a hard link to a file of the store, which must not be overwritten
+        // in place
+        if dest.symlink_metadata().is_ok() {
+            std::fs::remove_file(dest)
+                .with_context(|| format!("Failed to remove {}", dest.display()))?;
+        }
+        if self.compressed {
+            let source = File::open(&self.path)
+                .with_context(|| format!("Failed to open {}", self.path.display()))?;
+            let dest_file = File::create(dest)
+                .with_context(|| format!("Failed to create {}", dest.display()))?;
+            zstd::stream::copy_decode(source, dest_file).with_context(|| {
+                format!(
+                    "Failed to decompress {} -> {}",
+                    self.path.display(),
+                    dest.display()
+                )
+            })?;
+        } else {
+            reflink_copy::reflink_or_copy(&self.path, dest).with_context(|| {
+                format!(
+                    "Failed to copy {} -> {}",
+                    self.path.display(),
+                    dest.display()
+                )
+            })?;
+        }
+        Ok(())
+    }
+
     /// The key of the file pointed by this handle.
     pub fn key(&self) -> &FileStoreKey {
         &self.key
@@ -520,6 +1071,8 @@ impl Clone for FileStoreHandle {
 
         FileStoreHandle {
             path: self.path.clone(),
+            compressed: self.compressed,
+            shared: self.shared,
             locked_files: self.locked_files.clone(),
             key: self.key.clone(),
         }
@@ -561,6 +1114,8 @@ impl LockedFiles {
 mod tests {
     use std::fs::*;
     use std::io::{Read, Write};
+    use std::os::unix::fs::MetadataExt;
+    use std::time::SystemTime;
 
     use pretty_assertions::{assert_eq, assert_ne};
     use tempfile::TempDir;
@@ -647,6 +1202,242 @@ mod tests {
             .readonly());
     }
 
+    #[test]
+    fn test_store_compressed() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path().join("store"), 100000, 100000).unwrap();
+        let text = "1 2 3\n".repeat(1000);
+        let handle = add_file_to_store(&cwd.path().join("input.txt"), &text, &store);
+        assert!(handle.is_compressed());
+        assert!(store.compressed_path(&handle.key).exists());
+        assert!(!store.key_to_path(&handle.key).exists());
+        assert!(store.index.lock().unwrap().total_size() < text.len() as u64);
+
+        let handle = store.get(&handle.key).unwrap();
+        assert!(handle.is_compressed());
+        let content: Vec<u8> = handle.read().unwrap().flatten().collect();
+        assert_eq!(content, text.as_bytes());
+        let dest = cwd.path().join("materialized.txt");
+        handle.materialize(&dest).unwrap();
+        assert_eq!(read_to_string(&dest).unwrap(), text);
+        assert!(store.check_integrity(&handle.key));
+    }
+
+    #[test]
+    fn test_store_not_compressed() {
+        let cwd = get_cwd();
+        let mut store = FileStore::new(cwd.path().join("store"), 100000, 100000).unwrap();
+        // binary files are not compressed
+        let binary = "\0".repeat(10000);
+        let handle = add_file_to_store(&cwd.path().join("binary"), &binary, &store);
+        assert!(!handle.is_compressed());
+        // small files are not compressed
+        let handle = add_file_to_store(&cwd.path().join("small.txt"), "small", &store);
+        assert!(!handle.is_compressed());
+        // nothing is compressed when the compression is disabled
+        store.set_compression(false);
+        let text = "1 2 3\n".repeat(1000);
+        let handle = add_file_to_store(&cwd.path().join("input.txt"), &text, &store);
+        assert!(!handle.is_compressed());
+        let content: Vec<u8> = handle.read().unwrap().flatten().collect();
+        assert_eq!(content, text.as_bytes());
+    }
+
+    #[test]
+    fn test_shared_store() {
+        let cwd = get_cwd();
+        let mut store = FileStore::new(cwd.path().join("store"), 100000, 100000).unwrap();
+        store.set_shared(true);
+        let handle = add_file_to_store(&cwd.path().join("file"), "shared", &store);
+        let mode = |path: &Path| metadata(path).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode(handle.path()), SHARED_STORED_FILE_MODE);
+        assert_eq!(mode(handle.path().parent().unwrap()), SHARED_DIR_MODE);
+        assert_eq!(mode(&store.base_path), SHARED_DIR_MODE);
+        assert_eq!(
+            mode(&store.base_path.join(STORE_INDEX_FILE)),
+            SHARED_FILE_MODE
+        );
+
+        // the file is copied, so the permissions of the copy don't affect the store
+        let dest = cwd.path().join("copy");
+        handle.materialize(&dest).unwrap();
+        std::fs::set_permissions(&dest, Permissions::from_mode(0o400)).unwrap();
+        assert_eq!(mode(handle.path()), SHARED_STORED_FILE_MODE);
+    }
+
+    #[test]
+    fn test_copy_to() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path().join("store"), 100000, 100000).unwrap();
+        let handle = add_file_to_store(&cwd.path().join("file"), "content", &store);
+        let dest = cwd.path().join("dest");
+        handle.materialize(&dest).unwrap();
+        assert_eq!(
+            metadata(&dest).unwrap().ino(),
+            metadata(handle.path()).unwrap().ino()
+        );
+
+        // the hard link is replaced, and writing the copy doesn't change the store
+        handle.copy_to(&dest).unwrap();
+        assert_ne!(
+            metadata(&dest).unwrap().ino(),
+            metadata(handle.path()).unwrap().ino()
+        );
+        std::fs::set_permissions(&dest, Permissions::from_mode(0o600)).unwrap();
+        std::fs::write(&dest, "changed").unwrap();
+        assert_eq!(read_to_string(handle.path()).unwrap(), "content");
+        assert!(store.check_integrity(&handle.key));
+    }
+
+    #[test]
+    fn test_verify() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path().join("store"), 100000, 100000).unwrap();
+        let good = add_file_to_store(&cwd.path().join("good.txt"), "good", &store);
+        let corrupted = add_file_to_store(&cwd.path().join("bad.txt"), "bad", &store);
+        let text = "1 2 3\n".repeat(1000);
+        let compressed = add_file_to_store(&cwd.path().join("input.txt"), &text, &store);
+        let missing = add_file_to_store(&cwd.path().join("missing.txt"), "missing", &store);
+        corrupt_file(corrupted.path());
+        FileStore::remove_file(missing.path()).unwrap();
+        let total_size = store.total_size();
+
+        let verification = store.verify().unwrap();
+        assert_eq!(verification.checked, 4);
+        assert_eq!(verification.corrupted, vec![corrupted.key.clone()]);
+        assert_eq!(verification.missing, vec![missing.key.clone()]);
+        assert!(!corrupted.path().exists());
+        assert!(store.get(&good.key).is_some());
+        assert!(store.get(&compressed.key).is_some());
+        assert!(store.get(&corrupted.key).is_none());
+        assert_eq!(store.total_size(), total_size - 3 - 7);
+        assert_eq!(store.index.lock().unwrap().keys().count(), 2);
+    }
+
+    #[test]
+    fn test_store_file() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path().join("store"), 100000, 100000).unwrap();
+        let path = cwd.path().join("test.txt");
+        let key = fake_file(&path, "test");
+        let handle = store.store_file(&key, &path).unwrap();
+        assert_eq!(read(handle.path()).unwrap(), b"test");
+        assert!(path.exists());
+        assert!(store.get(&key).is_some());
+    }
+
+    #[test]
+    fn test_store_file_changed() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path().join("store"), 100000, 100000).unwrap();
+        let path = cwd.path().join("test.txt");
+        let key = fake_file(&path, "test");
+        // the file changes after its key is computed
+        std::fs::write(&path, "changed").unwrap();
+        assert!(store.store_file(&key, &path).is_err());
+        assert!(store.get(&key).is_none());
+        assert!(store.get(&FileStoreKey::from_content(b"changed")).is_none());
+    }
+
+    #[test]
+    fn test_store_moving() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path().join("store"), 100000, 100000).unwrap();
+        let path = cwd.path().join("output.txt");
+        std::fs::write(&path, "output").unwrap();
+        let (key, handle) = store.store_moving(&path).unwrap();
+        assert_eq!(key, FileStoreKey::from_content(b"output"));
+        assert_eq!(read(handle.path()).unwrap(), b"output");
+        assert!(!path.exists());
+        // the file already in the store is moved away anyway
+        std::fs::write(&path, "output").unwrap();
+        let (key2, handle) = store.store_moving(&path).unwrap();
+        assert_eq!(key2, key);
+        assert_eq!(read(handle.path()).unwrap(), b"output");
+        assert!(!path.exists());
+        assert_eq!(store.index.lock().unwrap().keys().count(), 1);
+    }
+
+    #[test]
+    fn test_store_resumable() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let key = fake_file(cwd.path().join("test.txt"), "hello world");
+        let chunks = vec![Ok(b"hello".to_vec()), Err(anyhow::anyhow!("disconnected"))];
+        assert!(store.store_resumable(&key, 0, chunks).is_err());
+        assert_eq!(store.partial_size(&key), 5);
+        assert!(store.get(&key).is_none());
+
+        let chunks = vec![Ok(b" world".to_vec())];
+        let handle = store.store_resumable(&key, 5, chunks).unwrap();
+        assert_eq!(std::fs::read(handle.path()).unwrap(), b"hello world");
+        assert_eq!(store.partial_size(&key), 0);
+    }
+
+    #[test]
+    fn test_collect_garbage_partial_transfers() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path().join("store"), 1000, 1000).unwrap();
+        let abandoned = fake_file(cwd.path().join("abandoned.txt"), "hello world");
+        let receiving = fake_file(cwd.path().join("receiving.txt"), "other file");
+        for key in [&abandoned, &receiving] {
+            let chunks = vec![Ok(b"hello".to_vec()), Err(anyhow::anyhow!("disconnected"))];
+            assert!(store.store_resumable(key, 0, chunks).is_err());
+        }
+        // the recent transfers may still be resumed
+        assert_eq!(store.collect_garbage(1000, &HashSet::new()).unwrap(), 0);
+        assert_eq!(store.partial_size(&abandoned), 5);
+
+        let long_ago = SystemTime::now() - PARTIAL_TRANSFER_TIMEOUT - Duration::from_secs(60);
+        for key in [&abandoned, &receiving] {
+            let file = OpenOptions::new()
+                .write(true)
+                .open(store.partial_path(key))
+                .unwrap();
+            file.set_modified(long_ago).unwrap();
+        }
+        store
+            .partial_transfers
+            .lock()
+            .unwrap()
+            .insert(receiving.clone());
+        assert_eq!(store.collect_garbage(1000, &HashSet::new()).unwrap(), 5);
+        assert_eq!(store.partial_size(&abandoned), 0);
+        assert_eq!(store.partial_size(&receiving), 5);
+    }
+
+    #[test]
+    fn test_store_resumable_wrong_content() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let key = fake_file(cwd.path().join("test.txt"), "hello world");
+        let chunks = vec![Ok(b"hello there".to_vec())];
+        assert!(store.store_resumable(&key, 0, chunks).is_err());
+        assert_eq!(store.partial_size(&key), 0);
+        assert!(store.store_resumable(&key, 5, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_collect_garbage() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path().join("store"), 1000, 1000).unwrap();
+        let key1 = add_file_to_store(&cwd.path().join("file1.txt"), "aaaa", &store)
+            .key
+            .clone();
+        let key2 = add_file_to_store(&cwd.path().join("file2.txt"), "bbbbbb", &store)
+            .key
+            .clone();
+        let handle3 = add_file_to_store(&cwd.path().join("file3.txt"), "cc", &store);
+        assert_eq!(store.total_size(), 12);
+
+        let keep = [key2.clone()].into_iter().collect();
+        assert_eq!(store.collect_garbage(0, &keep).unwrap(), 4);
+        assert!(store.get(&key1).is_none());
+        assert!(store.get(&key2).is_some());
+        assert!(handle3.path().exists());
+        assert_eq!(store.total_size(), 8);
+    }
+
     #[test]
     fn test_get() {
         let cwd = get_cwd();
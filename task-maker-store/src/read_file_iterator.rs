@@ -1,6 +1,5 @@
 use anyhow::{Context, Error};
-use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
 
 /// Buffer size when reading a file
@@ -30,7 +29,7 @@ type ReadFileBuffer = [u8; READ_FILE_BUFFER_SIZE];
 /// ```
 pub struct ReadFileIterator {
     /// Reader used to read the file
-    buf_reader: BufReader<File>,
+    reader: Box<dyn Read + Send>,
     /// Current read buffer
     buf: ReadFileBuffer,
 }
@@ -42,7 +41,33 @@ impl ReadFileIterator {
         let file = std::fs::File::open(path)
             .with_context(|| format!("Failed to open {}", path.display()))?;
         Ok(ReadFileIterator {
-            buf_reader: BufReader::new(file),
+            reader: Box::new(BufReader::new(file)),
+            buf: [0; READ_FILE_BUFFER_SIZE],
+        })
+    }
+
+    /// Make a new iterator over the decompressed content of the zstd-compressed file at that path.
+    pub fn new_compressed<P: AsRef<Path>>(path: P) -> Result<ReadFileIterator, Error> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let decoder = zstd::Decoder::new(file)
+            .with_context(|| format!("Failed to decompress {}", path.display()))?;
+        Ok(ReadFileIterator {
+            reader: Box::new(decoder),
+            buf: [0; READ_FILE_BUFFER_SIZE],
+        })
+    }
+
+    /// Make a new iterator reading the file at that path, skipping the first `offset` bytes.
+    pub fn new_at<P: AsRef<Path>>(path: P, offset: u64) -> Result<ReadFileIterator, Error> {
+        let path = path.as_ref();
+        let mut file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        file.seek(SeekFrom::Start(offset))
+            .with_context(|| format!("Failed to seek {} to {}", path.display(), offset))?;
+        Ok(ReadFileIterator {
+            reader: Box::new(BufReader::new(file)),
             buf: [0; READ_FILE_BUFFER_SIZE],
         })
     }
@@ -51,7 +76,7 @@ impl ReadFileIterator {
 impl Iterator for ReadFileIterator {
     type Item = Vec<u8>;
     fn next(&mut self) -> Option<Self::Item> {
-        match self.buf_reader.read(&mut self.buf) {
+        match self.reader.read(&mut self.buf) {
             Ok(0) => None,
             Ok(n) => Some(self.buf[0..n].to_vec()),
             Err(_) => None,
@@ -63,6 +88,7 @@ impl Iterator for ReadFileIterator {
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
+    use std::fs::File;
     use std::io::Write;
     use tempfile::TempDir;
 
@@ -174,4 +200,14 @@ mod tests {
         assert_eq!(pos, content.len());
         assert_eq!(iter.next(), None);
     }
+
+    #[test]
+    fn test_read_file_iterator_compressed() {
+        let cwd = get_cwd();
+        let path = cwd.path().join("file.txt.zst");
+        let content = vec![42; READ_FILE_BUFFER_SIZE * 3 + 5];
+        fake_file(&path, zstd::encode_all(&content[..], 0).unwrap());
+        let iter = ReadFileIterator::new_compressed(&path).unwrap();
+        assert_eq!(iter.flatten().collect::<Vec<_>>(), content);
+    }
 }
@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+
+use task_maker_format::ioi::IOITask;
+use task_maker_format::EvaluationConfig;
+use task_maker_rust::tools::att::{AttChange, AttPlan, MasterTemplate, TemplateType};
+use task_maker_rust::tools::init::{TaskFormat, TaskTemplate, TemplateLanguage};
+
+/// An IOI task with a grader, a master template and two sample cases, the second one without its
+/// output in the statement.
+fn make_task(task_dir: &Path) {
+    TaskTemplate {
+        format: TaskFormat::IOI,
+        name: "sum".into(),
+        title: "Sum".into(),
+        language: TemplateLanguage::Cpp,
+    }
+    .create(task_dir)
+    .unwrap();
+    let gen = std::fs::read_to_string(task_dir.join("gen/GEN")).unwrap();
+    let samples = "#ST: 0\n#COPY: statement/input0.txt\n#COPY: statement/input1.txt\n\n";
+    std::fs::write(task_dir.join("gen/GEN"), format!("{}{}", samples, gen)).unwrap();
+    std::fs::write(task_dir.join("statement/input0.txt"), "1\n1\n").unwrap();
+    std::fs::write(task_dir.join("statement/output0.txt"), "1\n").unwrap();
+    std::fs::write(task_dir.join("statement/input1.txt"), "2\n1 2\n").unwrap();
+    std::fs::create_dir_all(task_dir.join("output")).unwrap();
+    std::fs::write(task_dir.join("output/output1.txt"), "3\n").unwrap();
+    std::fs::write(task_dir.join("sol/grader.cpp"), "int main() {}\n").unwrap();
+    std::fs::write(
+        task_dir.join("sol/templates.yaml"),
+        "functions:\n  - long solve(int N, int[] A)\n",
+    )
+    .unwrap();
+}
+
+fn link(task_dir: &Path, path: &str) -> PathBuf {
+    task_dir.join(path).read_link().unwrap()
+}
+
+#[test]
+fn att_build() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let task_dir = tmpdir.path();
+    make_task(task_dir);
+    // a sample that is not in the task anymore
+    std::fs::create_dir_all(task_dir.join("att")).unwrap();
+    std::os::unix::fs::symlink("../statement/input5.txt", task_dir.join("att/input5.txt")).unwrap();
+
+    let task = IOITask::new(task_dir, &EvaluationConfig::default()).unwrap();
+    let plan = AttPlan::new(&task).unwrap();
+    assert!(plan.warnings.is_empty(), "{:?}", plan.warnings);
+    assert!(plan
+        .changes
+        .contains(&AttChange::Remove("att/input5.txt".into())));
+    plan.apply(task_dir).unwrap();
+
+    let template = std::fs::read_to_string(task_dir.join("att/sum.cpp")).unwrap();
+    assert!(template.contains("long long solve(int N, vector<int> A) {"));
+    assert_eq!(
+        link(task_dir, "sol/template.cpp"),
+        Path::new("../att/sum.cpp")
+    );
+    assert_eq!(
+        link(task_dir, "att/grader.cpp"),
+        Path::new("../sol/grader.cpp")
+    );
+    assert_eq!(
+        link(task_dir, "att/input0.txt"),
+        Path::new("../statement/input0.txt")
+    );
+    assert_eq!(
+        link(task_dir, "att/output1.txt"),
+        Path::new("../statement/output1.txt")
+    );
+    assert_eq!(
+        std::fs::read_to_string(task_dir.join("statement/output1.txt")).unwrap(),
+        "3\n"
+    );
+    assert!(!task_dir.join("att/input5.txt").is_symlink());
+
+    // now att/ is up to date
+    let task = IOITask::new(task_dir, &EvaluationConfig::default()).unwrap();
+    let plan = AttPlan::new(&task).unwrap();
+    assert!(plan.changes.is_empty(), "{:?}", plan.changes);
+}
+
+#[test]
+fn att_missing_sample_output() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let task_dir = tmpdir.path();
+    make_task(task_dir);
+    std::fs::remove_file(task_dir.join("output/output1.txt")).unwrap();
+
+    let task = IOITask::new(task_dir, &EvaluationConfig::default()).unwrap();
+    let plan = AttPlan::new(&task).unwrap();
+    assert_eq!(plan.warnings.len(), 1);
+    assert!(plan.warnings[0].contains("statement/input1.txt"));
+    assert!(!plan.changes.iter().any(
+        |change| matches!(change, AttChange::Symlink(path, _) if path.ends_with("output1.txt"))
+    ));
+}
+
+#[test]
+fn att_master_template() {
+    let master = MasterTemplate::parse(
+        "functions:\n  - void init(string S, char[][] grid)\n  - bool query(int x)\n",
+    )
+    .unwrap();
+    assert_eq!(master.functions.len(), 2);
+    assert_eq!(master.functions[0].returns, TemplateType::Void);
+    assert_eq!(
+        master.functions[0].params[1].0,
+        TemplateType::Array(Box::new(TemplateType::Array(Box::new(TemplateType::Char))))
+    );
+
+    let cpp = master.render("cpp").unwrap();
+    assert!(cpp.contains("void init(string S, vector<vector<char>> grid) {"));
+    assert!(cpp.contains("    return false;\n"));
+    let c = master.render("c").unwrap();
+    assert!(c.contains("void init(char* S, char** grid) {"));
+    assert!(c.contains("bool query(int x) {"));
+    let py = master.render("py").unwrap();
+    assert!(py.contains("def init(S, grid):\n    # insert your code here\n    pass\n"));
+    assert!(py.contains("    return False\n"));
+    assert!(master.render("pas").is_none());
+}
+
+#[test]
+fn att_master_template_errors() {
+    for functions in [
+        "[]",
+        "[\"long solve(int N\"]",
+        "[\"solve(int N)\"]",
+        "[\"long solve(float N)\"]",
+        "[\"long solve(void N)\"]",
+        "[\"long solve(int N, int N)\"]",
+        "[\"long solve(int 1N)\"]",
+        "[\"void[] solve()\"]",
+        "[\"int f()\", \"long f()\"]",
+    ] {
+        let yaml = format!("functions: {}", functions);
+        assert!(MasterTemplate::parse(&yaml).is_err(), "{}", functions);
+    }
+}
@@ -144,9 +144,18 @@ impl TestInterface {
                 booklet_solutions: false,
                 no_statement: false,
                 solution_paths: vec![],
+                extra_solution_paths: vec![],
                 disabled_sanity_checks: vec![],
                 seed: None,
+                terry_cases: None,
+                terry_seeds: None,
                 dry_run: false,
+                subtask_filter: vec![],
+                testcase_filter: vec![],
+                sample_testcases: None,
+                memory_metric: None,
+                verify_generators: false,
+                check_checker: false,
             },
         )
         .unwrap();
@@ -162,6 +171,9 @@ impl TestInterface {
             Evaluation::Clean => {
                 panic!("Unexpected task cleaning");
             }
+            Evaluation::Planned => {
+                panic!("Unexpected evaluation plan");
+            }
         }
     }
 
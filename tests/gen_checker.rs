@@ -0,0 +1,58 @@
+use task_maker_format::ioi::{Checker, IOITask, TaskType};
+use task_maker_format::EvaluationConfig;
+use task_maker_rust::tools::gen_checker::{CheckerTemplate, CheckerType};
+use task_maker_rust::tools::init::{TaskFormat, TaskTemplate, TemplateLanguage};
+
+fn make_task(task_dir: &std::path::Path) {
+    TaskTemplate {
+        format: TaskFormat::IOI,
+        name: "sum".into(),
+        title: "Sum".into(),
+        language: TemplateLanguage::Cpp,
+    }
+    .create(task_dir)
+    .unwrap();
+}
+
+#[test]
+fn gen_checker_replace() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    make_task(tmpdir.path());
+    let template = CheckerTemplate {
+        checker_type: CheckerType::Float,
+        eps: 1e-9,
+        language: TemplateLanguage::Python,
+    };
+    // the task already has the checker of the example
+    assert!(template.create(tmpdir.path(), false).is_err());
+    let path = template.create(tmpdir.path(), true).unwrap();
+    assert_eq!(path, tmpdir.path().join("check/checker.py"));
+    assert!(!tmpdir.path().join("check/checker.cpp").exists());
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .contains("EPS = 1e-9"));
+
+    let task = IOITask::new(tmpdir.path(), &EvaluationConfig::default()).unwrap();
+    match task.task_type {
+        TaskType::Batch(data) => assert!(matches!(data.checker, Checker::Custom(_))),
+        _ => panic!("Expecting a batch task"),
+    }
+}
+
+#[test]
+fn gen_checker_all_types() {
+    for checker_type in [CheckerType::Tokens, CheckerType::Float, CheckerType::YesNo] {
+        for language in [TemplateLanguage::Cpp, TemplateLanguage::Python] {
+            let tmpdir = tempfile::TempDir::new().unwrap();
+            let template = CheckerTemplate {
+                checker_type,
+                eps: 1e-6,
+                language,
+            };
+            let path = template.create(tmpdir.path(), false).unwrap();
+            let content = std::fs::read_to_string(path).unwrap();
+            assert!(!content.contains("%{"));
+            assert!(content.contains("translate:success"));
+        }
+    }
+}
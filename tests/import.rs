@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+
+use task_maker_format::ioi::{Checker, IOITask, TaskType};
+use task_maker_format::EvaluationConfig;
+use task_maker_rust::tools::import::{main_import, ImportFormat, ImportOpt};
+
+fn package(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/packages")
+        .join(name)
+}
+
+/// Import a package of `tests/packages` and load the task it becomes.
+fn import(format: ImportFormat, name: &str, task_dir: &Path) -> IOITask {
+    main_import(ImportOpt {
+        format,
+        package: package(name),
+        output: Some(task_dir.to_path_buf()),
+    })
+    .unwrap();
+    IOITask::new(task_dir, &EvaluationConfig::default()).unwrap()
+}
+
+/// The names, the scores and the number of own testcases of the subtasks, sorted by id.
+fn subtasks(task: &IOITask) -> Vec<(String, f64, usize)> {
+    let mut subtasks: Vec<_> = task.subtasks.values().collect();
+    subtasks.sort_by_key(|st| st.id);
+    subtasks
+        .iter()
+        .map(|st| {
+            (
+                st.name.clone().unwrap_or_default(),
+                st.max_score,
+                st.testcases_owned.len(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn import_polygon() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let task_dir = tmpdir.path().join("sum");
+    let task = import(ImportFormat::Polygon, "polygon", &task_dir);
+    assert_eq!(task.name, "sum");
+    assert_eq!(task.title, "Sum of two numbers");
+    assert_eq!(task.time_limit, Some(1.5));
+    assert_eq!(task.memory_limit, Some(256));
+    assert_eq!(
+        subtasks(&task),
+        vec![
+            ("samples".into(), 0.0, 1),
+            ("small".into(), 40.0, 1),
+            ("large".into(), 60.0, 2),
+        ]
+    );
+    let large = task.subtasks.values().find(|st| st.max_score == 60.0);
+    assert_eq!(large.unwrap().dependencies, vec![1]);
+    match task.task_type {
+        TaskType::Batch(data) => {
+            assert!(matches!(data.checker, Checker::Custom(_)));
+            assert!(data.output_generator.is_some());
+        }
+        _ => panic!("Expecting a batch task"),
+    }
+
+    let cases_gen = std::fs::read_to_string(task_dir.join("gen/cases.gen")).unwrap();
+    assert!(cases_gen.contains(": GEN gen gen/gen.cpp\n"));
+    assert!(cases_gen.contains(": VAL default gen/validator.cpp $INPUT $ST_NAME\n"));
+    assert!(cases_gen.contains(": COPY gen/manual/01.txt\n"));
+    assert!(cases_gen.contains(": RUN gen 3 1000000000\n"));
+    for file in [
+        "gen/testlib.h",
+        "gen/polygon_validator.cpp",
+        "check/testlib.h",
+        "check/polygon_checker.cpp",
+        "sol/solution.cpp",
+        "sol/overflow.cpp",
+        "statement/polygon/english/problem.tex",
+    ] {
+        assert!(task_dir.join(file).exists(), "{} is missing", file);
+    }
+}
+
+#[test]
+fn import_icpc() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let task_dir = tmpdir.path().join("sum");
+    let task = import(ImportFormat::Icpc, "icpc/sum", &task_dir);
+    assert_eq!(task.name, "sum");
+    assert_eq!(task.title, "Sum of two numbers");
+    assert_eq!(task.time_limit, Some(2.0));
+    assert_eq!(task.memory_limit, Some(512));
+    assert_eq!(
+        subtasks(&task),
+        vec![
+            ("samples".into(), 0.0, 1),
+            ("group1".into(), 30.0, 1),
+            ("group2".into(), 70.0, 2),
+        ]
+    );
+    assert_eq!(task.testcases.len(), 4);
+    match task.task_type {
+        TaskType::Batch(data) => {
+            assert!(matches!(data.checker, Checker::Custom(_)));
+            // the outputs are the answers of the package, not generated by a solution
+            assert!(data.output_generator.is_none());
+        }
+        _ => panic!("Expecting a batch task"),
+    }
+
+    let cases_gen = std::fs::read_to_string(task_dir.join("gen/cases.gen")).unwrap();
+    let commands: Vec<_> = cases_gen
+        .lines()
+        .filter(|line| line.starts_with(':'))
+        .collect();
+    assert_eq!(
+        commands,
+        vec![
+            ": VAL default gen/validator.cpp $INPUT",
+            ": SUBTASK 0 samples",
+            ": COPY gen/data/sample/1.in",
+            ": SUBTASK 30 group1",
+            ": COPY gen/data/secret/group1/1.in",
+            ": SUBTASK 70 group2",
+            ": COPY gen/data/secret/group2/1.in",
+            ": COPY gen/data/secret/group2/2.in",
+        ]
+    );
+    let task_yaml = std::fs::read_to_string(task_dir.join("task.yaml.orig")).unwrap();
+    assert_eq!(
+        task_yaml,
+        "name: sum\ntitle: Sum of two numbers\ntime_limit: 2.0\nmemory_limit: 512\n\
+         infile: ''\noutfile: ''\n"
+    );
+    // the answers of the package are the outputs, in the order of the testcases
+    let output = std::fs::read_to_string(task_dir.join("output/output2.txt")).unwrap();
+    assert_eq!(output, "2000000000\n");
+    for file in [
+        "gen/icpc_validator.cpp",
+        "check/checker.cpp",
+        "check/icpc_output_validator.cpp",
+        "sol/accepted_sum.cpp",
+        "sol/wrong_answer_overflow.cpp",
+        "statement/icpc/problem.en.tex",
+    ] {
+        assert!(task_dir.join(file).exists(), "{} is missing", file);
+    }
+}
+
+#[test]
+fn import_not_empty() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    std::fs::write(tmpdir.path().join("file"), "").unwrap();
+    let opt = ImportOpt {
+        format: ImportFormat::Polygon,
+        package: package("polygon"),
+        output: Some(tmpdir.path().to_path_buf()),
+    };
+    assert!(main_import(opt).is_err());
+}
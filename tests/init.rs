@@ -0,0 +1,62 @@
+use task_maker_format::ioi::{Checker, IOITask, TaskType};
+use task_maker_format::terry::TerryTask;
+use task_maker_format::EvaluationConfig;
+use task_maker_rust::tools::init::{TaskFormat, TaskTemplate, TemplateLanguage};
+
+fn template(format: TaskFormat, language: TemplateLanguage) -> TaskTemplate {
+    TaskTemplate {
+        format,
+        name: "sum".into(),
+        title: "Sum: the task".into(),
+        language,
+    }
+}
+
+#[test]
+fn init_ioi() {
+    for language in [TemplateLanguage::Cpp, TemplateLanguage::Python] {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let task_dir = tmpdir.path().join("sum");
+        template(TaskFormat::IOI, language)
+            .create(&task_dir)
+            .unwrap();
+        let task = IOITask::new(&task_dir, &EvaluationConfig::default()).unwrap();
+        assert_eq!(task.name, "sum");
+        assert_eq!(task.title, "Sum: the task");
+        let scores: f64 = task.subtasks.values().map(|st| st.max_score).sum();
+        assert_eq!(scores, 100.0);
+        assert_eq!(task.testcases.len(), 7);
+        match task.task_type {
+            TaskType::Batch(data) => {
+                assert!(matches!(data.checker, Checker::Custom(_)));
+                assert!(data.output_generator.is_some());
+            }
+            _ => panic!("Expecting a batch task"),
+        }
+        assert_eq!(task.booklets.len(), 1);
+    }
+}
+
+#[test]
+fn init_terry() {
+    for language in [TemplateLanguage::Cpp, TemplateLanguage::Python] {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let task_dir = tmpdir.path().join("sum");
+        template(TaskFormat::Terry, language)
+            .create(&task_dir)
+            .unwrap();
+        let task = TerryTask::new(&task_dir, &EvaluationConfig::default()).unwrap();
+        assert_eq!(task.name, "sum");
+        assert_eq!(task.description, "Sum: the task");
+        assert!(task.validator.is_some());
+        assert!(task.official_solution.is_some());
+    }
+}
+
+#[test]
+fn init_not_empty() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    std::fs::write(tmpdir.path().join("file"), "").unwrap();
+    let template = template(TaskFormat::IOI, TemplateLanguage::Cpp);
+    assert!(template.create(tmpdir.path()).is_err());
+}
@@ -0,0 +1,8 @@
+#include <iostream>
+
+int main() {
+    long long a, b;
+    if (!(std::cin >> a >> b)) return 43;
+    if (a < 0 || b < 0 || a > 1000000000 || b > 1000000000) return 43;
+    return 42;
+}
@@ -0,0 +1,8 @@
+#include "testlib.h"
+#include <iostream>
+
+int main(int argc, char* argv[]) {
+    registerGen(argc, argv, 1);
+    int max = opt<int>(2);
+    std::cout << rnd.next(0, max) << " " << rnd.next(0, max) << std::endl;
+}
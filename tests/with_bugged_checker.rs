@@ -3,7 +3,8 @@ use common::TestInterface;
 
 fn with_bugged_checker(test: TestInterface) {
     test.success()
-        .has_diagnostic("Checker returned an invalid score");
+        .has_diagnostic("Checker correttore.cpp returned an invalid score")
+        .solution_score("soluzione.sh", vec![0.0]);
 }
 
 #[test]
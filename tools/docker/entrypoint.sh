@@ -10,7 +10,7 @@ export RUST_LOG=info
 export RUST_BACKTRACE=1
 
 server_store=$(mktemp -d tmserver.XXXXXXX -p /tmp)
-worker_store=$(mktemp -d tmworker.XXXXXXX -p /tmp)
+worker_store=${WORKER_STORE:-$(mktemp -d tmworker.XXXXXXX -p /tmp)}
 
 function spawn_server() {
   task-maker-rust --store-dir "$server_store" $server_args --server